@@ -115,6 +115,27 @@ fn benchmark_header_generation(c: &mut Criterion) {
         })
     });
 
+    group.bench_function("complex_header_nonce_recompile", |b| {
+        b.iter(|| {
+            black_box(
+                complex_policy
+                    .compile_with_runtime_nonce("r4nd0mN0nc3Valu3")
+                    .unwrap(),
+            )
+        })
+    });
+
+    let compiled_complex = complex_policy.compile().unwrap();
+    group.bench_function("complex_header_nonce_splice", |b| {
+        b.iter(|| {
+            black_box(
+                compiled_complex
+                    .header_value_with_nonce("r4nd0mN0nc3Valu3")
+                    .unwrap(),
+            )
+        })
+    });
+
     group.finish();
 }
 
@@ -195,9 +216,7 @@ fn benchmark_policy_caching(c: &mut Criterion) {
 
     group.bench_function("cache_miss", |b| {
         b.iter(|| {
-            let policy_guard = config.policy();
-            let policy = policy_guard.read();
-            let mut policy_clone = policy.clone();
+            let mut policy_clone = config.policy().as_ref().clone();
             let hash = black_box(policy_clone.hash());
             black_box(config.get_cached_policy(hash))
         })
@@ -1,4 +1,4 @@
-use actix_web_csp::{CspConfigBuilder, CspPolicyBuilder, Source};
+use actix_web_csp::{CspConfigBuilder, CspPolicyBuilder, RequestId, Source};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let policy = CspPolicyBuilder::new()
@@ -13,14 +13,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_nonce_per_request(true)
         .build();
 
+    let request_one = RequestId::next();
     let request_one_nonce = config
-        .get_or_generate_request_nonce("request-1")
+        .get_or_generate_request_nonce(request_one)
         .expect("nonce generation should be enabled");
     let repeated_lookup = config
-        .get_or_generate_request_nonce("request-1")
+        .get_or_generate_request_nonce(request_one)
         .expect("request nonce should stay stable within the same request id");
     let request_two_nonce = config
-        .get_or_generate_request_nonce("request-2")
+        .get_or_generate_request_nonce(RequestId::next())
         .expect("a new request id should receive a new nonce");
 
     assert_eq!(request_one_nonce, repeated_lookup);
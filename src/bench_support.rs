@@ -0,0 +1,94 @@
+//! Stable entry points for benchmarking the crate from downstream code.
+//!
+//! Internal benchmarks reach into whatever is fastest to call; these wrap the
+//! paths real traffic takes, so numbers from two versions of the crate can be
+//! compared directly when upgrading. Their signatures only change with a major
+//! release.
+//!
+//! ```rust
+//! use actix_web_csp::bench_support::{serialize_policy, MiddlewareHarness};
+//! use actix_web_csp::{csp_middleware, CspPolicyBuilder, Source};
+//!
+//! let policy = CspPolicyBuilder::new().default_src([Source::Self_]).build()?;
+//! assert_eq!(serialize_policy(&policy)?, "default-src 'self'");
+//!
+//! # actix_web::rt::System::new().block_on(async {
+//! let harness = MiddlewareHarness::new(&csp_middleware(policy)).await?;
+//! let res = harness.roundtrip().await.unwrap();
+//! assert!(res.headers().contains_key("content-security-policy"));
+//! # Ok::<(), actix_web_csp::CspError>(())
+//! # })?;
+//! # Ok::<(), actix_web_csp::CspError>(())
+//! ```
+
+use crate::core::policy::CspPolicy;
+use crate::error::CspError;
+use crate::middleware::csp::CspMiddleware;
+use actix_service::{boxed::BoxService, ServiceExt};
+use actix_web::{
+    body::BoxBody,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    test::TestRequest,
+    Error, HttpResponse,
+};
+
+/// Serializes `policy` into a header value the way the middleware does when
+/// no compiled header is cached: a clone of the policy, then a full write.
+pub fn serialize_policy(policy: &CspPolicy) -> Result<String, CspError> {
+    let mut policy = policy.clone();
+    let value = policy.header_value()?;
+    value.to_str().map(str::to_owned).map_err(|_| {
+        CspError::SerializationError("header value is not printable ASCII".to_string())
+    })
+}
+
+/// Sends one `GET /` through `middleware` to an empty `200 OK` handler and
+/// returns the response.
+///
+/// Builds the service on every call; to leave that out of the measurement,
+/// create a [`MiddlewareHarness`] once and call
+/// [`roundtrip`](MiddlewareHarness::roundtrip) instead.
+pub async fn middleware_roundtrip(
+    middleware: &CspMiddleware,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    MiddlewareHarness::new(middleware).await?.roundtrip().await
+}
+
+/// A [`CspMiddleware`] wrapped around an empty `200 OK` handler, built once
+/// and called repeatedly.
+pub struct MiddlewareHarness {
+    service: BoxService<ServiceRequest, ServiceResponse<BoxBody>, Error>,
+    path: String,
+}
+
+impl MiddlewareHarness {
+    /// Builds the service the way an `App` does when it starts.
+    pub async fn new(middleware: &CspMiddleware) -> Result<Self, CspError> {
+        let handler = actix_service::fn_service(|req: ServiceRequest| async move {
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok().finish()))
+        });
+        let service = middleware.new_transform(handler).await.map_err(|()| {
+            CspError::ConfigError("CspMiddleware could not build its service".to_string())
+        })?;
+
+        Ok(Self {
+            service: actix_service::boxed::service(
+                service.map(ServiceResponse::map_into_boxed_body),
+            ),
+            path: "/".to_string(),
+        })
+    }
+
+    /// Requests `path` instead of `/`, e.g. to hit a route-specific policy.
+    #[inline]
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Sends one `GET` request through the middleware.
+    pub async fn roundtrip(&self) -> Result<ServiceResponse<BoxBody>, Error> {
+        let req = TestRequest::get().uri(&self.path).to_srv_request();
+        self.service.call(req).await
+    }
+}
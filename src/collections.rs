@@ -0,0 +1,178 @@
+//! Cache and map types whose backing crate is selected by cargo features.
+//!
+//! With the `lru` and `dashmap` features (both on by default) these are the
+//! crates' own types. Without them, small fallbacks built on `indexmap` and a
+//! `RwLock`-guarded map keep the same behaviour with fewer dependencies.
+
+pub(crate) use lru_imp::LruCache;
+pub(crate) use map_imp::ConcurrentMap;
+
+#[cfg(feature = "lru")]
+mod lru_imp {
+    pub(crate) use lru::LruCache;
+}
+
+#[cfg(not(feature = "lru"))]
+mod lru_imp {
+    use indexmap::IndexMap;
+    use std::hash::Hash;
+    use std::num::NonZeroUsize;
+
+    /// Least-recently-used cache; the most recently used entry is kept last.
+    ///
+    /// Promotion shifts entries, which is fine for the few hundred slots the
+    /// crate's caches hold.
+    #[derive(Debug)]
+    pub(crate) struct LruCache<K, V> {
+        entries: IndexMap<K, V>,
+        cap: NonZeroUsize,
+    }
+
+    impl<K: Hash + Eq, V> LruCache<K, V> {
+        pub(crate) fn new(cap: NonZeroUsize) -> Self {
+            Self {
+                entries: IndexMap::with_capacity(cap.get()),
+                cap,
+            }
+        }
+
+        pub(crate) fn get(&mut self, key: &K) -> Option<&V> {
+            let index = self.entries.get_index_of(key)?;
+            let last = self.entries.len() - 1;
+            self.entries.move_index(index, last);
+            self.entries.get_index(last).map(|(_, value)| value)
+        }
+
+        pub(crate) fn put(&mut self, key: K, value: V) -> Option<V> {
+            let (index, previous) = self.entries.insert_full(key, value);
+            let last = self.entries.len() - 1;
+            self.entries.move_index(index, last);
+            self.evict();
+            previous
+        }
+
+        pub(crate) fn pop(&mut self, key: &K) -> Option<V> {
+            self.entries.shift_remove(key)
+        }
+
+        #[inline]
+        pub(crate) fn contains(&self, key: &K) -> bool {
+            self.entries.contains_key(key)
+        }
+
+        #[inline]
+        pub(crate) fn cap(&self) -> NonZeroUsize {
+            self.cap
+        }
+
+        pub(crate) fn resize(&mut self, cap: NonZeroUsize) {
+            self.cap = cap;
+            self.evict();
+        }
+
+        #[inline]
+        pub(crate) fn len(&self) -> usize {
+            self.entries.len()
+        }
+
+        #[inline]
+        pub(crate) fn is_empty(&self) -> bool {
+            self.entries.is_empty()
+        }
+
+        /// Iterates from the most to the least recently used entry.
+        pub(crate) fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+            self.entries.iter().rev()
+        }
+
+        #[inline]
+        pub(crate) fn clear(&mut self) {
+            self.entries.clear();
+        }
+
+        fn evict(&mut self) {
+            let excess = self.entries.len().saturating_sub(self.cap.get());
+            if excess > 0 {
+                self.entries.drain(..excess);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "dashmap")]
+mod map_imp {
+    use std::hash::Hash;
+
+    /// Concurrent map with sharded locking.
+    #[derive(Debug)]
+    pub(crate) struct ConcurrentMap<K: Hash + Eq, V>(dashmap::DashMap<K, V>);
+
+    impl<K: Hash + Eq, V> ConcurrentMap<K, V> {
+        #[inline]
+        pub(crate) fn new() -> Self {
+            Self(dashmap::DashMap::new())
+        }
+
+        #[inline]
+        pub(crate) fn insert(&self, key: K, value: V) {
+            self.0.insert(key, value);
+        }
+
+        #[inline]
+        pub(crate) fn remove(&self, key: &K) -> bool {
+            self.0.remove(key).is_some()
+        }
+
+        #[inline]
+        pub(crate) fn is_empty(&self) -> bool {
+            self.0.is_empty()
+        }
+
+        /// Calls `f` for every value; inserting or removing from `f` deadlocks.
+        pub(crate) fn for_each_value(&self, mut f: impl FnMut(&V)) {
+            for entry in self.0.iter() {
+                f(entry.value());
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "dashmap"))]
+mod map_imp {
+    use parking_lot::RwLock;
+    use rustc_hash::FxHashMap;
+    use std::hash::Hash;
+
+    /// Concurrent map behind a single reader-writer lock.
+    #[derive(Debug)]
+    pub(crate) struct ConcurrentMap<K: Hash + Eq, V>(RwLock<FxHashMap<K, V>>);
+
+    impl<K: Hash + Eq, V> ConcurrentMap<K, V> {
+        #[inline]
+        pub(crate) fn new() -> Self {
+            Self(RwLock::new(FxHashMap::default()))
+        }
+
+        #[inline]
+        pub(crate) fn insert(&self, key: K, value: V) {
+            self.0.write().insert(key, value);
+        }
+
+        #[inline]
+        pub(crate) fn remove(&self, key: &K) -> bool {
+            self.0.write().remove(key).is_some()
+        }
+
+        #[inline]
+        pub(crate) fn is_empty(&self) -> bool {
+            self.0.read().is_empty()
+        }
+
+        /// Calls `f` for every value; inserting or removing from `f` deadlocks.
+        pub(crate) fn for_each_value(&self, mut f: impl FnMut(&V)) {
+            for value in self.0.read().values() {
+                f(value);
+            }
+        }
+    }
+}
@@ -1,30 +1,75 @@
 pub(crate) const HEADER_CSP: &str = "content-security-policy";
 pub(crate) const HEADER_CSP_REPORT_ONLY: &str = "content-security-policy-report-only";
+pub(crate) const HEADER_REPORTING_ENDPOINTS: &str = "reporting-endpoints";
 
-pub(crate) const DEFAULT_SRC: &str = "default-src";
-pub(crate) const SCRIPT_SRC: &str = "script-src";
-pub(crate) const STYLE_SRC: &str = "style-src";
-pub(crate) const IMG_SRC: &str = "img-src";
-pub(crate) const CONNECT_SRC: &str = "connect-src";
-pub(crate) const FONT_SRC: &str = "font-src";
-pub(crate) const OBJECT_SRC: &str = "object-src";
-pub(crate) const MEDIA_SRC: &str = "media-src";
-pub(crate) const FRAME_SRC: &str = "frame-src";
-pub(crate) const WORKER_SRC: &str = "worker-src";
-pub(crate) const MANIFEST_SRC: &str = "manifest-src";
-pub(crate) const CHILD_SRC: &str = "child-src";
-pub(crate) const FRAME_ANCESTORS: &str = "frame-ancestors";
-pub(crate) const BASE_URI: &str = "base-uri";
-pub(crate) const FORM_ACTION: &str = "form-action";
-pub(crate) const SANDBOX: &str = "sandbox";
-pub(crate) const SCRIPT_SRC_ELEM: &str = "script-src-elem";
-pub(crate) const SCRIPT_SRC_ATTR: &str = "script-src-attr";
-pub(crate) const STYLE_SRC_ELEM: &str = "style-src-elem";
-pub(crate) const STYLE_SRC_ATTR: &str = "style-src-attr";
-pub(crate) const PREFETCH_SRC: &str = "prefetch-src";
+pub const DEFAULT_SRC: &str = "default-src";
+pub const SCRIPT_SRC: &str = "script-src";
+pub const STYLE_SRC: &str = "style-src";
+pub const IMG_SRC: &str = "img-src";
+pub const CONNECT_SRC: &str = "connect-src";
+pub const FONT_SRC: &str = "font-src";
+pub const OBJECT_SRC: &str = "object-src";
+pub const MEDIA_SRC: &str = "media-src";
+pub const FRAME_SRC: &str = "frame-src";
+pub const WORKER_SRC: &str = "worker-src";
+pub const MANIFEST_SRC: &str = "manifest-src";
+pub const CHILD_SRC: &str = "child-src";
+pub const FRAME_ANCESTORS: &str = "frame-ancestors";
+pub const BASE_URI: &str = "base-uri";
+pub const FORM_ACTION: &str = "form-action";
+pub const SANDBOX: &str = "sandbox";
+pub const SCRIPT_SRC_ELEM: &str = "script-src-elem";
+pub const SCRIPT_SRC_ATTR: &str = "script-src-attr";
+pub const STYLE_SRC_ELEM: &str = "style-src-elem";
+pub const STYLE_SRC_ATTR: &str = "style-src-attr";
+pub const PREFETCH_SRC: &str = "prefetch-src";
+pub const UPGRADE_INSECURE_REQUESTS: &str = "upgrade-insecure-requests";
+pub const REQUIRE_SRI_FOR: &str = "require-sri-for";
+pub const BLOCK_ALL_MIXED_CONTENT: &str = "block-all-mixed-content";
+pub const REQUIRE_TRUSTED_TYPES_FOR: &str = "require-trusted-types-for";
+pub const TRUSTED_TYPES: &str = "trusted-types";
+pub const FENCED_FRAME_SRC: &str = "fenced-frame-src";
+pub const WEBRTC: &str = "webrtc";
+pub const NAVIGATE_TO: &str = "navigate-to";
 
-pub(crate) const REPORT_URI: &str = "report-uri";
-pub(crate) const REPORT_TO: &str = "report-to";
+pub const REPORT_URI: &str = "report-uri";
+pub const REPORT_TO: &str = "report-to";
+
+/// Every directive name this crate recognises, including deprecated and
+/// experimental ones, for catching misspelled names.
+pub const KNOWN_DIRECTIVES: &[&str] = &[
+    DEFAULT_SRC,
+    SCRIPT_SRC,
+    SCRIPT_SRC_ELEM,
+    SCRIPT_SRC_ATTR,
+    STYLE_SRC,
+    STYLE_SRC_ELEM,
+    STYLE_SRC_ATTR,
+    IMG_SRC,
+    FONT_SRC,
+    CONNECT_SRC,
+    MEDIA_SRC,
+    OBJECT_SRC,
+    CHILD_SRC,
+    FRAME_SRC,
+    FENCED_FRAME_SRC,
+    WORKER_SRC,
+    MANIFEST_SRC,
+    PREFETCH_SRC,
+    BASE_URI,
+    SANDBOX,
+    FORM_ACTION,
+    FRAME_ANCESTORS,
+    NAVIGATE_TO,
+    UPGRADE_INSECURE_REQUESTS,
+    BLOCK_ALL_MIXED_CONTENT,
+    REQUIRE_TRUSTED_TYPES_FOR,
+    TRUSTED_TYPES,
+    REQUIRE_SRI_FOR,
+    WEBRTC,
+    REPORT_URI,
+    REPORT_TO,
+];
 
 pub(crate) const NONE_SOURCE: &str = "'none'";
 pub(crate) const SELF_SOURCE: &str = "'self'";
@@ -35,6 +80,9 @@ pub(crate) const REPORT_SAMPLE_SOURCE: &str = "'report-sample'";
 pub(crate) const WASM_UNSAFE_EVAL_SOURCE: &str = "'wasm-unsafe-eval'";
 pub(crate) const UNSAFE_HASHES_SOURCE: &str = "'unsafe-hashes'";
 pub(crate) const NONCE_PREFIX: &str = "'nonce-";
+/// How [`Source::NoncePlaceholder`](crate::Source::NoncePlaceholder) is written
+/// outside headers, reusing the HTML placeholder of `CspResponseExt::csp_nonce_body`.
+pub(crate) const NONCE_PLACEHOLDER_SOURCE: &str = "'nonce-{{csp-nonce}}'";
 pub(crate) const HASH_PREFIX_SHA256: &str = "'sha256-";
 pub(crate) const HASH_PREFIX_SHA384: &str = "'sha384-";
 pub(crate) const HASH_PREFIX_SHA512: &str = "'sha512-";
@@ -44,9 +92,26 @@ pub(crate) const DEFAULT_NONCE_LENGTH: usize = 16;
 pub(crate) const DEFAULT_CACHE_DURATION_SECS: u64 = 60;
 pub(crate) const DEFAULT_MAX_REPORT_SIZE: usize = 16 * 1024;
 pub(crate) const DEFAULT_REPORT_PATH: &str = "/csp-report";
+pub(crate) const DEFAULT_REPORT_READ_TIMEOUT: std::time::Duration =
+    std::time::Duration::from_secs(10);
+/// Longest time a temporary source stays allowed; longer durations are clamped
+pub(crate) const MAX_TEMPORARY_SOURCE_DURATION: std::time::Duration =
+    std::time::Duration::from_secs(10 * 365 * 24 * 60 * 60);
+/// Shortest period of the background maintenance and nonce refill tasks
+pub(crate) const MIN_BACKGROUND_INTERVAL: std::time::Duration = std::time::Duration::from_millis(1);
+pub(crate) const DEFAULT_SHADOW_AUDIT_MAX_BYTES: usize = 1024 * 1024;
+pub(crate) const DEFAULT_NONCE_CACHE_CONTROL: &str = "private, no-store";
+pub(crate) const FALLBACK_POLICY: &str = "default-src 'self'";
+#[cfg(feature = "session")]
+pub(crate) const SESSION_NONCE_KEY: &str = "actix_web_csp.nonce";
 pub(crate) const SEMICOLON_SPACE: &[u8] = b"; ";
 
 pub(crate) const DEFAULT_BUFFER_CAPACITY: usize = 1024;
+pub(crate) const DEFAULT_BUFFER_POOL_SIZE: usize = 8;
+pub(crate) const DEFAULT_BUFFER_POOL_MIN_CAPACITY: usize = 512;
 pub(crate) const DEFAULT_POLICY_CACHE_ENTRIES: usize = 64;
+pub(crate) const DEFAULT_POLICY_HISTORY_ENTRIES: usize = 32;
 pub(crate) const DEFAULT_REQUEST_NONCE_CACHE_ENTRIES: usize = 1024;
+pub(crate) const DEFAULT_REQUEST_NONCE_TTL_SECS: u64 = 300;
 pub(crate) const NONCE_BUFFER_POOL_SIZE: usize = 32;
+pub(crate) const NONCE_BATCH_CAPACITY: usize = 256;
@@ -0,0 +1,462 @@
+//! Hosts allowed at runtime, kept in a store and applied to a live policy.
+
+use crate::core::config::CspConfig;
+use crate::core::directives::Directive;
+use crate::core::policy::CspPolicy;
+use crate::core::source::Source;
+use crate::error::CspError;
+use crate::logging::csp_log;
+use actix_web::rt::task::JoinHandle;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// A host in a [`DynamicAllowlist`], as handed to its [`AllowlistStore`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AllowlistEntry {
+    pub directive: String,
+    pub host: String,
+    /// When the host is removed again; `None` keeps it until it is removed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<SystemTime>,
+}
+
+impl AllowlistEntry {
+    #[inline]
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Where a [`DynamicAllowlist`] keeps its hosts between restarts.
+///
+/// Entries are saved in full after every change and loaded by
+/// [`DynamicAllowlist::load`]. One store can hold several allowlists, told
+/// apart by name.
+pub trait AllowlistStore: Send + Sync + 'static {
+    /// Returns the entries saved under `name`, empty if there are none.
+    fn load(&self, name: &str) -> Result<Vec<AllowlistEntry>, CspError>;
+
+    /// Replaces the entries saved under `name`.
+    fn save(&self, name: &str, entries: &[AllowlistEntry]) -> Result<(), CspError>;
+}
+
+/// Keeps allowlists in memory only; the default store.
+#[derive(Debug, Default)]
+pub struct MemoryAllowlistStore {
+    lists: Mutex<HashMap<String, Vec<AllowlistEntry>>>,
+}
+
+impl MemoryAllowlistStore {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AllowlistStore for MemoryAllowlistStore {
+    fn load(&self, name: &str) -> Result<Vec<AllowlistEntry>, CspError> {
+        Ok(self.lists.lock().get(name).cloned().unwrap_or_default())
+    }
+
+    fn save(&self, name: &str, entries: &[AllowlistEntry]) -> Result<(), CspError> {
+        self.lists.lock().insert(name.to_string(), entries.to_vec());
+        Ok(())
+    }
+}
+
+/// Keeps allowlists in a JSON file, one object keyed by allowlist name.
+///
+/// A missing file reads as empty. Saves write a temporary file next to it and
+/// rename it into place, so a crash never leaves a half-written file.
+#[derive(Debug)]
+pub struct JsonFileAllowlistStore {
+    path: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl JsonFileAllowlistStore {
+    #[inline]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    #[inline]
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    fn read_all(&self) -> Result<HashMap<String, Vec<AllowlistEntry>>, CspError> {
+        match std::fs::read(&self.path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|error| {
+                CspError::SerializationError(format!(
+                    "Invalid allowlist file '{}': {error}",
+                    self.path.display()
+                ))
+            }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+impl AllowlistStore for JsonFileAllowlistStore {
+    fn load(&self, name: &str) -> Result<Vec<AllowlistEntry>, CspError> {
+        Ok(self.read_all()?.remove(name).unwrap_or_default())
+    }
+
+    fn save(&self, name: &str, entries: &[AllowlistEntry]) -> Result<(), CspError> {
+        let _write = self.write_lock.lock();
+        let mut lists = self.read_all()?;
+        lists.insert(name.to_string(), entries.to_vec());
+        let json = serde_json::to_vec_pretty(&lists)
+            .map_err(|error| CspError::SerializationError(error.to_string()))?;
+
+        // A name of its own per write, so processes sharing the file never
+        // rename each other's half-written copy into place
+        let mut suffix = [0u8; 8];
+        getrandom::getrandom(&mut suffix)
+            .map_err(|error| CspError::IoError(std::io::Error::other(error.to_string())))?;
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(format!(
+            ".{}.{:016x}.tmp",
+            std::process::id(),
+            u64::from_ne_bytes(suffix)
+        ));
+
+        let written =
+            std::fs::write(&temporary, json).and_then(|()| std::fs::rename(&temporary, &self.path));
+        if written.is_err() {
+            let _ = std::fs::remove_file(&temporary);
+        }
+        Ok(written?)
+    }
+}
+
+/// A named set of hosts per directive that is edited at runtime and kept in
+/// the policy of a [`CspConfig`].
+///
+/// Meant for lists that change more often than the code, such as the
+/// marketing and analytics tags a site loads. Every change is saved to the
+/// [`AllowlistStore`] and then applied through [`CspConfig::update_policy`],
+/// so listeners, history and the middleware see it like any other update.
+///
+/// Hosts the policy already allowed are left alone and never removed. A host
+/// added to a directive the policy lacks starts that directive from the
+/// `default-src` sources, so whatever was allowed through the fallback keeps
+/// loading; the directive is removed again with its last allowlisted host.
+/// Expired hosts are dropped on the next change, by [`expire`](Self::expire),
+/// or periodically by [`start_expiry`](Self::start_expiry).
+///
+/// ```rust
+/// use actix_web_csp::core::{DynamicAllowlist, JsonFileAllowlistStore};
+/// use actix_web_csp::{CspConfig, CspPolicyBuilder, Source};
+/// use std::time::Duration;
+///
+/// let config = CspConfig::new(
+///     CspPolicyBuilder::new()
+///         .default_src([Source::Self_])
+///         .script_src([Source::Self_])
+///         .build()?,
+/// );
+/// # let dir = std::env::temp_dir().join(format!("csp-allowlist-doc-{}", std::process::id()));
+/// # std::fs::create_dir_all(&dir)?;
+/// # let path = dir.join("allowlists.json");
+///
+/// let marketing = DynamicAllowlist::new("marketing", &config)
+///     .with_store(JsonFileAllowlistStore::new(path));
+/// marketing.load()?;
+///
+/// marketing.add("script-src", "tags.example-analytics.com")?;
+/// marketing.add_for("img-src", "pixel.campaign.example", Duration::from_secs(30 * 24 * 3600))?;
+///
+/// assert_eq!(
+///     config.policy().get_directive("script-src").unwrap().to_string(),
+///     "script-src 'self' tags.example-analytics.com"
+/// );
+/// # std::fs::remove_dir_all(dir)?;
+/// # Ok::<(), actix_web_csp::CspError>(())
+/// ```
+#[derive(Clone)]
+pub struct DynamicAllowlist {
+    name: Cow<'static, str>,
+    config: CspConfig,
+    store: Arc<dyn AllowlistStore>,
+    state: Arc<Mutex<AllowlistState>>,
+}
+
+#[derive(Debug, Default)]
+struct AllowlistState {
+    entries: Vec<AllowlistEntry>,
+    /// Sources this allowlist added to the policy
+    applied: Vec<(String, Source)>,
+    /// Directives this allowlist added to the policy
+    created: Vec<String>,
+}
+
+impl DynamicAllowlist {
+    /// Creates an empty allowlist applied to `config`, kept in memory until a
+    /// store is set with [`with_store`](Self::with_store).
+    pub fn new(name: impl Into<Cow<'static, str>>, config: &CspConfig) -> Self {
+        Self {
+            name: name.into(),
+            config: config.clone(),
+            store: Arc::new(MemoryAllowlistStore::new()),
+            state: Arc::new(Mutex::new(AllowlistState::default())),
+        }
+    }
+
+    /// Sets the store entries are saved to and loaded from.
+    #[inline]
+    pub fn with_store(mut self, store: impl AllowlistStore) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Replaces the allowlist with the entries saved in the store and applies
+    /// them, returning how many are in effect.
+    ///
+    /// Fails without changing anything if an entry does not hold a valid host.
+    pub fn load(&self) -> Result<usize, CspError> {
+        let mut entries = self.store.load(&self.name)?;
+        for entry in &entries {
+            host_source(&entry.directive, &entry.host)?;
+        }
+        let now = SystemTime::now();
+        entries.retain(|entry| !entry.is_expired(now));
+
+        let mut state = self.state.lock();
+        state.entries = entries;
+        self.apply(&mut state);
+        Ok(state.entries.len())
+    }
+
+    /// Allows `host` under `directive` until it is removed.
+    ///
+    /// Adding a host that is already listed replaces its expiry.
+    #[inline]
+    pub fn add(&self, directive: &str, host: &str) -> Result<(), CspError> {
+        self.insert(directive, host, None)
+    }
+
+    /// Allows `host` under `directive` for `ttl`.
+    ///
+    /// Fails with [`CspError::ConfigError`] if `ttl` reaches past the latest
+    /// time the system clock can represent.
+    pub fn add_for(&self, directive: &str, host: &str, ttl: Duration) -> Result<(), CspError> {
+        let expires_at = SystemTime::now().checked_add(ttl).ok_or_else(|| {
+            CspError::ConfigError(format!(
+                "Allowlist TTL for {directive} host {host} is too long: {ttl:?}"
+            ))
+        })?;
+        self.insert(directive, host, Some(expires_at))
+    }
+
+    fn insert(
+        &self,
+        directive: &str,
+        host: &str,
+        expires_at: Option<SystemTime>,
+    ) -> Result<(), CspError> {
+        host_source(directive, host)?;
+        self.change(|entries| {
+            match entries
+                .iter_mut()
+                .find(|entry| entry.directive == directive && entry.host == host)
+            {
+                Some(entry) => entry.expires_at = expires_at,
+                None => entries.push(AllowlistEntry {
+                    directive: directive.to_string(),
+                    host: host.to_string(),
+                    expires_at,
+                }),
+            }
+        })?;
+        Ok(())
+    }
+
+    /// Removes `host` from `directive`, returning `true` if it was listed.
+    pub fn remove(&self, directive: &str, host: &str) -> Result<bool, CspError> {
+        let mut removed = false;
+        self.change(|entries| {
+            let before = entries.len();
+            entries.retain(|entry| entry.directive != directive || entry.host != host);
+            removed = entries.len() != before;
+        })?;
+        Ok(removed)
+    }
+
+    /// Removes hosts whose time has run out, returning how many were removed.
+    #[inline]
+    pub fn expire(&self) -> Result<usize, CspError> {
+        self.change(|_| {})
+    }
+
+    /// Spawns a background task that calls [`expire`](Self::expire) every
+    /// `interval`.
+    ///
+    /// Like [`CspConfig::start_maintenance`], it must be called from within an
+    /// Actix (Tokio) runtime and runs until the returned handle is aborted or
+    /// the runtime shuts down.
+    pub fn start_expiry(&self, interval: Duration) -> JoinHandle<()> {
+        let allowlist = self.clone();
+        actix_web::rt::spawn(async move {
+            let mut ticker = actix_web::rt::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(error) = allowlist.expire() {
+                    csp_log!(
+                        Warn,
+                        "Failed to expire hosts of CSP allowlist '{}': {error}",
+                        allowlist.name
+                    );
+                }
+            }
+        })
+    }
+
+    /// Returns the hosts currently allowed under `directive`.
+    pub fn hosts(&self, directive: &str) -> Vec<String> {
+        let now = SystemTime::now();
+        self.state
+            .lock()
+            .entries
+            .iter()
+            .filter(|entry| entry.directive == directive && !entry.is_expired(now))
+            .map(|entry| entry.host.clone())
+            .collect()
+    }
+
+    /// Returns every entry, including expired ones not yet removed.
+    #[inline]
+    pub fn entries(&self) -> Vec<AllowlistEntry> {
+        self.state.lock().entries.clone()
+    }
+
+    /// Edits a copy of the entries without expired hosts, saves it if anything
+    /// changed, then applies it. Returns how many hosts had expired.
+    fn change<F>(&self, f: F) -> Result<usize, CspError>
+    where
+        F: FnOnce(&mut Vec<AllowlistEntry>),
+    {
+        let mut state = self.state.lock();
+        let mut entries = state.entries.clone();
+        let now = SystemTime::now();
+        entries.retain(|entry| !entry.is_expired(now));
+        let expired = state.entries.len() - entries.len();
+        f(&mut entries);
+
+        if entries != state.entries {
+            self.store.save(&self.name, &entries)?;
+            state.entries = entries;
+            self.apply(&mut state);
+        }
+        Ok(expired)
+    }
+
+    /// Brings the policy in line with the entries.
+    fn apply(&self, state: &mut AllowlistState) {
+        let wanted: Vec<(String, Source)> = state
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                Some((
+                    entry.directive.clone(),
+                    host_source(&entry.directive, &entry.host).ok()?,
+                ))
+            })
+            .collect();
+        let stale: Vec<(String, Source)> = state
+            .applied
+            .iter()
+            .filter(|applied| !wanted.contains(applied))
+            .cloned()
+            .collect();
+        let missing: Vec<(String, Source)> = wanted
+            .into_iter()
+            .filter(|wanted| !state.applied.contains(wanted))
+            .collect();
+        if stale.is_empty() && missing.is_empty() {
+            return;
+        }
+
+        self.config.update_policy(|policy| {
+            for (directive, source) in &stale {
+                state
+                    .applied
+                    .retain(|applied| applied.0 != *directive || applied.1 != *source);
+                remove_source(policy, directive, source);
+            }
+            state.created.retain(|directive| {
+                let in_use = state.applied.iter().any(|applied| applied.0 == *directive);
+                if !in_use {
+                    policy.remove_directive(directive);
+                }
+                in_use
+            });
+
+            for (directive, source) in missing {
+                match policy.get_directive(&directive) {
+                    Some(existing) if existing.sources().contains(&source) => continue,
+                    Some(_) => {}
+                    // append_source seeds it with the sources it inherited
+                    None => state.created.push(directive.clone()),
+                }
+                policy.append_source(directive.clone(), source.clone());
+                state.applied.push((directive, source));
+            }
+        });
+    }
+}
+
+impl fmt::Debug for DynamicAllowlist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamicAllowlist")
+            .field("name", &self.name)
+            .field("entries", &self.state.lock().entries)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Parses `host` as a host source valid under `directive`.
+fn host_source(directive: &str, host: &str) -> Result<Source, CspError> {
+    let source: Source = host.parse()?;
+    if !matches!(source, Source::Host(_)) {
+        return Err(CspError::InvalidDirectiveValue(format!(
+            "'{host}' is not a host source"
+        )));
+    }
+    let mut check = Directive::new(directive.to_string());
+    check.add_source(source.clone());
+    check.validate()?;
+    Ok(source)
+}
+
+/// Removes a source this allowlist added, falling back to `'none'` rather than
+/// leaving a directive the allowlist did not create empty.
+fn remove_source(policy: &mut CspPolicy, directive: &str, source: &Source) {
+    let Some(mut updated) = policy.get_directive(directive).cloned() else {
+        return;
+    };
+    if !updated.remove_source(source) {
+        return;
+    }
+    if updated.sources().is_empty() {
+        updated.add_source(Source::None);
+    }
+    policy.add_directive(updated);
+}
@@ -114,45 +114,383 @@
 //!     .route("/", web::get().to(handler));
 //! ```
 //!
-//! ### With Custom Update Listeners
+//! ### With Policy Listeners
 //!
 //! ```rust
+//! use actix_web_csp::core::PolicyEvent;
 //! use actix_web_csp::{CspConfig, CspPolicy};
 //!
 //! let config = CspConfig::new(CspPolicy::default());
 //!
 //! // Add logging listener
-//! config.add_update_listener(|policy| {
-//!     println!("CSP policy updated: {} directives", policy.directives().count());
+//! config.add_policy_listener(|event| match event {
+//!     PolicyEvent::Updated { policy, .. } => {
+//!         println!("CSP policy updated: {} directives", policy.directives().count());
+//!     }
+//!     _ => {}
 //! });
 //!
 //! // Add notification listener
-//! config.add_update_listener(|_policy| {
+//! config.add_policy_listener(|_event| {
 //!     println!("Policy update notification sent");
 //! });
 //! ```
 
-use crate::constants::{DEFAULT_POLICY_CACHE_ENTRIES, DEFAULT_REQUEST_NONCE_CACHE_ENTRIES};
+use crate::collections::{ConcurrentMap, LruCache};
+use crate::constants::{
+    DEFAULT_NONCE_CACHE_CONTROL, DEFAULT_POLICY_CACHE_ENTRIES, DEFAULT_POLICY_HISTORY_ENTRIES,
+    DEFAULT_REQUEST_NONCE_CACHE_ENTRIES, DEFAULT_REQUEST_NONCE_TTL_SECS,
+    DEFAULT_SHADOW_AUDIT_MAX_BYTES, HEADER_REPORTING_ENDPOINTS, MAX_TEMPORARY_SOURCE_DURATION,
+    MIN_BACKGROUND_INTERVAL,
+};
 use crate::core::directives::DirectiveSpec;
+use crate::core::interop::PolicyDocument;
 use crate::core::policy::{CompiledCspPolicy, CspPolicy};
-use crate::monitoring::perf::PerformanceMetrics;
+use crate::core::source::Source;
+use crate::error::CspError;
+use crate::logging::csp_log;
+use crate::middleware::proxy::TrustedProxies;
+use crate::middleware::shadow::ShadowAuditor;
+use crate::monitoring::perf::{AdaptiveCache, PerformanceMetrics};
 use crate::monitoring::stats::CspStats;
-use crate::security::nonce::NonceGenerator;
-use arc_swap::ArcSwapOption;
-use lru::LruCache;
+use crate::security::hash::{HashAlgorithm, HashGenerator};
+use crate::security::nonce::{NonceGenerator, NonceScope, RequestId};
+use crate::utils::CachedValue;
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::rt::task::JoinHandle;
+use actix_web::web::Bytes;
+use arc_swap::ArcSwap;
 use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::num::{NonZeroU64, NonZeroUsize};
 use std::{
     borrow::Cow,
     sync::{
-        atomic::{AtomicBool, AtomicUsize},
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
 };
 
-/// Function type for policy update listeners.
-type UpdateFn = Box<dyn Fn(&mut CspPolicy) + Send + Sync + 'static>;
+/// Key for the policy cache: a selector scope paired with a structural policy hash.
+///
+/// The scope separates policies chosen per route (or by any other selector) so
+/// that variants do not evict each other, and the hash is expected to come from
+/// [`CspPolicy::structural_hash`] so nonce values never fragment the cache. A bare
+/// hash converts into a key with an empty scope.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PolicyCacheKey {
+    scope: Cow<'static, str>,
+    hash: NonZeroU64,
+}
+
+impl PolicyCacheKey {
+    #[inline]
+    pub fn new(scope: impl Into<Cow<'static, str>>, hash: NonZeroU64) -> Self {
+        Self {
+            scope: scope.into(),
+            hash,
+        }
+    }
+
+    #[inline]
+    pub fn scope(&self) -> &str {
+        &self.scope
+    }
+
+    #[inline]
+    pub fn hash(&self) -> NonZeroU64 {
+        self.hash
+    }
+}
+
+impl From<NonZeroU64> for PolicyCacheKey {
+    #[inline]
+    fn from(hash: NonZeroU64) -> Self {
+        Self::new("", hash)
+    }
+}
+
+/// Outcome of a single [`CspConfig::run_maintenance`] pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    /// Policies dropped from the policy cache because their header value expired
+    pub evicted_policies: usize,
+    /// Per-request nonces dropped after outliving their request
+    pub evicted_nonces: usize,
+    /// Whether the policy cache capacity was reduced
+    pub policy_cache_shrunk: bool,
+    /// Approximate bytes held by the caches after the pass
+    pub memory_usage_bytes: usize,
+    /// Temporary sources removed from the policy because their time ran out
+    pub expired_sources: usize,
+}
+
+/// What the middleware does with a response whose policy header could not be
+/// generated, set with [`CspConfigBuilder::with_header_failure_mode`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HeaderFailureMode {
+    /// Send the response without the header
+    #[default]
+    Serve,
+    /// Send `default-src 'self'` in place of a main policy that could not be
+    /// generated, so some protection is always present. It goes out in the
+    /// main policy's mode, report-only included, and allows the request nonce
+    /// for scripts and styles. Failed [additional policies] are left out, as
+    /// under `Serve`.
+    ///
+    /// [additional policies]: crate::middleware::CspMiddleware::with_additional_policy
+    Fallback,
+    /// Replace the response with a `500 Internal Server Error`, so content is
+    /// never served without its policy
+    Reject,
+}
+
+/// Health of a [`CspConfig`], as returned by [`CspConfig::health`].
+///
+/// Serializes to JSON for readiness endpoints such as the one registered by
+/// [`configure_csp_readiness`].
+///
+/// [`configure_csp_readiness`]: crate::middleware::configure_csp_readiness
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct CspHealth {
+    /// Whether the active policy compiled into a header value
+    pub policy_compiled: bool,
+    /// Why the active policy failed to compile
+    pub compile_error: Option<String>,
+    /// When the policy was last changed, `None` if it never was
+    pub last_reload: Option<SystemTime>,
+    /// Whether the policy published by the last change compiled
+    pub last_reload_compiled: Option<bool>,
+    /// Documents waiting for the shadow audit, `None` when it is disabled
+    pub shadow_audit_backlog: Option<usize>,
+    /// Whether the task started by [`CspConfig::start_maintenance`] is still
+    /// running and has run within two intervals, `None` when none was started
+    pub maintenance_alive: Option<bool>,
+}
+
+impl CspHealth {
+    /// Returns `true` when the policy compiled and maintenance, if started, is running.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.policy_compiled && self.maintenance_alive != Some(false)
+    }
+}
+
+/// Marks the maintenance task `task` as stopped when dropped, unless a newer
+/// one replaced it.
+struct MaintenanceStopped {
+    task: u64,
+    current: Arc<AtomicU64>,
+}
+
+impl Drop for MaintenanceStopped {
+    fn drop(&mut self) {
+        let _ = self
+            .current
+            .compare_exchange(self.task, 0, Ordering::Relaxed, Ordering::Relaxed);
+    }
+}
+
+/// The effective settings of a [`CspConfig`], as returned by
+/// [`CspConfig::export`] and restored by [`CspConfig::import`].
+///
+/// Covers the policy as it currently stands, including changes made at
+/// runtime, the nonce settings and the cache settings. Statistics, listeners
+/// and options without a snapshot field start from their defaults on import.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CspConfigSnapshot {
+    pub policy: PolicyDocument,
+    /// Length in bytes of generated nonces, `None` without a nonce generator
+    #[serde(default)]
+    pub nonce_length: Option<usize>,
+    #[serde(default)]
+    pub nonce_per_request: bool,
+    /// How long nonces are reused; a static nonce is generated anew on import
+    #[serde(default)]
+    pub nonce_scope: NonceScope,
+    #[serde(default)]
+    pub nonce_request_header: Option<String>,
+    #[serde(default)]
+    pub html_nonce_rewriting: bool,
+    #[serde(default)]
+    pub lazy_nonce: bool,
+    /// `Cache-Control` value for responses that carry a request nonce
+    #[serde(default)]
+    pub nonce_cache_control: Option<String>,
+    pub cache_duration_secs: u64,
+    pub cache_size: usize,
+    /// Sources added with [`CspConfig::add_temporary_source`] that have not
+    /// expired yet; they are part of `policy` as well
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub temporary_sources: Vec<TemporarySourceSnapshot>,
+}
+
+/// A temporary source in a [`CspConfigSnapshot`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemporarySourceSnapshot {
+    pub directive: String,
+    pub source: String,
+    /// Seconds left until the source is removed, counted from the export
+    pub expires_in_secs: u64,
+    /// The directive did not exist before the source was added, so it is
+    /// removed along with it
+    #[serde(default)]
+    pub created_directive: bool,
+}
+
+/// A change to the policy held by a [`CspConfig`], delivered to listeners
+/// registered with [`CspConfig::add_policy_listener`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum PolicyEvent {
+    /// [`CspConfig::update_policy`] finished; the hashes are structural, so
+    /// equal values mean the update left the policy unchanged.
+    Updated {
+        old_hash: NonZeroU64,
+        new_hash: NonZeroU64,
+        /// The policy as it stood once the update was applied
+        policy: Arc<CspPolicy>,
+    },
+}
+
+/// One published policy change, as returned by [`CspConfig::policy_history`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PolicyHistoryEntry {
+    /// When the change was published
+    pub at: SystemTime,
+    /// What changed, e.g. `update_policy` or the temporary source that was added or expired
+    pub description: Cow<'static, str>,
+    pub old_hash: NonZeroU64,
+    pub new_hash: NonZeroU64,
+    /// Whether the published policy compiled into a header value
+    pub compiled: bool,
+}
+
+/// A source added by [`CspConfig::add_temporary_source`] that is still pending removal.
+#[derive(Debug, Clone)]
+struct TemporarySource {
+    directive: Cow<'static, str>,
+    source: Source,
+    expires_at: Instant,
+    /// The directive did not exist before the source was added
+    created_directive: bool,
+}
+
+impl TemporarySource {
+    fn revert(&self, policy: &mut CspPolicy) {
+        let Some(mut directive) = policy.get_directive(&self.directive).cloned() else {
+            return;
+        };
+        if !directive.remove_source(&self.source) {
+            return;
+        }
+        // A directive created for the source was seeded with what it inherited;
+        // once only that is left it can go again
+        if self.created_directive {
+            let inherited = policy.inherited_sources(&self.directive).unwrap_or(&[]);
+            if directive
+                .sources()
+                .iter()
+                .all(|source| inherited.contains(source))
+            {
+                policy.remove_directive(&self.directive);
+                return;
+            }
+        }
+        if directive.sources().is_empty() {
+            directive.add_source(Source::None);
+        }
+        policy.add_directive(directive);
+    }
+}
+
+type EventFn = dyn Fn(&PolicyEvent) + Send + Sync + 'static;
+type LegacyUpdateFn = dyn Fn(&mut CspPolicy) + Send + Sync + 'static;
+
+/// A registered listener; cloned out of the registry before it is called so
+/// listeners may add or remove listeners themselves.
+#[derive(Clone)]
+enum PolicyListener {
+    Event(Arc<EventFn>),
+    Legacy(Arc<LegacyUpdateFn>),
+}
+
+/// Event listeners waiting to hear about an update that has been published.
+///
+/// Returned from under the locks so listeners are free to update the policy again.
+#[must_use]
+struct PendingNotification {
+    listeners: Vec<Arc<EventFn>>,
+    event: PolicyEvent,
+}
+
+impl PendingNotification {
+    fn notify(self) {
+        for listener in &self.listeners {
+            listener(&self.event);
+        }
+    }
+}
+
+/// The active policy and its compiled header, published together so readers
+/// never see one without the other.
+#[derive(Debug)]
+struct PolicyState {
+    policy: Arc<CspPolicy>,
+    compiled: Option<Arc<CompiledCspPolicy>>,
+    compile_error: Option<String>,
+}
+
+impl PolicyState {
+    fn new(policy: CspPolicy) -> Self {
+        let (compiled, compile_error) = match policy.compile() {
+            Ok(compiled) => (Some(Arc::new(compiled)), None),
+            Err(error) => (None, Some(error.to_string())),
+        };
+        Self {
+            policy: Arc::new(policy),
+            compiled,
+            compile_error,
+        }
+    }
+}
+
+/// Logs the compiled header each time it differs from the last one logged.
+#[derive(Debug, Default)]
+struct HeaderLog {
+    last: Mutex<Option<HeaderValue>>,
+}
+
+impl HeaderLog {
+    fn record(&self, state: &PolicyState) {
+        let Some(compiled) = &state.compiled else {
+            return;
+        };
+        let value = compiled.header_value();
+        let mut last = self.last.lock();
+        if last.as_ref() == Some(value) {
+            return;
+        }
+        *last = Some(value.clone());
+        drop(last);
+
+        csp_log!(
+            Info,
+            "CSP header changed: version={:016x} name={} bytes={} sha256={} value=\"{}\"",
+            state.policy.structural_hash(),
+            compiled.header_name(),
+            value.len(),
+            HashGenerator::generate(HashAlgorithm::Sha256, value.as_bytes()),
+            String::from_utf8_lossy(value.as_bytes())
+        );
+    }
+}
 
 /// Core CSP configuration container.
 ///
@@ -163,14 +501,14 @@ type UpdateFn = Box<dyn Fn(&mut CspPolicy) + Send + Sync + 'static>;
 ///
 /// # Features
 ///
-/// - **Thread-safe policy management** - Concurrent read/write access using
-///   `RwLock`
+/// - **Thread-safe policy management** - Lock-free reads of an immutable
+///   snapshot that updates replace atomically
 /// - **Nonce generation** - Optional cryptographic nonce generation for inline
 ///   content
 /// - **Policy caching** - LRU cache for compiled policies to improve
 ///   performance
 /// - **Real-time monitoring** - Built-in statistics and performance metrics
-/// - **Update listeners** - Callbacks for policy change notifications
+/// - **Policy listeners** - Callbacks for policy change notifications
 ///
 /// # Examples
 ///
@@ -187,16 +525,52 @@ type UpdateFn = Box<dyn Fn(&mut CspPolicy) + Send + Sync + 'static>;
 /// ```
 #[derive(Clone)]
 pub struct CspConfig {
-    /// The CSP policy wrapped in `Arc<RwLock>` for thread-safe access
-    policy: Arc<RwLock<CspPolicy>>,
+    /// The active policy and its compiled header, swapped atomically on update
+    policy: Arc<ArcSwap<PolicyState>>,
+    /// Serializes policy updates so none is lost between load and store
+    update_lock: Arc<Mutex<()>>,
     /// Optional nonce generator for inline content security
     nonce_generator: Option<Arc<NonceGenerator>>,
     /// Flag to enable per-request nonce generation
     nonce_per_request: Arc<AtomicBool>,
     /// Bounded cache for per-request nonces indexed by request ID
-    per_request_nonces: Arc<Mutex<LruCache<String, String>>>,
+    per_request_nonces: Arc<Mutex<LruCache<RequestId, CachedValue<Arc<str>>>>>,
+    /// How long a generated nonce is reused
+    nonce_scope: NonceScope,
+    /// The nonce sent with every response under `NonceScope::Static`
+    static_nonce: Option<Arc<str>>,
     /// Optional header name for nonce transmission
     nonce_request_header: Option<Cow<'static, str>>,
+    /// Inject the request nonce into inline tags of HTML responses
+    html_nonce_rewriting: bool,
+    /// Generate request nonces on first access instead of up front
+    lazy_nonce: bool,
+    /// `Cache-Control` value for responses that carry a request nonce
+    nonce_cache_control: Option<HeaderValue>,
+    /// Pre-rendered `Reporting-Endpoints` header value
+    reporting_endpoints: Option<HeaderValue>,
+    /// Add `upgrade-insecure-requests` to responses for requests received over TLS
+    upgrade_insecure_on_tls: bool,
+    /// Pre-rendered `Strict-Transport-Security` value sent on TLS requests
+    hsts: Option<HeaderValue>,
+    /// Treat `Forwarded`/`X-Forwarded-Proto` as authoritative for the request scheme
+    trust_forwarded_proto: bool,
+    /// Proxies whose forwarded headers determine the effective origin and client
+    trusted_proxies: Option<Arc<TrustedProxies>>,
+    /// Keep `ws:`/`wss:` twins of HTTP(S) `connect-src` sources after every update
+    websocket_mirroring: bool,
+    /// Derive `X-Frame-Options` from the enforced `frame-ancestors` directive
+    frame_options_sync: bool,
+    /// What happens to responses whose policy header could not be generated
+    header_failure_mode: HeaderFailureMode,
+    /// Body of the `500` sent under `HeaderFailureMode::Reject`, when set
+    header_failure_page: Option<Bytes>,
+    /// Structural hash of the policy the last logged header failure happened under
+    header_failure_logged_for: Arc<AtomicU64>,
+    /// Background page audit of HTML responses, when enabled
+    shadow_auditor: Option<ShadowAuditor>,
+    /// Logs the emitted header whenever it changes, when enabled
+    header_log: Option<Arc<HeaderLog>>,
     /// Cache duration in seconds for policy caching
     cache_duration: Arc<AtomicUsize>,
     /// Statistics collector for monitoring
@@ -204,13 +578,25 @@ pub struct CspConfig {
     /// Performance metrics collector
     perf_metrics: Arc<PerformanceMetrics>,
     /// Registered update listeners for policy changes
-    update_listeners: Arc<dashmap::DashMap<usize, UpdateFn>>,
+    update_listeners: Arc<ConcurrentMap<usize, PolicyListener>>,
     /// Counter for generating unique listener IDs
     next_listener_id: Arc<AtomicUsize>,
-    /// LRU cache for compiled policies
-    policy_cache: Arc<RwLock<LruCache<NonZeroU64, Arc<CspPolicy>>>>,
-    /// Lock-free compiled snapshot for the active policy
-    compiled_policy: Arc<ArcSwapOption<CompiledCspPolicy>>,
+    /// Adaptive LRU cache for compiled policies
+    policy_cache: Arc<RwLock<AdaptiveCache<PolicyCacheKey, Arc<CspPolicy>>>>,
+    /// The most recent published changes, oldest first
+    history: Arc<Mutex<VecDeque<PolicyHistoryEntry>>>,
+    /// Sources to remove from the policy once their time runs out
+    temporary_sources: Arc<Mutex<Vec<TemporarySource>>>,
+    /// Milliseconds after `clock_origin` at which the next temporary source expires
+    next_source_expiry: Arc<AtomicU64>,
+    /// Interval of the task started by `start_maintenance` in milliseconds, 0 when none was
+    maintenance_interval: Arc<AtomicU64>,
+    /// Milliseconds after `clock_origin` at which maintenance last ran
+    last_maintenance: Arc<AtomicU64>,
+    /// ID of the running maintenance task, 0 once it stopped
+    maintenance_task: Arc<AtomicU64>,
+    /// Reference point for `next_source_expiry` and `last_maintenance`
+    clock_origin: Instant,
 }
 
 impl CspConfig {
@@ -235,33 +621,76 @@ impl CspConfig {
     /// let config = CspConfig::new(policy);
     /// ```
     pub fn new(policy: CspPolicy) -> Self {
-        let compiled_policy = policy.compile().ok().map(Arc::new);
-
-        Self {
-            policy: Arc::new(RwLock::new(policy)),
+        let stats = Arc::new(CspStats::new());
+        let policy_cache = AdaptiveCache::with_metrics(
+            NonZeroUsize::new(DEFAULT_POLICY_CACHE_ENTRIES).unwrap(),
+            stats.policy_cache_metrics(),
+        );
+        let config = Self {
+            policy: Arc::new(ArcSwap::from_pointee(PolicyState::new(policy))),
+            update_lock: Arc::new(Mutex::new(())),
             nonce_generator: None,
             nonce_per_request: Arc::new(AtomicBool::new(false)),
             per_request_nonces: Arc::new(Mutex::new(LruCache::new(
                 NonZeroUsize::new(DEFAULT_REQUEST_NONCE_CACHE_ENTRIES).unwrap(),
             ))),
+            nonce_scope: NonceScope::Request,
+            static_nonce: None,
             nonce_request_header: None,
+            html_nonce_rewriting: false,
+            lazy_nonce: false,
+            nonce_cache_control: None,
+            reporting_endpoints: None,
+            upgrade_insecure_on_tls: false,
+            hsts: None,
+            trust_forwarded_proto: false,
+            trusted_proxies: None,
+            websocket_mirroring: false,
+            frame_options_sync: false,
+            header_failure_mode: HeaderFailureMode::Serve,
+            header_failure_page: None,
+            header_failure_logged_for: Arc::new(AtomicU64::new(0)),
+            shadow_auditor: None,
+            header_log: None,
             cache_duration: Arc::new(AtomicUsize::new(60)),
-            stats: Arc::new(CspStats::new()),
+            stats,
             perf_metrics: Arc::new(PerformanceMetrics::new()),
-            update_listeners: Arc::new(dashmap::DashMap::new()),
+            update_listeners: Arc::new(ConcurrentMap::new()),
             next_listener_id: Arc::new(AtomicUsize::new(0)),
-            policy_cache: Arc::new(RwLock::new(LruCache::new(
-                NonZeroUsize::new(DEFAULT_POLICY_CACHE_ENTRIES).unwrap(),
+            policy_cache: Arc::new(RwLock::new(policy_cache)),
+            history: Arc::new(Mutex::new(VecDeque::with_capacity(
+                DEFAULT_POLICY_HISTORY_ENTRIES,
             ))),
-            compiled_policy: Arc::new(ArcSwapOption::from(compiled_policy)),
-        }
+            temporary_sources: Arc::new(Mutex::new(Vec::new())),
+            next_source_expiry: Arc::new(AtomicU64::new(u64::MAX)),
+            maintenance_interval: Arc::new(AtomicU64::new(0)),
+            last_maintenance: Arc::new(AtomicU64::new(0)),
+            maintenance_task: Arc::new(AtomicU64::new(0)),
+            clock_origin: Instant::now(),
+        };
+        let policy = config.policy();
+        config.validate_policy(&policy);
+        config.stats.record_policy_version(policy.structural_hash());
+        config
+    }
+
+    /// Validates `policy`, counting the check in [`CspStats`] and logging a
+    /// warning when it fails. Invalid policies are still applied.
+    fn validate_policy(&self, policy: &CspPolicy) {
+        self.stats.increment_policy_validation_count();
+        let _ = policy.validate_and_log();
     }
 
     /// Updates the CSP policy using the provided closure.
     ///
+    /// The closure edits a copy of the current policy, which is compiled and then
+    /// published in one atomic swap; requests in flight keep the snapshot they
+    /// loaded. Concurrent updates are applied one after another.
+    ///
     /// This method provides thread-safe policy updates and automatically:
-    /// - Notifies all registered update listeners
+    /// - Notifies all registered policy listeners once the update is published
     /// - Clears the policy cache to ensure consistency
+    /// - Validates the new policy, logging a warning if it is invalid
     /// - Increments policy update statistics
     ///
     /// # Arguments
@@ -284,33 +713,409 @@ impl CspConfig {
     where
         F: FnOnce(&mut CspPolicy),
     {
-        {
-            let mut policy_guard = self.policy.write();
-            f(&mut policy_guard);
+        let update = |policy: &mut CspPolicy| {
+            f(policy);
+            true
+        };
+        if let Some(notification) = self.apply_update(Cow::Borrowed("update_policy"), update) {
+            notification.notify();
         }
+    }
 
+    /// Publishes an edited copy of the policy and records it in the history.
+    ///
+    /// `f` returns whether it changed the policy; nothing is published when it
+    /// did not. Event listeners are returned rather than called so the caller
+    /// can release its own locks first.
+    fn apply_update<F>(&self, description: Cow<'static, str>, f: F) -> Option<PendingNotification>
+    where
+        F: FnOnce(&mut CspPolicy) -> bool,
+    {
+        let mut legacy_listeners = Vec::new();
+        let mut event_listeners = Vec::new();
         if !self.update_listeners.is_empty() {
-            for listener in self.update_listeners.iter() {
-                let mut policy = self.policy.write();
-                listener.value()(&mut policy);
-            }
+            self.update_listeners
+                .for_each_value(|listener| match listener.clone() {
+                    PolicyListener::Event(listener) => event_listeners.push(listener),
+                    PolicyListener::Legacy(listener) => legacy_listeners.push(listener),
+                });
         }
 
-        self.refresh_compiled_policy();
+        let (old_hash, snapshot) = {
+            let _update = self.update_lock.lock();
+            let current = self.policy.load_full();
+            let old_hash = current.policy.structural_hash();
+
+            let mut policy = CspPolicy::clone(&current.policy);
+            if !f(&mut policy) {
+                return None;
+            }
+            for listener in &legacy_listeners {
+                listener(&mut policy);
+            }
+            if self.websocket_mirroring {
+                policy.mirror_websocket_sources();
+            }
+            self.validate_policy(&policy);
+
+            let state = PolicyState::new(policy);
+            let snapshot = state.policy.clone();
+            let compiled = state.compiled.is_some();
+            self.publish(state);
+            self.record_history(description, old_hash, snapshot.structural_hash(), compiled);
+            (old_hash, snapshot)
+        };
+
         self.stats.increment_policy_update_count();
+
+        (!event_listeners.is_empty()).then(|| PendingNotification {
+            listeners: event_listeners,
+            event: PolicyEvent::Updated {
+                old_hash,
+                new_hash: snapshot.structural_hash(),
+                policy: snapshot,
+            },
+        })
+    }
+
+    fn record_history(
+        &self,
+        description: Cow<'static, str>,
+        old_hash: NonZeroU64,
+        new_hash: NonZeroU64,
+        compiled: bool,
+    ) {
+        let mut history = self.history.lock();
+        if history.len() == DEFAULT_POLICY_HISTORY_ENTRIES {
+            history.pop_front();
+        }
+        history.push_back(PolicyHistoryEntry {
+            at: SystemTime::now(),
+            description,
+            old_hash,
+            new_hash,
+            compiled,
+        });
+    }
+
+    /// Returns the most recent policy changes, oldest first.
+    ///
+    /// Every [`update_policy`](Self::update_policy) call and every temporary
+    /// source added or expired is recorded; only the last few dozen entries are
+    /// kept.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<PolicyHistoryEntry>` - The recorded changes
+    pub fn policy_history(&self) -> Vec<PolicyHistoryEntry> {
+        self.history.lock().iter().cloned().collect()
+    }
+
+    /// Allows `source` under `directive` for `duration`, then removes it again.
+    ///
+    /// Meant for time-boxed exceptions such as a campaign's tracking pixel. The
+    /// source is added at once through the same path as
+    /// [`update_policy`](Self::update_policy); expiry is checked on every request
+    /// through [`CspMiddleware`] and by [`run_maintenance`](Self::run_maintenance),
+    /// and both the addition and the removal appear in
+    /// [`policy_history`](Self::policy_history).
+    ///
+    /// A source that was already allowed is left alone and never removed, and
+    /// publishes no update. Adding the same temporary source again extends its
+    /// time if the new deadline is later. Durations beyond ten years are
+    /// clamped to ten years. A directive this call creates starts with the sources it inherited
+    /// from `default-src`, and is dropped again once only those are left; a
+    /// directive the removal leaves empty otherwise falls back to `'none'`.
+    ///
+    /// # Arguments
+    ///
+    /// * `directive` - The directive to extend, e.g. `img-src`
+    /// * `source` - The source to allow
+    /// * `duration` - How long the source stays allowed
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use actix_web_csp::{CspConfig, CspPolicyBuilder, Source};
+    /// use std::time::Duration;
+    ///
+    /// let config = CspConfig::new(CspPolicyBuilder::new().img_src([Source::Self_]).build_unchecked());
+    /// config.add_temporary_source(
+    ///     "img-src",
+    ///     Source::Host("pixel.campaign.example".into()),
+    ///     Duration::from_secs(4 * 60 * 60),
+    /// );
+    /// ```
+    ///
+    /// [`CspMiddleware`]: crate::middleware::CspMiddleware
+    pub fn add_temporary_source(
+        &self,
+        directive: impl Into<Cow<'static, str>>,
+        source: Source,
+        duration: Duration,
+    ) {
+        let directive = directive.into();
+        let now = Instant::now();
+        let duration = duration.min(MAX_TEMPORARY_SOURCE_DURATION);
+        let expires_at = now.checked_add(duration).unwrap_or(now);
+        let mut temporary = self.temporary_sources.lock();
+
+        if let Some(grant) = temporary
+            .iter_mut()
+            .find(|grant| grant.directive == directive && grant.source == source)
+        {
+            grant.expires_at = grant.expires_at.max(expires_at);
+            self.schedule_expiry(&temporary);
+            return;
+        }
+
+        let mut grant = None;
+        let description = format!(
+            "temporary {directive} source {source} added for {}s",
+            duration.as_secs()
+        );
+        let notification = self.apply_update(Cow::Owned(description), |policy| {
+            let existing = policy.get_directive(&directive);
+            if existing.is_some_and(|existing| existing.sources().contains(&source)) {
+                return false;
+            }
+            grant = Some(TemporarySource {
+                directive: directive.clone(),
+                source: source.clone(),
+                expires_at,
+                created_directive: existing.is_none(),
+            });
+            policy.append_source(directive.clone(), source.clone());
+            true
+        });
+
+        if let Some(grant) = grant {
+            temporary.push(grant);
+            self.schedule_expiry(&temporary);
+        }
+        drop(temporary);
+
+        if let Some(notification) = notification {
+            notification.notify();
+        }
+    }
+
+    /// Captures the effective configuration so it can be persisted and restored
+    /// with [`import`](Self::import) after a restart.
+    ///
+    /// The snapshot holds the current policy, including updates and temporary
+    /// sources added at runtime, along with the nonce and cache settings.
+    ///
+    /// # Returns
+    ///
+    /// `CspConfigSnapshot` - The configuration as it stands now
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use actix_web_csp::{CspConfig, CspConfigBuilder, CspPolicyBuilder, Source};
+    ///
+    /// let config = CspConfigBuilder::new()
+    ///     .policy(CspPolicyBuilder::new().default_src([Source::Self_]).build()?)
+    ///     .with_nonce_generator(16)
+    ///     .build();
+    /// config.update_policy(|policy| {
+    ///     policy.append_source("img-src", Source::Host("images.example.com".into()));
+    /// });
+    ///
+    /// let json = serde_json::to_string(&config.export()).unwrap();
+    /// // ... after a restart
+    /// let restored = CspConfig::import(serde_json::from_str(&json).unwrap())?;
+    /// assert_eq!(restored.policy().to_string(), config.policy().to_string());
+    /// # Ok::<(), actix_web_csp::CspError>(())
+    /// ```
+    pub fn export(&self) -> CspConfigSnapshot {
+        let now = Instant::now();
+        // Holding the lock keeps the policy and its temporary sources in step
+        let temporary = self.temporary_sources.lock();
+        let temporary_sources = temporary
+            .iter()
+            .map(|grant| TemporarySourceSnapshot {
+                directive: grant.directive.to_string(),
+                source: grant.source.to_string(),
+                // Rounded up so repeated export and import never shortens it
+                expires_in_secs: grant
+                    .expires_at
+                    .saturating_duration_since(now)
+                    .as_millis()
+                    .div_ceil(1000) as u64,
+                created_directive: grant.created_directive,
+            })
+            .collect();
+        let policy = PolicyDocument::from(self.policy().as_ref());
+        drop(temporary);
+
+        CspConfigSnapshot {
+            policy,
+            nonce_length: self
+                .nonce_generator
+                .as_ref()
+                .map(|generator| generator.length()),
+            nonce_per_request: self.nonce_per_request.load(Ordering::Relaxed),
+            nonce_scope: self.nonce_scope,
+            nonce_request_header: self.nonce_request_header.as_deref().map(str::to_owned),
+            html_nonce_rewriting: self.html_nonce_rewriting,
+            lazy_nonce: self.lazy_nonce,
+            nonce_cache_control: self
+                .nonce_cache_control
+                .as_ref()
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned),
+            cache_duration_secs: self.cache_duration().as_secs(),
+            cache_size: self.policy_cache.read().cap().get(),
+            temporary_sources,
+        }
+    }
+
+    /// Builds a configuration from a snapshot taken by [`export`](Self::export).
+    ///
+    /// Temporary sources keep the time they had left when the snapshot was taken.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The exported configuration
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CspConfig)` - The restored configuration
+    /// * `Err(CspError)` - If the policy or a temporary source does not parse or
+    ///   validate, or a temporary source expires too far ahead to represent
+    pub fn import(snapshot: CspConfigSnapshot) -> Result<Self, CspError> {
+        let mut builder = CspConfigBuilder::new()
+            .policy(CspPolicy::try_from(snapshot.policy)?)
+            .with_nonce_per_request(snapshot.nonce_per_request)
+            .with_nonce_scope(snapshot.nonce_scope)
+            .with_html_nonce_rewriting(snapshot.html_nonce_rewriting)
+            .with_lazy_nonce(snapshot.lazy_nonce)
+            .with_cache_duration(Duration::from_secs(snapshot.cache_duration_secs))
+            .with_cache_size(snapshot.cache_size);
+        if let Some(length) = snapshot.nonce_length {
+            builder = builder.with_nonce_generator(length);
+        }
+        if let Some(header) = snapshot.nonce_request_header {
+            builder = builder.with_nonce_request_header(header);
+        }
+        if let Some(value) = &snapshot.nonce_cache_control {
+            builder = builder.with_nonce_cache_control_value(value);
+        }
+
+        let now = Instant::now();
+        let grants = snapshot
+            .temporary_sources
+            .into_iter()
+            .map(|grant| {
+                let expires_at = now
+                    .checked_add(Duration::from_secs(grant.expires_in_secs))
+                    .ok_or_else(|| {
+                        CspError::ConfigError(format!(
+                            "Temporary {} source {} expires too far in the future: {}s",
+                            grant.directive, grant.source, grant.expires_in_secs
+                        ))
+                    })?;
+                Ok(TemporarySource {
+                    directive: Cow::Owned(grant.directive),
+                    source: grant.source.parse()?,
+                    expires_at,
+                    created_directive: grant.created_directive,
+                })
+            })
+            .collect::<Result<Vec<_>, CspError>>()?;
+
+        let config = builder.build();
+        let mut temporary = config.temporary_sources.lock();
+        *temporary = grants;
+        config.schedule_expiry(&temporary);
+        drop(temporary);
+        Ok(config)
+    }
+
+    /// Removes temporary sources whose time has run out.
+    ///
+    /// Called on every request by [`CspMiddleware`] and by
+    /// [`run_maintenance`](Self::run_maintenance); calling it directly is only
+    /// needed when neither runs.
+    ///
+    /// # Returns
+    ///
+    /// `usize` - The number of sources removed
+    ///
+    /// [`CspMiddleware`]: crate::middleware::CspMiddleware
+    pub fn expire_temporary_sources(&self) -> usize {
+        let now = Instant::now();
+        let mut temporary = self.temporary_sources.lock();
+        let (expired, pending): (Vec<_>, Vec<_>) = temporary
+            .drain(..)
+            .partition(|grant| grant.expires_at <= now);
+        *temporary = pending;
+        self.schedule_expiry(&temporary);
+        if expired.is_empty() {
+            return 0;
+        }
+
+        let description = expired
+            .iter()
+            .map(|grant| {
+                format!(
+                    "temporary {} source {} expired",
+                    grant.directive, grant.source
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        let notification = self.apply_update(Cow::Owned(description), |policy| {
+            for grant in &expired {
+                grant.revert(policy);
+            }
+            true
+        });
+        drop(temporary);
+
+        if let Some(notification) = notification {
+            notification.notify();
+        }
+        expired.len()
+    }
+
+    /// Expires temporary sources if the earliest deadline has passed; a single
+    /// atomic load otherwise.
+    #[inline]
+    pub(crate) fn expire_due_temporary_sources(&self) {
+        if self.clock_millis(Instant::now()) >= self.next_source_expiry.load(Ordering::Acquire) {
+            self.expire_temporary_sources();
+        }
+    }
+
+    fn schedule_expiry(&self, temporary: &[TemporarySource]) {
+        let next = temporary
+            .iter()
+            .map(|grant| self.clock_millis(grant.expires_at))
+            .min()
+            .unwrap_or(u64::MAX);
+        self.next_source_expiry.store(next, Ordering::Release);
     }
 
-    /// Returns a cloned reference to the CSP policy.
+    #[inline]
+    fn clock_millis(&self, at: Instant) -> u64 {
+        at.saturating_duration_since(self.clock_origin).as_millis() as u64
+    }
+
+    /// Returns a snapshot of the current CSP policy.
     ///
-    /// The policy is wrapped in `Arc<RwLock<CspPolicy>>` for thread-safe access.
-    /// Multiple threads can hold read locks simultaneously, but write access is exclusive.
+    /// Loading the snapshot is a lock-free pointer read. It does not change when
+    /// the policy is updated later; use [`update_policy`](Self::update_policy)
+    /// to make changes.
     ///
     /// # Returns
     ///
-    /// `Arc<RwLock<CspPolicy>>` - Thread-safe reference to the policy
+    /// `Arc<CspPolicy>` - The policy as of this call
     #[inline]
-    pub fn policy(&self) -> Arc<RwLock<CspPolicy>> {
-        self.policy.clone()
+    pub fn policy(&self) -> Arc<CspPolicy> {
+        self.policy.load().policy.clone()
     }
 
     /// Generates a new cryptographic nonce if a generator is configured.
@@ -364,7 +1169,7 @@ impl CspConfig {
     /// # Examples
     ///
     /// ```rust
-    /// use actix_web_csp::{CspConfigBuilder, CspPolicy};
+    /// use actix_web_csp::{CspConfigBuilder, CspPolicy, RequestId};
     ///
     /// let config = CspConfigBuilder::new()
     ///     .policy(CspPolicy::default())
@@ -372,12 +1177,19 @@ impl CspConfig {
     ///     .with_nonce_per_request(true)
     ///     .build();
     ///
-    /// let request_id = "req_12345";
+    /// let request_id = RequestId::next();
     /// let nonce1 = config.get_or_generate_request_nonce(request_id);
     /// let nonce2 = config.get_or_generate_request_nonce(request_id);
     /// // nonce1 == nonce2 (same request gets same nonce)
     /// ```
-    pub fn get_or_generate_request_nonce(&self, request_id: &str) -> Option<String> {
+    pub fn get_or_generate_request_nonce(&self, request_id: RequestId) -> Option<String> {
+        self.shared_request_nonce(request_id)
+            .map(|nonce| nonce.to_string())
+    }
+
+    /// Like [`get_or_generate_request_nonce`](Self::get_or_generate_request_nonce),
+    /// returning the cached value itself rather than a copy.
+    pub(crate) fn shared_request_nonce(&self, request_id: RequestId) -> Option<Arc<str>> {
         if !self
             .nonce_per_request
             .load(std::sync::atomic::Ordering::Relaxed)
@@ -388,13 +1200,19 @@ impl CspConfig {
         let generator = self.nonce_generator.as_ref()?;
         let mut nonce_cache = self.per_request_nonces.lock();
 
-        if let Some(existing) = nonce_cache.get(request_id) {
-            return Some(existing.clone());
+        if let Some(existing) = nonce_cache.get(&request_id) {
+            return Some(existing.value().clone());
         }
 
         self.stats.increment_nonce_generation_count();
-        let nonce = generator.generate();
-        nonce_cache.put(request_id.to_string(), nonce.clone());
+        let nonce: Arc<str> = generator.generate().into();
+        nonce_cache.put(
+            request_id,
+            CachedValue::new(
+                Arc::clone(&nonce),
+                Duration::from_secs(DEFAULT_REQUEST_NONCE_TTL_SECS),
+            ),
+        );
         Some(nonce)
     }
 
@@ -424,21 +1242,184 @@ impl CspConfig {
         &self.perf_metrics
     }
 
+    /// Returns how long a generated nonce is reused.
+    #[inline]
+    pub fn nonce_scope(&self) -> NonceScope {
+        self.nonce_scope
+    }
+
     /// Returns the optional header name used to expose a generated nonce.
     #[inline]
     pub fn nonce_request_header(&self) -> Option<&str> {
         self.nonce_request_header.as_deref()
     }
 
-    /// Registers a callback function to be called when the policy is updated.
+    /// Returns whether the middleware adds the request nonce to inline
+    /// `<script>`/`<style>` tags of HTML responses.
+    #[inline]
+    pub fn html_nonce_rewriting(&self) -> bool {
+        self.html_nonce_rewriting
+    }
+
+    /// Returns whether request nonces are generated on first access through
+    /// [`CspNonce`] rather than for every request.
+    ///
+    /// [`CspNonce`]: crate::security::CspNonce
+    #[inline]
+    pub fn lazy_nonce(&self) -> bool {
+        self.lazy_nonce && self.nonce_generator.is_some()
+    }
+
+    /// Returns the `Cache-Control` value set on responses that carry a request
+    /// nonce, if enabled.
+    #[inline]
+    pub fn nonce_cache_control(&self) -> Option<&HeaderValue> {
+        self.nonce_cache_control.as_ref()
+    }
+
+    /// Returns the `Reporting-Endpoints` header value built from the configured endpoints.
+    #[inline]
+    pub fn reporting_endpoints(&self) -> Option<&HeaderValue> {
+        self.reporting_endpoints.as_ref()
+    }
+
+    /// Returns whether `upgrade-insecure-requests` is added to responses for
+    /// requests received over TLS.
+    #[inline]
+    pub fn upgrade_insecure_on_tls(&self) -> bool {
+        self.upgrade_insecure_on_tls
+    }
+
+    /// Returns the `Strict-Transport-Security` value sent on TLS requests, if enabled.
+    #[inline]
+    pub fn hsts(&self) -> Option<&HeaderValue> {
+        self.hsts.as_ref()
+    }
+
+    /// Returns whether forwarded protocol headers are trusted when deciding if a
+    /// request was received over TLS.
+    #[inline]
+    pub fn trust_forwarded_proto(&self) -> bool {
+        self.trust_forwarded_proto
+    }
+
+    /// Returns the proxies whose forwarded headers are honoured, if configured.
+    #[inline]
+    pub fn trusted_proxies(&self) -> Option<&Arc<TrustedProxies>> {
+        self.trusted_proxies.as_ref()
+    }
+
+    /// Returns whether `connect-src` HTTP(S) sources are mirrored to `ws:`/`wss:`.
+    #[inline]
+    pub fn websocket_mirroring(&self) -> bool {
+        self.websocket_mirroring
+    }
+
+    /// Returns whether `X-Frame-Options` is derived from `frame-ancestors`.
+    #[inline]
+    pub fn frame_options_sync(&self) -> bool {
+        self.frame_options_sync
+    }
+
+    /// Returns what happens to responses whose policy header could not be generated.
+    #[inline]
+    pub fn header_failure_mode(&self) -> HeaderFailureMode {
+        self.header_failure_mode
+    }
+
+    /// Returns the HTML body sent when a response is rejected, if configured.
+    #[inline]
+    pub fn header_failure_page(&self) -> Option<&Bytes> {
+        self.header_failure_page.as_ref()
+    }
+
+    /// Returns `true` for the first header generation failure under the
+    /// current policy, so a broken policy logs one warning per version rather
+    /// than one per request.
+    pub(crate) fn first_header_failure_for_policy(&self) -> bool {
+        let version = self.policy().structural_hash().get();
+        self.header_failure_logged_for
+            .swap(version, Ordering::Relaxed)
+            != version
+    }
+
+    /// Returns whether the emitted header is logged whenever it changes.
+    #[inline]
+    pub fn header_logging(&self) -> bool {
+        self.header_log.is_some()
+    }
+
+    /// Returns whether HTML responses are audited in the background.
+    #[inline]
+    pub fn shadow_audit(&self) -> bool {
+        self.shadow_auditor.is_some()
+    }
+
+    #[inline]
+    pub(crate) fn shadow_auditor(&self) -> Option<&ShadowAuditor> {
+        self.shadow_auditor.as_ref()
+    }
+
+    /// Returns the headers to attach to a `103 Early Hints` response.
     ///
-    /// Update listeners are useful for implementing custom logic that should run
+    /// Browsers apply a CSP delivered with early hints to the preloads announced
+    /// there, so sending the same policy the final response will carry keeps both
+    /// phases consistent. The map contains the compiled policy header and, when
+    /// configured, `Reporting-Endpoints`. Nonces are never included because the
+    /// final response has not been rendered yet.
+    ///
+    /// Actix Web does not emit informational responses on its own; hand these
+    /// headers to whatever layer sends the `103` (a reverse proxy, a custom HTTP/2
+    /// front end, or a CDN early-hints rule).
+    ///
+    /// ```rust
+    /// use actix_web_csp::{CspConfigBuilder, CspPolicyBuilder, Source};
+    ///
+    /// let config = CspConfigBuilder::new()
+    ///     .policy(
+    ///         CspPolicyBuilder::new()
+    ///             .default_src([Source::Self_])
+    ///             .report_to("csp")
+    ///             .build_unchecked(),
+    ///     )
+    ///     .with_reporting_endpoint("csp", "https://example.com/csp-report")
+    ///     .build();
+    ///
+    /// let headers = config.early_hints_headers();
+    /// assert!(headers.contains_key("content-security-policy"));
+    /// assert!(headers.contains_key("reporting-endpoints"));
+    /// ```
+    pub fn early_hints_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        if let Some(compiled_policy) = self.compiled_policy() {
+            headers.insert(
+                compiled_policy.header_name().clone(),
+                compiled_policy.header_value().clone(),
+            );
+        }
+
+        if let Some(endpoints) = &self.reporting_endpoints {
+            headers.insert(
+                HeaderName::from_static(HEADER_REPORTING_ENDPOINTS),
+                endpoints.clone(),
+            );
+        }
+
+        headers
+    }
+
+    /// Registers a callback that is told about every policy change.
+    ///
+    /// Listeners are useful for implementing custom logic that should run
     /// whenever the CSP policy changes, such as logging, notifications, or
-    /// cache invalidation in external systems.
+    /// cache invalidation in external systems. They run after the update has
+    /// been applied and no lock is held, so they may read the config, update
+    /// the policy again or register further listeners without deadlocking.
     ///
     /// # Arguments
     ///
-    /// * `f` - Callback function that receives a mutable reference to the updated policy
+    /// * `f` - Callback function that receives the [`PolicyEvent`]
     ///
     /// # Returns
     ///
@@ -447,41 +1428,61 @@ impl CspConfig {
     /// # Examples
     ///
     /// ```rust
+    /// use actix_web_csp::core::PolicyEvent;
     /// use actix_web_csp::{CspConfig, CspPolicy};
     ///
     /// let config = CspConfig::new(CspPolicy::default());
     ///
-    /// let listener_id = config.add_update_listener(|policy| {
-    ///     println!("Policy updated!");
-    ///     // Custom logic here
+    /// let listener_id = config.add_policy_listener(|event| {
+    ///     if let PolicyEvent::Updated { old_hash, new_hash, .. } = event {
+    ///         if old_hash != new_hash {
+    ///             println!("Policy changed!");
+    ///         }
+    ///     }
     /// });
     ///
     /// // Later, remove the listener
     /// config.remove_update_listener(listener_id);
     /// ```
+    pub fn add_policy_listener<F>(&self, f: F) -> usize
+    where
+        F: Fn(&PolicyEvent) + Send + Sync + 'static,
+    {
+        self.register_listener(PolicyListener::Event(Arc::new(f)))
+    }
+
+    /// Registers a callback that may modify the policy during every update.
+    ///
+    /// The callback runs inside the policy write lock, once per update, after
+    /// the update closure. Calling back into the config from it deadlocks.
+    #[deprecated(note = "use `add_policy_listener`, which receives an immutable snapshot")]
     pub fn add_update_listener<F>(&self, f: F) -> usize
     where
         F: Fn(&mut CspPolicy) + Send + Sync + 'static,
     {
+        self.register_listener(PolicyListener::Legacy(Arc::new(f)))
+    }
+
+    fn register_listener(&self, listener: PolicyListener) -> usize {
         let id = self
             .next_listener_id
             .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        self.update_listeners.insert(id, Box::new(f));
+        self.update_listeners.insert(id, listener);
         id
     }
 
-    /// Removes a previously registered update listener.
+    /// Removes a previously registered policy or update listener.
     ///
     /// # Arguments
     ///
-    /// * `id` - The listener ID returned by `add_update_listener`
+    /// * `id` - The listener ID returned by `add_policy_listener`
     ///
     /// # Returns
     ///
     /// `bool` - `true` if the listener was found and removed, `false` otherwise
     #[inline]
     pub fn remove_update_listener(&self, id: usize) -> bool {
-        self.update_listeners.remove(&id).is_some()
+        self.update_listeners.remove(&id)
     }
 
     /// Clears all cached per-request nonces.
@@ -510,73 +1511,241 @@ impl CspConfig {
         )
     }
 
-    /// Retrieves a cached policy by its hash.
+    /// Retrieves a cached policy by its cache key.
     ///
     /// The policy cache uses LRU eviction to manage memory usage while providing
     /// fast access to frequently used policy configurations.
     ///
     /// # Arguments
     ///
-    /// * `hash` - Hash of the policy configuration to retrieve
+    /// * `key` - Scope and structural hash of the policy, or a bare policy hash
     ///
     /// # Returns
     ///
     /// * `Some(Arc<CspPolicy>)` - Cached policy if found
     /// * `None` - If policy is not in cache
-    pub fn get_cached_policy(&self, hash: NonZeroU64) -> Option<Arc<CspPolicy>> {
+    pub fn get_cached_policy(&self, key: impl Into<PolicyCacheKey>) -> Option<Arc<CspPolicy>> {
         let mut cache = self.policy_cache.write();
-        cache.get(&hash).cloned()
+        cache.get(&key.into()).cloned()
     }
 
-    /// Stores a policy in the cache with the given hash.
+    /// Stores a policy in the cache under the given key.
     ///
     /// If the cache is full, the least recently used policy will be evicted
-    /// to make room for the new policy.
+    /// to make room for the new policy. Policies containing a nonce are never
+    /// stored, since replaying them would reuse the nonce across requests.
     ///
     /// # Arguments
     ///
-    /// * `hash` - Hash key for the policy
+    /// * `key` - Scope and structural hash of the policy, or a bare policy hash
     /// * `policy` - Policy to cache
     ///
     /// # Returns
     ///
-    /// `Arc<CspPolicy>` - The cached policy wrapped in Arc
-    pub fn cache_policy(&self, hash: NonZeroU64, policy: CspPolicy) -> Arc<CspPolicy> {
+    /// `Arc<CspPolicy>` - The policy wrapped in Arc
+    pub fn cache_policy(
+        &self,
+        key: impl Into<PolicyCacheKey>,
+        policy: CspPolicy,
+    ) -> Arc<CspPolicy> {
         let policy_arc = Arc::new(policy);
-        let mut cache = self.policy_cache.write();
-        cache.put(hash, policy_arc.clone());
+        if !policy_arc.contains_nonce() {
+            self.policy_cache
+                .write()
+                .put(key.into(), policy_arc.clone());
+        }
         policy_arc
     }
 
     #[inline]
     pub fn compiled_policy(&self) -> Option<Arc<CompiledCspPolicy>> {
-        self.compiled_policy.load_full()
+        self.policy.load().compiled.clone()
     }
 
+    /// Returns a snapshot of the configuration's health for readiness checks.
+    ///
+    /// A policy that fails to compile is still published, and responses may then
+    /// go out without a CSP header; reporting that here lets an orchestrator
+    /// hold traffic back from an instance whose policy reload went wrong.
+    ///
+    /// # Returns
+    ///
+    /// `CspHealth` - Compile state, last change, shadow audit backlog and maintenance liveness
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use actix_web_csp::{CspConfig, CspPolicy};
+    ///
+    /// let config = CspConfig::new(CspPolicy::default());
+    /// let health = config.health();
+    ///
+    /// assert!(health.is_ready());
+    /// assert_eq!(health.maintenance_alive, None);
+    /// ```
+    pub fn health(&self) -> CspHealth {
+        let state = self.policy.load();
+        let last_change = self
+            .history
+            .lock()
+            .back()
+            .map(|entry| (entry.at, entry.compiled));
+        let interval = self.maintenance_interval.load(Ordering::Relaxed);
+        let maintenance_alive = (interval > 0).then(|| {
+            if self.maintenance_task.load(Ordering::Relaxed) == 0 {
+                return false;
+            }
+            let since = self
+                .clock_millis(Instant::now())
+                .saturating_sub(self.last_maintenance.load(Ordering::Relaxed));
+            since <= interval.saturating_mul(2)
+        });
+
+        CspHealth {
+            policy_compiled: state.compiled.is_some(),
+            compile_error: state.compile_error.clone(),
+            last_reload: last_change.map(|(at, _)| at),
+            last_reload_compiled: last_change.map(|(_, compiled)| compiled),
+            shadow_audit_backlog: self.shadow_auditor.as_ref().map(ShadowAuditor::backlog),
+            maintenance_alive,
+        }
+    }
+
+    /// Assigns an ID for the per-request nonce cache, or `None` when per-request
+    /// nonces are disabled and no ID is needed.
     #[inline]
-    pub(crate) fn prepare_request_nonce(&self, request_id: &str) -> Option<String> {
-        if self
-            .nonce_per_request
+    pub(crate) fn next_request_id(&self) -> Option<RequestId> {
+        self.nonce_per_request
             .load(std::sync::atomic::Ordering::Relaxed)
-        {
-            self.get_or_generate_request_nonce(request_id)
-        } else {
-            self.generate_nonce()
+            .then(RequestId::next)
+    }
+
+    /// The nonce `req` shares with other requests under the configured
+    /// [`NonceScope`], or `None` when it needs a nonce of its own.
+    #[inline]
+    #[cfg_attr(not(feature = "session"), allow(unused_variables))]
+    pub(crate) fn scoped_nonce(&self, req: &ServiceRequest) -> Option<Arc<str>> {
+        match self.nonce_scope {
+            NonceScope::Request => None,
+            #[cfg(feature = "session")]
+            NonceScope::Session => crate::security::nonce::session_nonce(self, req).map(Arc::from),
+            NonceScope::Static => self.static_nonce.clone(),
         }
     }
 
     #[inline]
-    pub(crate) fn remove_request_nonce(&self, request_id: &str) {
-        if self
-            .nonce_per_request
-            .load(std::sync::atomic::Ordering::Relaxed)
+    pub(crate) fn prepare_request_nonce(&self, request_id: Option<RequestId>) -> Option<Arc<str>> {
+        match request_id {
+            Some(request_id) => self.shared_request_nonce(request_id),
+            None => self.generate_nonce().map(Arc::from),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn remove_request_nonce(&self, request_id: Option<RequestId>) {
+        if let Some(request_id) = request_id {
+            self.per_request_nonces.lock().pop(&request_id);
+        }
+    }
+
+    /// Runs one cache maintenance pass.
+    ///
+    /// Policies whose serialized header expired are dropped from the policy
+    /// cache, per-request nonces left behind by abandoned requests are
+    /// trimmed, expired temporary sources are removed, the policy cache is shrunk when it is mostly idle but hit often,
+    /// and the resulting footprint is recorded in [`PerformanceMetrics`].
+    ///
+    /// # Returns
+    ///
+    /// `MaintenanceReport` - What the pass evicted and the measured footprint
+    pub fn run_maintenance(&self) -> MaintenanceReport {
+        let mut report = MaintenanceReport::default();
+
+        {
+            let mut nonces = self.per_request_nonces.lock();
+            let expired: Vec<RequestId> = nonces
+                .iter()
+                .filter(|(_, nonce)| !nonce.is_valid())
+                .map(|(request_id, _)| *request_id)
+                .collect();
+            for request_id in &expired {
+                nonces.pop(request_id);
+            }
+            report.evicted_nonces = expired.len();
+            report.memory_usage_bytes += nonces
+                .iter()
+                .map(|(_, nonce)| std::mem::size_of::<RequestId>() + nonce.value().len())
+                .sum::<usize>();
+        }
+
         {
-            self.per_request_nonces.lock().pop(request_id);
+            let mut cache = self.policy_cache.write();
+            report.evicted_policies = cache.retain(|_, policy| !policy.has_expired_header_value());
+            report.policy_cache_shrunk = cache.shrink_if_underused();
+            report.memory_usage_bytes += cache
+                .iter()
+                .map(|(_, policy)| policy.estimated_size())
+                .sum::<usize>();
         }
+
+        report.expired_sources = self.expire_temporary_sources();
+
+        self.last_maintenance
+            .store(self.clock_millis(Instant::now()), Ordering::Relaxed);
+        self.perf_metrics
+            .record_memory_usage(report.memory_usage_bytes);
+        self.perf_metrics.record_maintenance_run();
+        report
+    }
+
+    /// Spawns a background task that calls [`run_maintenance`](Self::run_maintenance)
+    /// every `interval`.
+    ///
+    /// Must be called from within an Actix (Tokio) runtime, for example from
+    /// `main` under `#[actix_web::main]`. The task holds a clone of the
+    /// configuration and runs until the returned handle is aborted or the
+    /// runtime shuts down.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Time between maintenance passes; shorter than a
+    ///   millisecond, including zero, is raised to one millisecond
+    ///
+    /// # Returns
+    ///
+    /// `JoinHandle<()>` - Handle that can be used to abort the task
+    pub fn start_maintenance(&self, interval: Duration) -> JoinHandle<()> {
+        let interval = interval.max(MIN_BACKGROUND_INTERVAL);
+        self.last_maintenance
+            .store(self.clock_millis(Instant::now()), Ordering::Relaxed);
+        self.maintenance_interval
+            .store(interval.as_millis() as u64, Ordering::Relaxed);
+        static NEXT_TASK: AtomicU64 = AtomicU64::new(1);
+        let task = NEXT_TASK.fetch_add(1, Ordering::Relaxed);
+        self.maintenance_task.store(task, Ordering::Relaxed);
+        // dropped with the task when it is aborted, panics or its runtime
+        // shuts down, even before it first ran
+        let stopped = MaintenanceStopped {
+            task,
+            current: Arc::clone(&self.maintenance_task),
+        };
+        let config = self.clone();
+        actix_web::rt::spawn(async move {
+            let _stopped = stopped;
+            let mut ticker = actix_web::rt::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let report = config.run_maintenance();
+                csp_log!(Debug, "CSP cache maintenance: {report:?}");
+            }
+        })
     }
 
     pub fn rebuild_compiled_policy(&self) {
-        self.refresh_compiled_policy();
+        let _update = self.update_lock.lock();
+        let policy = CspPolicy::clone(&self.policy.load().policy);
+        self.publish(PolicyState::new(policy));
     }
 
     /// Adds default security directives if they are not already present.
@@ -601,7 +1770,8 @@ impl CspConfig {
     /// ```
     pub fn with_default_directives(self) -> Self {
         {
-            let mut policy = self.policy.write();
+            let _update = self.update_lock.lock();
+            let mut policy = CspPolicy::clone(&self.policy.load().policy);
             if policy.get_directive("default-src").is_none() {
                 use crate::core::directives::DefaultSrc;
                 use crate::core::source::Source;
@@ -615,18 +1785,18 @@ impl CspConfig {
                 let directive = ObjectSrc::new().add_source(Source::None).build();
                 policy.add_directive(directive);
             }
+            self.publish(PolicyState::new(policy));
         }
-        self.refresh_compiled_policy();
         self
     }
 
-    fn refresh_compiled_policy(&self) {
-        let compiled_policy = {
-            let policy = self.policy.read();
-            policy.compile().ok().map(Arc::new)
-        };
-
-        self.compiled_policy.store(compiled_policy);
+    fn publish(&self, state: PolicyState) {
+        self.stats
+            .record_policy_version(state.policy.structural_hash());
+        if let Some(header_log) = &self.header_log {
+            header_log.record(&state);
+        }
+        self.policy.store(Arc::new(state));
         self.policy_cache.write().clear();
     }
 }
@@ -659,8 +1829,38 @@ pub struct CspConfigBuilder {
     nonce_length: Option<usize>,
     /// Whether to generate unique nonces per request
     nonce_per_request: bool,
+    /// How long a generated nonce is reused
+    nonce_scope: NonceScope,
     /// Optional header name for nonce transmission
     nonce_request_header: Option<Cow<'static, str>>,
+    /// Whether HTML responses get nonce attributes injected
+    html_nonce_rewriting: bool,
+    /// Whether nonces are generated on first access
+    lazy_nonce: bool,
+    /// `Cache-Control` value for responses that carry a request nonce
+    nonce_cache_control: Option<HeaderValue>,
+    /// Named Reporting API endpoints
+    reporting_endpoints: Vec<(Cow<'static, str>, Cow<'static, str>)>,
+    /// Whether TLS requests get `upgrade-insecure-requests`
+    upgrade_insecure_on_tls: bool,
+    /// `Strict-Transport-Security` value for TLS requests
+    hsts: Option<HeaderValue>,
+    /// Whether forwarded protocol headers are trusted
+    trust_forwarded_proto: bool,
+    /// Proxies whose forwarded headers are honoured
+    trusted_proxies: Option<TrustedProxies>,
+    /// Whether `connect-src` gets WebSocket twins of HTTP(S) sources
+    websocket_mirroring: bool,
+    /// Whether `X-Frame-Options` follows `frame-ancestors`
+    frame_options_sync: bool,
+    /// What happens to responses whose policy header could not be generated
+    header_failure_mode: HeaderFailureMode,
+    /// HTML body of rejected responses
+    header_failure_page: Option<Bytes>,
+    /// Whether HTML responses are audited in the background
+    shadow_audit: bool,
+    /// Whether the emitted header is logged whenever it changes
+    header_logging: bool,
     /// Cache duration for policy caching
     cache_duration: Option<Duration>,
     /// Maximum number of cached policies
@@ -740,6 +1940,37 @@ impl CspConfigBuilder {
         self
     }
 
+    /// Sets how long a generated nonce is reused.
+    ///
+    /// The default, [`NonceScope::Request`], gives every response a fresh
+    /// nonce. `NonceScope::Session`, with the `session` feature, keeps one
+    /// nonce per `actix-session` session and [`NonceScope::Static`] one for the lifetime of the
+    /// configuration. Shared nonces are read when the request arrives, so
+    /// [`with_lazy_nonce`](Self::with_lazy_nonce) only affects requests that
+    /// end up needing a nonce of their own.
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - How long each nonce stays in use
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use actix_web_csp::{CspConfigBuilder, NonceScope};
+    ///
+    /// let config = CspConfigBuilder::new()
+    ///     .with_nonce_generator(16)
+    ///     .with_nonce_scope(NonceScope::Static)
+    ///     .build();
+    ///
+    /// assert_eq!(config.nonce_scope(), NonceScope::Static);
+    /// ```
+    #[inline]
+    pub fn with_nonce_scope(mut self, scope: NonceScope) -> Self {
+        self.nonce_scope = scope;
+        self
+    }
+
     /// Sets the header name for nonce transmission.
     ///
     /// # Arguments
@@ -751,6 +1982,309 @@ impl CspConfigBuilder {
         self
     }
 
+    /// Enables nonce injection into streamed HTML responses.
+    ///
+    /// When enabled and a nonce was issued for the request, the middleware scans
+    /// `text/html` bodies chunk by chunk and adds `nonce="..."` to every
+    /// `<script>` and `<style>` start tag that does not already carry one.
+    /// Compressed bodies (`Content-Encoding` other than `identity`) and
+    /// `206 Partial Content` responses are left untouched.
+    ///
+    /// This also covers HTML served from disk, e.g. an SPA `index.html` served
+    /// by `actix-files`. Because a rewritten body differs on every request,
+    /// `Content-Length`, `ETag`, `Last-Modified` and `Accept-Ranges` of the
+    /// original are dropped so no cache revalidates it into a stale nonce.
+    /// For the same reason, `If-None-Match`, `If-Modified-Since`, `If-Range` and
+    /// `Range` are removed from requests that accept `text/html`.
+    ///
+    /// # Security
+    ///
+    /// The rewriter cannot tell your markup from markup an attacker injected:
+    /// a `<script>` smuggled in through an HTML-injection bug gets the request
+    /// nonce too, which defeats the nonce for that page. Only enable this when
+    /// every HTML response is built from trusted templates or files that never
+    /// echo untrusted HTML. Otherwise leave it off and mark the trusted
+    /// responses with [`CspResponseExt::rewrite_html_nonces`], and have pages
+    /// that echo user input emit the nonce themselves through
+    /// [`CspResponseExt::csp_nonce_body`].
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to rewrite HTML bodies
+    ///
+    /// [`CspResponseExt::rewrite_html_nonces`]: crate::middleware::CspResponseExt::rewrite_html_nonces
+    /// [`CspResponseExt::csp_nonce_body`]: crate::middleware::CspResponseExt::csp_nonce_body
+    #[inline]
+    pub fn with_html_nonce_rewriting(mut self, enabled: bool) -> Self {
+        self.html_nonce_rewriting = enabled;
+        self
+    }
+
+    /// Defers nonce generation until a handler asks for the nonce.
+    ///
+    /// The middleware stores a [`CspNonce`] handle in the request extensions
+    /// instead of a [`RequestNonce`]. Responses whose handler never read the nonce
+    /// get the plain policy header, so JSON and other non-HTML endpoints skip
+    /// nonce generation and the per-request cache entirely. HTML responses still
+    /// get a nonce when [`with_html_nonce_rewriting`](Self::with_html_nonce_rewriting)
+    /// is enabled or the response is marked for rewriting.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to generate nonces lazily
+    ///
+    /// [`CspNonce`]: crate::security::CspNonce
+    /// [`RequestNonce`]: crate::security::RequestNonce
+    #[inline]
+    pub fn with_lazy_nonce(mut self, enabled: bool) -> Self {
+        self.lazy_nonce = enabled;
+        self
+    }
+
+    /// Marks responses that carry a request nonce as uncacheable by shared caches.
+    ///
+    /// A page cached by a CDN is served with the nonce of the request that
+    /// filled the cache, while each later response gets a fresh one in its policy.
+    /// When enabled, `Cache-Control: private, no-store` replaces any
+    /// handler-set value on every response whose policy includes a request nonce.
+    /// Responses without a nonce keep their caching headers.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to set `Cache-Control` on nonce-bearing responses
+    #[inline]
+    pub fn with_nonce_cache_control(mut self, enabled: bool) -> Self {
+        self.nonce_cache_control =
+            enabled.then(|| HeaderValue::from_static(DEFAULT_NONCE_CACHE_CONTROL));
+        self
+    }
+
+    /// Like [`with_nonce_cache_control`](Self::with_nonce_cache_control), with a
+    /// custom `Cache-Control` value such as `"private, no-cache"`.
+    ///
+    /// An invalid header value is logged as a warning and leaves the option
+    /// disabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The `Cache-Control` value for nonce-bearing responses
+    #[inline]
+    pub fn with_nonce_cache_control_value(mut self, value: &str) -> Self {
+        self.nonce_cache_control = HeaderValue::from_str(value)
+            .inspect_err(|_| {
+                csp_log!(Warn, "Ignoring invalid nonce Cache-Control value {value:?}");
+            })
+            .ok();
+        self
+    }
+
+    /// Declares a named Reporting API endpoint.
+    ///
+    /// Endpoints are sent in a `Reporting-Endpoints` header on every response
+    /// carrying the policy (and in [`CspConfig::early_hints_headers`]), so a
+    /// `report-to <name>` directive resolves to `url` in the browser.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Endpoint name referenced by `report-to`
+    /// * `url` - Absolute URL that receives the reports
+    #[inline]
+    pub fn with_reporting_endpoint(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        url: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.reporting_endpoints.push((name.into(), url.into()));
+        self
+    }
+
+    /// Adds `upgrade-insecure-requests` to the enforced policy of requests received
+    /// over TLS.
+    ///
+    /// Plain HTTP requests (typically local development) are left alone, so their
+    /// subresources are not rewritten to an `https:` URL nothing listens on. The
+    /// directive is not added to report-only policies, where browsers ignore it.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to upgrade insecure requests on TLS connections
+    #[inline]
+    pub fn with_upgrade_insecure_requests_on_tls(mut self, enabled: bool) -> Self {
+        self.upgrade_insecure_on_tls = enabled;
+        self
+    }
+
+    /// Sends `Strict-Transport-Security` on responses to requests received over TLS.
+    ///
+    /// A handler-set `Strict-Transport-Security` header is left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_age` - How long browsers should remember to use HTTPS only
+    /// * `include_subdomains` - Whether the policy also covers subdomains
+    #[inline]
+    pub fn with_hsts_on_tls(mut self, max_age: Duration, include_subdomains: bool) -> Self {
+        let value = if include_subdomains {
+            format!("max-age={}; includeSubDomains", max_age.as_secs())
+        } else {
+            format!("max-age={}", max_age.as_secs())
+        };
+        self.hsts = HeaderValue::from_str(&value).ok();
+        self
+    }
+
+    /// Trusts `Forwarded: proto=https` and `X-Forwarded-Proto: https` when deciding
+    /// whether a request was received over TLS.
+    ///
+    /// Only enable this behind a reverse proxy that overwrites these headers;
+    /// otherwise any client can claim to be on HTTPS. Prefer
+    /// [`with_trusted_proxies`](Self::with_trusted_proxies), which only believes
+    /// the headers when they come from a known proxy.
+    ///
+    /// # Arguments
+    ///
+    /// * `trusted` - Whether forwarded protocol headers are authoritative
+    #[inline]
+    pub fn with_trusted_forwarded_proto(mut self, trusted: bool) -> Self {
+        self.trust_forwarded_proto = trusted;
+        self
+    }
+
+    /// Honours `Forwarded` and `X-Forwarded-*` headers from the given proxies.
+    ///
+    /// When the connection's peer is a trusted proxy, the middleware resolves the
+    /// effective scheme, host and client address from those headers, uses the
+    /// scheme for TLS-only features such as
+    /// [`with_upgrade_insecure_requests_on_tls`](Self::with_upgrade_insecure_requests_on_tls),
+    /// matches `'self'` against the resolved origin in the verifier handlers get
+    /// from [`CspExtensions::policy_verifier`], and stores the result as a
+    /// [`ForwardedInfo`] request extension. Requests from any other peer are
+    /// judged by the connection alone.
+    ///
+    /// # Arguments
+    ///
+    /// * `proxies` - Networks of the reverse proxies in front of the application
+    ///
+    /// [`ForwardedInfo`]: crate::middleware::ForwardedInfo
+    /// [`CspExtensions::policy_verifier`]: crate::middleware::CspExtensions::policy_verifier
+    #[inline]
+    pub fn with_trusted_proxies(mut self, proxies: TrustedProxies) -> Self {
+        self.trusted_proxies = Some(proxies);
+        self
+    }
+
+    /// Mirrors HTTP(S) `connect-src` sources to their WebSocket equivalents.
+    ///
+    /// The initial policy and every later [`CspConfig::update_policy`] result are
+    /// passed through [`CspPolicy::mirror_websocket_sources`], so allowing
+    /// `https://api.example.com` also allows `wss://api.example.com`.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to mirror sources
+    #[inline]
+    pub fn with_websocket_mirroring(mut self, enabled: bool) -> Self {
+        self.websocket_mirroring = enabled;
+        self
+    }
+
+    /// Keeps `X-Frame-Options` consistent with the enforced `frame-ancestors`.
+    ///
+    /// `frame-ancestors 'none'` sends `DENY` and `frame-ancestors 'self'` sends
+    /// `SAMEORIGIN`. Any other allowlist removes `X-Frame-Options`, since its
+    /// `ALLOW-FROM` form is unsupported and the other values would block the
+    /// permitted embedders in browsers that only honour the legacy header.
+    /// Responses without `frame-ancestors` are left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to derive `X-Frame-Options`
+    #[inline]
+    pub fn with_frame_options_sync(mut self, enabled: bool) -> Self {
+        self.frame_options_sync = enabled;
+        self
+    }
+
+    /// Chooses what happens when the policy header for a response cannot be
+    /// generated, for example because a runtime nonce or a response override
+    /// produced an invalid value.
+    ///
+    /// [`HeaderFailureMode::Serve`], the default, sends the response without
+    /// the header. [`HeaderFailureMode::Fallback`] sends the minimal policy
+    /// `default-src 'self'` instead, counted in
+    /// [`CspStats::fallback_header_count`]; it is report-only when the main
+    /// policy is, and allows the request nonce for scripts and styles.
+    /// [`HeaderFailureMode::Reject`] fails closed: the
+    /// response is dropped and a `500 Internal Server Error` carrying
+    /// `Content-Security-Policy: default-src 'none'` is sent instead, with an
+    /// empty body unless [`with_header_failure_page`](Self::with_header_failure_page)
+    /// sets one. Either way the failure is logged and counted in
+    /// [`CspStats::header_generation_failure_count`]; rejections are also
+    /// counted in [`CspStats::rejected_response_count`].
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - How to handle a response without a policy header
+    #[inline]
+    pub fn with_header_failure_mode(mut self, mode: HeaderFailureMode) -> Self {
+        self.header_failure_mode = mode;
+        self
+    }
+
+    /// Sends `html` as the body of responses rejected under
+    /// [`HeaderFailureMode::Reject`].
+    ///
+    /// The page is served with `default-src 'none'`, so it cannot load scripts,
+    /// styles or images; keep it to plain markup.
+    ///
+    /// # Arguments
+    ///
+    /// * `html` - The error page markup
+    #[inline]
+    pub fn with_header_failure_page(mut self, html: impl Into<Bytes>) -> Self {
+        self.header_failure_page = Some(html.into());
+        self
+    }
+
+    /// Logs the header value sent to browsers each time it changes.
+    ///
+    /// One `Info` message is written when the configuration is built and again
+    /// whenever an update or a temporary source changes the compiled header,
+    /// never per request. It carries the policy version (its structural hash),
+    /// the header name, its size in bytes, the SHA-256 of the value and the
+    /// value itself, giving an audit trail of what browsers received across
+    /// deployments and runtime updates. Per-request nonces are not part of the
+    /// logged value.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to log header changes
+    #[inline]
+    pub fn with_header_logging(mut self, enabled: bool) -> Self {
+        self.header_logging = enabled;
+        self
+    }
+
+    /// Audits HTML responses against the policy they are sent with.
+    ///
+    /// Each uncompressed `text/html` response carrying a CSP header is copied as
+    /// it streams and checked by `security::page_audit` on a background thread.
+    /// Predicted violations are counted in [`CspStats::shadow_violation_count`]
+    /// and logged at `warn` level, before any browser reports them. Bodies over
+    /// 1 MiB are skipped, as are documents arriving while the worker is backed up.
+    ///
+    /// Requires the `page-audit` feature; without it this logs a warning and
+    /// does nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to audit responses
+    #[inline]
+    pub fn with_shadow_audit(mut self, enabled: bool) -> Self {
+        self.shadow_audit = enabled;
+        self
+    }
+
     /// Sets the cache duration for policy caching.
     ///
     /// Policies are cached to improve performance. This setting controls how long
@@ -802,14 +2336,35 @@ impl CspConfigBuilder {
     ///     .build();
     /// ```
     pub fn build(self) -> CspConfig {
-        let policy = self.policy.unwrap_or_default();
+        let mut policy = self.policy.unwrap_or_default();
+        if self.websocket_mirroring {
+            policy.mirror_websocket_sources();
+        }
         let mut config = CspConfig::new(policy);
+        config.websocket_mirroring = self.websocket_mirroring;
+        config.frame_options_sync = self.frame_options_sync;
+        config.header_failure_mode = self.header_failure_mode;
+        config.header_failure_page = self.header_failure_page;
+        if self.header_logging {
+            let header_log = Arc::new(HeaderLog::default());
+            header_log.record(&config.policy.load());
+            config.header_log = Some(header_log);
+        }
+        if self.shadow_audit {
+            config.shadow_auditor =
+                ShadowAuditor::spawn(config.stats.clone(), DEFAULT_SHADOW_AUDIT_MAX_BYTES);
+        }
 
         if let Some(generator) = self.nonce_generator {
             config.nonce_generator = Some(generator);
         } else if let Some(length) = self.nonce_length {
             config.nonce_generator = Some(Arc::new(NonceGenerator::with_capacity(32, length)));
         }
+        if let Some(generator) = &config.nonce_generator {
+            config
+                .perf_metrics
+                .attach_nonce_pool(generator.pool_metrics());
+        }
 
         if self.nonce_per_request {
             config
@@ -817,10 +2372,35 @@ impl CspConfigBuilder {
                 .store(true, std::sync::atomic::Ordering::Relaxed);
         }
 
+        if config.nonce_generator.is_none() && config.policy().contains_nonce_placeholder() {
+            csp_log!(
+                Warn,
+                "CSP policy has nonce placeholders but no nonce generator is configured; \
+                 they will be left out of the header"
+            );
+        }
+
+        config.nonce_scope = self.nonce_scope;
+        if self.nonce_scope == NonceScope::Static {
+            config.static_nonce = config.generate_nonce().map(Arc::from);
+        }
+
         if let Some(header) = self.nonce_request_header {
             config.nonce_request_header = Some(header);
         }
 
+        config.html_nonce_rewriting = self.html_nonce_rewriting;
+        config.lazy_nonce = self.lazy_nonce;
+        config.nonce_cache_control = self.nonce_cache_control;
+        config.reporting_endpoints = render_reporting_endpoints(&self.reporting_endpoints);
+        config.upgrade_insecure_on_tls = self.upgrade_insecure_on_tls;
+        config.hsts = self.hsts;
+        config.trust_forwarded_proto = self.trust_forwarded_proto;
+        config.trusted_proxies = self
+            .trusted_proxies
+            .filter(|proxies| !proxies.is_empty())
+            .map(Arc::new);
+
         if let Some(duration) = self.cache_duration {
             config.cache_duration.store(
                 duration.as_secs() as usize,
@@ -830,10 +2410,45 @@ impl CspConfigBuilder {
 
         if let Some(size) = self.cache_size {
             if let Some(non_zero) = NonZeroUsize::new(size) {
-                config.policy_cache = Arc::new(RwLock::new(LruCache::new(non_zero)));
+                config.policy_cache = Arc::new(RwLock::new(AdaptiveCache::with_metrics(
+                    non_zero,
+                    config.stats.policy_cache_metrics(),
+                )));
             }
         }
 
         config
     }
 }
+
+fn render_reporting_endpoints(
+    endpoints: &[(Cow<'static, str>, Cow<'static, str>)],
+) -> Option<HeaderValue> {
+    if endpoints.is_empty() {
+        return None;
+    }
+
+    let rendered = endpoints
+        .iter()
+        .filter(|(name, url)| {
+            let valid = !name.is_empty()
+                && name
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_'))
+                && !url.contains(['"', '\\', ',']);
+            if !valid {
+                csp_log!(
+                    Warn,
+                    "Ignoring invalid reporting endpoint '{name}' -> '{url}'"
+                );
+            }
+            valid
+        })
+        .map(|(name, url)| format!("{name}=\"{url}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    HeaderValue::from_str(&rendered)
+        .ok()
+        .filter(|value| !value.is_empty())
+}
@@ -1,6 +1,8 @@
 use crate::constants;
-use crate::core::source::Source;
+use crate::core::source::{Source, SourceKind};
 use crate::error::CspError;
+use crate::logging::csp_log;
+use crate::security::hash::HashAlgorithm;
 use crate::utils::BufferWriter;
 use bytes::BytesMut;
 use rustc_hash::FxHashSet;
@@ -9,6 +11,7 @@ use std::{
     borrow::Cow,
     fmt,
     hash::{Hash, Hasher},
+    ops::Range,
     str::FromStr,
 };
 
@@ -49,6 +52,13 @@ impl Directive {
         self
     }
 
+    /// Removes `source`, returning `true` if it was present.
+    pub fn remove_source(&mut self, source: &Source) -> bool {
+        let before = self.sources.len();
+        self.sources.retain(|s| s != source);
+        self.sources.len() != before
+    }
+
     pub fn add_sources<I>(&mut self, sources: I) -> &mut Self
     where
         I: IntoIterator<Item = Source>,
@@ -68,6 +78,15 @@ impl Directive {
         self
     }
 
+    /// Returns `true` if `name` is listed in [`KNOWN_DIRECTIVES`], ignoring case.
+    ///
+    /// [`KNOWN_DIRECTIVES`]: crate::constants::KNOWN_DIRECTIVES
+    pub fn is_known(name: &str) -> bool {
+        constants::KNOWN_DIRECTIVES
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(name))
+    }
+
     #[inline]
     pub fn name(&self) -> &str {
         &self.name
@@ -83,6 +102,60 @@ impl Directive {
         self.fallback_sources.as_deref()
     }
 
+    /// Iterates over the sources of the given kind, in order. Like
+    /// [`sources`](Self::sources), this leaves out fallback sources.
+    ///
+    /// ```rust
+    /// use actix_web_csp::core::{Directive, SourceKind};
+    /// use actix_web_csp::Source;
+    ///
+    /// let mut directive = Directive::new("script-src");
+    /// directive.add_sources([
+    ///     Source::Self_,
+    ///     Source::Host("cdn.example.com".into()),
+    ///     Source::Nonce("abc123".into()),
+    /// ]);
+    ///
+    /// assert_eq!(directive.sources_of_kind(SourceKind::Keyword).count(), 1);
+    /// assert_eq!(directive.hosts().collect::<Vec<_>>(), ["cdn.example.com"]);
+    /// assert_eq!(directive.nonces().collect::<Vec<_>>(), ["abc123"]);
+    /// ```
+    #[inline]
+    pub fn sources_of_kind(&self, kind: SourceKind) -> impl Iterator<Item = &Source> {
+        self.sources
+            .iter()
+            .filter(move |source| source.kind() == kind)
+    }
+
+    /// Iterates over the keyword sources, such as `'self'` and `'unsafe-inline'`.
+    #[inline]
+    pub fn keywords(&self) -> impl Iterator<Item = &Source> {
+        self.sources_of_kind(SourceKind::Keyword)
+    }
+
+    /// Iterates over the host sources, as written.
+    #[inline]
+    pub fn hosts(&self) -> impl Iterator<Item = &str> {
+        self.sources.iter().filter_map(Source::host)
+    }
+
+    /// Iterates over the nonce values, without the `'nonce-` prefix.
+    #[inline]
+    pub fn nonces(&self) -> impl Iterator<Item = &str> {
+        self.sources.iter().filter_map(Source::nonce)
+    }
+
+    /// Iterates over the hash values and their algorithms.
+    #[inline]
+    pub fn hashes(&self) -> impl Iterator<Item = (&str, HashAlgorithm)> {
+        self.sources.iter().filter_map(Source::hash_value)
+    }
+
+    /// Checks the directive for values browsers would reject.
+    ///
+    /// An unknown name is only logged as a warning, with the closest known name
+    /// when one is near: browsers ignore such directives, so a typo like
+    /// `script-scr` leaves the resource type unrestricted without any error.
     pub fn validate(&self) -> Result<(), CspError> {
         if self.name.is_empty() {
             return Err(CspError::ValidationError(
@@ -90,6 +163,17 @@ impl Directive {
             ));
         }
 
+        if !Self::is_known(&self.name) {
+            match closest_known_directive(&self.name) {
+                Some(known) => csp_log!(
+                    Warn,
+                    "Unknown CSP directive '{}', did you mean '{known}'?",
+                    self.name
+                ),
+                None => csp_log!(Warn, "Unknown CSP directive '{}'", self.name),
+            }
+        }
+
         if self.sources.len() > 1 && self.sources.iter().any(|s| s.is_none()) {
             return Err(CspError::ValidationError(format!(
                 "Directive '{}' contains 'none' with other sources",
@@ -127,6 +211,7 @@ impl Directive {
                         self.name
                     )));
                 }
+                Source::Host(host) => validate_host_source(&self.name, host)?,
                 _ => {}
             }
 
@@ -161,45 +246,92 @@ impl Directive {
         size
     }
 
+    /// Sorts sources and fallback sources into canonical order.
+    pub(crate) fn sort_sources(&mut self) {
+        self.sources.sort_by(Source::canonical_cmp);
+        if let Some(fallback) = &mut self.fallback_sources {
+            fallback.sort_by(Source::canonical_cmp);
+        }
+    }
+
     #[inline]
     pub fn contains_nonce(&self) -> bool {
         self.sources.iter().any(|s| s.contains_nonce())
     }
 
+    #[inline]
+    pub fn contains_nonce_placeholder(&self) -> bool {
+        self.sources
+            .iter()
+            .chain(self.fallback_sources.iter().flatten())
+            .any(Source::is_nonce_placeholder)
+    }
+
     #[inline]
     pub fn contains_hash(&self) -> bool {
         self.sources.iter().any(|s| s.contains_hash())
     }
 }
 
-#[cfg(feature = "extended-validation")]
-fn validate_source_semantics(directive_name: &str, source: &Source) -> Result<(), CspError> {
-    match source {
-        Source::Host(host) => {
-            if host.chars().any(char::is_whitespace) {
-                return Err(CspError::ValidationError(format!(
-                    "Directive '{directive_name}' contains host whitespace: {host}"
-                )));
-            }
-
-            if host.contains("://") {
-                return Err(CspError::ValidationError(format!(
-                    "Directive '{directive_name}' host should not include a scheme: {host}"
-                )));
-            }
+/// The known directive within two edits of `name`, if any.
+fn closest_known_directive(name: &str) -> Option<&'static str> {
+    let name = name.to_ascii_lowercase();
+    constants::KNOWN_DIRECTIVES
+        .iter()
+        .map(|known| (edit_distance(&name, known), *known))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known)| known)
+}
 
-            if host.starts_with('\'') || host.ends_with('\'') {
-                return Err(CspError::ValidationError(format!(
-                    "Directive '{directive_name}' host should use typed Source keywords instead of quoted values: {host}"
-                )));
-            }
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.as_bytes();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
 
-            if host.contains(';') || host.contains(',') {
-                return Err(CspError::ValidationError(format!(
-                    "Directive '{directive_name}' host contains an invalid separator: {host}"
-                )));
-            }
+    for (i, a_byte) in a.bytes().enumerate() {
+        current[0] = i + 1;
+        for (j, b_byte) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_byte != *b_byte);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
         }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+fn validate_host_source(directive_name: &str, host: &str) -> Result<(), CspError> {
+    if host.chars().any(char::is_whitespace) {
+        return Err(CspError::ValidationError(format!(
+            "Directive '{directive_name}' contains host whitespace: {host}"
+        )));
+    }
+
+    if host.starts_with('\'') || host.ends_with('\'') {
+        return Err(CspError::ValidationError(format!(
+            "Directive '{directive_name}' host should use typed Source keywords instead of quoted values: {host}"
+        )));
+    }
+
+    if host.contains(';') || host.contains(',') {
+        return Err(CspError::ValidationError(format!(
+            "Directive '{directive_name}' host contains an invalid separator: {host}"
+        )));
+    }
+
+    if host.parse::<crate::core::source::HostSource>().is_err() {
+        return Err(CspError::ValidationError(format!(
+            "Directive '{directive_name}' contains an invalid host source: {host}"
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "extended-validation")]
+fn validate_source_semantics(directive_name: &str, source: &Source) -> Result<(), CspError> {
+    match source {
         Source::Scheme(scheme) => {
             let mut chars = scheme.chars();
             let starts_correctly = chars
@@ -215,25 +347,23 @@ fn validate_source_semantics(directive_name: &str, source: &Source) -> Result<()
                 )));
             }
         }
-        Source::Nonce(nonce) => {
+        Source::Nonce(nonce)
             if nonce.chars().any(char::is_whitespace)
                 || nonce.contains('\'')
-                || !is_base64ish(nonce)
-            {
-                return Err(CspError::ValidationError(format!(
-                    "Directive '{directive_name}' contains an invalid nonce value"
-                )));
-            }
+                || !is_base64ish(nonce) =>
+        {
+            return Err(CspError::ValidationError(format!(
+                "Directive '{directive_name}' contains an invalid nonce value"
+            )));
         }
-        Source::Hash { value, .. } => {
+        Source::Hash { value, .. }
             if value.chars().any(char::is_whitespace)
                 || value.contains('\'')
-                || !is_base64ish(value)
-            {
-                return Err(CspError::ValidationError(format!(
-                    "Directive '{directive_name}' contains an invalid hash value"
-                )));
-            }
+                || !is_base64ish(value) =>
+        {
+            return Err(CspError::ValidationError(format!(
+                "Directive '{directive_name}' contains an invalid hash value"
+            )));
         }
         _ => {}
     }
@@ -282,31 +412,86 @@ impl BufferWriter for Directive {
     fn write_to_buffer(&self, buffer: &mut BytesMut) {
         buffer.extend_from_slice(self.name.as_bytes());
 
-        if !self.sources.is_empty() {
+        for source in self
+            .sources
+            .iter()
+            .chain(self.fallback_sources.iter().flatten())
+            .filter(|source| !source.is_nonce_placeholder())
+        {
+            buffer.extend_from_slice(b" ");
+            source.write_to_buffer(buffer);
+        }
+    }
+}
+
+impl Directive {
+    /// Writes the directive leaving room for a runtime nonce and returns the gap range.
+    ///
+    /// The gap sits where [`Directive::add_source`] would place a nonce: after the
+    /// regular sources and before fallback sources. A lone `'none'` is still written,
+    /// so the static value keeps its meaning, and the range covers it so the nonce
+    /// replaces it when spliced in.
+    pub(crate) fn write_with_nonce_gap(&self, buffer: &mut BytesMut) -> Range<usize> {
+        buffer.extend_from_slice(self.name.as_bytes());
+
+        let start = buffer.len();
+        let replaces_none = self.sources.first().is_some_and(Source::is_none);
+        for source in &self.sources {
+            buffer.extend_from_slice(b" ");
+            source.write_to_buffer(buffer);
+        }
+
+        let gap = if replaces_none {
+            start..buffer.len()
+        } else {
+            buffer.len()..buffer.len()
+        };
+
+        for source in self.fallback_sources.iter().flatten() {
             buffer.extend_from_slice(b" ");
+            source.write_to_buffer(buffer);
+        }
+
+        gap
+    }
 
-            if self.sources.len() == 1 {
-                self.sources[0].write_to_buffer(buffer);
+    /// Writes the directive with a runtime nonce gap wherever a
+    /// [`Source::NoncePlaceholder`] sits, pushing the gap ranges onto `gaps`.
+    pub(crate) fn write_with_placeholder_gaps(
+        &self,
+        buffer: &mut BytesMut,
+        gaps: &mut SmallVec<[Range<usize>; 4]>,
+    ) {
+        buffer.extend_from_slice(self.name.as_bytes());
+
+        for source in self
+            .sources
+            .iter()
+            .chain(self.fallback_sources.iter().flatten())
+        {
+            if source.is_nonce_placeholder() {
+                gaps.push(buffer.len()..buffer.len());
             } else {
-                let mut first = true;
-                for source in &self.sources {
-                    if !first {
-                        buffer.extend_from_slice(b" ");
-                    }
-                    source.write_to_buffer(buffer);
-                    first = false;
-                }
+                buffer.extend_from_slice(b" ");
+                source.write_to_buffer(buffer);
             }
         }
+    }
 
-        if let Some(fallback) = &self.fallback_sources {
-            if !fallback.is_empty() {
-                for source in fallback {
-                    buffer.extend_from_slice(b" ");
-                    source.write_to_buffer(buffer);
-                }
-            }
+    /// Replaces every [`Source::NoncePlaceholder`] with `nonce`, returning
+    /// `true` if there was one.
+    pub(crate) fn replace_nonce_placeholder(&mut self, nonce: &str) -> bool {
+        let mut replaced = false;
+        for source in self
+            .sources
+            .iter_mut()
+            .chain(self.fallback_sources.iter_mut().flatten())
+            .filter(|source| source.is_nonce_placeholder())
+        {
+            *source = Source::Nonce(Cow::Owned(nonce.to_owned()));
+            replaced = true;
         }
+        replaced
     }
 }
 
@@ -334,7 +519,16 @@ impl FromStr for Directive {
             CspError::InvalidDirectiveName("Directive name cannot be empty".to_string())
         })?;
 
-        let mut directive = Directive::new(name.to_owned());
+        if !name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-')
+        {
+            return Err(CspError::InvalidDirectiveName(format!(
+                "Invalid directive name: {name}"
+            )));
+        }
+
+        let mut directive = Directive::new(name.to_ascii_lowercase());
         for source in parts {
             directive.add_source(Source::from_str(source)?);
         }
@@ -524,3 +718,67 @@ impl Sandbox {
         directive
     }
 }
+
+/// Builds `require-sri-for`, which demands an `integrity` attribute on external
+/// scripts and/or stylesheets.
+///
+/// The directive never shipped unflagged in a major browser and is ignored there;
+/// it is still honoured by some in-house tooling and by `security::page_audit`
+/// (`page-audit` feature).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RequireSriFor {
+    script: bool,
+    style: bool,
+}
+
+impl RequireSriFor {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn script(mut self) -> Self {
+        self.script = true;
+        self
+    }
+
+    #[inline]
+    pub fn style(mut self) -> Self {
+        self.style = true;
+        self
+    }
+
+    /// Reads the tokens of an existing `require-sri-for` directive.
+    pub fn from_directive(directive: &Directive) -> Self {
+        directive
+            .sources()
+            .iter()
+            .fold(Self::default(), |sri, source| match source {
+                Source::Host(token) if token.eq_ignore_ascii_case("script") => sri.script(),
+                Source::Host(token) if token.eq_ignore_ascii_case("style") => sri.style(),
+                _ => sri,
+            })
+    }
+
+    #[inline]
+    pub fn requires_script(&self) -> bool {
+        self.script
+    }
+
+    #[inline]
+    pub fn requires_style(&self) -> bool {
+        self.style
+    }
+
+    pub fn build(self) -> Directive {
+        let mut directive = Directive::new(constants::REQUIRE_SRI_FOR);
+        if self.script {
+            directive.add_source(Source::Host(Cow::Borrowed("script")));
+        }
+        if self.style {
+            directive.add_source(Source::Host(Cow::Borrowed("style")));
+        }
+        directive
+    }
+}
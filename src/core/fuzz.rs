@@ -0,0 +1,160 @@
+//! [`Arbitrary`] implementations for fuzz targets.
+//!
+//! Inputs are shaped like real policies: directive names come from
+//! [`KNOWN_DIRECTIVES`] (reporting is set on the policy instead), hosts are
+//! built from hostname characters and hashes have the digest length of their
+//! algorithm, so most inputs reach the serializer and parser instead of failing
+//! validation. Pair them with
+//! [`CspPolicy::roundtrip_check`]:
+//!
+//! ```rust
+//! use actix_web_csp::CspPolicy;
+//! use arbitrary::{Arbitrary, Unstructured};
+//!
+//! fn fuzz_target(data: &[u8]) {
+//!     let Ok(policy) = CspPolicy::arbitrary(&mut Unstructured::new(data)) else {
+//!         return;
+//!     };
+//!     if policy.validate().is_ok() && policy.compile().is_ok() {
+//!         policy.roundtrip_check().unwrap();
+//!     }
+//! }
+//!
+//! fuzz_target(b"\x03\x10policy bytes from the fuzzer\x7f\x00\x42");
+//! ```
+//!
+//! [`KNOWN_DIRECTIVES`]: crate::constants::KNOWN_DIRECTIVES
+
+use crate::constants::{KNOWN_DIRECTIVES, REPORT_TO, REPORT_URI};
+use crate::core::directives::Directive;
+use crate::core::policy::CspPolicy;
+use crate::core::source::Source;
+use crate::security::hash::HashAlgorithm;
+use arbitrary::{Arbitrary, Result, Unstructured};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+const HOST_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789-";
+const NONCE_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/_-";
+const SCHEMES: &[&str] = &[
+    "https",
+    "http",
+    "data",
+    "blob",
+    "wss",
+    "ws",
+    "mediastream",
+    "filesystem",
+];
+const REPORT_URIS: &[&str] = &["/csp-report", "https://reports.example.com/csp", "/r?x=1"];
+const REPORT_GROUPS: &[&str] = &["csp-endpoint", "default", "reports"];
+
+const MAX_SOURCES: usize = 6;
+const MAX_DIRECTIVES: usize = 8;
+
+/// A string of 1 to `max_len` characters drawn from `chars`.
+fn string_from(u: &mut Unstructured<'_>, chars: &[u8], max_len: usize) -> Result<String> {
+    let len = u.int_in_range(1..=max_len)?;
+    (0..len)
+        .map(|_| u.choose(chars).map(|&byte| byte as char))
+        .collect()
+}
+
+fn host(u: &mut Unstructured<'_>) -> Result<String> {
+    let mut host = String::new();
+    if u.ratio(1, 4)? {
+        host.push_str("*.");
+    }
+    let labels = u.int_in_range(1..=3)?;
+    for index in 0..labels {
+        if index > 0 {
+            host.push('.');
+        }
+        host.push_str(&string_from(u, HOST_CHARS, 12)?);
+    }
+    if u.ratio(1, 5)? {
+        host = format!("{}://{host}", u.choose(&["https", "http", "wss"])?);
+    }
+    if u.ratio(1, 5)? {
+        host.push_str(&format!(":{}", u.int_in_range(1..=65535u32)?));
+    }
+    if u.ratio(1, 5)? {
+        host.push('/');
+        host.push_str(&string_from(u, HOST_CHARS, 8)?);
+    }
+    Ok(host)
+}
+
+impl<'a> Arbitrary<'a> for HashAlgorithm {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(*u.choose(&[
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Sha384,
+            HashAlgorithm::Sha512,
+        ])?)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Source {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=11u8)? {
+            0 => Source::None,
+            1 => Source::Self_,
+            2 => Source::UnsafeInline,
+            3 => Source::UnsafeEval,
+            4 => Source::StrictDynamic,
+            5 => Source::ReportSample,
+            6 => Source::WasmUnsafeEval,
+            7 => Source::UnsafeHashes,
+            8 => Source::Scheme((*u.choose(SCHEMES)?).into()),
+            9 => Source::Nonce(string_from(u, NONCE_CHARS, 32)?.into()),
+            10 => {
+                let algorithm = HashAlgorithm::arbitrary(u)?;
+                let digest_len = match algorithm {
+                    HashAlgorithm::Sha256 => 32,
+                    HashAlgorithm::Sha384 => 48,
+                    HashAlgorithm::Sha512 => 64,
+                };
+                Source::Hash {
+                    algorithm,
+                    value: BASE64.encode(u.bytes(digest_len)?).into(),
+                }
+            }
+            _ => Source::Host(host(u)?.into()),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Directive {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        // Reporting is set on the policy, not through source lists
+        let names = KNOWN_DIRECTIVES
+            .iter()
+            .copied()
+            .filter(|name| !matches!(*name, REPORT_URI | REPORT_TO))
+            .collect::<Vec<_>>();
+        let mut directive = Directive::new(*u.choose(&names)?);
+        let sources = u.int_in_range(0..=MAX_SOURCES)?;
+        for _ in 0..sources {
+            directive.add_source(Source::arbitrary(u)?);
+        }
+        Ok(directive)
+    }
+}
+
+impl<'a> Arbitrary<'a> for CspPolicy {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut policy = CspPolicy::new();
+        let directives = u.int_in_range(0..=MAX_DIRECTIVES)?;
+        for _ in 0..directives {
+            policy.add_directive(Directive::arbitrary(u)?);
+        }
+        if u.ratio(1, 3)? {
+            policy.set_report_uri(*u.choose(REPORT_URIS)?);
+        }
+        if u.ratio(1, 3)? {
+            policy.set_report_to(*u.choose(REPORT_GROUPS)?);
+        }
+        policy.set_report_only(bool::arbitrary(u)?);
+        Ok(policy)
+    }
+}
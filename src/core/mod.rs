@@ -1,11 +1,24 @@
+pub mod allowlist;
 pub mod config;
 pub mod directives;
+#[cfg(feature = "arbitrary")]
+pub mod fuzz;
 pub mod interop;
 pub mod policy;
+pub mod routes;
 pub mod source;
+pub mod summary;
 
-pub use config::{CspConfig, CspConfigBuilder};
+pub use allowlist::{
+    AllowlistEntry, AllowlistStore, DynamicAllowlist, JsonFileAllowlistStore, MemoryAllowlistStore,
+};
+pub use config::{
+    CspConfig, CspConfigBuilder, CspConfigSnapshot, CspHealth, HeaderFailureMode,
+    MaintenanceReport, PolicyCacheKey, PolicyEvent, PolicyHistoryEntry, TemporarySourceSnapshot,
+};
 pub use directives::*;
 pub use interop::{DirectiveDocument, PolicyDocument};
-pub use policy::{CompiledCspPolicy, CspPolicy, CspPolicyBuilder};
-pub use source::Source;
+pub use policy::{parse_header_value, CompiledCspPolicy, CspPolicy, CspPolicyBuilder};
+pub use routes::PolicyRoutes;
+pub use source::{HostSource, PortPart, SchemePart, Source, SourceKind};
+pub use summary::PolicySummary;
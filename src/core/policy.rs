@@ -1,17 +1,28 @@
 use crate::constants::{
-    DEFAULT_BUFFER_CAPACITY, DEFAULT_CACHE_DURATION_SECS, HEADER_CSP, HEADER_CSP_REPORT_ONLY,
-    REPORT_TO, REPORT_URI, SCRIPT_SRC, SCRIPT_SRC_ELEM, SEMICOLON_SPACE, STYLE_SRC, STYLE_SRC_ELEM,
+    BASE_URI, BLOCK_ALL_MIXED_CONTENT, CHILD_SRC, CONNECT_SRC, DEFAULT_BUFFER_CAPACITY,
+    DEFAULT_CACHE_DURATION_SECS, DEFAULT_SRC, FENCED_FRAME_SRC, FONT_SRC, FORM_ACTION,
+    FRAME_ANCESTORS, FRAME_SRC, HEADER_CSP, HEADER_CSP_REPORT_ONLY, IMG_SRC, MANIFEST_SRC,
+    MEDIA_SRC, NONCE_PREFIX, OBJECT_SRC, PREFETCH_SRC, REPORT_TO, REPORT_URI, REQUIRE_SRI_FOR,
+    REQUIRE_TRUSTED_TYPES_FOR, SANDBOX, SCRIPT_SRC, SCRIPT_SRC_ATTR, SCRIPT_SRC_ELEM,
+    SEMICOLON_SPACE, STYLE_SRC, STYLE_SRC_ATTR, STYLE_SRC_ELEM, SUFFIX_QUOTE, TRUSTED_TYPES,
+    UPGRADE_INSECURE_REQUESTS, WORKER_SRC,
 };
-use crate::core::directives::{Directive, DirectiveSpec, Sandbox};
+use crate::core::directives::{Directive, DirectiveSpec, RequireSriFor, Sandbox};
 use crate::core::interop::PolicyDocument;
 use crate::core::source::Source;
+use crate::core::summary::PolicySummary;
 use crate::error::CspError;
+use crate::integrations::Service;
+use crate::logging::csp_log;
+use crate::security::hash::{HashAlgorithm, HashGenerator};
 use crate::utils::{BufferWriter, BytesCache, CachedValue};
 use actix_web::http::header::{HeaderName, HeaderValue};
 use bytes::BytesMut;
 use indexmap::IndexMap;
 use rustc_hash::FxHasher;
+use smallvec::SmallVec;
 use std::num::NonZeroU64;
+use std::ops::Range;
 use std::{
     borrow::Cow,
     fmt,
@@ -35,12 +46,17 @@ pub struct CspPolicy {
     policy_hash: Option<NonZeroU64>,
 }
 
+const NONCE_DIRECTIVES: [&str; 4] = [SCRIPT_SRC, STYLE_SRC, SCRIPT_SRC_ELEM, STYLE_SRC_ELEM];
+
 #[derive(Debug, Clone)]
 pub struct CompiledCspPolicy {
     header_name: HeaderName,
     header_value: HeaderValue,
     policy_hash: NonZeroU64,
     report_only: bool,
+    /// Byte ranges in `header_value` replaced by a runtime nonce source; empty
+    /// ranges are plain insertions, non-empty ones cover a lone `'none'`.
+    nonce_gaps: SmallVec<[Range<usize>; 4]>,
 }
 
 impl CompiledCspPolicy {
@@ -63,6 +79,62 @@ impl CompiledCspPolicy {
     pub fn is_report_only(&self) -> bool {
         self.report_only
     }
+
+    /// Renders the header value with `nonce` added to the nonce-aware directives.
+    ///
+    /// The static part of the policy was serialized once at compile time; only the
+    /// nonce sources are spliced in here, producing the same value as
+    /// [`CspPolicy::compile_with_runtime_nonce`] without re-serializing every directive.
+    pub fn header_value_with_nonce(&self, nonce: &str) -> Result<HeaderValue, CspError> {
+        if self.nonce_gaps.is_empty() {
+            return Ok(self.header_value.clone());
+        }
+
+        let static_part = self.header_value.as_bytes();
+        let nonce_len = 1 + NONCE_PREFIX.len() + nonce.len() + SUFFIX_QUOTE.len();
+        let mut buffer =
+            BytesMut::with_capacity(static_part.len() + nonce_len * self.nonce_gaps.len());
+
+        let mut start = 0;
+        for gap in &self.nonce_gaps {
+            buffer.extend_from_slice(&static_part[start..gap.start]);
+            buffer.extend_from_slice(b" ");
+            buffer.extend_from_slice(NONCE_PREFIX.as_bytes());
+            buffer.extend_from_slice(nonce.as_bytes());
+            buffer.extend_from_slice(SUFFIX_QUOTE.as_bytes());
+            start = gap.end;
+        }
+        buffer.extend_from_slice(&static_part[start..]);
+
+        HeaderValue::from_maybe_shared(buffer.freeze()).map_err(|_| {
+            CspError::InvalidDirectiveValue("Failed to create header value".to_string())
+        })
+    }
+}
+
+/// Policy selected for a virtual host or path prefix together with its
+/// precompiled header.
+#[derive(Debug)]
+pub(crate) struct SelectedPolicy {
+    policy: CspPolicy,
+    compiled: Option<CompiledCspPolicy>,
+}
+
+impl SelectedPolicy {
+    pub(crate) fn new(policy: CspPolicy) -> Self {
+        let compiled = policy.compile().ok();
+        Self { policy, compiled }
+    }
+
+    #[inline]
+    pub(crate) fn policy(&self) -> &CspPolicy {
+        &self.policy
+    }
+
+    #[inline]
+    pub(crate) fn compiled(&self) -> Option<&CompiledCspPolicy> {
+        self.compiled.as_ref()
+    }
 }
 
 impl CspPolicy {
@@ -86,6 +158,94 @@ impl CspPolicy {
         self
     }
 
+    /// Removes the named directive, returning it if it was present.
+    pub fn remove_directive(&mut self, name: &str) -> Option<Directive> {
+        let directive = self.directives.shift_remove(name)?;
+        self.estimated_size = self
+            .estimated_size
+            .saturating_sub(directive.estimated_size());
+        self.cached_header_value = None;
+        self.policy_hash = None;
+        Some(directive)
+    }
+
+    /// Adds the sources a third-party service needs; see [`Service`].
+    #[inline]
+    pub fn apply_service(&mut self, service: Service) -> &mut Self {
+        service.apply(self);
+        self
+    }
+
+    /// Adds `source` to the named directive, so the policy only ever allows more.
+    ///
+    /// A missing directive is created with the sources of the directive it falls
+    /// back to, usually `default-src`, before `source` is added; otherwise adding
+    /// `img-src cdn.example.com` under `default-src 'self'` would block
+    /// same-origin images.
+    ///
+    /// ```rust
+    /// use actix_web_csp::{CspPolicyBuilder, Source};
+    ///
+    /// let mut policy = CspPolicyBuilder::new()
+    ///     .default_src([Source::Self_])
+    ///     .build_unchecked();
+    /// policy.append_source("img-src", Source::Host("cdn.example.com".into()));
+    ///
+    /// assert_eq!(
+    ///     policy.header_value().unwrap().to_str().unwrap(),
+    ///     "default-src 'self'; img-src 'self' cdn.example.com"
+    /// );
+    /// ```
+    pub fn append_source(
+        &mut self,
+        directive_name: impl Into<Cow<'static, str>>,
+        source: Source,
+    ) -> &mut Self {
+        let directive_name = directive_name.into();
+        let mut directive = match self.directives.get(directive_name.as_ref()) {
+            Some(directive) => directive.clone(),
+            None => {
+                let mut directive = Directive::new(directive_name.clone());
+                if let Some(inherited) = self.inherited_sources(&directive_name) {
+                    directive.add_sources(inherited.iter().cloned());
+                }
+                directive
+            }
+        };
+        directive.add_source(source);
+        self.add_directive(directive)
+    }
+
+    /// The sources `directive_name` gets from the directive it falls back to
+    /// while it is missing, `None` when nothing restricts it.
+    pub(crate) fn inherited_sources(&self, directive_name: &str) -> Option<&[Source]> {
+        fallback_directives(directive_name)
+            .iter()
+            .find_map(|name| self.directives.get(*name))
+            .map(Directive::sources)
+    }
+
+    /// Rewrites the policy into canonical form.
+    ///
+    /// Directives follow the order of the CSP specification with unknown directives
+    /// last in alphabetical order; sources are ordered keywords first, then nonces,
+    /// hashes, schemes and hosts, each group sorted. Two policies with the same
+    /// directives and sources produce identical header values and hashes after
+    /// canonicalization, whatever order they were assembled in.
+    pub fn canonicalize(&mut self) -> &mut Self {
+        for directive in self.directives.values_mut() {
+            directive.sort_sources();
+        }
+        self.directives.sort_by(|a, _, b, _| {
+            directive_rank(a)
+                .cmp(&directive_rank(b))
+                .then_with(|| a.cmp(b))
+        });
+        self.cached_header_value = None;
+        self.policy_hash = None;
+        self
+    }
+
     #[inline]
     pub fn set_report_only(&mut self, report_only: bool) -> &mut Self {
         self.report_only = report_only;
@@ -94,6 +254,7 @@ impl CspPolicy {
         self
     }
 
+    /// Sets `report-uri`; list several URIs separated by spaces.
     pub fn set_report_uri(&mut self, uri: impl Into<Cow<'static, str>>) -> &mut Self {
         let uri = uri.into();
         let old_size = self
@@ -135,10 +296,19 @@ impl CspPolicy {
         self.header_value_with_cache_duration(Duration::from_secs(DEFAULT_CACHE_DURATION_SECS))
     }
 
+    /// Serializes the policy, reusing the cached value while it is fresh.
+    ///
+    /// Policies containing a nonce are always serialized anew and never cached, so
+    /// a nonce can not outlive the request it was issued for.
     pub fn header_value_with_cache_duration(
         &mut self,
         ttl: Duration,
     ) -> Result<HeaderValue, CspError> {
+        if self.contains_nonce() {
+            self.cached_header_value = None;
+            return self.generate_header_value();
+        }
+
         if let Some(cached) = &self.cached_header_value {
             if cached.is_valid() {
                 return Ok(cached.value().clone());
@@ -150,10 +320,97 @@ impl CspPolicy {
         Ok(value)
     }
 
+    /// Returns `true` while [`header_value`](Self::header_value) would reuse a
+    /// cached value instead of serializing the policy again.
+    #[inline]
+    pub fn has_cached_header_value(&self) -> bool {
+        self.cached_header_value
+            .as_ref()
+            .is_some_and(CachedValue::is_valid)
+    }
+
+    #[inline]
+    pub(crate) fn estimated_size(&self) -> usize {
+        self.estimated_size
+    }
+
+    #[inline]
+    pub(crate) fn has_expired_header_value(&self) -> bool {
+        self.cached_header_value
+            .as_ref()
+            .is_some_and(|cached| !cached.is_valid())
+    }
+
     fn generate_header_value(&self) -> Result<HeaderValue, CspError> {
+        self.serialize(None)
+    }
+
+    /// Appends the header value to `buffer` without allocating beyond the buffer's
+    /// own growth, for callers assembling headers or `Link`/Early Hints frames
+    /// themselves. Pair it with [`header_name`](Self::header_name).
+    ///
+    /// Unlike [`header_value`](Self::header_value) this takes `&self` and neither
+    /// reads nor fills the cached value. On error `buffer` is left as it was.
+    ///
+    /// ```rust
+    /// use actix_web_csp::{CspPolicyBuilder, Source};
+    /// use bytes::BytesMut;
+    ///
+    /// let policy = CspPolicyBuilder::new()
+    ///     .default_src([Source::Self_])
+    ///     .img_src([Source::Self_, Source::Scheme("data".into())])
+    ///     .build()?;
+    ///
+    /// let mut buffer = BytesMut::from(&b"content-security-policy: "[..]);
+    /// policy.write_header(&mut buffer)?;
+    /// assert_eq!(
+    ///     &buffer[..],
+    ///     b"content-security-policy: default-src 'self'; img-src 'self' data:"
+    /// );
+    /// # Ok::<(), actix_web_csp::CspError>(())
+    /// ```
+    pub fn write_header(&self, buffer: &mut BytesMut) -> Result<(), CspError> {
+        let start = buffer.len();
+        self.write_into(buffer, None);
+        // The same bytes `HeaderValue` accepts: visible ASCII, spaces, tabs and obs-text
+        let valid = buffer[start..]
+            .iter()
+            .all(|&byte| (byte >= 0x20 && byte != 0x7f) || byte == b'\t');
+        if !valid {
+            buffer.truncate(start);
+            return Err(CspError::InvalidDirectiveValue(
+                "Failed to create header value".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Serializes the policy, optionally recording where a runtime nonce is spliced in.
+    fn serialize(
+        &self,
+        nonce_gaps: Option<&mut SmallVec<[Range<usize>; 4]>>,
+    ) -> Result<HeaderValue, CspError> {
         let capacity = self.estimated_size.max(DEFAULT_BUFFER_CAPACITY);
         let mut buffer = BYTES_CACHE.with(|cache| cache.borrow_mut().get(capacity));
 
+        self.write_into(&mut buffer, nonce_gaps);
+
+        // Copy out so the buffer, with the capacity it grew to, goes back to the pool
+        let result = HeaderValue::from_bytes(&buffer).map_err(|_| {
+            CspError::InvalidDirectiveValue("Failed to create header value".to_string())
+        });
+
+        BYTES_CACHE.with(|cache| cache.borrow_mut().recycle(buffer));
+
+        result
+    }
+
+    /// Appends the serialized policy to `buffer`; nonce gaps are ranges in `buffer`.
+    fn write_into(
+        &self,
+        buffer: &mut BytesMut,
+        mut nonce_gaps: Option<&mut SmallVec<[Range<usize>; 4]>>,
+    ) {
         let directives_count = self.directives.len();
         let has_report_uri = self.report_uri.is_some();
         let has_report_to = self.report_to.is_some();
@@ -166,12 +423,19 @@ impl CspPolicy {
 
         buffer.reserve(self.estimated_size + (total_semicolons * 2));
 
+        let placeholders = nonce_gaps.is_some() && self.contains_nonce_placeholder();
         let mut first = true;
-        for directive in self.directives.values() {
+        for (name, directive) in &self.directives {
             if !first {
                 buffer.extend_from_slice(SEMICOLON_SPACE);
             }
-            directive.write_to_buffer(&mut buffer);
+            match nonce_gaps.as_deref_mut() {
+                Some(gaps) if placeholders => directive.write_with_placeholder_gaps(buffer, gaps),
+                Some(gaps) if NONCE_DIRECTIVES.contains(&name.as_ref()) => {
+                    gaps.push(directive.write_with_nonce_gap(buffer));
+                }
+                _ => directive.write_to_buffer(buffer),
+            }
             first = false;
         }
 
@@ -193,29 +457,113 @@ impl CspPolicy {
             buffer.extend_from_slice(b" ");
             buffer.extend_from_slice(endpoint.as_bytes());
         }
-
-        let bytes = buffer.freeze();
-        let result = HeaderValue::from_maybe_shared(bytes).map_err(|_| {
-            CspError::InvalidDirectiveValue("Failed to create header value".to_string())
-        });
-
-        BYTES_CACHE.with(|cache| {
-            let new_buffer = BytesMut::with_capacity(capacity);
-            cache.borrow_mut().recycle(new_buffer);
-        });
-
-        result
     }
 
     pub fn compile(&self) -> Result<CompiledCspPolicy, CspError> {
+        let mut nonce_gaps = SmallVec::new();
+        let header_value = self.serialize(Some(&mut nonce_gaps))?;
+
         Ok(CompiledCspPolicy {
             header_name: self.header_name(),
-            header_value: self.generate_header_value()?,
+            header_value,
             policy_hash: self.calculate_hash(),
             report_only: self.report_only,
+            nonce_gaps,
         })
     }
 
+    /// Counts the policy's directives and sources and notes its header size,
+    /// reporting setup and `'unsafe-*'` keywords; see [`PolicySummary`].
+    #[inline]
+    pub fn summary(&self) -> PolicySummary {
+        PolicySummary::from(self)
+    }
+
+    /// Checks that the serialized policy parses back into the same policy.
+    ///
+    /// For any policy that passes [`validate`](Self::validate) and
+    /// [`compile`](Self::compile), [`parse_header_value`] applied to the serialized
+    /// header value yields the same directives in the same order (names compared
+    /// without regard to case), each with the same sources, and the same
+    /// `report-uri` and `report-to`. Fallback sources count as trailing sources,
+    /// since the header does not tell them apart, and report-only mode travels in
+    /// the header name rather than the value.
+    ///
+    /// Errors from `validate` and `compile` are returned unchanged and mean the
+    /// policy is outside the guarantee; a [`CspError::SerializationError`] means
+    /// it was broken. With the `arbitrary` feature, fuzz targets can generate
+    /// policies to check; see `core::fuzz`.
+    ///
+    /// ```rust
+    /// use actix_web_csp::{CspPolicyBuilder, Source};
+    ///
+    /// let policy = CspPolicyBuilder::new()
+    ///     .default_src([Source::Self_])
+    ///     .script_src([Source::Self_, Source::Host("cdn.example.com".into())])
+    ///     .report_uri("/csp-report")
+    ///     .build()?;
+    ///
+    /// policy.roundtrip_check()?;
+    /// # Ok::<(), actix_web_csp::CspError>(())
+    /// ```
+    pub fn roundtrip_check(&self) -> Result<(), CspError> {
+        self.validate()?;
+        self.compile()?;
+        let parsed = parse_header_value(&self.serialize(None)?).map_err(|error| {
+            CspError::SerializationError(format!("serialized policy does not parse: {error}"))
+        })?;
+        let changed =
+            |what: String| CspError::SerializationError(format!("round trip changed {what}"));
+        fn all_sources(directive: &Directive) -> Vec<&Source> {
+            directive
+                .sources()
+                .iter()
+                .chain(directive.fallback_sources().into_iter().flatten())
+                .collect()
+        }
+
+        let names = |policy: &CspPolicy| -> Vec<String> {
+            policy
+                .directives()
+                .map(|d| d.name().to_ascii_lowercase())
+                .collect()
+        };
+        if names(self) != names(&parsed) {
+            return Err(changed(format!(
+                "the directives from {:?} to {:?}",
+                names(self),
+                names(&parsed)
+            )));
+        }
+
+        for (original, reparsed) in self.directives().zip(parsed.directives()) {
+            let sources = all_sources(original);
+            if sources != all_sources(reparsed) || reparsed.fallback_sources().is_some() {
+                let list = |sources: Vec<&Source>| {
+                    sources
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                };
+                return Err(changed(format!(
+                    "the sources of {} from `{}` to `{}`",
+                    original.name(),
+                    list(sources),
+                    list(all_sources(reparsed))
+                )));
+            }
+        }
+
+        if self.report_uri != parsed.report_uri {
+            return Err(changed("report-uri".to_string()));
+        }
+        if self.report_to != parsed.report_to {
+            return Err(changed("report-to".to_string()));
+        }
+        Ok(())
+    }
+
     pub fn compile_with_runtime_nonce(
         &self,
         nonce: impl AsRef<str>,
@@ -225,16 +573,104 @@ impl CspPolicy {
         policy.compile()
     }
 
+    /// Renders the policy with one directive per line, for logs and diffs.
+    ///
+    /// ```rust
+    /// use actix_web_csp::{CspPolicyBuilder, Source};
+    ///
+    /// let mut policy = CspPolicyBuilder::new()
+    ///     .default_src([Source::Self_])
+    ///     .img_src([Source::Self_, Source::Scheme("data".into())])
+    ///     .build()?;
+    /// policy.set_report_uri("/csp-report");
+    ///
+    /// assert_eq!(
+    ///     policy.pretty(),
+    ///     "default-src 'self'\nimg-src 'self' data:\nreport-uri /csp-report"
+    /// );
+    /// # Ok::<(), actix_web_csp::CspError>(())
+    /// ```
+    #[inline]
+    pub fn pretty(&self) -> String {
+        format!("{self:#}")
+    }
+
+    /// Serializes the policy for the `csp` attribute of an `<iframe>`, which asks
+    /// the embedded document to enforce at least this policy (CSP Embedded
+    /// Enforcement).
+    ///
+    /// Browsers do not accept `report-uri` or `report-to` in the attribute, so
+    /// they are left out, and the attribute is always enforced whatever the
+    /// report-only setting. The policy is validated first; a value containing a
+    /// character that is unsafe in a double-quoted HTML attribute or outside
+    /// printable ASCII, or a comma that would split it into several policies,
+    /// is rejected with [`CspError::SerializationError`] rather than escaped.
+    ///
+    /// ```rust
+    /// use actix_web_csp::{CspPolicyBuilder, Source};
+    ///
+    /// let policy = CspPolicyBuilder::new()
+    ///     .script_src([Source::Self_])
+    ///     .object_src([Source::None])
+    ///     .report_uri("/csp-report")
+    ///     .build()?;
+    ///
+    /// let attr = policy.to_iframe_csp_attr()?;
+    /// assert_eq!(attr, "script-src 'self'; object-src 'none'");
+    /// let html = format!(r#"<iframe src="https://widget.example" csp="{attr}"></iframe>"#);
+    /// # Ok::<(), actix_web_csp::CspError>(())
+    /// ```
+    pub fn to_iframe_csp_attr(&self) -> Result<String, CspError> {
+        self.validate()?;
+        let mut policy = self.clone();
+        policy.report_uri = None;
+        policy.report_to = None;
+        let value = policy.serialize(None)?;
+        let value = value.to_str().map_err(|_| {
+            CspError::SerializationError("csp attribute values must be printable ASCII".to_string())
+        })?;
+
+        if let Some(unsafe_char) = value
+            .chars()
+            .find(|c| !matches!(c, ' '..='~') || matches!(c, '"' | '&' | '<' | '>' | '`' | ','))
+        {
+            return Err(CspError::SerializationError(format!(
+                "{unsafe_char:?} is not allowed in a csp attribute value"
+            )));
+        }
+        Ok(value.to_string())
+    }
+
+    /// Runs [`validate`](Self::validate) and logs a warning describing the
+    /// problem when it fails, so misconfigurations show up in the logs even
+    /// where the result is ignored.
+    pub fn validate_and_log(&self) -> Result<(), CspError> {
+        self.validate().inspect_err(|error| {
+            csp_log!(Warn, "Invalid CSP policy '{self}': {error}");
+        })
+    }
+
     pub fn validate(&self) -> Result<(), CspError> {
         for directive in self.directives.values() {
             directive.validate()?;
         }
 
+        validate_frame_embedding(self)?;
+
         #[cfg(feature = "extended-validation")]
         {
-            if let Some(report_uri) = &self.report_uri {
+            for report_uri in self.report_uris() {
                 validate_report_uri(report_uri)?;
             }
+            if self
+                .report_uri
+                .as_ref()
+                .is_some_and(|uri| uri.trim().is_empty())
+            {
+                return Err(CspError::InvalidReportUri(
+                    "report-uri cannot be empty".to_string(),
+                ));
+            }
 
             if let Some(report_to) = &self.report_to {
                 validate_report_to(report_to)?;
@@ -259,11 +695,20 @@ impl CspPolicy {
         self.directives.values()
     }
 
+    /// Returns the `report-uri` value; several URIs are separated by spaces.
     #[inline]
     pub fn report_uri(&self) -> Option<&str> {
         self.report_uri.as_deref()
     }
 
+    /// Returns each URI listed in `report-uri`.
+    #[inline]
+    pub fn report_uris(&self) -> impl Iterator<Item = &str> {
+        self.report_uri
+            .iter()
+            .flat_map(|uri| uri.split_whitespace())
+    }
+
     #[inline]
     pub fn report_to(&self) -> Option<&str> {
         self.report_to.as_deref()
@@ -280,6 +725,85 @@ impl CspPolicy {
         hash
     }
 
+    /// Hash of the policy structure with nonce values left out.
+    ///
+    /// Policies that differ only in the nonce they carry share a structural hash,
+    /// while the presence of a nonce in each directive still contributes to it, so
+    /// a nonce-bearing policy never collides with its nonce-free counterpart.
+    pub fn structural_hash(&self) -> NonZeroU64 {
+        let mut hasher = FxHasher::default();
+
+        self.directives.len().hash(&mut hasher);
+
+        for (name, directive) in &self.directives {
+            hasher.write(name.as_bytes());
+            // A placeholder hashes like the nonce that fills it.
+            let nonce_like =
+                |source: &Source| source.contains_nonce() || source.is_nonce_placeholder();
+            (directive.contains_nonce() || directive.contains_nonce_placeholder())
+                .hash(&mut hasher);
+            for source in directive.sources() {
+                if !nonce_like(source) {
+                    source.hash(&mut hasher);
+                }
+            }
+            for source in directive.fallback_sources().into_iter().flatten() {
+                if !nonce_like(source) {
+                    source.hash(&mut hasher);
+                }
+            }
+        }
+
+        self.report_only.hash(&mut hasher);
+        self.report_uri.hash(&mut hasher);
+        self.report_to.hash(&mut hasher);
+
+        NonZeroU64::new(hasher.finish()).unwrap_or(NonZeroU64::MIN)
+    }
+
+    /// Adds the WebSocket equivalent of every HTTP(S) source in `connect-src`.
+    ///
+    /// `https://host` gains `wss://host`, `http://host` gains `ws://host`, and the
+    /// `https:`/`http:` schemes gain `wss:`/`ws:`. Browsers that predate CSP Level 3
+    /// do not treat the two as equivalent, so realtime connections fail there unless
+    /// both are listed. Sources without a scheme already cover WebSockets and are
+    /// left alone.
+    pub fn mirror_websocket_sources(&mut self) -> &mut Self {
+        let Some(directive) = self.directives.get(CONNECT_SRC) else {
+            return self;
+        };
+
+        let mirrored: Vec<Source> = directive
+            .sources()
+            .iter()
+            .filter_map(|source| match source {
+                Source::Host(host) => {
+                    if let Some(rest) = host.strip_prefix("https://") {
+                        Some(Source::Host(Cow::Owned(format!("wss://{rest}"))))
+                    } else {
+                        host.strip_prefix("http://")
+                            .map(|rest| Source::Host(Cow::Owned(format!("ws://{rest}"))))
+                    }
+                }
+                Source::Scheme(scheme) if scheme.eq_ignore_ascii_case("https") => {
+                    Some(Source::Scheme(Cow::Borrowed("wss")))
+                }
+                Source::Scheme(scheme) if scheme.eq_ignore_ascii_case("http") => {
+                    Some(Source::Scheme(Cow::Borrowed("ws")))
+                }
+                _ => None,
+            })
+            .filter(|source| !directive.sources().contains(source))
+            .collect();
+
+        if !mirrored.is_empty() {
+            let mut directive = directive.clone();
+            directive.add_sources(mirrored);
+            self.add_directive(directive);
+        }
+        self
+    }
+
     #[inline]
     pub fn contains_nonce(&self) -> bool {
         self.directives.values().any(|d| d.contains_nonce())
@@ -290,6 +814,14 @@ impl CspPolicy {
         self.directives.values().any(|d| d.contains_hash())
     }
 
+    /// Returns `true` if any directive has a [`Source::NoncePlaceholder`].
+    #[inline]
+    pub fn contains_nonce_placeholder(&self) -> bool {
+        self.directives
+            .values()
+            .any(Directive::contains_nonce_placeholder)
+    }
+
     /// Returns a cloned policy with the nonce appended to nonce-aware directives.
     pub fn clone_with_runtime_nonce(&self, nonce: impl AsRef<str>) -> Self {
         let mut policy = self.clone();
@@ -297,15 +829,23 @@ impl CspPolicy {
         policy
     }
 
-    /// Appends the nonce to script/style directives on the current policy.
+    /// Appends the nonce to script/style directives on the current policy, or,
+    /// if the policy has any [`Source::NoncePlaceholder`], puts it in place of
+    /// each placeholder instead.
     pub fn inject_runtime_nonce(&mut self, nonce: impl AsRef<str>) -> &mut Self {
         let nonce: Cow<'static, str> = Cow::Owned(nonce.as_ref().to_owned());
         let mut updated = false;
 
-        for directive_name in [SCRIPT_SRC, STYLE_SRC, SCRIPT_SRC_ELEM, STYLE_SRC_ELEM] {
-            if let Some(directive) = self.directives.get_mut(directive_name) {
-                directive.add_source(Source::Nonce(nonce.clone()));
-                updated = true;
+        if self.contains_nonce_placeholder() {
+            for directive in self.directives.values_mut() {
+                updated |= directive.replace_nonce_placeholder(&nonce);
+            }
+        } else {
+            for directive_name in NONCE_DIRECTIVES {
+                if let Some(directive) = self.directives.get_mut(directive_name) {
+                    directive.add_source(Source::Nonce(nonce.clone()));
+                    updated = true;
+                }
             }
         }
 
@@ -408,6 +948,102 @@ fn validate_report_to(report_to: &str) -> Result<(), CspError> {
     Ok(())
 }
 
+/// Sandbox tokens that only matter for a document rendered inside a frame.
+const EMBED_SANDBOX_TOKENS: [&str; 4] = [
+    "allow-top-navigation",
+    "allow-top-navigation-by-user-activation",
+    "allow-top-navigation-to-custom-protocols",
+    "allow-popups-to-escape-sandbox",
+];
+
+fn validate_frame_embedding(policy: &CspPolicy) -> Result<(), CspError> {
+    let forbids_framing = policy
+        .get_directive(FRAME_ANCESTORS)
+        .is_some_and(|directive| matches!(directive.sources(), [Source::None]));
+    if !forbids_framing {
+        return Ok(());
+    }
+
+    let embed_token = policy.get_directive(SANDBOX).and_then(|sandbox| {
+        sandbox.sources().iter().find_map(|source| match source {
+            Source::Host(token) if EMBED_SANDBOX_TOKENS.contains(&token.as_ref()) => {
+                Some(token.as_ref())
+            }
+            _ => None,
+        })
+    });
+
+    match embed_token {
+        Some(token) => Err(CspError::ValidationError(format!(
+            "sandbox token '{token}' has no effect with frame-ancestors 'none'"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Directive names in the order of the CSP specification.
+const DIRECTIVE_ORDER: [&str; 25] = [
+    DEFAULT_SRC,
+    SCRIPT_SRC,
+    SCRIPT_SRC_ELEM,
+    SCRIPT_SRC_ATTR,
+    STYLE_SRC,
+    STYLE_SRC_ELEM,
+    STYLE_SRC_ATTR,
+    IMG_SRC,
+    FONT_SRC,
+    CONNECT_SRC,
+    MEDIA_SRC,
+    OBJECT_SRC,
+    CHILD_SRC,
+    FRAME_SRC,
+    WORKER_SRC,
+    MANIFEST_SRC,
+    PREFETCH_SRC,
+    BASE_URI,
+    SANDBOX,
+    FORM_ACTION,
+    FRAME_ANCESTORS,
+    UPGRADE_INSECURE_REQUESTS,
+    BLOCK_ALL_MIXED_CONTENT,
+    REQUIRE_TRUSTED_TYPES_FOR,
+    REQUIRE_SRI_FOR,
+];
+
+/// Directives a browser consults, in order, when `name` is missing from a
+/// policy, following the fetch directive fallback lists of CSP Level 3.
+fn fallback_directives(name: &str) -> &'static [&'static str] {
+    match name {
+        SCRIPT_SRC_ELEM | SCRIPT_SRC_ATTR => &[SCRIPT_SRC, DEFAULT_SRC],
+        STYLE_SRC_ELEM | STYLE_SRC_ATTR => &[STYLE_SRC, DEFAULT_SRC],
+        WORKER_SRC => &[CHILD_SRC, SCRIPT_SRC, DEFAULT_SRC],
+        FENCED_FRAME_SRC => &[FRAME_SRC, CHILD_SRC, DEFAULT_SRC],
+        FRAME_SRC => &[CHILD_SRC, DEFAULT_SRC],
+        SCRIPT_SRC | STYLE_SRC | IMG_SRC | FONT_SRC | CONNECT_SRC | MEDIA_SRC | OBJECT_SRC
+        | CHILD_SRC | MANIFEST_SRC | PREFETCH_SRC => &[DEFAULT_SRC],
+        _ => &[],
+    }
+}
+
+/// Canonical position of a directive name, following the order of the CSP
+/// specification. Unknown directives share the last rank.
+fn directive_rank(name: &str) -> usize {
+    DIRECTIVE_ORDER
+        .iter()
+        .position(|candidate| *candidate == name)
+        .unwrap_or(DIRECTIVE_ORDER.len())
+}
+
+/// Returns the static name of a directive this crate knows, matched
+/// case-insensitively.
+#[cfg_attr(not(feature = "stats"), allow(dead_code))]
+pub(crate) fn known_directive(name: &str) -> Option<&'static str> {
+    DIRECTIVE_ORDER
+        .iter()
+        .find(|candidate| candidate.eq_ignore_ascii_case(name))
+        .copied()
+}
+
 impl Hash for CspPolicy {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.directives.len().hash(state);
@@ -421,31 +1057,30 @@ impl Hash for CspPolicy {
     }
 }
 
+/// Renders the policy in header form, `default-src 'self'; img-src data:`.
+///
+/// The alternate form, `{:#}`, puts each directive on its own line; see
+/// [`CspPolicy::pretty`].
 impl fmt::Display for CspPolicy {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if f.alternate() { "\n" } else { "; " };
         let mut first = true;
-
-        for directive in self.directives.values() {
+        let mut write_part = |f: &mut fmt::Formatter<'_>, part: fmt::Arguments<'_>| {
             if !first {
-                f.write_str("; ")?;
+                f.write_str(separator)?;
             }
-            write!(f, "{directive}")?;
             first = false;
-        }
+            f.write_fmt(part)
+        };
 
+        for directive in self.directives.values() {
+            write_part(f, format_args!("{directive}"))?;
+        }
         if let Some(report_uri) = &self.report_uri {
-            if !first {
-                f.write_str("; ")?;
-            }
-            write!(f, "{REPORT_URI} {report_uri}")?;
-            first = false;
+            write_part(f, format_args!("{REPORT_URI} {report_uri}"))?;
         }
-
         if let Some(report_to) = &self.report_to {
-            if !first {
-                f.write_str("; ")?;
-            }
-            write!(f, "{REPORT_TO} {report_to}")?;
+            write_part(f, format_args!("{REPORT_TO} {report_to}"))?;
         }
 
         Ok(())
@@ -465,13 +1100,13 @@ impl FromStr for CspPolicy {
             }
 
             if let Some(report_uri) = segment.strip_prefix(REPORT_URI) {
-                let report_uri = report_uri.trim();
-                if report_uri.is_empty() || report_uri.contains(char::is_whitespace) {
+                let report_uris: Vec<&str> = report_uri.split_whitespace().collect();
+                if report_uris.is_empty() {
                     return Err(CspError::InvalidReportUri(
-                        "report-uri must contain exactly one value".to_string(),
+                        "report-uri must contain at least one value".to_string(),
                     ));
                 }
-                policy.set_report_uri(report_uri.to_owned());
+                policy.set_report_uri(report_uris.join(" "));
                 continue;
             }
 
@@ -494,6 +1129,142 @@ impl FromStr for CspPolicy {
     }
 }
 
+/// Parses a `Content-Security-Policy` header value as seen on the wire.
+///
+/// Unlike the [`FromStr`] implementation, which targets hand-written policy
+/// strings, this follows the header grammar: directive names are matched
+/// case-insensitively and stored lowercase, repeated directives after the first
+/// are ignored as browsers do, and every failure is reported as
+/// [`CspError::ParseError`] carrying the zero-based directive index and the byte
+/// offset of the offending token within the header value.
+///
+/// ```rust
+/// use actix_web::http::header::HeaderValue;
+/// use actix_web_csp::{core::parse_header_value, CspError};
+///
+/// let policy =
+///     parse_header_value(&HeaderValue::from_static("Script-Src 'self'; img-src data:"))?;
+/// assert!(policy.get_directive("script-src").is_some());
+///
+/// let error = parse_header_value(&HeaderValue::from_static("img-src 'self'; script-src 'sha1-abc'"))
+///     .unwrap_err();
+/// assert!(matches!(error, CspError::ParseError { directive_index: 1, offset: 27, .. }));
+/// # Ok::<(), CspError>(())
+/// ```
+pub fn parse_header_value(value: &HeaderValue) -> Result<CspPolicy, CspError> {
+    let bytes = value.as_bytes();
+    let parse_error =
+        |directive_index: usize, offset: usize, message: String| CspError::ParseError {
+            directive_index,
+            offset,
+            message,
+        };
+
+    if let Some(offset) = bytes
+        .iter()
+        .position(|&byte| byte != b'\t' && !(0x20..0x7f).contains(&byte))
+    {
+        let directive_index = bytes[..offset].iter().filter(|&&b| b == b';').count();
+        return Err(parse_error(
+            directive_index,
+            offset,
+            "header value contains a non-ASCII or control byte".to_string(),
+        ));
+    }
+
+    let text = std::str::from_utf8(bytes)
+        .map_err(|error| parse_error(0, error.valid_up_to(), error.to_string()))?;
+
+    let mut policy = CspPolicy::new();
+    let mut segment_offset = 0;
+
+    for (directive_index, segment) in text.split(';').enumerate() {
+        let base = segment_offset;
+        segment_offset += segment.len() + 1;
+
+        let mut tokens = segment
+            .split([' ', '\t'])
+            .scan(base, |offset, token| {
+                let start = *offset;
+                *offset += token.len() + 1;
+                Some((start, token))
+            })
+            .filter(|(_, token)| !token.is_empty());
+
+        let Some((name_offset, name)) = tokens.next() else {
+            continue;
+        };
+
+        if !name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-')
+        {
+            return Err(parse_error(
+                directive_index,
+                name_offset,
+                format!("invalid directive name '{name}'"),
+            ));
+        }
+
+        let name = name.to_ascii_lowercase();
+
+        if name == REPORT_URI {
+            let uris: Vec<&str> = tokens.map(|(_, uri)| uri).collect();
+            if uris.is_empty() {
+                return Err(parse_error(
+                    directive_index,
+                    name_offset + name.len(),
+                    format!("{name} requires a value"),
+                ));
+            }
+            if policy.report_uri.is_none() {
+                policy.set_report_uri(uris.join(" "));
+            }
+            continue;
+        }
+
+        if name == REPORT_TO {
+            let (_, value) = tokens.next().ok_or_else(|| {
+                parse_error(
+                    directive_index,
+                    name_offset + name.len(),
+                    format!("{name} requires a value"),
+                )
+            })?;
+            if let Some((extra_offset, _)) = tokens.next() {
+                return Err(parse_error(
+                    directive_index,
+                    extra_offset,
+                    format!("{name} accepts a single value"),
+                ));
+            }
+
+            if policy.report_to.is_none() {
+                policy.set_report_to(value.to_owned());
+            }
+            continue;
+        }
+
+        if policy.get_directive(&name).is_some() {
+            continue;
+        }
+
+        let mut directive = Directive::new(name);
+        for (offset, token) in tokens {
+            let source = Source::from_str(token)
+                .map_err(|error| parse_error(directive_index, offset, error.to_string()))?;
+            directive.add_source(source);
+        }
+
+        directive
+            .validate()
+            .map_err(|error| parse_error(directive_index, name_offset, error.to_string()))?;
+        policy.add_directive(directive);
+    }
+
+    Ok(policy)
+}
+
 impl TryFrom<&str> for CspPolicy {
     type Error = CspError;
 
@@ -587,19 +1358,75 @@ impl CspPolicyBuilder {
         self.add_directive(crate::core::directives::FormAction::new().add_sources(sources))
     }
 
+    /// Allows the given inline event handlers, the values of attributes like
+    /// `onclick="..."`, by adding their SHA-256 hashes and `'unsafe-hashes'` to
+    /// `script-src`.
+    ///
+    /// This lets legacy markup run under a policy without `'unsafe-inline'`
+    /// while the handlers are moved to `addEventListener`. Call it after
+    /// [`script_src`](Self::script_src); without a `script-src`, one is started
+    /// from the `default-src` sources so scripts allowed there keep loading.
+    /// See [`HashGenerator::event_handler_source`] for how values are hashed.
+    ///
+    /// ```rust
+    /// use actix_web_csp::{CspPolicyBuilder, Source};
+    ///
+    /// let policy = CspPolicyBuilder::new()
+    ///     .script_src([Source::Self_])
+    ///     .event_handler_hashes(["doSomething()", "return confirm('Delete?')"])
+    ///     .build()?;
+    ///
+    /// let script_src = policy.get_directive("script-src").unwrap().to_string();
+    /// assert!(script_src.starts_with("script-src 'self' 'unsafe-hashes' 'sha256-"));
+    /// # Ok::<(), actix_web_csp::CspError>(())
+    /// ```
+    pub fn event_handler_hashes(self, handlers: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        let mut directive = match self.policy.get_directive(SCRIPT_SRC) {
+            Some(directive) => directive.clone(),
+            None => {
+                let mut directive = Directive::new(SCRIPT_SRC);
+                if let Some(default_src) = self.policy.get_directive(DEFAULT_SRC) {
+                    directive.add_sources(default_src.sources().iter().cloned());
+                }
+                directive
+            }
+        };
+        directive.add_source(Source::UnsafeHashes);
+        for handler in handlers {
+            directive.add_source(HashGenerator::event_handler_source(
+                HashAlgorithm::Sha256,
+                handler.as_ref(),
+            ));
+        }
+        self.with_directive(directive)
+    }
+
+    /// Adds the sources a third-party service needs, such as Stripe's script,
+    /// frame and API hosts; see [`Service`]. Call it after the directives it
+    /// extends are set, since setting a directive replaces it.
+    #[inline]
+    pub fn apply_service(mut self, service: Service) -> Self {
+        self.policy.apply_service(service);
+        self
+    }
+
     pub fn sandbox(self, sandbox_builder: Sandbox) -> Self {
         self.with_directive(sandbox_builder.build())
     }
 
+    pub fn require_sri_for(self, require_sri_for: RequireSriFor) -> Self {
+        self.with_directive(require_sri_for.build())
+    }
+
     pub fn upgrade_insecure_requests(mut self) -> Self {
         self.policy
-            .add_directive(Directive::new("upgrade-insecure-requests"));
+            .add_directive(Directive::new(UPGRADE_INSECURE_REQUESTS));
         self
     }
 
     pub fn block_all_mixed_content(mut self) -> Self {
         self.policy
-            .add_directive(Directive::new("block-all-mixed-content"));
+            .add_directive(Directive::new(BLOCK_ALL_MIXED_CONTENT));
         self
     }
 
@@ -607,7 +1434,7 @@ impl CspPolicyBuilder {
         self,
         contexts: impl IntoIterator<Item = impl Into<Cow<'static, str>>>,
     ) -> Self {
-        let mut directive = Directive::new("require-trusted-types-for");
+        let mut directive = Directive::new(REQUIRE_TRUSTED_TYPES_FOR);
         for context in contexts {
             directive.add_source(Source::Host(context.into()));
         }
@@ -618,7 +1445,7 @@ impl CspPolicyBuilder {
         self,
         policies: impl IntoIterator<Item = impl Into<Cow<'static, str>>>,
     ) -> Self {
-        let mut directive = Directive::new("trusted-types");
+        let mut directive = Directive::new(TRUSTED_TYPES);
         for policy in policies {
             directive.add_source(Source::Host(policy.into()));
         }
@@ -648,8 +1475,15 @@ impl CspPolicyBuilder {
         Ok(self.policy)
     }
 
+    /// Returns the policy without validating it.
+    ///
+    /// Debug builds still validate and log a warning for an invalid policy, so
+    /// mistakes surface during development without changing release behavior.
     #[inline]
     pub fn build_unchecked(self) -> CspPolicy {
+        if cfg!(debug_assertions) {
+            let _ = self.policy.validate_and_log();
+        }
         self.policy
     }
 }
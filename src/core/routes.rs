@@ -0,0 +1,114 @@
+use crate::core::policy::{CspPolicy, SelectedPolicy};
+use actix_web::dev::ServiceRequest;
+use rustc_hash::FxHashMap;
+use std::sync::Arc;
+
+/// Path-based policy table, selected per request by the longest matching prefix.
+///
+/// Prefixes match whole path segments, so `/checkout` covers `/checkout` and
+/// `/checkout/confirm` but not `/checkouts`. Requests outside every prefix get
+/// the [`default`](Self::default) policy, or the middleware's own policy when
+/// none is set. Each policy is compiled once when it is added, and lookups walk
+/// a trie of path segments.
+///
+/// ```rust
+/// use actix_web_csp::{csp_middleware, CspPolicyBuilder, PolicyRoutes, Source};
+///
+/// let base = CspPolicyBuilder::new().default_src([Source::Self_]).build()?;
+/// let strict = CspPolicyBuilder::new()
+///     .default_src([Source::None])
+///     .script_src([Source::Self_])
+///     .build()?;
+/// let relaxed = CspPolicyBuilder::new()
+///     .default_src([Source::Self_])
+///     .style_src([Source::Self_, Source::UnsafeInline])
+///     .build()?;
+///
+/// let routes = PolicyRoutes::new()
+///     .prefix("/checkout", strict)
+///     .prefix("/docs", relaxed)
+///     .default(base.clone());
+/// assert!(routes.policy_for("/checkout/confirm").is_some());
+///
+/// let _middleware = csp_middleware(base).with_policy_routes(routes);
+/// # Ok::<(), actix_web_csp::CspError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PolicyRoutes {
+    root: RouteNode,
+    default: Option<Arc<SelectedPolicy>>,
+    len: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct RouteNode {
+    children: FxHashMap<Box<str>, RouteNode>,
+    policy: Option<Arc<SelectedPolicy>>,
+}
+
+impl PolicyRoutes {
+    #[inline]
+    pub fn new() -> Self {
+        <Self as Default>::default()
+    }
+
+    /// Uses `policy` for `prefix` and every path below it. Adding the same
+    /// prefix again replaces its policy; `/` matches every path.
+    pub fn prefix(mut self, prefix: impl AsRef<str>, policy: CspPolicy) -> Self {
+        let node = segments(prefix.as_ref()).fold(&mut self.root, |node, segment| {
+            node.children.entry(segment.into()).or_default()
+        });
+        if node.policy.is_none() {
+            self.len += 1;
+        }
+        node.policy = Some(Arc::new(SelectedPolicy::new(policy)));
+        self
+    }
+
+    /// Uses `policy` for paths outside every prefix.
+    pub fn default(mut self, policy: CspPolicy) -> Self {
+        self.default = Some(Arc::new(SelectedPolicy::new(policy)));
+        self
+    }
+
+    /// Number of prefixes in the table, not counting the default.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the table has neither prefixes nor a default.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0 && self.default.is_none()
+    }
+
+    /// The policy selected for `path`, `None` when the middleware's own policy
+    /// applies.
+    pub fn policy_for(&self, path: &str) -> Option<&CspPolicy> {
+        self.lookup(path).map(|selected| selected.policy())
+    }
+
+    /// Looks the request up by the path the router matches on, which has
+    /// percent-encoded unreserved characters already decoded.
+    pub(crate) fn resolve(&self, req: &ServiceRequest) -> Option<Arc<SelectedPolicy>> {
+        self.lookup(req.match_info().as_str()).cloned()
+    }
+
+    fn lookup(&self, path: &str) -> Option<&Arc<SelectedPolicy>> {
+        let mut node = &self.root;
+        let mut selected = node.policy.as_ref();
+        for segment in segments(path) {
+            match node.children.get(segment) {
+                Some(child) => node = child,
+                None => break,
+            }
+            selected = node.policy.as_ref().or(selected);
+        }
+        selected.or(self.default.as_ref())
+    }
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
@@ -1,18 +1,29 @@
 use crate::constants::{
-    NONCE_PREFIX, NONE_SOURCE, REPORT_SAMPLE_SOURCE, SELF_SOURCE, STRICT_DYNAMIC_SOURCE,
-    SUFFIX_QUOTE, UNSAFE_EVAL_SOURCE, UNSAFE_HASHES_SOURCE, UNSAFE_INLINE_SOURCE,
-    WASM_UNSAFE_EVAL_SOURCE,
+    NONCE_PLACEHOLDER_SOURCE, NONCE_PREFIX, NONE_SOURCE, REPORT_SAMPLE_SOURCE, SELF_SOURCE,
+    STRICT_DYNAMIC_SOURCE, SUFFIX_QUOTE, UNSAFE_EVAL_SOURCE, UNSAFE_HASHES_SOURCE,
+    UNSAFE_INLINE_SOURCE, WASM_UNSAFE_EVAL_SOURCE,
 };
 use crate::security::hash::HashAlgorithm;
 use crate::utils::BufferWriter;
 use bytes::BytesMut;
 use std::{
     borrow::Cow,
+    cmp::Ordering,
     fmt,
     hash::{Hash, Hasher},
     str::FromStr,
 };
 
+/// CSP Level 3 keywords parsed into [`Source::OtherKeyword`].
+const OTHER_KEYWORD_SOURCES: [&str; 6] = [
+    "'inline-speculation-rules'",
+    "'report-sha256'",
+    "'report-sha384'",
+    "'report-sha512'",
+    "'trusted-types-eval'",
+    "'unsafe-allow-redirects'",
+];
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Source {
     None,
@@ -26,13 +37,57 @@ pub enum Source {
     Host(Cow<'static, str>),
     Scheme(Cow<'static, str>),
     Nonce(Cow<'static, str>),
+    /// Stands for the per-request nonce: the middleware writes the request's
+    /// `'nonce-...'` here when it renders the header, and leaves it out when the
+    /// request has none. Written as `'nonce-{{csp-nonce}}'` outside headers.
+    ///
+    /// Once a policy contains a placeholder, the request nonce goes only where
+    /// placeholders are, instead of into every `script-src`/`style-src`
+    /// directive.
+    NoncePlaceholder,
     Hash {
         algorithm: HashAlgorithm,
         value: Cow<'static, str>,
     },
+    /// A CSP Level 3 keyword without a variant of its own, such as
+    /// `'inline-speculation-rules'` or `'report-sha256'`, kept with its quotes.
+    /// Parsing only produces the keywords the specification defines.
+    OtherKeyword(Cow<'static, str>),
+}
+
+/// Broad category of a [`Source`], from [`Source::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SourceKind {
+    /// A quoted keyword such as `'self'`, `'none'` or `'unsafe-inline'`
+    Keyword,
+    /// A scheme such as `https:` or `data:`
+    Scheme,
+    /// A host expression such as `cdn.example.com` or `https://*.example.com:443`
+    Host,
+    /// A `'nonce-...'` source
+    Nonce,
+    /// A `'sha256-...'`, `'sha384-...'` or `'sha512-...'` source
+    Hash,
 }
 
 impl Source {
+    /// Returns the category of this source.
+    #[inline]
+    pub const fn kind(&self) -> SourceKind {
+        match self {
+            Source::Host(_) => SourceKind::Host,
+            Source::Scheme(_) => SourceKind::Scheme,
+            Source::Nonce(_) | Source::NoncePlaceholder => SourceKind::Nonce,
+            Source::Hash { .. } => SourceKind::Hash,
+            _ => SourceKind::Keyword,
+        }
+    }
+
+    #[inline(always)]
+    pub const fn is_keyword(&self) -> bool {
+        matches!(self.kind(), SourceKind::Keyword)
+    }
+
     #[inline(always)]
     pub const fn is_none(&self) -> bool {
         matches!(self, Source::None)
@@ -68,6 +123,104 @@ impl Source {
         }
     }
 
+    /// Builds a [`Source::Host`] from `host[:port][/path]`, such as `cdn.example.com`
+    /// or `*.example.com:443`.
+    ///
+    /// The value must not carry a scheme; use [`try_scheme`](Self::try_scheme) for
+    /// `https:`-style sources, or parse `https://cdn.example.com` with [`FromStr`].
+    ///
+    /// ```rust
+    /// use actix_web_csp::Source;
+    ///
+    /// assert_eq!(Source::try_host("cdn.example.com")?.to_string(), "cdn.example.com");
+    /// assert!(Source::try_host("https://cdn.example.com").is_err());
+    /// # Ok::<(), actix_web_csp::CspError>(())
+    /// ```
+    pub fn try_host(host: impl Into<Cow<'static, str>>) -> Result<Self, crate::error::CspError> {
+        let host = host.into();
+        if host.contains("://") || host.ends_with(':') {
+            return Err(crate::error::CspError::InvalidDirectiveValue(format!(
+                "Host source must not include a scheme: {host}"
+            )));
+        }
+        host.parse::<HostSource>()?;
+        Ok(Source::Host(host))
+    }
+
+    /// Builds a [`Source::Scheme`] from a bare scheme name such as `https` or `data`.
+    pub fn try_scheme(
+        scheme: impl Into<Cow<'static, str>>,
+    ) -> Result<Self, crate::error::CspError> {
+        let scheme = scheme.into();
+        if !is_valid_scheme(&scheme) {
+            let hint = if scheme.ends_with(':') {
+                " (omit the trailing colon)"
+            } else {
+                ""
+            };
+            return Err(crate::error::CspError::InvalidDirectiveValue(format!(
+                "Invalid scheme source: {scheme}{hint}"
+            )));
+        }
+        Ok(Source::Scheme(scheme))
+    }
+
+    /// Builds a [`Source::Nonce`] from the bare nonce value, without the
+    /// `'nonce-` prefix, checking it against the base64 alphabet.
+    pub fn try_nonce(nonce: impl Into<Cow<'static, str>>) -> Result<Self, crate::error::CspError> {
+        let nonce = nonce.into();
+        if !is_base64_value(&nonce) {
+            return Err(crate::error::CspError::InvalidNonceValue(format!(
+                "Invalid nonce source: {nonce}"
+            )));
+        }
+        Ok(Source::Nonce(nonce))
+    }
+
+    /// Orders sources canonically: keywords, nonces, hashes, schemes, then hosts.
+    pub(crate) fn canonical_cmp(&self, other: &Self) -> Ordering {
+        fn rank(source: &Source) -> u8 {
+            match source {
+                Source::None => 0,
+                Source::Self_ => 1,
+                Source::StrictDynamic => 2,
+                Source::UnsafeInline => 3,
+                Source::UnsafeEval => 4,
+                Source::WasmUnsafeEval => 5,
+                Source::UnsafeHashes => 6,
+                Source::ReportSample => 7,
+                Source::OtherKeyword(_) => 8,
+                Source::Nonce(_) | Source::NoncePlaceholder => 9,
+                Source::Hash { .. } => 10,
+                Source::Scheme(_) => 11,
+                Source::Host(_) => 12,
+            }
+        }
+
+        rank(self)
+            .cmp(&rank(other))
+            .then_with(|| match (self, other) {
+                (Source::Nonce(a), Source::Nonce(b))
+                | (Source::OtherKeyword(a), Source::OtherKeyword(b))
+                | (Source::Scheme(a), Source::Scheme(b))
+                | (Source::Host(a), Source::Host(b)) => a.cmp(b),
+                (
+                    Source::Hash {
+                        algorithm: a_algorithm,
+                        value: a_value,
+                    },
+                    Source::Hash {
+                        algorithm: b_algorithm,
+                        value: b_value,
+                    },
+                ) => a_algorithm
+                    .name()
+                    .cmp(b_algorithm.name())
+                    .then_with(|| a_value.cmp(b_value)),
+                _ => Ordering::Equal,
+            })
+    }
+
     #[inline]
     pub fn estimated_size(&self) -> usize {
         match self {
@@ -79,20 +232,33 @@ impl Source {
             Source::ReportSample => REPORT_SAMPLE_SOURCE.len(),
             Source::WasmUnsafeEval => WASM_UNSAFE_EVAL_SOURCE.len(),
             Source::UnsafeHashes => UNSAFE_HASHES_SOURCE.len(),
+            Source::OtherKeyword(keyword) => keyword.len(),
             Source::Host(host) => host.len(),
             Source::Scheme(scheme) => scheme.len() + 1,
             Source::Nonce(nonce) => NONCE_PREFIX.len() + nonce.len() + SUFFIX_QUOTE.len(),
+            Source::NoncePlaceholder => NONCE_PLACEHOLDER_SOURCE.len(),
             Source::Hash { algorithm, value } => {
                 algorithm.prefix().len() + value.len() + SUFFIX_QUOTE.len()
             }
         }
     }
 
+    /// Returns `true` for nonces.
+    ///
+    /// A [`Source::NoncePlaceholder`] is not counted: it only turns into a
+    /// nonce when the middleware has a nonce generator to fill it, and is left
+    /// out of the header otherwise. The request policy the middleware stores
+    /// has its placeholders filled, so checks against it see the nonce.
     #[inline]
     pub fn contains_nonce(&self) -> bool {
         matches!(self, Source::Nonce(_))
     }
 
+    #[inline]
+    pub fn is_nonce_placeholder(&self) -> bool {
+        matches!(self, Source::NoncePlaceholder)
+    }
+
     #[inline]
     pub fn contains_hash(&self) -> bool {
         matches!(self, Source::Hash { .. })
@@ -114,6 +280,15 @@ impl Source {
         }
     }
 
+    /// Parses a [`Source::Host`] value into its scheme, host, port and path parts.
+    #[inline]
+    pub fn host_source(&self) -> Option<HostSource> {
+        match self {
+            Source::Host(host) => host.parse().ok(),
+            _ => None,
+        }
+    }
+
     #[inline]
     pub fn nonce(&self) -> Option<&str> {
         match self {
@@ -142,7 +317,9 @@ impl Hash for Source {
             | Source::StrictDynamic
             | Source::ReportSample
             | Source::WasmUnsafeEval
-            | Source::UnsafeHashes => {}
+            | Source::UnsafeHashes
+            | Source::NoncePlaceholder => {}
+            Source::OtherKeyword(keyword) => keyword.hash(state),
             Source::Host(host) => host.hash(state),
             Source::Scheme(scheme) => scheme.hash(state),
             Source::Nonce(nonce) => nonce.hash(state),
@@ -165,9 +342,11 @@ impl fmt::Display for Source {
             Source::ReportSample => f.write_str(REPORT_SAMPLE_SOURCE),
             Source::WasmUnsafeEval => f.write_str(WASM_UNSAFE_EVAL_SOURCE),
             Source::UnsafeHashes => f.write_str(UNSAFE_HASHES_SOURCE),
+            Source::OtherKeyword(keyword) => f.write_str(keyword),
             Source::Host(host) => f.write_str(host),
             Source::Scheme(scheme) => write!(f, "{scheme}:"),
             Source::Nonce(nonce) => write!(f, "{NONCE_PREFIX}{nonce}{SUFFIX_QUOTE}"),
+            Source::NoncePlaceholder => f.write_str(NONCE_PLACEHOLDER_SOURCE),
             Source::Hash { algorithm, value } => {
                 write!(f, "{}{}{}", algorithm.prefix(), value, SUFFIX_QUOTE)
             }
@@ -186,6 +365,7 @@ impl BufferWriter for Source {
             Source::ReportSample => buffer.extend_from_slice(REPORT_SAMPLE_SOURCE.as_bytes()),
             Source::WasmUnsafeEval => buffer.extend_from_slice(WASM_UNSAFE_EVAL_SOURCE.as_bytes()),
             Source::UnsafeHashes => buffer.extend_from_slice(UNSAFE_HASHES_SOURCE.as_bytes()),
+            Source::OtherKeyword(keyword) => buffer.extend_from_slice(keyword.as_bytes()),
             Source::Host(host) => {
                 if let Some(interned) = crate::utils::intern_string(host) {
                     buffer.extend_from_slice(interned.as_bytes());
@@ -203,6 +383,8 @@ impl BufferWriter for Source {
                 buffer.extend_from_slice(nonce.as_bytes());
                 buffer.extend_from_slice(SUFFIX_QUOTE.as_bytes());
             }
+            // Directives leave placeholders out of the header or splice the nonce in
+            Source::NoncePlaceholder => {}
             Source::Hash { algorithm, value } => {
                 let prefix = algorithm.prefix();
                 buffer.reserve(prefix.len() + value.len() + SUFFIX_QUOTE.len());
@@ -217,6 +399,14 @@ impl BufferWriter for Source {
 impl FromStr for Source {
     type Err = crate::error::CspError;
 
+    /// Parses a single source expression such as `'self'`, `'nonce-abc'`,
+    /// `'sha256-...'`, `https:` or `https://*.example.com:443/path`.
+    ///
+    /// Keywords are matched case-insensitively; CSP Level 3 keywords without a
+    /// variant of their own become [`Source::OtherKeyword`]. Unknown quoted
+    /// keywords, nonces or hashes outside the base64 alphabet, and host
+    /// expressions that do not follow the `[scheme://]host[:port][/path]` grammar
+    /// are rejected.
     fn from_str(value: &str) -> Result<Self, Self::Err> {
         let value = value.trim();
 
@@ -226,36 +416,87 @@ impl FromStr for Source {
             ));
         }
 
-        let source = match value {
-            NONE_SOURCE => Source::None,
-            SELF_SOURCE => Source::Self_,
-            UNSAFE_INLINE_SOURCE => Source::UnsafeInline,
-            UNSAFE_EVAL_SOURCE => Source::UnsafeEval,
-            STRICT_DYNAMIC_SOURCE => Source::StrictDynamic,
-            REPORT_SAMPLE_SOURCE => Source::ReportSample,
-            WASM_UNSAFE_EVAL_SOURCE => Source::WasmUnsafeEval,
-            UNSAFE_HASHES_SOURCE => Source::UnsafeHashes,
-            _ => {
-                if let Some(nonce) = value
-                    .strip_prefix(NONCE_PREFIX)
-                    .and_then(|value| value.strip_suffix(SUFFIX_QUOTE))
-                {
-                    Source::Nonce(Cow::Owned(nonce.to_owned()))
-                } else if let Some((algorithm, hash_value)) = parse_hash_source(value)? {
-                    Source::Hash {
-                        algorithm,
-                        value: Cow::Owned(hash_value),
-                    }
-                } else if let Some(scheme) = value.strip_suffix(':') {
-                    Source::Scheme(Cow::Owned(scheme.to_owned()))
-                } else {
-                    Source::Host(Cow::Owned(value.to_owned()))
-                }
+        if value.starts_with('\'') {
+            return parse_quoted_source(value);
+        }
+
+        if let Some(scheme) = value.strip_suffix(':') {
+            if !is_valid_scheme(scheme) {
+                return Err(crate::error::CspError::InvalidDirectiveValue(format!(
+                    "Invalid scheme source: {value}"
+                )));
             }
-        };
+            return Ok(Source::Scheme(Cow::Owned(scheme.to_owned())));
+        }
+
+        value.parse::<HostSource>()?;
+        Ok(Source::Host(Cow::Owned(value.to_owned())))
+    }
+}
+
+fn parse_quoted_source(value: &str) -> Result<Source, crate::error::CspError> {
+    let lowercase = value.to_ascii_lowercase();
+
+    let keyword = match lowercase.as_str() {
+        NONE_SOURCE => Some(Source::None),
+        SELF_SOURCE => Some(Source::Self_),
+        UNSAFE_INLINE_SOURCE => Some(Source::UnsafeInline),
+        UNSAFE_EVAL_SOURCE => Some(Source::UnsafeEval),
+        STRICT_DYNAMIC_SOURCE => Some(Source::StrictDynamic),
+        REPORT_SAMPLE_SOURCE => Some(Source::ReportSample),
+        WASM_UNSAFE_EVAL_SOURCE => Some(Source::WasmUnsafeEval),
+        UNSAFE_HASHES_SOURCE => Some(Source::UnsafeHashes),
+        _ => None,
+    };
+    if let Some(keyword) = keyword {
+        return Ok(keyword);
+    }
+    if value == NONCE_PLACEHOLDER_SOURCE {
+        return Ok(Source::NoncePlaceholder);
+    }
+
+    if lowercase.starts_with(NONCE_PREFIX) {
+        let nonce = value[NONCE_PREFIX.len()..]
+            .strip_suffix(SUFFIX_QUOTE)
+            .filter(|nonce| is_base64_value(nonce))
+            .ok_or_else(|| {
+                crate::error::CspError::InvalidNonceValue(format!("Invalid nonce source: {value}"))
+            })?;
+        return Ok(Source::Nonce(Cow::Owned(nonce.to_owned())));
+    }
+
+    if let Some((algorithm, hash_value)) = parse_hash_source(value)? {
+        return Ok(Source::Hash {
+            algorithm,
+            value: Cow::Owned(hash_value),
+        });
+    }
 
-        Ok(source)
+    if let Some(keyword) = OTHER_KEYWORD_SOURCES
+        .iter()
+        .find(|keyword| **keyword == lowercase)
+    {
+        return Ok(Source::OtherKeyword(Cow::Borrowed(keyword)));
     }
+
+    Err(crate::error::CspError::InvalidDirectiveValue(format!(
+        "Unknown keyword source: {value}"
+    )))
+}
+
+fn is_base64_value(value: &str) -> bool {
+    let trimmed = value.trim_end_matches('=');
+    !trimmed.is_empty()
+        && value.len() - trimmed.len() <= 2
+        && trimmed
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'+' | b'/' | b'-' | b'_'))
+}
+
+fn is_valid_scheme(scheme: &str) -> bool {
+    let mut bytes = scheme.bytes();
+    bytes.next().is_some_and(|byte| byte.is_ascii_alphabetic())
+        && bytes.all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'+' | b'-' | b'.'))
 }
 
 impl TryFrom<&str> for Source {
@@ -267,6 +508,15 @@ impl TryFrom<&str> for Source {
     }
 }
 
+impl TryFrom<String> for Source {
+    type Error = crate::error::CspError;
+
+    #[inline]
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from_str(&value)
+    }
+}
+
 fn parse_hash_source(
     value: &str,
 ) -> Result<Option<(HashAlgorithm, String)>, crate::error::CspError> {
@@ -279,6 +529,11 @@ fn parse_hash_source(
             .strip_prefix(algorithm.prefix())
             .and_then(|value| value.strip_suffix(SUFFIX_QUOTE))
         {
+            if !is_base64_value(hash_value) {
+                return Err(crate::error::CspError::InvalidDirectiveValue(format!(
+                    "Invalid hash source: {value}"
+                )));
+            }
             return Ok(Some((algorithm, hash_value.to_owned())));
         }
     }
@@ -291,3 +546,248 @@ fn parse_hash_source(
 
     Ok(None)
 }
+
+/// Scheme part of a host source expression.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SchemePart {
+    /// No scheme: matches the protected resource's scheme and its secure upgrade
+    Unspecified,
+    /// `*://`: any network scheme
+    Any,
+    Scheme(String),
+}
+
+/// Port part of a host source expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PortPart {
+    /// No port: only the default port of the URL's scheme
+    Unspecified,
+    /// `:*`
+    Any,
+    Port(u16),
+}
+
+/// A structured host source such as `https://*.example.com:*/static/`.
+///
+/// The host is either `*`, a `*.`-prefixed wildcard or an exact name, stored in
+/// lowercase. A path ending in `/` matches as a prefix, any other path exactly.
+///
+/// ```rust
+/// use actix_web_csp::core::source::{HostSource, PortPart, SchemePart};
+///
+/// let source: HostSource = "https://*.example.com:*".parse()?;
+/// assert_eq!(source.scheme, SchemePart::Scheme("https".to_string()));
+/// assert_eq!(source.port, PortPart::Any);
+/// assert!(source.matches(&"https://cdn.example.com:8443/app.js".parse().unwrap(), None));
+/// assert_eq!(source.to_string(), "https://*.example.com:*");
+/// # Ok::<(), actix_web_csp::CspError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HostSource {
+    pub scheme: SchemePart,
+    pub host: String,
+    pub port: PortPart,
+    pub path: Option<String>,
+}
+
+impl HostSource {
+    /// Returns `true` if `url` matches this expression.
+    ///
+    /// `protected_scheme` is the scheme of the document the policy protects; it
+    /// decides which schemes an expression without one allows. Without it, any of
+    /// `http`, `https`, `ws` and `wss` is accepted.
+    pub fn matches(&self, url: &url::Url, protected_scheme: Option<&str>) -> bool {
+        self.mismatch(url, protected_scheme).is_none()
+    }
+
+    /// Returns the first part of `url` this expression rejects, checked in the
+    /// order scheme, host, port, path; `None` when it matches.
+    pub(crate) fn mismatch(
+        &self,
+        url: &url::Url,
+        protected_scheme: Option<&str>,
+    ) -> Option<HostMismatch> {
+        let url_scheme = url.scheme();
+        let scheme_matches = match &self.scheme {
+            SchemePart::Any => is_network_scheme(url_scheme),
+            SchemePart::Scheme(scheme) => scheme_part_matches(scheme, url_scheme),
+            SchemePart::Unspecified => match protected_scheme {
+                Some(protected) => scheme_part_matches(protected, url_scheme),
+                None => is_network_scheme(url_scheme),
+            },
+        };
+        if !scheme_matches {
+            return Some(HostMismatch::Scheme);
+        }
+
+        let Some(url_host) = url.host_str() else {
+            return Some(HostMismatch::Host);
+        };
+        let host_matches = if self.host == "*" {
+            true
+        } else if let Some(domain) = self.host.strip_prefix("*.") {
+            url_host.len() > domain.len() + 1
+                && url_host[url_host.len() - domain.len()..].eq_ignore_ascii_case(domain)
+                && url_host.as_bytes()[url_host.len() - domain.len() - 1] == b'.'
+        } else {
+            url_host.eq_ignore_ascii_case(&self.host)
+        };
+        if !host_matches {
+            return Some(HostMismatch::Host);
+        }
+
+        let url_port = url.port_or_known_default();
+        let port_matches = match self.port {
+            PortPart::Any => true,
+            PortPart::Port(port) => {
+                url_port == Some(port)
+                    || (port == 80
+                        && matches!(url_scheme, "https" | "wss")
+                        && url_port == Some(443))
+            }
+            PortPart::Unspecified => url_port == default_port(url_scheme),
+        };
+        if !port_matches {
+            return Some(HostMismatch::Port);
+        }
+
+        let path_matches = match &self.path {
+            Some(path) if path.ends_with('/') => url.path().starts_with(path.as_str()),
+            Some(path) => url.path() == path,
+            None => true,
+        };
+        (!path_matches).then_some(HostMismatch::Path)
+    }
+}
+
+/// Part of a URL that a [`HostSource`] rejected, from [`HostSource::mismatch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HostMismatch {
+    Scheme,
+    Host,
+    Port,
+    Path,
+}
+
+impl FromStr for HostSource {
+    type Err = crate::error::CspError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            crate::error::CspError::InvalidDirectiveValue(format!("Invalid host source: {value}"))
+        };
+
+        let (scheme, rest) = match value.split_once("://") {
+            Some(("*", rest)) => (SchemePart::Any, rest),
+            Some((scheme, rest)) if is_valid_scheme(scheme) => {
+                (SchemePart::Scheme(scheme.to_ascii_lowercase()), rest)
+            }
+            Some(_) => return Err(invalid()),
+            None => (SchemePart::Unspecified, value),
+        };
+
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], Some(&rest[index..])),
+            None => (rest, None),
+        };
+
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, "*")) => (host, PortPart::Any),
+            Some((host, port)) => (
+                host,
+                PortPart::Port(
+                    port.bytes()
+                        .all(|byte| byte.is_ascii_digit())
+                        .then(|| port.parse().ok())
+                        .flatten()
+                        .ok_or_else(invalid)?,
+                ),
+            ),
+            None => (authority, PortPart::Unspecified),
+        };
+
+        let host_is_valid = host == "*" || {
+            let labels = host.strip_prefix("*.").unwrap_or(host);
+            !labels.is_empty()
+                && labels.split('.').all(|label| {
+                    !label.is_empty()
+                        && label
+                            .bytes()
+                            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-')
+                })
+        };
+
+        let path_is_valid = path.is_none_or(|path| {
+            !path
+                .bytes()
+                .any(|byte| byte.is_ascii_whitespace() || matches!(byte, b';' | b',' | b'\''))
+        });
+
+        if !host_is_valid || !path_is_valid {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            scheme,
+            host: host.to_ascii_lowercase(),
+            port,
+            path: path.map(str::to_owned),
+        })
+    }
+}
+
+impl fmt::Display for HostSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.scheme {
+            SchemePart::Unspecified => {}
+            SchemePart::Any => f.write_str("*://")?,
+            SchemePart::Scheme(scheme) => write!(f, "{scheme}://")?,
+        }
+        f.write_str(&self.host)?;
+        match self.port {
+            PortPart::Unspecified => {}
+            PortPart::Any => f.write_str(":*")?,
+            PortPart::Port(port) => write!(f, ":{port}")?,
+        }
+        if let Some(path) = &self.path {
+            f.write_str(path)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<HostSource> for Source {
+    #[inline]
+    fn from(source: HostSource) -> Self {
+        Source::Host(Cow::Owned(source.to_string()))
+    }
+}
+
+/// Scheme-part matching from CSP Level 3 (§6.7.2.8): `http` also matches
+/// `https`, `ws` also matches `wss`, `http` and `https`, and `wss` also matches
+/// `https`. No other pair matches; in particular `http`/`https` never match
+/// `ws`/`wss`.
+fn scheme_part_matches(expression: &str, url_scheme: &str) -> bool {
+    expression.eq_ignore_ascii_case(url_scheme)
+        || match expression.to_ascii_lowercase().as_str() {
+            "http" => url_scheme == "https",
+            "ws" => matches!(url_scheme, "wss" | "http" | "https"),
+            "wss" => url_scheme == "https",
+            _ => false,
+        }
+}
+
+#[inline]
+fn is_network_scheme(scheme: &str) -> bool {
+    matches!(scheme, "http" | "https" | "ws" | "wss")
+}
+
+#[inline]
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        "ftp" => Some(21),
+        _ => None,
+    }
+}
@@ -0,0 +1,157 @@
+//! Headline numbers of a policy, for logs, dashboards and audits.
+
+use crate::constants::{
+    UNSAFE_EVAL_SOURCE, UNSAFE_HASHES_SOURCE, UNSAFE_INLINE_SOURCE, WASM_UNSAFE_EVAL_SOURCE,
+};
+use crate::core::policy::CspPolicy;
+use crate::core::source::SourceKind;
+use serde::Serialize;
+use std::fmt;
+
+const UNSAFE_KEYWORDS: [&str; 4] = [
+    UNSAFE_INLINE_SOURCE,
+    UNSAFE_EVAL_SOURCE,
+    UNSAFE_HASHES_SOURCE,
+    WASM_UNSAFE_EVAL_SOURCE,
+];
+
+/// An overview of a policy, as returned by [`CspPolicy::summary`].
+///
+/// Source counts include fallback sources, since those are sent in the header
+/// too.
+///
+/// ```rust
+/// use actix_web_csp::core::SourceKind;
+/// use actix_web_csp::{CspPolicyBuilder, Source};
+///
+/// let policy = CspPolicyBuilder::new()
+///     .default_src([Source::Self_])
+///     .style_src([Source::Self_, Source::UnsafeInline])
+///     .img_src([Source::Self_, Source::Scheme("data".into())])
+///     .build_unchecked();
+///
+/// let summary = policy.summary();
+/// assert_eq!(summary.directive_count, 3);
+/// assert_eq!(summary.source_count, 5);
+/// assert_eq!(summary.count(SourceKind::Keyword), 4);
+/// assert_eq!(summary.unsafe_keywords, ["'unsafe-inline'"]);
+/// assert!(!summary.reporting);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct PolicySummary {
+    pub directive_count: usize,
+    pub source_count: usize,
+    pub keywords: usize,
+    pub schemes: usize,
+    pub hosts: usize,
+    pub nonces: usize,
+    pub hashes: usize,
+    /// `'unsafe-*'` keywords anywhere in the policy, each listed once
+    pub unsafe_keywords: Vec<&'static str>,
+    /// Whether violations are reported through `report-uri` or `report-to`
+    pub reporting: bool,
+    pub report_only: bool,
+    /// Size of the serialized header value; `None` if the policy does not compile
+    pub header_bytes: Option<usize>,
+}
+
+impl PolicySummary {
+    /// Number of sources of the given kind.
+    #[inline]
+    pub fn count(&self, kind: SourceKind) -> usize {
+        match kind {
+            SourceKind::Keyword => self.keywords,
+            SourceKind::Scheme => self.schemes,
+            SourceKind::Host => self.hosts,
+            SourceKind::Nonce => self.nonces,
+            SourceKind::Hash => self.hashes,
+        }
+    }
+
+    /// Returns `true` if the policy uses any `'unsafe-*'` keyword.
+    #[inline]
+    pub fn has_unsafe_keywords(&self) -> bool {
+        !self.unsafe_keywords.is_empty()
+    }
+}
+
+impl From<&CspPolicy> for PolicySummary {
+    fn from(policy: &CspPolicy) -> Self {
+        let mut summary = PolicySummary {
+            directive_count: 0,
+            source_count: 0,
+            keywords: 0,
+            schemes: 0,
+            hosts: 0,
+            nonces: 0,
+            hashes: 0,
+            unsafe_keywords: Vec::new(),
+            reporting: policy.report_uri().is_some() || policy.report_to().is_some(),
+            report_only: policy.is_report_only(),
+            header_bytes: policy
+                .compile()
+                .ok()
+                .map(|compiled| compiled.header_value().len()),
+        };
+
+        for directive in policy.directives() {
+            summary.directive_count += 1;
+            for source in directive
+                .sources()
+                .iter()
+                .chain(directive.fallback_sources().into_iter().flatten())
+            {
+                summary.source_count += 1;
+                match source.kind() {
+                    SourceKind::Keyword => summary.keywords += 1,
+                    SourceKind::Scheme => summary.schemes += 1,
+                    SourceKind::Host => summary.hosts += 1,
+                    SourceKind::Nonce => summary.nonces += 1,
+                    SourceKind::Hash => summary.hashes += 1,
+                }
+                if let Some(keyword) = source
+                    .as_static_str()
+                    .filter(|keyword| UNSAFE_KEYWORDS.contains(keyword))
+                {
+                    if !summary.unsafe_keywords.contains(&keyword) {
+                        summary.unsafe_keywords.push(keyword);
+                    }
+                }
+            }
+        }
+
+        summary
+    }
+}
+
+impl fmt::Display for PolicySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "CSP policy: {} directive{}{}, {} source{}, ",
+            self.directive_count,
+            if self.directive_count == 1 { "" } else { "s" },
+            if self.report_only {
+                " (report-only)"
+            } else {
+                ""
+            },
+            self.source_count,
+            if self.source_count == 1 { "" } else { "s" }
+        )?;
+        match self.header_bytes {
+            Some(bytes) => write!(f, "{bytes} byte header")?,
+            None => f.write_str("does not compile")?,
+        }
+        f.write_str(if self.reporting {
+            ", reporting configured"
+        } else {
+            ", no reporting configured"
+        })?;
+        if self.has_unsafe_keywords() {
+            write!(f, ", unsafe keywords: {}", self.unsafe_keywords.join(" "))?;
+        }
+        Ok(())
+    }
+}
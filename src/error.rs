@@ -40,6 +40,13 @@ pub enum CspError {
     #[error("Config error: {0}")]
     ConfigError(String),
 
+    #[error("Parse error in directive {directive_index} at byte {offset}: {message}")]
+    ParseError {
+        directive_index: usize,
+        offset: usize,
+        message: String,
+    },
+
     #[error("I/O error: {0}")]
     IoError(#[from] std::io::Error),
 }
@@ -54,7 +61,8 @@ impl ResponseError for CspError {
             | Self::InvalidReportUri(_)
             | Self::ValidationError(_)
             | Self::VerificationError(_)
-            | Self::ConfigError(_) => StatusCode::BAD_REQUEST,
+            | Self::ConfigError(_)
+            | Self::ParseError { .. } => StatusCode::BAD_REQUEST,
 
             Self::CryptoError(_)
             | Self::SerializationError(_)
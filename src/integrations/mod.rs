@@ -0,0 +1,5 @@
+//! Ready-made source sets for third-party services.
+
+pub mod services;
+
+pub use services::Service;
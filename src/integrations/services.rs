@@ -0,0 +1,275 @@
+//! A catalog of the sources common third-party services need.
+//!
+//! Lists follow each vendor's published CSP guidance for the default (US)
+//! region and cover what the standard embed loads; optional products such as
+//! regional data centres or preview modes need their hosts added by hand.
+
+use crate::constants::{
+    CONNECT_SRC, DEFAULT_SRC, FONT_SRC, FORM_ACTION, FRAME_SRC, IMG_SRC, MEDIA_SRC, SCRIPT_SRC,
+    STYLE_SRC,
+};
+use crate::core::directives::Directive;
+use crate::core::policy::CspPolicy;
+use crate::core::source::Source;
+use crate::error::CspError;
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+/// Sources to add, by directive.
+pub type ServiceSources = &'static [(&'static str, &'static [Source])];
+
+const fn host(host: &'static str) -> Source {
+    Source::Host(Cow::Borrowed(host))
+}
+
+const fn scheme(scheme: &'static str) -> Source {
+    Source::Scheme(Cow::Borrowed(scheme))
+}
+
+const GOOGLE_ANALYTICS: ServiceSources = &[
+    (SCRIPT_SRC, &[host("https://*.googletagmanager.com")]),
+    (
+        IMG_SRC,
+        &[
+            host("https://*.google-analytics.com"),
+            host("https://*.googletagmanager.com"),
+        ],
+    ),
+    (
+        CONNECT_SRC,
+        &[
+            host("https://*.google-analytics.com"),
+            host("https://*.analytics.google.com"),
+            host("https://*.googletagmanager.com"),
+        ],
+    ),
+];
+
+const GOOGLE_TAG_MANAGER: ServiceSources = &[
+    (SCRIPT_SRC, &[host("https://www.googletagmanager.com")]),
+    (IMG_SRC, &[host("https://www.googletagmanager.com")]),
+];
+
+const STRIPE: ServiceSources = &[
+    (
+        SCRIPT_SRC,
+        &[
+            host("https://js.stripe.com"),
+            host("https://*.js.stripe.com"),
+        ],
+    ),
+    (
+        FRAME_SRC,
+        &[
+            host("https://js.stripe.com"),
+            host("https://*.js.stripe.com"),
+            host("https://hooks.stripe.com"),
+        ],
+    ),
+    (CONNECT_SRC, &[host("https://api.stripe.com")]),
+];
+
+const INTERCOM: ServiceSources = &[
+    (
+        SCRIPT_SRC,
+        &[
+            host("https://app.intercom.io"),
+            host("https://widget.intercom.io"),
+            host("https://js.intercomcdn.com"),
+        ],
+    ),
+    (STYLE_SRC, &[Source::UnsafeInline]),
+    (
+        CONNECT_SRC,
+        &[
+            host("https://via.intercom.io"),
+            host("https://api.intercom.io"),
+            host("https://api-iam.intercom.io"),
+            host("https://api-ping.intercom.io"),
+            host("https://nexus-websocket-a.intercom.io"),
+            host("wss://nexus-websocket-a.intercom.io"),
+            host("https://nexus-websocket-b.intercom.io"),
+            host("wss://nexus-websocket-b.intercom.io"),
+            host("https://uploads.intercomcdn.com"),
+            host("https://uploads.intercomusercontent.com"),
+        ],
+    ),
+    (
+        IMG_SRC,
+        &[
+            scheme("blob"),
+            scheme("data"),
+            host("https://js.intercomcdn.com"),
+            host("https://static.intercomassets.com"),
+            host("https://downloads.intercomcdn.com"),
+            host("https://uploads.intercomusercontent.com"),
+            host("https://gifs.intercomcdn.com"),
+            host("https://video-messages.intercomcdn.com"),
+            host("https://messenger-apps.intercom.io"),
+        ],
+    ),
+    (
+        MEDIA_SRC,
+        &[
+            host("https://js.intercomcdn.com"),
+            host("https://downloads.intercomcdn.com"),
+        ],
+    ),
+    (
+        FONT_SRC,
+        &[
+            host("https://js.intercomcdn.com"),
+            host("https://fonts.intercomcdn.com"),
+        ],
+    ),
+    (
+        FRAME_SRC,
+        &[
+            host("https://intercom-sheets.com"),
+            host("https://www.intercom-reporting.com"),
+        ],
+    ),
+    (
+        FORM_ACTION,
+        &[
+            host("https://intercom.help"),
+            host("https://api-iam.intercom.io"),
+        ],
+    ),
+];
+
+const YOUTUBE: ServiceSources = &[
+    (
+        FRAME_SRC,
+        &[
+            host("https://www.youtube.com"),
+            host("https://www.youtube-nocookie.com"),
+        ],
+    ),
+    (IMG_SRC, &[host("https://i.ytimg.com")]),
+];
+
+const GOOGLE_FONTS: ServiceSources = &[
+    (STYLE_SRC, &[host("https://fonts.googleapis.com")]),
+    (FONT_SRC, &[host("https://fonts.gstatic.com")]),
+];
+
+/// A third-party service whose sources can be added to a policy with
+/// [`CspPolicyBuilder::apply_service`] or [`CspPolicy::apply_service`].
+///
+/// Sources go into the directives the service needs. A fetch directive the
+/// policy lacks is started from the `default-src` sources, so what the
+/// fallback allowed keeps loading; a directive that would not restrict
+/// anything, because neither it nor `default-src` is set, is left out.
+///
+/// Inline snippets, such as the Tag Manager loader, still need a nonce or a
+/// hash. [`Service::Intercom`] needs `'unsafe-inline'` in `style-src`, which
+/// [`PolicyAuditor::relaxations`] and [`CspPolicy::summary`] will point out.
+///
+/// ```rust
+/// use actix_web_csp::integrations::Service;
+/// use actix_web_csp::{CspPolicyBuilder, Source};
+///
+/// let policy = CspPolicyBuilder::new()
+///     .default_src([Source::Self_])
+///     .apply_service(Service::Stripe)
+///     .apply_service(Service::GoogleFonts)
+///     .build()?;
+///
+/// assert_eq!(
+///     policy.get_directive("connect-src").unwrap().to_string(),
+///     "connect-src 'self' https://api.stripe.com"
+/// );
+/// # Ok::<(), actix_web_csp::CspError>(())
+/// ```
+///
+/// [`CspPolicyBuilder::apply_service`]: crate::core::CspPolicyBuilder::apply_service
+/// [`PolicyAuditor::relaxations`]: crate::security::PolicyAuditor::relaxations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Service {
+    /// Google Analytics 4 through `gtag.js`
+    GoogleAnalytics,
+    GoogleTagManager,
+    /// Stripe.js, Elements and Checkout
+    Stripe,
+    /// The Intercom Messenger
+    Intercom,
+    /// Embedded YouTube players
+    YouTube,
+    GoogleFonts,
+}
+
+impl Service {
+    pub const ALL: [Service; 6] = [
+        Self::GoogleAnalytics,
+        Self::GoogleTagManager,
+        Self::Stripe,
+        Self::Intercom,
+        Self::YouTube,
+        Self::GoogleFonts,
+    ];
+
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::GoogleAnalytics => "google-analytics",
+            Self::GoogleTagManager => "google-tag-manager",
+            Self::Stripe => "stripe",
+            Self::Intercom => "intercom",
+            Self::YouTube => "youtube",
+            Self::GoogleFonts => "google-fonts",
+        }
+    }
+
+    /// The sources the service needs, by directive.
+    #[inline]
+    pub const fn sources(self) -> ServiceSources {
+        match self {
+            Self::GoogleAnalytics => GOOGLE_ANALYTICS,
+            Self::GoogleTagManager => GOOGLE_TAG_MANAGER,
+            Self::Stripe => STRIPE,
+            Self::Intercom => INTERCOM,
+            Self::YouTube => YOUTUBE,
+            Self::GoogleFonts => GOOGLE_FONTS,
+        }
+    }
+
+    /// Adds the service's sources to `policy`.
+    pub(crate) fn apply(self, policy: &mut CspPolicy) {
+        for (name, sources) in self.sources() {
+            let mut directive = match policy.get_directive(name) {
+                Some(directive) => directive.clone(),
+                None => match policy.get_directive(DEFAULT_SRC) {
+                    Some(default_src) if name.ends_with("-src") => {
+                        let mut directive = Directive::new(*name);
+                        directive.add_sources(default_src.sources().iter().cloned());
+                        directive
+                    }
+                    _ => continue,
+                },
+            };
+            directive.add_sources(sources.iter().cloned());
+            policy.add_directive(directive);
+        }
+    }
+}
+
+impl fmt::Display for Service {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Service {
+    type Err = CspError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim().to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|service| service.name() == value)
+            .ok_or_else(|| CspError::ConfigError(format!("Unknown service '{value}'")))
+    }
+}
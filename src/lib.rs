@@ -77,7 +77,35 @@
 //! - `stats`: runtime counters and lightweight metrics
 //! - `reporting`: CSP report parsing and reporting middleware helpers
 //! - `verify`: [`PolicyVerifier`] support for URI, nonce, and hash checks
+//! - `page-audit`: `security::page_audit`, which checks rendered HTML against a policy,
+//!   and `security::PolicyGenerator`, which derives a policy from existing pages;
+//!   pulls in `html5ever`
+//! - `crawl`: lets `PolicyGenerator` fetch pages over HTTP
 //! - `extended-validation`: stricter semantic validation for sources and reporting
+//! - `regex`: pattern redaction in `ReportScrubber`
+//! - `user-agent`: browser family and version of violation reports, read from
+//!   the reporting request's `User-Agent`
+//! - `arbitrary`: `arbitrary::Arbitrary` for policies, directives and sources,
+//!   for fuzzing [`CspPolicy::roundtrip_check`]
+//! - `session`: one nonce per `actix-session` session with
+//!   `NonceScope::Session`
+//! - `bench-support`: `bench_support`, stable entry points for comparing
+//!   performance across versions of the crate
+//! - `test-utils`: the `test` module and `assert_csp_contains!`, helpers for
+//!   application tests; enable it in `dev-dependencies`
+//! - `tracing`: emit the crate's log messages as `tracing` events instead of
+//!   through `log`; see [`logging`]
+//! - `ring`, `sha2`: hash backend; `ring` is used when enabled, the pure-Rust `sha2`
+//!   otherwise, and one of them is required
+//! - `simd`: AVX2 fast paths for hash comparison and buffer copies on x86-64
+//! - `lru`, `dashmap`: back the nonce, policy and verification caches and the
+//!   update-listener registry with those crates; without them small built-in
+//!   fallbacks are used
+//!
+//! For a static header middleware, `default-features = false, features = ["minimal"]`
+//! drops everything optional and hashes with `sha2`. Statistics then read as zero, violation reports are
+//! not parsed, [`PolicyVerifier`] checks return an error, and caches use the
+//! fallbacks, which promote entries in linear rather than constant time.
 //!
 //! # Walkthrough Examples
 //!
@@ -100,30 +128,41 @@
 //! verification, and JSON interop. See `BENCHMARKS.md` in the repository root for
 //! commands, baselines, and profiling workflow.
 
+#[cfg(feature = "bench-support")]
+pub mod bench_support;
+mod collections;
 pub mod constants;
 pub mod core;
 pub mod error;
+pub mod integrations;
+pub mod logging;
 pub mod middleware;
 pub mod monitoring;
 pub mod prelude;
 pub mod presets;
 pub mod security;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test;
 pub mod utils;
 
 // Re-export commonly used types for convenience
 pub use core::{
     CompiledCspPolicy, CspConfig, CspConfigBuilder, CspPolicy, CspPolicyBuilder, DirectiveDocument,
-    PolicyDocument, Source,
+    PolicyDocument, PolicyRoutes, Source,
 };
 pub use error::CspError;
 #[allow(deprecated)]
 pub use middleware::{
     configure_csp, configure_csp_with_reporting, csp_middleware, csp_middleware_with_nonce,
-    csp_middleware_with_request_nonce, csp_with_reporting, CspExtensions, CspMiddleware,
-    CspReportingMiddleware,
+    csp_middleware_with_request_nonce, csp_with_configured_reporting, csp_with_reporting,
+    CspDisabled, CspExtensions, CspHandle, CspMiddleware, CspReportingMiddleware, CspResponseExt,
+    HtmlNonceRewrite,
 };
 pub use monitoring::{
     AdaptiveCache, CspStats, CspViolationReport, PerformanceMetrics, PerformanceTimer,
 };
 pub use presets::{preset_policy, CspPreset};
-pub use security::{HashAlgorithm, HashGenerator, NonceGenerator, PolicyVerifier, RequestNonce};
+pub use security::{
+    CspNonce, HashAlgorithm, HashGenerator, NonceGenerator, NonceScope, PolicyVerifier, RequestId,
+    RequestNonce,
+};
@@ -0,0 +1,155 @@
+//! Where the crate's log output goes.
+//!
+//! Messages are written through the `log` crate, or through `tracing` with the
+//! `tracing` feature. Everything is logged under [`TARGET`], except messages
+//! about individual violation reports (oversized or malformed bodies, banned
+//! clients, failing handlers), which use [`REPORT_TARGET`]. A public reporting
+//! endpoint can produce a lot of those, so their verbosity can also be capped
+//! here without touching the application's logger configuration:
+//!
+//! ```rust
+//! use actix_web_csp::logging;
+//! use log::LevelFilter;
+//!
+//! // Keep warnings from the rest of the crate, drop per-report debug noise
+//! logging::set_report_level(LevelFilter::Warn);
+//! assert_eq!(logging::report_level(), LevelFilter::Warn);
+//! # logging::set_report_level(LevelFilter::Trace);
+//! ```
+//!
+//! With the `log` backend, [`set_target`] files both under a prefix of the
+//! application's choosing instead.
+
+use log::LevelFilter;
+#[cfg(not(feature = "tracing"))]
+use parking_lot::RwLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Target of the crate's general log messages.
+pub const TARGET: &str = "actix_web_csp";
+
+/// Target of messages about individual violation reports.
+pub const REPORT_TARGET: &str = "actix_web_csp::report";
+
+const LEVELS: [LevelFilter; 6] = [
+    LevelFilter::Off,
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+static REPORT_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Trace as usize);
+
+/// Replacements for [`TARGET`] and [`REPORT_TARGET`] set with [`set_target`]
+#[cfg(not(feature = "tracing"))]
+static CUSTOM_TARGETS: RwLock<Option<(Box<str>, Box<str>)>> = RwLock::new(None);
+
+/// Caps the verbosity of messages logged under [`REPORT_TARGET`].
+///
+/// The logger's own filters still apply; the default, `Trace`, leaves them
+/// as the only filter.
+#[inline]
+pub fn set_report_level(level: LevelFilter) {
+    REPORT_LEVEL.store(level as usize, Ordering::Relaxed);
+}
+
+/// Returns the cap set by [`set_report_level`].
+#[inline]
+pub fn report_level() -> LevelFilter {
+    LEVELS[REPORT_LEVEL.load(Ordering::Relaxed)]
+}
+
+/// Logs general messages under `target` and report messages under
+/// `{target}::report` instead of [`TARGET`] and [`REPORT_TARGET`].
+///
+/// Only the `log` backend can change targets at runtime; with the `tracing`
+/// feature this does nothing, and subscribers should filter on the constants.
+pub fn set_target(target: &str) {
+    #[cfg(not(feature = "tracing"))]
+    {
+        *CUSTOM_TARGETS.write() = Some((target.into(), format!("{target}::report").into()));
+    }
+    #[cfg(feature = "tracing")]
+    let _ = target;
+}
+
+/// Returns the target general messages are logged under.
+pub fn target() -> String {
+    #[cfg(not(feature = "tracing"))]
+    if let Some((target, _)) = &*CUSTOM_TARGETS.read() {
+        return target.to_string();
+    }
+    TARGET.to_string()
+}
+
+/// Returns the target report messages are logged under.
+pub fn report_target() -> String {
+    #[cfg(not(feature = "tracing"))]
+    if let Some((_, target)) = &*CUSTOM_TARGETS.read() {
+        return target.to_string();
+    }
+    REPORT_TARGET.to_string()
+}
+
+/// Calls `log` with `target`, or with its replacement from [`set_target`].
+#[cfg(not(feature = "tracing"))]
+#[inline]
+pub(crate) fn with_target(target: &'static str, log: impl FnOnce(&str)) {
+    match (&*CUSTOM_TARGETS.read(), target) {
+        (Some((custom, _)), TARGET) | (Some((_, custom)), REPORT_TARGET) => log(custom),
+        _ => log(target),
+    }
+}
+
+/// Logs a message under [`TARGET`], or under [`REPORT_TARGET`] when prefixed
+/// with `report:`, at one of the `log::Level` names.
+///
+/// ```text
+/// csp_log!(Warn, "Unknown CSP directive '{name}'");
+/// csp_log!(report: Debug, "Malformed CSP violation report: {reason}");
+/// ```
+macro_rules! csp_log {
+    (report: $level:ident, $($arg:tt)+) => {
+        if ::log::Level::$level <= $crate::logging::report_level() {
+            $crate::logging::emit!($crate::logging::REPORT_TARGET, $level, $($arg)+)
+        }
+    };
+    ($level:ident, $($arg:tt)+) => {
+        $crate::logging::emit!($crate::logging::TARGET, $level, $($arg)+)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! emit {
+    ($target:expr, $level:ident, $($arg:tt)+) => {
+        if ::log::Level::$level <= ::log::max_level() {
+            $crate::logging::with_target($target, |target| {
+                ::log::log!(target: target, ::log::Level::$level, $($arg)+)
+            })
+        }
+    };
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! emit {
+    ($target:expr, Error, $($arg:tt)+) => {
+        ::tracing::error!(target: $target, $($arg)+)
+    };
+    ($target:expr, Warn, $($arg:tt)+) => {
+        ::tracing::warn!(target: $target, $($arg)+)
+    };
+    ($target:expr, Info, $($arg:tt)+) => {
+        ::tracing::info!(target: $target, $($arg)+)
+    };
+    ($target:expr, Debug, $($arg:tt)+) => {
+        ::tracing::debug!(target: $target, $($arg)+)
+    };
+    ($target:expr, Trace, $($arg:tt)+) => {
+        ::tracing::trace!(target: $target, $($arg)+)
+    };
+}
+
+pub(crate) use csp_log;
+pub(crate) use emit;
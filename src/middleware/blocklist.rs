@@ -0,0 +1,194 @@
+//! Keeps abusive clients away from the violation reporting endpoint.
+
+use crate::collections::LruCache;
+use crate::error::CspError;
+use crate::logging::csp_log;
+use crate::middleware::proxy::{networks_contain, parse_network};
+use parking_lot::Mutex;
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
+
+/// Clients whose submission counts are tracked at once; the least recent are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 4096;
+
+/// Why a report request was turned away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Rejection {
+    /// The client address or origin is on the block-list
+    Blocked,
+    /// The client is temporarily banned for sending too many reports
+    Banned,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AutoBan {
+    max_reports: u32,
+    window: Duration,
+    duration: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ClientState {
+    window_start: Instant,
+    reports: u32,
+    banned_until: Option<Instant>,
+}
+
+/// Address and origin block-list for [`CspReportingMiddleware`], with optional
+/// temporary bans for clients that flood the endpoint.
+///
+/// Blocked and banned requests are answered before their body is read and never
+/// reach the violation handler. Origins are compared with the request's `Origin`
+/// header; requests without one are subject to the address rules only.
+///
+/// ```rust
+/// use actix_web_csp::middleware::{CspReportingMiddleware, ReportBlocklist};
+/// use std::time::Duration;
+///
+/// let blocklist = ReportBlocklist::new()
+///     .with_blocked_network("198.51.100.0/24")?
+///     .with_blocked_origin("https://spam.example")
+///     .with_auto_ban(100, Duration::from_secs(60), Duration::from_secs(600));
+///
+/// let reporting = CspReportingMiddleware::new(|_| {}).with_blocklist(blocklist);
+/// # Ok::<(), actix_web_csp::CspError>(())
+/// ```
+///
+/// [`CspReportingMiddleware`]: crate::middleware::CspReportingMiddleware
+#[derive(Debug)]
+pub struct ReportBlocklist {
+    networks: Vec<(IpAddr, u8)>,
+    origins: Vec<String>,
+    auto_ban: Option<AutoBan>,
+    clients: Mutex<LruCache<IpAddr, ClientState>>,
+}
+
+impl Default for ReportBlocklist {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReportBlocklist {
+    pub fn new() -> Self {
+        Self {
+            networks: Vec::new(),
+            origins: Vec::new(),
+            auto_ban: None,
+            clients: Mutex::new(LruCache::new(
+                NonZeroUsize::new(MAX_TRACKED_CLIENTS).unwrap(),
+            )),
+        }
+    }
+
+    /// Blocks a CIDR block such as `198.51.100.0/24`; a bare address blocks that host.
+    pub fn with_blocked_network(mut self, network: &str) -> Result<Self, CspError> {
+        let network = parse_network(network)
+            .ok_or_else(|| CspError::ConfigError(format!("Invalid blocked network: {network}")))?;
+        self.networks.push(network);
+        Ok(self)
+    }
+
+    /// Blocks reports sent with `Origin: origin`, e.g. `https://spam.example`.
+    pub fn with_blocked_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origins.push(normalize_origin(&origin.into()));
+        self
+    }
+
+    /// Bans a client for `duration` once it sends more than `max_reports` within `window`.
+    #[inline]
+    pub fn with_auto_ban(mut self, max_reports: u32, window: Duration, duration: Duration) -> Self {
+        self.auto_ban = Some(AutoBan {
+            max_reports,
+            window,
+            duration,
+        });
+        self
+    }
+
+    /// Returns `true` if `ip` is on the block-list.
+    #[inline]
+    pub fn is_blocked(&self, ip: IpAddr) -> bool {
+        networks_contain(&self.networks, ip)
+    }
+
+    /// Returns `true` if `ip` is currently banned for flooding.
+    pub fn is_banned(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        self.clients
+            .lock()
+            .get(&ip)
+            .and_then(|state| state.banned_until)
+            .is_some_and(|until| until > now)
+    }
+
+    /// Lifts a temporary ban on `ip` and forgets its recent submissions.
+    pub fn unban(&self, ip: IpAddr) {
+        self.clients.lock().pop(&ip);
+    }
+
+    /// Checks a report request and counts it against the client's allowance.
+    #[cfg_attr(not(feature = "reporting"), allow(dead_code))]
+    pub(crate) fn check(
+        &self,
+        client_ip: Option<IpAddr>,
+        origin: Option<&str>,
+    ) -> Result<(), Rejection> {
+        if origin.is_some_and(|origin| self.origins.contains(&normalize_origin(origin))) {
+            return Err(Rejection::Blocked);
+        }
+        let Some(ip) = client_ip else {
+            return Ok(());
+        };
+        if self.is_blocked(ip) {
+            return Err(Rejection::Blocked);
+        }
+        match self.auto_ban {
+            Some(auto_ban) => self.count(ip, auto_ban),
+            None => Ok(()),
+        }
+    }
+
+    fn count(&self, ip: IpAddr, auto_ban: AutoBan) -> Result<(), Rejection> {
+        let now = Instant::now();
+        let mut clients = self.clients.lock();
+        let mut state = clients.get(&ip).copied().unwrap_or(ClientState {
+            window_start: now,
+            reports: 0,
+            banned_until: None,
+        });
+
+        if state.banned_until.is_some_and(|until| until > now) {
+            return Err(Rejection::Banned);
+        }
+        if state.banned_until.is_some() || now.duration_since(state.window_start) >= auto_ban.window
+        {
+            state = ClientState {
+                window_start: now,
+                reports: 0,
+                banned_until: None,
+            };
+        }
+
+        state.reports = state.reports.saturating_add(1);
+        let verdict = if state.reports > auto_ban.max_reports {
+            csp_log!(
+                report: Warn,
+                "CSP reporting: banning {ip} for {:?} after {} reports",
+                auto_ban.duration,
+                state.reports
+            );
+            state.banned_until = Some(now + auto_ban.duration);
+            Err(Rejection::Banned)
+        } else {
+            Ok(())
+        };
+        clients.put(ip, state);
+        verdict
+    }
+}
+
+fn normalize_origin(origin: &str) -> String {
+    origin.trim().trim_end_matches('/').to_ascii_lowercase()
+}
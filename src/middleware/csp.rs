@@ -1,21 +1,71 @@
-use crate::constants::{HEADER_CSP, HEADER_CSP_REPORT_ONLY};
-use crate::core::config::CspConfig;
+use crate::constants::{
+    FALLBACK_POLICY, FRAME_ANCESTORS, HEADER_CSP, HEADER_CSP_REPORT_ONLY,
+    HEADER_REPORTING_ENDPOINTS, NONE_SOURCE, SELF_SOURCE, UPGRADE_INSECURE_REQUESTS,
+};
+use crate::core::config::{CspConfig, HeaderFailureMode, PolicyCacheKey};
+use crate::core::policy::{CompiledCspPolicy, CspPolicy, SelectedPolicy};
+use crate::core::routes::PolicyRoutes;
+use crate::error::CspError;
+use crate::logging::csp_log;
+use crate::middleware::extensions::RequestCspContext;
+use crate::middleware::proxy::{forwarded_proto, ForwardedInfo};
+use crate::middleware::response::{CspDisabled, HtmlNonceRewrite, ResponseCspOverrides};
+use crate::middleware::rewrite::{
+    drop_html_preconditions, drop_representation_headers, NonceRewriteBody,
+};
+use crate::middleware::vhost::VhostPolicies;
 use crate::monitoring::perf::PerformanceTimer;
-use crate::security::nonce::RequestNonce;
+use crate::security::audit::PolicyAuditor;
+use crate::security::nonce::{CspNonce, RequestNonce};
 use actix_web::{
+    body::{EitherBody, MessageBody},
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    http::header::{HeaderName, HeaderValue},
+    error::InternalError,
+    http::{
+        header::{
+            HeaderMap, HeaderName, HeaderValue, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE,
+            HOST, STRICT_TRANSPORT_SECURITY, X_FRAME_OPTIONS,
+        },
+        StatusCode,
+    },
     web::Data,
-    Error, HttpMessage,
+    Error, HttpMessage, HttpRequest, HttpResponse,
 };
 use futures::future::{ready, LocalBoxFuture, Ready};
-use std::borrow::Cow;
-use std::{rc::Rc, sync::Arc};
-use uuid::Uuid;
+use std::{
+    rc::Rc,
+    sync::{Arc, Once},
+};
 
 #[derive(Clone)]
 pub struct CspMiddleware {
     config: Arc<CspConfig>,
+    vhosts: Option<Arc<VhostPolicies>>,
+    routes: Option<Arc<PolicyRoutes>>,
+    additional_policies: Arc<[CompiledCspPolicy]>,
+    startup_report: Option<Arc<StartupReport>>,
+}
+
+/// Logs a [`PolicyAudit`] of the policy the first time the middleware is
+/// instantiated.
+///
+/// [`PolicyAudit`]: crate::security::PolicyAudit
+struct StartupReport {
+    auditor: PolicyAuditor,
+    logged: Once,
+}
+
+impl StartupReport {
+    fn log(&self, config: &CspConfig) {
+        self.logged.call_once(|| {
+            let audit = self.auditor.audit(&config.policy());
+            if audit.has_warnings() {
+                csp_log!(Warn, "{audit}");
+            } else {
+                csp_log!(Info, "{audit}");
+            }
+        });
+    }
 }
 
 impl CspMiddleware {
@@ -23,6 +73,10 @@ impl CspMiddleware {
     pub fn new(config: CspConfig) -> Self {
         Self {
             config: Arc::new(config),
+            vhosts: None,
+            routes: None,
+            additional_policies: Arc::new([]),
+            startup_report: None,
         }
     }
 
@@ -30,24 +84,141 @@ impl CspMiddleware {
     pub fn config(&self) -> Arc<CspConfig> {
         self.config.clone()
     }
+
+    /// Selects a policy per request based on the `Host` authority.
+    ///
+    /// Hosts are matched case-insensitively; a key with a port only matches that
+    /// port, while a bare host matches any port. Requests for unknown hosts fall
+    /// back to the policy held by the middleware's [`CspConfig`]. Each vhost policy
+    /// is compiled once here, so the per-request cost is a map lookup.
+    ///
+    /// ```rust
+    /// use actix_web_csp::{csp_middleware, CspPolicyBuilder, Source};
+    /// use std::collections::HashMap;
+    ///
+    /// let fallback = CspPolicyBuilder::new().default_src([Source::Self_]).build()?;
+    /// let tenant = CspPolicyBuilder::new()
+    ///     .default_src([Source::Self_])
+    ///     .script_src([Source::Self_, Source::Host("cdn.tenant-a.com".into())])
+    ///     .build()?;
+    ///
+    /// let _middleware = csp_middleware(fallback)
+    ///     .with_vhost_policies(HashMap::from([("tenant-a.com", tenant)]));
+    /// # Ok::<(), actix_web_csp::CspError>(())
+    /// ```
+    pub fn with_vhost_policies<I, K>(mut self, policies: I) -> Self
+    where
+        I: IntoIterator<Item = (K, CspPolicy)>,
+        K: Into<String>,
+    {
+        let vhosts = VhostPolicies::new(policies);
+        self.vhosts = (!vhosts.is_empty()).then(|| Arc::new(vhosts));
+        self
+    }
+
+    /// Selects a policy per request by path prefix, see [`PolicyRoutes`].
+    ///
+    /// A policy matched by [`with_vhost_policies`](Self::with_vhost_policies)
+    /// takes precedence; paths outside every route without a default fall back
+    /// to the policy held by the middleware's [`CspConfig`].
+    pub fn with_policy_routes(mut self, routes: PolicyRoutes) -> Self {
+        self.routes = (!routes.is_empty()).then(|| Arc::new(routes));
+        self
+    }
+
+    /// Sends `policy` as an extra header alongside the main one.
+    ///
+    /// Browsers enforce every CSP header they receive, so a resource loads only if
+    /// all policies allow it. This layers an organisation-wide baseline under the
+    /// application's own policy without merging the two. The request nonce is added
+    /// to the extra policy's nonce-aware directives too, and report-only policies
+    /// go out as `Content-Security-Policy-Report-Only`. A policy that cannot be
+    /// serialized is logged and skipped.
+    ///
+    /// ```rust
+    /// use actix_web_csp::{csp_middleware, CspPolicyBuilder, Source};
+    ///
+    /// let baseline = CspPolicyBuilder::new()
+    ///     .object_src([Source::None])
+    ///     .base_uri([Source::Self_])
+    ///     .frame_ancestors([Source::None])
+    ///     .build()?;
+    /// let app = CspPolicyBuilder::new()
+    ///     .default_src([Source::Self_])
+    ///     .script_src([Source::Self_, Source::Host("cdn.example.com".into())])
+    ///     .build()?;
+    ///
+    /// let _middleware = csp_middleware(app).with_additional_policy(baseline);
+    /// # Ok::<(), actix_web_csp::CspError>(())
+    /// ```
+    pub fn with_additional_policy(mut self, policy: CspPolicy) -> Self {
+        match policy.compile() {
+            Ok(compiled) => {
+                self.additional_policies = self
+                    .additional_policies
+                    .iter()
+                    .cloned()
+                    .chain(std::iter::once(compiled))
+                    .collect();
+            }
+            Err(error) => csp_log!(Warn, "Skipping additional CSP policy '{policy}': {error}"),
+        }
+        self
+    }
+
+    /// Logs a one-time [`PolicyAudit`] of the policy when the application
+    /// starts: the numbers from [`CspPolicy::summary`], such as the directive
+    /// count, `'unsafe-*'` keywords and header size, and what `auditor` finds.
+    ///
+    /// The audit is logged once, when the first worker instantiates the
+    /// middleware, as a warning if anything in it needs attention and at info
+    /// level otherwise. Use [`PolicyAuditor::audit`] to get it as a value.
+    ///
+    /// [`PolicyAudit`]: crate::security::PolicyAudit
+    ///
+    /// ```rust
+    /// use actix_web_csp::security::PolicyAuditor;
+    /// use actix_web_csp::{csp_middleware, CspPolicyBuilder, Source};
+    ///
+    /// let policy = CspPolicyBuilder::new()
+    ///     .default_src([Source::Self_])
+    ///     .report_uri("/csp-report")
+    ///     .build()?;
+    ///
+    /// let _middleware = csp_middleware(policy).with_startup_report(PolicyAuditor::new());
+    /// # Ok::<(), actix_web_csp::CspError>(())
+    /// ```
+    pub fn with_startup_report(mut self, auditor: PolicyAuditor) -> Self {
+        self.startup_report = Some(Arc::new(StartupReport {
+            auditor,
+            logged: Once::new(),
+        }));
+        self
+    }
 }
 
 impl<S, B> Transform<S, ServiceRequest> for CspMiddleware
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
-    B: 'static,
+    B: MessageBody + 'static,
 {
-    type Response = ServiceResponse<B>;
+    type Response = ServiceResponse<EitherBody<B, NonceRewriteBody<B>>>;
     type Error = Error;
     type Transform = CspMiddlewareService<S>;
     type InitError = ();
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
+        if let Some(startup_report) = &self.startup_report {
+            startup_report.log(&self.config);
+        }
         ready(Ok(CspMiddlewareService {
             service: Rc::new(service),
             config: self.config.clone(),
+            vhosts: self.vhosts.clone(),
+            routes: self.routes.clone(),
+            additional_policies: self.additional_policies.clone(),
         }))
     }
 }
@@ -55,136 +226,587 @@ where
 pub struct CspMiddlewareService<S> {
     service: Rc<S>,
     config: Arc<CspConfig>,
+    vhosts: Option<Arc<VhostPolicies>>,
+    routes: Option<Arc<PolicyRoutes>>,
+    additional_policies: Arc<[CompiledCspPolicy]>,
 }
 
 impl<S, B> Service<ServiceRequest> for CspMiddlewareService<S>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
-    B: 'static,
+    B: MessageBody + 'static,
 {
-    type Response = ServiceResponse<B>;
+    type Response = ServiceResponse<EitherBody<B, NonceRewriteBody<B>>>;
     type Error = Error;
     type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
 
     forward_ready!(service);
 
-    fn call(&self, req: ServiceRequest) -> Self::Future {
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
         let service = self.service.clone();
         let config = self.config.clone();
+        let vhost = self
+            .vhosts
+            .as_ref()
+            .and_then(|vhosts| vhosts.resolve(&req))
+            .or_else(|| self.routes.as_ref().and_then(|routes| routes.resolve(&req)));
+        let additional_policies = self.additional_policies.clone();
 
         Box::pin(async move {
-            let request_id = Uuid::new_v4()
-                .hyphenated()
-                .encode_lower(&mut Uuid::encode_buffer())
-                .to_owned();
-
-            req.extensions_mut()
-                .insert(Cow::<'static, str>::Owned(request_id.clone()));
+            config.expire_due_temporary_sources();
+            let request_id = config.next_request_id();
+            if let Some(request_id) = request_id {
+                req.extensions_mut().insert(request_id);
+            }
 
-            let request_nonce = config.prepare_request_nonce(&request_id);
+            let scoped_nonce = config.scoped_nonce(&req);
+            let lazy_nonce = (scoped_nonce.is_none() && config.lazy_nonce())
+                .then(|| CspNonce::new(config.clone(), request_id));
+            let request_nonce = match &lazy_nonce {
+                Some(lazy_nonce) => {
+                    req.extensions_mut().insert(lazy_nonce.clone());
+                    None
+                }
+                None => scoped_nonce.or_else(|| config.prepare_request_nonce(request_id)),
+            };
 
             if let Some(nonce) = request_nonce.as_ref() {
-                req.extensions_mut().insert(RequestNonce(nonce.clone()));
+                req.extensions_mut().insert(RequestNonce(Arc::clone(nonce)));
+            }
+            if config.html_nonce_rewriting() && (request_nonce.is_some() || lazy_nonce.is_some()) {
+                drop_html_preconditions(req.headers_mut());
             }
 
             config.stats().increment_request_count();
 
+            let forwarded = config
+                .trusted_proxies()
+                .map(|proxies| proxies.resolve(&req));
+            let secure =
+                is_secure_request(&req, config.trust_forwarded_proto(), forwarded.as_ref());
+            let origin = request_origin(&req, secure, forwarded.as_ref());
+            if let Some(forwarded) = forwarded {
+                req.extensions_mut().insert(forwarded);
+            }
+            req.extensions_mut().insert(RequestCspContext::new(
+                config.clone(),
+                vhost.clone(),
+                origin.clone(),
+            ));
+
             let mut res = match service.call(req).await {
                 Ok(res) => res,
                 Err(error) => {
-                    config.remove_request_nonce(&request_id);
+                    config.remove_request_nonce(request_id);
                     return Err(error);
                 }
             };
 
+            if res.request().extensions().contains::<CspDisabled>()
+                || res.response().extensions().contains::<CspDisabled>()
+            {
+                config.remove_request_nonce(request_id);
+                return Ok(res.map_into_left_body());
+            }
+
             let _timer = PerformanceTimer::new();
 
+            let request_overrides = res
+                .request()
+                .extensions_mut()
+                .remove::<ResponseCspOverrides>();
+            let response_overrides = res
+                .response_mut()
+                .extensions_mut()
+                .remove::<ResponseCspOverrides>();
+            let overrides = match (request_overrides, response_overrides) {
+                (Some(request), Some(response)) => Some(request.merge(response)),
+                (request, response) => request.or(response),
+            };
+            let is_html =
+                res.status() != StatusCode::PARTIAL_CONTENT && is_rewritable_html(res.headers());
+            let rewrite_html = is_html
+                && (config.html_nonce_rewriting()
+                    || res.request().extensions().contains::<HtmlNonceRewrite>()
+                    || res.response().extensions().contains::<HtmlNonceRewrite>());
+            let request_nonce = match lazy_nonce {
+                Some(lazy_nonce) if rewrite_html => Some(lazy_nonce.shared()),
+                Some(lazy_nonce) => lazy_nonce.generated_shared(),
+                None => request_nonce,
+            };
+
+            let http_req = res.request().clone();
             let headers = res.headers_mut();
 
-            if let Some(nonce) = request_nonce.as_deref() {
-                let serialize_timer = PerformanceTimer::new();
-                let compiled_policy = {
-                    let policy_guard = config.policy();
-                    let policy = policy_guard.read();
-                    policy.compile_with_runtime_nonce(nonce)
-                };
+            let generated =
+                if let Some(overrides) = overrides.filter(ResponseCspOverrides::has_changes) {
+                    insert_overridden_header(
+                        &config,
+                        vhost.as_deref(),
+                        request_nonce.as_deref(),
+                        &overrides,
+                        headers,
+                    )
+                } else if let Some(vhost) = vhost.as_deref() {
+                    insert_vhost_header(&config, vhost, request_nonce.as_deref(), headers)
+                } else if let Some(nonce) = request_nonce.as_deref() {
+                    let serialize_timer = PerformanceTimer::new();
+                    let generated = if let Some(compiled_policy) = config.compiled_policy() {
+                        compiled_policy.header_value_with_nonce(nonce).map(|value| {
+                            headers.insert(compiled_policy.header_name().clone(), value);
+                        })
+                    } else {
+                        config
+                            .policy()
+                            .compile_with_runtime_nonce(nonce)
+                            .map(|compiled_policy| {
+                                headers.insert(
+                                    compiled_policy.header_name().clone(),
+                                    compiled_policy.header_value().clone(),
+                                );
+                            })
+                    };
 
-                if let Ok(compiled_policy) = compiled_policy {
+                    config
+                        .stats()
+                        .add_policy_serialize_time(serialize_timer.elapsed().as_nanos() as usize);
+                    generated
+                } else if let Some(compiled_policy) = config.compiled_policy() {
+                    config.stats().increment_cache_hit_count();
                     headers.insert(
                         compiled_policy.header_name().clone(),
                         compiled_policy.header_value().clone(),
                     );
-                }
+                    Ok(())
+                } else {
+                    let policy = config.policy();
+
+                    let hash_timer = PerformanceTimer::new();
+                    let cache_key = PolicyCacheKey::new(
+                        http_req.match_pattern().unwrap_or_default(),
+                        policy.structural_hash(),
+                    );
+                    config
+                        .stats()
+                        .add_policy_hash_time(hash_timer.elapsed().as_nanos() as usize);
 
-                config
-                    .stats()
-                    .add_policy_serialize_time(serialize_timer.elapsed().as_nanos() as usize);
+                    if let Some(cached_policy) = config.get_cached_policy(cache_key.clone()) {
+                        config.stats().increment_cache_hit_count();
 
-                if let Some(header_name) = config.nonce_request_header() {
-                    if let (Ok(header_name), Ok(header_value)) = (
-                        HeaderName::try_from(header_name),
-                        HeaderValue::from_str(nonce),
-                    ) {
-                        headers.insert(header_name, header_value);
+                        let header_name = if cached_policy.is_report_only() {
+                            HeaderName::from_static(HEADER_CSP_REPORT_ONLY)
+                        } else {
+                            HeaderName::from_static(HEADER_CSP)
+                        };
+
+                        let mut policy_clone = cached_policy.as_ref().clone();
+                        let stale = policy_clone.has_expired_header_value();
+                        if stale {
+                            config.stats().increment_stale_policy_cache_count();
+                        }
+                        policy_clone
+                            .header_value_with_cache_duration(config.cache_duration())
+                            .map(|value| {
+                                headers.insert(header_name, value);
+                                if stale {
+                                    config.cache_policy(cache_key, policy_clone);
+                                }
+                            })
+                    } else {
+                        let serialize_timer = PerformanceTimer::new();
+                        let header_name = policy.header_name();
+                        let mut policy_clone = CspPolicy::clone(&policy);
+
+                        let header_value =
+                            policy_clone.header_value_with_cache_duration(config.cache_duration());
+                        config.stats().add_policy_serialize_time(
+                            serialize_timer.elapsed().as_nanos() as usize,
+                        );
+
+                        header_value.map(|value| {
+                            headers.insert(header_name, value);
+                            config.cache_policy(cache_key, policy_clone);
+                        })
                     }
+                };
+            let mut header_failed = match generated {
+                Ok(()) => false,
+                Err(error) => {
+                    header_generation_failed(&config, &error, true);
+                    if config.header_failure_mode() == HeaderFailureMode::Fallback {
+                        let report_only = match vhost.as_deref() {
+                            Some(vhost) => vhost.policy().is_report_only(),
+                            None => config.policy().is_report_only(),
+                        };
+                        insert_fallback_header(
+                            &config,
+                            headers,
+                            report_only,
+                            request_nonce.as_deref(),
+                        );
+                    }
+                    true
                 }
-            } else if let Some(compiled_policy) = config.compiled_policy() {
-                config.stats().increment_cache_hit_count();
-                headers.insert(
-                    compiled_policy.header_name().clone(),
-                    compiled_policy.header_value().clone(),
-                );
-            } else {
-                let policy_guard = config.policy();
-                let policy = policy_guard.read();
-
-                let hash_timer = PerformanceTimer::new();
-                let mut policy_for_hash = policy.clone();
-                let policy_hash = policy_for_hash.hash();
-                config
-                    .stats()
-                    .add_policy_hash_time(hash_timer.elapsed().as_nanos() as usize);
+            };
 
-                if let Some(cached_policy) = config.get_cached_policy(policy_hash) {
-                    config.stats().increment_cache_hit_count();
-                    drop(policy);
+            if secure {
+                if config.upgrade_insecure_on_tls() {
+                    insert_upgrade_insecure_requests(&config, headers);
+                }
+                if let Some(hsts) = config.hsts() {
+                    if !headers.contains_key(STRICT_TRANSPORT_SECURITY) {
+                        headers.insert(STRICT_TRANSPORT_SECURITY, hsts.clone());
+                    }
+                }
+            }
 
-                    let header_name = if cached_policy.is_report_only() {
-                        HeaderName::from_static(HEADER_CSP_REPORT_ONLY)
-                    } else {
-                        HeaderName::from_static(HEADER_CSP)
-                    };
+            if config.frame_options_sync() {
+                sync_frame_options(headers);
+            }
 
-                    let mut policy_clone = cached_policy.as_ref().clone();
-                    if let Ok(value) =
-                        policy_clone.header_value_with_cache_duration(config.cache_duration())
-                    {
-                        headers.insert(header_name, value);
+            for policy in additional_policies.iter() {
+                let value = match request_nonce.as_deref() {
+                    Some(nonce) => policy.header_value_with_nonce(nonce),
+                    None => Ok(policy.header_value().clone()),
+                };
+                match value {
+                    Ok(value) => {
+                        headers.append(policy.header_name().clone(), value);
                     }
-                } else {
-                    let serialize_timer = PerformanceTimer::new();
-                    let header_name = policy.header_name();
-                    let mut policy_clone = policy.clone();
-                    drop(policy);
+                    Err(error) => {
+                        header_generation_failed(&config, &error, false);
+                        header_failed = true;
+                    }
+                }
+            }
 
-                    let header_value =
-                        policy_clone.header_value_with_cache_duration(config.cache_duration());
-                    config
-                        .stats()
-                        .add_policy_serialize_time(serialize_timer.elapsed().as_nanos() as usize);
+            if header_failed && config.header_failure_mode() == HeaderFailureMode::Reject {
+                config.remove_request_nonce(request_id);
+                return Err(reject_unprotected_response(&config));
+            }
 
-                    if let Ok(value) = header_value {
-                        headers.insert(header_name, value);
-                        config.cache_policy(policy_hash, policy_clone);
+            if let Some(endpoints) = config.reporting_endpoints() {
+                headers.insert(
+                    HeaderName::from_static(HEADER_REPORTING_ENDPOINTS),
+                    endpoints.clone(),
+                );
+            }
+
+            if let (Some(nonce), Some(header_name)) =
+                (request_nonce.as_deref(), config.nonce_request_header())
+            {
+                match (
+                    HeaderName::try_from(header_name),
+                    HeaderValue::from_str(nonce),
+                ) {
+                    (Ok(header_name), Ok(header_value)) => {
+                        headers.insert(header_name, header_value);
                     }
+                    _ => header_insertion_failed(&config, header_name),
                 }
             }
 
-            config.remove_request_nonce(&request_id);
+            if let Some(cache_control) = config
+                .nonce_cache_control()
+                .filter(|_| request_nonce.is_some())
+            {
+                headers.insert(CACHE_CONTROL, cache_control.clone());
+            }
+
+            config.remove_request_nonce(request_id);
+
+            let rewrite_nonce = request_nonce.filter(|_| rewrite_html);
+            let tap = config
+                .shadow_auditor()
+                .filter(|_| is_html)
+                .and_then(|auditor| {
+                    let policy = res
+                        .headers()
+                        .get(HEADER_CSP)
+                        .or_else(|| res.headers().get(HEADER_CSP_REPORT_ONLY))?
+                        .clone();
+                    Some(auditor.tap(page_url(&http_req, &origin), policy))
+                });
+
+            if rewrite_nonce.is_none() && tap.is_none() {
+                return Ok(res.map_into_left_body());
+            }
+            if rewrite_nonce.is_some() {
+                drop_representation_headers(res.headers_mut());
+            }
+            Ok(res.map_body(|_, body| {
+                EitherBody::right(NonceRewriteBody::new(body, rewrite_nonce.as_deref(), tap))
+            }))
+        })
+    }
+}
+
+/// Only uncompressed `text/html` is rewritten; in particular `text/event-stream`
+/// responses are streamed through untouched and keep their framing.
+fn is_rewritable_html(headers: &HeaderMap) -> bool {
+    let is_html = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .trim_start()
+                .get(..9)
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case("text/html"))
+        });
+
+    let is_identity = headers
+        .get(CONTENT_ENCODING)
+        .is_none_or(|value| value.as_bytes().eq_ignore_ascii_case(b"identity"));
+
+    is_html && is_identity
+}
+
+/// Returns `true` if the request reached the server over TLS or, when forwarded
+/// headers are trusted, if the client-facing proxy did.
+fn is_secure_request(
+    req: &ServiceRequest,
+    trust_forwarded_proto: bool,
+    forwarded: Option<&ForwardedInfo>,
+) -> bool {
+    req.app_config().secure()
+        || forwarded.is_some_and(ForwardedInfo::is_secure)
+        || (trust_forwarded_proto
+            && forwarded_proto(req.headers())
+                .is_some_and(|proto| proto.eq_ignore_ascii_case("https")))
+}
+
+/// The origin the browser sent the request to, `'self'` for this response:
+/// the one resolved through trusted proxies, or else the connection's scheme
+/// and `Host` header.
+fn request_origin(req: &ServiceRequest, secure: bool, forwarded: Option<&ForwardedInfo>) -> String {
+    if let Some(forwarded) = forwarded {
+        return forwarded.origin();
+    }
+
+    let scheme = if secure { "https" } else { "http" };
+    let host = req
+        .headers()
+        .get(HOST)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_else(|| req.app_config().host());
+    format!("{scheme}://{host}")
+}
+
+/// Reconstructs the URL the browser requested from the request's origin.
+fn page_url(req: &HttpRequest, origin: &str) -> String {
+    let path = req.uri().path_and_query().map_or("/", |path| path.as_str());
+    format!("{origin}{path}")
+}
+
+fn sync_frame_options(headers: &mut HeaderMap) {
+    let Some(ancestors) = headers
+        .get(HEADER_CSP)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|policy| {
+            policy.split(';').find_map(|directive| {
+                let mut tokens = directive.split_whitespace();
+                tokens
+                    .next()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(FRAME_ANCESTORS))
+                    .then(|| tokens.collect::<Vec<_>>())
+            })
+        })
+    else {
+        return;
+    };
+
+    match ancestors.as_slice() {
+        [NONE_SOURCE] => {
+            headers.insert(X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+        }
+        [SELF_SOURCE] => {
+            headers.insert(X_FRAME_OPTIONS, HeaderValue::from_static("SAMEORIGIN"));
+        }
+        _ => {
+            headers.remove(X_FRAME_OPTIONS);
+        }
+    }
+}
+
+fn insert_upgrade_insecure_requests(config: &CspConfig, headers: &mut HeaderMap) {
+    let Some(policy) = headers
+        .get(HEADER_CSP)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return;
+    };
+
+    if policy.split(';').any(|directive| {
+        directive
+            .trim()
+            .eq_ignore_ascii_case(UPGRADE_INSECURE_REQUESTS)
+    }) {
+        return;
+    }
+
+    match HeaderValue::from_str(&format!("{policy}; {UPGRADE_INSECURE_REQUESTS}")) {
+        Ok(value) => {
+            headers.insert(HeaderName::from_static(HEADER_CSP), value);
+        }
+        Err(_) => header_insertion_failed(config, HEADER_CSP),
+    }
+}
+
+/// Counts and logs a policy header that could not be built. Depending on the
+/// [`HeaderFailureMode`], the response is then sent without it, rejected, or,
+/// when the failed header is the main policy, sent with the fallback policy.
+///
+/// Only the first failure under each policy version is logged as a warning;
+/// repeats are counted and logged at debug level, so a policy that cannot be
+/// serialized does not flood the log with one line per request.
+#[cold]
+fn header_generation_failed(config: &CspConfig, error: &CspError, main_policy: bool) {
+    config.stats().increment_header_generation_failure_count();
+    if !config.first_header_failure_for_policy() {
+        csp_log!(Debug, "Could not generate the CSP header again: {error}");
+        return;
+    }
+    match config.header_failure_mode() {
+        HeaderFailureMode::Reject => csp_log!(
+            Error,
+            "Could not generate the CSP header, rejecting the response: {error}"
+        ),
+        HeaderFailureMode::Fallback if main_policy => csp_log!(
+            Warn,
+            "Could not generate the CSP header, sending `{FALLBACK_POLICY}` instead: {error}"
+        ),
+        _ => csp_log!(
+            Warn,
+            "Could not generate the CSP header, sending the response without it: {error}"
+        ),
+    }
+}
+
+/// Sends [`FALLBACK_POLICY`] in place of a main policy that could not be
+/// generated, under the same header as that policy so a report-only rollout
+/// never starts blocking. The request nonce, when there is one, is allowed
+/// for scripts and styles so nonced elements keep working; a nonce that would
+/// not make a clean source is left out.
+#[cold]
+fn insert_fallback_header(
+    config: &CspConfig,
+    headers: &mut HeaderMap,
+    report_only: bool,
+    nonce: Option<&str>,
+) {
+    config.stats().increment_fallback_header_count();
 
-            Ok(res)
+    let header_name = if report_only {
+        HeaderName::from_static(HEADER_CSP_REPORT_ONLY)
+    } else {
+        HeaderName::from_static(HEADER_CSP)
+    };
+    let header_value = nonce
+        .filter(|nonce| {
+            !nonce.is_empty()
+                && nonce
+                    .bytes()
+                    .all(|byte| byte.is_ascii_alphanumeric() || b"+/=-_".contains(&byte))
         })
+        .and_then(|nonce| {
+            HeaderValue::from_str(&format!(
+                "{FALLBACK_POLICY}; script-src 'self' 'nonce-{nonce}'; style-src 'self' 'nonce-{nonce}'"
+            ))
+            .ok()
+        })
+        .unwrap_or_else(|| HeaderValue::from_static(FALLBACK_POLICY));
+    headers.insert(header_name, header_value);
+}
+
+/// The error that replaces a response left without its policy header under
+/// [`HeaderFailureMode::Reject`]. It renders as a `500` locked down with
+/// `default-src 'none'`, with the configured failure page as its body.
+#[cold]
+fn reject_unprotected_response(config: &CspConfig) -> Error {
+    config.stats().increment_rejected_response_count();
+
+    let mut response = HttpResponse::InternalServerError();
+    response.insert_header((
+        HeaderName::from_static(HEADER_CSP),
+        HeaderValue::from_static("default-src 'none'"),
+    ));
+    let response = match config.header_failure_page() {
+        Some(page) => response
+            .content_type("text/html; charset=utf-8")
+            .body(page.clone()),
+        None => response.finish(),
+    };
+    InternalError::from_response("CSP header could not be generated", response).into()
+}
+
+/// Counts and logs a header whose name or value was rejected by `http`.
+#[cold]
+fn header_insertion_failed(config: &CspConfig, header_name: &str) {
+    config.stats().increment_header_insertion_failure_count();
+    csp_log!(
+        Warn,
+        "Could not insert the {header_name} header: invalid name or value"
+    );
+}
+
+fn insert_overridden_header(
+    config: &CspConfig,
+    vhost: Option<&SelectedPolicy>,
+    nonce: Option<&str>,
+    overrides: &ResponseCspOverrides,
+    headers: &mut HeaderMap,
+) -> Result<(), CspError> {
+    let serialize_timer = PerformanceTimer::new();
+    let mut policy = match vhost {
+        Some(vhost) => overrides.apply(vhost.policy()),
+        None => overrides.apply(&config.policy()),
+    };
+
+    if let Some(nonce) = nonce {
+        policy.inject_runtime_nonce(nonce);
+    }
+
+    let generated = policy.compile().map(|compiled_policy| {
+        headers.insert(
+            compiled_policy.header_name().clone(),
+            compiled_policy.header_value().clone(),
+        );
+    });
+    config
+        .stats()
+        .add_policy_serialize_time(serialize_timer.elapsed().as_nanos() as usize);
+    generated
+}
+
+fn insert_vhost_header(
+    config: &CspConfig,
+    vhost: &SelectedPolicy,
+    nonce: Option<&str>,
+    headers: &mut HeaderMap,
+) -> Result<(), CspError> {
+    let Some(compiled_policy) = vhost.compiled() else {
+        // Only a policy that failed to compile has no compiled form; compiling
+        // it again recovers the error.
+        return vhost.policy().compile().map(drop);
+    };
+
+    if let Some(nonce) = nonce {
+        let serialize_timer = PerformanceTimer::new();
+        let generated = compiled_policy.header_value_with_nonce(nonce).map(|value| {
+            headers.insert(compiled_policy.header_name().clone(), value);
+        });
+        config
+            .stats()
+            .add_policy_serialize_time(serialize_timer.elapsed().as_nanos() as usize);
+        generated
+    } else {
+        config.stats().increment_cache_hit_count();
+        headers.insert(
+            compiled_policy.header_name().clone(),
+            compiled_policy.header_value().clone(),
+        );
+        Ok(())
     }
 }
 
@@ -221,7 +843,7 @@ pub fn csp_middleware_with_request_nonce(
 }
 
 #[deprecated(
-    note = "ServiceConfig cannot install application-wide middleware. Use csp_middleware(policy) with App::wrap(...) or CspMiddleware::new(config) instead."
+    note = "ServiceConfig cannot install application-wide middleware, and the config registered here is not the one a wrapped middleware uses. Wrap CspMiddleware and register its config with configure_csp_data(middleware.config()) instead."
 )]
 pub fn configure_csp(
     policy: crate::core::policy::CspPolicy,
@@ -232,6 +854,41 @@ pub fn configure_csp(
     }
 }
 
+/// Registers `config` as `Data<CspConfig>`, and its statistics as
+/// `Data<Arc<CspStats>>`, for handlers and admin routes.
+///
+/// Pass the config of the middleware the app wraps, so that policy updates made
+/// through the app data change the headers the middleware sends.
+///
+/// ```rust
+/// use actix_web::{web, App, HttpResponse};
+/// use actix_web_csp::middleware::configure_csp_data;
+/// use actix_web_csp::{csp_middleware, CspConfig, CspPolicy, Source};
+///
+/// async fn allow_cdn(config: web::Data<CspConfig>) -> HttpResponse {
+///     config.update_policy(|policy| {
+///         policy.append_source("script-src", Source::Host("cdn.example.com".into()));
+///     });
+///     HttpResponse::NoContent().finish()
+/// }
+///
+/// let middleware = csp_middleware(CspPolicy::default());
+/// let app = App::new()
+///     .configure(configure_csp_data(middleware.config()))
+///     .wrap(middleware)
+///     .route("/admin/csp/cdn", web::post().to(allow_cdn));
+/// ```
+///
+/// [`CspStats`]: crate::monitoring::CspStats
+pub fn configure_csp_data(
+    config: Arc<CspConfig>,
+) -> impl FnOnce(&mut actix_web::web::ServiceConfig) {
+    move |cfg| {
+        cfg.app_data(Data::new(config.stats().clone()));
+        cfg.app_data(Data::from(config));
+    }
+}
+
 #[cfg(feature = "reporting")]
 pub fn configure_csp_with_reporting<F>(
     policy: crate::core::policy::CspPolicy,
@@ -240,37 +897,35 @@ pub fn configure_csp_with_reporting<F>(
 where
     F: Fn(crate::monitoring::report::CspViolationReport) + Send + Sync + 'static,
 {
+    configure_report_route(
+        &policy,
+        crate::middleware::reporting::CspReportingMiddleware::new(report_handler),
+        std::sync::Arc::new(crate::monitoring::stats::CspStats::new()),
+    )
+}
+
+/// Serves `policy`'s report path with `reporting`, so the route applies the
+/// same body limits, timeouts, in-flight cap and report processors as the
+/// middleware would. The middleware's own report paths are replaced by the
+/// policy's.
+#[cfg(feature = "reporting")]
+fn configure_report_route(
+    policy: &crate::core::policy::CspPolicy,
+    reporting: crate::middleware::reporting::CspReportingMiddleware,
+    stats: Arc<crate::monitoring::stats::CspStats>,
+) -> impl FnOnce(&mut actix_web::web::ServiceConfig) {
     let report_path = policy
-        .report_uri()
+        .report_uris()
+        .next()
         .unwrap_or(crate::constants::DEFAULT_REPORT_PATH)
         .to_owned();
-    let report_handler: crate::middleware::reporting::ViolationHandler =
-        std::sync::Arc::new(report_handler);
+    let reporting = reporting
+        .with_report_path(report_path.clone())
+        .with_stats(stats.clone());
 
     move |cfg| {
-        let stats = std::sync::Arc::new(crate::monitoring::stats::CspStats::new());
-        let route_stats = stats.clone();
-        let route_handler = report_handler.clone();
-
         cfg.app_data(Data::new(stats));
-        cfg.route(
-            report_path.as_str(),
-            actix_web::web::post().to(move |body: actix_web::web::Bytes| {
-                let route_stats = route_stats.clone();
-                let route_handler = route_handler.clone();
-
-                async move {
-                    crate::middleware::reporting::process_violation_bytes(
-                        &body,
-                        crate::constants::DEFAULT_MAX_REPORT_SIZE,
-                        &route_stats,
-                        &route_handler,
-                    )?;
-
-                    Ok::<_, actix_web::Error>(actix_web::HttpResponse::Ok())
-                }
-            }),
-        );
+        cfg.service(actix_web::web::resource(report_path).wrap(reporting));
     }
 }
 
@@ -285,6 +940,16 @@ where
     move |_cfg| {}
 }
 
+/// Builds the middleware for `policy` together with a configurator that adds
+/// the report route and registers the middleware's config through
+/// [`configure_csp_data`], so reports count towards the middleware's
+/// statistics and policy updates made through the app data take effect.
+///
+/// The route uses the reporting defaults; [`csp_with_configured_reporting`]
+/// takes a [`CspReportingMiddleware`] whose limits and report processors it
+/// applies instead.
+///
+/// [`CspReportingMiddleware`]: crate::middleware::CspReportingMiddleware
 #[cfg(feature = "reporting")]
 pub fn csp_with_reporting<F>(
     policy: crate::core::policy::CspPolicy,
@@ -296,11 +961,57 @@ pub fn csp_with_reporting<F>(
 where
     F: Fn(crate::monitoring::report::CspViolationReport) + Send + Sync + 'static,
 {
+    csp_with_configured_reporting(
+        policy,
+        crate::middleware::reporting::CspReportingMiddleware::new(report_handler),
+    )
+}
+
+/// Like [`csp_with_reporting`], serving the report route with `reporting`:
+/// its body size limit, read timeout, in-flight cap, enrichers, scrubber,
+/// router and queue all apply. Its report paths are replaced by the policy's
+/// report path and its statistics by the middleware's.
+///
+/// ```rust
+/// use actix_web::App;
+/// use actix_web_csp::middleware::{csp_with_configured_reporting, CspReportingMiddleware};
+/// use actix_web_csp::monitoring::ReportScrubber;
+/// use actix_web_csp::{CspPolicyBuilder, Source};
+/// use std::time::Duration;
+///
+/// let policy = CspPolicyBuilder::new()
+///     .default_src([Source::Self_])
+///     .report_uri("/csp-report")
+///     .build_unchecked();
+/// let reporting = CspReportingMiddleware::new(|report| println!("{}", report.blocked_uri))
+///     .with_report_read_timeout(Duration::from_secs(2))
+///     .with_max_concurrent_reports(64)
+///     .with_scrubber(ReportScrubber::new());
+///
+/// let (csp, configure_reporting) = csp_with_configured_reporting(policy, reporting);
+/// let app = App::new().wrap(csp).configure(configure_reporting);
+/// ```
+#[cfg(feature = "reporting")]
+pub fn csp_with_configured_reporting(
+    policy: crate::core::policy::CspPolicy,
+    reporting: crate::middleware::reporting::CspReportingMiddleware,
+) -> (
+    CspMiddleware,
+    impl FnOnce(&mut actix_web::web::ServiceConfig),
+) {
     let middleware = csp_middleware(policy.clone());
-    let configurator = configure_csp_with_reporting(policy, report_handler);
+    let config = middleware.config();
+    let report_route = configure_report_route(&policy, reporting, config.stats().clone());
+    let configurator = move |cfg: &mut actix_web::web::ServiceConfig| {
+        report_route(cfg);
+        configure_csp_data(config)(cfg);
+    };
     (middleware, configurator)
 }
 
+/// Builds the middleware for `policy` together with a configurator that
+/// registers its config through [`configure_csp_data`]; reports are not
+/// handled without the `reporting` feature.
 #[cfg(not(feature = "reporting"))]
 pub fn csp_with_reporting<F>(
     policy: crate::core::policy::CspPolicy,
@@ -312,5 +1023,59 @@ pub fn csp_with_reporting<F>(
 where
     F: Fn(crate::monitoring::report::CspViolationReport) + Send + Sync + 'static,
 {
-    (csp_middleware(policy), move |_cfg| {})
+    let middleware = csp_middleware(policy);
+    let configurator = configure_csp_data(middleware.config());
+    (middleware, configurator)
+}
+
+/// Like [`csp_with_reporting`]; reports are not handled without the
+/// `reporting` feature.
+#[cfg(not(feature = "reporting"))]
+pub fn csp_with_configured_reporting(
+    policy: crate::core::policy::CspPolicy,
+    _reporting: crate::middleware::reporting::CspReportingMiddleware,
+) -> (
+    CspMiddleware,
+    impl FnOnce(&mut actix_web::web::ServiceConfig),
+) {
+    let middleware = csp_middleware(policy);
+    let configurator = configure_csp_data(middleware.config());
+    (middleware, configurator)
+}
+
+/// Registers a readiness probe at `path` that answers `GET` with the
+/// configuration's [`CspHealth`] as JSON: `200 OK` when it
+/// [`is_ready`](crate::core::CspHealth::is_ready), `503 Service Unavailable` otherwise.
+///
+/// ```rust
+/// use actix_web::App;
+/// use actix_web_csp::middleware::configure_csp_readiness;
+/// use actix_web_csp::{csp_middleware, CspPolicy};
+///
+/// let middleware = csp_middleware(CspPolicy::default());
+/// let app = App::new()
+///     .configure(configure_csp_readiness("/ready/csp", middleware.config()))
+///     .wrap(middleware);
+/// ```
+///
+/// [`CspHealth`]: crate::core::CspHealth
+pub fn configure_csp_readiness(
+    path: &str,
+    config: Arc<CspConfig>,
+) -> impl FnOnce(&mut actix_web::web::ServiceConfig) {
+    let path = path.to_owned();
+    move |cfg| {
+        cfg.route(
+            path.as_str(),
+            actix_web::web::get().to(move || {
+                let health = config.health();
+                let status = if health.is_ready() {
+                    StatusCode::OK
+                } else {
+                    StatusCode::SERVICE_UNAVAILABLE
+                };
+                ready(actix_web::HttpResponse::build(status).json(health))
+            }),
+        );
+    }
 }
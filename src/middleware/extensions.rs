@@ -1,12 +1,142 @@
+use crate::core::config::{CspConfig, CspHealth};
+use crate::core::directives::Directive;
+use crate::core::policy::{CspPolicy, SelectedPolicy};
 use crate::core::source::Source;
+use crate::logging::csp_log;
+use crate::middleware::response::ResponseCspOverrides;
+use crate::monitoring::stats::CspStats;
 use crate::security::hash::HashAlgorithm;
-use crate::security::nonce::RequestNonce;
-use actix_web::HttpMessage;
+use crate::security::nonce::{CspNonce, RequestNonce};
+use crate::security::verify::PolicyVerifier;
+use actix_web::dev::{Extensions, Payload};
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use futures::future::{ready, Ready};
+use std::borrow::Cow;
+use std::sync::Arc;
 
+/// The configuration, virtual-host policy and origin [`CspMiddleware`] resolved
+/// for a request.
+///
+/// [`CspMiddleware`]: crate::middleware::CspMiddleware
+#[derive(Clone)]
+pub(crate) struct RequestCspContext {
+    config: Arc<CspConfig>,
+    vhost: Option<Arc<SelectedPolicy>>,
+    /// `scheme://host` the browser used, through trusted proxies if any
+    origin: String,
+}
+
+impl RequestCspContext {
+    #[inline]
+    pub(crate) fn new(
+        config: Arc<CspConfig>,
+        vhost: Option<Arc<SelectedPolicy>>,
+        origin: String,
+    ) -> Self {
+        Self {
+            config,
+            vhost,
+            origin,
+        }
+    }
+
+    #[inline]
+    fn config(&self) -> &Arc<CspConfig> {
+        &self.config
+    }
+
+    #[inline]
+    fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    fn policy(&self) -> CspPolicy {
+        match &self.vhost {
+            Some(vhost) => vhost.policy().clone(),
+            None => CspPolicy::clone(&self.config.policy()),
+        }
+    }
+}
+
+/// The policy that applies to a request, including per-response overrides.
+pub(crate) fn request_policy(extensions: &Extensions) -> Option<CspPolicy> {
+    let policy = extensions.get::<RequestCspContext>()?.policy();
+
+    Some(match extensions.get::<ResponseCspOverrides>() {
+        Some(overrides) => overrides.apply(&policy),
+        None => policy,
+    })
+}
+
+/// Handler-side access to the CSP state of the current request.
+///
+/// Implemented for every [`HttpMessage`], so it works on both `HttpRequest` and
+/// `ServiceRequest`. Changes made through [`register_hash`](Self::register_hash)
+/// and [`override_directive`](Self::override_directive) apply to this request's
+/// response only and are merged with those made through [`CspResponseExt`].
+///
+/// ```rust
+/// use actix_web::{HttpRequest, HttpResponse};
+/// use actix_web_csp::core::Directive;
+/// use actix_web_csp::{CspExtensions, HashAlgorithm, Source};
+///
+/// async fn page(req: HttpRequest) -> HttpResponse {
+///     let script = "init()";
+///     req.register_hash("script-src", HashAlgorithm::Sha256, script.as_bytes());
+///
+///     let mut img_src = Directive::new("img-src");
+///     img_src.add_sources([Source::Self_, Source::Scheme("data".into())]);
+///     req.override_directive(img_src);
+///
+///     HttpResponse::Ok()
+///         .content_type("text/html")
+///         .body(format!("<script>{script}</script>"))
+/// }
+/// ```
+///
+/// [`CspResponseExt`]: crate::middleware::CspResponseExt
 pub trait CspExtensions {
     fn get_nonce(&self) -> Option<String>;
     fn generate_hash(&self, algorithm: HashAlgorithm, data: &[u8]) -> String;
     fn generate_hash_source(&self, algorithm: HashAlgorithm, data: &[u8]) -> Source;
+
+    /// Returns the request nonce as a ready-to-use `'nonce-...'` source.
+    fn nonce_source(&self) -> Option<Source>;
+
+    /// Returns a snapshot of the policy that will be sent for this request,
+    /// including changes registered so far.
+    ///
+    /// `None` when the request did not pass through [`CspMiddleware`].
+    ///
+    /// [`CspMiddleware`]: crate::middleware::CspMiddleware
+    fn policy(&self) -> Option<CspPolicy>;
+
+    /// Returns a [`PolicyVerifier`] for [`policy`](Self::policy) that matches
+    /// `'self'` against the origin the browser used, as resolved through
+    /// [`TrustedProxies`] when they are configured.
+    ///
+    /// `None` when the request did not pass through [`CspMiddleware`].
+    ///
+    /// [`CspMiddleware`]: crate::middleware::CspMiddleware
+    /// [`TrustedProxies`]: crate::middleware::TrustedProxies
+    fn policy_verifier(&self) -> Option<PolicyVerifier>;
+
+    /// Hashes `data` and allows it under `directive` for this response.
+    ///
+    /// Returns the hash source that was added. Not callable through
+    /// `dyn CspExtensions`.
+    fn register_hash(
+        &self,
+        directive: impl Into<Cow<'static, str>>,
+        algorithm: HashAlgorithm,
+        data: &[u8],
+    ) -> Source
+    where
+        Self: Sized;
+
+    /// Replaces, or adds, `directive` for this response.
+    fn override_directive(&self, directive: Directive);
 }
 
 impl<T> CspExtensions for T
@@ -14,9 +144,15 @@ where
     T: HttpMessage,
 {
     fn get_nonce(&self) -> Option<String> {
-        self.extensions()
+        let extensions = self.extensions();
+        extensions
             .get::<RequestNonce>()
-            .map(|nonce| nonce.0.clone())
+            .map(|nonce| nonce.to_string())
+            .or_else(|| {
+                extensions
+                    .get::<CspNonce>()
+                    .map(|nonce| nonce.get().to_string())
+            })
     }
 
     fn generate_hash(&self, algorithm: HashAlgorithm, data: &[u8]) -> String {
@@ -26,4 +162,121 @@ where
     fn generate_hash_source(&self, algorithm: HashAlgorithm, data: &[u8]) -> Source {
         crate::security::hash::HashGenerator::generate_source(algorithm, data)
     }
+
+    fn nonce_source(&self) -> Option<Source> {
+        self.get_nonce().map(|nonce| Source::Nonce(nonce.into()))
+    }
+
+    fn policy(&self) -> Option<CspPolicy> {
+        request_policy(&self.extensions())
+    }
+
+    fn policy_verifier(&self) -> Option<PolicyVerifier> {
+        let extensions = self.extensions();
+        let origin = extensions.get::<RequestCspContext>()?.origin();
+        let mut verifier = PolicyVerifier::new(request_policy(&extensions)?);
+        if let Err(error) = verifier.set_origin(origin) {
+            csp_log!(Debug, "CSP verifier left without an origin: {error}");
+        }
+        Some(verifier)
+    }
+
+    fn register_hash(
+        &self,
+        directive: impl Into<Cow<'static, str>>,
+        algorithm: HashAlgorithm,
+        data: &[u8],
+    ) -> Source
+    where
+        Self: Sized,
+    {
+        let source = self.generate_hash_source(algorithm, data);
+        self.extensions_mut()
+            .get_or_insert_with(ResponseCspOverrides::default)
+            .append_source(directive.into(), source.clone());
+        source
+    }
+
+    fn override_directive(&self, directive: Directive) {
+        self.extensions_mut()
+            .get_or_insert_with(ResponseCspOverrides::default)
+            .replace_directive(directive);
+    }
+}
+
+/// Read-only access to the [`CspConfig`] behind the current request.
+///
+/// Extracting it takes the configuration of the [`CspMiddleware`] that handled
+/// the request, or else the `Data<CspConfig>` registered as app data, so
+/// handlers and admin routes work the same whichever way the crate was set up.
+/// Extraction fails with `500 Internal Server Error` when neither is present.
+///
+/// ```rust
+/// use actix_web::HttpResponse;
+/// use actix_web_csp::middleware::CspHandle;
+///
+/// async fn csp_status(csp: CspHandle) -> HttpResponse {
+///     HttpResponse::Ok().json(csp.health())
+/// }
+/// ```
+///
+/// [`CspMiddleware`]: crate::middleware::CspMiddleware
+#[derive(Clone)]
+pub struct CspHandle {
+    config: Arc<CspConfig>,
+}
+
+impl CspHandle {
+    /// The current policy, unaffected by later updates.
+    #[inline]
+    pub fn policy(&self) -> Arc<CspPolicy> {
+        self.config.policy()
+    }
+
+    /// A fresh nonce, `None` without a nonce generator.
+    #[inline]
+    pub fn generate_nonce(&self) -> Option<String> {
+        self.config.generate_nonce()
+    }
+
+    #[inline]
+    pub fn stats(&self) -> &Arc<CspStats> {
+        self.config.stats()
+    }
+
+    #[inline]
+    pub fn health(&self) -> CspHealth {
+        self.config.health()
+    }
+}
+
+impl std::fmt::Debug for CspHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CspHandle")
+            .field("policy", &self.config.policy().to_string())
+            .finish()
+    }
+}
+
+impl FromRequest for CspHandle {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let config = req
+            .extensions()
+            .get::<RequestCspContext>()
+            .map(|context| context.config().clone())
+            .or_else(|| {
+                req.app_data::<Data<CspConfig>>()
+                    .map(|config| config.clone().into_inner())
+            });
+
+        ready(match config {
+            Some(config) => Ok(Self { config }),
+            None => Err(actix_web::error::ErrorInternalServerError(
+                "CspHandle needs CspMiddleware or Data<CspConfig> to be registered",
+            )),
+        })
+    }
 }
@@ -0,0 +1,119 @@
+//! Route guards on the CSP state of a request.
+
+use crate::middleware::extensions::request_policy;
+use actix_web::guard::{Guard, GuardContext};
+use std::borrow::Cow;
+
+/// An actix [`Guard`] that matches requests by their Content Security Policy.
+///
+/// The policy is the one [`CspMiddleware`] resolved for the request, so the
+/// middleware has to wrap the app or scope the route is registered on;
+/// without it no request matches. Every condition added must hold.
+///
+/// With the `session` feature, `with_nonce_header` lets sensitive endpoints
+/// insist on the nonce the server issued to the client's session.
+///
+/// [`CspMiddleware`]: crate::middleware::CspMiddleware
+#[derive(Debug, Clone, Default)]
+pub struct CspGuard {
+    #[cfg(feature = "session")]
+    nonce_header: Option<Cow<'static, str>>,
+    directives: Vec<Cow<'static, str>>,
+}
+
+impl CspGuard {
+    /// A guard matching every request the CSP middleware handled.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requires the `header` request header to carry the nonce stored in the
+    /// client's session, compared in constant time.
+    ///
+    /// The nonce a form served to the client would echo back is only known to
+    /// the server when it is kept per session, so this needs
+    /// [`NonceScope::Session`] and `SessionMiddleware` registered outside the
+    /// CSP middleware; requests without a session nonce do not match. A nonce
+    /// written into the policy itself is public and is never accepted.
+    ///
+    /// ```rust
+    /// use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+    /// use actix_web::{cookie::Key, web, App, HttpResponse};
+    /// use actix_web_csp::middleware::CspGuard;
+    /// use actix_web_csp::{CspConfigBuilder, CspMiddleware, CspPolicyBuilder, NonceScope, Source};
+    ///
+    /// let config = CspConfigBuilder::new()
+    ///     .policy(
+    ///         CspPolicyBuilder::new()
+    ///             .default_src([Source::Self_])
+    ///             .script_src([Source::Self_])
+    ///             .build_unchecked(),
+    ///     )
+    ///     .with_nonce_generator(16)
+    ///     .with_nonce_scope(NonceScope::Session)
+    ///     .build();
+    ///
+    /// let app = App::new()
+    ///     .wrap(CspMiddleware::new(config))
+    ///     .wrap(SessionMiddleware::new(CookieSessionStore::default(), Key::generate()))
+    ///     .route(
+    ///         "/transfer",
+    ///         web::post()
+    ///             .guard(CspGuard::new().with_nonce_header("x-csp-nonce"))
+    ///             .to(HttpResponse::Ok),
+    ///     );
+    /// ```
+    ///
+    /// [`NonceScope::Session`]: crate::security::NonceScope::Session
+    #[cfg(feature = "session")]
+    #[inline]
+    pub fn with_nonce_header(mut self, header: impl Into<Cow<'static, str>>) -> Self {
+        self.nonce_header = Some(header.into());
+        self
+    }
+
+    /// Requires the policy to contain `directive`, e.g.
+    /// `require-trusted-types-for` for routes that rely on Trusted Types.
+    #[inline]
+    pub fn with_directive(mut self, directive: impl Into<Cow<'static, str>>) -> Self {
+        self.directives.push(directive.into());
+        self
+    }
+}
+
+impl Guard for CspGuard {
+    fn check(&self, ctx: &GuardContext<'_>) -> bool {
+        let Some(policy) = request_policy(&ctx.req_data()) else {
+            return false;
+        };
+
+        if !self
+            .directives
+            .iter()
+            .all(|directive| policy.get_directive(directive).is_some())
+        {
+            return false;
+        }
+
+        #[cfg(feature = "session")]
+        if let Some(header) = &self.nonce_header {
+            return ctx
+                .head()
+                .headers()
+                .get(header.as_ref())
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|nonce| issued_nonce_matches(ctx, nonce));
+        }
+
+        true
+    }
+}
+
+#[cfg(feature = "session")]
+fn issued_nonce_matches(ctx: &GuardContext<'_>, nonce: &str) -> bool {
+    use crate::security::nonce::{nonces_match, stored_session_nonce};
+    use actix_session::SessionExt;
+
+    stored_session_nonce(&ctx.get_session()).is_some_and(|issued| nonces_match(&issued, nonce))
+}
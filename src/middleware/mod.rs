@@ -1,13 +1,25 @@
+pub mod blocklist;
 pub mod csp;
 pub mod extensions;
+pub mod guard;
+pub mod proxy;
 pub mod reporting;
+pub mod response;
+pub mod rewrite;
+pub(crate) mod shadow;
+pub(crate) mod vhost;
 
-pub use csp::{CspMiddleware, CspMiddlewareService};
-pub use extensions::CspExtensions;
-pub use reporting::{CspReportingMiddleware, CspReportingMiddlewareService};
+pub use blocklist::ReportBlocklist;
+pub use csp::{configure_csp_data, configure_csp_readiness, CspMiddleware, CspMiddlewareService};
+pub use extensions::{CspExtensions, CspHandle};
+pub use guard::CspGuard;
+pub use proxy::{ForwardedInfo, TrustedProxies};
+pub use reporting::{CspReportingMiddleware, CspReportingMiddlewareService, ReportPath};
+pub use response::{CspDisabled, CspResponseExt, HtmlNonceRewrite, NONCE_PLACEHOLDER};
+pub use rewrite::NonceRewriteBody;
 
 #[allow(deprecated)]
 pub use csp::{
     configure_csp, configure_csp_with_reporting, csp_middleware, csp_middleware_with_nonce,
-    csp_middleware_with_request_nonce, csp_with_reporting,
+    csp_middleware_with_request_nonce, csp_with_configured_reporting, csp_with_reporting,
 };
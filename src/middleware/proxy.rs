@@ -0,0 +1,260 @@
+use crate::error::CspError;
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::{HeaderMap, FORWARDED, HOST};
+use std::net::IpAddr;
+
+/// Reverse proxies whose `Forwarded`/`X-Forwarded-*` headers are believed.
+///
+/// Forwarded headers are only honoured when the direct peer of the connection
+/// lies in one of the configured networks; otherwise anyone could spoof them.
+///
+/// ```rust
+/// use actix_web_csp::middleware::TrustedProxies;
+///
+/// let proxies = TrustedProxies::new(["10.0.0.0/8", "::1"])?;
+/// assert!(proxies.contains("10.1.2.3".parse().unwrap()));
+/// assert!(!proxies.contains("192.0.2.1".parse().unwrap()));
+/// # Ok::<(), actix_web_csp::CspError>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedProxies {
+    networks: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    /// Parses a list of CIDR blocks; a bare address trusts that single host.
+    pub fn new<I, S>(networks: I) -> Result<Self, CspError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        networks
+            .into_iter()
+            .try_fold(Self::default(), |proxies, network| {
+                proxies.with_network(network.as_ref())
+            })
+    }
+
+    /// Adds a CIDR block such as `10.0.0.0/8` or `fd00::/8`.
+    pub fn with_network(mut self, network: &str) -> Result<Self, CspError> {
+        let network = parse_network(network).ok_or_else(|| {
+            CspError::ConfigError(format!("Invalid trusted proxy network: {network}"))
+        })?;
+        self.networks.push(network);
+        Ok(self)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.networks.is_empty()
+    }
+
+    /// Returns `true` if `ip` lies in one of the trusted networks.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        networks_contain(&self.networks, ip)
+    }
+
+    /// Resolves the scheme, host and client address the request was made with.
+    ///
+    /// Forwarded headers are consulted only when the peer is trusted. Proxies append
+    /// to these headers, so entries left of the trusted ones are whatever the client
+    /// sent. Walking from the right past hops whose `for=`/`X-Forwarded-For` address
+    /// is itself a trusted proxy, the first remaining hop is the one recorded by the
+    /// outermost trusted proxy; its address, protocol and host are used.
+    pub fn resolve(&self, req: &ServiceRequest) -> ForwardedInfo {
+        let peer = req.peer_addr().map(|addr| addr.ip());
+        let direct = ForwardedInfo {
+            scheme: if req.app_config().secure() {
+                "https".to_string()
+            } else {
+                "http".to_string()
+            },
+            host: req
+                .headers()
+                .get(HOST)
+                .and_then(|value| value.to_str().ok())
+                .map_or_else(|| req.app_config().host().to_string(), str::to_string),
+            client_ip: peer,
+        };
+
+        if !peer.is_some_and(|peer| self.contains(peer)) {
+            return direct;
+        }
+
+        let headers = req.headers();
+        let forwarded = header_entries(headers, FORWARDED.as_str());
+        if !forwarded.is_empty() {
+            let hop = forwarded[self.client_hop(
+                forwarded
+                    .iter()
+                    .map(|hop| hop_param(hop, "for").and_then(parse_node)),
+            )];
+            return ForwardedInfo {
+                scheme: hop_param(hop, "proto")
+                    .map(str::to_ascii_lowercase)
+                    .unwrap_or(direct.scheme),
+                host: hop_param(hop, "host")
+                    .map(str::to_string)
+                    .unwrap_or(direct.host),
+                client_ip: hop_param(hop, "for")
+                    .and_then(parse_node)
+                    .or(direct.client_ip),
+            };
+        }
+
+        let forwarded_for = header_entries(headers, "x-forwarded-for");
+        let hop = self.client_hop(forwarded_for.iter().map(|node| parse_node(node)));
+        // Hops right of the chosen one were added by trusted proxies; count the
+        // same number of entries from the right of the other headers
+        let trusted_hops = forwarded_for.len().saturating_sub(hop + 1);
+        ForwardedInfo {
+            scheme: entry_from_right(headers, "x-forwarded-proto", trusted_hops)
+                .map(str::to_ascii_lowercase)
+                .unwrap_or(direct.scheme),
+            host: entry_from_right(headers, "x-forwarded-host", trusted_hops)
+                .map(str::to_string)
+                .unwrap_or(direct.host),
+            client_ip: forwarded_for
+                .get(hop)
+                .and_then(|node| parse_node(node))
+                .or(direct.client_ip),
+        }
+    }
+
+    /// Index of the right-most hop whose address is not a trusted proxy, or of
+    /// the left-most hop when every one is. Unparseable addresses count as
+    /// untrusted.
+    fn client_hop(&self, addresses: impl Iterator<Item = Option<IpAddr>>) -> usize {
+        let addresses: Vec<Option<IpAddr>> = addresses.collect();
+        addresses
+            .iter()
+            .rposition(|ip| !ip.is_some_and(|ip| self.contains(ip)))
+            .unwrap_or(0)
+    }
+}
+
+/// The effective request origin and client, as seen by the browser.
+///
+/// Stored in the request extensions by [`CspMiddleware`] when trusted proxies are
+/// configured. Its [`origin`](Self::origin) is the request's `'self'`: the
+/// verifier from [`CspExtensions::policy_verifier`] and the shadow audit match
+/// against it, and the scheme decides whether `upgrade-insecure-requests` is sent.
+///
+/// [`CspMiddleware`]: crate::middleware::CspMiddleware
+/// [`CspExtensions::policy_verifier`]: crate::middleware::CspExtensions::policy_verifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardedInfo {
+    pub scheme: String,
+    pub host: String,
+    pub client_ip: Option<IpAddr>,
+}
+
+impl ForwardedInfo {
+    #[inline]
+    pub fn is_secure(&self) -> bool {
+        self.scheme == "https"
+    }
+
+    /// Returns `scheme://host`, suitable for [`PolicyVerifier::with_origin`].
+    ///
+    /// [`PolicyVerifier::with_origin`]: crate::security::PolicyVerifier::with_origin
+    pub fn origin(&self) -> String {
+        format!("{}://{}", self.scheme, self.host)
+    }
+}
+
+/// Returns the protocol of the last hop recorded in `Forwarded`, falling back to
+/// `X-Forwarded-Proto`; that hop was added by the proxy the request came from.
+pub(crate) fn forwarded_proto(headers: &HeaderMap) -> Option<&str> {
+    header_entries(headers, FORWARDED.as_str())
+        .last()
+        .and_then(|hop| hop_param(hop, "proto"))
+        .or_else(|| entry_from_right(headers, "x-forwarded-proto", 0))
+}
+
+/// Splits every `name` header line on commas, in the order the hops were added.
+fn header_entries<'a>(headers: &'a HeaderMap, name: &str) -> Vec<&'a str> {
+    headers
+        .get_all(name)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+/// Returns the entry `skip` places from the right of `name`, or the left-most
+/// one if the header has fewer entries.
+fn entry_from_right<'a>(headers: &'a HeaderMap, name: &str, skip: usize) -> Option<&'a str> {
+    let entries = header_entries(headers, name);
+    let index = entries.len().checked_sub(1)?.saturating_sub(skip);
+    entries.get(index).copied()
+}
+
+fn hop_param<'a>(hop: &'a str, name: &str) -> Option<&'a str> {
+    hop.split(';').find_map(|pair| {
+        let (key, value) = pair.trim().split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// Parses a `Forwarded` node (`192.0.2.1`, `192.0.2.1:8080`, `[2001:db8::1]:80`).
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Ok(ip) = node.parse() {
+        return Some(ip);
+    }
+    if let Some(bracketed) = node.strip_prefix('[') {
+        return bracketed.split(']').next()?.parse().ok();
+    }
+    node.rsplit_once(':')?.0.parse().ok()
+}
+
+/// Parses a CIDR block such as `10.0.0.0/8`; a bare address is a single host.
+pub(crate) fn parse_network(network: &str) -> Option<(IpAddr, u8)> {
+    let (address, prefix) = match network.trim().split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix)),
+        None => (network.trim(), None),
+    };
+    let address: IpAddr = address.parse().ok()?;
+    let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix.parse::<u8>().ok()?,
+        None => max_prefix,
+    };
+    (prefix <= max_prefix).then_some((address, prefix))
+}
+
+/// Returns `true` if `ip` lies in one of `networks`.
+pub(crate) fn networks_contain(networks: &[(IpAddr, u8)], ip: IpAddr) -> bool {
+    let ip = canonical_ip(ip);
+    networks
+        .iter()
+        .any(|&(network, prefix)| match (network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                masked(u32::from(network).into(), prefix, 32)
+                    == masked(u32::from(ip).into(), prefix, 32)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                masked(u128::from(network), prefix, 128) == masked(u128::from(ip), prefix, 128)
+            }
+            _ => false,
+        })
+}
+
+fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+#[inline]
+fn masked(value: u128, prefix: u8, bits: u8) -> u128 {
+    if prefix == 0 {
+        0
+    } else {
+        value >> (bits - prefix)
+    }
+}
@@ -1,33 +1,187 @@
 use crate::constants::DEFAULT_MAX_REPORT_SIZE;
 use crate::constants::DEFAULT_REPORT_PATH;
-use crate::monitoring::report::CspViolationReport;
+use crate::constants::DEFAULT_REPORT_READ_TIMEOUT;
+#[cfg(feature = "reporting")]
+use crate::logging::csp_log;
+#[cfg(feature = "reporting")]
+use crate::middleware::blocklist::Rejection;
+use crate::middleware::blocklist::ReportBlocklist;
+use crate::middleware::proxy::TrustedProxies;
+use crate::monitoring::enrich::Enricher;
+use crate::monitoring::queue::{OverflowStrategy, ReportQueue};
+use crate::monitoring::report::{CspViolationReport, MalformedReport};
+use crate::monitoring::route::ReportRouter;
+use crate::monitoring::scrub::ReportScrubber;
+#[cfg(feature = "reporting")]
+use crate::monitoring::user_agent::{parse_user_agent, BrowserInfo};
+#[cfg(feature = "reporting")]
+use actix_http::BoxedPayloadStream;
 use actix_web::{
     body::EitherBody,
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    guard::Guard,
     Error,
 };
 #[cfg(feature = "reporting")]
 use actix_web::{
-    error::ErrorBadRequest,
-    http::Method,
-    web::{self},
-    FromRequest, HttpResponse,
+    dev::Payload,
+    error::PayloadError,
+    http::{
+        header::{CONTENT_ENCODING, CONTENT_LENGTH, ORIGIN, USER_AGENT},
+        Method,
+    },
+    web::Bytes,
+    FromRequest, HttpRequest, HttpResponse,
 };
+#[cfg(feature = "reporting")]
+use futures::StreamExt;
 use futures::{
     future::{ready, Ready},
     Future,
 };
+use smallvec::{smallvec, SmallVec};
+#[cfg(feature = "reporting")]
+use std::net::IpAddr;
+#[cfg(feature = "reporting")]
+use std::panic::{catch_unwind, AssertUnwindSafe};
 #[cfg(feature = "reporting")]
-use log;
-use std::{borrow::Cow, pin::Pin, sync::Arc};
+use std::sync::atomic::Ordering;
+#[cfg(feature = "reporting")]
+use std::sync::OnceLock;
+use std::{
+    borrow::Cow,
+    pin::Pin,
+    rc::Rc,
+    sync::{atomic::AtomicUsize, Arc},
+    time::Duration,
+};
 
 pub(crate) type ViolationHandler = Arc<dyn Fn(CspViolationReport) + Send + Sync + 'static>;
+type PanicFallback = Arc<dyn Fn(&CspViolationReport) + Send + Sync + 'static>;
+type MalformedReportHandler = Arc<dyn Fn(MalformedReport) + Send + Sync + 'static>;
+
+/// A path on which [`CspReportingMiddleware`] accepts violation reports.
+///
+/// Paths are compared without trailing slashes, against both the full request
+/// path and the path relative to the enclosing `web::scope`, so `"/csp-report"`
+/// also matches `/api/csp-report/` when the middleware wraps `web::scope("/api")`.
+/// Plain strings convert into [`ReportPath::Exact`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportPath {
+    /// Matches this path only
+    Exact(Cow<'static, str>),
+    /// Matches this path and every path below it, on segment boundaries
+    Prefix(Cow<'static, str>),
+}
+
+impl ReportPath {
+    #[inline]
+    pub fn exact(path: impl Into<Cow<'static, str>>) -> Self {
+        Self::Exact(path.into())
+    }
 
+    #[inline]
+    pub fn prefix(path: impl Into<Cow<'static, str>>) -> Self {
+        Self::Prefix(path.into())
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let path = trim_trailing_slash(path);
+        match self {
+            Self::Exact(expected) => path == trim_trailing_slash(expected),
+            Self::Prefix(prefix) => {
+                let prefix = trim_trailing_slash(prefix);
+                match path.strip_prefix(prefix) {
+                    Some(rest) => rest.is_empty() || rest.starts_with('/') || prefix == "/",
+                    None => false,
+                }
+            }
+        }
+    }
+}
+
+impl From<&'static str> for ReportPath {
+    #[inline]
+    fn from(path: &'static str) -> Self {
+        Self::Exact(Cow::Borrowed(path))
+    }
+}
+
+impl From<String> for ReportPath {
+    #[inline]
+    fn from(path: String) -> Self {
+        Self::Exact(Cow::Owned(path))
+    }
+}
+
+impl From<Cow<'static, str>> for ReportPath {
+    #[inline]
+    fn from(path: Cow<'static, str>) -> Self {
+        Self::Exact(path)
+    }
+}
+
+#[inline]
+fn trim_trailing_slash(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    }
+}
+
+/// Decides which requests [`CspReportingMiddleware`] handles as reports.
+#[derive(Clone)]
+struct ReportMatcher {
+    paths: SmallVec<[ReportPath; 2]>,
+    guards: Vec<Arc<dyn Guard + Send + Sync>>,
+}
+
+impl ReportMatcher {
+    #[cfg_attr(not(feature = "reporting"), allow(dead_code))]
+    fn matches(&self, req: &ServiceRequest) -> bool {
+        let path = req.path();
+        let scoped = req.match_info().unprocessed();
+        if !self
+            .paths
+            .iter()
+            .any(|report_path| report_path.matches(path) || report_path.matches(scoped))
+        {
+            return false;
+        }
+
+        let context = req.guard_ctx();
+        self.guards.iter().all(|guard| guard.check(&context))
+    }
+}
+
+/// Accepts violation reports POSTed to its report paths and passes everything
+/// else through.
+///
+/// ```rust
+/// use actix_web::{guard, web, App};
+/// use actix_web_csp::middleware::{CspReportingMiddleware, ReportPath};
+///
+/// let reporting = CspReportingMiddleware::new(|report| println!("{}", report.blocked_uri))
+///     .with_report_paths([ReportPath::exact("/csp-report"), ReportPath::prefix("/reports")])
+///     .with_report_guard(guard::Header("content-type", "application/csp-report"));
+///
+/// let app = App::new().service(web::scope("/api").wrap(reporting));
+/// ```
 pub struct CspReportingMiddleware {
     handler: ViolationHandler,
-    report_path: Cow<'static, str>,
+    matcher: ReportMatcher,
+    malformed_handler: Option<MalformedReportHandler>,
+    reject_malformed: bool,
     max_report_size: usize,
+    read_timeout: Duration,
+    in_flight: InFlightLimit,
     stats: Arc<crate::monitoring::stats::CspStats>,
+    trusted_proxies: Option<Arc<TrustedProxies>>,
+    blocklist: Option<Arc<ReportBlocklist>>,
+    fingerprint_clients: bool,
+    #[cfg_attr(not(feature = "reporting"), allow(dead_code))]
+    report_queue: Option<(usize, OverflowStrategy)>,
+    processors: ReportProcessors,
 }
 
 impl CspReportingMiddleware {
@@ -37,30 +191,265 @@ impl CspReportingMiddleware {
     {
         Self {
             handler: Arc::new(handler),
-            report_path: Cow::Borrowed(DEFAULT_REPORT_PATH),
+            matcher: ReportMatcher {
+                paths: smallvec![ReportPath::Exact(Cow::Borrowed(DEFAULT_REPORT_PATH))],
+                guards: Vec::new(),
+            },
+            malformed_handler: None,
+            reject_malformed: false,
             max_report_size: DEFAULT_MAX_REPORT_SIZE,
+            read_timeout: DEFAULT_REPORT_READ_TIMEOUT,
+            in_flight: InFlightLimit::default(),
             stats: Arc::new(crate::monitoring::stats::CspStats::new()),
+            trusted_proxies: None,
+            blocklist: None,
+            fingerprint_clients: false,
+            report_queue: None,
+            processors: ReportProcessors::default(),
         }
     }
 
+    /// Replaces the report paths with `path`.
     #[inline]
-    pub fn with_report_path(mut self, path: impl Into<Cow<'static, str>>) -> Self {
-        self.report_path = path.into();
+    pub fn with_report_path(mut self, path: impl Into<ReportPath>) -> Self {
+        self.matcher.paths = smallvec![path.into()];
         self
     }
 
+    /// Replaces the report paths, e.g. to serve a legacy `report-uri` endpoint
+    /// next to a Reporting API one.
+    pub fn with_report_paths<I>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<ReportPath>,
+    {
+        self.matcher.paths = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Only handles reports for which `guard` also passes. Can be called
+    /// repeatedly; every guard must pass.
+    #[inline]
+    pub fn with_report_guard(mut self, guard: impl Guard + Send + Sync + 'static) -> Self {
+        self.matcher.guards.push(Arc::new(guard));
+        self
+    }
+
+    #[inline]
+    pub fn report_paths(&self) -> &[ReportPath] {
+        &self.matcher.paths
+    }
+
+    /// Sets the largest report body accepted, in bytes (16 KiB by default).
+    ///
+    /// A smaller limit in the app's `web::PayloadConfig` takes precedence.
+    /// Bodies over the limit are rejected with `413 Payload Too Large` as soon
+    /// as they exceed it, without buffering the rest.
     #[inline]
     pub fn with_max_report_size(mut self, size: usize) -> Self {
         self.max_report_size = size;
         self
     }
 
+    /// Sets how long a client may take to send a report body, ten seconds by
+    /// default; `Duration::ZERO` removes the limit.
+    ///
+    /// Clients that trickle their body in slower than this get
+    /// `408 Request Timeout`, counted in [`CspStats::timed_out_report_count`],
+    /// so they cannot hold a worker open indefinitely.
+    ///
+    /// [`CspStats::timed_out_report_count`]: crate::monitoring::CspStats::timed_out_report_count
+    #[inline]
+    pub fn with_report_read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Caps how many reports are read and processed at once. Unlimited by
+    /// default.
+    ///
+    /// Reports beyond the cap are answered with `503 Service Unavailable`
+    /// without reading their body, and counted in
+    /// [`CspStats::shed_report_count`]. The count belongs to this middleware
+    /// value and is shared by every service built from it, on any thread.
+    /// `HttpServer` runs the app factory once per worker, so a middleware
+    /// created inside the factory gets a count of its own on each worker: with
+    /// N workers, up to N times `limit` reports can be in flight.
+    ///
+    /// ```rust
+    /// use actix_web_csp::middleware::CspReportingMiddleware;
+    /// use std::time::Duration;
+    ///
+    /// let reporting = CspReportingMiddleware::new(|report| println!("{}", report.blocked_uri))
+    ///     .with_report_read_timeout(Duration::from_secs(2))
+    ///     .with_max_concurrent_reports(64);
+    /// assert_eq!(reporting.max_concurrent_reports(), Some(64));
+    /// ```
+    ///
+    /// [`CspStats::shed_report_count`]: crate::monitoring::CspStats::shed_report_count
+    #[inline]
+    pub fn with_max_concurrent_reports(mut self, limit: usize) -> Self {
+        self.in_flight.max = Some(limit);
+        self
+    }
+
+    #[inline]
+    pub fn report_read_timeout(&self) -> Duration {
+        self.read_timeout
+    }
+
+    #[inline]
+    pub fn max_concurrent_reports(&self) -> Option<usize> {
+        self.in_flight.max
+    }
+
+    /// Reports currently being read or processed.
+    #[inline]
+    pub fn reports_in_flight(&self) -> usize {
+        self.in_flight.current()
+    }
+
+    /// Calls `handler` with every report body that could not be parsed, for
+    /// example to capture samples of junk traffic.
+    #[inline]
+    pub fn with_malformed_report_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(MalformedReport) + Send + Sync + 'static,
+    {
+        self.malformed_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Answers malformed reports with `400 Bad Request` instead of
+    /// `204 No Content`.
+    ///
+    /// Off by default: browsers ignore the status either way, and a uniform
+    /// answer tells probing clients nothing.
+    #[inline]
+    pub fn with_reject_malformed_reports(mut self, reject: bool) -> Self {
+        self.reject_malformed = reject;
+        self
+    }
+
     #[inline]
     pub fn with_stats(mut self, stats: Arc<crate::monitoring::stats::CspStats>) -> Self {
         self.stats = stats;
         self
     }
 
+    /// Resolves the reporting browser's address through `X-Forwarded-For` or
+    /// `Forwarded` when the peer is one of `proxies`.
+    #[inline]
+    pub fn with_trusted_proxies(mut self, proxies: TrustedProxies) -> Self {
+        self.trusted_proxies = Some(Arc::new(proxies));
+        self
+    }
+
+    /// Refuses reports from blocked addresses and origins, and from clients
+    /// banned for flooding. Combine with [`with_trusted_proxies`] so bans hit
+    /// the browser rather than the proxy.
+    ///
+    /// [`with_trusted_proxies`]: Self::with_trusted_proxies
+    #[inline]
+    pub fn with_blocklist(mut self, blocklist: ReportBlocklist) -> Self {
+        self.blocklist = Some(Arc::new(blocklist));
+        self
+    }
+
+    /// Tags each report with [`client_fingerprint`], a salted hash of the
+    /// client address and `User-Agent`, and counts reports per fingerprint in
+    /// [`CspStats::top_client_fingerprints`].
+    ///
+    /// A handful of fingerprints behind most reports points at one misbehaving
+    /// browser or extension rather than a broken page. The salt is random per
+    /// process, so fingerprints cannot be traced back to an address and do not
+    /// survive a restart.
+    ///
+    /// [`client_fingerprint`]: crate::monitoring::CspViolationReport::client_fingerprint
+    /// [`CspStats::top_client_fingerprints`]: crate::monitoring::CspStats::top_client_fingerprints
+    #[inline]
+    pub fn with_client_fingerprinting(mut self, enabled: bool) -> Self {
+        self.fingerprint_clients = enabled;
+        self
+    }
+
+    /// Runs `enricher` on every parsed report before it is counted and handed
+    /// to the handler. Can be called repeatedly; enrichers run in order.
+    #[inline]
+    pub fn with_enricher(mut self, enricher: impl Enricher) -> Self {
+        self.processors.enrichers.push(Arc::new(enricher));
+        self
+    }
+
+    /// Calls `fallback` with a copy of the report whenever the violation
+    /// handler panics, e.g. to log it somewhere simpler.
+    ///
+    /// Panics in the handler and in enrichers are always caught and counted in
+    /// [`CspStats::handler_panic_count`]; the endpoint keeps answering either way.
+    /// Setting a fallback costs a clone of every report.
+    ///
+    /// [`CspStats::handler_panic_count`]: crate::monitoring::CspStats::handler_panic_count
+    #[inline]
+    pub fn with_handler_panic_fallback<F>(mut self, fallback: F) -> Self
+    where
+        F: Fn(&CspViolationReport) + Send + Sync + 'static,
+    {
+        self.processors.panic_fallback = Some(Arc::new(fallback));
+        self
+    }
+
+    /// Hands each report to the sink `router` picks for its directive; reports
+    /// that match no route still go to the handler given to [`new`](Self::new).
+    ///
+    /// Routed reports are counted and protected against panics like any other.
+    #[inline]
+    pub fn with_router(mut self, router: ReportRouter) -> Self {
+        self.processors.router = Some(router);
+        self
+    }
+
+    /// Hands reports to the handler through a queue of up to `capacity` reports,
+    /// drained by a background thread, so a slow handler such as a database
+    /// insert or a webhook no longer holds up the reporting requests.
+    ///
+    /// Reports are enriched, scrubbed and counted before they are queued; the
+    /// handler, the router's sinks and the panic fallback run on the queue's
+    /// thread. `overflow` decides what happens when the handler falls behind.
+    /// The queue depth and reports dropped on overflow are tracked in
+    /// [`CspStats::report_queue_depth`] and [`CspStats::dropped_report_count`].
+    ///
+    /// ```rust
+    /// use actix_web_csp::middleware::CspReportingMiddleware;
+    /// use actix_web_csp::monitoring::OverflowStrategy;
+    ///
+    /// let reporting = CspReportingMiddleware::new(|report| {
+    ///     // e.g. a blocking database insert
+    ///     println!("{}", report.blocked_uri);
+    /// })
+    /// .with_report_queue(1000, OverflowStrategy::DropOldest);
+    /// ```
+    ///
+    /// [`CspStats::report_queue_depth`]: crate::monitoring::CspStats::report_queue_depth
+    /// [`CspStats::dropped_report_count`]: crate::monitoring::CspStats::dropped_report_count
+    #[inline]
+    pub fn with_report_queue(mut self, capacity: usize, overflow: OverflowStrategy) -> Self {
+        self.report_queue = Some((capacity, overflow));
+        self
+    }
+
+    /// Scrubs personal data from every parsed report, after the enrichers and
+    /// before it is counted and handed to the handler.
+    #[inline]
+    pub fn with_scrubber(mut self, scrubber: ReportScrubber) -> Self {
+        self.processors.scrubber = Some(scrubber);
+        self
+    }
+
+    #[inline]
+    pub fn blocklist(&self) -> Option<&Arc<ReportBlocklist>> {
+        self.blocklist.as_ref()
+    }
+
     #[inline]
     pub fn stats(&self) -> &Arc<crate::monitoring::stats::CspStats> {
         &self.stats
@@ -69,7 +458,7 @@ impl CspReportingMiddleware {
 
 impl<S, B> Transform<S, ServiceRequest> for CspReportingMiddleware
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + Clone + 'static,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -80,28 +469,57 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
+        #[cfg_attr(not(feature = "reporting"), allow(unused_mut))]
+        let mut processors = self.processors.clone();
+        #[cfg(feature = "reporting")]
+        if let Some((capacity, overflow)) = self.report_queue {
+            let delivery = self.processors.clone();
+            let stats = self.stats.clone();
+            let handler = self.handler.clone();
+            processors.queue =
+                ReportQueue::spawn(capacity, overflow, self.stats.clone(), move |report| {
+                    deliver_report(report, &delivery, &stats, &handler)
+                });
+        }
+
         ready(Ok(CspReportingMiddlewareService {
-            service,
+            service: Rc::new(service),
             handler: self.handler.clone(),
-            report_path: self.report_path.clone(),
+            matcher: Rc::new(self.matcher.clone()),
+            malformed_handler: self.malformed_handler.clone(),
+            reject_malformed: self.reject_malformed,
             max_report_size: self.max_report_size,
+            read_timeout: self.read_timeout,
+            in_flight: self.in_flight.clone(),
             stats: self.stats.clone(),
+            trusted_proxies: self.trusted_proxies.clone(),
+            blocklist: self.blocklist.clone(),
+            fingerprint_clients: self.fingerprint_clients,
+            processors: Arc::new(processors),
         }))
     }
 }
 
 #[cfg_attr(not(feature = "reporting"), allow(dead_code))]
 pub struct CspReportingMiddlewareService<S> {
-    service: S,
+    service: Rc<S>,
     handler: ViolationHandler,
-    report_path: Cow<'static, str>,
+    matcher: Rc<ReportMatcher>,
+    malformed_handler: Option<MalformedReportHandler>,
+    reject_malformed: bool,
     max_report_size: usize,
+    read_timeout: Duration,
+    in_flight: InFlightLimit,
     stats: Arc<crate::monitoring::stats::CspStats>,
+    trusted_proxies: Option<Arc<TrustedProxies>>,
+    blocklist: Option<Arc<ReportBlocklist>>,
+    fingerprint_clients: bool,
+    processors: Arc<ReportProcessors>,
 }
 
 impl<S, B> Service<ServiceRequest> for CspReportingMiddlewareService<S>
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + Clone + 'static,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -112,43 +530,223 @@ where
     forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        #[cfg(not(feature = "reporting"))]
-        {
-            let service = self.service.clone();
-            return Box::pin(async move {
-                let res = service.call(req).await?;
-                Ok(res.map_into_left_body())
-            });
-        }
-
         #[cfg(feature = "reporting")]
-        if req.path() == self.report_path && req.method() == Method::POST {
+        if req.method() == Method::POST && self.matcher.matches(&req) {
             let handler = self.handler.clone();
+            let malformed_handler = self.malformed_handler.clone();
+            let reject_malformed = self.reject_malformed;
             let max_size = self.max_report_size;
+            let read_timeout = self.read_timeout;
             let stats = self.stats.clone();
+            let processors = self.processors.clone();
+            let client_ip = match &self.trusted_proxies {
+                Some(proxies) => proxies.resolve(&req).client_ip,
+                None => req.peer_addr().map(|addr| addr.ip()),
+            };
 
-            Box::pin(async move {
-                let (http_req, mut payload) = req.into_parts();
-                let body = match web::Bytes::from_request(&http_req, &mut payload).await {
-                    Ok(bytes) => bytes,
-                    Err(e) => return Err(e),
-                };
+            if let Some(blocklist) = &self.blocklist {
+                let origin = req
+                    .headers()
+                    .get(ORIGIN)
+                    .and_then(|value| value.to_str().ok());
+                if let Err(rejection) = blocklist.check(client_ip, origin) {
+                    stats.increment_blocked_report_count();
+                    let response = match rejection {
+                        Rejection::Blocked => HttpResponse::Forbidden().finish(),
+                        Rejection::Banned => HttpResponse::TooManyRequests().finish(),
+                    };
+                    return Box::pin(ready(Ok(req.into_response(response.map_into_right_body()))));
+                }
+            }
+
+            let Some(permit) = self.in_flight.acquire() else {
+                stats.increment_shed_report_count();
+                csp_log!(report: Debug, "CSP report to {} shed: too many in flight", req.path());
+                let response = HttpResponse::ServiceUnavailable().finish();
+                return Box::pin(ready(Ok(req.into_response(response.map_into_right_body()))));
+            };
 
-                process_violation_bytes(&body, max_size, &stats, &handler)?;
+            let user_agent = req
+                .headers()
+                .get(USER_AGENT)
+                .and_then(|value| value.to_str().ok());
+            let client = ReportClient {
+                ip: client_ip,
+                fingerprint: self
+                    .fingerprint_clients
+                    .then(|| client_fingerprint(client_ip, user_agent)),
+                browser: user_agent.and_then(parse_user_agent),
+            };
 
-                let response = HttpResponse::Ok().finish().map_into_right_body();
+            return Box::pin(async move {
+                let _permit = permit;
+                let (http_req, mut payload) = req.into_parts();
+                let body =
+                    read_report_body(&http_req, &mut payload, max_size, read_timeout, &stats)
+                        .await?;
+
+                let response = match process_violation_bytes(
+                    body,
+                    client,
+                    &processors,
+                    &stats,
+                    &handler,
+                )
+                .await
+                {
+                    Ok(()) => HttpResponse::Ok().finish(),
+                    Err(malformed) => {
+                        if let Some(malformed_handler) = &malformed_handler {
+                            malformed_handler(malformed);
+                        }
+                        if reject_malformed {
+                            HttpResponse::BadRequest().finish()
+                        } else {
+                            HttpResponse::NoContent().finish()
+                        }
+                    }
+                }
+                .map_into_right_body();
                 Ok(ServiceResponse::new(http_req, response))
+            });
+        }
+
+        let service = self.service.clone();
+        Box::pin(async move {
+            let res = service.call(req).await?;
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+/// Shared count of reports being handled, and the optional cap on it.
+#[derive(Debug, Clone, Default)]
+struct InFlightLimit {
+    max: Option<usize>,
+    current: Arc<AtomicUsize>,
+}
+
+impl InFlightLimit {
+    #[inline]
+    fn current(&self) -> usize {
+        self.current.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Takes a slot, or returns `None` when the cap is reached.
+    #[cfg(feature = "reporting")]
+    fn acquire(&self) -> Option<InFlightPermit> {
+        let max = self.max.unwrap_or(usize::MAX);
+        self.current
+            .fetch_update(Ordering::AcqRel, Ordering::Relaxed, |current| {
+                (current < max).then_some(current + 1)
             })
-        } else {
-            let service = self.service.clone();
-            Box::pin(async move {
-                let res = service.call(req).await?;
-                Ok(res.map_into_left_body())
-            })
+            .ok()?;
+        Some(InFlightPermit(self.current.clone()))
+    }
+}
+
+/// Gives its slot back when the report is done.
+#[cfg(feature = "reporting")]
+struct InFlightPermit(Arc<AtomicUsize>);
+
+#[cfg(feature = "reporting")]
+impl Drop for InFlightPermit {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Reads a report body, giving up as soon as it grows past the size limit or
+/// takes longer than `timeout`.
+///
+/// The limit is `max_size` or the app's [`PayloadConfig`] limit, whichever is
+/// smaller; the app's `PayloadConfig` applies as it would to any raw body
+/// extractor. A `Content-Length` above the limit is rejected before anything is
+/// read; otherwise the payload is streamed and dropped at the first chunk over
+/// the limit. Either way the request fails with `413 Payload Too Large` and the
+/// rejection is counted in [`CspStats::oversized_report_count`]. Browsers never
+/// compress reports, so a body with a `Content-Encoding` other than `identity`
+/// fails with `415 Unsupported Media Type` without being read. A body still
+/// incomplete after `timeout` fails with `408 Request Timeout` and is counted
+/// in [`CspStats::timed_out_report_count`]; a zero `timeout` waits forever.
+///
+/// [`PayloadConfig`]: actix_web::web::PayloadConfig
+/// [`CspStats::oversized_report_count`]: crate::monitoring::CspStats::oversized_report_count
+/// [`CspStats::timed_out_report_count`]: crate::monitoring::CspStats::timed_out_report_count
+#[cfg(feature = "reporting")]
+pub(crate) async fn read_report_body(
+    req: &HttpRequest,
+    payload: &mut Payload,
+    max_size: usize,
+    timeout: Duration,
+    stats: &crate::monitoring::stats::CspStats,
+) -> Result<Bytes, Error> {
+    let read = read_limited_body(req, payload, max_size, stats);
+    if timeout.is_zero() {
+        return read.await;
+    }
+    match actix_web::rt::time::timeout(timeout, read).await {
+        Ok(body) => body,
+        Err(_) => {
+            stats.increment_timed_out_report_count();
+            csp_log!(report: Debug, "CSP report to {} not received within {timeout:?}", req.path());
+            Err(actix_web::error::ErrorRequestTimeout(
+                "report body not received in time",
+            ))
         }
     }
 }
 
+#[cfg(feature = "reporting")]
+async fn read_limited_body(
+    req: &HttpRequest,
+    payload: &mut Payload,
+    max_size: usize,
+    stats: &crate::monitoring::stats::CspStats,
+) -> Result<Bytes, Error> {
+    let reject = || {
+        stats.increment_oversized_report_count();
+        csp_log!(report: Debug, "CSP report to {} exceeds its size limit", req.path());
+        Error::from(PayloadError::Overflow)
+    };
+
+    if let Some(encoding) = req
+        .headers()
+        .get(CONTENT_ENCODING)
+        .filter(|encoding| !encoding.as_bytes().eq_ignore_ascii_case(b"identity"))
+    {
+        csp_log!(report: Debug, "CSP report to {} has Content-Encoding {encoding:?}", req.path());
+        return Err(actix_web::error::ErrorUnsupportedMediaType(
+            "compressed reports are not accepted",
+        ));
+    }
+
+    let declared = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok()?.parse::<usize>().ok());
+    if declared.is_some_and(|length| length > max_size) {
+        return Err(reject());
+    }
+
+    let mut received = 0;
+    let limited: BoxedPayloadStream = Box::pin(payload.take().map(move |chunk| {
+        let chunk = chunk?;
+        received += chunk.len();
+        if received > max_size {
+            return Err(PayloadError::Overflow);
+        }
+        Ok(chunk)
+    }));
+
+    Bytes::from_request(req, &mut Payload::from(limited))
+        .await
+        .map_err(|error| match error.as_error::<PayloadError>() {
+            Some(PayloadError::Overflow) => reject(),
+            _ => error,
+        })
+}
+
 #[cfg(feature = "reporting")]
 #[inline]
 pub(crate) fn process_violation_report(
@@ -165,42 +763,167 @@ pub(crate) fn process_violation_report(
     }
 }
 
+/// Steps run on every parsed report before it is counted and handled, the
+/// routing to a sink, and the fallback for when handling panics.
+#[derive(Clone, Default)]
+pub(crate) struct ReportProcessors {
+    enrichers: Vec<Arc<dyn Enricher>>,
+    scrubber: Option<ReportScrubber>,
+    router: Option<ReportRouter>,
+    panic_fallback: Option<PanicFallback>,
+    /// Hands reports to the handler on another thread, when configured
+    #[cfg_attr(not(feature = "reporting"), allow(dead_code))]
+    queue: Option<ReportQueue>,
+}
+
+impl ReportProcessors {
+    #[cfg_attr(not(feature = "reporting"), allow(dead_code))]
+    fn apply(&self, report: &mut CspViolationReport) {
+        for enricher in &self.enrichers {
+            enricher.enrich(report);
+        }
+        if let Some(scrubber) = &self.scrubber {
+            scrubber.scrub(report);
+        }
+    }
+}
+
+/// What is known about the client that sent a report.
 #[cfg(feature = "reporting")]
-pub(crate) fn process_violation_bytes(
-    bytes: &[u8],
-    max_size: usize,
-    stats: &crate::monitoring::stats::CspStats,
-    handler: &ViolationHandler,
-) -> Result<(), Error> {
-    if bytes.len() > max_size {
-        return Err(ErrorBadRequest("CSP report too large"));
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReportClient {
+    pub(crate) ip: Option<IpAddr>,
+    pub(crate) fingerprint: Option<String>,
+    pub(crate) browser: Option<BrowserInfo>,
+}
+
+/// Salted SHA-256 of the client address and `User-Agent`, shortened to 16
+/// base64 characters.
+#[cfg(feature = "reporting")]
+fn client_fingerprint(ip: Option<IpAddr>, user_agent: Option<&str>) -> String {
+    static SALT: OnceLock<[u8; 16]> = OnceLock::new();
+    let salt = SALT.get_or_init(|| {
+        let mut salt = [0u8; 16];
+        getrandom::getrandom(&mut salt).expect("Failed to generate random bytes");
+        salt
+    });
+
+    let mut data = salt.to_vec();
+    match ip {
+        Some(IpAddr::V4(ip)) => data.extend_from_slice(&ip.octets()),
+        Some(IpAddr::V6(ip)) => data.extend_from_slice(&ip.octets()),
+        None => {}
     }
+    data.push(0);
+    data.extend_from_slice(user_agent.unwrap_or_default().as_bytes());
 
-    match process_violation_report(bytes) {
-        Ok(Some(report)) => {
-            stats.increment_violation_count();
-            handler(report);
-        }
-        Ok(None) => {
-            log::debug!("CSP violation report missing 'csp-report' field");
+    let mut fingerprint = crate::security::hash::HashGenerator::generate(
+        crate::security::hash::HashAlgorithm::Sha256,
+        &data,
+    );
+    fingerprint.truncate(16);
+    fingerprint
+}
+
+/// Parses `body`, runs the report processors and passes the report to `handler`.
+///
+/// The body size and the time spent in `handler` are recorded in [`CspStats`].
+///
+/// Bodies that are not a `{"csp-report": ...}` document are counted in
+/// [`CspStats::malformed_report_count`] and handed back to the caller.
+///
+/// [`CspStats`]: crate::monitoring::CspStats
+/// [`CspStats::malformed_report_count`]: crate::monitoring::CspStats::malformed_report_count
+#[cfg(feature = "reporting")]
+pub(crate) async fn process_violation_bytes(
+    body: Bytes,
+    client: ReportClient,
+    processors: &ReportProcessors,
+    stats: &crate::monitoring::stats::CspStats,
+    handler: &ViolationHandler,
+) -> Result<(), MalformedReport> {
+    stats.record_report_size(body.len());
+    let reason = match process_violation_report(&body) {
+        Ok(Some(mut report)) => {
+            report.client_ip = client.ip;
+            report.client_fingerprint = client.fingerprint;
+            report.browser = client.browser;
+            handle_report(report, processors, stats, handler).await;
+            return Ok(());
         }
-        Err(e) => {
-            log::error!("Failed to process CSP violation report: {}", e);
+        Ok(None) => "missing 'csp-report' field".to_string(),
+        Err(error) => error.to_string(),
+    };
+
+    stats.increment_malformed_report_count();
+    csp_log!(report: Debug, "Malformed CSP violation report: {reason}");
+    Err(MalformedReport {
+        body,
+        reason,
+        client_ip: client.ip,
+    })
+}
+
+/// Runs the processors on `report` and queues it or passes it to the handler,
+/// containing any panic so one bad report cannot take the endpoint down.
+#[cfg(feature = "reporting")]
+async fn handle_report(
+    mut report: CspViolationReport,
+    processors: &ReportProcessors,
+    stats: &crate::monitoring::stats::CspStats,
+    handler: &ViolationHandler,
+) {
+    let processed = catch_unwind(AssertUnwindSafe(|| {
+        processors.apply(&mut report);
+        report
+    }));
+    let Ok(report) = processed else {
+        stats.increment_handler_panic_count();
+        csp_log!(report: Error, "CSP report enricher panicked; the report was dropped");
+        return;
+    };
+
+    stats.record_violation(&report);
+    if let Some(queue) = &processors.queue {
+        if let Some(report) = queue.push(report) {
+            let queue = queue.clone();
+            let _ = actix_web::rt::task::spawn_blocking(move || queue.push_blocking(report)).await;
         }
+        return;
     }
-
-    Ok(())
+    deliver_report(report, processors, stats, handler);
 }
 
-#[cfg(not(feature = "reporting"))]
-#[allow(dead_code)]
-pub(crate) fn process_violation_bytes(
-    _bytes: &[u8],
-    _max_size: usize,
-    _stats: &crate::monitoring::stats::CspStats,
-    _handler: &ViolationHandler,
-) -> Result<(), Error> {
-    Ok(())
+/// Passes `report` to the sink the router picks, or to `handler`.
+#[cfg(feature = "reporting")]
+fn deliver_report(
+    report: CspViolationReport,
+    processors: &ReportProcessors,
+    stats: &crate::monitoring::stats::CspStats,
+    handler: &ViolationHandler,
+) {
+    let fallback_copy = processors
+        .panic_fallback
+        .as_ref()
+        .map(|fallback| (fallback, report.clone()));
+    let handler = processors
+        .router
+        .as_ref()
+        .and_then(|router| router.sink_for(&report))
+        .unwrap_or(handler);
+    let started = std::time::Instant::now();
+    let handled = catch_unwind(AssertUnwindSafe(|| handler(report)));
+    stats.record_handler_duration(started.elapsed());
+
+    if handled.is_err() {
+        stats.increment_handler_panic_count();
+        csp_log!(report: Error, "CSP violation handler panicked");
+        if let Some((fallback, report)) = fallback_copy {
+            if catch_unwind(AssertUnwindSafe(|| fallback(&report))).is_err() {
+                csp_log!(report: Error, "CSP violation handler panic fallback panicked");
+            }
+        }
+    }
 }
 
 #[inline]
@@ -0,0 +1,178 @@
+use crate::core::directives::Directive;
+use crate::core::policy::CspPolicy;
+use crate::core::source::Source;
+use crate::middleware::extensions::CspExtensions;
+use actix_web::{http::header::ContentType, HttpMessage, HttpResponse, HttpResponseBuilder};
+use smallvec::SmallVec;
+use std::borrow::Cow;
+
+/// Placeholder replaced with the request nonce by [`CspResponseExt::csp_nonce_body`].
+pub const NONCE_PLACEHOLDER: &str = "{{csp-nonce}}";
+
+/// Marker extension that makes [`CspMiddleware`] leave a response untouched.
+///
+/// Insert it into the request extensions, from a handler or an inner middleware, or
+/// into the response through [`CspResponseExt::disable_csp`]. No CSP, HSTS or
+/// reporting headers are added and the request nonce is discarded; useful for pages
+/// rendered by third-party SDKs, such as OAuth callbacks, that cannot carry a nonce.
+///
+/// ```rust
+/// use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+/// use actix_web_csp::middleware::CspDisabled;
+///
+/// async fn oauth_callback(req: HttpRequest) -> HttpResponse {
+///     req.extensions_mut().insert(CspDisabled);
+///     HttpResponse::Ok().body("<script>window.opener.postMessage('done', '*')</script>")
+/// }
+/// ```
+///
+/// [`CspMiddleware`]: crate::middleware::CspMiddleware
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CspDisabled;
+
+/// Marker extension that asks [`CspMiddleware`] to add the request nonce to the
+/// `<script>` and `<style>` tags of this `text/html` response.
+///
+/// Insert it into the request extensions or into the response through
+/// [`CspResponseExt::rewrite_html_nonces`]. Only mark responses rendered
+/// entirely from trusted templates or files: the rewriter nonces every tag it
+/// finds, including one injected through untrusted content, and a page that
+/// echoes user input should carry its nonce through [`NONCE_PLACEHOLDER`]
+/// instead. Tags that already carry a `nonce` attribute are left alone.
+///
+/// Unlike [`CspConfigBuilder::with_html_nonce_rewriting`], which rewrites every
+/// HTML response, the middleware cannot strip `If-None-Match` or `Range` before
+/// it knows the response is rewritten, so a marked handler should always
+/// render the full page. A nonce generator must be configured.
+///
+/// [`CspMiddleware`]: crate::middleware::CspMiddleware
+/// [`CspConfigBuilder::with_html_nonce_rewriting`]: crate::core::CspConfigBuilder::with_html_nonce_rewriting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HtmlNonceRewrite;
+
+#[derive(Debug, Clone)]
+enum PolicyChange {
+    AppendSource(Cow<'static, str>, Source),
+    ReplaceDirective(Directive),
+}
+
+/// Per-response adjustments recorded by [`CspResponseExt`] or [`CspExtensions`] and
+/// applied by the middleware.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ResponseCspOverrides {
+    changes: SmallVec<[PolicyChange; 2]>,
+}
+
+impl ResponseCspOverrides {
+    #[inline]
+    pub(crate) fn has_changes(&self) -> bool {
+        !self.changes.is_empty()
+    }
+
+    #[inline]
+    pub(crate) fn append_source(&mut self, directive: Cow<'static, str>, source: Source) {
+        self.changes
+            .push(PolicyChange::AppendSource(directive, source));
+    }
+
+    #[inline]
+    pub(crate) fn replace_directive(&mut self, directive: Directive) {
+        self.changes.push(PolicyChange::ReplaceDirective(directive));
+    }
+
+    /// Combines overrides recorded on the request with those on the response;
+    /// the response's changes are applied last.
+    pub(crate) fn merge(mut self, other: Self) -> Self {
+        self.changes.extend(other.changes);
+        self
+    }
+
+    /// Applies the recorded changes, in order, to a copy of `policy`.
+    pub(crate) fn apply(&self, policy: &CspPolicy) -> CspPolicy {
+        let mut policy = policy.clone();
+        for change in &self.changes {
+            match change {
+                PolicyChange::AppendSource(directive, source) => {
+                    policy.append_source(directive.clone(), source.clone());
+                }
+                PolicyChange::ReplaceDirective(directive) => {
+                    policy.add_directive(directive.clone());
+                }
+            }
+        }
+        policy
+    }
+}
+
+/// Handler-side helpers for shaping the CSP header of a single response.
+///
+/// The helpers only record intent on the response; [`CspMiddleware`] applies it
+/// when the response passes back through, so they have no effect on routes that
+/// are not wrapped by the middleware.
+///
+/// ```rust
+/// use actix_web::{HttpRequest, HttpResponse};
+/// use actix_web_csp::{CspResponseExt, Source};
+///
+/// async fn page(req: HttpRequest) -> HttpResponse {
+///     HttpResponse::Ok()
+///         .append_csp_source("img-src", Source::Host("images.example.com".into()))
+///         .csp_nonce_body(&req, r#"<script nonce="{{csp-nonce}}">init()</script>"#)
+/// }
+/// ```
+///
+/// [`CspMiddleware`]: crate::middleware::CspMiddleware
+pub trait CspResponseExt {
+    /// Sends `html` as the body after replacing every [`NONCE_PLACEHOLDER`] with the
+    /// request nonce. Without a nonce the placeholder is replaced with an empty string.
+    fn csp_nonce_body(&mut self, req: &impl HttpMessage, html: impl Into<String>) -> HttpResponse;
+
+    /// Adds `source` to `directive` for this response only.
+    fn append_csp_source(
+        &mut self,
+        directive: impl Into<Cow<'static, str>>,
+        source: Source,
+    ) -> &mut Self;
+
+    /// Suppresses the CSP header for this response by attaching [`CspDisabled`].
+    fn disable_csp(&mut self) -> &mut Self;
+
+    /// Adds the request nonce to the `<script>` and `<style>` tags of this HTML
+    /// response by attaching [`HtmlNonceRewrite`]; see there for when that is safe.
+    fn rewrite_html_nonces(&mut self) -> &mut Self;
+}
+
+impl CspResponseExt for HttpResponseBuilder {
+    fn csp_nonce_body(&mut self, req: &impl HttpMessage, html: impl Into<String>) -> HttpResponse {
+        let html = html.into();
+        let nonce = req.get_nonce().unwrap_or_default();
+        let body = if html.contains(NONCE_PLACEHOLDER) {
+            html.replace(NONCE_PLACEHOLDER, &nonce)
+        } else {
+            html
+        };
+
+        self.insert_header(ContentType::html()).body(body)
+    }
+
+    fn append_csp_source(
+        &mut self,
+        directive: impl Into<Cow<'static, str>>,
+        source: Source,
+    ) -> &mut Self {
+        self.extensions_mut()
+            .get_or_insert_with(ResponseCspOverrides::default)
+            .append_source(directive.into(), source);
+        self
+    }
+
+    fn disable_csp(&mut self) -> &mut Self {
+        self.extensions_mut().insert(CspDisabled);
+        self
+    }
+
+    fn rewrite_html_nonces(&mut self) -> &mut Self {
+        self.extensions_mut().insert(HtmlNonceRewrite);
+        self
+    }
+}
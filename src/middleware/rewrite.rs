@@ -0,0 +1,307 @@
+use crate::middleware::shadow::ShadowTap;
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::http::header::{
+    HeaderMap, HeaderName, ACCEPT, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, RANGE,
+};
+use bytes::{Bytes, BytesMut};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Upper bound for bytes held back while waiting for the end of a partial tag.
+///
+/// A `<script` or `<style` start tag longer than this is passed through unchanged
+/// rather than buffering an unbounded amount of a malformed document.
+const MAX_CARRY_BYTES: usize = 8 * 1024;
+
+const REWRITTEN_TAGS: [&[u8]; 2] = [b"script", b"style"];
+
+/// Request headers that let a handler answer from, or with part of, the original body.
+const PRECONDITION_HEADERS: [HeaderName; 4] = [IF_NONE_MATCH, IF_MODIFIED_SINCE, IF_RANGE, RANGE];
+
+/// Response headers that describe the original body and no longer hold once it
+/// is rewritten.
+const REPRESENTATION_HEADERS: [&str; 7] = [
+    "content-length",
+    "etag",
+    "last-modified",
+    "accept-ranges",
+    "content-md5",
+    "digest",
+    "repr-digest",
+];
+
+/// Removes preconditions from a request whose HTML response may be rewritten.
+///
+/// A rewritten page carries a nonce the original never had, so a handler must
+/// not answer `304 Not Modified` or `206 Partial Content` from the file or
+/// template it renders: the client would keep a copy without the current nonce.
+/// Only requests that explicitly accept `text/html` are touched, so assets keep
+/// revalidating normally.
+pub(crate) fn drop_html_preconditions(headers: &mut HeaderMap) {
+    let accepts_html = headers
+        .get_all(ACCEPT)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media_range| {
+            media_range
+                .split(';')
+                .next()
+                .is_some_and(|media_type| media_type.trim().eq_ignore_ascii_case("text/html"))
+        });
+
+    if accepts_html {
+        for name in PRECONDITION_HEADERS {
+            headers.remove(name);
+        }
+    }
+}
+
+/// Removes the headers that describe the original body of a rewritten response.
+///
+/// The rewritten body is sent chunked, and its validators would be shared by
+/// every variant with a different nonce, so length, `ETag`, `Last-Modified`,
+/// range support and digests of the original are all dropped.
+pub(crate) fn drop_representation_headers(headers: &mut HeaderMap) {
+    for name in REPRESENTATION_HEADERS {
+        headers.remove(name);
+    }
+}
+
+/// Incremental scanner that adds a `nonce` attribute to `<script>` and `<style>` start tags.
+///
+/// Input may be split at arbitrary byte offsets: a tag that straddles two chunks is
+/// held back until its closing `>` arrives. Tags that already carry a `nonce`
+/// attribute are left untouched.
+#[derive(Debug)]
+pub(crate) struct HtmlNonceRewriter {
+    attribute: Bytes,
+    carry: BytesMut,
+}
+
+impl HtmlNonceRewriter {
+    pub(crate) fn new(nonce: &str) -> Self {
+        Self {
+            attribute: Bytes::from(format!(" nonce=\"{nonce}\"")),
+            carry: BytesMut::new(),
+        }
+    }
+
+    /// Consumes the next chunk and returns the bytes that are safe to emit.
+    pub(crate) fn feed(&mut self, chunk: &[u8]) -> Bytes {
+        if self.carry.is_empty() {
+            self.rewrite(chunk, false)
+        } else {
+            let mut input = std::mem::take(&mut self.carry);
+            input.extend_from_slice(chunk);
+            self.rewrite(&input, false)
+        }
+    }
+
+    /// Flushes any held-back bytes at end of stream.
+    pub(crate) fn finish(&mut self) -> Bytes {
+        let input = std::mem::take(&mut self.carry);
+        self.rewrite(&input, true)
+    }
+
+    fn rewrite(&mut self, input: &[u8], eof: bool) -> Bytes {
+        let mut output = BytesMut::with_capacity(input.len() + self.attribute.len());
+        let mut emitted = 0;
+        let mut cursor = 0;
+
+        while let Some(offset) = memchr(b'<', &input[cursor..]) {
+            let tag_start = cursor + offset;
+            cursor = tag_start + 1;
+
+            match classify_tag(&input[tag_start..], eof) {
+                TagMatch::No => continue,
+                TagMatch::Incomplete => {
+                    if input.len() - tag_start > MAX_CARRY_BYTES {
+                        continue;
+                    }
+                    output.extend_from_slice(&input[emitted..tag_start]);
+                    self.carry.extend_from_slice(&input[tag_start..]);
+                    return output.freeze();
+                }
+                TagMatch::Yes { name_end, tag_end } => {
+                    let name_end = tag_start + name_end;
+                    let tag_end = tag_start + tag_end;
+
+                    if !has_nonce_attribute(&input[name_end..tag_end]) {
+                        output.extend_from_slice(&input[emitted..name_end]);
+                        output.extend_from_slice(&self.attribute);
+                        emitted = name_end;
+                    }
+
+                    cursor = tag_end;
+                }
+            }
+        }
+
+        output.extend_from_slice(&input[emitted..]);
+        output.freeze()
+    }
+}
+
+enum TagMatch {
+    No,
+    Incomplete,
+    Yes { name_end: usize, tag_end: usize },
+}
+
+/// Inspects `input`, which starts at a `<`, for a rewritable start tag.
+fn classify_tag(input: &[u8], eof: bool) -> TagMatch {
+    for name in REWRITTEN_TAGS {
+        let name_end = 1 + name.len();
+
+        if input.len() <= name_end {
+            let available = &input[1..];
+            if !eof && name[..available.len()].eq_ignore_ascii_case(available) {
+                return TagMatch::Incomplete;
+            }
+            continue;
+        }
+
+        if !input[1..name_end].eq_ignore_ascii_case(name) {
+            continue;
+        }
+
+        if !matches!(
+            input[name_end],
+            b'>' | b'/' | b' ' | b'\t' | b'\n' | b'\r' | b'\x0c'
+        ) {
+            return TagMatch::No;
+        }
+
+        return match memchr(b'>', &input[name_end..]) {
+            Some(offset) => TagMatch::Yes {
+                name_end,
+                tag_end: name_end + offset,
+            },
+            None if eof => TagMatch::No,
+            None => TagMatch::Incomplete,
+        };
+    }
+
+    TagMatch::No
+}
+
+fn has_nonce_attribute(attributes: &[u8]) -> bool {
+    attributes.windows(6).enumerate().any(|(index, window)| {
+        window[0].is_ascii_whitespace()
+            && window[1..].eq_ignore_ascii_case(b"nonce")
+            && matches!(
+                attributes.get(index + 6),
+                None | Some(b'=' | b' ' | b'\t' | b'\n' | b'\r' | b'/')
+            )
+    })
+}
+
+#[inline]
+fn memchr(needle: u8, haystack: &[u8]) -> Option<usize> {
+    haystack.iter().position(|&byte| byte == needle)
+}
+
+/// Response body that injects the request nonce into inline `<script>`/`<style>` tags.
+///
+/// Produced by [`CspMiddleware`](crate::middleware::CspMiddleware) for `text/html`
+/// responses marked with [`HtmlNonceRewrite`], or for all of them when HTML nonce
+/// rewriting is enabled on the config. The body is rewritten chunk by chunk, so
+/// streamed responses are never fully buffered.
+///
+/// With shadow auditing enabled, the emitted bytes are also copied to the audit
+/// worker; a body that is only audited passes through unchanged.
+///
+/// # Security
+///
+/// Every `<script>`/`<style>` start tag without a nonce is given one, including
+/// tags an attacker injected through an HTML-injection bug. Such a page loses the
+/// protection the nonce was meant to provide, so rewriting only suits responses
+/// rendered from trusted templates or files that never echo untrusted markup;
+/// mark those one by one rather than enabling it for the whole config.
+///
+/// [`HtmlNonceRewrite`]: crate::middleware::HtmlNonceRewrite
+pub struct NonceRewriteBody<B> {
+    body: Pin<Box<B>>,
+    rewriter: Option<HtmlNonceRewriter>,
+    tap: Option<ShadowTap>,
+    finished: bool,
+}
+
+impl<B> NonceRewriteBody<B> {
+    pub(crate) fn new(body: B, nonce: Option<&str>, tap: Option<ShadowTap>) -> Self {
+        Self {
+            body: Box::pin(body),
+            rewriter: nonce.map(HtmlNonceRewriter::new),
+            tap,
+            finished: false,
+        }
+    }
+
+    fn emit(&mut self, output: Bytes) -> Bytes {
+        if let Some(tap) = self.tap.as_mut() {
+            tap.record(&output);
+        }
+        output
+    }
+}
+
+impl<B: MessageBody> MessageBody for NonceRewriteBody<B> {
+    type Error = B::Error;
+
+    #[inline]
+    fn size(&self) -> BodySize {
+        match self.body.size() {
+            BodySize::None => BodySize::None,
+            size if self.rewriter.is_none() => size,
+            _ => BodySize::Stream,
+        }
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.finished {
+                return Poll::Ready(None);
+            }
+
+            match this.body.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    let output = match this.rewriter.as_mut() {
+                        Some(rewriter) => rewriter.feed(&chunk),
+                        None => chunk,
+                    };
+                    if !output.is_empty() {
+                        return Poll::Ready(Some(Ok(this.emit(output))));
+                    }
+                }
+                Poll::Ready(Some(Err(error))) => {
+                    this.tap = None;
+                    return Poll::Ready(Some(Err(error)));
+                }
+                Poll::Ready(None) => {
+                    this.finished = true;
+                    let output = this
+                        .rewriter
+                        .as_mut()
+                        .map_or_else(Bytes::new, HtmlNonceRewriter::finish);
+                    let output = this.emit(output);
+                    if let Some(tap) = this.tap.as_mut() {
+                        tap.finish();
+                    }
+                    return if output.is_empty() {
+                        Poll::Ready(None)
+                    } else {
+                        Poll::Ready(Some(Ok(output)))
+                    };
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
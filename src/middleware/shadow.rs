@@ -0,0 +1,207 @@
+//! Server-side "would violate" checks of HTML responses.
+//!
+//! The response body is copied while it streams to the client and handed to a
+//! background thread once complete, where it is run through the page audit against
+//! the policy header actually sent. Findings go to [`CspStats`] and the log. The
+//! request path only pays for the copy; when the worker falls behind, documents
+//! are dropped rather than queued without bound.
+//!
+//! [`CspStats`]: crate::monitoring::CspStats
+
+pub(crate) use imp::{ShadowAuditor, ShadowTap};
+
+#[cfg(feature = "page-audit")]
+mod imp {
+    use crate::core::policy::parse_header_value;
+    use crate::logging::csp_log;
+    use crate::monitoring::stats::CspStats;
+    use crate::security::page_audit::audit_html;
+    use crate::security::verify::PolicyVerifier;
+    use actix_web::http::header::HeaderValue;
+    use bytes::BytesMut;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+    use std::sync::Arc;
+    use url::Url;
+
+    /// Documents waiting for the worker before new ones are dropped.
+    const QUEUE_CAPACITY: usize = 64;
+
+    struct ShadowJob {
+        page_url: String,
+        policy: HeaderValue,
+        html: BytesMut,
+    }
+
+    #[derive(Debug, Clone)]
+    pub(crate) struct ShadowAuditor {
+        sender: SyncSender<ShadowJob>,
+        max_body_bytes: usize,
+        /// Documents queued or being audited
+        backlog: Arc<AtomicUsize>,
+    }
+
+    impl ShadowAuditor {
+        /// Starts the worker thread; it exits once every auditor handle is dropped.
+        pub(crate) fn spawn(stats: Arc<CspStats>, max_body_bytes: usize) -> Option<Self> {
+            let (sender, receiver) = sync_channel::<ShadowJob>(QUEUE_CAPACITY);
+            let backlog = Arc::new(AtomicUsize::new(0));
+            let worker_backlog = backlog.clone();
+
+            std::thread::Builder::new()
+                .name("csp-shadow-audit".to_string())
+                .spawn(move || {
+                    for job in receiver {
+                        audit(&job, &stats);
+                        worker_backlog.fetch_sub(1, Ordering::Relaxed);
+                    }
+                })
+                .map_err(|error| csp_log!(Warn, "CSP shadow audit disabled: {error}"))
+                .ok()?;
+
+            Some(Self {
+                sender,
+                max_body_bytes,
+                backlog,
+            })
+        }
+
+        /// Documents waiting for, or being checked by, the worker.
+        #[inline]
+        pub(crate) fn backlog(&self) -> usize {
+            self.backlog.load(Ordering::Relaxed)
+        }
+
+        pub(crate) fn tap(&self, page_url: String, policy: HeaderValue) -> ShadowTap {
+            ShadowTap {
+                job: Some(ShadowJob {
+                    page_url,
+                    policy,
+                    html: BytesMut::new(),
+                }),
+                sender: self.sender.clone(),
+                max_body_bytes: self.max_body_bytes,
+                backlog: self.backlog.clone(),
+            }
+        }
+    }
+
+    /// Copies a response body as it streams and submits it when complete.
+    pub(crate) struct ShadowTap {
+        job: Option<ShadowJob>,
+        sender: SyncSender<ShadowJob>,
+        max_body_bytes: usize,
+        backlog: Arc<AtomicUsize>,
+    }
+
+    impl ShadowTap {
+        pub(crate) fn record(&mut self, chunk: &[u8]) {
+            let Some(job) = self.job.as_mut() else {
+                return;
+            };
+            if job.html.len() + chunk.len() > self.max_body_bytes {
+                csp_log!(
+                    Debug,
+                    "CSP shadow audit skipped {}: body exceeds {} bytes",
+                    job.page_url,
+                    self.max_body_bytes
+                );
+                self.job = None;
+            } else {
+                job.html.extend_from_slice(chunk);
+            }
+        }
+
+        pub(crate) fn finish(&mut self) {
+            let Some(job) = self.job.take() else {
+                return;
+            };
+            // Counted before sending so the worker never sees the backlog below zero
+            self.backlog.fetch_add(1, Ordering::Relaxed);
+            if let Err(error) = self.sender.try_send(job) {
+                self.backlog.fetch_sub(1, Ordering::Relaxed);
+                if let TrySendError::Full(job) = error {
+                    csp_log!(
+                        Debug,
+                        "CSP shadow audit queue full, skipped {}",
+                        job.page_url
+                    );
+                }
+            }
+        }
+    }
+
+    fn audit(job: &ShadowJob, stats: &CspStats) {
+        let result = Url::parse(&job.page_url)
+            .map_err(|error| crate::error::CspError::VerificationError(error.to_string()))
+            .and_then(|url| {
+                let policy = parse_header_value(&job.policy)?;
+                let verifier =
+                    PolicyVerifier::with_origin(policy, url.origin().ascii_serialization())?;
+                audit_html(
+                    &verifier,
+                    &String::from_utf8_lossy(&job.html),
+                    &job.page_url,
+                )
+            });
+
+        match result {
+            Ok(report) => {
+                stats.add_shadow_violations(report.violations.len());
+                for violation in &report.violations {
+                    csp_log!(
+                        Warn,
+                        "CSP shadow audit: {} would violate {}: {}",
+                        job.page_url,
+                        violation.directive,
+                        violation.reason
+                    );
+                }
+            }
+            Err(error) => csp_log!(
+                Debug,
+                "CSP shadow audit of {} failed: {error}",
+                job.page_url
+            ),
+        }
+    }
+}
+
+#[cfg(not(feature = "page-audit"))]
+mod imp {
+    use crate::logging::csp_log;
+    use crate::monitoring::stats::CspStats;
+    use actix_web::http::header::HeaderValue;
+    use std::sync::Arc;
+
+    #[derive(Debug, Clone)]
+    pub(crate) struct ShadowAuditor;
+
+    impl ShadowAuditor {
+        #[inline]
+        pub(crate) fn spawn(_stats: Arc<CspStats>, _max_body_bytes: usize) -> Option<Self> {
+            csp_log!(Warn, "CSP shadow audit requires the `page-audit` feature");
+            None
+        }
+
+        #[inline]
+        pub(crate) fn backlog(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub(crate) fn tap(&self, _page_url: String, _policy: HeaderValue) -> ShadowTap {
+            ShadowTap
+        }
+    }
+
+    pub(crate) struct ShadowTap;
+
+    impl ShadowTap {
+        #[inline]
+        pub(crate) fn record(&mut self, _chunk: &[u8]) {}
+
+        #[inline]
+        pub(crate) fn finish(&mut self) {}
+    }
+}
@@ -0,0 +1,95 @@
+use crate::core::policy::{CspPolicy, SelectedPolicy};
+use actix_web::{dev::ServiceRequest, http::header::HOST};
+use rustc_hash::FxHashMap;
+use std::sync::Arc;
+
+/// Lookup table from request authority to tenant policy.
+///
+/// Keys are normalized to lowercase without a trailing dot. A key may include a
+/// port (`tenant.example.com:8443`), in which case it only matches requests on
+/// that port; port-less keys match any port.
+#[derive(Debug, Default)]
+pub(crate) struct VhostPolicies {
+    entries: FxHashMap<String, Arc<SelectedPolicy>>,
+}
+
+impl VhostPolicies {
+    pub(crate) fn new<I, K>(policies: I) -> Self
+    where
+        I: IntoIterator<Item = (K, CspPolicy)>,
+        K: Into<String>,
+    {
+        let entries = policies
+            .into_iter()
+            .map(|(host, policy)| {
+                (
+                    normalize_authority(&host.into()),
+                    Arc::new(SelectedPolicy::new(policy)),
+                )
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn resolve(&self, req: &ServiceRequest) -> Option<Arc<SelectedPolicy>> {
+        let authority = req
+            .headers()
+            .get(HOST)
+            .and_then(|value| value.to_str().ok())
+            .or_else(|| req.uri().authority().map(|authority| authority.as_str()))?;
+
+        self.lookup(authority)
+    }
+
+    fn lookup(&self, authority: &str) -> Option<Arc<SelectedPolicy>> {
+        let authority = normalize_authority(authority);
+
+        if let Some(policy) = self.entries.get(&authority) {
+            return Some(policy.clone());
+        }
+
+        let host = strip_port(&authority);
+        if host.len() != authority.len() {
+            return self.entries.get(host).cloned();
+        }
+
+        None
+    }
+}
+
+fn normalize_authority(authority: &str) -> String {
+    let authority = authority.trim().to_ascii_lowercase();
+    let (host, port) = split_port(&authority);
+    let host = host.strip_suffix('.').unwrap_or(host);
+
+    match port {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_owned(),
+    }
+}
+
+fn strip_port(authority: &str) -> &str {
+    split_port(authority).0
+}
+
+fn split_port(authority: &str) -> (&str, Option<&str>) {
+    if authority.ends_with(']') {
+        return (authority, None);
+    }
+
+    match authority.rfind(':') {
+        Some(index)
+            if !authority[index + 1..].is_empty()
+                && authority[index + 1..].bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            (&authority[..index], Some(&authority[index + 1..]))
+        }
+        _ => (authority, None),
+    }
+}
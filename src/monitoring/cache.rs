@@ -0,0 +1,111 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Point-in-time counts of one cache, as found in
+/// [`StatsSnapshot::caches`](crate::monitoring::StatsSnapshot::caches).
+///
+/// Hits, misses and evictions count up from process start (or, for the policy
+/// cache, from the last [`CspStats::reset`](crate::CspStats::reset)); entries
+/// and capacity describe the cache right now.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped to make room or because they went stale
+    pub evictions: u64,
+    pub entries: usize,
+    /// Most entries the cache holds; for the per-thread buffer pool, the limit
+    /// of each thread
+    pub capacity: usize,
+}
+
+impl CacheStats {
+    #[inline]
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// Live counters behind a [`CacheStats`], shared by the cache that updates them
+/// and the statistics that report them.
+#[derive(Debug, Default)]
+pub(crate) struct CacheMetrics {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    entries: AtomicUsize,
+    capacity: AtomicUsize,
+}
+
+impl CacheMetrics {
+    pub(crate) const fn new() -> Self {
+        Self {
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            entries: AtomicUsize::new(0),
+            capacity: AtomicUsize::new(0),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn record_hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn record_miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn record_evictions(&self, count: usize) {
+        if count > 0 {
+            self.evictions.fetch_add(count as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Sets the occupancy of a cache that owns these counters alone.
+    #[inline]
+    pub(crate) fn set_occupancy(&self, entries: usize, capacity: usize) {
+        self.entries.store(entries, Ordering::Relaxed);
+        self.capacity.store(capacity, Ordering::Relaxed);
+    }
+
+    /// Adjusts the occupancy of counters shared by several caches, such as one
+    /// per thread or per verifier.
+    #[inline]
+    pub(crate) fn add_occupancy(&self, entries: usize, capacity: usize) {
+        self.entries.fetch_add(entries, Ordering::Relaxed);
+        self.capacity.fetch_add(capacity, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn remove_occupancy(&self, entries: usize, capacity: usize) {
+        self.entries.fetch_sub(entries, Ordering::Relaxed);
+        self.capacity.fetch_sub(capacity, Ordering::Relaxed);
+    }
+
+    /// Zeroes hits, misses and evictions; occupancy is left alone.
+    #[cfg_attr(not(feature = "stats"), allow(dead_code))]
+    pub(crate) fn reset_counters(&self) {
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+        self.evictions.store(0, Ordering::Relaxed);
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: self.entries.load(Ordering::Relaxed),
+            capacity: self.capacity.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,348 @@
+//! Human-readable violation output for local development.
+
+use crate::monitoring::report::CspViolationReport;
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::io::IsTerminal;
+use std::sync::Arc;
+use url::Url;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Pages remembered for deduplication and the summary, oldest forgotten first.
+const MAX_PAGES: usize = 100;
+/// Distinct violations remembered per page, oldest forgotten first.
+const MAX_VIOLATIONS_PER_PAGE: usize = 100;
+
+/// Directives that have a same-named [`CspPolicyBuilder`] method.
+///
+/// [`CspPolicyBuilder`]: crate::core::CspPolicyBuilder
+const BUILDER_DIRECTIVES: [&str; 15] = [
+    "default-src",
+    "script-src",
+    "style-src",
+    "img-src",
+    "connect-src",
+    "font-src",
+    "object-src",
+    "media-src",
+    "frame-src",
+    "worker-src",
+    "manifest-src",
+    "child-src",
+    "frame-ancestors",
+    "base-uri",
+    "form-action",
+];
+
+#[derive(Debug, Default)]
+struct DevState {
+    /// Rendered violation -> times seen, grouped by document
+    pages: IndexMap<String, IndexMap<String, usize>>,
+    last_page: Option<String>,
+}
+
+/// Pretty-prints violation reports to stderr, grouped by page, with a suggested
+/// builder call for each.
+///
+/// Repeated violations are printed once and counted; [`summary`](Self::summary)
+/// renders everything seen so far. Only the latest 100 pages and 100 distinct
+/// violations per page are kept, the oldest forgotten first. Control characters in report fields
+/// are escaped, so a crafted report cannot drive the terminal. Plug it into the
+/// reporting endpoint through [`handler`](Self::handler):
+///
+/// ```rust
+/// use actix_web_csp::monitoring::DevReporter;
+/// use actix_web_csp::{csp_with_reporting, CspPolicyBuilder, Source};
+///
+/// let policy = CspPolicyBuilder::new()
+///     .default_src([Source::Self_])
+///     .report_uri("/csp-report")
+///     .build_unchecked();
+///
+/// let reporter = DevReporter::new();
+/// let (_middleware, _configure) = csp_with_reporting(policy, reporter.handler());
+/// ```
+#[derive(Debug, Clone)]
+pub struct DevReporter {
+    enabled: bool,
+    colors: bool,
+    state: Arc<Mutex<DevState>>,
+}
+
+impl Default for DevReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DevReporter {
+    /// Creates a reporter that is enabled in debug builds only.
+    ///
+    /// Colors are used when stderr is a terminal and `NO_COLOR` is not set.
+    pub fn new() -> Self {
+        Self {
+            enabled: cfg!(debug_assertions),
+            colors: std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+            state: Arc::new(Mutex::new(DevState::default())),
+        }
+    }
+
+    #[inline]
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    #[inline]
+    pub fn with_colors(mut self, colors: bool) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns a violation handler for [`CspReportingMiddleware`] or
+    /// [`csp_with_reporting`] that feeds this reporter.
+    ///
+    /// [`CspReportingMiddleware`]: crate::middleware::CspReportingMiddleware
+    /// [`csp_with_reporting`]: crate::middleware::csp_with_reporting
+    pub fn handler(&self) -> impl Fn(CspViolationReport) + Send + Sync + 'static {
+        let reporter = self.clone();
+        move |report| reporter.report(&report)
+    }
+
+    /// Records `report` and prints it unless the same violation was already
+    /// printed for that page.
+    pub fn report(&self, report: &CspViolationReport) {
+        if !self.enabled {
+            return;
+        }
+
+        let page = page_of(report);
+        let entry = self.format_violation(report);
+        let mut output = String::new();
+        {
+            let mut state = self.state.lock();
+            let pages = &mut state.pages;
+            if !pages.contains_key(&page) && pages.len() >= MAX_PAGES {
+                pages.shift_remove_index(0);
+            }
+
+            let violations = pages.entry(page.clone()).or_default();
+            if let Some(count) = violations.get_mut(&entry) {
+                *count += 1;
+                return;
+            }
+            if violations.len() >= MAX_VIOLATIONS_PER_PAGE {
+                violations.shift_remove_index(0);
+            }
+            violations.insert(entry.clone(), 1);
+
+            if state.last_page.as_deref() != Some(page.as_str()) {
+                output.push_str(&self.format_page(&page));
+                state.last_page = Some(page);
+            }
+        }
+        output.push_str(&entry);
+        eprint!("{output}");
+    }
+
+    /// Renders one violation: what was blocked, where, and how to allow it.
+    pub fn format_violation(&self, report: &CspViolationReport) -> String {
+        let directive = escape_controls(effective_directive(report));
+        let blocked = if report.blocked_uri.is_empty() {
+            Cow::Borrowed("(unknown)")
+        } else {
+            escape_controls(&report.blocked_uri)
+        };
+
+        let mut output = String::new();
+        let _ = writeln!(
+            output,
+            "  {} {blocked}{}",
+            self.paint(RED, &format!("{directive} blocked")),
+            if report.is_report() {
+                self.paint(DIM, " (report-only)")
+            } else {
+                String::new()
+            }
+        );
+        if let Some(source_file) = &report.source_file {
+            let mut location = source_file.clone();
+            if let Some(line) = report.line_number {
+                let _ = write!(location, ":{line}");
+                if let Some(column) = report.column_number {
+                    let _ = write!(location, ":{column}");
+                }
+            }
+            let _ = writeln!(
+                output,
+                "    {}",
+                self.paint(DIM, &format!("at {}", escape_controls(&location)))
+            );
+        }
+        if let Some(sample) = report.script_sample.as_deref().filter(|s| !s.is_empty()) {
+            let _ = writeln!(
+                output,
+                "    {}",
+                self.paint(DIM, &format!("sample: {}", escape_controls(sample)))
+            );
+        }
+        if let Some(fix) = suggest_fix(report) {
+            let _ = writeln!(output, "    {}", self.paint(GREEN, &format!("fix: {fix}")));
+        }
+        output
+    }
+
+    /// Renders every violation seen so far, grouped by page, with repeat counts.
+    pub fn summary(&self) -> String {
+        let state = self.state.lock();
+        let mut output = String::new();
+
+        for (page, entries) in &state.pages {
+            output.push_str(&self.format_page(page));
+            for (entry, count) in entries {
+                output.push_str(entry);
+                if *count > 1 {
+                    let _ = writeln!(
+                        output,
+                        "    {}",
+                        self.paint(YELLOW, &format!("seen {count} times"))
+                    );
+                }
+            }
+        }
+        output
+    }
+
+    /// Forgets every recorded violation.
+    pub fn clear(&self) {
+        *self.state.lock() = DevState::default();
+    }
+
+    fn format_page(&self, page: &str) -> String {
+        format!("{}\n", self.paint(BOLD, &format!("[csp] {page}")))
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.colors {
+            format!("{color}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Suggests the builder call that would allow the blocked load, such as
+/// ``add `.script_src([... Source::Host("cdn.example.com".into())])` ``.
+///
+/// Returns `None` when the report does not carry enough information, or for
+/// directives without a fetch-style builder method.
+pub fn suggest_fix(report: &CspViolationReport) -> Option<String> {
+    let directive = effective_directive(report);
+    let method = builder_directive(directive)?.replace('-', "_");
+    let blocked = report.blocked_uri.trim();
+
+    let source = match blocked {
+        "" => return None,
+        "inline" => {
+            return Some(format!(
+                "add a nonce to the element (`CspConfigBuilder::with_nonce_generator`) or \
+                 its hash: `.{method}([... Source::Hash {{ algorithm: HashAlgorithm::Sha256, \
+                 value: \"...\".into() }}])`"
+            ))
+        }
+        "eval" => {
+            return Some(format!(
+                "avoid eval(); if unavoidable add `.{method}([... Source::UnsafeEval])`"
+            ))
+        }
+        "wasm-eval" => "Source::WasmUnsafeEval".to_string(),
+        "data" | "blob" | "mediastream" | "filesystem" => {
+            format!("Source::Scheme(\"{blocked}\".into())")
+        }
+        _ => {
+            let url = Url::parse(blocked).ok()?;
+            match url.scheme() {
+                "data" | "blob" | "mediastream" | "filesystem" => {
+                    format!("Source::Scheme(\"{}\".into())", url.scheme())
+                }
+                _ if Url::parse(&report.document_uri)
+                    .is_ok_and(|document| document.origin() == url.origin()) =>
+                {
+                    "Source::Self_".to_string()
+                }
+                _ => {
+                    let host = url.host_str()?;
+                    let host = match url.port() {
+                        Some(port) => format!("{host}:{port}"),
+                        None => host.to_string(),
+                    };
+                    format!("Source::Host(\"{host}\".into())")
+                }
+            }
+        }
+    };
+
+    Some(format!("add `.{method}([... {source}])`"))
+}
+
+fn page_of(report: &CspViolationReport) -> String {
+    if report.document_uri.is_empty() {
+        "(unknown page)".to_string()
+    } else {
+        escape_controls(&report.document_uri).into_owned()
+    }
+}
+
+/// Escapes control characters, such as the ANSI escapes a crafted report could
+/// use to rewrite the developer's terminal, as `\u{..}`.
+fn escape_controls(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(char::is_control) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        if c.is_control() {
+            let _ = write!(escaped, "{}", c.escape_unicode());
+        } else {
+            escaped.push(c);
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+fn effective_directive(report: &CspViolationReport) -> &str {
+    if report.effective_directive.is_empty() {
+        report
+            .violated_directive
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+    } else {
+        report.effective_directive.as_str()
+    }
+}
+
+/// Maps `script-src-elem`/`-attr` and friends onto the directive they fall back to.
+fn builder_directive(directive: &str) -> Option<&'static str> {
+    let directive = directive
+        .strip_suffix("-elem")
+        .or_else(|| directive.strip_suffix("-attr"))
+        .unwrap_or(directive);
+    BUILDER_DIRECTIVES
+        .iter()
+        .find(|candidate| candidate.eq_ignore_ascii_case(directive))
+        .copied()
+}
@@ -0,0 +1,229 @@
+//! Annotating violation reports before they reach the handler.
+
+#[cfg(feature = "geoip")]
+use crate::error::CspError;
+use crate::monitoring::report::CspViolationReport;
+use serde::Serialize;
+use std::net::IpAddr;
+#[cfg(feature = "geoip")]
+use std::path::Path;
+
+/// Adds information to a violation report before it is counted and handed to
+/// the violation handler.
+///
+/// Enrichers registered with [`CspReportingMiddleware::with_enricher`] run in
+/// registration order, after the client address, fingerprint and browser have
+/// been filled in. They run on the request path, so lookups should be local.
+/// Closures taking `&mut CspViolationReport` implement the trait.
+///
+/// [`CspReportingMiddleware::with_enricher`]: crate::middleware::CspReportingMiddleware::with_enricher
+pub trait Enricher: Send + Sync + 'static {
+    fn enrich(&self, report: &mut CspViolationReport);
+}
+
+impl<F> Enricher for F
+where
+    F: Fn(&mut CspViolationReport) + Send + Sync + 'static,
+{
+    #[inline]
+    fn enrich(&self, report: &mut CspViolationReport) {
+        self(report)
+    }
+}
+
+/// Where a reporting client is on the network, as found by a [`GeoIpEnricher`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GeoInfo {
+    /// ISO 3166-1 alpha-2 country code, e.g. `DE`
+    pub country: Option<String>,
+    /// Autonomous system number of the client's network
+    pub asn: Option<u32>,
+    /// Organization that operates the autonomous system
+    pub as_organization: Option<String>,
+}
+
+/// Fills [`CspViolationReport::geo`] from the client address.
+///
+/// The lookup is supplied by the caller; with the `geoip` feature,
+/// `MaxMindEnricher` does it from MaxMind databases. A cluster of reports from one hosting
+/// provider's ASN usually means a scanner, while breakage seen by real users
+/// spreads across residential networks and countries. Reports without a client
+/// address are left untouched.
+///
+/// ```rust
+/// use actix_web_csp::middleware::CspReportingMiddleware;
+/// use actix_web_csp::monitoring::{GeoInfo, GeoIpEnricher};
+///
+/// let enricher = GeoIpEnricher::new(|ip| {
+///     ip.is_ipv4().then(|| GeoInfo {
+///         country: Some("DE".to_string()),
+///         ..GeoInfo::default()
+///     })
+/// });
+///
+/// let reporting = CspReportingMiddleware::new(|report| {
+///     println!("{:?} from {:?}", report.blocked_uri, report.geo);
+/// })
+/// .with_enricher(enricher);
+/// ```
+pub struct GeoIpEnricher<F> {
+    lookup: F,
+}
+
+impl<F> GeoIpEnricher<F>
+where
+    F: Fn(IpAddr) -> Option<GeoInfo> + Send + Sync + 'static,
+{
+    #[inline]
+    pub fn new(lookup: F) -> Self {
+        Self { lookup }
+    }
+}
+
+impl<F> Enricher for GeoIpEnricher<F>
+where
+    F: Fn(IpAddr) -> Option<GeoInfo> + Send + Sync + 'static,
+{
+    fn enrich(&self, report: &mut CspViolationReport) {
+        if let Some(ip) = report.client_ip {
+            report.geo = (self.lookup)(ip);
+        }
+    }
+}
+
+/// Fills [`CspViolationReport::geo`] from MaxMind databases, such as the free
+/// GeoLite2-Country and GeoLite2-ASN or a commercial GeoIP2 database.
+///
+/// Every database added is searched for the country and autonomous system of
+/// the client, and the first value found for each is kept, so separate
+/// country and ASN databases combine into one [`GeoInfo`]. The databases are
+/// read into memory when the enricher is built; lookups do no I/O. Needs the
+/// `geoip` feature.
+///
+/// ```rust,no_run
+/// use actix_web_csp::middleware::CspReportingMiddleware;
+/// use actix_web_csp::monitoring::MaxMindEnricher;
+///
+/// # fn main() -> Result<(), actix_web_csp::CspError> {
+/// let enricher = MaxMindEnricher::open("/var/lib/GeoIP/GeoLite2-Country.mmdb")?
+///     .with_database("/var/lib/GeoIP/GeoLite2-ASN.mmdb")?;
+///
+/// let reporting = CspReportingMiddleware::new(|report| {
+///     println!("{:?} from {:?}", report.blocked_uri, report.geo);
+/// })
+/// .with_enricher(enricher);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "geoip")]
+pub struct MaxMindEnricher {
+    readers: Vec<maxminddb::Reader<Vec<u8>>>,
+}
+
+#[cfg(feature = "geoip")]
+#[derive(serde::Deserialize)]
+struct MaxMindRecord<'a> {
+    #[serde(borrow)]
+    country: Option<MaxMindCountry<'a>>,
+    #[serde(borrow)]
+    registered_country: Option<MaxMindCountry<'a>>,
+    autonomous_system_number: Option<u32>,
+    autonomous_system_organization: Option<&'a str>,
+}
+
+#[cfg(feature = "geoip")]
+#[derive(serde::Deserialize)]
+struct MaxMindCountry<'a> {
+    iso_code: Option<&'a str>,
+}
+
+#[cfg(feature = "geoip")]
+impl MaxMindEnricher {
+    /// Reads the database at `path`.
+    ///
+    /// # Errors
+    ///
+    /// [`CspError::IoError`] when the file cannot be read, and
+    /// [`CspError::ConfigError`] when it is not a MaxMind database.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, CspError> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    /// Uses a database already in memory.
+    pub fn from_bytes(database: Vec<u8>) -> Result<Self, CspError> {
+        Ok(Self {
+            readers: vec![Self::reader(database)?],
+        })
+    }
+
+    /// Also searches the database at `path`, after those added before.
+    pub fn with_database(mut self, path: impl AsRef<Path>) -> Result<Self, CspError> {
+        self.readers.push(Self::reader(std::fs::read(path)?)?);
+        Ok(self)
+    }
+
+    fn reader(database: Vec<u8>) -> Result<maxminddb::Reader<Vec<u8>>, CspError> {
+        maxminddb::Reader::from_source(database)
+            .map_err(|error| CspError::ConfigError(format!("Invalid MaxMind database: {error}")))
+    }
+
+    /// Looks up `ip`, `None` when no database knows it. IPv4-mapped IPv6
+    /// addresses, as dual-stack sockets report IPv4 peers, are looked up as
+    /// IPv4.
+    pub fn lookup(&self, ip: IpAddr) -> Option<GeoInfo> {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            IpAddr::V4(_) => ip,
+        };
+        let mut info = GeoInfo::default();
+        for reader in &self.readers {
+            // an IPv4-only database would walk its tree with the first bits
+            // of an IPv6 address
+            if ip.is_ipv6() && reader.metadata.ip_version == 4 {
+                continue;
+            }
+            let Ok(record) = reader.lookup::<MaxMindRecord<'_>>(ip) else {
+                continue;
+            };
+
+            if info.country.is_none() {
+                info.country = record
+                    .country
+                    .or(record.registered_country)
+                    .and_then(|country| country.iso_code)
+                    .map(str::to_string);
+            }
+            if info.asn.is_none() {
+                info.asn = record.autonomous_system_number;
+                info.as_organization = record.autonomous_system_organization.map(str::to_string);
+            }
+        }
+
+        (info != GeoInfo::default()).then_some(info)
+    }
+}
+
+#[cfg(feature = "geoip")]
+impl std::fmt::Debug for MaxMindEnricher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MaxMindEnricher")
+            .field(
+                "databases",
+                &self
+                    .readers
+                    .iter()
+                    .map(|reader| reader.metadata.database_type.as_str())
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[cfg(feature = "geoip")]
+impl Enricher for MaxMindEnricher {
+    fn enrich(&self, report: &mut CspViolationReport) {
+        if let Some(ip) = report.client_ip {
+            report.geo = self.lookup(ip);
+        }
+    }
+}
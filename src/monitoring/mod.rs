@@ -1,7 +1,23 @@
+pub mod cache;
+pub mod dev;
+pub mod enrich;
 pub mod perf;
+pub mod queue;
 pub mod report;
+pub mod route;
+pub mod scrub;
 pub mod stats;
+pub mod user_agent;
 
+pub use cache::CacheStats;
+pub use dev::DevReporter;
+#[cfg(feature = "geoip")]
+pub use enrich::MaxMindEnricher;
+pub use enrich::{Enricher, GeoInfo, GeoIpEnricher};
 pub use perf::{AdaptiveCache, PerformanceMetrics, PerformanceTimer};
-pub use report::CspViolationReport;
-pub use stats::CspStats;
+pub use queue::OverflowStrategy;
+pub use report::{CspViolationReport, MalformedReport};
+pub use route::ReportRouter;
+pub use scrub::ReportScrubber;
+pub use stats::{CspStats, HistogramSnapshot, PolicyTrend, StatsSnapshot, WindowedRates};
+pub use user_agent::BrowserInfo;
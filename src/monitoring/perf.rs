@@ -1,6 +1,10 @@
+use crate::monitoring::cache::{CacheMetrics, CacheStats};
 #[cfg(feature = "stats")]
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "stats")]
+use std::sync::OnceLock;
 use std::time::{Duration, Instant};
 
 #[cfg(feature = "stats")]
@@ -19,6 +23,10 @@ pub struct PerformanceMetrics {
 
     memory_pressure_events: AtomicUsize,
     gc_events: AtomicUsize,
+    memory_usage_bytes: AtomicUsize,
+    maintenance_runs: AtomicUsize,
+
+    nonce_pool: OnceLock<Arc<CacheMetrics>>,
 }
 
 #[cfg(feature = "stats")]
@@ -38,6 +46,10 @@ impl Default for PerformanceMetrics {
 
             memory_pressure_events: AtomicUsize::new(0),
             gc_events: AtomicUsize::new(0),
+            memory_usage_bytes: AtomicUsize::new(0),
+            maintenance_runs: AtomicUsize::new(0),
+
+            nonce_pool: OnceLock::new(),
         }
     }
 }
@@ -92,6 +104,9 @@ impl PerformanceMetrics {
         self.policy_hash_total_ns.fetch_add(ns, Ordering::Relaxed);
     }
 
+    /// Counts a hit for [`cache_hit_rate`](Self::cache_hit_rate). Nothing in the
+    /// crate calls this; the caches it maintains report to
+    /// [`StatsSnapshot::caches`](crate::monitoring::StatsSnapshot::caches).
     pub fn record_cache_hit(&self) {
         self.cache_hit_ratio.fetch_add(1, Ordering::Relaxed);
     }
@@ -100,6 +115,18 @@ impl PerformanceMetrics {
         self.cache_miss_ratio.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Records the cache footprint measured by a maintenance pass.
+    pub fn record_memory_usage(&self, bytes: usize) {
+        self.memory_usage_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Counts a completed [`CspConfig::run_maintenance`] pass.
+    ///
+    /// [`CspConfig::run_maintenance`]: crate::core::CspConfig::run_maintenance
+    pub fn record_maintenance_run(&self) {
+        self.maintenance_runs.fetch_add(1, Ordering::Relaxed);
+    }
+
     pub fn avg_header_generation_ns(&self) -> f64 {
         let samples = self.header_generation_samples.load(Ordering::Relaxed);
         if samples == 0 {
@@ -143,6 +170,49 @@ impl PerformanceMetrics {
         self.header_generation_max_ns.load(Ordering::Relaxed)
     }
 
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.memory_usage_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn gc_events(&self) -> usize {
+        self.gc_events.load(Ordering::Relaxed)
+    }
+
+    /// Maintenance passes run since the last [`reset`](Self::reset).
+    pub fn maintenance_runs(&self) -> usize {
+        self.maintenance_runs.load(Ordering::Relaxed)
+    }
+
+    /// Share of header serializations that reused a pooled buffer, from
+    /// [`buffer_pool_stats`].
+    ///
+    /// The buffer pool is per thread but counted process-wide, so every
+    /// `PerformanceMetrics` reports the same value and [`reset`](Self::reset)
+    /// leaves it alone.
+    ///
+    /// [`buffer_pool_stats`]: crate::utils::buffer_pool_stats
+    pub fn buffer_pool_hit_rate(&self) -> f64 {
+        crate::utils::buffer_pool_stats().hit_rate()
+    }
+
+    /// Buffer reuse of the configuration's [`NonceGenerator`], `None` without
+    /// one. These are the generator's own counters, so [`reset`](Self::reset)
+    /// leaves them alone.
+    ///
+    /// [`NonceGenerator`]: crate::security::NonceGenerator
+    pub fn nonce_pool_stats(&self) -> Option<CacheStats> {
+        self.nonce_pool.get().map(|metrics| metrics.stats())
+    }
+
+    pub fn nonce_pool_hit_rate(&self) -> f64 {
+        self.nonce_pool_stats()
+            .map_or(0.0, |stats| stats.hit_rate())
+    }
+
+    pub(crate) fn attach_nonce_pool(&self, metrics: Arc<CacheMetrics>) {
+        let _ = self.nonce_pool.set(metrics);
+    }
+
     pub fn reset(&self) {
         self.header_generation_samples.store(0, Ordering::Relaxed);
         self.header_generation_total_ns.store(0, Ordering::Relaxed);
@@ -158,6 +228,8 @@ impl PerformanceMetrics {
 
         self.memory_pressure_events.store(0, Ordering::Relaxed);
         self.gc_events.store(0, Ordering::Relaxed);
+        self.memory_usage_bytes.store(0, Ordering::Relaxed);
+        self.maintenance_runs.store(0, Ordering::Relaxed);
     }
 }
 
@@ -179,6 +251,10 @@ impl PerformanceMetrics {
 
     pub fn record_cache_miss(&self) {}
 
+    pub fn record_memory_usage(&self, _bytes: usize) {}
+
+    pub fn record_maintenance_run(&self) {}
+
     pub fn avg_header_generation_ns(&self) -> f64 {
         0.0
     }
@@ -199,6 +275,32 @@ impl PerformanceMetrics {
         0
     }
 
+    pub fn memory_usage_bytes(&self) -> usize {
+        0
+    }
+
+    pub fn gc_events(&self) -> usize {
+        0
+    }
+
+    pub fn maintenance_runs(&self) -> usize {
+        0
+    }
+
+    pub fn buffer_pool_hit_rate(&self) -> f64 {
+        0.0
+    }
+
+    pub fn nonce_pool_stats(&self) -> Option<CacheStats> {
+        None
+    }
+
+    pub fn nonce_pool_hit_rate(&self) -> f64 {
+        0.0
+    }
+
+    pub(crate) fn attach_nonce_pool(&self, _metrics: Arc<CacheMetrics>) {}
+
     pub fn reset(&self) {}
 }
 
@@ -225,22 +327,38 @@ impl Default for PerformanceTimer {
     }
 }
 
+const MIN_ADAPTIVE_CAPACITY: usize = 16;
+
+/// LRU cache that grows while its hit rate is low.
+///
+/// The hit rate driving the resizing restarts on [`clear`](Self::clear), while
+/// [`stats`](Self::stats) keeps counting for the cache's whole life.
 pub struct AdaptiveCache<K, V> {
-    cache: lru::LruCache<K, V>,
+    cache: crate::collections::LruCache<K, V>,
     hit_count: AtomicUsize,
     miss_count: AtomicUsize,
     last_resize: Instant,
     resize_threshold: usize,
+    metrics: Arc<CacheMetrics>,
 }
 
 impl<K: std::hash::Hash + Eq, V> AdaptiveCache<K, V> {
     pub fn new(capacity: std::num::NonZeroUsize) -> Self {
+        Self::with_metrics(capacity, Arc::default())
+    }
+
+    pub(crate) fn with_metrics(
+        capacity: std::num::NonZeroUsize,
+        metrics: Arc<CacheMetrics>,
+    ) -> Self {
+        metrics.set_occupancy(0, capacity.get());
         Self {
-            cache: lru::LruCache::new(capacity),
+            cache: crate::collections::LruCache::new(capacity),
             hit_count: AtomicUsize::new(0),
             miss_count: AtomicUsize::new(0),
             last_resize: Instant::now(),
             resize_threshold: 1000,
+            metrics,
         }
     }
 
@@ -248,16 +366,34 @@ impl<K: std::hash::Hash + Eq, V> AdaptiveCache<K, V> {
         let is_hit = self.cache.contains(key);
         if is_hit {
             self.hit_count.fetch_add(1, Ordering::Relaxed);
+            self.metrics.record_hit();
             self.cache.get(key)
         } else {
             self.miss_count.fetch_add(1, Ordering::Relaxed);
+            self.metrics.record_miss();
             self.maybe_resize();
             None
         }
     }
 
     pub fn put(&mut self, key: K, value: V) -> Option<V> {
-        self.cache.put(key, value)
+        if !self.cache.contains(&key) && self.cache.len() >= self.cache.cap().get() {
+            self.metrics.record_evictions(1);
+        }
+        let previous = self.cache.put(key, value);
+        self.update_occupancy();
+        previous
+    }
+
+    /// Lifetime hits, misses and evictions, with the current occupancy.
+    pub fn stats(&self) -> CacheStats {
+        self.metrics.stats()
+    }
+
+    #[inline]
+    fn update_occupancy(&self) {
+        self.metrics
+            .set_occupancy(self.cache.len(), self.cache.cap().get());
     }
 
     pub fn hit_rate(&self) -> f64 {
@@ -285,14 +421,77 @@ impl<K: std::hash::Hash + Eq, V> AdaptiveCache<K, V> {
                 if let Some(new_capacity) = std::num::NonZeroUsize::new(new_cap) {
                     self.cache.resize(new_capacity);
                     self.last_resize = Instant::now();
+                    self.update_occupancy();
                 }
             }
         }
     }
 
+    /// Halves the capacity when the hit rate is high and most slots are unused.
+    ///
+    /// Returns `true` if the cache was resized. Capacity never drops below
+    /// twice the current number of entries.
+    pub fn shrink_if_underused(&mut self) -> bool {
+        let cap = self.cache.cap().get();
+        let len = self.cache.len();
+
+        if self.hit_rate() < 0.9 || len * 4 > cap || cap <= MIN_ADAPTIVE_CAPACITY {
+            return false;
+        }
+
+        let new_cap = (cap / 2).max(len * 2).max(MIN_ADAPTIVE_CAPACITY);
+        match std::num::NonZeroUsize::new(new_cap) {
+            Some(new_capacity) if new_cap < cap => {
+                self.cache.resize(new_capacity);
+                self.last_resize = Instant::now();
+                self.update_occupancy();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Removes every entry for which `keep` returns `false`, returning how many were removed.
+    pub fn retain(&mut self, mut keep: impl FnMut(&K, &V) -> bool) -> usize
+    where
+        K: Clone,
+    {
+        let stale: Vec<K> = self
+            .cache
+            .iter()
+            .filter(|(key, value)| !keep(key, value))
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        for key in &stale {
+            self.cache.pop(key);
+        }
+        self.metrics.record_evictions(stale.len());
+        self.update_occupancy();
+
+        stale.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    pub fn cap(&self) -> std::num::NonZeroUsize {
+        self.cache.cap()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.cache.iter()
+    }
+
     pub fn clear(&mut self) {
         self.cache.clear();
         self.hit_count.store(0, Ordering::Relaxed);
         self.miss_count.store(0, Ordering::Relaxed);
+        self.update_occupancy();
     }
 }
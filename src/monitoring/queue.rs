@@ -0,0 +1,158 @@
+//! Buffering violation reports between the reporting endpoint and the handler.
+
+use crate::logging::csp_log;
+use crate::monitoring::report::CspViolationReport;
+use crate::monitoring::stats::CspStats;
+use parking_lot::{Condvar, Mutex};
+use std::collections::VecDeque;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+/// How long the worker waits for a report before checking whether the queue
+/// is still in use.
+const IDLE_POLL: Duration = Duration::from_millis(100);
+
+/// What happens to a report that arrives while the report queue is full, set
+/// with [`CspReportingMiddleware::with_report_queue`].
+///
+/// [`CspReportingMiddleware::with_report_queue`]: crate::middleware::CspReportingMiddleware::with_report_queue
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OverflowStrategy {
+    /// Drop the oldest queued report to make room, keeping the freshest data
+    #[default]
+    DropOldest,
+    /// Drop the report that just arrived
+    DropNewest,
+    /// Hold the reporting request until there is room, slowing the browsers
+    /// down to the handler's pace. The wait runs on the blocking thread pool,
+    /// not on the actix worker.
+    Block,
+}
+
+/// A bounded queue drained by a dedicated thread that calls the handler.
+///
+/// Queue depth and dropped reports are tracked in [`CspStats`]. The thread
+/// exits shortly after the last handle is dropped; reports still queued then
+/// are lost.
+#[derive(Clone)]
+#[cfg_attr(not(feature = "reporting"), allow(dead_code))]
+pub(crate) struct ReportQueue {
+    shared: Arc<QueueShared>,
+}
+
+struct QueueShared {
+    reports: Mutex<VecDeque<CspViolationReport>>,
+    capacity: usize,
+    strategy: OverflowStrategy,
+    /// Signalled when a report is queued
+    queued: Condvar,
+    /// Signalled when a report leaves the queue
+    room: Condvar,
+    stats: Arc<CspStats>,
+}
+
+impl Drop for QueueShared {
+    fn drop(&mut self) {
+        for _ in self.reports.get_mut().drain(..) {
+            self.stats.decrement_report_queue_depth();
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "reporting"), allow(dead_code))]
+impl ReportQueue {
+    /// Starts the worker thread that passes queued reports to `deliver`.
+    pub(crate) fn spawn<F>(
+        capacity: usize,
+        strategy: OverflowStrategy,
+        stats: Arc<CspStats>,
+        deliver: F,
+    ) -> Option<Self>
+    where
+        F: Fn(CspViolationReport) + Send + 'static,
+    {
+        let shared = Arc::new(QueueShared {
+            reports: Mutex::new(VecDeque::with_capacity(capacity.min(1024))),
+            capacity: capacity.max(1),
+            strategy,
+            queued: Condvar::new(),
+            room: Condvar::new(),
+            stats,
+        });
+        let worker_shared = Arc::downgrade(&shared);
+
+        std::thread::Builder::new()
+            .name("csp-report-queue".to_string())
+            .spawn(move || drain(worker_shared, deliver))
+            .map_err(|error| csp_log!(Warn, "CSP report queue disabled: {error}"))
+            .ok()?;
+
+        Some(Self { shared })
+    }
+
+    /// Queues `report`, applying the overflow strategy when the queue is full.
+    ///
+    /// Hands the report back when the queue is full and the strategy is
+    /// [`OverflowStrategy::Block`]; pass it to [`push_blocking`](Self::push_blocking).
+    pub(crate) fn push(&self, report: CspViolationReport) -> Option<CspViolationReport> {
+        let shared = &self.shared;
+        let mut reports = shared.reports.lock();
+        if reports.len() >= shared.capacity {
+            match shared.strategy {
+                OverflowStrategy::DropOldest => {
+                    reports.pop_front();
+                    shared.stats.increment_dropped_report_count();
+                    shared.stats.decrement_report_queue_depth();
+                    csp_log!(report: Debug, "CSP report queue full, dropped the oldest report");
+                }
+                OverflowStrategy::DropNewest => {
+                    shared.stats.increment_dropped_report_count();
+                    csp_log!(report: Debug, "CSP report queue full, dropped a report");
+                    return None;
+                }
+                OverflowStrategy::Block => return Some(report),
+            }
+        }
+        reports.push_back(report);
+        shared.stats.increment_report_queue_depth();
+        drop(reports);
+        shared.queued.notify_one();
+        None
+    }
+
+    /// Queues `report`, waiting for room first.
+    pub(crate) fn push_blocking(&self, report: CspViolationReport) {
+        let shared = &self.shared;
+        let mut reports = shared.reports.lock();
+        while reports.len() >= shared.capacity {
+            shared.room.wait(&mut reports);
+        }
+        reports.push_back(report);
+        shared.stats.increment_report_queue_depth();
+        drop(reports);
+        shared.queued.notify_one();
+    }
+}
+
+fn drain<F>(shared: Weak<QueueShared>, deliver: F)
+where
+    F: Fn(CspViolationReport),
+{
+    while let Some(queue) = shared.upgrade() {
+        let report = {
+            let mut reports = queue.reports.lock();
+            if reports.is_empty() {
+                queue.queued.wait_for(&mut reports, IDLE_POLL);
+            }
+            reports.pop_front()
+        };
+        let Some(report) = report else {
+            continue;
+        };
+        queue.stats.decrement_report_queue_depth();
+        queue.room.notify_one();
+        // Not held while the handler runs, so dropping the last handle stops the worker
+        drop(queue);
+        deliver(report);
+    }
+}
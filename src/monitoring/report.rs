@@ -1,5 +1,9 @@
+use crate::monitoring::enrich::GeoInfo;
+use crate::monitoring::user_agent::BrowserInfo;
+use actix_web::web::Bytes;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
+use std::net::IpAddr;
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct CspViolationReport {
@@ -38,6 +42,28 @@ pub struct CspViolationReport {
 
     #[serde(rename = "script-sample", skip_serializing_if = "Option::is_none")]
     pub script_sample: Option<String>,
+
+    /// Address of the browser that sent the report, resolved through trusted proxies
+    #[serde(skip)]
+    pub client_ip: Option<IpAddr>,
+
+    /// Salted hash of the client address and `User-Agent`, when
+    /// [`CspReportingMiddleware::with_client_fingerprinting`] is on
+    ///
+    /// [`CspReportingMiddleware::with_client_fingerprinting`]: crate::middleware::CspReportingMiddleware::with_client_fingerprinting
+    #[serde(skip)]
+    pub client_fingerprint: Option<String>,
+
+    /// Browser read from the reporting request's `User-Agent`; requires the
+    /// `user-agent` feature
+    #[serde(skip)]
+    pub browser: Option<BrowserInfo>,
+
+    /// Country and network of the client, filled in by a [`GeoIpEnricher`]
+    ///
+    /// [`GeoIpEnricher`]: crate::monitoring::GeoIpEnricher
+    #[serde(skip)]
+    pub geo: Option<GeoInfo>,
 }
 
 impl CspViolationReport {
@@ -64,6 +90,10 @@ impl CspViolationReport {
             column_number: None,
             status_code: None,
             script_sample: None,
+            client_ip: None,
+            client_fingerprint: None,
+            browser: None,
+            geo: None,
         }
     }
 
@@ -97,6 +127,24 @@ impl CspViolationReport {
         self
     }
 
+    #[inline]
+    pub fn with_client_ip(mut self, client_ip: IpAddr) -> Self {
+        self.client_ip = Some(client_ip);
+        self
+    }
+
+    #[inline]
+    pub fn with_client_fingerprint(mut self, client_fingerprint: String) -> Self {
+        self.client_fingerprint = Some(client_fingerprint);
+        self
+    }
+
+    #[inline]
+    pub fn with_browser(mut self, browser: BrowserInfo) -> Self {
+        self.browser = Some(browser);
+        self
+    }
+
     #[inline]
     pub fn is_enforce(&self) -> bool {
         self.disposition == "enforce"
@@ -115,3 +163,20 @@ impl TryFrom<&serde_json::Value> for CspViolationReport {
         serde_json::from_value(value.clone())
     }
 }
+
+/// A report body that could not be read as a violation report.
+///
+/// Passed to the handler set with
+/// [`CspReportingMiddleware::with_malformed_report_handler`], for example to
+/// keep samples of junk traffic hitting the reporting endpoint.
+///
+/// [`CspReportingMiddleware::with_malformed_report_handler`]: crate::middleware::CspReportingMiddleware::with_malformed_report_handler
+#[derive(Clone, Debug)]
+pub struct MalformedReport {
+    /// The request body exactly as received
+    pub body: Bytes,
+    /// Why the body was rejected
+    pub reason: String,
+    /// Address of the client that sent it, resolved through trusted proxies
+    pub client_ip: Option<IpAddr>,
+}
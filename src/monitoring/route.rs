@@ -0,0 +1,125 @@
+//! Sending violation reports to different sinks by directive.
+
+use crate::constants::FRAME_ANCESTORS;
+use crate::middleware::reporting::ViolationHandler;
+use crate::monitoring::report::CspViolationReport;
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+/// Picks a sink for each violation report based on the directive it violated.
+///
+/// Script violations may belong to the frontend team while `frame-ancestors`
+/// violations, which usually mean someone tried to frame the site, belong to
+/// security. Register the router with
+/// [`CspReportingMiddleware::with_router`]; reports that match no route go to
+/// the middleware's own handler.
+///
+/// A route for `script-src` also takes `script-src-elem` and `script-src-attr`
+/// reports, and likewise for `style-src`. Routes are tried in the order they
+/// were added and the first match wins. The directive is read from the
+/// report's `effective-directive`, or from the first word of
+/// `violated-directive` for browsers that leave the former empty.
+///
+/// ```rust
+/// use actix_web_csp::middleware::CspReportingMiddleware;
+/// use actix_web_csp::monitoring::ReportRouter;
+///
+/// let router = ReportRouter::new()
+///     .with_clickjacking_alert(|report| {
+///         println!("framing attempt on {}", report.document_uri);
+///     })
+///     .with_route("script-src", |report| {
+///         println!("blocked script {}", report.blocked_uri);
+///     });
+///
+/// let reporting = CspReportingMiddleware::new(|report| {
+///     println!("other violation of {}", report.effective_directive);
+/// })
+/// .with_router(router);
+/// ```
+///
+/// [`CspReportingMiddleware::with_router`]: crate::middleware::CspReportingMiddleware::with_router
+#[derive(Clone, Default)]
+pub struct ReportRouter {
+    routes: Vec<(Cow<'static, str>, ViolationHandler)>,
+}
+
+impl ReportRouter {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends reports about `directive` to `sink`.
+    #[inline]
+    pub fn with_route<F>(mut self, directive: impl Into<Cow<'static, str>>, sink: F) -> Self
+    where
+        F: Fn(CspViolationReport) + Send + Sync + 'static,
+    {
+        self.routes.push((directive.into(), Arc::new(sink)));
+        self
+    }
+
+    /// Sends `frame-ancestors` reports to `sink`.
+    ///
+    /// Unlike most violations, these rarely come from a misconfigured policy:
+    /// each one is a page that tried to frame the site, often a clickjacking
+    /// attempt, so `sink` is the place to page someone or raise an alert. The
+    /// report's `document-uri` is the framed page.
+    #[inline]
+    pub fn with_clickjacking_alert<F>(self, sink: F) -> Self
+    where
+        F: Fn(CspViolationReport) + Send + Sync + 'static,
+    {
+        self.with_route(FRAME_ANCESTORS, sink)
+    }
+
+    /// Returns the sink for `report`, `None` if no route matches.
+    pub(crate) fn sink_for(&self, report: &CspViolationReport) -> Option<&ViolationHandler> {
+        let directive = report_directive(report);
+        self.routes
+            .iter()
+            .find(|(route, _)| directive_matches(route, directive))
+            .map(|(_, sink)| sink)
+    }
+
+    /// Returns `true` if a route matches `report`.
+    #[inline]
+    pub fn has_route(&self, report: &CspViolationReport) -> bool {
+        self.sink_for(report).is_some()
+    }
+}
+
+impl fmt::Debug for ReportRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.routes.iter().map(|(directive, _)| directive))
+            .finish()
+    }
+}
+
+fn report_directive(report: &CspViolationReport) -> &str {
+    if report.effective_directive.is_empty() {
+        report
+            .violated_directive
+            .split_ascii_whitespace()
+            .next()
+            .unwrap_or_default()
+    } else {
+        &report.effective_directive
+    }
+}
+
+fn directive_matches(route: &str, directive: &str) -> bool {
+    let Some((prefix, rest)) = directive
+        .get(..route.len())
+        .zip(directive.get(route.len()..))
+    else {
+        return false;
+    };
+    prefix.eq_ignore_ascii_case(route)
+        && (rest.is_empty()
+            || rest.eq_ignore_ascii_case("-elem")
+            || rest.eq_ignore_ascii_case("-attr"))
+}
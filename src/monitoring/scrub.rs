@@ -0,0 +1,127 @@
+//! Removing personal data from violation reports.
+
+use crate::monitoring::report::CspViolationReport;
+
+/// Replacement for text matched by a redaction pattern.
+#[cfg(feature = "regex")]
+const REDACTED: &str = "[REDACTED]";
+
+/// Removes personal data from violation reports before they are counted,
+/// logged or handed to the violation handler.
+///
+/// Browsers copy full URLs into reports, so session tokens and e-mail
+/// addresses in query strings end up in whatever stores them. Register the
+/// scrubber with [`CspReportingMiddleware::with_scrubber`]; it runs after any
+/// enrichers.
+///
+/// ```rust
+/// use actix_web_csp::middleware::CspReportingMiddleware;
+/// use actix_web_csp::monitoring::ReportScrubber;
+///
+/// let scrubber = ReportScrubber::new()
+///     .with_strip_query_strings(true)
+///     .with_max_script_sample(40);
+///
+/// let reporting = CspReportingMiddleware::new(|_| {}).with_scrubber(scrubber);
+/// ```
+///
+/// [`CspReportingMiddleware::with_scrubber`]: crate::middleware::CspReportingMiddleware::with_scrubber
+#[derive(Debug, Clone, Default)]
+pub struct ReportScrubber {
+    strip_query_strings: bool,
+    max_script_sample: Option<usize>,
+    #[cfg(feature = "regex")]
+    redactions: Vec<regex::Regex>,
+}
+
+impl ReportScrubber {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drops the query string and fragment of the document, referrer, blocked
+    /// and source file URLs.
+    #[inline]
+    pub fn with_strip_query_strings(mut self, enabled: bool) -> Self {
+        self.strip_query_strings = enabled;
+        self
+    }
+
+    /// Cuts script samples down to `max_chars` characters; `0` drops them.
+    #[inline]
+    pub fn with_max_script_sample(mut self, max_chars: usize) -> Self {
+        self.max_script_sample = Some(max_chars);
+        self
+    }
+
+    /// Replaces every match of `pattern` in the report's text fields with
+    /// `[REDACTED]`, e.g. `token=[^&]+` or an e-mail pattern.
+    #[cfg(feature = "regex")]
+    pub fn with_redaction(mut self, pattern: &str) -> Result<Self, crate::error::CspError> {
+        let pattern = regex::Regex::new(pattern).map_err(|error| {
+            crate::error::CspError::ConfigError(format!("Invalid redaction pattern: {error}"))
+        })?;
+        self.redactions.push(pattern);
+        Ok(self)
+    }
+
+    /// Applies the configured rules to `report` in place.
+    pub fn scrub(&self, report: &mut CspViolationReport) {
+        if self.strip_query_strings {
+            for url in [
+                &mut report.document_uri,
+                &mut report.referrer,
+                &mut report.blocked_uri,
+            ] {
+                strip_query(url);
+            }
+            if let Some(source_file) = &mut report.source_file {
+                strip_query(source_file);
+            }
+        }
+
+        #[cfg(feature = "regex")]
+        if !self.redactions.is_empty() {
+            for field in [
+                &mut report.document_uri,
+                &mut report.referrer,
+                &mut report.blocked_uri,
+                &mut report.original_policy,
+            ] {
+                self.redact(field);
+            }
+            for field in [&mut report.source_file, &mut report.script_sample]
+                .into_iter()
+                .flatten()
+            {
+                self.redact(field);
+            }
+        }
+
+        if let Some(max_chars) = self.max_script_sample {
+            if max_chars == 0 {
+                report.script_sample = None;
+            } else if let Some(sample) = &mut report.script_sample {
+                if let Some((end, _)) = sample.char_indices().nth(max_chars) {
+                    sample.truncate(end);
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    fn redact(&self, field: &mut String) {
+        for pattern in &self.redactions {
+            if let std::borrow::Cow::Owned(redacted) = pattern.replace_all(field, REDACTED) {
+                *field = redacted;
+            }
+        }
+    }
+}
+
+fn strip_query(url: &mut String) {
+    if let Some(end) = url.find(['?', '#']) {
+        url.truncate(end);
+    }
+}
@@ -1,8 +1,936 @@
+use crate::monitoring::cache::CacheStats;
+#[cfg(feature = "stats")]
+use crate::monitoring::report::CspViolationReport;
+use serde::Serialize;
+#[cfg(feature = "stats")]
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::num::NonZeroU64;
+#[cfg(feature = "stats")]
+use url::Url;
+
+#[cfg(feature = "stats")]
+/// Distinct blocked origins tracked at once; rarer ones are evicted.
+const MAX_TRACKED_ORIGINS: usize = 64;
+#[cfg(feature = "stats")]
+/// Distinct client fingerprints tracked at once; rarer ones are evicted.
+const MAX_TRACKED_CLIENTS: usize = 64;
+#[cfg(feature = "stats")]
+/// Distinct browser versions tracked at once; rarer ones are evicted.
+const MAX_TRACKED_BROWSERS: usize = 64;
+#[cfg(feature = "stats")]
+/// Blocked origins included in a [`StatsSnapshot`].
+const SNAPSHOT_TOP_ORIGINS: usize = 10;
+#[cfg(feature = "stats")]
+/// Client fingerprints included in a [`StatsSnapshot`].
+const SNAPSHOT_TOP_CLIENTS: usize = 10;
+
+/// Per-minute rates over the last 1, 5 and 15 minutes.
+///
+/// Rates are computed from 5-second buckets, so they trail real time by at most
+/// one bucket. Right after startup or a reset, the rate is taken over the time
+/// that has actually elapsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct WindowedRates {
+    pub one_minute: f64,
+    pub five_minutes: f64,
+    pub fifteen_minutes: f64,
+}
+
+/// Upper bounds of the report size histogram, in bytes.
+#[cfg(feature = "stats")]
+const REPORT_SIZE_BUCKETS: [f64; 8] = [
+    256.0, 512.0, 1024.0, 2048.0, 4096.0, 8192.0, 16384.0, 65536.0,
+];
+/// Upper bounds of the violation handler duration histogram, in seconds.
+#[cfg(feature = "stats")]
+const HANDLER_DURATION_BUCKETS: [f64; 9] =
+    [0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0];
+
+/// Point-in-time copy of a histogram in Prometheus form.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct HistogramSnapshot {
+    /// Upper bound of each bucket paired with the number of observations at or
+    /// below it; the implicit `+Inf` bucket equals [`count`](Self::count)
+    pub buckets: Vec<(f64, usize)>,
+    pub sum: f64,
+    pub count: usize,
+}
+
+/// Violation rate observed while one policy version was active.
+///
+/// Versions are identified by [`CspPolicy::structural_hash`], the same hash found
+/// in [`PolicyHistoryEntry::new_hash`], so a trend can be traced back to the
+/// change that deployed it.
+///
+/// [`CspPolicy::structural_hash`]: crate::core::CspPolicy::structural_hash
+/// [`PolicyHistoryEntry::new_hash`]: crate::core::PolicyHistoryEntry::new_hash
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PolicyTrend {
+    pub policy_hash: NonZeroU64,
+    /// Seconds after the statistics were created (or reset) at which the version became active
+    pub active_since_secs: u64,
+    /// Whether this is the version currently served
+    pub active: bool,
+    pub violations: usize,
+    /// Violation reports per minute while the version was active
+    pub violations_per_minute: f64,
+    /// The same rate for the version this one replaced
+    pub previous_violations_per_minute: Option<f64>,
+    /// The violation rate spiked after this version was deployed, compared
+    /// with a previous version that drew enough reports or ran long enough to
+    /// be a baseline
+    pub regression: bool,
+}
+
+/// Point-in-time copy of every [`CspStats`] counter.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StatsSnapshot {
+    pub uptime_secs: u64,
+    pub request_count: usize,
+    pub nonce_generation_count: usize,
+    pub policy_update_count: usize,
+    pub policy_validations: usize,
+    /// Responses that reused the precompiled policy header
+    pub cache_hit_count: usize,
+    pub violation_count: usize,
+    pub shadow_violation_count: usize,
+    pub oversized_report_count: usize,
+    /// Reports whose body was not received within the read timeout
+    pub timed_out_report_count: usize,
+    /// Reports turned away because too many were already being processed
+    pub shed_report_count: usize,
+    pub malformed_report_count: usize,
+    pub blocked_report_count: usize,
+    pub handler_panic_count: usize,
+    /// Responses sent with the fallback policy because their own could not be generated
+    pub fallback_header_count: usize,
+    /// Responses replaced with a 500 because their policy header could not be generated
+    pub rejected_response_count: usize,
+    /// Responses whose CSP header could not be generated
+    pub header_generation_failure_count: usize,
+    /// Headers that could not be converted into a valid header name or value
+    pub header_insertion_failure_count: usize,
+    /// Cached policies served after their header value expired
+    pub stale_policy_cache_count: usize,
+    /// Reports dropped because the report queue was full
+    pub dropped_report_count: usize,
+    /// Reports waiting in the report queue
+    pub report_queue_depth: usize,
+    /// URIs given to any `PolicyVerifier::verify_uri` since process start that
+    /// did not parse (with the `verify` feature)
+    pub invalid_uri_count: usize,
+    /// Requests per minute
+    pub request_rates: WindowedRates,
+    /// Violation reports per minute
+    pub violation_rates: WindowedRates,
+    /// Violation reports per minute from enforced policies
+    pub enforced_violation_rates: WindowedRates,
+    /// Violation reports per minute from report-only policies
+    pub report_only_violation_rates: WindowedRates,
+    /// Keyed by effective directive; unrecognised names are counted as `other`
+    pub violations_by_directive: BTreeMap<String, usize>,
+    /// Keyed by `enforce`, `report` or `unknown`
+    pub violations_by_disposition: BTreeMap<String, usize>,
+    /// Keyed by disposition, then by effective directive
+    pub violations_by_disposition_and_directive: BTreeMap<String, BTreeMap<String, usize>>,
+    /// Keyed by browser family and major version, e.g. `Safari 16`. Approximate
+    /// once more than 64 versions have been seen
+    pub violations_by_browser: BTreeMap<String, usize>,
+    /// Most frequently blocked origins, most frequent first
+    pub top_blocked_origins: Vec<(String, usize)>,
+    /// Client fingerprints behind the most reports, most frequent first
+    pub top_client_fingerprints: Vec<(String, usize)>,
+    /// Violation rates of recent policy versions, oldest first
+    pub policy_trends: Vec<PolicyTrend>,
+    /// Body sizes of the report requests read, in bytes
+    pub report_size_bytes: HistogramSnapshot,
+    /// Time spent in the violation handler per report, in seconds
+    pub handler_duration_seconds: HistogramSnapshot,
+    /// Keyed by cache: `policy` for the [`CspConfig`] policy cache,
+    /// `verification` for the results cached by every `PolicyVerifier` (with the
+    /// `verify` feature) and `buffer_pool` for the per-thread serialization
+    /// buffers
+    ///
+    /// [`CspConfig`]: crate::core::CspConfig
+    pub caches: BTreeMap<String, CacheStats>,
+}
+
+impl StatsSnapshot {
+    /// Renders the snapshot in the Prometheus text exposition format.
+    ///
+    /// ```rust
+    /// use actix_web_csp::CspStats;
+    ///
+    /// let metrics = CspStats::new().snapshot().to_prometheus();
+    /// assert!(metrics.contains("# TYPE csp_requests_total counter"));
+    /// ```
+    pub fn to_prometheus(&self) -> String {
+        let mut output = String::new();
+
+        for (name, help, value) in [
+            (
+                "csp_requests_total",
+                "Requests processed by the CSP middleware",
+                self.request_count,
+            ),
+            (
+                "csp_nonces_generated_total",
+                "Nonces generated",
+                self.nonce_generation_count,
+            ),
+            (
+                "csp_policy_updates_total",
+                "Policy updates applied",
+                self.policy_update_count,
+            ),
+            (
+                "csp_policy_validations_total",
+                "Policy validations run by CspConfig",
+                self.policy_validations,
+            ),
+            (
+                "csp_policy_cache_hits_total",
+                "Responses that reused the precompiled policy header",
+                self.cache_hit_count,
+            ),
+            (
+                "csp_violations_total",
+                "Violation reports received",
+                self.violation_count,
+            ),
+            (
+                "csp_shadow_violations_total",
+                "Violations predicted by the server-side shadow audit",
+                self.shadow_violation_count,
+            ),
+            (
+                "csp_reports_oversized_total",
+                "Violation reports rejected for exceeding the size limit",
+                self.oversized_report_count,
+            ),
+            (
+                "csp_reports_timed_out_total",
+                "Violation reports whose body was not received within the read timeout",
+                self.timed_out_report_count,
+            ),
+            (
+                "csp_reports_shed_total",
+                "Violation reports turned away because too many were in flight",
+                self.shed_report_count,
+            ),
+            (
+                "csp_reports_malformed_total",
+                "Violation reports that could not be parsed",
+                self.malformed_report_count,
+            ),
+            (
+                "csp_reports_blocked_total",
+                "Violation reports refused by the block-list or a temporary ban",
+                self.blocked_report_count,
+            ),
+            (
+                "csp_violation_handler_panics_total",
+                "Violation handler or enricher calls that panicked",
+                self.handler_panic_count,
+            ),
+            (
+                "csp_fallback_headers_total",
+                "Responses sent with the fallback policy because their own could not be generated",
+                self.fallback_header_count,
+            ),
+            (
+                "csp_responses_rejected_total",
+                "Responses replaced with a 500 because their policy header could not be generated",
+                self.rejected_response_count,
+            ),
+            (
+                "csp_header_generation_failures_total",
+                "Responses whose CSP header could not be generated",
+                self.header_generation_failure_count,
+            ),
+            (
+                "csp_header_insertion_failures_total",
+                "Headers the middleware could not insert because the name or value was invalid",
+                self.header_insertion_failure_count,
+            ),
+            (
+                "csp_policy_cache_stale_total",
+                "Cached policies whose header value had expired when served",
+                self.stale_policy_cache_count,
+            ),
+            (
+                "csp_reports_dropped_total",
+                "Violation reports dropped because the report queue was full",
+                self.dropped_report_count,
+            ),
+            (
+                "csp_verifier_invalid_uris_total",
+                "URIs given to the policy verifier that did not parse",
+                self.invalid_uri_count,
+            ),
+        ] {
+            let _ = writeln!(output, "# HELP {name} {help}");
+            let _ = writeln!(output, "# TYPE {name} counter");
+            let _ = writeln!(output, "{name} {value}");
+        }
+
+        let _ = writeln!(
+            output,
+            "# HELP csp_uptime_seconds Seconds since the statistics were created"
+        );
+        let _ = writeln!(output, "# TYPE csp_uptime_seconds gauge");
+        let _ = writeln!(output, "csp_uptime_seconds {}", self.uptime_secs);
+
+        let _ = writeln!(
+            output,
+            "# HELP csp_report_queue_depth Violation reports waiting for the handler"
+        );
+        let _ = writeln!(output, "# TYPE csp_report_queue_depth gauge");
+        let _ = writeln!(output, "csp_report_queue_depth {}", self.report_queue_depth);
+
+        let _ = writeln!(
+            output,
+            "# HELP csp_policy_regressions Recent policy versions whose deployment correlates with a violation spike"
+        );
+        let _ = writeln!(output, "# TYPE csp_policy_regressions gauge");
+        let _ = writeln!(
+            output,
+            "csp_policy_regressions {}",
+            self.policy_trends
+                .iter()
+                .filter(|trend| trend.regression)
+                .count()
+        );
+
+        for (name, help, rates) in [
+            (
+                "csp_requests_per_minute",
+                "Requests per minute over a trailing window",
+                &self.request_rates,
+            ),
+            (
+                "csp_violations_per_minute",
+                "Violation reports per minute over a trailing window",
+                &self.violation_rates,
+            ),
+        ] {
+            let _ = writeln!(output, "# HELP {name} {help}");
+            let _ = writeln!(output, "# TYPE {name} gauge");
+            for (window, rate) in [
+                ("1m", rates.one_minute),
+                ("5m", rates.five_minutes),
+                ("15m", rates.fifteen_minutes),
+            ] {
+                let _ = writeln!(output, "{name}{{window=\"{window}\"}} {rate}");
+            }
+        }
+
+        let name = "csp_violations_per_minute_by_disposition";
+        let _ = writeln!(
+            output,
+            "# HELP {name} Violation reports per minute over a trailing window, by disposition"
+        );
+        let _ = writeln!(output, "# TYPE {name} gauge");
+        for (disposition, rates) in [
+            ("enforce", &self.enforced_violation_rates),
+            ("report", &self.report_only_violation_rates),
+        ] {
+            for (window, rate) in [
+                ("1m", rates.one_minute),
+                ("5m", rates.five_minutes),
+                ("15m", rates.fifteen_minutes),
+            ] {
+                let _ = writeln!(
+                    output,
+                    "{name}{{disposition=\"{disposition}\",window=\"{window}\"}} {rate}"
+                );
+            }
+        }
+
+        write_histogram(
+            &mut output,
+            "csp_report_size_bytes",
+            "Body size of violation report requests",
+            &self.report_size_bytes,
+        );
+        write_histogram(
+            &mut output,
+            "csp_violation_handler_duration_seconds",
+            "Time spent in the violation handler per report",
+            &self.handler_duration_seconds,
+        );
+
+        for (name, help, kind, value) in [
+            (
+                "csp_cache_hits_total",
+                "Cache lookups answered from the cache",
+                "counter",
+                (|stats| stats.hits as usize) as fn(&CacheStats) -> usize,
+            ),
+            (
+                "csp_cache_misses_total",
+                "Cache lookups that missed",
+                "counter",
+                |stats| stats.misses as usize,
+            ),
+            (
+                "csp_cache_evictions_total",
+                "Entries dropped to make room or because they went stale",
+                "counter",
+                |stats| stats.evictions as usize,
+            ),
+            (
+                "csp_cache_entries",
+                "Entries currently held",
+                "gauge",
+                |stats| stats.entries,
+            ),
+            (
+                "csp_cache_capacity",
+                "Most entries the cache holds",
+                "gauge",
+                |stats| stats.capacity,
+            ),
+        ] {
+            write_labeled(
+                &mut output,
+                name,
+                help,
+                kind,
+                "cache",
+                self.caches
+                    .iter()
+                    .map(|(cache, stats)| (cache.as_str(), value(stats))),
+            );
+        }
+
+        write_labeled(
+            &mut output,
+            "csp_violations_by_directive_total",
+            "Violation reports by effective directive",
+            "counter",
+            "directive",
+            self.violations_by_directive
+                .iter()
+                .map(|(label, count)| (label.as_str(), *count)),
+        );
+        write_labeled(
+            &mut output,
+            "csp_violations_by_disposition_total",
+            "Violation reports by disposition",
+            "counter",
+            "disposition",
+            self.violations_by_disposition
+                .iter()
+                .map(|(label, count)| (label.as_str(), *count)),
+        );
+        let name = "csp_violations_by_directive_and_disposition_total";
+        let _ = writeln!(
+            output,
+            "# HELP {name} Violation reports by disposition and effective directive"
+        );
+        let _ = writeln!(output, "# TYPE {name} counter");
+        for (disposition, directives) in &self.violations_by_disposition_and_directive {
+            for (directive, count) in directives {
+                let _ = writeln!(
+                    output,
+                    "{name}{{disposition=\"{disposition}\",directive=\"{directive}\"}} {count}"
+                );
+            }
+        }
+        write_labeled(
+            &mut output,
+            "csp_browser_violations",
+            "Violation reports by browser family and major version (approximate)",
+            "gauge",
+            "browser",
+            self.violations_by_browser
+                .iter()
+                .map(|(label, count)| (label.as_str(), *count)),
+        );
+        write_labeled(
+            &mut output,
+            "csp_blocked_origin_violations",
+            "Violation reports for the most frequently blocked origins (approximate)",
+            "gauge",
+            "origin",
+            self.top_blocked_origins
+                .iter()
+                .map(|(label, count)| (label.as_str(), *count)),
+        );
+        write_labeled(
+            &mut output,
+            "csp_client_fingerprint_violations",
+            "Violation reports from the most active client fingerprints (approximate)",
+            "gauge",
+            "fingerprint",
+            self.top_client_fingerprints
+                .iter()
+                .map(|(label, count)| (label.as_str(), *count)),
+        );
+
+        output
+    }
+}
+
+fn write_histogram(output: &mut String, name: &str, help: &str, histogram: &HistogramSnapshot) {
+    let _ = writeln!(output, "# HELP {name} {help}");
+    let _ = writeln!(output, "# TYPE {name} histogram");
+    for (bound, count) in &histogram.buckets {
+        let _ = writeln!(output, "{name}_bucket{{le=\"{bound}\"}} {count}");
+    }
+    let _ = writeln!(output, "{name}_bucket{{le=\"+Inf\"}} {}", histogram.count);
+    let _ = writeln!(output, "{name}_sum {}", histogram.sum);
+    let _ = writeln!(output, "{name}_count {}", histogram.count);
+}
+
+fn write_labeled<'a>(
+    output: &mut String,
+    name: &str,
+    help: &str,
+    kind: &str,
+    label: &str,
+    values: impl Iterator<Item = (&'a str, usize)>,
+) {
+    let _ = writeln!(output, "# HELP {name} {help}");
+    let _ = writeln!(output, "# TYPE {name} {kind}");
+    for (value, count) in values {
+        let escaped = value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        let _ = writeln!(output, "{name}{{{label}=\"{escaped}\"}} {count}");
+    }
+}
+
+#[cfg(feature = "stats")]
+/// Label under which a report's directive is counted.
+fn directive_label(report: &CspViolationReport) -> &'static str {
+    let directive = if report.effective_directive.is_empty() {
+        report
+            .violated_directive
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+    } else {
+        report.effective_directive.as_str()
+    };
+    crate::core::policy::known_directive(directive).unwrap_or("other")
+}
+
+#[cfg(feature = "stats")]
+fn disposition_label(report: &CspViolationReport) -> &'static str {
+    if report.is_enforce() {
+        "enforce"
+    } else if report.is_report() {
+        "report"
+    } else {
+        "unknown"
+    }
+}
+
+#[cfg(feature = "stats")]
+/// Origin of the blocked resource, or the keyword browsers report instead of a URL.
+fn blocked_origin(blocked_uri: &str) -> Cow<'static, str> {
+    match blocked_uri.trim() {
+        "" => Cow::Borrowed("unknown"),
+        "inline" => Cow::Borrowed("inline"),
+        "eval" => Cow::Borrowed("eval"),
+        "wasm-eval" => Cow::Borrowed("wasm-eval"),
+        "self" => Cow::Borrowed("self"),
+        "data" => Cow::Borrowed("data:"),
+        "blob" => Cow::Borrowed("blob:"),
+        uri => match Url::parse(uri) {
+            Ok(url) if url.origin().is_tuple() => Cow::Owned(url.origin().ascii_serialization()),
+            Ok(url) => Cow::Owned(format!("{}:", url.scheme())),
+            Err(_) => Cow::Borrowed("other"),
+        },
+    }
+}
+
 #[cfg(feature = "stats")]
 mod imp {
+    use super::{
+        blocked_origin, directive_label, disposition_label, CspViolationReport, HistogramSnapshot,
+        PolicyTrend, StatsSnapshot, WindowedRates, HANDLER_DURATION_BUCKETS, MAX_TRACKED_BROWSERS,
+        MAX_TRACKED_CLIENTS, MAX_TRACKED_ORIGINS, REPORT_SIZE_BUCKETS, SNAPSHOT_TOP_CLIENTS,
+        SNAPSHOT_TOP_ORIGINS,
+    };
+    use crate::logging::csp_log;
+    use crate::monitoring::cache::{CacheMetrics, CacheStats};
+    use parking_lot::Mutex;
+    use std::collections::{BTreeMap, HashMap, VecDeque};
     use std::fmt;
-    use std::sync::atomic::{AtomicUsize, Ordering};
-    use std::time::Instant;
+    use std::num::NonZeroU64;
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    const BUCKET_SECS: u64 = 5;
+    /// Enough buckets for the longest (15 minute) window
+    const BUCKETS: usize = (15 * 60 / BUCKET_SECS) as usize;
+    /// Policy versions whose violation counts are kept
+    const MAX_TRACKED_POLICY_VERSIONS: usize = 16;
+    /// Violations a policy version needs before it can be flagged as a regression
+    const REGRESSION_MIN_VIOLATIONS: usize = 10;
+    /// How many times the previous version's violation rate counts as a spike
+    const REGRESSION_RATE_FACTOR: f64 = 2.0;
+    /// How long a version with few violations must have been active before its
+    /// rate is trusted as the baseline for the next one
+    const REGRESSION_MIN_BASELINE: Duration = Duration::from_secs(60);
+
+    #[derive(Debug, Default)]
+    struct Bucket {
+        tick: AtomicU64,
+        count: AtomicUsize,
+    }
+
+    /// Event counts for the last 15 minutes in a ring of fixed-width buckets.
+    ///
+    /// Buckets are recycled lazily by the first event of a new tick. A concurrent
+    /// event racing that recycle may be lost, which is acceptable for rates.
+    #[derive(Debug)]
+    struct RollingCounter {
+        buckets: Box<[Bucket]>,
+    }
+
+    impl Default for RollingCounter {
+        fn default() -> Self {
+            Self {
+                buckets: (0..BUCKETS).map(|_| Bucket::default()).collect(),
+            }
+        }
+    }
+
+    impl RollingCounter {
+        fn add(&self, tick: u64) {
+            let bucket = &self.buckets[tick as usize % BUCKETS];
+            let seen = bucket.tick.load(Ordering::Acquire);
+            if seen != tick
+                && bucket
+                    .tick
+                    .compare_exchange(seen, tick, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok()
+            {
+                bucket.count.store(0, Ordering::Release);
+            }
+            bucket.count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn sum(&self, now: u64, ticks: u64) -> usize {
+            self.buckets
+                .iter()
+                .filter(|bucket| now.saturating_sub(bucket.tick.load(Ordering::Acquire)) < ticks)
+                .map(|bucket| bucket.count.load(Ordering::Relaxed))
+                .sum()
+        }
+
+        fn clear(&self) {
+            for bucket in self.buckets.iter() {
+                bucket.count.store(0, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Observation counts per bucket, with a running sum in the recorded unit.
+    #[derive(Debug)]
+    struct Histogram {
+        bounds: &'static [f64],
+        /// Per-bucket (not cumulative) counts, with one extra for `+Inf`
+        counts: Box<[AtomicUsize]>,
+        /// Sum of observations in millionths of the unit
+        sum_micros: AtomicU64,
+    }
+
+    impl Histogram {
+        fn new(bounds: &'static [f64]) -> Self {
+            Self {
+                bounds,
+                counts: (0..=bounds.len()).map(|_| AtomicUsize::new(0)).collect(),
+                sum_micros: AtomicU64::new(0),
+            }
+        }
+
+        #[cfg(feature = "reporting")]
+        fn observe(&self, value: f64) {
+            let bucket = self
+                .bounds
+                .iter()
+                .position(|bound| value <= *bound)
+                .unwrap_or(self.bounds.len());
+            self.counts[bucket].fetch_add(1, Ordering::Relaxed);
+            let micros = (value * 1_000_000.0) as u64;
+            let _ = self
+                .sum_micros
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |sum| {
+                    Some(sum.saturating_add(micros))
+                });
+        }
+
+        fn snapshot(&self) -> HistogramSnapshot {
+            let mut cumulative = 0;
+            let buckets = self
+                .bounds
+                .iter()
+                .zip(self.counts.iter())
+                .map(|(bound, count)| {
+                    cumulative += count.load(Ordering::Relaxed);
+                    (*bound, cumulative)
+                })
+                .collect();
+            HistogramSnapshot {
+                buckets,
+                sum: self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+                count: cumulative + self.counts[self.bounds.len()].load(Ordering::Relaxed),
+            }
+        }
+
+        fn clear(&self) {
+            for count in self.counts.iter() {
+                count.store(0, Ordering::Relaxed);
+            }
+            self.sum_micros.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Per-directive, per-disposition, per-origin, per-client and per-browser
+    /// violation counts.
+    ///
+    /// Blocked origins, client fingerprints and browsers are counted with the Space-Saving
+    /// algorithm: once the table is full, a new key replaces the least frequent
+    /// one and inherits its count. Frequent keys are therefore never lost, while
+    /// counts of the rarest entries may be overestimated.
+    #[derive(Debug, Default)]
+    struct ViolationBreakdown {
+        by_directive: HashMap<&'static str, usize>,
+        by_disposition: HashMap<&'static str, usize>,
+        by_disposition_and_directive: HashMap<(&'static str, &'static str), usize>,
+        by_origin: HashMap<String, usize>,
+        by_client: HashMap<String, usize>,
+        by_browser: HashMap<String, usize>,
+    }
+
+    impl ViolationBreakdown {
+        fn record(&mut self, report: &CspViolationReport) {
+            let directive = directive_label(report);
+            let disposition = disposition_label(report);
+            *self.by_directive.entry(directive).or_default() += 1;
+            *self.by_disposition.entry(disposition).or_default() += 1;
+            *self
+                .by_disposition_and_directive
+                .entry((disposition, directive))
+                .or_default() += 1;
+
+            count_bounded(
+                &mut self.by_origin,
+                &blocked_origin(&report.blocked_uri),
+                MAX_TRACKED_ORIGINS,
+            );
+            if let Some(fingerprint) = &report.client_fingerprint {
+                count_bounded(&mut self.by_client, fingerprint, MAX_TRACKED_CLIENTS);
+            }
+            if let Some(browser) = &report.browser {
+                let label = match browser.major_version() {
+                    Some(major) => format!("{} {major}", browser.family),
+                    None => browser.family.to_string(),
+                };
+                count_bounded(&mut self.by_browser, &label, MAX_TRACKED_BROWSERS);
+            }
+        }
+
+        fn by_disposition_and_directive(&self) -> BTreeMap<String, BTreeMap<String, usize>> {
+            let mut nested: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+            for ((disposition, directive), count) in &self.by_disposition_and_directive {
+                nested
+                    .entry(disposition.to_string())
+                    .or_default()
+                    .insert(directive.to_string(), *count);
+            }
+            nested
+        }
+
+        fn disposition_count(&self, disposition: &str) -> usize {
+            self.by_disposition
+                .get(disposition)
+                .copied()
+                .unwrap_or_default()
+        }
+
+        fn top_origins(&self, limit: usize) -> Vec<(String, usize)> {
+            top_counts(&self.by_origin, limit)
+        }
+
+        fn top_clients(&self, limit: usize) -> Vec<(String, usize)> {
+            top_counts(&self.by_client, limit)
+        }
+    }
+
+    /// Counts `key` in a Space-Saving table of at most `capacity` entries.
+    fn count_bounded(counts: &mut HashMap<String, usize>, key: &str, capacity: usize) {
+        if let Some(count) = counts.get_mut(key) {
+            *count += 1;
+            return;
+        }
+        let mut count = 1;
+        if counts.len() >= capacity {
+            let evicted = counts
+                .iter()
+                .min_by_key(|(_, count)| **count)
+                .map(|(key, count)| (key.clone(), *count));
+            if let Some((evicted, evicted_count)) = evicted {
+                counts.remove(&evicted);
+                count += evicted_count;
+            }
+        }
+        counts.insert(key.to_owned(), count);
+    }
+
+    fn top_counts(counts: &HashMap<String, usize>, limit: usize) -> Vec<(String, usize)> {
+        let mut top: Vec<_> = counts
+            .iter()
+            .map(|(key, count)| (key.clone(), *count))
+            .collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(limit);
+        top
+    }
+
+    #[derive(Debug)]
+    struct PolicyVersion {
+        hash: NonZeroU64,
+        since: Instant,
+        until: Option<Instant>,
+        violations: usize,
+        /// A regression warning was logged for this version
+        flagged: bool,
+    }
+
+    impl PolicyVersion {
+        fn new(hash: NonZeroU64, since: Instant) -> Self {
+            Self {
+                hash,
+                since,
+                until: None,
+                violations: 0,
+                flagged: false,
+            }
+        }
+
+        fn active_for(&self, now: Instant) -> Duration {
+            self.until
+                .unwrap_or(now)
+                .saturating_duration_since(self.since)
+        }
+
+        fn violations_per_minute(&self, now: Instant) -> f64 {
+            let active = self.active_for(now).as_secs_f64().max(1.0);
+            self.violations as f64 * 60.0 / active
+        }
+
+        /// Whether the version saw enough violations, or ran long enough, for
+        /// its rate to mean something when the next version is compared to it.
+        fn is_baseline(&self, now: Instant) -> bool {
+            self.violations >= REGRESSION_MIN_VIOLATIONS
+                || self.active_for(now) >= REGRESSION_MIN_BASELINE
+        }
+    }
+
+    /// Violation counts per policy version, oldest first.
+    ///
+    /// A version is a regression when it has drawn at least
+    /// `REGRESSION_MIN_VIOLATIONS` reports at `REGRESSION_RATE_FACTOR` times the
+    /// rate of the version before it, or any such number after a clean one.
+    /// The version before only counts as a baseline once it has drawn
+    /// `REGRESSION_MIN_VIOLATIONS` reports itself or stayed active for
+    /// `REGRESSION_MIN_BASELINE`, so a version replaced seconds after startup
+    /// does not make every later one look like a spike.
+    #[derive(Debug, Default)]
+    struct PolicyTimeline {
+        versions: VecDeque<PolicyVersion>,
+    }
+
+    impl PolicyTimeline {
+        fn switch_to(&mut self, hash: NonZeroU64, now: Instant) {
+            if self
+                .versions
+                .back()
+                .is_some_and(|current| current.hash == hash)
+            {
+                return;
+            }
+            if let Some(current) = self.versions.back_mut() {
+                current.until = Some(now);
+            }
+            if self.versions.len() == MAX_TRACKED_POLICY_VERSIONS {
+                self.versions.pop_front();
+            }
+            self.versions.push_back(PolicyVersion::new(hash, now));
+        }
+
+        fn record_violation(&mut self, now: Instant) {
+            let Some(current) = self.versions.back_mut() else {
+                return;
+            };
+            current.violations += 1;
+            if current.flagged {
+                return;
+            }
+            let trend = self.trend(self.versions.len() - 1, now, now);
+            if trend.regression {
+                csp_log!(
+                    Warn,
+                    "CSP policy {:016x} correlates with a violation spike: {:.1}/min, {:.1}/min before it was deployed",
+                    trend.policy_hash,
+                    trend.violations_per_minute,
+                    trend.previous_violations_per_minute.unwrap_or_default()
+                );
+                if let Some(current) = self.versions.back_mut() {
+                    current.flagged = true;
+                }
+            }
+        }
+
+        fn trend(&self, index: usize, now: Instant, origin: Instant) -> PolicyTrend {
+            let version = &self.versions[index];
+            let rate = version.violations_per_minute(now);
+            let previous = index
+                .checked_sub(1)
+                .map(|previous| &self.versions[previous]);
+            let regression = version.violations >= REGRESSION_MIN_VIOLATIONS
+                && previous.is_some_and(|previous| {
+                    previous.is_baseline(now)
+                        && rate >= previous.violations_per_minute(now) * REGRESSION_RATE_FACTOR
+                });
+            let previous = previous.map(|previous| previous.violations_per_minute(now));
+
+            PolicyTrend {
+                policy_hash: version.hash,
+                active_since_secs: version.since.saturating_duration_since(origin).as_secs(),
+                active: version.until.is_none(),
+                violations: version.violations,
+                violations_per_minute: rate,
+                previous_violations_per_minute: previous,
+                regression,
+            }
+        }
+
+        fn trends(&self, now: Instant, origin: Instant) -> Vec<PolicyTrend> {
+            (0..self.versions.len())
+                .map(|index| self.trend(index, now, origin))
+                .collect()
+        }
+
+        fn reset(&mut self, now: Instant) {
+            let current = self.versions.pop_back();
+            self.versions.clear();
+            if let Some(current) = current {
+                self.versions
+                    .push_back(PolicyVersion::new(current.hash, now));
+            }
+        }
+    }
 
     #[derive(Debug)]
     pub struct CspStats {
@@ -11,7 +939,32 @@ mod imp {
         policy_update_count: AtomicUsize,
         header_generation_time_ns: AtomicUsize,
         violation_count: AtomicUsize,
+        shadow_violation_count: AtomicUsize,
+        oversized_report_count: AtomicUsize,
+        timed_out_report_count: AtomicUsize,
+        shed_report_count: AtomicUsize,
+        malformed_report_count: AtomicUsize,
+        blocked_report_count: AtomicUsize,
+        handler_panic_count: AtomicUsize,
+        fallback_header_count: AtomicUsize,
+        rejected_response_count: AtomicUsize,
+        header_generation_failure_count: AtomicUsize,
+        header_insertion_failure_count: AtomicUsize,
+        stale_policy_cache_count: AtomicUsize,
+        dropped_report_count: AtomicUsize,
+        report_queue_depth: AtomicUsize,
+        violations: Mutex<ViolationBreakdown>,
+        policy_timeline: Mutex<PolicyTimeline>,
+        report_sizes: Histogram,
+        handler_durations: Histogram,
+        recent_requests: RollingCounter,
+        recent_violations: RollingCounter,
+        recent_enforced_violations: RollingCounter,
+        recent_report_only_violations: RollingCounter,
+        /// Seconds after `start_time` at which the rolling windows were last reset
+        window_start_secs: AtomicU64,
         cache_hit_count: AtomicUsize,
+        policy_cache: Arc<CacheMetrics>,
         policy_hash_time_ns: AtomicUsize,
         policy_serialize_time_ns: AtomicUsize,
         policy_validations: AtomicUsize,
@@ -26,7 +979,31 @@ mod imp {
                 policy_update_count: Default::default(),
                 header_generation_time_ns: Default::default(),
                 violation_count: Default::default(),
+                shadow_violation_count: Default::default(),
+                oversized_report_count: Default::default(),
+                timed_out_report_count: Default::default(),
+                shed_report_count: Default::default(),
+                malformed_report_count: Default::default(),
+                blocked_report_count: Default::default(),
+                handler_panic_count: Default::default(),
+                fallback_header_count: Default::default(),
+                rejected_response_count: Default::default(),
+                header_generation_failure_count: Default::default(),
+                header_insertion_failure_count: Default::default(),
+                stale_policy_cache_count: Default::default(),
+                dropped_report_count: Default::default(),
+                report_queue_depth: Default::default(),
+                violations: Default::default(),
+                policy_timeline: Default::default(),
+                report_sizes: Histogram::new(&REPORT_SIZE_BUCKETS),
+                handler_durations: Histogram::new(&HANDLER_DURATION_BUCKETS),
+                recent_requests: Default::default(),
+                recent_violations: Default::default(),
+                recent_enforced_violations: Default::default(),
+                recent_report_only_violations: Default::default(),
+                window_start_secs: Default::default(),
                 cache_hit_count: Default::default(),
+                policy_cache: Default::default(),
                 policy_hash_time_ns: Default::default(),
                 policy_serialize_time_ns: Default::default(),
                 policy_validations: Default::default(),
@@ -66,6 +1043,329 @@ mod imp {
             self.violation_count.load(Ordering::Relaxed)
         }
 
+        /// Violations predicted by the server-side shadow audit of HTML responses.
+        #[inline]
+        pub fn shadow_violation_count(&self) -> usize {
+            self.shadow_violation_count.load(Ordering::Relaxed)
+        }
+
+        /// Violation reports rejected because their body exceeded the size limit.
+        #[inline]
+        pub fn oversized_report_count(&self) -> usize {
+            self.oversized_report_count.load(Ordering::Relaxed)
+        }
+
+        /// Violation reports whose body did not arrive within the read timeout.
+        #[inline]
+        pub fn timed_out_report_count(&self) -> usize {
+            self.timed_out_report_count.load(Ordering::Relaxed)
+        }
+
+        /// Violation reports turned away because the in-flight limit was reached.
+        #[inline]
+        pub fn shed_report_count(&self) -> usize {
+            self.shed_report_count.load(Ordering::Relaxed)
+        }
+
+        /// Violation reports whose body was not valid report JSON.
+        #[inline]
+        pub fn malformed_report_count(&self) -> usize {
+            self.malformed_report_count.load(Ordering::Relaxed)
+        }
+
+        /// Violation reports refused by the block-list or a temporary ban.
+        #[inline]
+        pub fn blocked_report_count(&self) -> usize {
+            self.blocked_report_count.load(Ordering::Relaxed)
+        }
+
+        /// Violation handler or enricher calls that panicked. The report
+        /// endpoint keeps answering; the affected report is lost.
+        #[inline]
+        pub fn handler_panic_count(&self) -> usize {
+            self.handler_panic_count.load(Ordering::Relaxed)
+        }
+
+        /// Responses sent with `default-src 'self'` under
+        /// [`HeaderFailureMode::Fallback`](crate::core::HeaderFailureMode::Fallback).
+        #[inline]
+        pub fn fallback_header_count(&self) -> usize {
+            self.fallback_header_count.load(Ordering::Relaxed)
+        }
+
+        /// Responses replaced with a `500` under
+        /// [`HeaderFailureMode::Reject`](crate::core::HeaderFailureMode::Reject).
+        #[inline]
+        pub fn rejected_response_count(&self) -> usize {
+            self.rejected_response_count.load(Ordering::Relaxed)
+        }
+
+        /// Responses whose policy header could not be serialized or compiled.
+        #[inline]
+        pub fn header_generation_failure_count(&self) -> usize {
+            self.header_generation_failure_count.load(Ordering::Relaxed)
+        }
+
+        /// Headers the middleware could not insert because their name or value
+        /// was invalid, such as a nonce echo header with a malformed name.
+        #[inline]
+        pub fn header_insertion_failure_count(&self) -> usize {
+            self.header_insertion_failure_count.load(Ordering::Relaxed)
+        }
+
+        /// Cached policies whose header value had expired when a response
+        /// needed it. The header is regenerated and the cache entry refreshed.
+        #[inline]
+        pub fn stale_policy_cache_count(&self) -> usize {
+            self.stale_policy_cache_count.load(Ordering::Relaxed)
+        }
+
+        /// Violation reports dropped because the report queue was full.
+        #[inline]
+        pub fn dropped_report_count(&self) -> usize {
+            self.dropped_report_count.load(Ordering::Relaxed)
+        }
+
+        /// Violation reports waiting in the report queue for the handler.
+        #[inline]
+        pub fn report_queue_depth(&self) -> usize {
+            self.report_queue_depth.load(Ordering::Relaxed)
+        }
+
+        /// Reported violations per effective directive, such as `script-src-elem`.
+        ///
+        /// Directive names this crate does not know are counted under `other`, so
+        /// crafted reports can not grow the table.
+        pub fn violations_by_directive(&self) -> BTreeMap<String, usize> {
+            self.violations
+                .lock()
+                .by_directive
+                .iter()
+                .map(|(directive, count)| (directive.to_string(), *count))
+                .collect()
+        }
+
+        /// Reported violations per disposition: `enforce`, `report` or `unknown`.
+        pub fn violations_by_disposition(&self) -> BTreeMap<String, usize> {
+            self.violations
+                .lock()
+                .by_disposition
+                .iter()
+                .map(|(disposition, count)| (disposition.to_string(), *count))
+                .collect()
+        }
+
+        /// Violations reported by browsers enforcing the policy.
+        #[inline]
+        pub fn enforced_violation_count(&self) -> usize {
+            self.violations.lock().disposition_count("enforce")
+        }
+
+        /// Violations reported under a `Content-Security-Policy-Report-Only`
+        /// header, which the browser did not block.
+        #[inline]
+        pub fn report_only_violation_count(&self) -> usize {
+            self.violations.lock().disposition_count("report")
+        }
+
+        /// Reported violations per disposition, then per effective directive.
+        ///
+        /// With an enforced and a report-only policy served side by side, this
+        /// separates what users run into from what a stricter candidate policy
+        /// would add, which a per-directive total mixes up.
+        pub fn violations_by_disposition_and_directive(
+            &self,
+        ) -> BTreeMap<String, BTreeMap<String, usize>> {
+            self.violations.lock().by_disposition_and_directive()
+        }
+
+        /// The `limit` most frequently blocked origins, most frequent first.
+        ///
+        /// At most 64 origins are tracked; counts are exact for origins that were
+        /// never evicted and upper bounds otherwise.
+        pub fn top_blocked_origins(&self, limit: usize) -> Vec<(String, usize)> {
+            self.violations.lock().top_origins(limit)
+        }
+
+        /// Body sizes of the report requests read, in bytes.
+        #[inline]
+        pub fn report_size_histogram(&self) -> HistogramSnapshot {
+            self.report_sizes.snapshot()
+        }
+
+        /// Time spent in the violation handler per report, in seconds.
+        ///
+        /// Handlers run on the request path; a tail here usually means a handler
+        /// doing blocking work such as synchronous database writes.
+        #[inline]
+        pub fn handler_duration_histogram(&self) -> HistogramSnapshot {
+            self.handler_durations.snapshot()
+        }
+
+        /// Violation counts keyed by browser family and major version, e.g.
+        /// `Safari 16`.
+        ///
+        /// Only reports whose browser was recognised are counted, which requires
+        /// the `user-agent` feature. At most 64 versions are tracked; when a new
+        /// one arrives the rarest is evicted and its count carried over, so the
+        /// counts are approximate and one may go down.
+        pub fn violations_by_browser(&self) -> BTreeMap<String, usize> {
+            self.violations
+                .lock()
+                .by_browser
+                .iter()
+                .map(|(browser, count)| (browser.clone(), *count))
+                .collect()
+        }
+
+        /// Client fingerprints that sent the most reports, most frequent first.
+        ///
+        /// Only reports received with
+        /// [`with_client_fingerprinting`] on are counted. At most 64 fingerprints
+        /// are tracked, with the same accuracy as
+        /// [`top_blocked_origins`](Self::top_blocked_origins).
+        ///
+        /// [`with_client_fingerprinting`]: crate::middleware::CspReportingMiddleware::with_client_fingerprinting
+        pub fn top_client_fingerprints(&self, limit: usize) -> Vec<(String, usize)> {
+            self.violations.lock().top_clients(limit)
+        }
+
+        /// Requests per minute over the last 1, 5 and 15 minutes.
+        ///
+        /// Unlike [`requests_per_second`](Self::requests_per_second), which averages
+        /// over the whole uptime, these follow recent traffic and suit alerting.
+        #[inline]
+        pub fn request_rates(&self) -> WindowedRates {
+            self.rates(&self.recent_requests)
+        }
+
+        /// Violation reports per minute over the last 1, 5 and 15 minutes.
+        #[inline]
+        pub fn violation_rates(&self) -> WindowedRates {
+            self.rates(&self.recent_violations)
+        }
+
+        /// Like [`violation_rates`](Self::violation_rates), counting only reports
+        /// from enforced policies.
+        #[inline]
+        pub fn enforced_violation_rates(&self) -> WindowedRates {
+            self.rates(&self.recent_enforced_violations)
+        }
+
+        /// Like [`violation_rates`](Self::violation_rates), counting only reports
+        /// from report-only policies.
+        #[inline]
+        pub fn report_only_violation_rates(&self) -> WindowedRates {
+            self.rates(&self.recent_report_only_violations)
+        }
+
+        fn rates(&self, counter: &RollingCounter) -> WindowedRates {
+            let elapsed = self.start_time.elapsed().as_secs_f64();
+            let since_reset =
+                (elapsed - self.window_start_secs.load(Ordering::Relaxed) as f64).max(1.0);
+            let now = self.tick();
+            let rate = |minutes: u64| {
+                let count = counter.sum(now, minutes * 60 / BUCKET_SECS);
+                count as f64 * 60.0 / since_reset.min((minutes * 60) as f64)
+            };
+
+            WindowedRates {
+                one_minute: rate(1),
+                five_minutes: rate(5),
+                fifteen_minutes: rate(15),
+            }
+        }
+
+        /// Violation rates of the recent policy versions, oldest first.
+        ///
+        /// Versions are recorded by the [`CspConfig`] these statistics belong to;
+        /// violations only count when the reporting middleware shares them through
+        /// [`with_stats`]. Only the last 16 versions are kept.
+        ///
+        /// [`CspConfig`]: crate::core::CspConfig
+        /// [`with_stats`]: crate::middleware::CspReportingMiddleware::with_stats
+        pub fn policy_trends(&self) -> Vec<PolicyTrend> {
+            let origin = self.start_time
+                + std::time::Duration::from_secs(self.window_start_secs.load(Ordering::Relaxed));
+            self.policy_timeline.lock().trends(Instant::now(), origin)
+        }
+
+        #[inline]
+        fn tick(&self) -> u64 {
+            self.start_time.elapsed().as_secs() / BUCKET_SECS
+        }
+
+        pub fn snapshot(&self) -> StatsSnapshot {
+            let violations = self.violations.lock();
+            StatsSnapshot {
+                uptime_secs: self.uptime_secs(),
+                request_count: self.request_count(),
+                nonce_generation_count: self.nonce_generation_count(),
+                policy_update_count: self.policy_update_count(),
+                policy_validations: self.policy_validations(),
+                cache_hit_count: self.cache_hit_count(),
+                violation_count: self.violation_count(),
+                shadow_violation_count: self.shadow_violation_count(),
+                oversized_report_count: self.oversized_report_count(),
+                timed_out_report_count: self.timed_out_report_count(),
+                shed_report_count: self.shed_report_count(),
+                malformed_report_count: self.malformed_report_count(),
+                blocked_report_count: self.blocked_report_count(),
+                handler_panic_count: self.handler_panic_count(),
+                fallback_header_count: self.fallback_header_count(),
+                rejected_response_count: self.rejected_response_count(),
+                header_generation_failure_count: self.header_generation_failure_count(),
+                header_insertion_failure_count: self.header_insertion_failure_count(),
+                stale_policy_cache_count: self.stale_policy_cache_count(),
+                dropped_report_count: self.dropped_report_count(),
+                report_queue_depth: self.report_queue_depth(),
+                invalid_uri_count: crate::security::verify::invalid_uri_count(),
+                request_rates: self.request_rates(),
+                violation_rates: self.violation_rates(),
+                enforced_violation_rates: self.enforced_violation_rates(),
+                report_only_violation_rates: self.report_only_violation_rates(),
+                violations_by_directive: violations
+                    .by_directive
+                    .iter()
+                    .map(|(directive, count)| (directive.to_string(), *count))
+                    .collect(),
+                violations_by_disposition: violations
+                    .by_disposition
+                    .iter()
+                    .map(|(disposition, count)| (disposition.to_string(), *count))
+                    .collect(),
+                violations_by_disposition_and_directive: violations.by_disposition_and_directive(),
+                violations_by_browser: violations
+                    .by_browser
+                    .iter()
+                    .map(|(browser, count)| (browser.clone(), *count))
+                    .collect(),
+                top_blocked_origins: violations.top_origins(SNAPSHOT_TOP_ORIGINS),
+                top_client_fingerprints: violations.top_clients(SNAPSHOT_TOP_CLIENTS),
+                policy_trends: self.policy_trends(),
+                report_size_bytes: self.report_size_histogram(),
+                handler_duration_seconds: self.handler_duration_histogram(),
+                caches: self.cache_stats(),
+            }
+        }
+
+        /// Hits, misses, evictions and occupancy of each cache, see
+        /// [`StatsSnapshot::caches`].
+        pub fn cache_stats(&self) -> BTreeMap<String, CacheStats> {
+            let mut caches = BTreeMap::new();
+            caches.insert("policy".to_string(), self.policy_cache.stats());
+            if let Some(verification) = crate::security::verify::verification_cache_stats() {
+                caches.insert("verification".to_string(), verification);
+            }
+            caches.insert("buffer_pool".to_string(), crate::utils::buffer_pool_stats());
+            caches
+        }
+
+        #[inline]
+        pub(crate) fn policy_cache_metrics(&self) -> Arc<CacheMetrics> {
+            self.policy_cache.clone()
+        }
+
         #[inline]
         pub fn cache_hit_count(&self) -> usize {
             self.cache_hit_count.load(Ordering::Relaxed)
@@ -104,6 +1404,7 @@ mod imp {
         #[inline]
         pub(crate) fn increment_request_count(&self) {
             self.request_count.fetch_add(1, Ordering::Relaxed);
+            self.recent_requests.add(self.tick());
         }
 
         #[inline]
@@ -126,6 +1427,124 @@ mod imp {
         #[inline]
         pub(crate) fn increment_violation_count(&self) {
             self.violation_count.fetch_add(1, Ordering::Relaxed);
+            self.recent_violations.add(self.tick());
+        }
+
+        /// Counts a received report and files it under its directive,
+        /// disposition and blocked origin.
+        #[allow(dead_code)]
+        pub(crate) fn record_violation(&self, report: &CspViolationReport) {
+            self.increment_violation_count();
+            if report.is_enforce() {
+                self.recent_enforced_violations.add(self.tick());
+            } else if report.is_report() {
+                self.recent_report_only_violations.add(self.tick());
+            }
+            self.violations.lock().record(report);
+            self.policy_timeline.lock().record_violation(Instant::now());
+        }
+
+        #[cfg(feature = "reporting")]
+        #[inline]
+        pub(crate) fn record_report_size(&self, bytes: usize) {
+            self.report_sizes.observe(bytes as f64);
+        }
+
+        #[cfg(feature = "reporting")]
+        #[inline]
+        pub(crate) fn record_handler_duration(&self, duration: Duration) {
+            self.handler_durations.observe(duration.as_secs_f64());
+        }
+
+        /// Starts attributing violations to the policy version `hash`.
+        pub(crate) fn record_policy_version(&self, hash: NonZeroU64) {
+            self.policy_timeline.lock().switch_to(hash, Instant::now());
+        }
+
+        #[cfg(feature = "page-audit")]
+        #[inline]
+        pub(crate) fn add_shadow_violations(&self, count: usize) {
+            self.shadow_violation_count
+                .fetch_add(count, Ordering::Relaxed);
+        }
+
+        #[cfg(feature = "reporting")]
+        #[inline]
+        pub(crate) fn increment_oversized_report_count(&self) {
+            self.oversized_report_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[cfg(feature = "reporting")]
+        #[inline]
+        pub(crate) fn increment_timed_out_report_count(&self) {
+            self.timed_out_report_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[cfg(feature = "reporting")]
+        #[inline]
+        pub(crate) fn increment_shed_report_count(&self) {
+            self.shed_report_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[cfg(feature = "reporting")]
+        #[inline]
+        pub(crate) fn increment_malformed_report_count(&self) {
+            self.malformed_report_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[cfg(feature = "reporting")]
+        #[inline]
+        pub(crate) fn increment_blocked_report_count(&self) {
+            self.blocked_report_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[cfg(feature = "reporting")]
+        #[inline]
+        pub(crate) fn increment_handler_panic_count(&self) {
+            self.handler_panic_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[inline]
+        pub(crate) fn increment_fallback_header_count(&self) {
+            self.fallback_header_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[inline]
+        pub(crate) fn increment_rejected_response_count(&self) {
+            self.rejected_response_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[inline]
+        pub(crate) fn increment_header_generation_failure_count(&self) {
+            self.header_generation_failure_count
+                .fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[inline]
+        pub(crate) fn increment_header_insertion_failure_count(&self) {
+            self.header_insertion_failure_count
+                .fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[inline]
+        pub(crate) fn increment_stale_policy_cache_count(&self) {
+            self.stale_policy_cache_count
+                .fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[inline]
+        pub(crate) fn increment_dropped_report_count(&self) {
+            self.dropped_report_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[inline]
+        pub(crate) fn increment_report_queue_depth(&self) {
+            self.report_queue_depth.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[inline]
+        pub(crate) fn decrement_report_queue_depth(&self) {
+            self.report_queue_depth.fetch_sub(1, Ordering::Relaxed);
         }
 
         #[inline]
@@ -145,7 +1564,6 @@ mod imp {
                 .fetch_add(time_ns, Ordering::Relaxed);
         }
 
-        #[allow(dead_code)]
         #[inline]
         pub(crate) fn increment_policy_validation_count(&self) {
             self.policy_validations.fetch_add(1, Ordering::Relaxed);
@@ -166,10 +1584,36 @@ mod imp {
             self.policy_update_count.store(0, Ordering::Relaxed);
             self.header_generation_time_ns.store(0, Ordering::Relaxed);
             self.violation_count.store(0, Ordering::Relaxed);
+            self.shadow_violation_count.store(0, Ordering::Relaxed);
+            self.oversized_report_count.store(0, Ordering::Relaxed);
+            self.timed_out_report_count.store(0, Ordering::Relaxed);
+            self.shed_report_count.store(0, Ordering::Relaxed);
+            self.malformed_report_count.store(0, Ordering::Relaxed);
+            self.blocked_report_count.store(0, Ordering::Relaxed);
+            self.handler_panic_count.store(0, Ordering::Relaxed);
+            self.fallback_header_count.store(0, Ordering::Relaxed);
+            self.rejected_response_count.store(0, Ordering::Relaxed);
+            self.header_generation_failure_count
+                .store(0, Ordering::Relaxed);
+            self.header_insertion_failure_count
+                .store(0, Ordering::Relaxed);
+            self.stale_policy_cache_count.store(0, Ordering::Relaxed);
+            self.dropped_report_count.store(0, Ordering::Relaxed);
+            *self.violations.lock() = ViolationBreakdown::default();
+            self.policy_timeline.lock().reset(Instant::now());
+            self.report_sizes.clear();
+            self.handler_durations.clear();
             self.cache_hit_count.store(0, Ordering::Relaxed);
+            self.policy_cache.reset_counters();
             self.policy_hash_time_ns.store(0, Ordering::Relaxed);
             self.policy_serialize_time_ns.store(0, Ordering::Relaxed);
             self.policy_validations.store(0, Ordering::Relaxed);
+            self.recent_requests.clear();
+            self.recent_violations.clear();
+            self.recent_enforced_violations.clear();
+            self.recent_report_only_violations.clear();
+            self.window_start_secs
+                .store(self.start_time.elapsed().as_secs(), Ordering::Relaxed);
         }
     }
 
@@ -183,6 +1627,12 @@ mod imp {
                 "  Requests per second: {:.2}",
                 self.requests_per_second()
             )?;
+            let rates = self.request_rates();
+            writeln!(
+                f,
+                "  Requests per minute (1m/5m/15m): {:.2} / {:.2} / {:.2}",
+                rates.one_minute, rates.five_minutes, rates.fifteen_minutes
+            )?;
             writeln!(f, "  Nonces generated: {}", self.nonce_generation_count())?;
             writeln!(f, "  Policy updates: {}", self.policy_update_count())?;
             writeln!(f, "  Policy validations: {}", self.policy_validations())?;
@@ -201,8 +1651,71 @@ mod imp {
                 "  Total policy serialize time: {} ns",
                 self.total_policy_serialize_time_ns()
             )?;
-            writeln!(f, "  Violations reported: {}", self.violation_count())?;
+            writeln!(
+                f,
+                "  Violations reported: {} ({} enforced, {} report-only)",
+                self.violation_count(),
+                self.enforced_violation_count(),
+                self.report_only_violation_count()
+            )?;
+            writeln!(
+                f,
+                "  Violations predicted: {}",
+                self.shadow_violation_count()
+            )?;
+            writeln!(
+                f,
+                "  Oversized reports rejected: {}",
+                self.oversized_report_count()
+            )?;
+            writeln!(f, "  Reports timed out: {}", self.timed_out_report_count())?;
+            writeln!(f, "  Reports shed: {}", self.shed_report_count())?;
+            writeln!(f, "  Malformed reports: {}", self.malformed_report_count())?;
+            writeln!(f, "  Reports blocked: {}", self.blocked_report_count())?;
+            writeln!(f, "  Handler panics: {}", self.handler_panic_count())?;
+            writeln!(f, "  Fallback headers: {}", self.fallback_header_count())?;
+            writeln!(
+                f,
+                "  Rejected responses: {}",
+                self.rejected_response_count()
+            )?;
+            writeln!(
+                f,
+                "  Header generation failures: {}",
+                self.header_generation_failure_count()
+            )?;
+            writeln!(
+                f,
+                "  Header insertion failures: {}",
+                self.header_insertion_failure_count()
+            )?;
+            writeln!(
+                f,
+                "  Stale cached policies: {}",
+                self.stale_policy_cache_count()
+            )?;
+            writeln!(
+                f,
+                "  Reports dropped: {} ({} queued)",
+                self.dropped_report_count(),
+                self.report_queue_depth()
+            )?;
+            writeln!(
+                f,
+                "  Policy regressions: {}",
+                self.policy_trends()
+                    .iter()
+                    .filter(|trend| trend.regression)
+                    .count()
+            )?;
             writeln!(f, "  Cache hits: {}", self.cache_hit_count())?;
+            for (name, cache) in self.cache_stats() {
+                writeln!(
+                    f,
+                    "  {name} cache: {} hits, {} misses, {} evictions, {}/{} entries",
+                    cache.hits, cache.misses, cache.evictions, cache.entries, cache.capacity
+                )?;
+            }
             Ok(())
         }
     }
@@ -210,7 +1723,12 @@ mod imp {
 
 #[cfg(not(feature = "stats"))]
 mod imp {
+    use super::{HistogramSnapshot, PolicyTrend, StatsSnapshot, WindowedRates};
+    use crate::monitoring::cache::{CacheMetrics, CacheStats};
+    use std::collections::BTreeMap;
     use std::fmt;
+    use std::num::NonZeroU64;
+    use std::sync::Arc;
 
     #[derive(Debug, Default)]
     pub struct CspStats;
@@ -246,11 +1764,173 @@ mod imp {
             0
         }
 
+        #[inline]
+        pub fn shadow_violation_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn oversized_report_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn timed_out_report_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn shed_report_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn malformed_report_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn blocked_report_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn dropped_report_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn report_queue_depth(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn handler_panic_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn fallback_header_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn rejected_response_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn header_generation_failure_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn header_insertion_failure_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn stale_policy_cache_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn violations_by_directive(&self) -> BTreeMap<String, usize> {
+            BTreeMap::new()
+        }
+
+        #[inline]
+        pub fn violations_by_disposition(&self) -> BTreeMap<String, usize> {
+            BTreeMap::new()
+        }
+
+        #[inline]
+        pub fn enforced_violation_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn report_only_violation_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn violations_by_disposition_and_directive(
+            &self,
+        ) -> BTreeMap<String, BTreeMap<String, usize>> {
+            BTreeMap::new()
+        }
+
+        #[inline]
+        pub fn top_blocked_origins(&self, _limit: usize) -> Vec<(String, usize)> {
+            Vec::new()
+        }
+
+        #[inline]
+        pub fn report_size_histogram(&self) -> HistogramSnapshot {
+            HistogramSnapshot::default()
+        }
+
+        #[inline]
+        pub fn handler_duration_histogram(&self) -> HistogramSnapshot {
+            HistogramSnapshot::default()
+        }
+
+        #[inline]
+        pub fn violations_by_browser(&self) -> BTreeMap<String, usize> {
+            BTreeMap::new()
+        }
+
+        #[inline]
+        pub fn top_client_fingerprints(&self, _limit: usize) -> Vec<(String, usize)> {
+            Vec::new()
+        }
+
+        #[inline]
+        pub fn policy_trends(&self) -> Vec<PolicyTrend> {
+            Vec::new()
+        }
+
+        #[inline]
+        pub fn request_rates(&self) -> WindowedRates {
+            WindowedRates::default()
+        }
+
+        #[inline]
+        pub fn violation_rates(&self) -> WindowedRates {
+            WindowedRates::default()
+        }
+
+        #[inline]
+        pub fn enforced_violation_rates(&self) -> WindowedRates {
+            WindowedRates::default()
+        }
+
+        #[inline]
+        pub fn report_only_violation_rates(&self) -> WindowedRates {
+            WindowedRates::default()
+        }
+
+        #[inline]
+        pub fn snapshot(&self) -> StatsSnapshot {
+            StatsSnapshot::default()
+        }
+
         #[inline]
         pub fn cache_hit_count(&self) -> usize {
             0
         }
 
+        #[inline]
+        pub fn cache_stats(&self) -> BTreeMap<String, CacheStats> {
+            BTreeMap::new()
+        }
+
+        #[inline]
+        pub(crate) fn policy_cache_metrics(&self) -> Arc<CacheMetrics> {
+            Arc::default()
+        }
+
         #[inline]
         pub fn total_policy_hash_time_ns(&self) -> usize {
             0
@@ -293,6 +1973,37 @@ mod imp {
         #[inline]
         pub(crate) fn increment_violation_count(&self) {}
 
+        #[inline]
+        pub(crate) fn record_policy_version(&self, _hash: NonZeroU64) {}
+
+        #[cfg(feature = "page-audit")]
+        #[inline]
+        pub(crate) fn add_shadow_violations(&self, _count: usize) {}
+
+        #[inline]
+        pub(crate) fn increment_fallback_header_count(&self) {}
+
+        #[inline]
+        pub(crate) fn increment_rejected_response_count(&self) {}
+
+        #[inline]
+        pub(crate) fn increment_header_generation_failure_count(&self) {}
+
+        #[inline]
+        pub(crate) fn increment_header_insertion_failure_count(&self) {}
+
+        #[inline]
+        pub(crate) fn increment_stale_policy_cache_count(&self) {}
+
+        #[inline]
+        pub(crate) fn increment_dropped_report_count(&self) {}
+
+        #[inline]
+        pub(crate) fn increment_report_queue_depth(&self) {}
+
+        #[inline]
+        pub(crate) fn decrement_report_queue_depth(&self) {}
+
         #[inline]
         pub(crate) fn increment_cache_hit_count(&self) {}
 
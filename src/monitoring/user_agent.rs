@@ -0,0 +1,132 @@
+//! Browser detection for violation reports.
+//!
+//! With the `user-agent` feature, [`CspReportingMiddleware`] reads the
+//! reporting request's `User-Agent` into [`CspViolationReport::browser`] so
+//! violations can be broken down by browser without external tooling. Only
+//! the major engines are recognised; anything else is left as `None`.
+//!
+//! [`CspReportingMiddleware`]: crate::middleware::CspReportingMiddleware
+//! [`CspViolationReport::browser`]: crate::monitoring::CspViolationReport::browser
+
+use serde::Serialize;
+use std::fmt;
+
+pub use imp::parse_user_agent;
+
+/// Browser family and version read from a `User-Agent` header.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct BrowserInfo {
+    /// `Chrome`, `Edge`, `Firefox`, `Safari`, `Opera`, `Samsung Internet` or
+    /// `Internet Explorer`
+    pub family: &'static str,
+    /// Version as sent by the browser, e.g. `16.4` or `120.0.6099.109`
+    pub version: Option<String>,
+}
+
+impl BrowserInfo {
+    /// Leading number of [`version`](Self::version), e.g. `16` for Safari 16.4.
+    pub fn major_version(&self) -> Option<u32> {
+        self.version.as_deref()?.split('.').next()?.parse().ok()
+    }
+}
+
+impl fmt::Display for BrowserInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{} {version}", self.family),
+            None => f.write_str(self.family),
+        }
+    }
+}
+
+#[cfg(feature = "user-agent")]
+mod imp {
+    use super::BrowserInfo;
+
+    /// Product tokens checked in order; browsers built on another engine also
+    /// send that engine's token, so the more specific ones come first.
+    const PRODUCTS: [(&str, &str); 14] = [
+        ("EdgiOS/", "Edge"),
+        ("EdgA/", "Edge"),
+        ("Edg/", "Edge"),
+        ("Edge/", "Edge"),
+        ("OPR/", "Opera"),
+        ("OPiOS/", "Opera"),
+        ("SamsungBrowser/", "Samsung Internet"),
+        ("FxiOS/", "Firefox"),
+        ("Firefox/", "Firefox"),
+        ("CriOS/", "Chrome"),
+        ("Chromium/", "Chrome"),
+        ("Chrome/", "Chrome"),
+        ("Version/", "Safari"),
+        ("Trident/", "Internet Explorer"),
+    ];
+
+    /// Reads the browser family and version from a `User-Agent` value.
+    ///
+    /// ```rust
+    /// use actix_web_csp::monitoring::user_agent::parse_user_agent;
+    ///
+    /// let browser = parse_user_agent(
+    ///     "Mozilla/5.0 (Macintosh; Intel Mac OS X 13_4) AppleWebKit/605.1.15 \
+    ///      (KHTML, like Gecko) Version/16.4 Safari/605.1.15",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(browser.family, "Safari");
+    /// assert_eq!(browser.major_version(), Some(16));
+    /// ```
+    pub fn parse_user_agent(user_agent: &str) -> Option<BrowserInfo> {
+        if let Some(version) = msie_version(user_agent) {
+            return Some(BrowserInfo {
+                family: "Internet Explorer",
+                version: Some(version.to_string()),
+            });
+        }
+
+        for (token, family) in PRODUCTS {
+            let Some(start) = user_agent.find(token) else {
+                continue;
+            };
+            let version = match family {
+                // Safari's `Version/` token only counts next to `Safari/`
+                "Safari" if !user_agent.contains("Safari/") => continue,
+                // IE 11 reports its version as `rv:11.0`
+                "Internet Explorer" => product_version(user_agent, "rv:"),
+                _ => Some(read_version(&user_agent[start + token.len()..]).to_string()),
+            };
+            return Some(BrowserInfo {
+                family,
+                version: version.filter(|version| !version.is_empty()),
+            });
+        }
+        None
+    }
+
+    fn msie_version(user_agent: &str) -> Option<&str> {
+        let start = user_agent.find("MSIE ")? + "MSIE ".len();
+        Some(read_version(&user_agent[start..])).filter(|version| !version.is_empty())
+    }
+
+    fn product_version(user_agent: &str, token: &str) -> Option<String> {
+        let start = user_agent.find(token)? + token.len();
+        Some(read_version(&user_agent[start..]).to_string())
+    }
+
+    fn read_version(rest: &str) -> &str {
+        let end = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        rest[..end].trim_end_matches('.')
+    }
+}
+
+#[cfg(not(feature = "user-agent"))]
+mod imp {
+    use super::BrowserInfo;
+
+    /// Always `None`; browser detection requires the `user-agent` feature.
+    #[inline]
+    pub fn parse_user_agent(_user_agent: &str) -> Option<BrowserInfo> {
+        None
+    }
+}
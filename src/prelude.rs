@@ -7,7 +7,7 @@ pub use crate::core::{
 #[allow(deprecated)]
 pub use crate::middleware::{
     configure_csp, csp_middleware, csp_middleware_with_nonce, csp_middleware_with_request_nonce,
-    CspExtensions, CspMiddleware,
+    CspDisabled, CspExtensions, CspHandle, CspMiddleware, CspResponseExt, HtmlNonceRewrite,
 };
 pub use crate::monitoring::{CspStats, CspViolationReport};
 pub use crate::presets::{preset_policy, CspPreset};
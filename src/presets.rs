@@ -1,7 +1,9 @@
-use crate::core::{CspPolicy, CspPolicyBuilder, Source};
+use crate::core::{CspPolicy, CspPolicyBuilder, Directive, Source};
 use crate::error::CspError;
+use std::borrow::Cow;
 use std::fmt;
 use std::str::FromStr;
+use url::Url;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[non_exhaustive]
@@ -160,3 +162,209 @@ impl TryFrom<&str> for CspPreset {
 pub fn preset_policy(preset: CspPreset) -> CspPolicy {
     preset.build()
 }
+
+/// A same-origin policy for apps that hold WebSocket or server-sent event
+/// connections to `ws_hosts`.
+///
+/// Hosts without a scheme are allowed as `https://` and `wss://`; hosts with an
+/// `http(s)://` scheme also get their `ws(s)://` twin, so the connection works in
+/// browsers that do not treat the two as equivalent.
+///
+/// ```rust
+/// use actix_web_csp::presets;
+///
+/// let policy = presets::realtime(["events.example.com"]);
+/// assert!(policy
+///     .to_string()
+///     .contains("connect-src 'self' https://events.example.com wss://events.example.com"));
+/// ```
+pub fn realtime<I, S>(ws_hosts: I) -> CspPolicy
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let hosts = ws_hosts.into_iter().map(|host| {
+        let host = host.as_ref().trim();
+        if host.contains("://") {
+            Source::Host(host.to_owned().into())
+        } else {
+            Source::Host(format!("https://{host}").into())
+        }
+    });
+
+    let mut policy = CspPolicyBuilder::new()
+        .default_src([Source::Self_])
+        .script_src([Source::Self_])
+        .style_src([Source::Self_])
+        .img_src([Source::Self_, Source::Scheme("data".into())])
+        .connect_src(std::iter::once(Source::Self_).chain(hosts))
+        .object_src([Source::None])
+        .base_uri([Source::Self_])
+        .form_action([Source::Self_])
+        .frame_ancestors([Source::None])
+        .build_unchecked();
+    policy.mirror_websocket_sources();
+    policy
+}
+
+/// A party allowed to embed the application in a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameAncestor(Source);
+
+impl FrameAncestor {
+    /// Pages of the application's own origin.
+    #[inline]
+    pub fn same_origin() -> Self {
+        Self(Source::Self_)
+    }
+
+    /// A single origin such as `https://partner.example.com`, with an optional port.
+    ///
+    /// Paths, queries and credentials are rejected: `frame-ancestors` matches the
+    /// embedding origin, so anything more specific would be silently ignored.
+    pub fn origin(origin: &str) -> Result<Self, CspError> {
+        let invalid = |reason: &str| {
+            CspError::ConfigError(format!("Invalid frame ancestor '{origin}': {reason}"))
+        };
+
+        let url = Url::parse(origin).map_err(|error| invalid(&error.to_string()))?;
+        if url.host_str().is_none() {
+            return Err(invalid("missing host"));
+        }
+        if url.path() != "/" || url.query().is_some() || !url.username().is_empty() {
+            return Err(invalid("only scheme, host and port are allowed"));
+        }
+
+        Ok(Self(Source::Host(Cow::Owned(
+            url.origin().ascii_serialization(),
+        ))))
+    }
+
+    /// Every page served over `scheme`, for example `https`.
+    #[inline]
+    pub fn scheme(scheme: impl Into<Cow<'static, str>>) -> Self {
+        Self(Source::Scheme(scheme.into()))
+    }
+}
+
+impl From<FrameAncestor> for Source {
+    #[inline]
+    fn from(ancestor: FrameAncestor) -> Self {
+        ancestor.0
+    }
+}
+
+/// The [`CspPreset::Strict`] policy, except that `ancestors` may embed the pages.
+///
+/// An empty allowlist yields `frame-ancestors 'none'`. Enable
+/// [`CspConfigBuilder::with_frame_options_sync`] so legacy browsers that only
+/// understand `X-Frame-Options` receive a matching value, or none at all when
+/// third-party origins are allowed.
+///
+/// ```rust
+/// use actix_web_csp::presets::{self, FrameAncestor};
+///
+/// let policy = presets::embeddable([
+///     FrameAncestor::same_origin(),
+///     FrameAncestor::origin("https://partner.example.com")?,
+/// ]);
+/// assert!(policy
+///     .to_string()
+///     .contains("frame-ancestors 'self' https://partner.example.com"));
+/// # Ok::<(), actix_web_csp::CspError>(())
+/// ```
+///
+/// [`CspConfigBuilder::with_frame_options_sync`]: crate::CspConfigBuilder::with_frame_options_sync
+pub fn embeddable<I>(ancestors: I) -> CspPolicy
+where
+    I: IntoIterator<Item = FrameAncestor>,
+{
+    let mut policy = CspPreset::Strict.build();
+    policy.add_directive(frame_ancestors(ancestors));
+    policy
+}
+
+/// A policy made of `frame-ancestors` alone, so only `allowed_ancestors` may
+/// embed the pages and nothing else is restricted.
+///
+/// Unlike [`embeddable`], this leaves resource loading alone: it protects
+/// against clickjacking on applications whose full policy is not ready, or sits
+/// next to the main policy through
+/// [`CspMiddleware::with_additional_policy`], where browsers enforce both. An
+/// empty allowlist yields `frame-ancestors 'none'`.
+///
+/// Framing violations usually mean someone is trying to embed the site rather
+/// than a misconfiguration; route them to their own sink with
+/// [`ReportRouter::with_clickjacking_alert`].
+///
+/// ```rust
+/// use actix_web_csp::presets::{self, FrameAncestor};
+/// use actix_web_csp::{csp_middleware, CspPolicyBuilder, Source};
+///
+/// let framing = presets::clickjacking_protection([FrameAncestor::same_origin()]);
+/// assert_eq!(framing.to_string(), "frame-ancestors 'self'");
+///
+/// let app = CspPolicyBuilder::new().default_src([Source::Self_]).build()?;
+/// let _middleware = csp_middleware(app).with_additional_policy(framing);
+/// # Ok::<(), actix_web_csp::CspError>(())
+/// ```
+///
+/// [`CspMiddleware::with_additional_policy`]: crate::CspMiddleware::with_additional_policy
+/// [`ReportRouter::with_clickjacking_alert`]: crate::monitoring::ReportRouter::with_clickjacking_alert
+pub fn clickjacking_protection<I>(allowed_ancestors: I) -> CspPolicy
+where
+    I: IntoIterator<Item = FrameAncestor>,
+{
+    let mut policy = CspPolicy::new();
+    policy.add_directive(frame_ancestors(allowed_ancestors));
+    policy
+}
+
+fn frame_ancestors<I>(ancestors: I) -> Directive
+where
+    I: IntoIterator<Item = FrameAncestor>,
+{
+    let mut directive = Directive::new(crate::constants::FRAME_ANCESTORS);
+    directive.add_sources(ancestors.into_iter().map(Source::from));
+    if directive.sources().is_empty() {
+        directive.add_source(Source::None);
+    }
+    directive
+}
+
+/// A same-origin policy for WebAssembly applications built with `wasm-bindgen`.
+///
+/// Allows `'wasm-unsafe-eval'` so modules can be compiled, and `blob:` in
+/// `script-src` and `worker-src` for the worker bootstrap scripts that threaded
+/// builds create at runtime. Each of these widens what an injected script could
+/// do; [`PolicyAuditor::relaxations`] explains the trade-off, so drop the `blob:`
+/// entries when the application does not spawn workers.
+///
+/// ```rust
+/// use actix_web_csp::presets;
+///
+/// let policy = presets::wasm_app();
+/// let rendered = policy.to_string();
+/// assert!(rendered.contains("script-src 'self' 'wasm-unsafe-eval' blob:"));
+/// assert!(rendered.contains("worker-src 'self' blob:"));
+/// ```
+///
+/// [`PolicyAuditor::relaxations`]: crate::security::PolicyAuditor::relaxations
+pub fn wasm_app() -> CspPolicy {
+    CspPolicyBuilder::new()
+        .default_src([Source::Self_])
+        .script_src([
+            Source::Self_,
+            Source::WasmUnsafeEval,
+            Source::Scheme("blob".into()),
+        ])
+        .worker_src([Source::Self_, Source::Scheme("blob".into())])
+        .style_src([Source::Self_])
+        .img_src([Source::Self_, Source::Scheme("data".into())])
+        .connect_src([Source::Self_])
+        .object_src([Source::None])
+        .base_uri([Source::Self_])
+        .form_action([Source::Self_])
+        .frame_ancestors([Source::None])
+        .build_unchecked()
+}
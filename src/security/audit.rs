@@ -0,0 +1,1017 @@
+//! Static analysis of policies beyond what [`CspPolicy::validate`] checks.
+
+use crate::constants::{
+    CHILD_SRC, CONNECT_SRC, DEFAULT_SRC, FENCED_FRAME_SRC, FONT_SRC, FORM_ACTION, FRAME_SRC,
+    IMG_SRC, MANIFEST_SRC, MEDIA_SRC, NAVIGATE_TO, PREFETCH_SRC, REPORT_TO, REQUIRE_SRI_FOR,
+    REQUIRE_TRUSTED_TYPES_FOR, SCRIPT_SRC, SCRIPT_SRC_ATTR, SCRIPT_SRC_ELEM, STRICT_DYNAMIC_SOURCE,
+    STYLE_SRC, STYLE_SRC_ATTR, STYLE_SRC_ELEM, TRUSTED_TYPES, UNSAFE_EVAL_SOURCE,
+    UNSAFE_HASHES_SOURCE, WASM_UNSAFE_EVAL_SOURCE, WORKER_SRC,
+};
+use crate::core::policy::CspPolicy;
+use crate::core::source::Source;
+use crate::core::summary::PolicySummary;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Browser {
+    Chrome,
+    Edge,
+    Firefox,
+    Safari,
+}
+
+impl Browser {
+    #[inline]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Browser::Chrome => "Chrome",
+            Browser::Edge => "Edge",
+            Browser::Firefox => "Firefox",
+            Browser::Safari => "Safari",
+        }
+    }
+
+    #[inline]
+    const fn index(&self) -> usize {
+        match self {
+            Browser::Chrome => 0,
+            Browser::Edge => 1,
+            Browser::Firefox => 2,
+            Browser::Safari => 3,
+        }
+    }
+}
+
+impl fmt::Display for Browser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The oldest major version of a browser a policy has to work in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BrowserTarget {
+    pub browser: Browser,
+    pub version: u32,
+}
+
+impl BrowserTarget {
+    #[inline]
+    pub const fn new(browser: Browser, version: u32) -> Self {
+        Self { browser, version }
+    }
+}
+
+/// A directive or keyword that a targeted browser does not understand.
+///
+/// Browsers ignore such entries, so the policy silently degrades there: an unknown
+/// directive falls back to its parent (or to nothing), an unknown keyword is dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatNote {
+    pub target: BrowserTarget,
+    pub directive: String,
+    pub feature: &'static str,
+    /// First major version supporting the feature, `None` if no release does
+    pub supported_since: Option<u32>,
+}
+
+impl fmt::Display for CompatNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} in {} is ignored by {} {}",
+            self.feature, self.directive, self.target.browser, self.target.version
+        )?;
+        match self.supported_since {
+            Some(version) => write!(f, " (supported since {version})"),
+            None => f.write_str(" (not supported)"),
+        }
+    }
+}
+
+/// A source that loosens the policy, with what an attacker gains from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relaxation {
+    pub directive: String,
+    pub source: &'static str,
+    pub trade_off: &'static str,
+}
+
+impl fmt::Display for Relaxation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} in {}: {}",
+            self.source, self.directive, self.trade_off
+        )
+    }
+}
+
+/// How a directive interacts with `default-src` in a way that is easy to miss,
+/// see [`PolicyAuditor::directive_conflicts`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ConflictKind {
+    /// The directive is not set and inherits `default-src 'none'`
+    InheritsNone,
+    /// An `-elem` or `-attr` directive is set without the directive it refines
+    MissingBaseDirective,
+    /// The directive replaces a `default-src` that allows `'self'` and does not
+    /// allow it itself
+    DropsSelf,
+}
+
+/// A surprising interaction between `default-src` and a more specific
+/// directive, with what breaks and how to fix it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectiveConflict {
+    pub kind: ConflictKind,
+    /// The directive the finding is about, e.g. the missing `connect-src`
+    pub directive: String,
+    /// The directive it falls back to or refines
+    pub related: String,
+    pub advice: String,
+}
+
+impl fmt::Display for DirectiveConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.directive, self.advice)
+    }
+}
+
+/// A literal `'nonce-...'` source in a configured policy, see
+/// [`PolicyAuditor::static_nonces`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticNonce {
+    pub directive: String,
+    pub nonce: String,
+}
+
+impl fmt::Display for StaticNonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'nonce-{}' in {} is sent with every response, so an injected script can \
+             copy it; use Source::NoncePlaceholder with a nonce generator instead",
+            self.nonce, self.directive
+        )
+    }
+}
+
+/// A payment-page check failed by a policy, see [`PolicyAuditor::audit_payment_page`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PaymentRule {
+    /// Neither `script-src` nor `default-src` restricts scripts
+    ScriptsUnrestricted,
+    /// `'unsafe-inline'` without a nonce or hash, or `'unsafe-eval'`, lets
+    /// scripts run that were never authorized
+    UnsafeScriptKeyword,
+    /// A `*` host, a wildcard over a top-level domain or public suffix, or a
+    /// bare scheme allows sources nobody reviewed
+    WildcardSource,
+    /// Neither `report-uri` nor `report-to` is set, so changes go unnoticed
+    ReportingMissing,
+    /// The policy is only reported, not enforced
+    ReportOnly,
+}
+
+impl PaymentRule {
+    /// The PCI DSS 4.0 requirement the rule supports.
+    #[inline]
+    pub const fn requirement(self) -> &'static str {
+        match self {
+            Self::ScriptsUnrestricted | Self::UnsafeScriptKeyword | Self::WildcardSource => "6.4.3",
+            Self::ReportingMissing | Self::ReportOnly => "11.6.1",
+        }
+    }
+
+    #[inline]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::ScriptsUnrestricted => {
+                "scripts are not restricted; every script on a payment page must be authorized"
+            }
+            Self::UnsafeScriptKeyword => {
+                "scripts can run without being authorized by host, nonce or hash"
+            }
+            Self::WildcardSource => {
+                "a wildcard or bare scheme allows sources that were never reviewed"
+            }
+            Self::ReportingMissing => {
+                "violations are not reported, so unauthorized changes go unnoticed"
+            }
+            Self::ReportOnly => "the policy is report-only and does not block anything",
+        }
+    }
+}
+
+/// One failed payment-page check, with the directive and source it concerns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentFinding {
+    pub rule: PaymentRule,
+    pub directive: Option<String>,
+    pub source: Option<String>,
+}
+
+impl fmt::Display for PaymentFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[PCI DSS {}] ", self.rule.requirement())?;
+        match (&self.source, &self.directive) {
+            (Some(source), Some(directive)) => write!(f, "{source} in {directive}: ")?,
+            (None, Some(directive)) => write!(f, "{directive}: ")?,
+            _ => {}
+        }
+        f.write_str(self.rule.description())
+    }
+}
+
+/// The payment-page checks of one route, as returned by
+/// [`PolicyAuditor::audit_payment_page`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentPageAudit {
+    pub path: String,
+    pub findings: Vec<PaymentFinding>,
+}
+
+impl PaymentPageAudit {
+    /// Returns `true` if the policy passed every check.
+    #[inline]
+    pub fn is_compliant(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Returns `true` if `rule` failed.
+    #[inline]
+    pub fn has(&self, rule: PaymentRule) -> bool {
+        self.findings.iter().any(|finding| finding.rule == rule)
+    }
+}
+
+impl fmt::Display for PaymentPageAudit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_compliant() {
+            return write!(f, "payment page {}: all checks passed", self.path);
+        }
+        write!(
+            f,
+            "payment page {}: {} finding{}",
+            self.path,
+            self.findings.len(),
+            if self.findings.len() == 1 { "" } else { "s" }
+        )?;
+        for finding in &self.findings {
+            write!(f, "\n    {finding}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A [`PolicySummary`] with the auditor's findings, as returned by
+/// [`PolicyAuditor::audit`] and [`PolicyAuditor::audit_route`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyAudit {
+    pub policy: PolicySummary,
+    pub relaxations: Vec<Relaxation>,
+    pub compat_notes: Vec<CompatNote>,
+    pub conflicts: Vec<DirectiveConflict>,
+    pub static_nonces: Vec<StaticNonce>,
+    /// Payment-page checks, for routes registered with
+    /// [`PolicyAuditor::with_payment_path`]
+    pub payment: Option<PaymentPageAudit>,
+}
+
+impl PolicyAudit {
+    /// Returns `true` if anything in the audit deserves a second look.
+    pub fn has_warnings(&self) -> bool {
+        self.policy.has_unsafe_keywords()
+            || !self.policy.reporting
+            || self.policy.header_bytes.is_none()
+            || !self.relaxations.is_empty()
+            || !self.compat_notes.is_empty()
+            || !self.conflicts.is_empty()
+            || !self.static_nonces.is_empty()
+            || self
+                .payment
+                .as_ref()
+                .is_some_and(|payment| !payment.is_compliant())
+    }
+}
+
+impl fmt::Display for PolicyAudit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.policy)?;
+        for relaxation in &self.relaxations {
+            write!(f, "\n  relaxation: {relaxation}")?;
+        }
+        for note in &self.compat_notes {
+            write!(f, "\n  compatibility: {note}")?;
+        }
+        for conflict in &self.conflicts {
+            write!(f, "\n  conflict: {conflict}")?;
+        }
+        for nonce in &self.static_nonces {
+            write!(f, "\n  static nonce: {nonce}")?;
+        }
+        if let Some(payment) = &self.payment {
+            write!(f, "\n  {payment}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Feature support indexed by [`Browser::index`]. Versions are major releases; a
+/// feature that shipped in a point release is listed under the next major.
+type Support = [Option<u32>; 4];
+
+const DIRECTIVE_SUPPORT: &[(&str, Support)] = &[
+    (SCRIPT_SRC_ELEM, [Some(75), Some(79), Some(108), Some(16)]),
+    (SCRIPT_SRC_ATTR, [Some(75), Some(79), Some(108), Some(16)]),
+    (STYLE_SRC_ELEM, [Some(75), Some(79), Some(108), Some(16)]),
+    (STYLE_SRC_ATTR, [Some(75), Some(79), Some(108), Some(16)]),
+    (WORKER_SRC, [Some(59), Some(79), Some(58), Some(16)]),
+    (REPORT_TO, [Some(70), Some(79), None, Some(17)]),
+    (FENCED_FRAME_SRC, [Some(115), Some(115), None, None]),
+    (
+        REQUIRE_TRUSTED_TYPES_FOR,
+        [Some(83), Some(83), None, Some(26)],
+    ),
+    (TRUSTED_TYPES, [Some(83), Some(83), None, Some(26)]),
+    (PREFETCH_SRC, [None, None, None, None]),
+    (NAVIGATE_TO, [None, None, None, None]),
+    (REQUIRE_SRI_FOR, [None, None, None, None]),
+];
+
+const KEYWORD_SUPPORT: &[(&str, Support)] = &[
+    (
+        WASM_UNSAFE_EVAL_SOURCE,
+        [Some(97), Some(97), Some(102), Some(16)],
+    ),
+    (
+        STRICT_DYNAMIC_SOURCE,
+        [Some(52), Some(79), Some(52), Some(16)],
+    ),
+    (
+        UNSAFE_HASHES_SOURCE,
+        [Some(69), Some(79), Some(115), Some(16)],
+    ),
+];
+
+const SCRIPT_DIRECTIVES: &[&str] = &[DEFAULT_SRC, SCRIPT_SRC, SCRIPT_SRC_ELEM];
+
+/// Relaxations checked by [`PolicyAuditor::relaxations`]: the directives they
+/// matter in, the source as written, and the trade-off.
+const RELAXATIONS: &[(&[&str], &str, &str)] = &[
+    (
+        SCRIPT_DIRECTIVES,
+        UNSAFE_EVAL_SOURCE,
+        "injected strings can run as JavaScript through eval() and new Function(); \
+         use 'wasm-unsafe-eval' if only WebAssembly needs compiling",
+    ),
+    (
+        SCRIPT_DIRECTIVES,
+        WASM_UNSAFE_EVAL_SOURCE,
+        "any script on the page may compile and run WebAssembly, so an injected \
+         script can load its own modules; JavaScript eval stays blocked",
+    ),
+    (
+        SCRIPT_DIRECTIVES,
+        "blob:",
+        "scripts loaded from blob: URLs bypass the host allowlist, and any script \
+         that can build a Blob can create them",
+    ),
+    (
+        &[DEFAULT_SRC, SCRIPT_SRC, SCRIPT_SRC_ATTR],
+        UNSAFE_HASHES_SOURCE,
+        "an injected element can carry any of the hashed event handlers, so they \
+         run wherever an attacker can add markup; move them to addEventListener \
+         in an external or nonced script and drop the hashes",
+    ),
+    (
+        &[WORKER_SRC, CHILD_SRC],
+        "blob:",
+        "any script on the page can start a worker from a Blob it built; workers \
+         cannot touch the DOM but can fetch anything connect-src allows",
+    ),
+];
+
+/// Directives commonly needed under `default-src 'none'`, with what their
+/// absence blocks.
+const NONE_INHERITED: &[(&str, &str)] = &[
+    (SCRIPT_SRC, "all scripts, including same-origin ones,"),
+    (STYLE_SRC, "all stylesheets and inline styles"),
+    (IMG_SRC, "all images, including favicons,"),
+    (
+        CONNECT_SRC,
+        "fetch(), XMLHttpRequest, WebSocket and EventSource connections",
+    ),
+    (FONT_SRC, "all web fonts"),
+];
+
+/// Directives refined by `-elem`/`-attr` variants.
+const REFINED_DIRECTIVES: &[(&str, &[&str])] = &[
+    (SCRIPT_SRC, &[SCRIPT_SRC_ELEM, SCRIPT_SRC_ATTR]),
+    (STYLE_SRC, &[STYLE_SRC_ELEM, STYLE_SRC_ATTR]),
+];
+
+/// Fetch directives that replace `default-src` for their resource type.
+const SELF_INHERITING: &[&str] = &[
+    SCRIPT_SRC,
+    SCRIPT_SRC_ELEM,
+    STYLE_SRC,
+    STYLE_SRC_ELEM,
+    IMG_SRC,
+    CONNECT_SRC,
+    FONT_SRC,
+    MEDIA_SRC,
+    FRAME_SRC,
+    CHILD_SRC,
+    WORKER_SRC,
+    MANIFEST_SRC,
+];
+
+/// Directives checked for wildcards on payment pages.
+const PAYMENT_DIRECTIVES: &[&str] = &[
+    DEFAULT_SRC,
+    SCRIPT_SRC,
+    SCRIPT_SRC_ELEM,
+    FRAME_SRC,
+    CONNECT_SRC,
+    FORM_ACTION,
+];
+
+const BASELINE_TARGETS: [BrowserTarget; 4] = [
+    BrowserTarget::new(Browser::Chrome, 90),
+    BrowserTarget::new(Browser::Edge, 90),
+    BrowserTarget::new(Browser::Firefox, 91),
+    BrowserTarget::new(Browser::Safari, 14),
+];
+
+/// Checks policies against an embedded browser feature table.
+///
+/// ```rust
+/// use actix_web_csp::security::{Browser, PolicyAuditor};
+/// use actix_web_csp::{CspPolicyBuilder, Source};
+///
+/// let policy = CspPolicyBuilder::new()
+///     .script_src([Source::Self_, Source::WasmUnsafeEval])
+///     .build_unchecked();
+///
+/// let notes = PolicyAuditor::new()
+///     .with_target(Browser::Firefox, 115)
+///     .browser_compat(&policy);
+///
+/// assert!(notes.iter().all(|note| note.target.browser != Browser::Firefox));
+/// assert!(notes.iter().any(|note| note.target.browser == Browser::Safari));
+/// ```
+#[derive(Debug, Clone)]
+pub struct PolicyAuditor {
+    targets: Vec<BrowserTarget>,
+    payment_paths: Vec<String>,
+}
+
+impl Default for PolicyAuditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PolicyAuditor {
+    /// Creates an auditor targeting Chrome/Edge 90, Firefox 91 ESR and Safari 14.
+    pub fn new() -> Self {
+        Self {
+            targets: BASELINE_TARGETS.to_vec(),
+            payment_paths: Vec::new(),
+        }
+    }
+
+    /// Marks `path` and everything below it as a payment page, checked by
+    /// [`audit_route`](Self::audit_route).
+    pub fn with_payment_path(mut self, path: impl Into<String>) -> Self {
+        let path = path.into();
+        let trimmed = path.trim_end_matches('/');
+        self.payment_paths.push(if trimmed.is_empty() {
+            "/".to_string()
+        } else {
+            trimmed.to_string()
+        });
+        self
+    }
+
+    /// Returns `true` if `path` is at or below a path registered with
+    /// [`with_payment_path`](Self::with_payment_path).
+    pub fn is_payment_path(&self, path: &str) -> bool {
+        self.payment_paths.iter().any(|payment| {
+            payment == "/"
+                || path.strip_prefix(payment.as_str()).is_some_and(|rest| {
+                    rest.is_empty() || rest.starts_with('/') || rest.starts_with('?')
+                })
+        })
+    }
+
+    /// Sets the oldest supported version of `browser`, replacing any previous target.
+    pub fn with_target(mut self, browser: Browser, version: u32) -> Self {
+        self.targets.retain(|target| target.browser != browser);
+        self.targets.push(BrowserTarget::new(browser, version));
+        self
+    }
+
+    /// Stops checking `browser`.
+    pub fn without_browser(mut self, browser: Browser) -> Self {
+        self.targets.retain(|target| target.browser != browser);
+        self
+    }
+
+    #[inline]
+    pub fn targets(&self) -> &[BrowserTarget] {
+        &self.targets
+    }
+
+    /// Lists every directive and keyword of `policy` that a targeted browser ignores.
+    pub fn browser_compat(&self, policy: &CspPolicy) -> Vec<CompatNote> {
+        let mut notes = Vec::new();
+
+        for directive in policy.directives() {
+            let name = directive.name();
+
+            if let Some((feature, support)) = DIRECTIVE_SUPPORT
+                .iter()
+                .find(|(feature, _)| feature.eq_ignore_ascii_case(name))
+            {
+                self.check(name, feature, support, &mut notes);
+            }
+
+            for keyword in directive.keywords().filter_map(Source::as_static_str) {
+                if let Some((feature, support)) = KEYWORD_SUPPORT
+                    .iter()
+                    .find(|(feature, _)| *feature == keyword)
+                {
+                    self.check(name, feature, support, &mut notes);
+                }
+            }
+        }
+
+        notes
+    }
+
+    /// Lists the sources of `policy` that trade security for compatibility, such as
+    /// those [`presets::wasm_app`] needs, with an explanation of each.
+    ///
+    /// ```rust
+    /// use actix_web_csp::presets;
+    /// use actix_web_csp::security::PolicyAuditor;
+    ///
+    /// for relaxation in PolicyAuditor::new().relaxations(&presets::wasm_app()) {
+    ///     println!("{relaxation}");
+    /// }
+    /// ```
+    ///
+    /// [`presets::wasm_app`]: crate::presets::wasm_app
+    pub fn relaxations(&self, policy: &CspPolicy) -> Vec<Relaxation> {
+        let mut relaxations = Vec::new();
+
+        for directive in policy.directives() {
+            let name = directive.name();
+            let sources = directive.sources();
+
+            for &(directives, source, trade_off) in RELAXATIONS {
+                let applies = directives
+                    .iter()
+                    .any(|directive| directive.eq_ignore_ascii_case(name));
+                if applies
+                    && sources
+                        .iter()
+                        .any(|candidate| matches_source(candidate, source))
+                {
+                    relaxations.push(Relaxation {
+                        directive: name.to_string(),
+                        source,
+                        trade_off,
+                    });
+                }
+            }
+        }
+
+        relaxations
+    }
+
+    /// Finds directives whose relation to `default-src` likely differs from
+    /// what was intended:
+    ///
+    /// - under `default-src 'none'`, a missing `script-src`, `style-src`,
+    ///   `img-src`, `connect-src` or `font-src` blocks every load of that kind;
+    /// - `script-src-elem` or `-attr` (and the `style-src` pair) set without
+    ///   `script-src` leaves the other one, and browsers that predate them,
+    ///   falling back to `default-src`;
+    /// - a fetch directive replaces `default-src` rather than extending it, so
+    ///   one without `'self'` blocks same-origin loads `default-src 'self'`
+    ///   allowed. Directives using nonces, hashes or `'strict-dynamic'` are
+    ///   assumed to drop `'self'` on purpose.
+    ///
+    /// ```rust
+    /// use actix_web_csp::security::{ConflictKind, PolicyAuditor};
+    /// use actix_web_csp::{CspPolicyBuilder, Source};
+    ///
+    /// let policy = CspPolicyBuilder::new()
+    ///     .default_src([Source::None])
+    ///     .script_src([Source::Self_])
+    ///     .style_src([Source::Self_])
+    ///     .img_src([Source::Self_])
+    ///     .font_src([Source::Self_])
+    ///     .build_unchecked();
+    ///
+    /// let conflicts = PolicyAuditor::new().directive_conflicts(&policy);
+    /// assert_eq!(conflicts.len(), 1);
+    /// assert_eq!(conflicts[0].kind, ConflictKind::InheritsNone);
+    /// assert_eq!(conflicts[0].directive, "connect-src");
+    /// ```
+    pub fn directive_conflicts(&self, policy: &CspPolicy) -> Vec<DirectiveConflict> {
+        let mut conflicts = Vec::new();
+        let default = policy.get_directive(DEFAULT_SRC);
+        let is_set = |name: &str| policy.get_directive(name).is_some();
+
+        if default.is_some_and(|directive| directive.sources().iter().any(Source::is_none)) {
+            for &(name, blocked) in NONE_INHERITED {
+                let refined = [SCRIPT_SRC, STYLE_SRC].contains(&name)
+                    && (is_set(&format!("{name}-elem")) || is_set(&format!("{name}-attr")));
+                if !is_set(name) && !refined {
+                    conflicts.push(DirectiveConflict {
+                        kind: ConflictKind::InheritsNone,
+                        directive: name.to_string(),
+                        related: DEFAULT_SRC.to_string(),
+                        advice: format!(
+                            "not set, so it falls back to default-src 'none' and {blocked} \
+                             are blocked; set {name} if the page needs them"
+                        ),
+                    });
+                }
+            }
+        }
+
+        for &(base, variants) in REFINED_DIRECTIVES {
+            if is_set(base) {
+                continue;
+            }
+            let set = variants
+                .iter()
+                .copied()
+                .filter(|variant| is_set(variant))
+                .collect::<Vec<_>>();
+            let Some(&variant) = set.first() else {
+                continue;
+            };
+            let fallback = if default.is_some() {
+                "fall back to default-src"
+            } else {
+                "are unrestricted"
+            };
+            let unset = variants
+                .iter()
+                .copied()
+                .filter(|candidate| !set.contains(candidate))
+                .collect::<Vec<_>>();
+            let advice = if unset.is_empty() {
+                format!(
+                    "not set while {} are, so browsers without support for them {fallback}; \
+                     set {base} as well",
+                    set.join(" and ")
+                )
+            } else {
+                format!(
+                    "not set while {variant} is, so {} and browsers without {variant} \
+                     support {fallback}; set {base} as well",
+                    unset.join(" and ")
+                )
+            };
+            conflicts.push(DirectiveConflict {
+                kind: ConflictKind::MissingBaseDirective,
+                directive: base.to_string(),
+                related: variant.to_string(),
+                advice,
+            });
+        }
+
+        if default.is_some_and(|directive| directive.sources().contains(&Source::Self_)) {
+            for directive in policy.directives() {
+                let name = directive.name();
+                let sources = directive.sources();
+                let shadows = SELF_INHERITING
+                    .iter()
+                    .any(|candidate| candidate.eq_ignore_ascii_case(name));
+                let deliberate = sources.iter().any(|source| {
+                    source.is_none()
+                        || source.contains_nonce()
+                        || source.contains_hash()
+                        || matches!(
+                            source,
+                            Source::Self_ | Source::StrictDynamic | Source::NoncePlaceholder
+                        )
+                });
+                if shadows && !deliberate {
+                    conflicts.push(DirectiveConflict {
+                        kind: ConflictKind::DropsSelf,
+                        directive: name.to_string(),
+                        related: DEFAULT_SRC.to_string(),
+                        advice: format!(
+                            "replaces default-src rather than extending it, so same-origin \
+                             loads allowed by default-src 'self' are blocked; add 'self' to \
+                             {name} if they are expected"
+                        ),
+                    });
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    /// Finds literal `Source::Nonce` values. A nonce written into the configured
+    /// policy is the same for every response, so it protects nothing; the
+    /// middleware's per-request nonce, through a nonce generator and
+    /// [`Source::NoncePlaceholder`], should be used instead. Audit the policy as
+    /// configured, not a copy the request nonce was already added to.
+    ///
+    /// ```rust
+    /// use actix_web_csp::security::PolicyAuditor;
+    /// use actix_web_csp::{CspPolicyBuilder, Source};
+    ///
+    /// let fixed = CspPolicyBuilder::new()
+    ///     .script_src([Source::Self_, Source::Nonce("abc123".into())])
+    ///     .build_unchecked();
+    /// assert_eq!(PolicyAuditor::new().static_nonces(&fixed)[0].nonce, "abc123");
+    ///
+    /// let per_request = CspPolicyBuilder::new()
+    ///     .script_src([Source::Self_, Source::NoncePlaceholder])
+    ///     .build_unchecked();
+    /// assert!(PolicyAuditor::new().static_nonces(&per_request).is_empty());
+    /// ```
+    pub fn static_nonces(&self, policy: &CspPolicy) -> Vec<StaticNonce> {
+        policy
+            .directives()
+            .flat_map(|directive| {
+                directive
+                    .sources()
+                    .iter()
+                    .chain(directive.fallback_sources().into_iter().flatten())
+                    .filter_map(Source::nonce)
+                    .map(|nonce| StaticNonce {
+                        directive: directive.name().to_string(),
+                        nonce: nonce.to_string(),
+                    })
+            })
+            .collect()
+    }
+
+    /// Summarizes `policy` with [`CspPolicy::summary`] and adds the findings of
+    /// [`relaxations`](Self::relaxations),
+    /// [`browser_compat`](Self::browser_compat),
+    /// [`directive_conflicts`](Self::directive_conflicts) and
+    /// [`static_nonces`](Self::static_nonces).
+    ///
+    /// ```rust
+    /// use actix_web_csp::security::PolicyAuditor;
+    /// use actix_web_csp::{CspPolicyBuilder, Source};
+    ///
+    /// let policy = CspPolicyBuilder::new()
+    ///     .default_src([Source::Self_])
+    ///     .style_src([Source::Self_, Source::UnsafeInline])
+    ///     .build_unchecked();
+    ///
+    /// let audit = PolicyAuditor::new().audit(&policy);
+    /// assert_eq!(audit.policy.directive_count, 2);
+    /// assert_eq!(audit.policy.unsafe_keywords, ["'unsafe-inline'"]);
+    /// assert!(audit.has_warnings());
+    /// ```
+    pub fn audit(&self, policy: &CspPolicy) -> PolicyAudit {
+        PolicyAudit {
+            policy: policy.summary(),
+            relaxations: self.relaxations(policy),
+            compat_notes: self.browser_compat(policy),
+            conflicts: self.directive_conflicts(policy),
+            static_nonces: self.static_nonces(policy),
+            payment: None,
+        }
+    }
+
+    /// Like [`audit`](Self::audit) for the policy served on `path`,
+    /// adding the [`audit_payment_page`](Self::audit_payment_page) section
+    /// when the path is a payment page.
+    ///
+    /// ```rust
+    /// use actix_web_csp::integrations::Service;
+    /// use actix_web_csp::security::PolicyAuditor;
+    /// use actix_web_csp::{CspPolicyBuilder, Source};
+    ///
+    /// let checkout = CspPolicyBuilder::new()
+    ///     .default_src([Source::Self_])
+    ///     .script_src([Source::Self_])
+    ///     .apply_service(Service::Stripe)
+    ///     .report_to("csp-endpoint")
+    ///     .build()?;
+    ///
+    /// let auditor = PolicyAuditor::new().with_payment_path("/checkout");
+    /// let audit = auditor.audit_route("/checkout/pay", &checkout);
+    /// assert!(audit.payment.unwrap().is_compliant());
+    /// assert!(auditor.audit_route("/blog", &checkout).payment.is_none());
+    /// # Ok::<(), actix_web_csp::CspError>(())
+    /// ```
+    pub fn audit_route(&self, path: &str, policy: &CspPolicy) -> PolicyAudit {
+        let mut audit = self.audit(policy);
+        if self.is_payment_path(path) {
+            audit.payment = Some(self.audit_payment_page(path, policy));
+        }
+        audit
+    }
+
+    /// Checks the policy of a payment page against rules drawn from PCI DSS 4.0
+    /// requirements 6.4.3 (every script on the page is authorized) and 11.6.1
+    /// (unauthorized changes are detected).
+    ///
+    /// Scripts have to be restricted by `script-src` or `default-src` without
+    /// `'unsafe-eval'` or an unguarded `'unsafe-inline'`; script, frame,
+    /// connect and form targets must not use `*`, wildcards over a top-level
+    /// domain or public suffix, or bare schemes; and the policy must be enforced with reporting
+    /// configured. Passing is evidence for an assessment, not compliance by itself.
+    pub fn audit_payment_page(&self, path: &str, policy: &CspPolicy) -> PaymentPageAudit {
+        let mut findings = Vec::new();
+        let finding = |rule, directive: Option<&str>, source: Option<&Source>| PaymentFinding {
+            rule,
+            directive: directive.map(str::to_string),
+            source: source.map(Source::to_string),
+        };
+
+        match policy
+            .get_directive(SCRIPT_SRC)
+            .or_else(|| policy.get_directive(DEFAULT_SRC))
+        {
+            None => findings.push(finding(PaymentRule::ScriptsUnrestricted, None, None)),
+            Some(scripts) => {
+                let guarded = scripts
+                    .sources()
+                    .iter()
+                    .any(|source| source.contains_nonce() || source.contains_hash());
+                for source in scripts.sources() {
+                    if source.is_unsafe_eval() || (source.is_unsafe_inline() && !guarded) {
+                        findings.push(finding(
+                            PaymentRule::UnsafeScriptKeyword,
+                            Some(scripts.name()),
+                            Some(source),
+                        ));
+                    }
+                }
+            }
+        }
+
+        for directive in policy.directives().filter(|directive| {
+            PAYMENT_DIRECTIVES
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(directive.name()))
+        }) {
+            let scripts = !directive.name().eq_ignore_ascii_case(FRAME_SRC)
+                && !directive.name().eq_ignore_ascii_case(CONNECT_SRC)
+                && !directive.name().eq_ignore_ascii_case(FORM_ACTION);
+            for source in directive.sources() {
+                if is_wildcard(source, scripts) {
+                    findings.push(finding(
+                        PaymentRule::WildcardSource,
+                        Some(directive.name()),
+                        Some(source),
+                    ));
+                }
+            }
+        }
+
+        if policy.report_uri().is_none() && policy.report_to().is_none() {
+            findings.push(finding(PaymentRule::ReportingMissing, None, None));
+        }
+        if policy.is_report_only() {
+            findings.push(finding(PaymentRule::ReportOnly, None, None));
+        }
+
+        PaymentPageAudit {
+            path: path.to_string(),
+            findings,
+        }
+    }
+
+    fn check(
+        &self,
+        directive: &str,
+        feature: &'static str,
+        support: &Support,
+        notes: &mut Vec<CompatNote>,
+    ) {
+        for target in &self.targets {
+            let supported_since = support[target.browser.index()];
+            if supported_since.is_none_or(|version| target.version < version) {
+                notes.push(CompatNote {
+                    target: *target,
+                    directive: directive.to_string(),
+                    feature,
+                    supported_since,
+                });
+            }
+        }
+    }
+}
+
+fn matches_source(source: &Source, expected: &str) -> bool {
+    match source {
+        Source::Scheme(scheme) => expected
+            .strip_suffix(':')
+            .is_some_and(|expected| scheme.eq_ignore_ascii_case(expected)),
+        _ => source.as_static_str() == Some(expected),
+    }
+}
+
+/// Registrable-domain suffixes with more than one label, where anyone can
+/// register a domain below, e.g. `*.co.uk` spans every UK company. A short
+/// table of the common ones, not the full Public Suffix List.
+const MULTI_LABEL_PUBLIC_SUFFIXES: &[&str] = &[
+    "ac.jp",
+    "ac.uk",
+    "appspot.com",
+    "azurewebsites.net",
+    "blogspot.com",
+    "cloudfront.net",
+    "co.id",
+    "co.il",
+    "co.in",
+    "co.jp",
+    "co.kr",
+    "co.nz",
+    "co.th",
+    "co.uk",
+    "co.za",
+    "com.ar",
+    "com.au",
+    "com.br",
+    "com.cn",
+    "com.hk",
+    "com.mx",
+    "com.my",
+    "com.ph",
+    "com.pl",
+    "com.sg",
+    "com.tr",
+    "com.tw",
+    "com.ua",
+    "com.vn",
+    "edu.au",
+    "firebaseapp.com",
+    "github.io",
+    "gov.uk",
+    "herokuapp.com",
+    "me.uk",
+    "ne.jp",
+    "net.au",
+    "net.br",
+    "net.cn",
+    "net.in",
+    "net.nz",
+    "netlify.app",
+    "or.jp",
+    "org.au",
+    "org.br",
+    "org.cn",
+    "org.in",
+    "org.nz",
+    "org.uk",
+    "org.za",
+    "pages.dev",
+    "vercel.app",
+    "web.app",
+    "workers.dev",
+];
+
+/// A source matching origins nobody listed: `*`, a wildcard directly below a
+/// top-level domain or a known public suffix, a bare network scheme, or in
+/// script directives also `data:` and `blob:`. Wildcard subdomains of one
+/// vendor's domain, such as `https://*.js.stripe.com`, are left alone.
+fn is_wildcard(source: &Source, scripts: bool) -> bool {
+    match source {
+        Source::Host(host) => {
+            let host = host
+                .split_once("://")
+                .map_or(host.as_ref(), |(_, rest)| rest);
+            let host = host.split(['/', ':']).next().unwrap_or_default();
+            host == "*"
+                || host.strip_prefix("*.").is_some_and(|domain| {
+                    !domain.contains('.')
+                        || MULTI_LABEL_PUBLIC_SUFFIXES
+                            .iter()
+                            .any(|suffix| domain.eq_ignore_ascii_case(suffix))
+                })
+        }
+        Source::Scheme(scheme) => {
+            ["https", "http", "wss", "ws"]
+                .iter()
+                .any(|network| scheme.eq_ignore_ascii_case(network))
+                || (scripts
+                    && ["data", "blob"]
+                        .iter()
+                        .any(|local| scheme.eq_ignore_ascii_case(local)))
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,216 @@
+//! Bootstraps a policy from the pages of an existing site.
+//!
+//! Feed rendered HTML into a [`PolicyGenerator`] and it allow-lists exactly the
+//! origins and inline content those pages use. With the `crawl` feature,
+//! documents can also be fetched over HTTP.
+
+use crate::constants::{FORM_ACTION, SCRIPT_SRC, STYLE_SRC};
+use crate::core::directives::Directive;
+use crate::core::policy::{CspPolicy, CspPolicyBuilder};
+use crate::core::source::Source;
+use crate::error::CspError;
+use crate::security::hash::{HashAlgorithm, HashGenerator};
+use crate::security::page_audit::{extract_resources, PageResource};
+use indexmap::IndexMap;
+use std::borrow::Cow;
+use url::Url;
+
+/// The hash source an inline script, style or event handler needs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineHash {
+    pub page_url: String,
+    pub directive: &'static str,
+    pub source: Source,
+    /// The start of the hashed content, to recognise it in the template
+    pub excerpt: String,
+}
+
+/// Output of [`PolicyGenerator::generate`].
+#[derive(Debug, Clone)]
+pub struct GeneratedPolicy {
+    pub policy: CspPolicy,
+    /// Every inline hash the policy lists, with the page it was found on
+    pub hashes: Vec<InlineHash>,
+}
+
+/// Derives a minimal policy from a set of HTML documents.
+///
+/// The result starts from `default-src 'none'` and `base-uri 'none'` and adds
+/// only what the documents load: same-origin URLs become `'self'`, other origins
+/// become host sources, `data:`/`blob:` URLs become scheme sources, and inline
+/// content is allowed by hash. Event handlers additionally need `'unsafe-hashes'`.
+///
+/// Only markup is inspected; anything a script loads at runtime still has to be
+/// added by hand or learned from violation reports.
+///
+/// ```rust
+/// use actix_web_csp::security::PolicyGenerator;
+///
+/// let mut generator = PolicyGenerator::new();
+/// generator.add_document(
+///     "https://shop.example.com/",
+///     r#"<script src="https://cdn.example.net/app.js"></script>
+///        <img src="/logo.png"><script>init()</script>"#,
+/// )?;
+///
+/// let mut generated = generator.generate();
+/// let header = generated.policy.header_value()?;
+/// assert!(header.to_str().unwrap().contains("img-src 'self'"));
+/// assert_eq!(generated.hashes.len(), 1);
+/// # Ok::<(), actix_web_csp::CspError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct PolicyGenerator {
+    algorithm: HashAlgorithm,
+    directives: IndexMap<String, Directive>,
+    hashes: Vec<InlineHash>,
+    documents: usize,
+}
+
+impl Default for PolicyGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PolicyGenerator {
+    pub fn new() -> Self {
+        Self {
+            algorithm: HashAlgorithm::Sha256,
+            directives: IndexMap::new(),
+            hashes: Vec::new(),
+            documents: 0,
+        }
+    }
+
+    /// Sets the algorithm inline content is hashed with (SHA-256 by default).
+    #[inline]
+    pub fn with_hash_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    #[inline]
+    pub fn document_count(&self) -> usize {
+        self.documents
+    }
+
+    /// Scans `html`, served at `page_url`, and records what it loads.
+    pub fn add_document(&mut self, page_url: &str, html: &str) -> Result<(), CspError> {
+        let resources = extract_resources(html, page_url)?;
+        let page = Url::parse(page_url).map_err(|error| {
+            CspError::VerificationError(format!("Invalid page URL '{page_url}': {error}"))
+        })?;
+
+        for resource in resources {
+            match resource {
+                PageResource::External(reference) => {
+                    if let Some(source) = source_for(&page, &reference.url) {
+                        self.allow(&reference.directive, source);
+                    }
+                }
+                PageResource::InlineScript { content, .. } => {
+                    self.allow_inline(page_url, SCRIPT_SRC, &content);
+                }
+                PageResource::InlineStyle { content, .. } => {
+                    self.allow_inline(page_url, STYLE_SRC, &content);
+                }
+                PageResource::EventHandler { content, .. } => {
+                    self.allow(SCRIPT_SRC, Source::UnsafeHashes);
+                    self.allow_inline(page_url, SCRIPT_SRC, &content);
+                }
+            }
+        }
+
+        self.documents += 1;
+        Ok(())
+    }
+
+    /// Fetches `url` and scans the returned document.
+    ///
+    /// Redirects are followed; resources are resolved against the final URL.
+    #[cfg(feature = "crawl")]
+    pub fn fetch_document(&mut self, url: &str) -> Result<(), CspError> {
+        let response = ureq::get(url).call().map_err(|error| {
+            CspError::IoError(std::io::Error::other(format!(
+                "Failed to fetch '{url}': {error}"
+            )))
+        })?;
+        let page_url = response.get_url().to_string();
+        let html = response.into_string()?;
+        self.add_document(&page_url, &html)
+    }
+
+    /// Builds the policy for everything scanned so far.
+    pub fn generate(&self) -> GeneratedPolicy {
+        let mut builder = CspPolicyBuilder::new()
+            .default_src([Source::None])
+            .base_uri([Source::None]);
+        if !self.directives.contains_key(FORM_ACTION) {
+            builder = builder.form_action([Source::None]);
+        }
+        for directive in self.directives.values() {
+            builder = builder.with_directive(directive.clone());
+        }
+
+        let mut policy = builder.build_unchecked();
+        policy.canonicalize();
+
+        GeneratedPolicy {
+            policy,
+            hashes: self.hashes.clone(),
+        }
+    }
+
+    fn allow(&mut self, directive: &str, source: Source) {
+        self.directives
+            .entry(directive.to_string())
+            .or_insert_with(|| Directive::new(directive.to_string()))
+            .add_source(source);
+    }
+
+    fn allow_inline(&mut self, page_url: &str, directive: &'static str, content: &str) {
+        let source = HashGenerator::generate_source(self.algorithm, content.as_bytes());
+        self.allow(directive, source.clone());
+
+        if !self
+            .hashes
+            .iter()
+            .any(|hash| hash.directive == directive && hash.source == source)
+        {
+            self.hashes.push(InlineHash {
+                page_url: page_url.to_string(),
+                directive,
+                source,
+                excerpt: content.trim().chars().take(40).collect(),
+            });
+        }
+    }
+}
+
+/// The narrowest source expression that allows `url` on `page`.
+fn source_for(page: &Url, url: &str) -> Option<Source> {
+    let url = Url::parse(url).ok()?;
+
+    match url.scheme() {
+        "data" | "blob" | "mediastream" | "filesystem" => {
+            return Some(Source::Scheme(Cow::Owned(url.scheme().to_string())))
+        }
+        "javascript" | "about" => return None,
+        _ => {}
+    }
+    if url.origin() == page.origin() {
+        return Some(Source::Self_);
+    }
+
+    let mut host = url.host_str()?.to_string();
+    if let Some(port) = url.port() {
+        host = format!("{host}:{port}");
+    }
+    // A scheme-less host source only matches the page's scheme and its secure upgrade
+    let upgrades = (page.scheme(), url.scheme()) == ("http", "https");
+    if url.scheme() != page.scheme() && !upgrades {
+        host = format!("{}://{host}", url.scheme());
+    }
+    Some(Source::Host(Cow::Owned(host)))
+}
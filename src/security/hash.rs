@@ -1,11 +1,17 @@
 use crate::constants::{HASH_PREFIX_SHA256, HASH_PREFIX_SHA384, HASH_PREFIX_SHA512};
 use crate::core::source::Source;
 use crate::error::CspError;
+#[cfg(any(feature = "ring", feature = "sha2"))]
+use backend::Context;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-use ring::digest::{self, Context, SHA256, SHA384, SHA512};
+#[cfg(feature = "ring")]
+use ring::digest::{self, SHA256, SHA384, SHA512};
 use smallvec::SmallVec;
 use std::fmt;
 
+#[cfg(not(any(feature = "ring", feature = "sha2")))]
+compile_error!("actix-web-csp needs a hash backend: enable the `ring` or `sha2` feature");
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum HashAlgorithm {
     Sha256,
@@ -14,6 +20,7 @@ pub enum HashAlgorithm {
 }
 
 impl HashAlgorithm {
+    #[cfg(feature = "ring")]
     #[inline(always)]
     pub fn digest_algorithm(&self) -> &'static digest::Algorithm {
         match self {
@@ -41,6 +48,7 @@ impl HashAlgorithm {
         }
     }
 
+    #[cfg(feature = "ring")]
     #[inline]
     pub fn from_digest_algorithm(algo: &'static digest::Algorithm) -> Option<Self> {
         if algo == &SHA256 {
@@ -74,6 +82,86 @@ impl TryFrom<&str> for HashAlgorithm {
     }
 }
 
+/// `ring` is used when enabled; the pure-Rust `sha2` crate otherwise. Both produce
+/// identical digests.
+#[cfg(feature = "ring")]
+mod backend {
+    use super::HashAlgorithm;
+
+    pub(super) struct Context(ring::digest::Context);
+
+    impl Context {
+        #[inline]
+        pub(super) fn new(algorithm: HashAlgorithm) -> Self {
+            Self(ring::digest::Context::new(algorithm.digest_algorithm()))
+        }
+
+        #[inline]
+        pub(super) fn update(&mut self, data: &[u8]) {
+            self.0.update(data);
+        }
+
+        #[inline]
+        pub(super) fn finish(self) -> ring::digest::Digest {
+            self.0.finish()
+        }
+    }
+
+    #[inline]
+    pub(super) fn digest(algorithm: HashAlgorithm, data: &[u8]) -> ring::digest::Digest {
+        ring::digest::digest(algorithm.digest_algorithm(), data)
+    }
+}
+
+#[cfg(all(feature = "sha2", not(feature = "ring")))]
+mod backend {
+    use super::HashAlgorithm;
+    use sha2::{Digest, Sha256, Sha384, Sha512};
+    use smallvec::SmallVec;
+
+    pub(super) enum Context {
+        Sha256(Sha256),
+        Sha384(Sha384),
+        Sha512(Sha512),
+    }
+
+    impl Context {
+        #[inline]
+        pub(super) fn new(algorithm: HashAlgorithm) -> Self {
+            match algorithm {
+                HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+                HashAlgorithm::Sha384 => Self::Sha384(Sha384::new()),
+                HashAlgorithm::Sha512 => Self::Sha512(Sha512::new()),
+            }
+        }
+
+        #[inline]
+        pub(super) fn update(&mut self, data: &[u8]) {
+            match self {
+                Self::Sha256(hasher) => hasher.update(data),
+                Self::Sha384(hasher) => hasher.update(data),
+                Self::Sha512(hasher) => hasher.update(data),
+            }
+        }
+
+        #[inline]
+        pub(super) fn finish(self) -> SmallVec<[u8; 64]> {
+            match self {
+                Self::Sha256(hasher) => SmallVec::from_slice(&hasher.finalize()),
+                Self::Sha384(hasher) => SmallVec::from_slice(&hasher.finalize()),
+                Self::Sha512(hasher) => SmallVec::from_slice(&hasher.finalize()),
+            }
+        }
+    }
+
+    #[inline]
+    pub(super) fn digest(algorithm: HashAlgorithm, data: &[u8]) -> SmallVec<[u8; 64]> {
+        let mut context = Context::new(algorithm);
+        context.update(data);
+        context.finish()
+    }
+}
+
 thread_local! {
     static HASH_CONTEXTS: std::cell::RefCell<HashContextPool> = std::cell::RefCell::new(HashContextPool::new());
 }
@@ -98,15 +186,15 @@ impl HashContextPool {
             HashAlgorithm::Sha256 => self
                 .sha256_contexts
                 .pop()
-                .unwrap_or_else(|| Context::new(&SHA256)),
+                .unwrap_or_else(|| Context::new(HashAlgorithm::Sha256)),
             HashAlgorithm::Sha384 => self
                 .sha384_contexts
                 .pop()
-                .unwrap_or_else(|| Context::new(&SHA384)),
+                .unwrap_or_else(|| Context::new(HashAlgorithm::Sha384)),
             HashAlgorithm::Sha512 => self
                 .sha512_contexts
                 .pop()
-                .unwrap_or_else(|| Context::new(&SHA512)),
+                .unwrap_or_else(|| Context::new(HashAlgorithm::Sha512)),
         }
     }
 
@@ -114,19 +202,19 @@ impl HashContextPool {
         match algorithm {
             HashAlgorithm::Sha256 => {
                 if self.sha256_contexts.len() < 4 {
-                    let new_context = Context::new(&SHA256);
+                    let new_context = Context::new(HashAlgorithm::Sha256);
                     self.sha256_contexts.push(new_context);
                 }
             }
             HashAlgorithm::Sha384 => {
                 if self.sha384_contexts.len() < 4 {
-                    let new_context = Context::new(&SHA384);
+                    let new_context = Context::new(HashAlgorithm::Sha384);
                     self.sha384_contexts.push(new_context);
                 }
             }
             HashAlgorithm::Sha512 => {
                 if self.sha512_contexts.len() < 4 {
-                    let new_context = Context::new(&SHA512);
+                    let new_context = Context::new(HashAlgorithm::Sha512);
                     self.sha512_contexts.push(new_context);
                 }
             }
@@ -149,7 +237,7 @@ impl HashGenerator {
 
     #[inline]
     fn generate_small(algorithm: HashAlgorithm, data: &[u8]) -> String {
-        let digest = digest::digest(algorithm.digest_algorithm(), data);
+        let digest = backend::digest(algorithm, data);
         BASE64.encode(digest.as_ref())
     }
 
@@ -170,7 +258,7 @@ impl HashGenerator {
 
             let digest = context.finish();
             let result = BASE64.encode(digest.as_ref());
-            pool.return_context(Context::new(algorithm.digest_algorithm()), algorithm);
+            pool.return_context(Context::new(algorithm), algorithm);
             result
         })
     }
@@ -184,6 +272,28 @@ impl HashGenerator {
         }
     }
 
+    /// Hashes the value of an inline event handler attribute such as `onclick`.
+    ///
+    /// `handler` is the value as the browser sees it: without the surrounding
+    /// quotes, with HTML entities decoded and whitespace kept exactly as written.
+    /// Browsers only match such hashes against event handlers when the directive
+    /// also lists `'unsafe-hashes'`; [`CspPolicyBuilder::event_handler_hashes`]
+    /// adds both.
+    ///
+    /// ```rust
+    /// use actix_web_csp::security::{HashAlgorithm, HashGenerator};
+    ///
+    /// // <button onclick="doSomething()">
+    /// let source = HashGenerator::event_handler_source(HashAlgorithm::Sha256, "doSomething()");
+    /// assert!(source.to_string().starts_with("'sha256-"));
+    /// ```
+    ///
+    /// [`CspPolicyBuilder::event_handler_hashes`]: crate::core::CspPolicyBuilder::event_handler_hashes
+    #[inline]
+    pub fn event_handler_source(algorithm: HashAlgorithm, handler: &str) -> Source {
+        Self::generate_source(algorithm, handler.as_bytes())
+    }
+
     #[inline]
     pub fn generate_multiple(requests: &[(HashAlgorithm, &[u8])]) -> Vec<String> {
         let mut results = Vec::with_capacity(requests.len());
@@ -196,7 +306,7 @@ impl HashGenerator {
                 context.update(data);
                 let digest = context.finish();
                 results.push(BASE64.encode(digest.as_ref()));
-                pool.return_context(Context::new(algorithm.digest_algorithm()), algorithm);
+                pool.return_context(Context::new(algorithm), algorithm);
             }
         });
 
@@ -218,7 +328,7 @@ impl HashGenerator {
             context.update(nonce.as_bytes());
             let digest = context.finish();
             let result = BASE64.encode(digest.as_ref());
-            pool.return_context(Context::new(algorithm.digest_algorithm()), algorithm);
+            pool.return_context(Context::new(algorithm), algorithm);
             result
         })
     }
@@ -256,7 +366,7 @@ impl HashGenerator {
                     let calculated = BASE64.encode(digest.as_ref());
                     results[i] = crate::utils::fast_string_compare(&calculated, expected_hash);
 
-                    context = Context::new(&SHA256);
+                    context = Context::new(HashAlgorithm::Sha256);
                 }
                 pool.return_context(context, HashAlgorithm::Sha256);
             }
@@ -269,7 +379,7 @@ impl HashGenerator {
                     let calculated = BASE64.encode(digest.as_ref());
                     results[i] = crate::utils::fast_string_compare(&calculated, expected_hash);
 
-                    context = Context::new(&SHA384);
+                    context = Context::new(HashAlgorithm::Sha384);
                 }
                 pool.return_context(context, HashAlgorithm::Sha384);
             }
@@ -282,7 +392,7 @@ impl HashGenerator {
                     let calculated = BASE64.encode(digest.as_ref());
                     results[i] = crate::utils::fast_string_compare(&calculated, expected_hash);
 
-                    context = Context::new(&SHA512);
+                    context = Context::new(HashAlgorithm::Sha512);
                 }
                 pool.return_context(context, HashAlgorithm::Sha512);
             }
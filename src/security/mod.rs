@@ -1,7 +1,23 @@
+pub mod audit;
+#[cfg(feature = "page-audit")]
+pub mod generator;
 pub mod hash;
 pub mod nonce;
+#[cfg(feature = "page-audit")]
+pub mod page_audit;
+pub mod remote;
 pub mod verify;
 
+pub use audit::{
+    Browser, BrowserTarget, CompatNote, ConflictKind, DirectiveConflict, PaymentFinding,
+    PaymentPageAudit, PaymentRule, PolicyAudit, PolicyAuditor, Relaxation, StaticNonce,
+};
+#[cfg(feature = "page-audit")]
+pub use generator::{GeneratedPolicy, InlineHash, PolicyGenerator};
 pub use hash::{HashAlgorithm, HashGenerator};
-pub use nonce::{NonceGenerator, RequestNonce};
-pub use verify::PolicyVerifier;
+pub use nonce::{CspNonce, NonceGenerator, NonceScope, RequestId, RequestNonce};
+pub use remote::{AsyncPolicyVerifier, RemoteAllowlist};
+pub use verify::{
+    CheckOutcome, CoverageReport, DefaultUrlParser, Explanation, MatchDetail, MatchSpecificity,
+    PolicyVerifier, ResourceCoverage, ResourceRef, SourceCheck, UrlParser, Verdict,
+};
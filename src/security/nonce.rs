@@ -1,38 +1,55 @@
-use crate::constants::{DEFAULT_NONCE_LENGTH, NONCE_BUFFER_POOL_SIZE};
+use crate::constants::{
+    DEFAULT_NONCE_LENGTH, MIN_BACKGROUND_INTERVAL, NONCE_BATCH_CAPACITY, NONCE_BUFFER_POOL_SIZE,
+};
+use crate::core::config::CspConfig;
+use crate::monitoring::cache::{CacheMetrics, CacheStats};
+use actix_web::rt::task::JoinHandle;
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64, Engine};
 use getrandom::getrandom;
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use std::{
-    ops::{Deref, DerefMut},
+    collections::VecDeque,
+    ops::Deref,
     sync::{
         atomic::{AtomicU64, AtomicUsize, Ordering},
-        Arc,
+        Arc, OnceLock,
     },
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+/// Generates base64url nonces from OS randomness, reusing byte buffers from a
+/// small pool shared by its clones.
+///
+/// The pool holds up to [`pool_size`](Self::pool_size) buffers;
+/// [`pool_stats`](Self::pool_stats) shows how often a buffer was reused, so the
+/// size can be tuned under load. At very high request rates,
+/// [`generate_batch`](Self::generate_batch) fills a queue of ready nonces with
+/// one `getrandom` call, and [`generate`](Self::generate) takes from it first.
+/// The queue only holds nonces of the current [`length`](Self::length).
 #[derive(Debug)]
 pub struct NonceGenerator {
-    length: AtomicUsize,
+    length: Arc<AtomicUsize>,
     buffer_pool: Arc<Mutex<SmallVec<[Vec<u8>; NONCE_BUFFER_POOL_SIZE]>>>,
-    stats: Arc<NonceStats>,
+    pool_size: Arc<AtomicUsize>,
+    pool_metrics: Arc<CacheMetrics>,
+    batch: Arc<OnceLock<Mutex<VecDeque<String>>>>,
+    batch_capacity: usize,
+    generated: Arc<AtomicUsize>,
     last_cleanup: Arc<AtomicU64>,
 }
 
-#[derive(Debug, Default)]
-struct NonceStats {
-    generated: AtomicUsize,
-    buffer_hits: AtomicUsize,
-    buffer_misses: AtomicUsize,
-}
-
 impl Clone for NonceGenerator {
     fn clone(&self) -> Self {
         Self {
-            length: AtomicUsize::new(self.length.load(Ordering::Relaxed)),
+            length: self.length.clone(),
             buffer_pool: self.buffer_pool.clone(),
-            stats: self.stats.clone(),
+            pool_size: self.pool_size.clone(),
+            pool_metrics: self.pool_metrics.clone(),
+            batch: self.batch.clone(),
+            batch_capacity: self.batch_capacity,
+            generated: self.generated.clone(),
             last_cleanup: self.last_cleanup.clone(),
         }
     }
@@ -41,29 +58,40 @@ impl Clone for NonceGenerator {
 impl NonceGenerator {
     #[inline]
     pub fn new(length: usize) -> Self {
+        let pool_metrics = CacheMetrics::new();
+        pool_metrics.set_occupancy(0, NONCE_BUFFER_POOL_SIZE);
         Self {
-            length: AtomicUsize::new(length),
+            length: Arc::new(AtomicUsize::new(length)),
             buffer_pool: Arc::new(Mutex::new(SmallVec::new())),
-            stats: Arc::new(NonceStats::default()),
+            pool_size: Arc::new(AtomicUsize::new(NONCE_BUFFER_POOL_SIZE)),
+            pool_metrics: Arc::new(pool_metrics),
+            batch: Arc::new(OnceLock::new()),
+            batch_capacity: NONCE_BATCH_CAPACITY,
+            generated: Arc::new(AtomicUsize::new(0)),
             last_cleanup: Arc::new(AtomicU64::new(0)),
         }
     }
 
     #[inline]
     pub fn generate(&self) -> String {
-        self.stats.generated.fetch_add(1, Ordering::Relaxed);
+        self.generated.fetch_add(1, Ordering::Relaxed);
+        if let Some(nonce) = self.batch.get().and_then(|queue| queue.lock().pop_front()) {
+            return nonce;
+        }
         self.maybe_cleanup_pools();
 
         let length = self.length.load(Ordering::Relaxed);
+        let pool_size = self.pool_size();
         let mut buffer = {
             let mut pool = self.buffer_pool.lock();
             if let Some(mut buf) = pool.pop() {
-                self.stats.buffer_hits.fetch_add(1, Ordering::Relaxed);
+                self.pool_metrics.record_hit();
+                self.pool_metrics.set_occupancy(pool.len(), pool_size);
                 buf.clear();
                 buf.resize(length, 0);
                 buf
             } else {
-                self.stats.buffer_misses.fetch_add(1, Ordering::Relaxed);
+                self.pool_metrics.record_miss();
                 vec![0u8; length]
             }
         };
@@ -73,8 +101,11 @@ impl NonceGenerator {
 
         {
             let mut pool = self.buffer_pool.lock();
-            if pool.len() < NONCE_BUFFER_POOL_SIZE {
+            if pool.len() < pool_size {
                 pool.push(buffer);
+                self.pool_metrics.set_occupancy(pool.len(), pool_size);
+            } else {
+                self.pool_metrics.record_evictions(1);
             }
         }
 
@@ -100,13 +131,29 @@ impl NonceGenerator {
 
     fn cleanup_pools(&self) {
         let mut buffer_pool = self.buffer_pool.lock();
+        let before = buffer_pool.len();
         buffer_pool.retain(|buf| buf.capacity() <= 1024);
         buffer_pool.shrink_to_fit();
+        self.pool_metrics
+            .record_evictions(before - buffer_pool.len());
+        self.pool_metrics
+            .set_occupancy(buffer_pool.len(), self.pool_size());
     }
 
+    /// Changes the length of new nonces for this generator and its clones,
+    /// dropping any queued by [`generate_batch`](Self::generate_batch).
     #[inline]
     pub fn set_length(&self, length: usize) {
-        self.length.store(length, Ordering::Relaxed);
+        match self.batch.get() {
+            // Holding the queue lock keeps a concurrent batch from queuing
+            // nonces of the old length after the queue is cleared.
+            Some(queue) => {
+                let mut queue = queue.lock();
+                self.length.store(length, Ordering::Relaxed);
+                queue.clear();
+            }
+            None => self.length.store(length, Ordering::Relaxed),
+        }
     }
 
     #[inline]
@@ -114,27 +161,141 @@ impl NonceGenerator {
         self.length.load(Ordering::Relaxed)
     }
 
+    /// Keeps at most `size` buffers in the pool. Defaults to 32; zero disables
+    /// pooling.
+    pub fn with_pool_size(self, size: usize) -> Self {
+        self.set_pool_size(size);
+        self
+    }
+
+    /// Like [`with_pool_size`](Self::with_pool_size), for a generator already in
+    /// use. Buffers beyond the new size are dropped.
+    pub fn set_pool_size(&self, size: usize) {
+        self.pool_size.store(size, Ordering::Relaxed);
+        let mut pool = self.buffer_pool.lock();
+        if pool.len() > size {
+            self.pool_metrics.record_evictions(pool.len() - size);
+            pool.truncate(size);
+        }
+        self.pool_metrics.set_occupancy(pool.len(), size);
+    }
+
+    #[inline]
+    pub fn pool_size(&self) -> usize {
+        self.pool_size.load(Ordering::Relaxed)
+    }
+
+    /// Fills the pool with up to `count` buffers so the first requests do not
+    /// allocate, returning how many were added. The pool never grows beyond
+    /// [`pool_size`](Self::pool_size).
+    pub fn prewarm(&self, count: usize) -> usize {
+        let length = self.length();
+        let pool_size = self.pool_size();
+        let mut pool = self.buffer_pool.lock();
+        let added = count.min(pool_size.saturating_sub(pool.len()));
+        pool.extend((0..added).map(|_| vec![0u8; length]));
+        self.pool_metrics.set_occupancy(pool.len(), pool_size);
+        added
+    }
+
+    /// Most nonces [`generate_batch`](Self::generate_batch) keeps queued.
+    /// Defaults to 256.
+    pub fn with_batch_capacity(mut self, capacity: usize) -> Self {
+        self.batch_capacity = capacity.max(1);
+        self.batch = Arc::new(OnceLock::new());
+        self
+    }
+
+    /// Generates up to `count` nonces from a single `getrandom` call and queues
+    /// them for [`generate`](Self::generate), returning how many were queued.
+    /// The queue never holds more than its
+    /// [`with_batch_capacity`](Self::with_batch_capacity).
+    pub fn generate_batch(&self, count: usize) -> usize {
+        let length = self.length();
+        let queue = self
+            .batch
+            .get_or_init(|| Mutex::new(VecDeque::with_capacity(self.batch_capacity)));
+        let count = count.min(self.batch_capacity.saturating_sub(queue.lock().len()));
+        if count == 0 || length == 0 {
+            return 0;
+        }
+
+        let mut bytes = vec![0u8; length * count];
+        getrandom(&mut bytes).expect("Failed to generate random bytes");
+        let nonces = bytes
+            .chunks_exact(length)
+            .map(|chunk| BASE64.encode(chunk))
+            .collect::<Vec<_>>();
+
+        let mut queue = queue.lock();
+        // The length changed while the batch was generated.
+        if self.length() != length {
+            return 0;
+        }
+        let room = self.batch_capacity.saturating_sub(queue.len());
+        let queued = nonces.len().min(room);
+        queue.extend(nonces.into_iter().take(queued));
+        queued
+    }
+
+    /// Number of nonces waiting in the batch queue.
+    #[inline]
+    pub fn queued(&self) -> usize {
+        self.batch.get().map_or(0, |queue| queue.lock().len())
+    }
+
+    /// Keeps the batch queue topped up: every `interval`, if fewer than
+    /// `low_water` nonces are queued, it is filled back to capacity.
+    ///
+    /// Like [`CspConfig::start_maintenance`], it must be called from within an
+    /// Actix (Tokio) runtime and runs until the returned handle is aborted or
+    /// the runtime shuts down. An `interval` shorter than a millisecond,
+    /// including zero, is raised to one millisecond.
+    pub fn start_refill(&self, low_water: usize, interval: Duration) -> JoinHandle<()> {
+        let generator = self.clone();
+        let interval = interval.max(MIN_BACKGROUND_INTERVAL);
+        actix_web::rt::spawn(async move {
+            let mut ticker = actix_web::rt::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if generator.queued() < low_water {
+                    generator.generate_batch(generator.batch_capacity);
+                }
+            }
+        })
+    }
+
+    /// Buffer reuse since the generator was created: a hit is a nonce generated
+    /// with a pooled buffer, a miss one that had to allocate, and an eviction a
+    /// buffer dropped because the pool was full or shrunk.
+    #[inline]
+    pub fn pool_stats(&self) -> CacheStats {
+        self.pool_metrics.stats()
+    }
+
+    /// Number of nonces generated by this generator and its clones.
+    #[inline]
+    pub fn generated_count(&self) -> usize {
+        self.generated.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub(crate) fn pool_metrics(&self) -> Arc<CacheMetrics> {
+        self.pool_metrics.clone()
+    }
+
     #[inline]
     pub fn with_default_length() -> Self {
         Self::new(DEFAULT_NONCE_LENGTH)
     }
 
+    /// Creates a generator whose pool starts with `capacity` buffers, up to the
+    /// default pool size.
     #[inline]
     pub fn with_capacity(capacity: usize, length: usize) -> Self {
-        let buffer_pool = Arc::new(Mutex::new({
-            let mut buffers = SmallVec::new();
-            for _ in 0..capacity.min(NONCE_BUFFER_POOL_SIZE) {
-                buffers.push(vec![0u8; length]);
-            }
-            buffers
-        }));
-
-        Self {
-            length: AtomicUsize::new(length),
-            buffer_pool,
-            stats: Arc::new(NonceStats::default()),
-            last_cleanup: Arc::new(AtomicU64::new(0)),
-        }
+        let generator = Self::new(length);
+        generator.prewarm(capacity);
+        generator
     }
 }
 
@@ -144,19 +305,274 @@ impl Default for NonceGenerator {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct RequestNonce(pub String);
+/// The nonce of the current request, inserted into the request extensions by
+/// [`CspMiddleware`].
+///
+/// The value is allocated once and shared with the policy header and any nonce
+/// response header, so cloning it out of the extensions is cheap.
+///
+/// [`CspMiddleware`]: crate::middleware::CspMiddleware
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestNonce(pub Arc<str>);
+
+impl RequestNonce {
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the shared value without copying it.
+    #[inline]
+    pub fn shared(&self) -> Arc<str> {
+        Arc::clone(&self.0)
+    }
+}
+
+/// How long a generated nonce is reused, set with
+/// [`CspConfigBuilder::with_nonce_scope`].
+///
+/// [`CspConfigBuilder::with_nonce_scope`]: crate::core::CspConfigBuilder::with_nonce_scope
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum NonceScope {
+    /// A fresh nonce for every response
+    #[default]
+    Request,
+    /// One nonce per `actix-session` session, for frameworks that cache
+    /// rendered partials per session. Needs `SessionMiddleware` registered
+    /// outside the CSP middleware; requests without a session fall back to a
+    /// fresh nonce.
+    #[cfg(feature = "session")]
+    Session,
+    /// One nonce for the lifetime of the configuration. Pages can then be
+    /// cached whole, at the price of a nonce an attacker can learn from any
+    /// response; prefer hashes where that matters.
+    Static,
+}
+
+/// The nonce stored in the request's session, generating and storing one if
+/// the session has none yet.
+#[cfg(feature = "session")]
+pub(crate) fn session_nonce(
+    config: &CspConfig,
+    req: &actix_web::dev::ServiceRequest,
+) -> Option<String> {
+    use crate::constants::SESSION_NONCE_KEY;
+    use crate::logging::csp_log;
+    use actix_session::SessionExt;
+
+    let session = req.get_session();
+    if let Some(nonce) = stored_session_nonce(&session) {
+        return Some(nonce);
+    }
+
+    let nonce = config.generate_nonce()?;
+    if let Err(error) = session.insert(SESSION_NONCE_KEY, &nonce) {
+        csp_log!(Warn, "Could not store CSP nonce in session: {error}");
+    }
+    Some(nonce)
+}
+
+/// The nonce already stored in `session`, without generating one.
+#[cfg(feature = "session")]
+pub(crate) fn stored_session_nonce(session: &actix_session::Session) -> Option<String> {
+    use crate::constants::SESSION_NONCE_KEY;
+
+    session
+        .get::<String>(SESSION_NONCE_KEY)
+        .ok()
+        .flatten()
+        .filter(|nonce| is_nonce_value(nonce))
+}
+
+/// Compares a nonce sent by a client with the one issued to it, in time that
+/// depends only on their lengths.
+#[cfg(feature = "session")]
+pub(crate) fn nonces_match(issued: &str, presented: &str) -> bool {
+    let (issued, presented) = (issued.as_bytes(), presented.as_bytes());
+    if issued.is_empty() || issued.len() != presented.len() {
+        return false;
+    }
+
+    issued
+        .iter()
+        .zip(presented)
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+/// Whether `value` looks like a nonce this crate generated, so a tampered
+/// session cannot put arbitrary text into the policy header.
+#[cfg(feature = "session")]
+fn is_nonce_value(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 256
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+}
+
+/// A request nonce that is generated the first time it is read.
+///
+/// Inserted into the request extensions by [`CspMiddleware`] when
+/// [`CspConfigBuilder::with_lazy_nonce`] is enabled. The middleware only adds the
+/// nonce to the policy header if a handler actually used it.
+///
+/// [`CspMiddleware`]: crate::middleware::CspMiddleware
+/// [`CspConfigBuilder::with_lazy_nonce`]: crate::core::CspConfigBuilder::with_lazy_nonce
+#[derive(Clone)]
+pub struct CspNonce {
+    inner: Arc<LazyNonce>,
+}
+
+struct LazyNonce {
+    config: Arc<CspConfig>,
+    request_id: Option<RequestId>,
+    value: OnceLock<Arc<str>>,
+}
+
+impl CspNonce {
+    pub(crate) fn new(config: Arc<CspConfig>, request_id: Option<RequestId>) -> Self {
+        Self {
+            inner: Arc::new(LazyNonce {
+                config,
+                request_id,
+                value: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Returns the nonce, generating it on first access.
+    pub fn get(&self) -> &str {
+        self.value()
+    }
+
+    /// Like [`get`](Self::get), returning the value shared with the middleware.
+    #[inline]
+    pub(crate) fn shared(&self) -> Arc<str> {
+        Arc::clone(self.value())
+    }
+
+    fn value(&self) -> &Arc<str> {
+        self.inner.value.get_or_init(|| {
+            self.inner
+                .config
+                .prepare_request_nonce(self.inner.request_id)
+                .unwrap_or_else(|| Arc::from(""))
+        })
+    }
+
+    /// Returns `true` once the nonce has been generated.
+    #[inline]
+    pub fn is_used(&self) -> bool {
+        self.inner.value.get().is_some()
+    }
+
+    /// Returns the nonce if it has been generated, without generating it.
+    #[inline]
+    pub fn generated(&self) -> Option<&str> {
+        self.inner.value.get().map(|value| &**value)
+    }
+
+    #[inline]
+    pub(crate) fn generated_shared(&self) -> Option<Arc<str>> {
+        self.inner.value.get().cloned()
+    }
+}
+
+impl Drop for LazyNonce {
+    fn drop(&mut self) {
+        // handlers can keep a clone past the middleware's cleanup and read it
+        // late, so the last clone takes the request's cache entry with it
+        self.config.remove_request_nonce(self.request_id);
+    }
+}
+
+impl std::fmt::Debug for CspNonce {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CspNonce")
+            .field("request_id", &self.inner.request_id)
+            .field("used", &self.is_used())
+            .finish()
+    }
+}
+
+/// Identifies a request to the per-request nonce cache.
+///
+/// [`CspMiddleware`] assigns one from a process-wide counter and stores it in the
+/// request extensions when per-request nonces are enabled. IDs only come from
+/// [`RequestId::next`], so a caller cannot pick one that shares a cache entry
+/// with a request in flight.
+///
+/// [`CspMiddleware`]: crate::middleware::CspMiddleware
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RequestId(u64);
+
+impl RequestId {
+    /// Returns an ID not handed out before in this process.
+    #[inline]
+    pub fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+
+    #[inline]
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
 
 impl Deref for RequestNonce {
-    type Target = String;
+    type Target = str;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl DerefMut for RequestNonce {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+impl AsRef<str> for RequestNonce {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<Arc<str>> for RequestNonce {
+    #[inline]
+    fn from(nonce: Arc<str>) -> Self {
+        Self(nonce)
+    }
+}
+
+impl From<String> for RequestNonce {
+    #[inline]
+    fn from(nonce: String) -> Self {
+        Self(nonce.into())
+    }
+}
+
+impl From<&str> for RequestNonce {
+    #[inline]
+    fn from(nonce: &str) -> Self {
+        Self(nonce.into())
+    }
+}
+
+impl From<RequestNonce> for String {
+    #[inline]
+    fn from(nonce: RequestNonce) -> Self {
+        nonce.0.as_ref().to_owned()
+    }
+}
+
+impl std::fmt::Display for RequestNonce {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
     }
 }
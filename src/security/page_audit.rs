@@ -0,0 +1,384 @@
+//! Static audit of rendered HTML against a policy.
+//!
+//! Pages are read with the `html5ever` tokenizer, so comments, character
+//! references, malformed markup and the raw text of `<script>`, `<style>`,
+//! `<textarea>` and similar elements are handled as browsers handle them. Content
+//! injected at runtime by scripts is invisible to it, as is markup inside
+//! `<svg>`/`<math>`, which is tokenized as HTML.
+
+use crate::constants::REQUIRE_SRI_FOR;
+use crate::core::directives::RequireSriFor;
+use crate::error::CspError;
+use crate::security::verify::{PolicyVerifier, ResourceRef};
+use html5ever::tendril::StrTendril;
+use html5ever::tokenizer::states::RawKind;
+use html5ever::tokenizer::{
+    BufferQueue, Tag, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOpts,
+};
+use url::Url;
+
+/// Something on the page that the policy governs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageResource {
+    /// A URL loaded by an element, resolved against the page URL
+    External(ResourceRef),
+    InlineScript {
+        content: String,
+        nonce: Option<String>,
+    },
+    InlineStyle {
+        content: String,
+        nonce: Option<String>,
+    },
+    /// An inline event handler attribute such as `onclick`
+    EventHandler { attribute: String, content: String },
+}
+
+/// A resource the policy is predicted to block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageViolation {
+    pub resource: PageResource,
+    pub directive: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageAuditReport {
+    pub resources: Vec<PageResource>,
+    pub violations: Vec<PageViolation>,
+}
+
+impl PageAuditReport {
+    #[inline]
+    pub fn is_clean(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Extracts policy-relevant resources from `html`, resolving URLs against `page_url`.
+///
+/// URLs that can not be resolved (for example `javascript:` pseudo-URLs with
+/// invalid syntax) are skipped.
+pub fn extract_resources(html: &str, page_url: &str) -> Result<Vec<PageResource>, CspError> {
+    scan(html, page_url).map(|scan| scan.resources)
+}
+
+struct Scan {
+    resources: Vec<PageResource>,
+    /// External scripts and stylesheets without an `integrity` attribute
+    missing_integrity: Vec<ResourceRef>,
+}
+
+fn scan(html: &str, page_url: &str) -> Result<Scan, CspError> {
+    let base = Url::parse(page_url).map_err(|error| {
+        CspError::VerificationError(format!("Invalid page URL '{page_url}': {error}"))
+    })?;
+
+    let mut input = BufferQueue::default();
+    input.push_back(StrTendril::from_slice(html));
+    let mut tokenizer = Tokenizer::new(
+        Collector {
+            base,
+            scan: Scan {
+                resources: Vec::new(),
+                missing_integrity: Vec::new(),
+            },
+            raw_text: None,
+        },
+        TokenizerOpts::default(),
+    );
+    let _ = tokenizer.feed(&mut input);
+    tokenizer.end();
+
+    Ok(tokenizer.sink.scan)
+}
+
+/// Token sink gathering the resources of a page.
+struct Collector {
+    base: Url,
+    scan: Scan,
+    /// The open `<script>` or `<style>` element and its text so far
+    raw_text: Option<(Tag, String)>,
+}
+
+impl TokenSink for Collector {
+    type Handle = ();
+
+    fn process_token(&mut self, token: Token, _line_number: u64) -> TokenSinkResult<()> {
+        match token {
+            Token::TagToken(tag) if tag.kind == TagKind::StartTag => {
+                self.finish_raw_text();
+                return self.start_tag(tag);
+            }
+            Token::TagToken(_) | Token::EOFToken => self.finish_raw_text(),
+            Token::CharacterTokens(text) => {
+                if let Some((_, content)) = &mut self.raw_text {
+                    content.push_str(&text);
+                }
+            }
+            _ => {}
+        }
+        TokenSinkResult::Continue
+    }
+}
+
+impl Collector {
+    fn start_tag(&mut self, tag: Tag) -> TokenSinkResult<()> {
+        let resources = &mut self.scan.resources;
+        collect_handlers(&tag, resources);
+
+        match tag.name.as_ref() {
+            "script" => {
+                if let Some(src) = attribute(&tag, "src") {
+                    let pushed = push_external(resources, &self.base, "script-src", src);
+                    if let Some(reference) = pushed.filter(|_| !has_integrity(&tag)) {
+                        self.scan.missing_integrity.push(reference.clone());
+                    }
+                }
+                self.raw_text = Some((tag, String::new()));
+                return TokenSinkResult::RawData(RawKind::ScriptData);
+            }
+            "style" => {
+                self.raw_text = Some((tag, String::new()));
+                return TokenSinkResult::RawData(RawKind::Rawtext);
+            }
+            // Raw text and escapable raw text elements, whose content is not markup
+            "xmp" | "iframe" | "noembed" | "noframes" | "noscript" => {
+                if tag.name.as_ref() == "iframe" {
+                    if let Some(src) = attribute(&tag, "src") {
+                        push_external(resources, &self.base, "frame-src", src);
+                    }
+                }
+                return TokenSinkResult::RawData(RawKind::Rawtext);
+            }
+            "textarea" | "title" => return TokenSinkResult::RawData(RawKind::Rcdata),
+            "plaintext" => return TokenSinkResult::Plaintext,
+            "link" => {
+                let rel = attribute(&tag, "rel")
+                    .unwrap_or_default()
+                    .to_ascii_lowercase();
+                let stylesheet = rel.split_whitespace().any(|r| r == "stylesheet");
+                let directive = if stylesheet {
+                    Some("style-src")
+                } else if rel.split_whitespace().any(|r| r == "manifest") {
+                    Some("manifest-src")
+                } else if rel.split_whitespace().any(|r| r == "icon") {
+                    Some("img-src")
+                } else if rel.split_whitespace().any(|r| r == "preload") {
+                    match attribute(&tag, "as") {
+                        Some("script") => Some("script-src"),
+                        Some("style") => Some("style-src"),
+                        Some("font") => Some("font-src"),
+                        Some("image") => Some("img-src"),
+                        _ => None,
+                    }
+                } else {
+                    None
+                };
+
+                if let (Some(directive), Some(href)) = (directive, attribute(&tag, "href")) {
+                    let pushed = push_external(resources, &self.base, directive, href);
+                    if let Some(reference) = pushed.filter(|_| stylesheet && !has_integrity(&tag)) {
+                        self.scan.missing_integrity.push(reference.clone());
+                    }
+                }
+            }
+            "img" | "frame" | "audio" | "video" | "source" | "track" | "embed" => {
+                let directive = match tag.name.as_ref() {
+                    "img" => "img-src",
+                    "frame" => "frame-src",
+                    "embed" => "object-src",
+                    _ => "media-src",
+                };
+                if let Some(src) = attribute(&tag, "src") {
+                    push_external(resources, &self.base, directive, src);
+                }
+            }
+            "object" => {
+                if let Some(data) = attribute(&tag, "data") {
+                    push_external(resources, &self.base, "object-src", data);
+                }
+            }
+            "form" => {
+                if let Some(action) = attribute(&tag, "action") {
+                    push_external(resources, &self.base, "form-action", action);
+                }
+            }
+            _ => {}
+        }
+        TokenSinkResult::Continue
+    }
+
+    /// Records the text of the open `<script>` or `<style>` element, if any.
+    fn finish_raw_text(&mut self) {
+        let Some((tag, content)) = self.raw_text.take() else {
+            return;
+        };
+        if content.trim().is_empty() {
+            return;
+        }
+
+        let nonce = attribute(&tag, "nonce").map(str::to_string);
+        if tag.name.as_ref() == "style" {
+            self.scan
+                .resources
+                .push(PageResource::InlineStyle { content, nonce });
+        } else if attribute(&tag, "src").is_none() {
+            self.scan
+                .resources
+                .push(PageResource::InlineScript { content, nonce });
+        }
+    }
+}
+
+/// Predicts which resources of `html` the verifier's policy would block.
+///
+/// When the policy carries `require-sri-for`, external scripts and stylesheets
+/// without an `integrity` attribute are reported against that directive too.
+///
+/// ```rust
+/// use actix_web_csp::security::{page_audit, PolicyVerifier};
+/// use actix_web_csp::{CspPolicyBuilder, Source};
+///
+/// let policy = CspPolicyBuilder::new()
+///     .default_src([Source::Self_])
+///     .build_unchecked();
+/// let verifier = PolicyVerifier::with_origin(policy, "https://app.example.com")?;
+///
+/// let report = page_audit::audit_html(
+///     &verifier,
+///     r#"<script src="/app.js"></script><img src="https://tracker.example.net/p.gif">"#,
+///     "https://app.example.com/",
+/// )?;
+///
+/// assert_eq!(report.violations.len(), 1);
+/// # Ok::<(), actix_web_csp::CspError>(())
+/// ```
+pub fn audit_html(
+    verifier: &PolicyVerifier,
+    html: &str,
+    page_url: &str,
+) -> Result<PageAuditReport, CspError> {
+    let Scan {
+        resources,
+        missing_integrity,
+    } = scan(html, page_url)?;
+    let mut violations = Vec::new();
+
+    let external: Vec<ResourceRef> = resources
+        .iter()
+        .filter_map(|resource| match resource {
+            PageResource::External(reference) => Some(reference.clone()),
+            _ => None,
+        })
+        .collect();
+
+    for coverage in verifier.coverage_report(&external).blocked() {
+        violations.push(PageViolation {
+            resource: PageResource::External(coverage.resource.clone()),
+            directive: coverage
+                .effective_directive
+                .clone()
+                .unwrap_or_else(|| coverage.resource.directive.to_string()),
+            reason: format!("'{}' is not allowed", coverage.resource.url),
+        });
+    }
+
+    for resource in &resources {
+        let (directive, allowed) = match resource {
+            PageResource::External(_) => continue,
+            PageResource::InlineScript { content, nonce } => (
+                "script-src",
+                verifier.verify_inline_script(content.as_bytes(), nonce.as_deref())?,
+            ),
+            PageResource::InlineStyle { content, nonce } => (
+                "style-src",
+                verifier.verify_inline_style(content.as_bytes(), nonce.as_deref())?,
+            ),
+            PageResource::EventHandler { content, .. } => (
+                "script-src",
+                verifier.verify_inline_script(content.as_bytes(), None)?,
+            ),
+        };
+
+        if !allowed {
+            let reason = match resource {
+                PageResource::EventHandler { attribute, .. } => {
+                    format!("inline '{attribute}' handler is not allowed")
+                }
+                _ => "inline content has no matching nonce, hash or 'unsafe-inline'".to_string(),
+            };
+            violations.push(PageViolation {
+                resource: resource.clone(),
+                directive: directive.to_string(),
+                reason,
+            });
+        }
+    }
+
+    if let Some(sri) = verifier
+        .policy()
+        .get_directive(REQUIRE_SRI_FOR)
+        .map(RequireSriFor::from_directive)
+    {
+        for reference in missing_integrity {
+            let required = match reference.directive.as_ref() {
+                "script-src" => sri.requires_script(),
+                _ => sri.requires_style(),
+            };
+            if required {
+                violations.push(PageViolation {
+                    reason: format!("'{}' has no integrity attribute", reference.url),
+                    resource: PageResource::External(reference),
+                    directive: REQUIRE_SRI_FOR.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(PageAuditReport {
+        resources,
+        violations,
+    })
+}
+
+fn attribute<'a>(tag: &'a Tag, name: &str) -> Option<&'a str> {
+    tag.attrs
+        .iter()
+        .find(|attribute| attribute.name.local.as_ref() == name)
+        .map(|attribute| attribute.value.as_ref())
+}
+
+fn has_integrity(tag: &Tag) -> bool {
+    attribute(tag, "integrity").is_some_and(|value| !value.trim().is_empty())
+}
+
+fn collect_handlers(tag: &Tag, resources: &mut Vec<PageResource>) {
+    for attribute in &tag.attrs {
+        let name: &str = attribute.name.local.as_ref();
+        if name.len() > 2 && name.starts_with("on") {
+            resources.push(PageResource::EventHandler {
+                attribute: name.to_string(),
+                content: attribute.value.to_string(),
+            });
+        }
+    }
+}
+
+/// Resolves `url` and records it, returning the new reference.
+fn push_external<'a>(
+    resources: &'a mut Vec<PageResource>,
+    base: &Url,
+    directive: &'static str,
+    url: &str,
+) -> Option<&'a ResourceRef> {
+    let resolved = base.join(url.trim()).ok()?;
+    resources.push(PageResource::External(ResourceRef::new(
+        directive,
+        resolved.to_string(),
+    )));
+    match resources.last() {
+        Some(PageResource::External(reference)) => Some(reference),
+        _ => None,
+    }
+}
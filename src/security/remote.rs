@@ -0,0 +1,187 @@
+//! Verification that falls back to an external allowlist service.
+
+use crate::collections::LruCache;
+use crate::error::CspError;
+use crate::logging::csp_log;
+use crate::security::verify::PolicyVerifier;
+use crate::utils::CachedValue;
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+const DEFAULT_REMOTE_TIMEOUT: Duration = Duration::from_secs(2);
+const DEFAULT_REMOTE_CACHE_TTL: Duration = Duration::from_secs(300);
+const DEFAULT_REMOTE_CACHE_ENTRIES: usize = 1024;
+
+/// External source of truth consulted by an [`AsyncPolicyVerifier`] for URIs
+/// the policy itself does not allow, such as a tenant allowlist service or a
+/// DNS-based ownership check.
+///
+/// Closures taking the parsed URL and the directive name and returning a
+/// `Send` future implement the trait, so a verifier can be moved into spawned
+/// tasks and shared handlers.
+pub trait RemoteAllowlist: Send + Sync + 'static {
+    fn is_allowed<'a>(
+        &'a self,
+        url: &'a Url,
+        directive: &'a str,
+    ) -> BoxFuture<'a, Result<bool, CspError>>;
+}
+
+impl<F, Fut> RemoteAllowlist for F
+where
+    F: Fn(Url, String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<bool, CspError>> + Send + 'static,
+{
+    fn is_allowed<'a>(
+        &'a self,
+        url: &'a Url,
+        directive: &'a str,
+    ) -> BoxFuture<'a, Result<bool, CspError>> {
+        Box::pin(self(url.clone(), directive.to_string()))
+    }
+}
+
+/// A [`PolicyVerifier`] that asks a [`RemoteAllowlist`] about URIs the policy
+/// blocks.
+///
+/// For platforms whose full allowlist is too large to send in the header: the
+/// header carries the common sources, and server-side checks cover the rest. A
+/// URI is allowed when the policy allows it or the remote allowlist does.
+/// Remote answers are cached per URI and directive for
+/// [`with_cache_ttl`](Self::with_cache_ttl); lookups that fail or exceed
+/// [`with_timeout`](Self::with_timeout) return an error and are not cached.
+///
+/// ```rust
+/// use actix_web_csp::security::{AsyncPolicyVerifier, PolicyVerifier};
+/// use actix_web_csp::{CspError, CspPolicyBuilder, Source};
+/// use url::Url;
+///
+/// # actix_web::rt::System::new().block_on(async {
+/// let policy = CspPolicyBuilder::new().default_src([Source::Self_]).build()?;
+/// let mut verifier = AsyncPolicyVerifier::new(
+///     PolicyVerifier::new(policy),
+///     |url: Url, _directive: String| async move {
+///         Ok::<_, CspError>(url.host_str() == Some("tenant-cdn.example.com"))
+///     },
+/// );
+///
+/// # #[cfg(feature = "verify")]
+/// assert!(verifier
+///     .verify_uri("https://tenant-cdn.example.com/app.js", "script-src")
+///     .await?);
+/// # Ok::<(), CspError>(())
+/// # }).unwrap();
+/// ```
+pub struct AsyncPolicyVerifier {
+    verifier: PolicyVerifier,
+    remote: Arc<dyn RemoteAllowlist>,
+    timeout: Duration,
+    cache_ttl: Duration,
+    cache: LruCache<(String, String), CachedValue<bool>>,
+}
+
+impl AsyncPolicyVerifier {
+    pub fn new(verifier: PolicyVerifier, remote: impl RemoteAllowlist) -> Self {
+        Self::with_shared_remote(verifier, Arc::new(remote))
+    }
+
+    /// Like [`new`](Self::new), for a remote allowlist shared by several verifiers.
+    pub fn with_shared_remote(verifier: PolicyVerifier, remote: Arc<dyn RemoteAllowlist>) -> Self {
+        Self {
+            verifier,
+            remote,
+            timeout: DEFAULT_REMOTE_TIMEOUT,
+            cache_ttl: DEFAULT_REMOTE_CACHE_TTL,
+            cache: LruCache::new(NonZeroUsize::new(DEFAULT_REMOTE_CACHE_ENTRIES).unwrap()),
+        }
+    }
+
+    /// How long a remote lookup may take before it fails. Defaults to two seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How long a remote answer is reused. Defaults to five minutes; zero
+    /// disables caching.
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// How many remote answers are kept. Defaults to 1024.
+    pub fn with_cache_size(mut self, entries: NonZeroUsize) -> Self {
+        self.cache = LruCache::new(entries);
+        self
+    }
+
+    #[inline]
+    pub fn verifier(&self) -> &PolicyVerifier {
+        &self.verifier
+    }
+
+    /// Gives mutable access to the local verifier. Cached remote answers are
+    /// kept, since they do not depend on the policy.
+    #[inline]
+    pub fn verifier_mut(&mut self) -> &mut PolicyVerifier {
+        &mut self.verifier
+    }
+
+    /// Drops every cached remote answer.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Returns `true` if the policy allows `uri` under `directive_name`, or else
+    /// if the remote allowlist does. A URI that does not parse is blocked
+    /// without asking the remote allowlist.
+    ///
+    /// Must run inside an actix (Tokio) runtime for the timeout to work.
+    pub async fn verify_uri(&mut self, uri: &str, directive_name: &str) -> Result<bool, CspError> {
+        if self.verifier.verify_uri(uri, directive_name)? {
+            return Ok(true);
+        }
+
+        let cache_key = (uri.to_string(), directive_name.to_string());
+        if let Some(cached) = self.cache.get(&cache_key) {
+            if cached.is_valid() {
+                return Ok(*cached.value());
+            }
+            self.cache.pop(&cache_key);
+        }
+
+        // Already counted by the local verifier, and never worth a remote lookup
+        let Some(url) = self.verifier.parse_uri(uri) else {
+            return Ok(false);
+        };
+        let lookup = self.remote.is_allowed(&url, directive_name);
+        let allowed = match actix_web::rt::time::timeout(self.timeout, lookup).await {
+            Ok(Ok(allowed)) => allowed,
+            Ok(Err(error)) => {
+                csp_log!(Warn, "Remote allowlist lookup for {uri} failed: {error}");
+                return Err(error);
+            }
+            Err(_) => {
+                csp_log!(
+                    Warn,
+                    "Remote allowlist lookup for {uri} timed out after {:?}",
+                    self.timeout
+                );
+                return Err(CspError::VerificationError(format!(
+                    "Remote allowlist lookup for {uri} timed out after {:?}",
+                    self.timeout
+                )));
+            }
+        };
+
+        if !self.cache_ttl.is_zero() {
+            self.cache
+                .put(cache_key, CachedValue::new(allowed, self.cache_ttl));
+        }
+        Ok(allowed)
+    }
+}
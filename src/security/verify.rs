@@ -1,31 +1,415 @@
 use crate::core::policy::CspPolicy;
+use crate::core::source::Source;
 use crate::error::CspError;
+use std::borrow::Cow;
+use std::fmt;
+use url::Url;
+
+/// Turns the URIs a [`PolicyVerifier`] checks into [`Url`]s, set with
+/// [`PolicyVerifier::with_url_parser`].
+///
+/// The default, [`DefaultUrlParser`], is [`Url::parse`]. A custom parser can
+/// normalize URIs first, for example to strip tracking parameters, or adjust
+/// the corner cases where `url` and browsers disagree. Returning `None` makes
+/// the URI invalid, and invalid URIs are blocked.
+///
+/// Closures taking the URI and returning an `Option<Url>` implement the trait.
+///
+/// ```rust
+/// use actix_web_csp::security::PolicyVerifier;
+/// use actix_web_csp::{CspPolicyBuilder, Source};
+/// use url::Url;
+///
+/// let policy = CspPolicyBuilder::new()
+///     .img_src([Source::Host("cdn.example.com".into())])
+///     .build()?;
+/// // Browsers fetch `//cdn.example.com` relative to the page's scheme.
+/// let mut verifier = PolicyVerifier::new(policy).with_url_parser(|uri: &str| match uri {
+///     uri if uri.starts_with("//") => Url::parse(&format!("https:{uri}")).ok(),
+///     uri => Url::parse(uri).ok(),
+/// });
+///
+/// # #[cfg(feature = "verify")]
+/// assert!(verifier.verify_uri("//cdn.example.com/logo.png", "img-src")?);
+/// # Ok::<(), actix_web_csp::CspError>(())
+/// ```
+pub trait UrlParser: Send + Sync + 'static {
+    fn parse(&self, uri: &str) -> Option<Url>;
+}
+
+impl<F> UrlParser for F
+where
+    F: Fn(&str) -> Option<Url> + Send + Sync + 'static,
+{
+    #[inline]
+    fn parse(&self, uri: &str) -> Option<Url> {
+        self(uri)
+    }
+}
+
+/// Parses URIs with [`Url::parse`], unchanged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultUrlParser;
+
+impl UrlParser for DefaultUrlParser {
+    #[inline]
+    fn parse(&self, uri: &str) -> Option<Url> {
+        Url::parse(uri).ok()
+    }
+}
+
+/// A resource referenced by a page: the fetch directive it loads under and its URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceRef {
+    pub directive: Cow<'static, str>,
+    pub url: String,
+}
+
+impl ResourceRef {
+    #[inline]
+    pub fn new(directive: impl Into<Cow<'static, str>>, url: impl Into<String>) -> Self {
+        Self {
+            directive: directive.into(),
+            url: url.into(),
+        }
+    }
+}
+
+/// Verdict for a single [`ResourceRef`] in a [`CoverageReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceCoverage {
+    pub resource: ResourceRef,
+    /// Directive that was actually consulted, after falling back to `default-src`
+    pub effective_directive: Option<String>,
+    pub allowed: bool,
+    /// Source that allowed the load; `None` when blocked or when no directive applies
+    pub granted_by: Option<Source>,
+}
+
+/// How narrowly the source that allowed a URI describes it, most specific first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MatchSpecificity {
+    /// `'self'`
+    SelfOrigin,
+    /// A host source with a path, e.g. `cdn.example.com/js/`
+    Path,
+    /// A single host, e.g. `cdn.example.com`
+    Host,
+    /// A wildcard subdomain, e.g. `*.example.com`
+    WildcardSubdomain,
+    /// A bare scheme such as `https:`, allowing every host
+    Scheme,
+    /// `*`, allowing every host
+    AnyHost,
+    /// No directive restricts the load
+    Unrestricted,
+}
+
+impl MatchSpecificity {
+    /// Returns `true` for matches that allow every host.
+    #[inline]
+    pub fn is_broad(self) -> bool {
+        self >= Self::Scheme
+    }
+}
+
+/// Result of [`PolicyVerifier::verify_uri_detailed`]: whether a URI is allowed
+/// and which source allowed it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchDetail {
+    pub allowed: bool,
+    /// Directive that was actually consulted, after falling back to `default-src`
+    pub effective_directive: Option<String>,
+    /// Source that allowed the load; `None` when blocked or when no directive applies
+    pub matched_source: Option<Source>,
+    /// `None` when blocked
+    pub specificity: Option<MatchSpecificity>,
+    /// The URI is allowed over plain `http:` or `ws:`
+    pub insecure_scheme: bool,
+}
+
+impl MatchDetail {
+    /// Returns `true` if the URI is allowed only because of a source that
+    /// allows every host, such as `https:` or `*`, or because nothing
+    /// restricts the directive.
+    #[inline]
+    pub fn is_broad(&self) -> bool {
+        self.specificity.is_some_and(MatchSpecificity::is_broad)
+    }
+}
+
+/// Why a single source did or did not allow the URI in an [`Explanation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CheckOutcome {
+    Matched,
+    /// The URI's scheme is not the one the source names or implies
+    SchemeMismatch,
+    HostMismatch,
+    PortMismatch,
+    PathMismatch,
+    /// `'self'` against a URI from another origin
+    NotSameOrigin,
+    /// `'self'` with no origin set on the verifier, see [`PolicyVerifier::set_origin`]
+    NoOrigin,
+    /// Nonces, hashes and keywords other than `'self'` never allow a URI
+    NotApplicable,
+    /// A host source that does not parse
+    InvalidSource,
+}
+
+impl CheckOutcome {
+    #[inline]
+    pub fn is_match(self) -> bool {
+        self == Self::Matched
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Matched => "matches",
+            Self::SchemeMismatch => "scheme does not match",
+            Self::HostMismatch => "host does not match",
+            Self::PortMismatch => "port does not match",
+            Self::PathMismatch => "path does not match",
+            Self::NotSameOrigin => "not the same origin as the document",
+            Self::NoOrigin => "no document origin to compare against",
+            Self::NotApplicable => "does not apply to URLs",
+            Self::InvalidSource => "not a valid host source",
+        }
+    }
+}
+
+impl fmt::Display for CheckOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+/// One source tested while evaluating a URI, in policy order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceCheck {
+    pub source: Source,
+    pub outcome: CheckOutcome,
+}
+
+/// Overall result of an [`Explanation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Verdict {
+    /// A source allowed the URI
+    Matched,
+    /// No directive, not even `default-src`, restricts the load
+    Unrestricted,
+    /// The directive is `'none'`
+    NoneKeyword,
+    /// `'strict-dynamic'` with a nonce or hash makes browsers ignore host and
+    /// scheme sources
+    StrictDynamic,
+    /// Every source was tested and none matched
+    NoMatch,
+    /// The URI did not parse, which blocks it whatever the policy says
+    InvalidUri,
+}
+
+impl Verdict {
+    #[inline]
+    pub fn is_allowed(self) -> bool {
+        matches!(self, Self::Matched | Self::Unrestricted)
+    }
+}
+
+/// Evaluation trace from [`PolicyVerifier::explain`]: the directive consulted
+/// after fallback, every source tested and why each one failed.
+///
+/// Its `Display` output is meant for people, e.g. in a development reporter
+/// explaining why a load would be blocked:
+///
+/// ```text
+/// script-src would block https://evil.example/x.js (checked default-src)
+///   'self': not the same origin as the document
+///   cdn.example.com: host does not match
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub uri: String,
+    pub requested_directive: String,
+    /// Directive that was actually consulted, `None` when none applies
+    pub effective_directive: Option<String>,
+    pub verdict: Verdict,
+    /// Sources tested, up to and including the one that matched; empty when the
+    /// verdict was decided before any source was tested
+    pub checks: Vec<SourceCheck>,
+}
+
+impl Explanation {
+    #[inline]
+    pub fn is_allowed(&self) -> bool {
+        self.verdict.is_allowed()
+    }
+
+    /// Returns `true` if the requested directive is absent and another one,
+    /// such as `default-src`, was consulted instead.
+    #[inline]
+    pub fn fell_back(&self) -> bool {
+        self.effective_directive
+            .as_deref()
+            .is_some_and(|effective| effective != self.requested_directive)
+    }
+
+    /// The source that allowed the URI, if any.
+    pub fn matched_source(&self) -> Option<&Source> {
+        self.checks
+            .iter()
+            .find(|check| check.outcome.is_match())
+            .map(|check| &check.source)
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = if self.is_allowed() {
+            "allows"
+        } else {
+            "would block"
+        };
+        write!(f, "{} {action} {}", self.requested_directive, self.uri)?;
+        match &self.effective_directive {
+            Some(effective) if self.fell_back() => write!(f, " (checked {effective})")?,
+            Some(_) => {}
+            None => f.write_str(" (no directive applies)")?,
+        }
+        match self.verdict {
+            Verdict::NoneKeyword => f.write_str("\n  'none' allows nothing")?,
+            Verdict::InvalidUri => f.write_str("\n  not a valid URL")?,
+            Verdict::StrictDynamic => f.write_str(
+                "\n  'strict-dynamic' with a nonce or hash: host and scheme sources are ignored",
+            )?,
+            _ => {}
+        }
+        for check in &self.checks {
+            write!(f, "\n  {}: {}", check.source, check.outcome)?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of [`PolicyVerifier::coverage_report`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverageReport {
+    pub resources: Vec<ResourceCoverage>,
+    /// Location-based sources of fetch directives that allowed none of the resources
+    pub unused_sources: Vec<(String, Source)>,
+}
+
+impl CoverageReport {
+    pub fn blocked(&self) -> impl Iterator<Item = &ResourceCoverage> {
+        self.resources.iter().filter(|coverage| !coverage.allowed)
+    }
+
+    pub fn allowed(&self) -> impl Iterator<Item = &ResourceCoverage> {
+        self.resources.iter().filter(|coverage| coverage.allowed)
+    }
+
+    #[inline]
+    pub fn is_fully_allowed(&self) -> bool {
+        self.resources.iter().all(|coverage| coverage.allowed)
+    }
+}
 
 #[cfg(feature = "verify")]
 mod imp {
     use super::*;
-    use crate::core::source::Source;
+    use crate::core::source::{HostMismatch, HostSource};
+    use crate::monitoring::cache::{CacheMetrics, CacheStats};
     use std::collections::HashMap;
-    use url::Url;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const VERIFICATION_CACHE_ENTRIES: usize = 512;
+
+    /// Shared by every verifier; occupancy sums the live ones.
+    static VERIFICATION_CACHE_METRICS: CacheMetrics = CacheMetrics::new();
+
+    /// URIs passed to any verifier that did not parse.
+    static INVALID_URI_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// How a directive decided on a URL, shared by the checks and `explain`.
+    enum Grant<'a> {
+        NoneKeyword,
+        StrictDynamic,
+        Source(&'a Source),
+        NoMatch,
+    }
+
+    #[cfg_attr(not(feature = "stats"), allow(dead_code))]
+    pub(crate) fn verification_cache_stats() -> Option<CacheStats> {
+        Some(VERIFICATION_CACHE_METRICS.stats())
+    }
+
+    #[cfg_attr(not(feature = "stats"), allow(dead_code))]
+    pub(crate) fn invalid_uri_count() -> usize {
+        INVALID_URI_COUNT.load(Ordering::Relaxed)
+    }
+
+    /// A cached `verify_uri` result; invalid URIs are kept apart from other
+    /// blocked ones so repeats are still counted.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum CachedVerdict {
+        Allowed,
+        Blocked,
+        InvalidUri,
+    }
+
+    impl From<bool> for CachedVerdict {
+        #[inline]
+        fn from(allowed: bool) -> Self {
+            if allowed {
+                Self::Allowed
+            } else {
+                Self::Blocked
+            }
+        }
+    }
 
     pub struct PolicyVerifier {
         policy: CspPolicy,
         origin: Option<Url>,
         url_cache: HashMap<String, Url>,
-        verification_cache: lru::LruCache<u64, bool>,
+        verification_cache: crate::collections::LruCache<u64, CachedVerdict>,
+        invalid_uris: AtomicUsize,
+        parser: Arc<dyn UrlParser>,
     }
 
     impl PolicyVerifier {
         #[inline]
         pub fn new(policy: CspPolicy) -> Self {
+            VERIFICATION_CACHE_METRICS.add_occupancy(0, VERIFICATION_CACHE_ENTRIES);
             Self {
                 policy,
                 origin: None,
                 url_cache: HashMap::with_capacity(256),
-                verification_cache: lru::LruCache::new(std::num::NonZeroUsize::new(512).unwrap()),
+                verification_cache: crate::collections::LruCache::new(
+                    std::num::NonZeroUsize::new(VERIFICATION_CACHE_ENTRIES).unwrap(),
+                ),
+                invalid_uris: AtomicUsize::new(0),
+                parser: Arc::new(DefaultUrlParser),
             }
         }
 
+        /// Parses the URIs to check with `parser` instead of [`Url::parse`].
+        /// Origins given to [`set_origin`](Self::set_origin) are still parsed
+        /// with `Url::parse`.
+        pub fn with_url_parser(mut self, parser: impl UrlParser) -> Self {
+            self.parser = Arc::new(parser);
+            self.clear_caches();
+            self
+        }
+
+        #[inline]
+        pub(crate) fn parse_uri(&self, uri: &str) -> Option<Url> {
+            self.parser.parse(uri)
+        }
+
         pub fn with_origin(policy: CspPolicy, origin: impl AsRef<str>) -> Result<Self, CspError> {
             let mut verifier = Self::new(policy);
             verifier.set_origin(origin)?;
@@ -42,10 +426,20 @@ mod imp {
             })?;
 
             self.origin = Some(parsed_origin);
-            self.verification_cache.clear();
+            self.clear_verification_cache();
             Ok(())
         }
 
+        /// Returns `true` if the policy allows loading `uri` under
+        /// `directive_name`, falling back to `default-src`.
+        ///
+        /// A URI that does not parse is blocked, whatever the policy says, and
+        /// the verdict is cached like any other. Such URIs are counted in
+        /// [`invalid_uri_count`](Self::invalid_uri_count) and, across all
+        /// verifiers, in [`StatsSnapshot::invalid_uri_count`]. The other
+        /// checks on a single URI treat them the same way.
+        ///
+        /// [`StatsSnapshot::invalid_uri_count`]: crate::monitoring::StatsSnapshot::invalid_uri_count
         pub fn verify_uri(&mut self, uri: &str, directive_name: &str) -> Result<bool, CspError> {
             let cache_key = {
                 let mut hasher = rustc_hash::FxHasher::default();
@@ -54,37 +448,42 @@ mod imp {
                 std::hash::Hasher::finish(&hasher)
             };
 
-            if let Some(&cached_result) = self.verification_cache.get(&cache_key) {
-                return Ok(cached_result);
-            }
-
-            let directive = match self.policy.get_directive(directive_name) {
-                Some(d) => d,
-                None => {
-                    if directive_name != "default-src" {
-                        return self.verify_uri(uri, "default-src");
-                    } else {
-                        let result = true;
-                        self.verification_cache.put(cache_key, result);
-                        return Ok(result);
-                    }
+            if let Some(&cached) = self.verification_cache.get(&cache_key) {
+                VERIFICATION_CACHE_METRICS.record_hit();
+                if cached == CachedVerdict::InvalidUri {
+                    self.record_invalid_uri();
                 }
-            };
+                return Ok(cached == CachedVerdict::Allowed);
+            }
+            VERIFICATION_CACHE_METRICS.record_miss();
 
             let parsed_url = if let Some(cached) = self.url_cache.get(uri) {
                 cached.clone()
             } else {
-                match Url::parse(uri) {
-                    Ok(url) => {
+                match self.parse_uri(uri) {
+                    Some(url) => {
                         if self.url_cache.len() < 256 {
                             self.url_cache.insert(uri.to_string(), url.clone());
                         }
                         url
                     }
-                    Err(_) => {
-                        let result = false;
-                        self.verification_cache.put(cache_key, result);
-                        return Err(CspError::VerificationError(format!("Invalid URI: {uri}")));
+                    None => {
+                        self.record_invalid_uri();
+                        self.cache_verdict(cache_key, CachedVerdict::InvalidUri);
+                        return Ok(false);
+                    }
+                }
+            };
+
+            let directive = match self.policy.get_directive(directive_name) {
+                Some(d) => d,
+                None => {
+                    if directive_name != "default-src" {
+                        return self.verify_uri(uri, "default-src");
+                    } else {
+                        let result = true;
+                        self.cache_result(cache_key, result);
+                        return Ok(result);
                     }
                 }
             };
@@ -96,7 +495,7 @@ mod imp {
                 .collect::<Vec<_>>();
             if sources.iter().any(|s| s.is_none()) {
                 let result = false;
-                self.verification_cache.put(cache_key, result);
+                self.cache_result(cache_key, result);
                 return Ok(result);
             }
 
@@ -109,7 +508,7 @@ mod imp {
                     .any(|source| source.contains_nonce() || source.contains_hash())
             {
                 let result = false;
-                self.verification_cache.put(cache_key, result);
+                self.cache_result(cache_key, result);
                 return Ok(result);
             }
 
@@ -119,36 +518,30 @@ mod imp {
                 match source {
                     Source::None => {
                         let result = false;
-                        self.verification_cache.put(cache_key, result);
+                        self.cache_result(cache_key, result);
                         return Ok(result);
                     }
-                    Source::Self_ => {
-                        if self.is_same_origin(&parsed_url) {
-                            let result = true;
-                            self.verification_cache.put(cache_key, result);
-                            return Ok(result);
-                        }
+                    Source::Self_ if self.is_same_origin(&parsed_url) => {
+                        let result = true;
+                        self.cache_result(cache_key, result);
+                        return Ok(result);
                     }
-                    Source::Host(host) => {
-                        if self.match_host_source(&parsed_url, host) {
-                            let result = true;
-                            self.verification_cache.put(cache_key, result);
-                            return Ok(result);
-                        }
+                    Source::Host(host) if self.match_host_source(&parsed_url, host) => {
+                        let result = true;
+                        self.cache_result(cache_key, result);
+                        return Ok(result);
                     }
-                    Source::Scheme(scheme) => {
-                        if uri_scheme == scheme.as_ref() {
-                            let result = true;
-                            self.verification_cache.put(cache_key, result);
-                            return Ok(result);
-                        }
+                    Source::Scheme(scheme) if uri_scheme == scheme.as_ref() => {
+                        let result = true;
+                        self.cache_result(cache_key, result);
+                        return Ok(result);
                     }
                     _ => {}
                 }
             }
 
             let result = false;
-            self.verification_cache.put(cache_key, result);
+            self.cache_result(cache_key, result);
             Ok(result)
         }
 
@@ -168,13 +561,10 @@ mod imp {
                 return Ok(false);
             }
 
-            for source in directive.sources() {
-                if let Source::Hash { algorithm, value } = source {
-                    let calculated =
-                        crate::security::hash::HashGenerator::generate(*algorithm, content);
-                    if calculated == value.as_ref() {
-                        return Ok(true);
-                    }
+            for (value, algorithm) in directive.hashes() {
+                let calculated = crate::security::hash::HashGenerator::generate(algorithm, content);
+                if calculated == value {
+                    return Ok(true);
                 }
             }
 
@@ -197,84 +587,301 @@ mod imp {
                 return Ok(false);
             }
 
-            for source in directive.sources() {
-                if let Source::Nonce(expected_nonce) = source {
-                    if nonce == expected_nonce.as_ref() {
-                        return Ok(true);
-                    }
+            Ok(directive.nonces().any(|expected| expected == nonce))
+        }
+
+        /// Checks every resource against the policy and reports which source allowed
+        /// it, which resources would be blocked and which allowances went unused.
+        ///
+        /// Only location-based sources (`'self'`, hosts and schemes) of `*-src`
+        /// directives are considered for the unused list; keywords, nonces and hashes
+        /// govern inline content and are never reported. Resources with an
+        /// unparseable URL are reported as blocked and counted like those given
+        /// to [`verify_uri`](Self::verify_uri).
+        pub fn coverage_report(&self, resources: &[ResourceRef]) -> CoverageReport {
+            let mut used: Vec<(&str, &Source)> = Vec::new();
+            let mut coverage = Vec::with_capacity(resources.len());
+
+            for resource in resources {
+                let url = self.parse_uri(&resource.url);
+                if url.is_none() {
+                    self.record_invalid_uri();
                 }
+                let (effective_directive, granted_by, allowed) = match self
+                    .policy
+                    .get_directive(&resource.directive)
+                    .or_else(|| self.policy.get_directive("default-src"))
+                {
+                    None => (None, None, url.is_some()),
+                    Some(directive) => {
+                        let granted_by = url
+                            .as_ref()
+                            .and_then(|url| self.granting_source(url, directive));
+                        if let Some(source) = granted_by {
+                            used.push((directive.name(), source));
+                        }
+                        (
+                            Some(directive.name().to_string()),
+                            granted_by.cloned(),
+                            granted_by.is_some(),
+                        )
+                    }
+                };
+
+                coverage.push(ResourceCoverage {
+                    resource: resource.clone(),
+                    effective_directive,
+                    allowed,
+                    granted_by,
+                });
             }
 
-            Ok(false)
+            let unused_sources = self
+                .policy
+                .directives()
+                .filter(|directive| directive.name().ends_with("-src"))
+                .flat_map(|directive| {
+                    directive
+                        .sources()
+                        .iter()
+                        .filter(|source| {
+                            matches!(source, Source::Self_ | Source::Host(_) | Source::Scheme(_))
+                        })
+                        .filter(|source| !used.contains(&(directive.name(), *source)))
+                        .map(|source| (directive.name().to_string(), source.clone()))
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+
+            CoverageReport {
+                resources: coverage,
+                unused_sources,
+            }
         }
 
-        #[inline]
-        fn is_same_origin(&self, url: &Url) -> bool {
-            if let Some(origin) = &self.origin {
-                return url.scheme() == origin.scheme()
-                    && url.host_str() == origin.host_str()
-                    && url.port_or_known_default() == origin.port_or_known_default();
-            }
+        /// Like [`verify_uri`](Self::verify_uri), reporting which source allowed
+        /// the URI and how specific it is, so allowances that only hold because
+        /// of a bare `https:` or `*` can be flagged. Results are not cached.
+        pub fn verify_uri_detailed(
+            &self,
+            uri: &str,
+            directive_name: &str,
+        ) -> Result<MatchDetail, CspError> {
+            let directive = self
+                .policy
+                .get_directive(directive_name)
+                .or_else(|| self.policy.get_directive("default-src"));
+            let Some(url) = self.parse_uri(uri) else {
+                self.record_invalid_uri();
+                return Ok(MatchDetail {
+                    allowed: false,
+                    effective_directive: directive.map(|directive| directive.name().to_string()),
+                    matched_source: None,
+                    specificity: None,
+                    insecure_scheme: false,
+                });
+            };
+            let insecure_scheme = matches!(url.scheme(), "http" | "ws");
+
+            let Some(directive) = directive else {
+                return Ok(MatchDetail {
+                    allowed: true,
+                    effective_directive: None,
+                    matched_source: None,
+                    specificity: Some(MatchSpecificity::Unrestricted),
+                    insecure_scheme,
+                });
+            };
 
-            false
+            let matched = self.granting_source(&url, directive);
+            Ok(MatchDetail {
+                allowed: matched.is_some(),
+                effective_directive: Some(directive.name().to_string()),
+                specificity: matched.map(specificity),
+                matched_source: matched.cloned(),
+                insecure_scheme: insecure_scheme && matched.is_some(),
+            })
         }
 
-        #[inline]
-        fn match_host_source(&self, url: &Url, source: &str) -> bool {
-            let (host_part, path_part) = split_host_source(source);
-            let (host_pattern, expected_port) = split_host_port(host_part);
+        /// Traces how `uri` is evaluated under `directive_name`: the directive
+        /// consulted after fallback and, for each source tested, why it did or
+        /// did not match. Nothing is enforced or cached, so this also answers
+        /// what a report-only policy would block.
+        pub fn explain(&self, uri: &str, directive_name: &str) -> Result<Explanation, CspError> {
+            let directive = self
+                .policy
+                .get_directive(directive_name)
+                .or_else(|| self.policy.get_directive("default-src"));
+            let mut explanation = Explanation {
+                uri: uri.to_string(),
+                requested_directive: directive_name.to_string(),
+                effective_directive: directive.map(|directive| directive.name().to_string()),
+                verdict: Verdict::Unrestricted,
+                checks: Vec::new(),
+            };
 
-            if !self.match_host(url, host_pattern) {
-                return false;
-            }
+            let Some(url) = self.parse_uri(uri) else {
+                self.record_invalid_uri();
+                explanation.verdict = Verdict::InvalidUri;
+                return Ok(explanation);
+            };
+            let Some(directive) = directive else {
+                return Ok(explanation);
+            };
 
-            if let Some(expected_port) = expected_port {
-                let actual_port = url.port_or_known_default();
-                if expected_port != "*" && actual_port != expected_port.parse::<u16>().ok() {
-                    return false;
-                }
-            }
+            let checks = &mut explanation.checks;
+            explanation.verdict = match self.grant(&url, directive, |source, outcome| {
+                checks.push(SourceCheck {
+                    source: source.clone(),
+                    outcome,
+                });
+            }) {
+                Grant::NoneKeyword => Verdict::NoneKeyword,
+                Grant::StrictDynamic => Verdict::StrictDynamic,
+                Grant::Source(_) => Verdict::Matched,
+                Grant::NoMatch => Verdict::NoMatch,
+            };
+            Ok(explanation)
+        }
 
-            if let Some(path_part) = path_part {
-                return url.path().starts_with(path_part);
+        fn check_source(&self, url: &Url, source: &Source) -> CheckOutcome {
+            match source {
+                Source::Self_ => match &self.origin {
+                    None => CheckOutcome::NoOrigin,
+                    Some(_) if self.is_same_origin(url) => CheckOutcome::Matched,
+                    Some(_) => CheckOutcome::NotSameOrigin,
+                },
+                Source::Host(host) => match host.parse::<HostSource>() {
+                    Ok(host) => match host.mismatch(url, self.origin.as_ref().map(Url::scheme)) {
+                        None => CheckOutcome::Matched,
+                        Some(HostMismatch::Scheme) => CheckOutcome::SchemeMismatch,
+                        Some(HostMismatch::Host) => CheckOutcome::HostMismatch,
+                        Some(HostMismatch::Port) => CheckOutcome::PortMismatch,
+                        Some(HostMismatch::Path) => CheckOutcome::PathMismatch,
+                    },
+                    Err(_) => CheckOutcome::InvalidSource,
+                },
+                Source::Scheme(scheme) if url.scheme() == scheme.as_ref() => CheckOutcome::Matched,
+                Source::Scheme(_) => CheckOutcome::SchemeMismatch,
+                _ => CheckOutcome::NotApplicable,
             }
+        }
 
-            true
+        fn granting_source<'a>(
+            &self,
+            url: &Url,
+            directive: &'a crate::core::directives::Directive,
+        ) -> Option<&'a Source> {
+            match self.grant(url, directive, |_, _| {}) {
+                Grant::Source(source) => Some(source),
+                _ => None,
+            }
         }
 
-        #[inline]
-        fn match_host(&self, url: &Url, host: &str) -> bool {
-            let url_host = match url.host_str() {
-                Some(h) => h,
-                None => return false,
-            };
+        /// Decides whether `directive` lets `url` load, passing each source
+        /// checked and its outcome to `checked` in order.
+        fn grant<'a>(
+            &self,
+            url: &Url,
+            directive: &'a crate::core::directives::Directive,
+            mut checked: impl FnMut(&'a Source, CheckOutcome),
+        ) -> Grant<'a> {
+            let sources = directive
+                .sources()
+                .iter()
+                .chain(directive.fallback_sources().into_iter().flatten());
+
+            if sources.clone().any(|s| s.is_none()) {
+                return Grant::NoneKeyword;
+            }
 
-            if url_host == host {
-                return true;
+            if directive.name().starts_with("script-src")
+                && sources.clone().any(|s| matches!(s, Source::StrictDynamic))
+                && sources
+                    .clone()
+                    .any(|s| s.contains_nonce() || s.contains_hash())
+            {
+                return Grant::StrictDynamic;
             }
 
-            if let Some(domain) = host.strip_prefix("*.") {
-                if url_host.len() > domain.len() && url_host.ends_with(domain) {
-                    let split_index = url_host.len() - domain.len() - 1;
-                    return url_host.as_bytes().get(split_index) == Some(&b'.');
+            for source in sources {
+                let outcome = self.check_source(url, source);
+                checked(source, outcome);
+                if outcome.is_match() {
+                    return Grant::Source(source);
                 }
             }
+            Grant::NoMatch
+        }
+
+        #[inline]
+        fn is_same_origin(&self, url: &Url) -> bool {
+            if let Some(origin) = &self.origin {
+                return url.scheme() == origin.scheme()
+                    && url.host_str() == origin.host_str()
+                    && url.port_or_known_default() == origin.port_or_known_default();
+            }
 
             false
         }
 
+        #[inline]
+        fn match_host_source(&self, url: &Url, source: &str) -> bool {
+            source.parse::<HostSource>().is_ok_and(|host_source| {
+                host_source.matches(url, self.origin.as_ref().map(Url::scheme))
+            })
+        }
+
         #[inline]
         pub fn policy(&self) -> &CspPolicy {
             &self.policy
         }
 
+        /// Gives mutable access to the policy, dropping the cached verification
+        /// results so none computed under the old policy are served afterwards.
         #[inline]
         pub fn policy_mut(&mut self) -> &mut CspPolicy {
+            self.clear_verification_cache();
             &mut self.policy
         }
 
         pub fn clear_caches(&mut self) {
             self.url_cache.clear();
+            self.clear_verification_cache();
+        }
+
+        /// URIs given to this verifier that did not parse, counting repeats
+        /// answered from the cache.
+        #[inline]
+        pub fn invalid_uri_count(&self) -> usize {
+            self.invalid_uris.load(Ordering::Relaxed)
+        }
+
+        #[inline]
+        fn record_invalid_uri(&self) {
+            self.invalid_uris.fetch_add(1, Ordering::Relaxed);
+            INVALID_URI_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[inline]
+        fn cache_result(&mut self, key: u64, result: bool) {
+            self.cache_verdict(key, result.into());
+        }
+
+        fn cache_verdict(&mut self, key: u64, result: CachedVerdict) {
+            let cache = &mut self.verification_cache;
+            if !cache.contains(&key) {
+                if cache.len() >= cache.cap().get() {
+                    VERIFICATION_CACHE_METRICS.record_evictions(1);
+                } else {
+                    VERIFICATION_CACHE_METRICS.add_occupancy(1, 0);
+                }
+            }
+            cache.put(key, result);
+        }
+
+        fn clear_verification_cache(&mut self) {
+            VERIFICATION_CACHE_METRICS.remove_occupancy(self.verification_cache.len(), 0);
             self.verification_cache.clear();
         }
 
@@ -311,24 +918,16 @@ mod imp {
                 }
 
                 if let Some(nonce_value) = nonce {
-                    if directive.sources().iter().any(|s| {
-                        if let Source::Nonce(expected) = s {
-                            expected.as_ref() == nonce_value
-                        } else {
-                            false
-                        }
-                    }) {
+                    if directive.nonces().any(|expected| expected == nonce_value) {
                         return Ok(true);
                     }
                 }
 
-                for source in directive.sources() {
-                    if let Source::Hash { algorithm, value } = source {
-                        let calculated =
-                            crate::security::hash::HashGenerator::generate(*algorithm, content);
-                        if calculated == value.as_ref() {
-                            return Ok(true);
-                        }
+                for (value, algorithm) in directive.hashes() {
+                    let calculated =
+                        crate::security::hash::HashGenerator::generate(algorithm, content);
+                    if calculated == value {
+                        return Ok(true);
                     }
                 }
 
@@ -371,24 +970,16 @@ mod imp {
                 }
 
                 if let Some(nonce_value) = nonce {
-                    if directive.sources().iter().any(|s| {
-                        if let Source::Nonce(expected) = s {
-                            expected.as_ref() == nonce_value
-                        } else {
-                            false
-                        }
-                    }) {
+                    if directive.nonces().any(|expected| expected == nonce_value) {
                         return Ok(true);
                     }
                 }
 
-                for source in directive.sources() {
-                    if let Source::Hash { algorithm, value } = source {
-                        let calculated =
-                            crate::security::hash::HashGenerator::generate(*algorithm, content);
-                        if calculated == value.as_ref() {
-                            return Ok(true);
-                        }
+                for (value, algorithm) in directive.hashes() {
+                    let calculated =
+                        crate::security::hash::HashGenerator::generate(algorithm, content);
+                    if calculated == value {
+                        return Ok(true);
                     }
                 }
 
@@ -437,30 +1028,44 @@ mod imp {
         }
     }
 
-    fn split_host_source(source: &str) -> (&str, Option<&str>) {
-        match source.find('/') {
-            Some(index) => (&source[..index], Some(&source[index..])),
-            None => (source, None),
+    fn specificity(source: &Source) -> MatchSpecificity {
+        match source {
+            Source::Self_ => MatchSpecificity::SelfOrigin,
+            Source::Scheme(_) => MatchSpecificity::Scheme,
+            Source::Host(host) => match host.parse::<HostSource>() {
+                Ok(host) if host.host == "*" => MatchSpecificity::AnyHost,
+                Ok(host) if host.host.starts_with("*.") => MatchSpecificity::WildcardSubdomain,
+                Ok(host) if host.path.as_deref().is_some_and(|path| path != "/") => {
+                    MatchSpecificity::Path
+                }
+                _ => MatchSpecificity::Host,
+            },
+            _ => MatchSpecificity::Host,
         }
     }
 
-    fn split_host_port(host: &str) -> (&str, Option<&str>) {
-        if let Some(index) = host.rfind(':') {
-            let port_candidate = &host[index + 1..];
-            if !port_candidate.is_empty()
-                && (port_candidate == "*" || port_candidate.chars().all(|ch| ch.is_ascii_digit()))
-            {
-                return (&host[..index], Some(port_candidate));
-            }
+    impl Drop for PolicyVerifier {
+        fn drop(&mut self) {
+            VERIFICATION_CACHE_METRICS
+                .remove_occupancy(self.verification_cache.len(), VERIFICATION_CACHE_ENTRIES);
         }
-
-        (host, None)
     }
 }
 
 #[cfg(not(feature = "verify"))]
 mod imp {
     use super::*;
+    use crate::monitoring::cache::CacheStats;
+
+    #[cfg_attr(not(feature = "stats"), allow(dead_code))]
+    pub(crate) fn verification_cache_stats() -> Option<CacheStats> {
+        None
+    }
+
+    #[cfg_attr(not(feature = "stats"), allow(dead_code))]
+    pub(crate) fn invalid_uri_count() -> usize {
+        0
+    }
 
     pub struct PolicyVerifier {
         policy: CspPolicy,
@@ -493,6 +1098,41 @@ mod imp {
         #[inline]
         pub fn clear_caches(&mut self) {}
 
+        #[inline]
+        pub fn with_url_parser(self, _parser: impl UrlParser) -> Self {
+            self
+        }
+
+        #[inline]
+        pub(crate) fn parse_uri(&self, uri: &str) -> Option<Url> {
+            DefaultUrlParser.parse(uri)
+        }
+
+        #[inline]
+        pub fn invalid_uri_count(&self) -> usize {
+            0
+        }
+
+        #[inline]
+        pub fn verify_uri_detailed(
+            &self,
+            _uri: &str,
+            _directive_name: &str,
+        ) -> Result<MatchDetail, CspError> {
+            Err(CspError::ConfigError(
+                "Policy verification is disabled. Rebuild with the `verify` feature enabled."
+                    .to_string(),
+            ))
+        }
+
+        #[inline]
+        pub fn explain(&self, _uri: &str, _directive_name: &str) -> Result<Explanation, CspError> {
+            Err(CspError::ConfigError(
+                "Policy verification is disabled. Rebuild with the `verify` feature enabled."
+                    .to_string(),
+            ))
+        }
+
         #[inline]
         pub fn verify_uri(&mut self, _uri: &str, _directive_name: &str) -> Result<bool, CspError> {
             Err(CspError::ConfigError(
@@ -521,6 +1161,21 @@ mod imp {
             ))
         }
 
+        pub fn coverage_report(&self, resources: &[ResourceRef]) -> CoverageReport {
+            CoverageReport {
+                resources: resources
+                    .iter()
+                    .map(|resource| ResourceCoverage {
+                        resource: resource.clone(),
+                        effective_directive: None,
+                        allowed: false,
+                        granted_by: None,
+                    })
+                    .collect(),
+                unused_sources: Vec::new(),
+            }
+        }
+
         #[inline]
         pub fn verify_inline_script(
             &self,
@@ -584,3 +1239,5 @@ mod imp {
 }
 
 pub use imp::PolicyVerifier;
+#[cfg_attr(not(feature = "stats"), allow(unused_imports))]
+pub(crate) use imp::{invalid_uri_count, verification_cache_stats};
@@ -0,0 +1,450 @@
+//! Helpers for writing CSP assertions in application tests.
+//!
+//! ```rust
+//! use actix_web::{test, web, App, HttpResponse};
+//! use actix_web_csp::{assert_csp_contains, csp_middleware, test::TestPolicy};
+//!
+//! # actix_web::rt::System::new().block_on(async {
+//! let app = test::init_service(
+//!     App::new()
+//!         .wrap(csp_middleware(TestPolicy::default().into()))
+//!         .route("/", web::get().to(HttpResponse::Ok)),
+//! )
+//! .await;
+//!
+//! let res = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+//! assert_csp_contains!(res, "default-src 'self'");
+//! # });
+//! ```
+
+use crate::constants::{HEADER_CSP, HEADER_CSP_REPORT_ONLY, NONCE_PREFIX};
+use crate::core::policy::{CspPolicy, CspPolicyBuilder};
+use crate::core::source::Source;
+use crate::middleware::csp::CspMiddleware;
+use crate::monitoring::report::CspViolationReport;
+use actix_web::http::header::{HeaderMap, CONTENT_TYPE};
+use actix_web::test::TestRequest;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Asserts that a response carries a CSP header containing the given directive.
+///
+/// The expectation is a single directive (`"script-src 'self' cdn.example.com"`).
+/// It matches when the header has a directive of that name listing every expected
+/// source, in any order and possibly alongside others. The response can be anything
+/// with a `headers()` method returning a [`HeaderMap`], such as `ServiceResponse`
+/// or `HttpResponse`.
+#[macro_export]
+macro_rules! assert_csp_contains {
+    ($response:expr, $expected:expr $(,)?) => {{
+        let expected: &str = $expected;
+        match $crate::test::csp_header(($response).headers()) {
+            Some(header) => assert!(
+                $crate::test::policy_contains(header, expected),
+                "CSP header `{}` does not contain `{}`",
+                header,
+                expected
+            ),
+            None => panic!("response has no Content-Security-Policy header"),
+        }
+    }};
+}
+
+/// Returns the enforced CSP header value, falling back to the report-only header.
+pub fn csp_header(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(HEADER_CSP)
+        .or_else(|| headers.get(HEADER_CSP_REPORT_ONLY))
+        .and_then(|value| value.to_str().ok())
+}
+
+/// Returns `true` if `header` has a directive named like the first token of
+/// `expected` that lists every remaining token of `expected`.
+pub fn policy_contains(header: &str, expected: &str) -> bool {
+    let mut expected_tokens = expected.split_whitespace();
+    let Some(expected_name) = expected_tokens.next() else {
+        return true;
+    };
+
+    header
+        .split(';')
+        .map(str::split_whitespace)
+        .filter_map(|mut tokens| tokens.next().map(|name| (name, tokens)))
+        .filter(|(name, _)| name.eq_ignore_ascii_case(expected_name))
+        .any(|(_, tokens)| {
+            let sources: Vec<&str> = tokens.collect();
+            expected_tokens
+                .clone()
+                .all(|token| sources.contains(&token))
+        })
+}
+
+/// Returns the first `nonce="..."` attribute value found in a response body.
+pub fn extract_nonce(body: impl AsRef<[u8]>) -> Option<String> {
+    let body = std::str::from_utf8(body.as_ref()).ok()?;
+    let start = body.find("nonce=\"")? + "nonce=\"".len();
+    let end = body[start..].find('"')? + start;
+    Some(body[start..end].to_string())
+}
+
+/// Returns the first `'nonce-...'` source of the CSP header.
+pub fn extract_header_nonce(headers: &HeaderMap) -> Option<String> {
+    let header = csp_header(headers)?;
+    let start = header.find(NONCE_PREFIX)? + NONCE_PREFIX.len();
+    let end = header[start..].find('\'')? + start;
+    Some(header[start..end].to_string())
+}
+
+/// A permissive policy that lets any test page load, while still emitting a header.
+#[derive(Debug, Clone)]
+pub struct TestPolicy(CspPolicy);
+
+impl Default for TestPolicy {
+    fn default() -> Self {
+        Self(
+            CspPolicyBuilder::new()
+                .default_src([
+                    Source::Self_,
+                    Source::Host(Cow::Borrowed("*")),
+                    Source::Scheme(Cow::Borrowed("data")),
+                    Source::Scheme(Cow::Borrowed("blob")),
+                    Source::UnsafeInline,
+                    Source::UnsafeEval,
+                ])
+                .build_unchecked(),
+        )
+    }
+}
+
+impl TestPolicy {
+    #[inline]
+    pub fn policy(&self) -> &CspPolicy {
+        &self.0
+    }
+
+    #[inline]
+    pub fn into_policy(self) -> CspPolicy {
+        self.0
+    }
+}
+
+impl From<TestPolicy> for CspPolicy {
+    #[inline]
+    fn from(policy: TestPolicy) -> Self {
+        policy.0
+    }
+}
+
+/// Builds a browser-style `application/csp-report` POST for reporting endpoints.
+#[derive(Debug, Clone)]
+pub struct ViolationReportBuilder {
+    report: CspViolationReport,
+}
+
+impl ViolationReportBuilder {
+    pub fn new(blocked_uri: impl Into<String>, violated_directive: impl Into<String>) -> Self {
+        let violated_directive = violated_directive.into();
+        Self {
+            report: CspViolationReport {
+                document_uri: "https://example.com/".to_string(),
+                blocked_uri: blocked_uri.into(),
+                effective_directive: violated_directive.clone(),
+                violated_directive,
+                disposition: "enforce".to_string(),
+                ..CspViolationReport::default()
+            },
+        }
+    }
+
+    pub fn document_uri(mut self, document_uri: impl Into<String>) -> Self {
+        self.report.document_uri = document_uri.into();
+        self
+    }
+
+    pub fn original_policy(mut self, original_policy: impl Into<String>) -> Self {
+        self.report.original_policy = original_policy.into();
+        self
+    }
+
+    pub fn report_only(mut self) -> Self {
+        self.report.disposition = "report".to_string();
+        self
+    }
+
+    #[inline]
+    pub fn report(&self) -> &CspViolationReport {
+        &self.report
+    }
+
+    /// Returns a POST request to `path` carrying the report as its body.
+    pub fn to_request(&self, path: &str) -> TestRequest {
+        let body = serde_json::json!({ "csp-report": self.report });
+        TestRequest::post()
+            .uri(path)
+            .insert_header((CONTENT_TYPE, "application/csp-report"))
+            .set_payload(body.to_string())
+    }
+}
+
+const PROPAGATION_PROBE_PATH: &str = "/__csp_propagation_probe";
+
+/// Checks that a policy update reaches every worker of a multi-worker server.
+///
+/// Each worker of an `HttpServer` builds its own `App` from the factory, so a
+/// middleware whose configuration is created inside the factory, rather than
+/// cloned from one shared [`CspConfig`], silently ignores runtime updates on
+/// all but one worker. [`assert_propagates`](Self::assert_propagates) starts
+/// a real server on a local port with the given middleware factory, waits
+/// until every worker has answered, applies the update and then keeps
+/// requesting until every worker sends a header containing the expected
+/// directive, panicking when the deadline passes first. Starting the workers
+/// has a deadline of its own, so a slow machine does not eat into the time
+/// given to the update.
+///
+/// ```rust,no_run
+/// use actix_web_csp::test::PolicyPropagationCheck;
+/// use actix_web_csp::{CspConfig, CspMiddleware, CspPolicyBuilder, Source};
+///
+/// let config = CspConfig::new(
+///     CspPolicyBuilder::new()
+///         .script_src([Source::Self_])
+///         .build_unchecked(),
+/// );
+///
+/// let factory_config = config.clone();
+/// PolicyPropagationCheck::new(move || CspMiddleware::new(factory_config.clone()))
+///     .with_workers(4)
+///     .assert_propagates(
+///         || {
+///             config.update_policy(|policy| {
+///                 policy.append_source("script-src", Source::Host("cdn.example.com".into()));
+///             });
+///         },
+///         "script-src 'self' cdn.example.com",
+///     );
+/// ```
+///
+/// [`CspConfig`]: crate::core::CspConfig
+pub struct PolicyPropagationCheck<F> {
+    factory: F,
+    workers: usize,
+    startup_deadline: Duration,
+    deadline: Duration,
+}
+
+/// Outcome of a successful [`PolicyPropagationCheck`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PropagationReport {
+    /// Workers that answered before the update and sent the new header after it
+    pub workers: usize,
+    /// Time from the update until the last worker sent the new header
+    pub elapsed: Duration,
+    /// Requests sent after the update
+    pub probes: usize,
+}
+
+impl<F> PolicyPropagationCheck<F>
+where
+    F: Fn() -> CspMiddleware + Send + Clone + 'static,
+{
+    /// A check against 4 workers that allows 30 seconds for them to start and
+    /// 5 seconds for the update to show.
+    #[inline]
+    pub fn new(factory: F) -> Self {
+        Self {
+            factory,
+            workers: 4,
+            startup_deadline: Duration::from_secs(30),
+            deadline: Duration::from_secs(5),
+        }
+    }
+
+    #[inline]
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// How long the workers get to pick up the update.
+    #[inline]
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// How long every worker gets to answer once before the update is applied.
+    #[inline]
+    pub fn with_startup_deadline(mut self, deadline: Duration) -> Self {
+        self.startup_deadline = deadline;
+        self
+    }
+
+    /// Runs the check, calling `update` once every worker has answered.
+    ///
+    /// `expected` is matched like [`assert_csp_contains!`](crate::assert_csp_contains).
+    ///
+    /// # Panics
+    ///
+    /// If the server does not start, a worker never answers, or a worker
+    /// still lacks `expected` when the deadline passes. The server is stopped
+    /// first, also when `update` panics.
+    pub fn assert_propagates(self, update: impl FnOnce(), expected: &str) -> PropagationReport {
+        let server = self.start();
+        let outcome = self.probe(server.addr, update, expected);
+
+        drop(server);
+        outcome.unwrap_or_else(|message| panic!("{message}"))
+    }
+
+    fn start(&self) -> ProbeServer {
+        let (sender, receiver) = mpsc::channel();
+        let factory = self.factory.clone();
+        let workers = self.workers;
+
+        let server = thread::spawn(move || {
+            actix_web::rt::System::new().block_on(async move {
+                let server = actix_web::HttpServer::new(move || {
+                    actix_web::App::new().wrap(factory()).route(
+                        PROPAGATION_PROBE_PATH,
+                        actix_web::web::get().to(|| async {
+                            actix_web::HttpResponse::Ok()
+                                .body(format!("{:?}", thread::current().id()))
+                        }),
+                    )
+                })
+                .workers(workers)
+                .bind(("127.0.0.1", 0));
+                let server = match server {
+                    Ok(server) => server,
+                    Err(error) => {
+                        let _ = sender.send(Err(error));
+                        return;
+                    }
+                };
+                let addr = server.addrs()[0];
+                let server = server.run();
+                let _ = sender.send(Ok((addr, server.handle())));
+                let _ = server.await;
+            })
+        });
+
+        match receiver.recv() {
+            Ok(Ok((addr, handle))) => ProbeServer {
+                addr,
+                handle,
+                thread: Some(server),
+            },
+            Ok(Err(error)) => panic!("propagation check server failed to start: {error}"),
+            Err(_) => panic!("propagation check server failed to start"),
+        }
+    }
+
+    fn probe(
+        &self,
+        addr: SocketAddr,
+        update: impl FnOnce(),
+        expected: &str,
+    ) -> Result<PropagationReport, String> {
+        let started = Instant::now();
+        let mut seen = HashSet::new();
+        while seen.len() < self.workers {
+            if started.elapsed() > self.startup_deadline {
+                return Err(format!(
+                    "only {} of {} workers answered within {:?}",
+                    seen.len(),
+                    self.workers,
+                    self.startup_deadline
+                ));
+            }
+            if let Ok((worker, _)) = probe_worker(addr) {
+                seen.insert(worker);
+            }
+        }
+
+        update();
+
+        let updated_at = Instant::now();
+        let mut stale: HashMap<String, Option<String>> =
+            seen.into_iter().map(|worker| (worker, None)).collect();
+        let mut updated = HashSet::new();
+        let mut probes = 0;
+        while updated.len() < self.workers {
+            if updated_at.elapsed() > self.deadline {
+                let stale = stale
+                    .iter()
+                    .filter(|(worker, _)| !updated.contains(*worker))
+                    .map(|(worker, header)| format!("{worker}: {header:?}"))
+                    .collect::<Vec<_>>();
+                return Err(format!(
+                    "{} of {} workers did not send `{expected}` within {:?}: {}",
+                    stale.len(),
+                    self.workers,
+                    self.deadline,
+                    stale.join(", ")
+                ));
+            }
+            probes += 1;
+            let Ok((worker, header)) = probe_worker(addr) else {
+                continue;
+            };
+            if header
+                .as_deref()
+                .is_some_and(|header| policy_contains(header, expected))
+            {
+                updated.insert(worker);
+            } else {
+                stale.insert(worker, header);
+            }
+        }
+
+        Ok(PropagationReport {
+            workers: self.workers,
+            elapsed: updated_at.elapsed(),
+            probes,
+        })
+    }
+}
+
+/// The server behind a [`PolicyPropagationCheck`], stopped when dropped.
+struct ProbeServer {
+    addr: SocketAddr,
+    handle: actix_web::dev::ServerHandle,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for ProbeServer {
+    fn drop(&mut self) {
+        futures::executor::block_on(self.handle.stop(false));
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Requests the probe route on a fresh connection, returning the answering
+/// worker and its CSP header.
+fn probe_worker(addr: SocketAddr) -> std::io::Result<(String, Option<String>)> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    write!(
+        stream,
+        "GET {PROPAGATION_PROBE_PATH} HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\n\r\n"
+    )?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| std::io::Error::other("incomplete probe response"))?;
+    let header = head.lines().skip(1).find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.eq_ignore_ascii_case(HEADER_CSP) || name.eq_ignore_ascii_case(HEADER_CSP_REPORT_ONLY))
+            .then(|| value.trim().to_string())
+    });
+    Ok((body.to_string(), header))
+}
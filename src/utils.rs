@@ -1,15 +1,47 @@
+use crate::constants::{DEFAULT_BUFFER_POOL_MIN_CAPACITY, DEFAULT_BUFFER_POOL_SIZE};
+use crate::monitoring::cache::{CacheMetrics, CacheStats};
 use bytes::BytesMut;
 use parking_lot::Mutex;
 use smallvec::SmallVec;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+static BUFFER_POOL_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_BUFFER_POOL_SIZE);
+static BUFFER_POOL_MIN_CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_BUFFER_POOL_MIN_CAPACITY);
+static BUFFER_POOL_METRICS: CacheMetrics = CacheMetrics::new();
+
+/// Sets how many buffers each thread keeps for serializing policies, and the
+/// smallest capacity worth keeping; `size` 0 turns pooling off.
+///
+/// The setting is process-wide: every policy and configuration serializes
+/// through the same per-thread pools, so call it once at startup. Defaults
+/// are 8 buffers of at least 512 bytes.
+///
+/// ```rust
+/// use actix_web_csp::utils::configure_buffer_pool;
+///
+/// configure_buffer_pool(16, 1024);
+/// ```
+pub fn configure_buffer_pool(size: usize, min_capacity: usize) {
+    BUFFER_POOL_SIZE.store(size, Ordering::Relaxed);
+    BUFFER_POOL_MIN_CAPACITY.store(min_capacity, Ordering::Relaxed);
+}
+
+/// Buffers served from, allocated past and dropped by the serialization
+/// pools of every thread since the process started; see
+/// [`configure_buffer_pool`].
+pub fn buffer_pool_stats() -> CacheStats {
+    CacheStats {
+        capacity: BUFFER_POOL_SIZE.load(Ordering::Relaxed),
+        ..BUFFER_POOL_METRICS.stats()
+    }
+}
+
+/// Per-thread pool of serialization buffers; `N` buffers are held inline.
 #[derive(Debug)]
 pub(crate) struct BytesCache<const N: usize> {
     buffers: SmallVec<[BytesMut; N]>,
-    hit_count: usize,
-    miss_count: usize,
 }
 
 impl<const N: usize> BytesCache<N> {
@@ -17,35 +49,45 @@ impl<const N: usize> BytesCache<N> {
     pub fn new() -> Self {
         Self {
             buffers: SmallVec::new(),
-            hit_count: 0,
-            miss_count: 0,
         }
     }
 
     #[inline]
     pub fn get(&mut self, capacity: usize) -> BytesMut {
         if let Some(mut buf) = self.buffers.pop() {
-            self.hit_count += 1;
+            BUFFER_POOL_METRICS.record_hit();
+            BUFFER_POOL_METRICS.remove_occupancy(1, 0);
             buf.clear();
             if buf.capacity() < capacity {
                 buf.reserve(capacity.saturating_sub(buf.capacity()));
             }
             buf
         } else {
-            self.miss_count += 1;
+            BUFFER_POOL_METRICS.record_miss();
             BytesMut::with_capacity(capacity.max(1024))
         }
     }
 
     #[inline]
     pub fn recycle(&mut self, mut buffer: BytesMut) {
-        if self.buffers.len() < N && buffer.capacity() >= 512 {
+        if self.buffers.len() < BUFFER_POOL_SIZE.load(Ordering::Relaxed)
+            && buffer.capacity() >= BUFFER_POOL_MIN_CAPACITY.load(Ordering::Relaxed)
+        {
             buffer.clear();
             self.buffers.push(buffer);
+            BUFFER_POOL_METRICS.add_occupancy(1, 0);
+        } else {
+            BUFFER_POOL_METRICS.record_evictions(1);
         }
     }
 }
 
+impl<const N: usize> Drop for BytesCache<N> {
+    fn drop(&mut self) {
+        BUFFER_POOL_METRICS.remove_occupancy(self.buffers.len(), 0);
+    }
+}
+
 impl<const N: usize> Default for BytesCache<N> {
     #[inline]
     fn default() -> Self {
@@ -85,6 +127,7 @@ impl<T> CachedValue<T> {
     }
 }
 
+use arc_swap::ArcSwap;
 use rustc_hash::FxHashMap;
 use std::sync::OnceLock;
 
@@ -123,18 +166,110 @@ static COMMON_STRINGS: &[&str] = &[
     "sandbox",
 ];
 
-static STRING_INTERN_MAP: OnceLock<FxHashMap<&'static str, &'static str>> = OnceLock::new();
+type InternMap = FxHashMap<&'static str, &'static str>;
 
-#[inline]
-pub fn intern_string(s: &str) -> Option<&'static str> {
-    let map = STRING_INTERN_MAP.get_or_init(|| {
+static STRING_INTERN_MAP: OnceLock<ArcSwap<InternMap>> = OnceLock::new();
+static INTERN_HITS: AtomicU64 = AtomicU64::new(0);
+static INTERN_MISSES: AtomicU64 = AtomicU64::new(0);
+
+fn intern_map() -> &'static ArcSwap<InternMap> {
+    STRING_INTERN_MAP.get_or_init(|| {
         let mut map = FxHashMap::with_capacity_and_hasher(COMMON_STRINGS.len(), Default::default());
         for &common in COMMON_STRINGS {
             map.insert(common, common);
         }
+        ArcSwap::from_pointee(map)
+    })
+}
+
+#[inline]
+pub fn intern_string(s: &str) -> Option<&'static str> {
+    let interned = intern_map().load().get(s).copied();
+    let counter = if interned.is_some() {
+        &INTERN_HITS
+    } else {
+        &INTERN_MISSES
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+    interned
+}
+
+/// Adds `strings`, typically the CDN and API hosts of an application's policy,
+/// to the table used by [`intern_string`].
+///
+/// Call it at startup: registered strings live for the rest of the process.
+/// Strings already in the table are skipped. Returns how many were added.
+///
+/// ```rust
+/// use actix_web_csp::utils::{intern_string, register_interned_strings};
+///
+/// register_interned_strings(["cdn.example.com", "api.example.com"]);
+/// assert_eq!(intern_string("cdn.example.com"), Some("cdn.example.com"));
+/// ```
+pub fn register_interned_strings<I, S>(strings: I) -> usize
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let map = intern_map();
+    let mut leaked: Vec<&'static str> = Vec::new();
+    {
+        let current = map.load();
+        for string in strings {
+            let string = string.as_ref();
+            if !current.contains_key(string) && !leaked.contains(&string) {
+                leaked.push(Box::leak(string.into()));
+            }
+        }
+    }
+    if leaked.is_empty() {
+        return 0;
+    }
+
+    let mut added = 0;
+    map.rcu(|current| {
+        let mut map = InternMap::clone(current);
+        added = 0;
+        for &string in &leaked {
+            if !map.contains_key(string) {
+                map.insert(string, string);
+                added += 1;
+            }
+        }
         map
     });
-    map.get(s).copied()
+    added
+}
+
+/// Lookup counts of [`intern_string`], for judging whether registering more
+/// strings with [`register_interned_strings`] would pay off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InternStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Strings in the table, built-in ones included
+    pub entries: usize,
+}
+
+impl InternStats {
+    #[inline]
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// Returns how often [`intern_string`] found its argument since the process started.
+pub fn intern_stats() -> InternStats {
+    InternStats {
+        hits: INTERN_HITS.load(Ordering::Relaxed),
+        misses: INTERN_MISSES.load(Ordering::Relaxed),
+        entries: intern_map().load().len(),
+    }
 }
 
 pub struct PooledItem<T> {
@@ -172,7 +307,7 @@ impl<T> Drop for PooledItem<T> {
     }
 }
 
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
 use std::arch::x86_64::*;
 
 #[allow(dead_code)]
@@ -241,7 +376,7 @@ impl FastStringBuilder {
         self.buffer.reserve(additional);
     }
 
-    #[cfg(target_arch = "x86_64")]
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
     #[target_feature(enable = "avx2")]
     #[allow(dead_code)]
     unsafe fn simd_copy_aligned(src: &[u8], dst: &mut [u8]) {
@@ -270,7 +405,7 @@ impl FastStringBuilder {
         let total_len: usize = sources.iter().map(|s| s.len()).sum();
         self.reserve(total_len);
 
-        #[cfg(target_arch = "x86_64")]
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
         {
             if is_x86_feature_detected!("avx2") && total_len >= 128 {
                 for &src in sources {
@@ -419,7 +554,7 @@ pub fn fast_string_compare(a: &str, b: &str) -> bool {
     let a_bytes = a.as_bytes();
     let b_bytes = b.as_bytes();
 
-    #[cfg(target_arch = "x86_64")]
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
     {
         if a_bytes.len() >= 32 && is_x86_feature_detected!("avx2") {
             return unsafe { simd_string_compare_avx2(a_bytes, b_bytes) };
@@ -429,7 +564,7 @@ pub fn fast_string_compare(a: &str, b: &str) -> bool {
     a_bytes == b_bytes
 }
 
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
 #[target_feature(enable = "avx2")]
 unsafe fn simd_string_compare_avx2(a: &[u8], b: &[u8]) -> bool {
     let len = a.len();
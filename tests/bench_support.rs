@@ -0,0 +1,86 @@
+#![cfg(feature = "bench-support")]
+
+use actix_web_csp::bench_support::{middleware_roundtrip, serialize_policy, MiddlewareHarness};
+use actix_web_csp::{
+    csp_middleware, CspConfigBuilder, CspMiddleware, CspPolicyBuilder, PolicyRoutes, Source,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_policy_matches_header_value() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .img_src([Source::Self_, Source::Scheme("data".into())])
+            .build_unchecked();
+
+        let value = serialize_policy(&policy).unwrap();
+        assert_eq!(value, "default-src 'self'; img-src 'self' data:");
+        assert_eq!(policy.clone().header_value().unwrap(), value.as_str());
+    }
+
+    #[actix_web::test]
+    async fn test_middleware_roundtrip_returns_policy_header() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .build_unchecked();
+
+        let res = middleware_roundtrip(&csp_middleware(policy)).await.unwrap();
+        assert_eq!(
+            res.headers().get("content-security-policy").unwrap(),
+            "default-src 'self'"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_middleware_harness_is_reusable() {
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .script_src([Source::Self_])
+                    .build_unchecked(),
+            )
+            .with_nonce_generator(16)
+            .with_nonce_per_request(true)
+            .build();
+        let harness = MiddlewareHarness::new(&CspMiddleware::new(config))
+            .await
+            .unwrap();
+
+        let first = harness.roundtrip().await.unwrap();
+        let second = harness.roundtrip().await.unwrap();
+        let header = |res: &actix_web::dev::ServiceResponse| {
+            res.headers()
+                .get("content-security-policy")
+                .unwrap()
+                .clone()
+        };
+        assert_ne!(header(&first), header(&second));
+    }
+
+    #[actix_web::test]
+    async fn test_middleware_harness_requests_configured_path() {
+        let base = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .build_unchecked();
+        let strict = CspPolicyBuilder::new()
+            .default_src([Source::None])
+            .build_unchecked();
+        let middleware =
+            csp_middleware(base).with_policy_routes(PolicyRoutes::new().prefix("/admin", strict));
+
+        let res = MiddlewareHarness::new(&middleware)
+            .await
+            .unwrap()
+            .with_path("/admin/users")
+            .roundtrip()
+            .await
+            .unwrap();
+        assert_eq!(
+            res.headers().get("content-security-policy").unwrap(),
+            "default-src 'none'"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use actix_web_csp::core::{
+    AllowlistEntry, AllowlistStore, DynamicAllowlist, JsonFileAllowlistStore, MemoryAllowlistStore,
+};
+use actix_web_csp::{CspConfig, CspError, CspPolicyBuilder, Source};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+fn config() -> CspConfig {
+    CspConfig::new(
+        CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_, Source::Host("cdn.example.com".into())])
+            .build_unchecked(),
+    )
+}
+
+fn directive(config: &CspConfig, name: &str) -> Option<String> {
+    config
+        .policy()
+        .get_directive(name)
+        .map(|directive| directive.to_string())
+}
+
+/// A store shared between allowlists, as a database would be across restarts.
+#[derive(Clone, Default)]
+struct SharedStore(Arc<MemoryAllowlistStore>);
+
+impl AllowlistStore for SharedStore {
+    fn load(&self, name: &str) -> Result<Vec<AllowlistEntry>, CspError> {
+        self.0.load(name)
+    }
+
+    fn save(&self, name: &str, entries: &[AllowlistEntry]) -> Result<(), CspError> {
+        self.0.save(name, entries)
+    }
+}
+
+struct FailingStore;
+
+impl AllowlistStore for FailingStore {
+    fn load(&self, _name: &str) -> Result<Vec<AllowlistEntry>, CspError> {
+        Ok(Vec::new())
+    }
+
+    fn save(&self, _name: &str, _entries: &[AllowlistEntry]) -> Result<(), CspError> {
+        Err(CspError::ConfigError("store offline".to_string()))
+    }
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "actix-web-csp-{name}-{}-{:?}.json",
+        std::process::id(),
+        std::thread::current().id()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowlist_adds_and_removes_hosts() {
+        let config = config();
+        let allowlist = DynamicAllowlist::new("marketing", &config);
+
+        allowlist.add("script-src", "tags.example.net").unwrap();
+        assert_eq!(
+            directive(&config, "script-src").unwrap(),
+            "script-src 'self' cdn.example.com tags.example.net"
+        );
+        assert_eq!(allowlist.hosts("script-src"), ["tags.example.net"]);
+
+        assert!(allowlist.remove("script-src", "tags.example.net").unwrap());
+        assert!(!allowlist.remove("script-src", "tags.example.net").unwrap());
+        assert_eq!(
+            directive(&config, "script-src").unwrap(),
+            "script-src 'self' cdn.example.com"
+        );
+    }
+
+    #[test]
+    fn test_allowlist_never_removes_hosts_the_policy_already_had() {
+        let config = config();
+        let allowlist = DynamicAllowlist::new("marketing", &config);
+
+        allowlist.add("script-src", "cdn.example.com").unwrap();
+        allowlist.remove("script-src", "cdn.example.com").unwrap();
+
+        assert_eq!(
+            directive(&config, "script-src").unwrap(),
+            "script-src 'self' cdn.example.com"
+        );
+    }
+
+    #[test]
+    fn test_allowlist_creates_directives_from_default_src() {
+        let config = config();
+        let allowlist = DynamicAllowlist::new("marketing", &config);
+
+        allowlist.add("img-src", "pixel.example.net").unwrap();
+        allowlist.add("img-src", "ads.example.net").unwrap();
+        assert_eq!(
+            directive(&config, "img-src").unwrap(),
+            "img-src 'self' pixel.example.net ads.example.net"
+        );
+
+        allowlist.remove("img-src", "pixel.example.net").unwrap();
+        assert_eq!(
+            directive(&config, "img-src").unwrap(),
+            "img-src 'self' ads.example.net"
+        );
+        allowlist.remove("img-src", "ads.example.net").unwrap();
+        assert_eq!(directive(&config, "img-src"), None);
+    }
+
+    #[test]
+    fn test_allowlist_expires_hosts() {
+        let config = config();
+        let allowlist = DynamicAllowlist::new("campaign", &config);
+
+        allowlist
+            .add_for("img-src", "pixel.example.net", Duration::from_millis(20))
+            .unwrap();
+        allowlist.add("img-src", "ads.example.net").unwrap();
+        assert_eq!(allowlist.expire().unwrap(), 0);
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(allowlist.hosts("img-src"), ["ads.example.net"]);
+        assert_eq!(allowlist.expire().unwrap(), 1);
+        assert_eq!(
+            directive(&config, "img-src").unwrap(),
+            "img-src 'self' ads.example.net"
+        );
+
+        allowlist
+            .add_for("img-src", "ads.example.net", Duration::ZERO)
+            .unwrap();
+        assert_eq!(allowlist.expire().unwrap(), 1);
+        assert_eq!(directive(&config, "img-src"), None);
+    }
+
+    #[test]
+    fn test_allowlist_rejects_non_host_sources() {
+        let allowlist = DynamicAllowlist::new("marketing", &config());
+
+        for host in ["'unsafe-inline'", "https:", ""] {
+            assert!(allowlist.add("script-src", host).is_err(), "{host}");
+        }
+        assert!(allowlist.entries().is_empty());
+    }
+
+    #[test]
+    fn test_allowlist_rejects_ttl_past_the_clock() {
+        let config = config();
+        let allowlist = DynamicAllowlist::new("campaign", &config);
+
+        assert!(matches!(
+            allowlist.add_for("img-src", "pixel.example.net", Duration::MAX),
+            Err(CspError::ConfigError(_))
+        ));
+        assert!(allowlist.entries().is_empty());
+        assert_eq!(directive(&config, "img-src"), None);
+    }
+
+    #[test]
+    fn test_allowlist_persists_and_reloads_entries() {
+        let store = SharedStore::default();
+        let expires_at = SystemTime::now() + Duration::from_secs(3600);
+        {
+            let allowlist = DynamicAllowlist::new("marketing", &config()).with_store(store.clone());
+            allowlist.add("connect-src", "api.example.net").unwrap();
+            allowlist
+                .add_for("img-src", "pixel.example.net", Duration::from_secs(3600))
+                .unwrap();
+        }
+
+        let saved = store.load("marketing").unwrap();
+        assert_eq!(saved.len(), 2);
+        assert!(saved[1].expires_at.unwrap() <= expires_at + Duration::from_secs(1));
+
+        let config = config();
+        let allowlist = DynamicAllowlist::new("marketing", &config).with_store(store.clone());
+        assert_eq!(allowlist.load().unwrap(), 2);
+        assert_eq!(
+            directive(&config, "connect-src").unwrap(),
+            "connect-src 'self' api.example.net"
+        );
+        assert!(store.load("other").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_allowlist_keeps_state_when_store_fails() {
+        let config = config();
+        let allowlist = DynamicAllowlist::new("marketing", &config).with_store(FailingStore);
+
+        assert!(matches!(
+            allowlist.add("script-src", "tags.example.net"),
+            Err(CspError::ConfigError(_))
+        ));
+        assert!(allowlist.entries().is_empty());
+        assert_eq!(
+            directive(&config, "script-src").unwrap(),
+            "script-src 'self' cdn.example.com"
+        );
+    }
+
+    #[test]
+    fn test_json_file_store_round_trips_several_allowlists() {
+        let path = temp_path("allowlist");
+        let _ = std::fs::remove_file(&path);
+        let store = JsonFileAllowlistStore::new(&path);
+        assert!(store.load("marketing").unwrap().is_empty());
+
+        let entry = AllowlistEntry {
+            directive: "script-src".to_string(),
+            host: "tags.example.net".to_string(),
+            expires_at: None,
+        };
+        store
+            .save("marketing", std::slice::from_ref(&entry))
+            .unwrap();
+        store.save("support", &[]).unwrap();
+
+        let reopened = JsonFileAllowlistStore::new(&path);
+        assert_eq!(reopened.load("marketing").unwrap(), [entry]);
+        assert!(reopened.load("support").unwrap().is_empty());
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(matches!(
+            reopened.load("marketing"),
+            Err(CspError::SerializationError(_))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_json_file_stores_sharing_a_path_save_concurrently() {
+        let path = temp_path("allowlist-shared");
+        let _ = std::fs::remove_file(&path);
+
+        let writers: Vec<_> = (0..4)
+            .map(|index| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let store = JsonFileAllowlistStore::new(&path);
+                    for _ in 0..20 {
+                        store.save(&format!("list-{index}"), &[]).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let prefix = path.file_name().unwrap().to_string_lossy().into_owned();
+        let leftovers = std::fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with(&prefix) && name.ends_with(".tmp"))
+            .count();
+        assert_eq!(leftovers, 0);
+        assert!(JsonFileAllowlistStore::new(&path).load("list-0").is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_allowlist_changes_are_recorded_in_history() {
+        let config = config();
+        let allowlist = DynamicAllowlist::new("marketing", &config);
+        let before = config.policy_history().len();
+
+        allowlist.add("script-src", "tags.example.net").unwrap();
+        allowlist.add("script-src", "tags.example.net").unwrap();
+
+        assert_eq!(config.policy_history().len(), before + 1);
+    }
+}
@@ -1,5 +1,9 @@
-use actix_web_csp::core::{CspConfig, CspConfigBuilder, CspPolicy, CspPolicyBuilder, Source};
-use actix_web_csp::security::NonceGenerator;
+use actix_web_csp::core::{
+    CspConfig, CspConfigBuilder, CspConfigSnapshot, CspPolicy, CspPolicyBuilder, Directive,
+    PolicyCacheKey, PolicyEvent, Source,
+};
+use actix_web_csp::security::{NonceGenerator, NonceScope, RequestId};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -70,9 +74,10 @@ mod tests {
             .with_nonce_per_request(true)
             .build();
 
-        let nonce1 = config.get_or_generate_request_nonce("request1");
-        let nonce2 = config.get_or_generate_request_nonce("request1");
-        let nonce3 = config.get_or_generate_request_nonce("request2");
+        let first = RequestId::next();
+        let nonce1 = config.get_or_generate_request_nonce(first);
+        let nonce2 = config.get_or_generate_request_nonce(first);
+        let nonce3 = config.get_or_generate_request_nonce(RequestId::next());
 
         assert!(nonce1.is_some());
         assert!(nonce2.is_some());
@@ -90,13 +95,15 @@ mod tests {
             .with_nonce_per_request(true)
             .build();
 
-        let _nonce = config.get_or_generate_request_nonce("request1");
+        let request_id = RequestId::next();
+        let _nonce = config.get_or_generate_request_nonce(request_id);
         config.clear_request_nonces();
 
-        let new_nonce = config.get_or_generate_request_nonce("request1");
+        let new_nonce = config.get_or_generate_request_nonce(request_id);
         assert!(new_nonce.is_some());
     }
 
+    #[cfg(feature = "stats")]
     #[test]
     fn test_csp_config_policy_update() {
         let policy = CspPolicy::new();
@@ -108,6 +115,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_csp_config_update_listeners() {
         let policy = CspPolicy::new();
         let config = CspConfig::new(policy);
@@ -118,13 +126,135 @@ mod tests {
         assert!(!config.remove_update_listener(listener_id));
     }
 
+    #[test]
+    fn test_policy_listener_receives_update_event() {
+        let config = Arc::new(CspConfig::new(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .build_unchecked(),
+        ));
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let listener_config = config.clone();
+        let listener_events = events.clone();
+        config.add_policy_listener(move |event| {
+            let PolicyEvent::Updated {
+                old_hash,
+                new_hash,
+                policy,
+            } = event
+            else {
+                return;
+            };
+            assert_eq!(listener_config.policy().structural_hash(), *new_hash);
+            listener_events
+                .lock()
+                .unwrap()
+                .push((*old_hash, *new_hash, policy.to_string()));
+        });
+
+        let before = config.policy().structural_hash();
+        config.update_policy(|policy| {
+            policy.append_source("img-src", Source::Scheme("data".into()));
+        });
+        config.update_policy(|_policy| {});
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].0, before);
+        assert_ne!(events[0].0, events[0].1);
+        assert_eq!(events[0].2, "default-src 'self'; img-src 'self' data:");
+        assert_eq!(events[1].0, events[1].1);
+    }
+
+    #[test]
+    fn test_policy_validations_are_counted() {
+        let config = CspConfig::new(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .build_unchecked(),
+        );
+        config.update_policy(|policy| {
+            policy.append_source("script-src", Source::Host("".into()));
+        });
+
+        let expected = if cfg!(feature = "stats") { 2 } else { 0 };
+        assert_eq!(config.stats().policy_validations(), expected);
+        assert!(config.policy().validate_and_log().is_err());
+    }
+
+    #[test]
+    fn test_policy_snapshot_is_unaffected_by_later_updates() {
+        let config = CspConfig::new(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .build_unchecked(),
+        );
+        let before = config.policy();
+        let compiled_before = config.compiled_policy().unwrap();
+
+        config.update_policy(|policy| {
+            policy.append_source("img-src", Source::Scheme("data".into()));
+        });
+
+        assert_eq!(before.to_string(), "default-src 'self'");
+        assert_eq!(compiled_before.header_value(), "default-src 'self'");
+        assert_eq!(
+            config.policy().to_string(),
+            "default-src 'self'; img-src 'self' data:"
+        );
+        assert_eq!(
+            config.compiled_policy().unwrap().header_value(),
+            "default-src 'self'; img-src 'self' data:"
+        );
+    }
+
+    #[test]
+    fn test_policy_listener_can_remove_itself() {
+        let config = Arc::new(CspConfig::new(CspPolicy::new()));
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let id = Arc::new(std::sync::OnceLock::new());
+
+        let listener_config = config.clone();
+        let listener_calls = calls.clone();
+        let listener_id = id.clone();
+        let registered = config.add_policy_listener(move |_event| {
+            listener_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            listener_config.remove_update_listener(*listener_id.get().unwrap());
+        });
+        id.set(registered).unwrap();
+
+        config.update_policy(|_policy| {});
+        config.update_policy(|_policy| {});
+
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_legacy_update_listener_runs_once_per_update() {
+        let config = CspConfig::new(CspPolicy::new());
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let listener_calls = calls.clone();
+        config.add_update_listener(move |policy| {
+            listener_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            policy.append_source("object-src", Source::None);
+        });
+        config.add_policy_listener(|_event| {});
+
+        config.update_policy(|_policy| {});
+
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(config.policy().get_directive("object-src").is_some());
+    }
+
     #[test]
     fn test_csp_config_with_default_directives() {
         let policy = CspPolicy::new();
         let config = CspConfig::new(policy).with_default_directives();
 
-        let policy_guard = config.policy();
-        let policy_ref = policy_guard.read();
+        let policy_ref = config.policy();
         assert!(policy_ref.get_directive("default-src").is_some());
         assert!(policy_ref.get_directive("object-src").is_some());
     }
@@ -162,4 +292,434 @@ mod tests {
         assert!(header.contains("default-src 'self'"));
         assert!(header.contains("script-src 'self'"));
     }
+
+    #[test]
+    fn test_csp_config_early_hints_headers() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .report_to("csp")
+            .build_unchecked();
+        let config = CspConfigBuilder::new()
+            .policy(policy)
+            .with_reporting_endpoint("csp", "https://example.com/csp")
+            .with_reporting_endpoint("default", "https://example.com/reports")
+            .build();
+
+        let headers = config.early_hints_headers();
+        assert!(headers
+            .get("content-security-policy")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .contains("report-to csp"));
+        assert_eq!(
+            headers.get("reporting-endpoints").unwrap(),
+            "csp=\"https://example.com/csp\", default=\"https://example.com/reports\""
+        );
+    }
+
+    #[test]
+    fn test_csp_config_skips_invalid_reporting_endpoints() {
+        let config = CspConfigBuilder::new()
+            .with_reporting_endpoint("bad name", "https://example.com/a")
+            .with_reporting_endpoint("quoted", "https://example.com/\"b")
+            .build();
+
+        assert!(config.reporting_endpoints().is_none());
+        assert!(!config
+            .early_hints_headers()
+            .contains_key("reporting-endpoints"));
+    }
+
+    #[test]
+    fn test_csp_config_policy_cache_is_scoped() {
+        let config = CspConfig::new(CspPolicy::default());
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .build_unchecked();
+        let hash = policy.structural_hash();
+
+        config.cache_policy(PolicyCacheKey::new("/admin", hash), policy);
+
+        assert!(config
+            .get_cached_policy(PolicyCacheKey::new("/admin", hash))
+            .is_some());
+        assert!(config
+            .get_cached_policy(PolicyCacheKey::new("/public", hash))
+            .is_none());
+        assert!(config.get_cached_policy(hash).is_none());
+    }
+
+    #[test]
+    fn test_csp_config_does_not_cache_nonce_policies() {
+        let config = CspConfig::new(CspPolicy::default());
+        let policy = CspPolicyBuilder::new()
+            .script_src([Source::Nonce("fixed".into())])
+            .build_unchecked();
+        let hash = policy.structural_hash();
+
+        let cached = config.cache_policy(hash, policy);
+
+        assert!(cached.contains_nonce());
+        assert!(config.get_cached_policy(hash).is_none());
+    }
+
+    #[test]
+    fn test_csp_config_maintenance_evicts_expired_policies() {
+        let config = CspConfig::new(CspPolicy::default());
+        let mut policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .build_unchecked();
+        policy
+            .header_value_with_cache_duration(Duration::from_millis(1))
+            .unwrap();
+        let hash = policy.structural_hash();
+        config.cache_policy(hash, policy);
+
+        std::thread::sleep(Duration::from_millis(5));
+        let report = config.run_maintenance();
+
+        assert_eq!(report.evicted_policies, 1);
+        assert!(config.get_cached_policy(hash).is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_csp_config_start_maintenance_records_usage() {
+        let config = CspConfig::new(CspPolicy::default());
+
+        let handle = config.start_maintenance(Duration::from_millis(5));
+        actix_web::rt::time::sleep(Duration::from_millis(40)).await;
+        handle.abort();
+
+        if cfg!(feature = "stats") {
+            assert!(config.perf_metrics().maintenance_runs() > 0);
+        }
+        assert_eq!(config.perf_metrics().gc_events(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_csp_config_start_maintenance_clamps_zero_interval() {
+        let config = CspConfig::new(CspPolicy::default());
+
+        let handle = config.start_maintenance(Duration::ZERO);
+        actix_web::rt::time::sleep(Duration::from_millis(20)).await;
+        handle.abort();
+
+        if cfg!(feature = "stats") {
+            assert!(config.perf_metrics().maintenance_runs() > 0);
+        }
+    }
+
+    fn campaign_config() -> CspConfig {
+        CspConfig::new(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .img_src([Source::Self_])
+                .build_unchecked(),
+        )
+    }
+
+    #[test]
+    fn test_temporary_source_is_added_and_expires() {
+        let config = campaign_config();
+        let pixel = Source::Host("pixel.campaign.example".into());
+
+        config.add_temporary_source("img-src", pixel.clone(), Duration::from_millis(1));
+        let img_src = config.policy().get_directive("img-src").unwrap().clone();
+        assert!(img_src.sources().contains(&pixel));
+
+        std::thread::sleep(Duration::from_millis(5));
+        let report = config.run_maintenance();
+
+        assert_eq!(report.expired_sources, 1);
+        let img_src = config.policy().get_directive("img-src").unwrap().clone();
+        assert_eq!(img_src.sources(), &[Source::Self_]);
+    }
+
+    #[test]
+    fn test_temporary_source_keeps_unexpired_and_existing_sources() {
+        let config = campaign_config();
+
+        config.add_temporary_source("img-src", Source::Self_, Duration::ZERO);
+        config.add_temporary_source(
+            "script-src",
+            Source::Host("cdn.example.com".into()),
+            Duration::from_secs(3600),
+        );
+
+        assert_eq!(config.expire_temporary_sources(), 0);
+        let policy = config.policy();
+        assert_eq!(
+            policy.get_directive("img-src").unwrap().sources(),
+            &[Source::Self_]
+        );
+        assert!(policy.get_directive("script-src").is_some());
+    }
+
+    #[test]
+    fn test_temporary_source_removes_directive_it_created() {
+        let config = campaign_config();
+
+        config.add_temporary_source(
+            "script-src",
+            Source::Host("cdn.example.com".into()),
+            Duration::ZERO,
+        );
+        assert_eq!(config.expire_temporary_sources(), 1);
+
+        assert!(config.policy().get_directive("script-src").is_none());
+    }
+
+    #[test]
+    fn test_temporary_source_keeps_created_directive_still_in_use() {
+        let config = campaign_config();
+        let widgets = Source::Host("widgets.example.com".into());
+
+        config.add_temporary_source(
+            "script-src",
+            Source::Host("cdn.example.com".into()),
+            Duration::ZERO,
+        );
+        config.add_temporary_source("script-src", widgets.clone(), Duration::from_secs(3600));
+        assert_eq!(config.expire_temporary_sources(), 1);
+
+        assert_eq!(
+            config
+                .policy()
+                .get_directive("script-src")
+                .unwrap()
+                .sources(),
+            &[Source::Self_, widgets]
+        );
+    }
+
+    #[test]
+    fn test_temporary_source_changes_are_recorded_in_history() {
+        let config = campaign_config();
+        let original = config.policy().structural_hash();
+
+        config.add_temporary_source(
+            "img-src",
+            Source::Host("pixel.campaign.example".into()),
+            Duration::ZERO,
+        );
+        config.expire_temporary_sources();
+
+        let history = config.policy_history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].old_hash, original);
+        assert!(history[0].description.contains("pixel.campaign.example"));
+        assert!(history[1].description.contains("expired"));
+        assert_eq!(history[1].new_hash, original);
+    }
+
+    #[test]
+    fn test_temporary_source_clamps_huge_durations() {
+        let config = campaign_config();
+        let pixel = Source::Host("pixel.campaign.example".into());
+
+        config.add_temporary_source("img-src", pixel.clone(), Duration::MAX);
+        config.add_temporary_source("img-src", pixel.clone(), Duration::MAX);
+
+        assert_eq!(config.expire_temporary_sources(), 0);
+        let img_src = config.policy().get_directive("img-src").unwrap().clone();
+        assert!(img_src.sources().contains(&pixel));
+    }
+
+    #[test]
+    fn test_temporary_source_already_allowed_publishes_nothing() {
+        let config = campaign_config();
+        let events = Arc::new(AtomicUsize::new(0));
+        let counter = events.clone();
+        config.add_policy_listener(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        config.add_temporary_source("img-src", Source::Self_, Duration::from_secs(60));
+
+        assert!(config.policy_history().is_empty());
+        assert_eq!(events.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_policy_history_is_bounded() {
+        let config = CspConfig::new(CspPolicy::default());
+
+        for _ in 0..100 {
+            config.update_policy(|_| {});
+        }
+
+        let history = config.policy_history();
+        assert!(history.len() < 100);
+        assert!(history
+            .iter()
+            .all(|entry| entry.description == "update_policy"));
+    }
+
+    #[test]
+    fn test_health_reports_policy_that_fails_to_compile() {
+        let config = CspConfig::new(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .build_unchecked(),
+        );
+
+        let health = config.health();
+        assert!(health.is_ready());
+        assert_eq!(health.last_reload, None);
+        assert_eq!(health.shadow_audit_backlog, None);
+
+        config.update_policy(|policy| {
+            let mut directive = Directive::new("img-src");
+            directive.add_source(Source::Host("bad\nhost".into()));
+            policy.add_directive(directive);
+        });
+
+        let health = config.health();
+        assert!(!health.is_ready());
+        assert!(!health.policy_compiled);
+        assert!(health.compile_error.is_some());
+        assert!(health.last_reload.is_some());
+        assert_eq!(health.last_reload_compiled, Some(false));
+        assert!(!config.policy_history()[0].compiled);
+    }
+
+    #[actix_web::test]
+    async fn test_health_tracks_maintenance_task() {
+        let config = CspConfig::new(CspPolicy::default());
+        assert_eq!(config.health().maintenance_alive, None);
+
+        let handle = config.start_maintenance(Duration::from_secs(3600));
+        assert_eq!(config.health().maintenance_alive, Some(true));
+
+        handle.abort();
+        assert!(handle.await.unwrap_err().is_cancelled());
+        let health = config.health();
+        assert_eq!(health.maintenance_alive, Some(false));
+        assert!(!health.is_ready());
+    }
+
+    #[test]
+    fn test_export_import_restores_runtime_state() {
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .default_src([Source::Self_])
+                    .report_uri("/csp-report")
+                    .build_unchecked(),
+            )
+            .with_nonce_generator(24)
+            .with_nonce_per_request(true)
+            .with_nonce_scope(NonceScope::Static)
+            .with_nonce_request_header("X-Nonce")
+            .with_nonce_cache_control(true)
+            .with_cache_duration(Duration::from_secs(300))
+            .with_cache_size(32)
+            .build();
+        config.update_policy(|policy| {
+            policy.append_source("script-src", Source::Host("cdn.example.com".into()));
+        });
+        config.add_temporary_source(
+            "img-src",
+            Source::Host("pixel.example.com".into()),
+            Duration::from_secs(3600),
+        );
+
+        let json = serde_json::to_string(&config.export()).unwrap();
+        let snapshot: CspConfigSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot.nonce_length, Some(24));
+        assert_eq!(snapshot.cache_size, 32);
+        assert_eq!(snapshot.temporary_sources.len(), 1);
+        assert!(snapshot.temporary_sources[0].created_directive);
+
+        let restored = CspConfig::import(snapshot).unwrap();
+        assert_eq!(restored.policy().to_string(), config.policy().to_string());
+        assert_eq!(restored.cache_duration(), Duration::from_secs(300));
+        assert_eq!(restored.nonce_request_header(), Some("X-Nonce"));
+        assert_eq!(restored.nonce_scope(), NonceScope::Static);
+        assert!(restored.nonce_cache_control().is_some());
+        assert_eq!(restored.generate_nonce().unwrap().len(), 32);
+        assert_eq!(restored.export(), config.export());
+    }
+
+    #[cfg(not(feature = "session"))]
+    #[test]
+    fn test_session_nonce_scope_needs_the_session_feature() {
+        assert!(serde_json::from_str::<NonceScope>("\"session\"").is_err());
+        assert_eq!(
+            serde_json::from_str::<NonceScope>("\"static\"").unwrap(),
+            NonceScope::Static
+        );
+    }
+
+    #[test]
+    fn test_import_keeps_temporary_sources_temporary() {
+        let config = CspConfig::new(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .img_src([Source::Self_])
+                .build_unchecked(),
+        );
+        config.add_temporary_source(
+            "img-src",
+            Source::Host("pixel.example.com".into()),
+            Duration::from_secs(3600),
+        );
+
+        let mut snapshot = config.export();
+        snapshot.temporary_sources[0].expires_in_secs = 0;
+        let restored = CspConfig::import(snapshot).unwrap();
+
+        assert!(restored.policy().to_string().contains("pixel.example.com"));
+        assert_eq!(restored.expire_temporary_sources(), 1);
+        assert_eq!(
+            restored
+                .policy()
+                .get_directive("img-src")
+                .unwrap()
+                .sources(),
+            [Source::Self_]
+        );
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_temporary_source() {
+        let config = CspConfig::new(
+            CspPolicyBuilder::new()
+                .img_src([Source::Self_])
+                .build_unchecked(),
+        );
+        let mut snapshot = config.export();
+        snapshot
+            .temporary_sources
+            .push(actix_web_csp::core::TemporarySourceSnapshot {
+                directive: "img-src".to_string(),
+                source: "'nonce-'".to_string(),
+                expires_in_secs: 60,
+                created_directive: false,
+            });
+
+        assert!(CspConfig::import(snapshot).is_err());
+    }
+
+    #[test]
+    fn test_import_rejects_unrepresentable_expiry() {
+        let config = campaign_config();
+        let mut snapshot = config.export();
+        snapshot
+            .temporary_sources
+            .push(actix_web_csp::core::TemporarySourceSnapshot {
+                directive: "img-src".to_string(),
+                source: "pixel.campaign.example".to_string(),
+                expires_in_secs: u64::MAX,
+                created_directive: false,
+            });
+
+        match CspConfig::import(snapshot).err() {
+            Some(actix_web_csp::CspError::ConfigError(message)) => {
+                assert!(message.contains("pixel.campaign.example"), "{message}")
+            }
+            other => panic!("expected a config error, got {other:?}"),
+        }
+    }
 }
@@ -1,4 +1,6 @@
+pub mod allowlist;
 pub mod config;
 pub mod interop;
 pub mod policy;
+pub mod routes;
 pub mod source;
@@ -1,5 +1,10 @@
-use actix_web::http::header::HeaderName;
-use actix_web_csp::core::{CspPolicy, CspPolicyBuilder, Source};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web_csp::core::{
+    parse_header_value, CspPolicy, CspPolicyBuilder, Directive, RequireSriFor, Source, SourceKind,
+};
+use actix_web_csp::security::{HashAlgorithm, HashGenerator};
+use actix_web_csp::CspError;
+use bytes::BytesMut;
 
 #[cfg(test)]
 mod tests {
@@ -200,6 +205,47 @@ mod tests {
         assert!(policy.get_directive("default-src").is_some());
     }
 
+    #[test]
+    fn test_csp_policy_builder_event_handler_hashes() {
+        let handler_hash = Source::Hash {
+            algorithm: HashAlgorithm::Sha256,
+            value: HashGenerator::generate(HashAlgorithm::Sha256, b"doSomething()").into(),
+        };
+
+        let policy = CspPolicyBuilder::new()
+            .script_src([Source::Self_])
+            .event_handler_hashes(["doSomething()", "doSomething()"])
+            .build()
+            .unwrap();
+        assert_eq!(
+            policy.get_directive("script-src").unwrap().sources(),
+            [Source::Self_, Source::UnsafeHashes, handler_hash.clone()]
+        );
+
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_, Source::Host("cdn.example.com".into())])
+            .event_handler_hashes(["doSomething()"])
+            .build_unchecked();
+        assert_eq!(
+            policy.get_directive("script-src").unwrap().sources(),
+            [
+                Source::Self_,
+                Source::Host("cdn.example.com".into()),
+                Source::UnsafeHashes,
+                handler_hash.clone(),
+            ]
+        );
+
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::None])
+            .event_handler_hashes(["doSomething()"])
+            .build_unchecked();
+        assert_eq!(
+            policy.get_directive("script-src").unwrap().sources(),
+            [Source::UnsafeHashes, handler_hash]
+        );
+    }
+
     #[test]
     fn test_csp_policy_contains_nonce() {
         use std::borrow::Cow;
@@ -216,6 +262,189 @@ mod tests {
         assert!(policy_with_nonce.contains_nonce());
     }
 
+    #[test]
+    fn test_csp_policy_structural_hash_ignores_nonce_values() {
+        let policy = |nonce: &'static str| {
+            CspPolicyBuilder::new()
+                .script_src([Source::Self_, Source::Nonce(nonce.into())])
+                .build_unchecked()
+        };
+        let without_nonce = CspPolicyBuilder::new()
+            .script_src([Source::Self_])
+            .build_unchecked();
+
+        assert_eq!(policy("a").structural_hash(), policy("b").structural_hash());
+        assert_ne!(
+            policy("a").structural_hash(),
+            without_nonce.structural_hash()
+        );
+    }
+
+    #[test]
+    fn test_csp_policy_header_value_with_nonce_is_not_cached() {
+        let mut policy = CspPolicyBuilder::new()
+            .script_src([Source::Self_])
+            .build_unchecked();
+        assert!(!policy.has_cached_header_value());
+        let cached = policy.header_value().unwrap();
+        assert!(policy.has_cached_header_value());
+
+        let mut with_nonce = policy.clone_with_runtime_nonce("first");
+        assert!(!with_nonce.has_cached_header_value());
+        let first = with_nonce.header_value().unwrap();
+        assert!(!with_nonce.has_cached_header_value());
+        let mut cloned = with_nonce.clone();
+        let second = cloned.header_value().unwrap();
+        assert!(!cloned.has_cached_header_value());
+
+        assert_eq!(cached, "script-src 'self'");
+        assert_eq!(first, "script-src 'self' 'nonce-first'");
+        assert_eq!(first, second);
+        assert_eq!(policy.header_value().unwrap(), cached);
+        assert!(policy.has_cached_header_value());
+
+        policy.append_source("script-src", Source::Nonce("second".into()));
+        assert!(!policy.has_cached_header_value());
+        assert_eq!(
+            policy.header_value().unwrap(),
+            "script-src 'self' 'nonce-second'"
+        );
+        assert!(!policy.has_cached_header_value());
+    }
+
+    #[test]
+    fn test_compiled_policy_nonce_splice_matches_recompile() {
+        let mut script_src = actix_web_csp::core::Directive::new("script-src");
+        script_src
+            .add_source(Source::Self_)
+            .add_fallback_sources([Source::UnsafeInline]);
+
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .with_directive(script_src)
+            .style_src([Source::None])
+            .img_src([Source::Self_])
+            .report_uri("/csp-report")
+            .build_unchecked();
+
+        let compiled = policy.compile().unwrap();
+        let spliced = compiled.header_value_with_nonce("abc123").unwrap();
+        let recompiled = policy.compile_with_runtime_nonce("abc123").unwrap();
+
+        assert_eq!(&spliced, recompiled.header_value());
+        assert_eq!(
+            spliced,
+            "default-src 'self'; script-src 'self' 'nonce-abc123' 'unsafe-inline'; \
+             style-src 'nonce-abc123'; img-src 'self'; report-uri /csp-report"
+        );
+    }
+
+    #[test]
+    fn test_nonce_placeholder_marks_where_the_request_nonce_goes() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([
+                Source::Self_,
+                Source::NoncePlaceholder,
+                Source::Host("cdn.example.com".into()),
+            ])
+            .style_src([Source::Self_])
+            .build_unchecked();
+        assert!(policy.contains_nonce_placeholder());
+
+        let compiled = policy.compile().unwrap();
+        assert_eq!(
+            compiled.header_value(),
+            "default-src 'self'; script-src 'self' cdn.example.com; style-src 'self'"
+        );
+
+        let spliced = compiled.header_value_with_nonce("abc123").unwrap();
+        let recompiled = policy.compile_with_runtime_nonce("abc123").unwrap();
+        assert_eq!(&spliced, recompiled.header_value());
+        assert_eq!(
+            spliced,
+            "default-src 'self'; script-src 'self' 'nonce-abc123' cdn.example.com; \
+             style-src 'self'"
+        );
+
+        let injected = policy.clone_with_runtime_nonce("abc123");
+        assert!(!injected.contains_nonce_placeholder());
+        assert_eq!(injected.structural_hash(), policy.structural_hash());
+    }
+
+    #[test]
+    fn test_nonce_placeholder_round_trips_through_text() {
+        let policy = CspPolicyBuilder::new()
+            .script_src([Source::Self_, Source::NoncePlaceholder])
+            .build_unchecked();
+
+        let text = policy.to_string();
+        assert_eq!(text, "script-src 'self' 'nonce-{{csp-nonce}}'");
+        assert_eq!(text.parse::<CspPolicy>().unwrap().to_string(), text);
+        assert_eq!(
+            "'nonce-{{csp-nonce}}'".parse::<Source>().unwrap(),
+            Source::NoncePlaceholder
+        );
+        assert_eq!(Source::NoncePlaceholder.kind(), SourceKind::Nonce);
+        assert!(!Source::NoncePlaceholder.contains_nonce());
+        assert_eq!(Source::NoncePlaceholder.nonce(), None);
+
+        let only = CspPolicyBuilder::new()
+            .script_src([Source::NoncePlaceholder])
+            .build_unchecked();
+        assert_eq!(only.compile().unwrap().header_value(), "script-src");
+    }
+
+    #[test]
+    fn test_nonce_placeholder_counts_as_a_nonce_once_filled() {
+        let policy = CspPolicyBuilder::new()
+            .script_src([Source::Self_, Source::NoncePlaceholder])
+            .build_unchecked();
+        assert!(!policy.contains_nonce());
+        assert!(!policy.get_directive("script-src").unwrap().contains_nonce());
+
+        let filled = policy.clone_with_runtime_nonce("abc123");
+        assert!(filled.contains_nonce());
+        assert!(filled.get_directive("script-src").unwrap().contains_nonce());
+    }
+
+    #[test]
+    fn test_compiled_policy_keeps_lone_none() {
+        let mut policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::None])
+            .style_src([Source::None])
+            .build_unchecked();
+
+        let compiled = policy.compile().unwrap();
+        assert_eq!(compiled.header_value(), &policy.header_value().unwrap());
+        assert_eq!(
+            compiled.header_value(),
+            "default-src 'self'; script-src 'none'; style-src 'none'"
+        );
+
+        let spliced = compiled.header_value_with_nonce("abc123").unwrap();
+        let recompiled = policy.compile_with_runtime_nonce("abc123").unwrap();
+        assert_eq!(&spliced, recompiled.header_value());
+        assert_eq!(
+            spliced,
+            "default-src 'self'; script-src 'nonce-abc123'; style-src 'nonce-abc123'"
+        );
+    }
+
+    #[test]
+    fn test_compiled_policy_nonce_splice_without_nonce_directives() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .build_unchecked();
+        let compiled = policy.compile().unwrap();
+
+        assert_eq!(
+            &compiled.header_value_with_nonce("abc123").unwrap(),
+            compiled.header_value()
+        );
+    }
+
     #[test]
     fn test_csp_policy_contains_hash() {
         use actix_web_csp::security::HashAlgorithm;
@@ -291,9 +520,14 @@ mod tests {
 
     #[cfg(feature = "extended-validation")]
     #[test]
-    fn test_extended_validation_rejects_host_with_scheme() {
+    fn test_extended_validation_checks_host_source_grammar() {
+        assert!(CspPolicyBuilder::new()
+            .script_src([Source::Host("https://*.example.com:*".into())])
+            .build()
+            .is_ok());
+
         let result = CspPolicyBuilder::new()
-            .script_src([Source::Host("https://cdn.example.com".into())])
+            .script_src([Source::Host("https://cdn..example.com".into())])
             .build();
 
         assert!(result.is_err());
@@ -340,4 +574,385 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_parse_header_value_roundtrip() {
+        let mut policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_, Source::Nonce("abc".into())])
+            .img_src([Source::Scheme("data".into())])
+            .report_uri("/csp-report")
+            .build_unchecked();
+        let header = policy.header_value().unwrap();
+
+        let parsed = parse_header_value(&header).unwrap();
+
+        assert_eq!(parsed.to_string(), policy.to_string());
+    }
+
+    #[test]
+    fn test_parse_header_value_follows_header_grammar() {
+        let header =
+            HeaderValue::from_static("SCRIPT-SRC 'self';; script-src https:; report-to csp");
+
+        let policy = parse_header_value(&header).unwrap();
+
+        assert_eq!(policy.to_string(), "script-src 'self'; report-to csp");
+    }
+
+    #[test]
+    fn test_parse_header_value_keeps_unknown_keywords() {
+        let header = HeaderValue::from_static(
+            "script-src 'self' 'inline-speculation-rules' 'report-sha256'; img-src 'self'",
+        );
+
+        let policy = parse_header_value(&header).unwrap();
+
+        assert_eq!(
+            policy.to_string(),
+            "script-src 'self' 'inline-speculation-rules' 'report-sha256'; img-src 'self'"
+        );
+        policy.roundtrip_check().unwrap();
+    }
+
+    #[test]
+    fn test_parse_header_value_accepts_report_uri_list() {
+        let header = HeaderValue::from_static(
+            "default-src 'self'; report-uri /csp-report  https://reports.example.com/csp",
+        );
+
+        let policy = parse_header_value(&header).unwrap();
+
+        assert_eq!(
+            policy.report_uris().collect::<Vec<_>>(),
+            ["/csp-report", "https://reports.example.com/csp"]
+        );
+        assert_eq!(
+            policy.to_string(),
+            "default-src 'self'; report-uri /csp-report https://reports.example.com/csp"
+        );
+        policy.roundtrip_check().unwrap();
+        assert_eq!(
+            policy.to_string().parse::<CspPolicy>().unwrap().to_string(),
+            policy.to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_header_value_error_positions() {
+        let cases = [
+            ("default-src 'self'; img-src 'sha1-abc'", 1, 28),
+            ("default-src 'self'; scr!pt-src 'self'", 1, 20),
+            ("report-to a b", 0, 12),
+            ("default-src 'self' 'sha512-'", 0, 19),
+        ];
+
+        for (header, expected_index, expected_offset) in cases {
+            match parse_header_value(&HeaderValue::from_static(header)) {
+                Err(CspError::ParseError {
+                    directive_index,
+                    offset,
+                    ..
+                }) => {
+                    assert_eq!(
+                        (directive_index, offset),
+                        (expected_index, expected_offset),
+                        "{header}"
+                    );
+                }
+                other => panic!("unexpected result for {header}: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_append_source_keeps_default_src_fallback() {
+        let mut policy: CspPolicy = "default-src 'self' https://assets.example.com; \
+                                     script-src 'self'; object-src 'none'"
+            .parse()
+            .unwrap();
+
+        policy
+            .append_source("img-src", Source::Host("cdn.example.com".into()))
+            .append_source(
+                "script-src-elem",
+                Source::Host("widgets.example.com".into()),
+            )
+            .append_source("object-src", Source::Self_)
+            .append_source("form-action", Source::Self_);
+
+        assert_eq!(
+            policy.to_string(),
+            "default-src 'self' https://assets.example.com; script-src 'self'; \
+             object-src 'self'; img-src 'self' https://assets.example.com cdn.example.com; \
+             script-src-elem 'self' widgets.example.com; form-action 'self'"
+        );
+    }
+
+    #[test]
+    fn test_append_source_replaces_inherited_none() {
+        let mut policy: CspPolicy = "default-src 'none'".parse().unwrap();
+
+        policy.append_source("img-src", Source::Self_);
+
+        assert_eq!(policy.to_string(), "default-src 'none'; img-src 'self'");
+    }
+
+    #[test]
+    fn test_canonicalize_orders_directives_and_sources() {
+        let mut policy: CspPolicy =
+            "img-src data: https://b.example.com https://a.example.com 'self'; x-custom a; \
+             script-src 'sha256-abc=' 'nonce-xyz' 'strict-dynamic' 'self'; default-src 'self'"
+                .parse()
+                .unwrap();
+
+        policy.canonicalize();
+
+        assert_eq!(
+            policy.to_string(),
+            "default-src 'self'; script-src 'self' 'strict-dynamic' 'nonce-xyz' 'sha256-abc='; \
+             img-src 'self' data: https://a.example.com https://b.example.com; x-custom a"
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_makes_hash_independent_of_assembly_order() {
+        let mut first = CspPolicyBuilder::new()
+            .script_src([Source::Self_, Source::Host("cdn.example.com".into())])
+            .default_src([Source::None])
+            .build_unchecked();
+        let mut second = CspPolicyBuilder::new()
+            .default_src([Source::None])
+            .script_src([Source::Host("cdn.example.com".into()), Source::Self_])
+            .build_unchecked();
+
+        assert_ne!(first.hash(), second.hash());
+
+        first.canonicalize();
+        second.canonicalize();
+
+        assert_eq!(first.hash(), second.hash());
+        assert_eq!(
+            first.header_value().unwrap(),
+            second.header_value().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_require_sri_for_builder() {
+        let mut policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .require_sri_for(RequireSriFor::new().style().script())
+            .build_unchecked();
+
+        let header = policy.header_value().unwrap();
+        assert!(header
+            .to_str()
+            .unwrap()
+            .contains("require-sri-for script style"));
+
+        let parsed =
+            parse_header_value(&HeaderValue::from_static("require-sri-for style")).unwrap();
+        let sri = RequireSriFor::from_directive(parsed.get_directive("require-sri-for").unwrap());
+        assert!(sri.requires_style());
+        assert!(!sri.requires_script());
+    }
+
+    #[test]
+    fn test_pretty_renders_one_directive_per_line() {
+        let mut policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_, Source::Host("cdn.example.com".into())])
+            .build_unchecked();
+        policy.set_report_to("csp");
+
+        assert_eq!(
+            policy.to_string(),
+            "default-src 'self'; script-src 'self' cdn.example.com; report-to csp"
+        );
+        assert_eq!(
+            policy.pretty(),
+            "default-src 'self'\nscript-src 'self' cdn.example.com\nreport-to csp"
+        );
+        assert_eq!(format!("{policy:#}"), policy.pretty());
+        assert_eq!(CspPolicy::new().pretty(), "");
+    }
+
+    #[test]
+    fn test_to_iframe_csp_attr_leaves_out_reporting() {
+        let mut policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_, Source::Host("cdn.example.com".into())])
+            .report_uri("/csp-report")
+            .report_to("csp")
+            .build_unchecked();
+        policy.set_report_only(true);
+
+        assert_eq!(
+            policy.to_iframe_csp_attr().unwrap(),
+            "default-src 'self'; script-src 'self' cdn.example.com"
+        );
+    }
+
+    #[test]
+    fn test_to_iframe_csp_attr_rejects_attribute_unsafe_characters() {
+        for (host, unsafe_char) in [
+            ("https://cdn.example.com/a?b=1&c=2", "'&'"),
+            ("cdn.example.com/\"onload=\"x", "'\"'"),
+            ("cdn.example.com/<b>", "'<'"),
+            ("cdn.example.com/a`b", "'`'"),
+        ] {
+            let policy = CspPolicyBuilder::new()
+                .img_src([Source::Host(host.into())])
+                .build_unchecked();
+            assert!(policy.validate().is_ok(), "{host}");
+
+            match policy.to_iframe_csp_attr() {
+                Err(CspError::SerializationError(message)) => assert_eq!(
+                    message,
+                    format!("{unsafe_char} is not allowed in a csp attribute value")
+                ),
+                other => panic!("expected a serialization error for {host}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_header_matches_header_value() {
+        let mut policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_, Source::Host("cdn.example.com".into())])
+            .report_uri("/csp-report")
+            .build_unchecked();
+
+        let mut buffer = BytesMut::from(&b"prefix "[..]);
+        policy.write_header(&mut buffer).unwrap();
+
+        let expected = policy.header_value().unwrap();
+        assert_eq!(&buffer[..7], b"prefix ");
+        assert_eq!(&buffer[7..], expected.as_bytes());
+    }
+
+    #[test]
+    fn test_write_header_rejects_invalid_bytes_and_leaves_buffer_untouched() {
+        let policy = CspPolicyBuilder::new()
+            .img_src([Source::Host("bad\nhost".into())])
+            .build_unchecked();
+
+        let mut buffer = BytesMut::from(&b"kept"[..]);
+        let result = policy.write_header(&mut buffer);
+
+        assert!(matches!(result, Err(CspError::InvalidDirectiveValue(_))));
+        assert_eq!(&buffer[..], b"kept");
+    }
+
+    #[test]
+    fn test_roundtrip_check_accepts_well_formed_policy() {
+        let mut policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_, Source::Host("cdn.example.com".into())])
+            .img_src([Source::Scheme("data".into())])
+            .report_uri("/csp-report")
+            .report_to("csp-endpoint")
+            .build_unchecked();
+        policy.set_report_only(true);
+
+        assert!(policy.roundtrip_check().is_ok());
+    }
+
+    // Extended validation rejects these sources before they are serialized.
+    #[cfg(not(feature = "extended-validation"))]
+    #[test]
+    fn test_roundtrip_check_detects_sources_that_change_meaning() {
+        let doubled_colon = CspPolicyBuilder::new()
+            .script_src([Source::Scheme("https:".into())])
+            .build_unchecked();
+        let quoted_nonce = CspPolicyBuilder::new()
+            .script_src([Source::Nonce("abc'".into())])
+            .build_unchecked();
+
+        for (policy, offending) in [(doubled_colon, "https::"), (quoted_nonce, "'nonce-abc''")] {
+            assert!(policy.validate().is_ok());
+            assert!(policy.compile().is_ok());
+            match policy.roundtrip_check() {
+                Err(CspError::SerializationError(message)) => {
+                    assert!(message.contains(offending), "{message}")
+                }
+                other => panic!("expected a serialization error, got {other:?}"),
+            }
+        }
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_policies_round_trip() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        for _ in 0..256 {
+            let data: Vec<u8> = (0..512)
+                .map(|_| {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 7;
+                    seed ^= seed << 17;
+                    seed as u8
+                })
+                .collect();
+            let policy = CspPolicy::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            if policy.validate().is_ok() && policy.compile().is_ok() {
+                policy.roundtrip_check().unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_summary_counts_sources_by_kind() {
+        let mut policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([
+                Source::Self_,
+                Source::UnsafeEval,
+                Source::Nonce("abc123".into()),
+                Source::Host("cdn.example.com".into()),
+            ])
+            .style_src([Source::UnsafeInline, Source::Scheme("https".into())])
+            .report_to("csp-endpoint")
+            .build_unchecked();
+        let mut img_src = Directive::new("img-src");
+        img_src.add_source(Source::Self_);
+        img_src.add_fallback_sources([Source::Host("img.example.com".into())]);
+        policy.add_directive(img_src);
+        policy.set_report_only(true);
+
+        let summary = policy.summary();
+
+        assert_eq!(summary.directive_count, 4);
+        assert_eq!(summary.source_count, 9);
+        assert_eq!(summary.count(SourceKind::Keyword), 5);
+        assert_eq!(summary.count(SourceKind::Host), 2);
+        assert_eq!(summary.count(SourceKind::Scheme), 1);
+        assert_eq!(summary.count(SourceKind::Nonce), 1);
+        assert_eq!(summary.count(SourceKind::Hash), 0);
+        assert_eq!(
+            summary.unsafe_keywords,
+            ["'unsafe-eval'", "'unsafe-inline'"]
+        );
+        assert!(summary.reporting);
+        assert!(summary.report_only);
+        assert_eq!(
+            summary.header_bytes,
+            Some(policy.compile().unwrap().header_value().len())
+        );
+        assert!(summary
+            .to_string()
+            .starts_with("CSP policy: 4 directives (report-only), 9 sources"));
+
+        let single = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .build_unchecked()
+            .summary();
+        assert!(single
+            .to_string()
+            .starts_with("CSP policy: 1 directive, 1 source, "));
+    }
 }
@@ -0,0 +1,94 @@
+use actix_web_csp::{CspPolicy, CspPolicyBuilder, PolicyRoutes, Source};
+
+fn policy_with_script_host(host: &'static str) -> CspPolicy {
+    CspPolicyBuilder::new()
+        .default_src([Source::Self_])
+        .script_src([Source::Self_, Source::Host(host.into())])
+        .build_unchecked()
+}
+
+fn script_host(policy: Option<&CspPolicy>) -> Option<String> {
+    policy?
+        .get_directive("script-src")?
+        .sources()
+        .iter()
+        .find_map(|source| match source {
+            Source::Host(host) => Some(host.to_string()),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_matches_whole_segments() {
+        let routes =
+            PolicyRoutes::new().prefix("/checkout", policy_with_script_host("js.stripe.com"));
+
+        for path in ["/checkout", "/checkout/", "/checkout/confirm/step-2"] {
+            assert_eq!(
+                script_host(routes.policy_for(path)).as_deref(),
+                Some("js.stripe.com"),
+                "{path}"
+            );
+        }
+        assert!(routes.policy_for("/checkouts").is_none());
+        assert!(routes.policy_for("/").is_none());
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let routes = PolicyRoutes::new()
+            .prefix("/docs", policy_with_script_host("docs.example.com"))
+            .prefix("/docs/api/", policy_with_script_host("api.example.com"));
+
+        assert_eq!(
+            script_host(routes.policy_for("/docs/api/v1")).as_deref(),
+            Some("api.example.com")
+        );
+        assert_eq!(
+            script_host(routes.policy_for("/docs/apix")).as_deref(),
+            Some("docs.example.com")
+        );
+        assert_eq!(routes.len(), 2);
+    }
+
+    #[test]
+    fn test_default_applies_outside_prefixes() {
+        let routes = PolicyRoutes::new()
+            .prefix("/checkout", policy_with_script_host("js.stripe.com"))
+            .default(policy_with_script_host("cdn.example.com"));
+
+        assert_eq!(
+            script_host(routes.policy_for("/blog/post")).as_deref(),
+            Some("cdn.example.com")
+        );
+        assert_eq!(routes.len(), 1);
+    }
+
+    #[test]
+    fn test_repeated_prefix_replaces_policy() {
+        let routes = PolicyRoutes::new()
+            .prefix("/docs", policy_with_script_host("old.example.com"))
+            .prefix("/docs/", policy_with_script_host("new.example.com"));
+
+        assert_eq!(routes.len(), 1);
+        assert_eq!(
+            script_host(routes.policy_for("/docs")).as_deref(),
+            Some("new.example.com")
+        );
+    }
+
+    #[test]
+    fn test_empty_table() {
+        let routes = PolicyRoutes::new();
+
+        assert!(routes.is_empty());
+        assert!(routes.policy_for("/anything").is_none());
+        assert!(!routes
+            .default(policy_with_script_host("a.example.com"))
+            .is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use actix_web_csp::core::Source;
+use actix_web_csp::core::{Directive, HostSource, PortPart, SchemePart, Source, SourceKind};
 use actix_web_csp::security::HashAlgorithm;
 use std::borrow::Cow;
 
@@ -44,6 +44,80 @@ mod tests {
         assert!(nonce_source.to_string().contains(nonce_value));
     }
 
+    #[test]
+    fn test_checked_constructors_accept_valid_values() {
+        assert_eq!(
+            Source::try_host("*.example.com:443/static/").unwrap(),
+            Source::Host(Cow::Borrowed("*.example.com:443/static/"))
+        );
+        assert_eq!(
+            Source::try_scheme("data").unwrap(),
+            Source::Scheme(Cow::Borrowed("data"))
+        );
+        assert_eq!(
+            Source::try_nonce(String::from("dGVzdC1ub25jZQ==")).unwrap(),
+            Source::Nonce(Cow::Borrowed("dGVzdC1ub25jZQ=="))
+        );
+    }
+
+    #[test]
+    fn test_checked_constructors_reject_malformed_values() {
+        assert!(Source::try_host("https://cdn.example.com").is_err());
+        assert!(Source::try_host("https:").is_err());
+        assert!(Source::try_host("cdn example.com").is_err());
+        assert!(Source::try_scheme("https:").is_err());
+        assert!(Source::try_scheme("1http").is_err());
+        assert!(Source::try_nonce("not a nonce").is_err());
+        assert!(Source::try_nonce("").is_err());
+    }
+
+    #[test]
+    fn test_strings_convert_to_sources() {
+        assert_eq!(Source::try_from("'self'").unwrap(), Source::Self_);
+        assert_eq!(
+            Source::try_from("'WASM-UNSAFE-EVAL'").unwrap(),
+            Source::WasmUnsafeEval
+        );
+        assert_eq!(
+            Source::try_from("https:").unwrap(),
+            Source::Scheme(Cow::Borrowed("https"))
+        );
+        assert_eq!(
+            Source::try_from("*.example.com").unwrap(),
+            Source::Host(Cow::Borrowed("*.example.com"))
+        );
+        assert_eq!(
+            Source::try_from("'nonce-abc123'").unwrap(),
+            Source::Nonce(Cow::Borrowed("abc123"))
+        );
+        assert_eq!(
+            Source::try_from(String::from("https://cdn.example.com")).unwrap(),
+            Source::Host(Cow::Borrowed("https://cdn.example.com"))
+        );
+        assert!(Source::try_from("'self").is_err());
+        assert!(Source::try_from(String::from("'slef'")).is_err());
+    }
+
+    #[test]
+    fn test_build_rejects_malformed_hosts() {
+        for host in [
+            "'self",
+            "'self'",
+            "cdn example.com",
+            "a.com;b.com",
+            "https://",
+        ] {
+            let result = actix_web_csp::CspPolicyBuilder::new()
+                .script_src([Source::Host(Cow::Borrowed(host))])
+                .build();
+
+            assert!(
+                matches!(result, Err(actix_web_csp::CspError::ValidationError(_))),
+                "{host}"
+            );
+        }
+    }
+
     #[test]
     fn test_source_hash() {
         let hash_value = "sha256-abc123";
@@ -138,4 +212,254 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_source_from_str_matches_keywords_case_insensitively() {
+        assert_eq!("'SELF'".parse::<Source>().unwrap(), Source::Self_);
+        assert_eq!(
+            "'Strict-Dynamic'".parse::<Source>().unwrap(),
+            Source::StrictDynamic
+        );
+        assert_eq!(
+            "https://*.example.com:443/static/"
+                .parse::<Source>()
+                .unwrap(),
+            Source::Host("https://*.example.com:443/static/".into())
+        );
+    }
+
+    #[test]
+    fn test_source_from_str_keeps_csp3_keywords() {
+        for value in [
+            "'inline-speculation-rules'",
+            "'report-sha256'",
+            "'unsafe-allow-redirects'",
+        ] {
+            let source = value.parse::<Source>().unwrap();
+            assert_eq!(source, Source::OtherKeyword(Cow::Borrowed(value)));
+            assert!(source.is_keyword());
+            assert_eq!(source.to_string(), value);
+        }
+        assert_eq!(
+            "'Report-SHA256'".parse::<Source>().unwrap().to_string(),
+            "'report-sha256'"
+        );
+    }
+
+    #[test]
+    fn test_source_from_str_rejects_malformed_sources() {
+        for value in [
+            "'unsafe-everything'",
+            "'slef'",
+            "'unsafe-inlin'",
+            "'unsafe everything'",
+            "'unsafe-everything",
+            "''",
+            "'nonce-'",
+            "'nonce-abc$def'",
+            "'sha256-not base64'",
+            "1https:",
+            "example..com",
+            "https://example.com:port",
+            "*.",
+            "example.com/a;b",
+        ] {
+            assert!(value.parse::<Source>().is_err(), "{value} should not parse");
+        }
+    }
+
+    #[test]
+    fn test_directive_from_str_validates_name() {
+        let directive = "Script-Src 'self'".parse::<Directive>().unwrap();
+        assert_eq!(directive.name(), "script-src");
+
+        assert!("script_src 'self'".parse::<Directive>().is_err());
+    }
+
+    #[test]
+    fn test_directive_is_known_covers_all_directive_constants() {
+        use actix_web_csp::constants::{
+            FENCED_FRAME_SRC, KNOWN_DIRECTIVES, REPORT_TO, SCRIPT_SRC, TRUSTED_TYPES,
+        };
+
+        for name in [SCRIPT_SRC, TRUSTED_TYPES, FENCED_FRAME_SRC, REPORT_TO] {
+            assert!(KNOWN_DIRECTIVES.contains(&name));
+        }
+        assert!(Directive::is_known("Script-Src"));
+        assert!(Directive::is_known("require-trusted-types-for"));
+        assert!(!Directive::is_known("script-scr"));
+        assert!(!Directive::is_known(""));
+    }
+
+    #[test]
+    fn test_unknown_directive_still_validates() {
+        let mut directive = Directive::new("script-scr");
+        directive.add_source(Source::Self_);
+
+        assert!(directive.validate().is_ok());
+    }
+
+    #[test]
+    fn test_host_source_parses_wildcard_scheme_and_port() {
+        let source: HostSource = "*://CDN.example.com:*/static/".parse().unwrap();
+
+        assert_eq!(source.scheme, SchemePart::Any);
+        assert_eq!(source.host, "cdn.example.com");
+        assert_eq!(source.port, PortPart::Any);
+        assert_eq!(source.path.as_deref(), Some("/static/"));
+        assert_eq!(source.to_string(), "*://cdn.example.com:*/static/");
+        assert_eq!(
+            Source::Host("https://*.example.com:8443".into()).host_source(),
+            Some(HostSource {
+                scheme: SchemePart::Scheme("https".to_string()),
+                host: "*.example.com".to_string(),
+                port: PortPart::Port(8443),
+                path: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_host_source_matches_urls() {
+        let matches = |source: &str, url: &str, protected: Option<&str>| {
+            source
+                .parse::<HostSource>()
+                .unwrap()
+                .matches(&url.parse().unwrap(), protected)
+        };
+
+        assert!(matches(
+            "https://*.example.com:*",
+            "https://a.example.com:9000/x",
+            None
+        ));
+        assert!(!matches(
+            "https://*.example.com:*",
+            "https://example.com/x",
+            None
+        ));
+        assert!(!matches(
+            "https://*.example.com:*",
+            "http://a.example.com/x",
+            None
+        ));
+        assert!(matches(
+            "*://cdn.example.com",
+            "wss://cdn.example.com/socket",
+            None
+        ));
+        assert!(!matches("*://cdn.example.com", "data:text/plain,x", None));
+        assert!(matches(
+            "http://cdn.example.com",
+            "https://cdn.example.com/",
+            None
+        ));
+        assert!(!matches(
+            "cdn.example.com",
+            "https://cdn.example.com:8443/",
+            None
+        ));
+        assert!(!matches(
+            "cdn.example.com",
+            "http://cdn.example.com/",
+            Some("https")
+        ));
+        assert!(matches(
+            "ws://cdn.example.com",
+            "https://cdn.example.com/",
+            None
+        ));
+        assert!(matches(
+            "wss://cdn.example.com",
+            "https://cdn.example.com/",
+            None
+        ));
+        assert!(!matches(
+            "http://cdn.example.com",
+            "ws://cdn.example.com/",
+            None
+        ));
+        assert!(!matches(
+            "http://cdn.example.com",
+            "wss://cdn.example.com/",
+            None
+        ));
+        assert!(!matches(
+            "https://cdn.example.com",
+            "wss://cdn.example.com/",
+            None
+        ));
+        assert!(!matches(
+            "wss://cdn.example.com",
+            "http://cdn.example.com/",
+            None
+        ));
+        assert!(!matches(
+            "cdn.example.com",
+            "wss://cdn.example.com/",
+            Some("https")
+        ));
+        assert!(matches(
+            "cdn.example.com/app.js",
+            "https://cdn.example.com/app.js",
+            None
+        ));
+        assert!(!matches(
+            "cdn.example.com/app.js",
+            "https://cdn.example.com/app.js.map",
+            None
+        ));
+    }
+
+    #[test]
+    fn test_source_kind() {
+        assert_eq!(Source::Self_.kind(), SourceKind::Keyword);
+        assert_eq!(Source::WasmUnsafeEval.kind(), SourceKind::Keyword);
+        assert_eq!(Source::Scheme("https".into()).kind(), SourceKind::Scheme);
+        assert_eq!(
+            Source::Host("cdn.example.com".into()).kind(),
+            SourceKind::Host
+        );
+        assert_eq!(Source::Nonce("abc".into()).kind(), SourceKind::Nonce);
+        let hash = Source::Hash {
+            algorithm: HashAlgorithm::Sha256,
+            value: "abc=".into(),
+        };
+        assert_eq!(hash.kind(), SourceKind::Hash);
+        assert!(Source::None.is_keyword());
+        assert!(!hash.is_keyword());
+    }
+
+    #[test]
+    fn test_directive_source_accessors() {
+        let mut directive = Directive::new("script-src");
+        directive.add_sources([
+            Source::Self_,
+            Source::Host("cdn.example.com".into()),
+            Source::StrictDynamic,
+            Source::Nonce("n0nce".into()),
+            Source::Hash {
+                algorithm: HashAlgorithm::Sha384,
+                value: "h4sh".into(),
+            },
+            Source::Scheme("https".into()),
+            Source::Host("*.example.org".into()),
+        ]);
+        directive.add_fallback_sources([Source::Host("fallback.example.com".into())]);
+
+        assert_eq!(
+            directive.keywords().collect::<Vec<_>>(),
+            [&Source::Self_, &Source::StrictDynamic]
+        );
+        assert_eq!(
+            directive.hosts().collect::<Vec<_>>(),
+            ["cdn.example.com", "*.example.org"]
+        );
+        assert_eq!(directive.nonces().collect::<Vec<_>>(), ["n0nce"]);
+        assert_eq!(
+            directive.hashes().collect::<Vec<_>>(),
+            [("h4sh", HashAlgorithm::Sha384)]
+        );
+        assert_eq!(directive.sources_of_kind(SourceKind::Scheme).count(), 1);
+    }
 }
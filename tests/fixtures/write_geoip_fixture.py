@@ -0,0 +1,117 @@
+#!/usr/bin/env python3
+"""Writes geoip-test.mmdb, the MaxMind DB used by the `geoip` feature tests.
+
+The database is IPv4-only and holds two networks:
+
+- 198.51.100.0/24: country DE, AS64500 "Example Hosting"
+- 203.0.113.0/24: country FR, no ASN
+"""
+
+import ipaddress
+import os
+import struct
+
+NETWORKS = [
+    (
+        "198.51.100.0/24",
+        {
+            "country": {"iso_code": "DE"},
+            "autonomous_system_number": 64500,
+            "autonomous_system_organization": "Example Hosting",
+        },
+    ),
+    ("203.0.113.0/24", {"country": {"iso_code": "FR"}}),
+]
+
+STRING, UINT16, UINT32, MAP = 2, 5, 6, 7
+UINT64, ARRAY = 9, 11
+
+
+def control(kind, size):
+    if size < 29:
+        head, extra = size, b""
+    elif size < 285:
+        head, extra = 29, bytes([size - 29])
+    else:
+        head, extra = 30, struct.pack(">H", size - 285)
+    if kind <= 7:
+        return bytes([(kind << 5) | head]) + extra
+    return bytes([head, kind - 7]) + extra
+
+
+def encode(value, kind=UINT32):
+    """Encodes `value`; integers are written as `kind`, or pass a
+    `(value, kind)` tuple inside containers."""
+    if isinstance(value, tuple):
+        return encode(*value)
+    if isinstance(value, dict):
+        out = control(MAP, len(value))
+        for key, item in value.items():
+            out += encode(key) + encode(item)
+        return out
+    if isinstance(value, list):
+        return control(ARRAY, len(value)) + b"".join(encode(item) for item in value)
+    if isinstance(value, str):
+        raw = value.encode()
+        return control(STRING, len(raw)) + raw
+    raw = value.to_bytes((value.bit_length() + 7) // 8, "big")
+    return control(kind, len(raw)) + raw
+
+
+def main():
+    data = b""
+    offsets = []
+    for _, record in NETWORKS:
+        offsets.append(len(data))
+        data += encode(record)
+
+    # node -> [left, right]; None marks an empty branch
+    nodes = [[None, None]]
+    leaves = {}
+    for index, (network, _) in enumerate(NETWORKS):
+        network = ipaddress.ip_network(network)
+        bits = int(network.network_address)
+        node = 0
+        for depth in range(network.prefixlen):
+            bit = (bits >> (31 - depth)) & 1
+            if depth == network.prefixlen - 1:
+                leaves[(node, bit)] = index
+            else:
+                if nodes[node][bit] is None:
+                    nodes.append([None, None])
+                    nodes[node][bit] = len(nodes) - 1
+                node = nodes[node][bit]
+
+    node_count = len(nodes)
+    tree = b""
+    for number, children in enumerate(nodes):
+        for bit, child in enumerate(children):
+            if (number, bit) in leaves:
+                record = node_count + 16 + offsets[leaves[(number, bit)]]
+            elif child is None:
+                record = node_count
+            else:
+                record = child
+            tree += record.to_bytes(3, "big")
+
+    metadata = encode(
+        {
+            "binary_format_major_version": (2, UINT16),
+            "binary_format_minor_version": (0, UINT16),
+            "build_epoch": (1700000000, UINT64),
+            "database_type": "actix-web-csp-test",
+            "description": {"en": "Test networks for actix-web-csp"},
+            "ip_version": (4, UINT16),
+            "languages": ["en"],
+            "node_count": node_count,
+            "record_size": (24, UINT16),
+        }
+    )
+
+    path = os.path.join(os.path.dirname(os.path.abspath(__file__)), "geoip-test.mmdb")
+    with open(path, "wb") as out:
+        out.write(tree + bytes(16) + data + b"\xab\xcd\xefMaxMind.com" + metadata)
+
+
+if __name__ == "__main__":
+    main()
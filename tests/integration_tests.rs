@@ -1,3 +1,5 @@
+#![cfg_attr(not(feature = "reporting"), allow(unused_imports))]
+
 use actix_web::{test, web, App, HttpMessage, HttpRequest, HttpResponse, Result};
 use actix_web_csp::{
     csp_middleware, csp_middleware_with_nonce, csp_middleware_with_request_nonce,
@@ -316,6 +318,7 @@ mod integration_tests {
         assert!(csp_value.contains("frame-src 'none'"));
     }
 
+    #[cfg(feature = "reporting")]
     #[actix_web::test]
     async fn test_csp_with_reporting_endpoint() {
         let policy = CspPolicyBuilder::new()
@@ -414,4 +417,47 @@ mod integration_tests {
         println!("Time elapsed for 100 requests: {duration:?}");
         assert!(duration.as_secs() < 1, "Performance too low: {duration:?}");
     }
+
+    #[actix_web::test]
+    async fn test_root_and_prelude_exports_are_module_tree_items() {
+        let _: fn(actix_web_csp::core::CspConfig) -> actix_web_csp::middleware::CspMiddleware =
+            actix_web_csp::CspMiddleware::new;
+        let _: fn(actix_web_csp::CspConfig) -> actix_web_csp::prelude::CspMiddleware =
+            actix_web_csp::middleware::CspMiddleware::new;
+        let _: fn() -> actix_web_csp::monitoring::stats::CspStats = actix_web_csp::CspStats::new;
+        let _: fn() -> actix_web_csp::prelude::CspStats = actix_web_csp::monitoring::CspStats::new;
+        let _: fn(usize) -> actix_web_csp::security::nonce::NonceGenerator =
+            actix_web_csp::NonceGenerator::new;
+        let _: fn(actix_web_csp::CspPolicy) -> actix_web_csp::middleware::CspMiddleware =
+            actix_web_csp::prelude::csp_middleware;
+    }
+
+    #[actix_web::test]
+    async fn test_root_and_module_middleware_send_identical_headers() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_])
+            .build_unchecked();
+
+        let root = test::init_service(
+            App::new()
+                .wrap(csp_middleware(policy.clone()))
+                .route("/", web::get().to(test_api_endpoint)),
+        )
+        .await;
+        let module = test::init_service(
+            App::new()
+                .wrap(actix_web_csp::middleware::csp::csp_middleware(policy))
+                .route("/", web::get().to(test_api_endpoint)),
+        )
+        .await;
+
+        let root = test::call_service(&root, test::TestRequest::get().uri("/").to_request()).await;
+        let module =
+            test::call_service(&module, test::TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(
+            root.headers().get("content-security-policy"),
+            module.headers().get("content-security-policy")
+        );
+    }
 }
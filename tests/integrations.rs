@@ -0,0 +1,104 @@
+use actix_web_csp::integrations::Service;
+use actix_web_csp::security::PolicyAuditor;
+use actix_web_csp::{CspPolicy, CspPolicyBuilder, Source};
+
+fn directive(policy: &CspPolicy, name: &str) -> Option<String> {
+    policy
+        .get_directive(name)
+        .map(|directive| directive.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_service_yields_a_valid_round_tripping_policy() {
+        for service in Service::ALL {
+            let policy = CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .form_action([Source::Self_])
+                .apply_service(service)
+                .build()
+                .unwrap_or_else(|error| panic!("{service}: {error}"));
+
+            policy.roundtrip_check().unwrap();
+            for (name, sources) in service.sources() {
+                let applied = policy.get_directive(name).unwrap();
+                assert!(
+                    sources
+                        .iter()
+                        .all(|source| applied.sources().contains(source)),
+                    "{service} {name}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_apply_service_extends_existing_directives() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_])
+            .apply_service(Service::Stripe)
+            .build_unchecked();
+
+        assert_eq!(
+            directive(&policy, "script-src").unwrap(),
+            "script-src 'self' https://js.stripe.com https://*.js.stripe.com"
+        );
+        assert_eq!(
+            directive(&policy, "frame-src").unwrap(),
+            "frame-src 'self' https://js.stripe.com https://*.js.stripe.com https://hooks.stripe.com"
+        );
+    }
+
+    #[test]
+    fn test_apply_service_skips_directives_that_restrict_nothing() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .apply_service(Service::Intercom)
+            .build_unchecked();
+        assert_eq!(directive(&policy, "form-action"), None);
+
+        let policy = CspPolicyBuilder::new()
+            .img_src([Source::Self_])
+            .apply_service(Service::GoogleFonts)
+            .build_unchecked();
+        assert_eq!(directive(&policy, "style-src"), None);
+        assert_eq!(directive(&policy, "font-src"), None);
+    }
+
+    #[test]
+    fn test_apply_service_twice_adds_sources_once() {
+        let mut policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .apply_service(Service::GoogleFonts)
+            .build_unchecked();
+        let once = policy.to_string();
+
+        policy.apply_service(Service::GoogleFonts);
+
+        assert_eq!(policy.to_string(), once);
+    }
+
+    #[test]
+    fn test_intercom_relaxation_is_reported() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .apply_service(Service::Intercom)
+            .build_unchecked();
+
+        assert_eq!(policy.summary().unsafe_keywords, ["'unsafe-inline'"]);
+        assert!(PolicyAuditor::new().audit(&policy).has_warnings());
+    }
+
+    #[test]
+    fn test_service_names_parse() {
+        for service in Service::ALL {
+            assert_eq!(service.name().parse::<Service>().unwrap(), service);
+        }
+        assert_eq!(" Stripe ".parse::<Service>().unwrap(), Service::Stripe);
+        assert!("segment".parse::<Service>().is_err());
+    }
+}
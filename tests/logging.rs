@@ -0,0 +1,290 @@
+use actix_web_csp::logging::{self, REPORT_TARGET};
+use log::LevelFilter;
+#[cfg(not(feature = "tracing"))]
+use {
+    log::{Level, Log, Metadata, Record},
+    std::sync::Mutex,
+};
+
+#[cfg(not(feature = "tracing"))]
+struct CaptureLogger {
+    records: Mutex<Vec<(String, Level, String)>>,
+}
+
+#[cfg(not(feature = "tracing"))]
+impl Log for CaptureLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.records.lock().unwrap().push((
+            record.target().to_string(),
+            record.level(),
+            record.args().to_string(),
+        ));
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(not(feature = "tracing"))]
+static LOGGER: CaptureLogger = CaptureLogger {
+    records: Mutex::new(Vec::new()),
+};
+
+/// Held by every test that changes the crate's logging settings or asserts
+/// on them, so the settings one test changes never leak into another.
+static SETTINGS: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[cfg(not(feature = "tracing"))]
+fn captured(needle: &str) -> Vec<(String, Level)> {
+    LOGGER
+        .records
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, _, message)| message.contains(needle))
+        .map(|(target, level, _)| (target.clone(), *level))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_level_defaults_to_trace() {
+        let _settings = SETTINGS.blocking_lock();
+        assert_eq!(logging::report_level(), LevelFilter::Trace);
+        assert_eq!(REPORT_TARGET, "actix_web_csp::report");
+        assert!(REPORT_TARGET.starts_with(logging::TARGET));
+    }
+
+    #[cfg(all(feature = "reporting", not(feature = "tracing")))]
+    #[actix_web::test]
+    async fn test_report_messages_use_report_target_and_level() {
+        use actix_web::{test as actix_test, web, App, HttpResponse};
+        use actix_web_csp::middleware::CspReportingMiddleware;
+        use actix_web_csp::test::ViolationReportBuilder;
+
+        let _settings = SETTINGS.lock().await;
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(LevelFilter::Trace);
+
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(
+                    CspReportingMiddleware::new(|_| {})
+                        .with_report_path("/logging-report")
+                        .with_max_report_size(16),
+                )
+                .default_service(web::to(HttpResponse::NotFound)),
+        )
+        .await;
+        let send = || {
+            ViolationReportBuilder::new("https://evil.example/x.js", "script-src")
+                .to_request("/logging-report")
+                .to_request()
+        };
+
+        let _ = actix_test::try_call_service(&app, send()).await;
+        assert_eq!(
+            captured("/logging-report"),
+            [(REPORT_TARGET.to_string(), Level::Debug)]
+        );
+
+        logging::set_report_level(LevelFilter::Warn);
+        let _ = actix_test::try_call_service(&app, send()).await;
+        logging::set_report_level(LevelFilter::Trace);
+
+        assert_eq!(captured("/logging-report").len(), 1);
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    #[test]
+    fn test_header_logging_logs_each_header_change_once() {
+        use actix_web_csp::core::Directive;
+        use actix_web_csp::{
+            CspConfigBuilder, CspPolicyBuilder, HashAlgorithm, HashGenerator, Source,
+        };
+
+        let _settings = SETTINGS.blocking_lock();
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(LevelFilter::Trace);
+
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .default_src([Source::Self_])
+                    .img_src([Source::Host("header-log.example".into())])
+                    .build_unchecked(),
+            )
+            .with_header_logging(true)
+            .build();
+        assert!(config.header_logging());
+
+        let header = "default-src 'self'; img-src header-log.example";
+        let logged = captured("header-log.example");
+        assert_eq!(logged, [(logging::TARGET.to_string(), Level::Info)]);
+        let message = LOGGER
+            .records
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(_, _, message)| message.contains("header-log.example"))
+            .map(|(_, _, message)| message.clone())
+            .unwrap();
+        assert!(message.contains("name=content-security-policy"));
+        assert!(message.contains(&format!("bytes={}", header.len())));
+        assert!(message.contains(&format!(
+            "sha256={}",
+            HashGenerator::generate(HashAlgorithm::Sha256, header.as_bytes())
+        )));
+        assert!(message.contains(&format!("value=\"{header}\"")));
+
+        config.update_policy(|_| {});
+        assert_eq!(captured("header-log.example").len(), 1);
+
+        config.update_policy(|policy| {
+            let mut directive = Directive::new("script-src");
+            directive.add_source(Source::Host("cdn.header-log.example".into()));
+            policy.add_directive(directive);
+        });
+        assert_eq!(captured("header-log.example").len(), 2);
+        assert_eq!(captured("cdn.header-log.example").len(), 1);
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    #[actix_web::test]
+    async fn test_header_failures_warn_once_per_policy_version() {
+        use actix_web::{test as actix_test, web, App, HttpResponse};
+        use actix_web_csp::core::Directive;
+        use actix_web_csp::{CspConfig, CspMiddleware, CspPolicy, Source};
+
+        let _settings = SETTINGS.lock().await;
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(LevelFilter::Trace);
+
+        let mut directive = Directive::new("img-src");
+        directive.add_source(Source::Host("bad\nhost".into()));
+        let mut policy = CspPolicy::default();
+        policy.add_directive(directive);
+        let config = CspConfig::new(policy);
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config.clone()))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let warnings = || {
+            captured("Could not generate the CSP header")
+                .into_iter()
+                .filter(|(_, level)| *level == Level::Warn)
+                .count()
+        };
+
+        for _ in 0..3 {
+            actix_test::call_service(&app, actix_test::TestRequest::get().to_request()).await;
+        }
+        assert_eq!(warnings(), 1);
+        assert_eq!(captured("Could not generate the CSP header again").len(), 2);
+
+        config.update_policy(|policy| {
+            policy.append_source("script-src", Source::Self_);
+        });
+        for _ in 0..2 {
+            actix_test::call_service(&app, actix_test::TestRequest::get().to_request()).await;
+        }
+        assert_eq!(warnings(), 2);
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    #[test]
+    fn test_log_target_can_be_changed() {
+        use actix_web_csp::{CspConfigBuilder, CspPolicyBuilder, Source};
+
+        let _settings = SETTINGS.blocking_lock();
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(LevelFilter::Trace);
+
+        logging::set_target("app::security::csp");
+        assert_eq!(logging::target(), "app::security::csp");
+        assert_eq!(logging::report_target(), "app::security::csp::report");
+        CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .img_src([Source::Host("custom-target.example".into())])
+                    .build_unchecked(),
+            )
+            .with_header_logging(true)
+            .build();
+        logging::set_target(logging::TARGET);
+
+        assert_eq!(
+            captured("custom-target.example"),
+            [("app::security::csp".to_string(), Level::Info)]
+        );
+        assert_eq!(logging::target(), logging::TARGET);
+        assert_eq!(logging::report_target(), REPORT_TARGET);
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    #[test]
+    fn test_invalid_nonce_cache_control_value_is_logged() {
+        use actix_web_csp::CspConfigBuilder;
+
+        let _settings = SETTINGS.blocking_lock();
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(LevelFilter::Trace);
+
+        let config = CspConfigBuilder::new()
+            .with_nonce_generator(16)
+            .with_nonce_cache_control_value("private,\nno-cache")
+            .build();
+
+        assert!(config.nonce_cache_control().is_none());
+        assert_eq!(
+            captured("Ignoring invalid nonce Cache-Control value \"private,\\nno-cache\""),
+            [(logging::TARGET.to_string(), Level::Warn)]
+        );
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    #[actix_web::test]
+    async fn test_startup_report_logs_the_audit_once() {
+        use actix_web::{test as actix_test, App};
+        use actix_web_csp::security::PolicyAuditor;
+        use actix_web_csp::{csp_middleware, CspPolicyBuilder, Source};
+
+        let _settings = SETTINGS.lock().await;
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(LevelFilter::Trace);
+
+        let middleware = csp_middleware(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .script_src([Source::Self_, Source::UnsafeEval])
+                .report_uri("/startup-report")
+                .build_unchecked(),
+        )
+        .with_startup_report(PolicyAuditor::new());
+        for _ in 0..2 {
+            actix_test::init_service(App::new().wrap(middleware.clone())).await;
+        }
+
+        let logged = LOGGER
+            .records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, _, message)| message.starts_with("CSP policy: 2 directives"))
+            .map(|(_, level, message)| (*level, message.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(logged.len(), 1, "{logged:?}");
+        assert_eq!(logged[0].0, Level::Warn);
+        assert!(logged[0].1.contains("reporting configured"));
+        assert!(logged[0].1.contains("unsafe keywords: 'unsafe-eval'"));
+    }
+}
@@ -0,0 +1,124 @@
+use actix_web::{http::StatusCode, test as actix_test, web, App, HttpResponse};
+use actix_web_csp::middleware::{CspReportingMiddleware, ReportBlocklist};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+fn report_from(ip: [u8; 4]) -> actix_test::TestRequest {
+    actix_test::TestRequest::post()
+        .uri("/csp-report")
+        .peer_addr(SocketAddr::from((ip, 5000)))
+        .set_json(serde_json::json!({
+            "csp-report": {
+                "document-uri": "https://app.example/",
+                "referrer": "",
+                "blocked-uri": "https://evil.example/x.js",
+                "violated-directive": "script-src",
+                "effective-directive": "script-src",
+                "original-policy": "script-src 'self'",
+                "disposition": "enforce"
+            }
+        }))
+}
+
+async fn fallback() -> HttpResponse {
+    HttpResponse::NotFound().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_blocklist_matches_networks() {
+        let blocklist = ReportBlocklist::new()
+            .with_blocked_network("198.51.100.0/24")
+            .unwrap()
+            .with_blocked_network("2001:db8::1")
+            .unwrap();
+
+        assert!(blocklist.is_blocked("198.51.100.77".parse().unwrap()));
+        assert!(blocklist.is_blocked("2001:db8::1".parse().unwrap()));
+        assert!(!blocklist.is_blocked("203.0.113.1".parse().unwrap()));
+        assert!(!blocklist.is_banned("198.51.100.77".parse().unwrap()));
+        assert!(ReportBlocklist::new()
+            .with_blocked_network("198.51.100.0/40")
+            .is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_refuses_blocked_clients_and_origins() {
+        let middleware = CspReportingMiddleware::new(|_| panic!("blocked report was handled"))
+            .with_blocklist(
+                ReportBlocklist::new()
+                    .with_blocked_network("198.51.100.0/24")
+                    .unwrap()
+                    .with_blocked_origin("https://Spam.example/"),
+            );
+        let stats = middleware.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        let res = actix_test::call_service(&app, report_from([198, 51, 100, 9]).to_request()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let req = report_from([203, 0, 113, 9])
+            .insert_header(("origin", "https://spam.example"))
+            .to_request();
+        let res = actix_test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let expected = if cfg!(feature = "stats") { 2 } else { 0 };
+        assert_eq!(stats.blocked_report_count(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_bans_flooding_clients() {
+        let middleware = CspReportingMiddleware::new(|_| {}).with_blocklist(
+            ReportBlocklist::new().with_auto_ban(
+                2,
+                Duration::from_secs(60),
+                Duration::from_secs(600),
+            ),
+        );
+        let blocklist = middleware.blocklist().unwrap().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        let clients = [
+            [203, 0, 113, 9],
+            [203, 0, 113, 9],
+            [203, 0, 113, 9],
+            [203, 0, 113, 10],
+            [203, 0, 113, 9],
+        ];
+        let mut received = Vec::new();
+        for ip in clients {
+            let res = actix_test::call_service(&app, report_from(ip).to_request()).await;
+            received.push(res.status());
+        }
+        assert_eq!(
+            received,
+            [
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::OK,
+                StatusCode::TOO_MANY_REQUESTS,
+            ]
+        );
+
+        let banned = "203.0.113.9".parse().unwrap();
+        assert!(blocklist.is_banned(banned));
+        blocklist.unban(banned);
+        let res = actix_test::call_service(&app, report_from([203, 0, 113, 9]).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
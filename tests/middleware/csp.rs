@@ -1,7 +1,34 @@
+use actix_web::{
+    http::StatusCode, test as actix_test, web, App, HttpMessage, HttpRequest, HttpResponse,
+};
 use actix_web_csp::{
-    core::{CspConfigBuilder, CspPolicy, CspPolicyBuilder, Source},
-    middleware::{csp_middleware, CspMiddleware},
+    core::{CspConfigBuilder, CspPolicy, CspPolicyBuilder, Directive, HeaderFailureMode, Source},
+    middleware::{configure_csp_data, configure_csp_readiness, csp_middleware, CspMiddleware},
+    security::PolicyAuditor,
+    CspExtensions, CspNonce, NonceScope, RequestId, RequestNonce,
 };
+use std::time::Duration;
+
+fn unserializable_policy() -> CspPolicy {
+    let mut directive = Directive::new("img-src");
+    directive.add_source(Source::Host("bad\nhost".into()));
+    let mut policy = CspPolicy::default();
+    policy.add_directive(directive);
+    policy
+}
+
+fn nonce_of<B>(res: &actix_web::dev::ServiceResponse<B>) -> String {
+    res.headers()
+        .get("content-security-policy")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .split("'nonce-")
+        .nth(1)
+        .and_then(|rest| rest.split('\'').next())
+        .unwrap_or_default()
+        .to_owned()
+}
 
 #[cfg(test)]
 mod tests {
@@ -19,13 +46,11 @@ mod tests {
         assert!(middleware
             .config()
             .policy()
-            .read()
             .get_directive("default-src")
             .is_some());
         assert!(middleware
             .config()
             .policy()
-            .read()
             .get_directive("script-src")
             .is_some());
     }
@@ -47,7 +72,6 @@ mod tests {
         assert!(middleware
             .config()
             .policy()
-            .read()
             .get_directive("default-src")
             .is_some());
     }
@@ -61,7 +85,7 @@ mod tests {
 
         let middleware = csp_middleware(policy);
 
-        assert!(middleware.config().policy().read().is_report_only());
+        assert!(middleware.config().policy().is_report_only());
     }
 
     #[test]
@@ -74,7 +98,7 @@ mod tests {
         let middleware = csp_middleware(policy);
 
         assert_eq!(
-            middleware.config().policy().read().report_uri(),
+            middleware.config().policy().report_uri(),
             Some("https://example.com/csp-report")
         );
     }
@@ -89,7 +113,7 @@ mod tests {
         let middleware = csp_middleware(policy);
 
         assert_eq!(
-            middleware.config().policy().read().report_to(),
+            middleware.config().policy().report_to(),
             Some("csp-endpoint")
         );
     }
@@ -106,8 +130,7 @@ mod tests {
 
         let middleware = csp_middleware(policy);
 
-        let policy_guard = middleware.config().policy();
-        let policy = policy_guard.read();
+        let policy = middleware.config().policy();
         assert!(policy.get_directive("default-src").is_some());
         assert!(policy.get_directive("script-src").is_some());
         assert!(policy.get_directive("style-src").is_some());
@@ -121,7 +144,7 @@ mod tests {
 
         let middleware = csp_middleware(policy);
 
-        assert_eq!(middleware.config().policy().read().directives().count(), 0);
+        assert_eq!(middleware.config().policy().directives().count(), 0);
     }
 
     #[test]
@@ -141,4 +164,982 @@ mod tests {
         assert!(nonce.is_some());
         assert!(!nonce.unwrap().is_empty());
     }
+
+    #[actix_web::test]
+    async fn test_csp_middleware_sends_reporting_endpoints() {
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .default_src([Source::Self_])
+                    .report_to("csp")
+                    .build_unchecked(),
+            )
+            .with_reporting_endpoint("csp", "https://example.com/csp")
+            .build();
+
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config))
+                .route("/", web::get().to(|| async { HttpResponse::Ok().finish() })),
+        )
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        assert_eq!(
+            res.headers().get("reporting-endpoints").unwrap(),
+            "csp=\"https://example.com/csp\""
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_csp_middleware_upgrades_insecure_requests_only_behind_tls() {
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .default_src([Source::Self_])
+                    .build_unchecked(),
+            )
+            .with_upgrade_insecure_requests_on_tls(true)
+            .with_hsts_on_tls(Duration::from_secs(31_536_000), true)
+            .with_trusted_forwarded_proto(true)
+            .build();
+
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config))
+                .route("/", web::get().to(|| async { HttpResponse::Ok().finish() })),
+        )
+        .await;
+
+        let plain =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        assert_eq!(
+            plain.headers().get("content-security-policy").unwrap(),
+            "default-src 'self'"
+        );
+        assert!(plain.headers().get("strict-transport-security").is_none());
+
+        let forwarded = actix_test::call_service(
+            &app,
+            actix_test::TestRequest::get()
+                .uri("/")
+                .insert_header(("x-forwarded-proto", "https"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(
+            forwarded.headers().get("content-security-policy").unwrap(),
+            "default-src 'self'; upgrade-insecure-requests"
+        );
+        assert_eq!(
+            forwarded
+                .headers()
+                .get("strict-transport-security")
+                .unwrap(),
+            "max-age=31536000; includeSubDomains"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_csp_middleware_ignores_forwarded_proto_when_untrusted() {
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .default_src([Source::Self_])
+                    .build_unchecked(),
+            )
+            .with_upgrade_insecure_requests_on_tls(true)
+            .build();
+
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config))
+                .route("/", web::get().to(|| async { HttpResponse::Ok().finish() })),
+        )
+        .await;
+
+        let res = actix_test::call_service(
+            &app,
+            actix_test::TestRequest::get()
+                .uri("/")
+                .insert_header(("forwarded", "for=192.0.2.60;proto=https"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(
+            res.headers().get("content-security-policy").unwrap(),
+            "default-src 'self'"
+        );
+    }
+
+    async fn request_id_of(req: HttpRequest) -> HttpResponse {
+        match req.extensions().get::<RequestId>() {
+            Some(id) => HttpResponse::Ok().body(id.to_string()),
+            None => HttpResponse::Ok().finish(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_request_id_is_only_assigned_for_per_request_nonces() {
+        for per_request in [false, true] {
+            let config = CspConfigBuilder::new()
+                .policy(
+                    CspPolicyBuilder::new()
+                        .script_src([Source::Self_])
+                        .build_unchecked(),
+                )
+                .with_nonce_generator(16)
+                .with_nonce_per_request(per_request)
+                .build();
+            let app = actix_test::init_service(
+                App::new()
+                    .wrap(CspMiddleware::new(config.clone()))
+                    .route("/", web::get().to(request_id_of)),
+            )
+            .await;
+
+            let first = actix_test::call_and_read_body(
+                &app,
+                actix_test::TestRequest::get().uri("/").to_request(),
+            )
+            .await;
+            let second = actix_test::call_and_read_body(
+                &app,
+                actix_test::TestRequest::get().uri("/").to_request(),
+            )
+            .await;
+
+            assert_eq!(!first.is_empty(), per_request);
+            if per_request {
+                assert_ne!(first, second);
+            }
+            assert_eq!(config.run_maintenance().memory_usage_bytes, 0);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_nonce_placeholder_is_replaced_with_request_nonce() {
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .script_src([Source::NoncePlaceholder, Source::StrictDynamic])
+                    .style_src([Source::Self_])
+                    .build_unchecked(),
+            )
+            .with_nonce_generator(16)
+            .with_nonce_per_request(true)
+            .build();
+        let app = actix_test::init_service(App::new().wrap(CspMiddleware::new(config)).route(
+            "/",
+            web::get().to(|req: HttpRequest| async move {
+                HttpResponse::Ok().body(req.get_nonce().unwrap_or_default())
+            }),
+        ))
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        let nonce = nonce_of(&res);
+        let header = res
+            .headers()
+            .get("content-security-policy")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let body = actix_test::read_body(res).await;
+
+        assert!(!nonce.is_empty());
+        assert_eq!(body, nonce.as_bytes());
+        assert_eq!(
+            header,
+            format!("script-src 'nonce-{nonce}' 'strict-dynamic'; style-src 'self'")
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_request_nonce_is_shared_with_headers() {
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .script_src([Source::Self_])
+                    .build_unchecked(),
+            )
+            .with_nonce_generator(16)
+            .with_nonce_per_request(true)
+            .with_nonce_request_header("X-Nonce")
+            .build();
+        let app = actix_test::init_service(App::new().wrap(CspMiddleware::new(config)).route(
+            "/",
+            web::get().to(|req: HttpRequest| async move {
+                let nonce = req.extensions().get::<RequestNonce>().cloned().unwrap();
+                HttpResponse::Ok().body(nonce.to_string())
+            }),
+        ))
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        let nonce = nonce_of(&res);
+        let echoed = res.headers().get("x-nonce").unwrap().to_str().unwrap();
+
+        assert!(!nonce.is_empty());
+        assert_eq!(echoed, nonce);
+        let body = actix_test::read_body(res).await;
+        assert_eq!(body, nonce.as_bytes());
+    }
+
+    #[actix_web::test]
+    async fn test_lazy_nonce_is_only_generated_when_read() {
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .script_src([Source::Self_])
+                    .build_unchecked(),
+            )
+            .with_nonce_generator(16)
+            .with_nonce_per_request(true)
+            .with_lazy_nonce(true)
+            .build();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config.clone()))
+                .route(
+                    "/api",
+                    web::get().to(|| async { HttpResponse::Ok().json([1, 2, 3]) }),
+                )
+                .route(
+                    "/page",
+                    web::get().to(|req: HttpRequest| async move {
+                        HttpResponse::Ok().body(req.get_nonce().unwrap_or_default())
+                    }),
+                ),
+        )
+        .await;
+
+        let api = actix_test::call_service(
+            &app,
+            actix_test::TestRequest::get().uri("/api").to_request(),
+        )
+        .await;
+        assert_eq!(
+            api.headers().get("content-security-policy").unwrap(),
+            "script-src 'self'"
+        );
+        #[cfg(feature = "stats")]
+        assert_eq!(config.stats().nonce_generation_count(), 0);
+
+        let page = actix_test::call_service(
+            &app,
+            actix_test::TestRequest::get().uri("/page").to_request(),
+        )
+        .await;
+        let header = page
+            .headers()
+            .get("content-security-policy")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let nonce = actix_test::read_body(page).await;
+        let nonce = std::str::from_utf8(&nonce).unwrap();
+        assert!(!nonce.is_empty());
+        assert!(header.contains(&format!("'nonce-{nonce}'")));
+        #[cfg(feature = "stats")]
+        assert_eq!(config.stats().nonce_generation_count(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_lazy_nonce_read_after_the_response_leaves_no_cache_entry() {
+        use std::sync::{Arc, Mutex};
+
+        let config = CspConfigBuilder::new()
+            .with_nonce_generator(16)
+            .with_nonce_per_request(true)
+            .with_lazy_nonce(true)
+            .build();
+        let kept: Arc<Mutex<Option<(RequestId, CspNonce)>>> = Arc::default();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config.clone()))
+                .app_data(web::Data::from(kept.clone()))
+                .route(
+                    "/",
+                    web::get().to(
+                        |req: HttpRequest, kept: web::Data<Mutex<Option<(RequestId, CspNonce)>>>| async move {
+                            let extensions = req.extensions();
+                            *kept.lock().unwrap() = Some((
+                                *extensions.get::<RequestId>().unwrap(),
+                                extensions.get::<CspNonce>().unwrap().clone(),
+                            ));
+                            HttpResponse::Ok().finish()
+                        },
+                    ),
+                ),
+        )
+        .await;
+
+        let res = actix_test::call_service(&app, actix_test::TestRequest::get().to_request()).await;
+        drop(res);
+        let (request_id, nonce) = kept.lock().unwrap().take().unwrap();
+        let late = nonce.get().to_owned();
+        assert_eq!(
+            config.get_or_generate_request_nonce(request_id),
+            Some(late.clone())
+        );
+
+        drop(nonce);
+        assert_ne!(config.get_or_generate_request_nonce(request_id), Some(late));
+    }
+
+    #[actix_web::test]
+    async fn test_lazy_nonce_is_generated_for_rewritten_html() {
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .script_src([Source::Self_])
+                    .build_unchecked(),
+            )
+            .with_nonce_generator(16)
+            .with_lazy_nonce(true)
+            .with_html_nonce_rewriting(true)
+            .build();
+        let app = actix_test::init_service(App::new().wrap(CspMiddleware::new(config)).route(
+            "/",
+            web::get().to(|| async {
+                HttpResponse::Ok()
+                    .content_type("text/html")
+                    .body("<script>init()</script>")
+            }),
+        ))
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        let header = res
+            .headers()
+            .get("content-security-policy")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let body = actix_test::read_body(res).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        let nonce = header
+            .split("'nonce-")
+            .nth(1)
+            .and_then(|rest| rest.split('\'').next())
+            .unwrap();
+        assert_eq!(body, format!("<script nonce=\"{nonce}\">init()</script>"));
+    }
+
+    async fn allow_cdn(config: web::Data<actix_web_csp::CspConfig>) -> HttpResponse {
+        config.update_policy(|policy| {
+            policy.append_source("script-src", Source::Host("cdn.example.com".into()));
+        });
+        HttpResponse::NoContent().finish()
+    }
+
+    #[actix_web::test]
+    async fn test_app_data_updates_reach_the_wrapped_middleware() {
+        let middleware = csp_middleware(
+            CspPolicyBuilder::new()
+                .script_src([Source::Self_])
+                .build_unchecked(),
+        );
+        let app = actix_test::init_service(
+            App::new()
+                .configure(configure_csp_data(middleware.config()))
+                .wrap(middleware)
+                .route("/", web::get().to(HttpResponse::Ok))
+                .route("/admin/cdn", web::post().to(allow_cdn)),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::post()
+            .uri("/admin/cdn")
+            .to_request();
+        assert_eq!(
+            actix_test::call_service(&app, req).await.status(),
+            StatusCode::NO_CONTENT
+        );
+
+        let res = actix_test::call_service(&app, actix_test::TestRequest::get().to_request()).await;
+        assert_eq!(
+            res.headers().get("content-security-policy").unwrap(),
+            "script-src 'self' cdn.example.com"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_csp_with_reporting_shares_the_middleware_config() {
+        let (middleware, configure_reporting) = actix_web_csp::csp_with_reporting(
+            CspPolicyBuilder::new()
+                .script_src([Source::Self_])
+                .report_uri("/csp-report")
+                .build_unchecked(),
+            |_| {},
+        );
+        let config = middleware.config();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .configure(configure_reporting)
+                .route("/", web::get().to(HttpResponse::Ok))
+                .route("/admin/cdn", web::post().to(allow_cdn)),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::post()
+            .uri("/admin/cdn")
+            .to_request();
+        actix_test::call_service(&app, req).await;
+        let res = actix_test::call_service(&app, actix_test::TestRequest::get().to_request()).await;
+        assert_eq!(
+            res.headers().get("content-security-policy").unwrap(),
+            "script-src 'self' cdn.example.com; report-uri /csp-report"
+        );
+
+        let req = actix_web_csp::test::ViolationReportBuilder::new(
+            "https://evil.example/x.js",
+            "script-src",
+        )
+        .to_request("/csp-report")
+        .to_request();
+        actix_test::call_service(&app, req).await;
+        let expected = if cfg!(feature = "reporting") { 1 } else { 0 };
+        assert_eq!(config.stats().violation_count(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_static_nonce_scope_reuses_one_nonce() {
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .script_src([Source::Self_])
+                    .build_unchecked(),
+            )
+            .with_nonce_generator(16)
+            .with_nonce_scope(NonceScope::Static)
+            .with_lazy_nonce(true)
+            .build();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config.clone()))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let mut nonces = Vec::new();
+        for _ in 0..3 {
+            let res =
+                actix_test::call_service(&app, actix_test::TestRequest::get().to_request()).await;
+            nonces.push(nonce_of(&res));
+        }
+        assert!(!nonces[0].is_empty());
+        assert!(nonces.iter().all(|nonce| *nonce == nonces[0]));
+        #[cfg(feature = "stats")]
+        assert_eq!(config.stats().nonce_generation_count(), 1);
+    }
+
+    #[cfg(feature = "session")]
+    #[actix_web::test]
+    async fn test_session_nonce_scope_keeps_nonce_per_session() {
+        use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+        use actix_web::cookie::Key;
+
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .script_src([Source::Self_])
+                    .build_unchecked(),
+            )
+            .with_nonce_generator(16)
+            .with_nonce_scope(NonceScope::Session)
+            .build();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config))
+                .wrap(SessionMiddleware::new(
+                    CookieSessionStore::default(),
+                    Key::generate(),
+                ))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let first =
+            actix_test::call_service(&app, actix_test::TestRequest::get().to_request()).await;
+        let nonce = nonce_of(&first);
+        let cookie = first
+            .response()
+            .cookies()
+            .next()
+            .expect("session cookie")
+            .into_owned();
+
+        let same_session = actix_test::call_service(
+            &app,
+            actix_test::TestRequest::get().cookie(cookie).to_request(),
+        )
+        .await;
+        assert_eq!(nonce_of(&same_session), nonce);
+
+        let new_session =
+            actix_test::call_service(&app, actix_test::TestRequest::get().to_request()).await;
+        assert_ne!(nonce_of(&new_session), nonce);
+    }
+
+    #[actix_web::test]
+    async fn test_nonce_bearing_responses_are_marked_uncacheable() {
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .script_src([Source::Self_])
+                    .build_unchecked(),
+            )
+            .with_nonce_generator(16)
+            .with_lazy_nonce(true)
+            .with_nonce_cache_control(true)
+            .build();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config))
+                .route(
+                    "/asset",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .insert_header(("cache-control", "public, max-age=3600"))
+                            .finish()
+                    }),
+                )
+                .route(
+                    "/page",
+                    web::get().to(|req: HttpRequest| async move {
+                        HttpResponse::Ok()
+                            .insert_header(("cache-control", "public, max-age=3600"))
+                            .body(req.get_nonce().unwrap_or_default())
+                    }),
+                ),
+        )
+        .await;
+
+        let asset = actix_test::call_service(
+            &app,
+            actix_test::TestRequest::get().uri("/asset").to_request(),
+        )
+        .await;
+        assert_eq!(
+            asset.headers().get("cache-control").unwrap(),
+            "public, max-age=3600"
+        );
+
+        let page = actix_test::call_service(
+            &app,
+            actix_test::TestRequest::get().uri("/page").to_request(),
+        )
+        .await;
+        assert_eq!(
+            page.headers().get("cache-control").unwrap(),
+            "private, no-store"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_nonce_cache_control_value_is_configurable() {
+        let config = CspConfigBuilder::new()
+            .with_nonce_generator(16)
+            .with_nonce_per_request(true)
+            .with_nonce_cache_control_value("private, no-cache")
+            .build();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        assert_eq!(
+            res.headers().get("cache-control").unwrap(),
+            "private, no-cache"
+        );
+        assert!(CspConfigBuilder::new()
+            .with_nonce_cache_control_value("private\n")
+            .build()
+            .nonce_cache_control()
+            .is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_additional_policies_are_sent_as_separate_headers() {
+        let app_policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_])
+            .build_unchecked();
+        let baseline = CspPolicyBuilder::new()
+            .script_src([Source::Self_])
+            .object_src([Source::None])
+            .build_unchecked();
+        let mut monitored = CspPolicyBuilder::new()
+            .img_src([Source::Self_])
+            .build_unchecked();
+        monitored.set_report_only(true);
+
+        let config = CspConfigBuilder::new()
+            .policy(app_policy)
+            .with_nonce_generator(16)
+            .with_nonce_per_request(true)
+            .build();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(
+                    CspMiddleware::new(config)
+                        .with_additional_policy(baseline)
+                        .with_additional_policy(monitored),
+                )
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        let enforced: Vec<_> = res
+            .headers()
+            .get_all("content-security-policy")
+            .map(|value| value.to_str().unwrap().to_owned())
+            .collect();
+        assert_eq!(enforced.len(), 2);
+
+        let nonce = enforced[0]
+            .split("'nonce-")
+            .nth(1)
+            .and_then(|rest| rest.split('\'').next())
+            .unwrap();
+        assert_eq!(
+            enforced[1],
+            format!("script-src 'self' 'nonce-{nonce}'; object-src 'none'")
+        );
+        assert_eq!(
+            res.headers()
+                .get("content-security-policy-report-only")
+                .unwrap(),
+            "img-src 'self'"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_readiness_route_reflects_policy_health() {
+        let middleware = csp_middleware(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .build_unchecked(),
+        );
+        let config = middleware.config();
+        let app = actix_test::init_service(
+            App::new()
+                .configure(configure_csp_readiness("/ready/csp", config.clone()))
+                .wrap(middleware),
+        )
+        .await;
+        let probe = || {
+            actix_test::TestRequest::get()
+                .uri("/ready/csp")
+                .to_request()
+        };
+
+        let res = actix_test::call_service(&app, probe()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: serde_json::Value = actix_test::read_body_json(res).await;
+        assert_eq!(body["policy_compiled"], true);
+
+        config.update_policy(|policy| {
+            let mut directive = Directive::new("img-src");
+            directive.add_source(Source::Host("bad\nhost".into()));
+            policy.add_directive(directive);
+        });
+
+        let res = actix_test::call_service(&app, probe()).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = actix_test::read_body_json(res).await;
+        assert_eq!(body["last_reload_compiled"], false);
+    }
+
+    #[actix_web::test]
+    async fn test_startup_report_does_not_affect_responses() {
+        let middleware = csp_middleware(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .script_src([Source::Self_, Source::UnsafeInline])
+                .build_unchecked(),
+        )
+        .with_startup_report(PolicyAuditor::new());
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        assert!(res.headers().contains_key("content-security-policy"));
+    }
+
+    #[actix_web::test]
+    async fn test_header_generation_failures_are_counted() {
+        let config = CspConfigBuilder::new()
+            .policy(unserializable_policy())
+            .build();
+        let stats = config.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        for _ in 0..2 {
+            let res = actix_test::call_service(
+                &app,
+                actix_test::TestRequest::get().uri("/").to_request(),
+            )
+            .await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert!(res.headers().get("content-security-policy").is_none());
+        }
+
+        let expected = if cfg!(feature = "stats") { 2 } else { 0 };
+        assert_eq!(stats.header_generation_failure_count(), expected);
+        assert_eq!(stats.header_insertion_failure_count(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_nonce_header_name_is_counted() {
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .script_src([Source::Self_])
+                    .build_unchecked(),
+            )
+            .with_nonce_generator(16)
+            .with_nonce_per_request(true)
+            .with_nonce_request_header("bad header")
+            .build();
+        let stats = config.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        assert!(!nonce_of(&res).is_empty());
+
+        let expected = if cfg!(feature = "stats") { 1 } else { 0 };
+        assert_eq!(stats.header_insertion_failure_count(), expected);
+        assert_eq!(stats.header_generation_failure_count(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_reject_mode_replaces_unprotected_responses() {
+        let config = CspConfigBuilder::new()
+            .policy(unserializable_policy())
+            .with_header_failure_mode(HeaderFailureMode::Reject)
+            .with_header_failure_page("<h1>Temporarily unavailable</h1>")
+            .build();
+        let stats = config.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let error = actix_test::try_call_service(
+            &app,
+            actix_test::TestRequest::get().uri("/").to_request(),
+        )
+        .await
+        .unwrap_err();
+        let res = error.error_response();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            res.headers().get("content-security-policy").unwrap(),
+            "default-src 'none'"
+        );
+        assert_eq!(
+            res.headers().get("content-type").unwrap(),
+            "text/html; charset=utf-8"
+        );
+        let body = actix_web::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "<h1>Temporarily unavailable</h1>");
+
+        let expected = if cfg!(feature = "stats") { 1 } else { 0 };
+        assert_eq!(stats.rejected_response_count(), expected);
+        assert_eq!(stats.header_generation_failure_count(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_reject_mode_leaves_healthy_responses_alone() {
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .default_src([Source::Self_])
+                    .build_unchecked(),
+            )
+            .with_header_failure_mode(HeaderFailureMode::Reject)
+            .build();
+        let stats = config.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get("content-security-policy").unwrap(),
+            "default-src 'self'"
+        );
+        assert_eq!(stats.rejected_response_count(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_reject_mode_without_page_sends_empty_500() {
+        let middleware = CspMiddleware::new(
+            CspConfigBuilder::new()
+                .policy(unserializable_policy())
+                .with_header_failure_mode(HeaderFailureMode::Reject)
+                .build(),
+        );
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let error = actix_test::try_call_service(
+            &app,
+            actix_test::TestRequest::get().uri("/").to_request(),
+        )
+        .await
+        .unwrap_err();
+        let res = error.error_response();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = actix_web::body::to_bytes(res.into_body()).await.unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_fallback_mode_sends_minimal_policy() {
+        let config = CspConfigBuilder::new()
+            .policy(unserializable_policy())
+            .with_header_failure_mode(HeaderFailureMode::Fallback)
+            .build();
+        let stats = config.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get("content-security-policy").unwrap(),
+            "default-src 'self'"
+        );
+
+        let expected = if cfg!(feature = "stats") { 1 } else { 0 };
+        assert_eq!(stats.fallback_header_count(), expected);
+        assert_eq!(stats.header_generation_failure_count(), expected);
+        assert_eq!(stats.rejected_response_count(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_fallback_policy_keeps_report_only_mode() {
+        let mut policy = unserializable_policy();
+        policy.set_report_only(true);
+        let config = CspConfigBuilder::new()
+            .policy(policy)
+            .with_header_failure_mode(HeaderFailureMode::Fallback)
+            .build();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        assert_eq!(
+            res.headers()
+                .get("content-security-policy-report-only")
+                .unwrap(),
+            "default-src 'self'"
+        );
+        assert!(res.headers().get("content-security-policy").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_fallback_policy_allows_the_request_nonce() {
+        let config = CspConfigBuilder::new()
+            .policy(unserializable_policy())
+            .with_nonce_generator(16)
+            .with_nonce_per_request(true)
+            .with_header_failure_mode(HeaderFailureMode::Fallback)
+            .build();
+        let app = actix_test::init_service(App::new().wrap(CspMiddleware::new(config)).route(
+            "/",
+            web::get().to(|req: HttpRequest| async move {
+                HttpResponse::Ok().body(req.get_nonce().unwrap_or_default())
+            }),
+        ))
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        let header = res
+            .headers()
+            .get("content-security-policy")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let nonce = actix_test::read_body(res).await;
+        let nonce = std::str::from_utf8(&nonce).unwrap();
+
+        assert!(!nonce.is_empty());
+        assert_eq!(
+            header,
+            format!(
+                "default-src 'self'; script-src 'self' 'nonce-{nonce}'; style-src 'self' 'nonce-{nonce}'"
+            )
+        );
+    }
 }
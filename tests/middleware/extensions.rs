@@ -1,4 +1,17 @@
+use actix_web::{test as actix_test, web, App, HttpRequest, HttpResponse};
+use actix_web_csp::core::Directive;
 use actix_web_csp::security::HashAlgorithm;
+use actix_web_csp::{csp_middleware, csp_middleware_with_nonce, CspExtensions, CspResponseExt};
+use actix_web_csp::{CspConfig, CspHandle, CspPolicyBuilder, Source};
+
+fn csp_header<B>(res: &actix_web::dev::ServiceResponse<B>) -> String {
+    res.headers()
+        .get("content-security-policy")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
 
 #[cfg(test)]
 mod tests {
@@ -59,4 +72,166 @@ mod tests {
 
         assert!(!hash.is_empty());
     }
+
+    #[actix_web::test]
+    async fn test_registered_hashes_and_overrides_apply_to_one_response() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_])
+            .img_src([Source::Self_])
+            .build_unchecked();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(csp_middleware(policy))
+                .route(
+                    "/page",
+                    web::get().to(|req: HttpRequest| async move {
+                        req.register_hash("script-src", HashAlgorithm::Sha256, b"init()");
+                        let mut img_src = Directive::new("img-src");
+                        img_src.add_source(Source::Scheme("data".into()));
+                        req.override_directive(img_src);
+
+                        let snapshot = req.policy().unwrap();
+                        assert_eq!(
+                            snapshot.get_directive("img-src").unwrap().to_string(),
+                            "img-src data:"
+                        );
+
+                        HttpResponse::Ok()
+                            .append_csp_source("connect-src", Source::Self_)
+                            .finish()
+                    }),
+                )
+                .route("/plain", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res = actix_test::call_service(
+            &app,
+            actix_test::TestRequest::get().uri("/page").to_request(),
+        )
+        .await;
+        let header = csp_header(&res);
+        let hash = HashGenerator::generate(HashAlgorithm::Sha256, b"init()");
+        assert!(header.contains(&format!("script-src 'self' 'sha256-{hash}'")));
+        assert!(header.contains("img-src data:"));
+        assert!(header.contains("connect-src 'self'"));
+
+        let res = actix_test::call_service(
+            &app,
+            actix_test::TestRequest::get().uri("/plain").to_request(),
+        )
+        .await;
+        assert_eq!(
+            csp_header(&res),
+            "default-src 'self'; script-src 'self'; img-src 'self'"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_nonce_source_matches_header_nonce() {
+        let policy = CspPolicyBuilder::new()
+            .script_src([Source::Self_])
+            .build_unchecked();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(csp_middleware_with_nonce(policy, 16))
+                .route(
+                    "/",
+                    web::get().to(|req: HttpRequest| async move {
+                        match req.nonce_source() {
+                            Some(source) => HttpResponse::Ok().body(source.to_string()),
+                            None => HttpResponse::NotFound().finish(),
+                        }
+                    }),
+                ),
+        )
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        let header = csp_header(&res);
+        let source = actix_test::read_body(res).await;
+        let source = std::str::from_utf8(&source).unwrap();
+        assert!(source.starts_with("'nonce-"));
+        assert!(header.contains(source));
+    }
+
+    #[test]
+    fn test_policy_is_none_outside_the_middleware() {
+        let req = actix_test::TestRequest::default().to_http_request();
+
+        assert!(req.policy().is_none());
+        assert!(req.nonce_source().is_none());
+    }
+
+    #[test]
+    fn test_csp_extensions_is_dyn_compatible() {
+        let req = actix_test::TestRequest::default().to_http_request();
+        let extensions: &dyn CspExtensions = &req;
+
+        assert!(extensions.get_nonce().is_none());
+        assert!(extensions.policy().is_none());
+    }
+
+    async fn handle_summary(csp: CspHandle) -> HttpResponse {
+        HttpResponse::Ok().body(format!(
+            "{} {}",
+            csp.policy(),
+            csp.generate_nonce().is_some()
+        ))
+    }
+
+    #[actix_web::test]
+    async fn test_csp_handle_reads_middleware_config() {
+        let middleware = csp_middleware_with_nonce(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .build_unchecked(),
+            16,
+        );
+        let config = middleware.config();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .route("/", web::get().to(handle_summary)),
+        )
+        .await;
+
+        config.update_policy(|policy| {
+            policy.append_source("img-src", Source::Self_);
+        });
+        let res = actix_test::call_service(&app, actix_test::TestRequest::get().to_request()).await;
+        let body = actix_test::read_body(res).await;
+        assert_eq!(body, "default-src 'self'; img-src 'self' true");
+    }
+
+    #[actix_web::test]
+    async fn test_csp_handle_falls_back_to_app_data() {
+        let config = CspConfig::new(
+            CspPolicyBuilder::new()
+                .script_src([Source::Self_])
+                .build_unchecked(),
+        );
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .route("/", web::get().to(handle_summary)),
+        )
+        .await;
+
+        let res = actix_test::call_service(&app, actix_test::TestRequest::get().to_request()).await;
+        let body = actix_test::read_body(res).await;
+        assert_eq!(body, "script-src 'self' false");
+    }
+
+    #[actix_web::test]
+    async fn test_csp_handle_requires_a_config() {
+        let app =
+            actix_test::init_service(App::new().route("/", web::get().to(handle_summary))).await;
+
+        let res = actix_test::call_service(&app, actix_test::TestRequest::get().to_request()).await;
+        assert_eq!(res.status(), 500);
+    }
 }
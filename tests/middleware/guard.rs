@@ -0,0 +1,166 @@
+use actix_web::{test as actix_test, web, App, HttpResponse};
+use actix_web_csp::middleware::CspGuard;
+use actix_web_csp::{csp_middleware, CspPolicy, CspPolicyBuilder, Source};
+
+fn pinned_nonce_policy() -> CspPolicy {
+    CspPolicyBuilder::new()
+        .default_src([Source::Self_])
+        .script_src([Source::Self_, Source::Nonce("deploy-7f3a".into())])
+        .build_unchecked()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_guard_requires_policy_from_middleware() {
+        let app = actix_test::init_service(
+            App::new()
+                .route("/", web::get().guard(CspGuard::new()).to(HttpResponse::Ok))
+                .default_service(web::to(HttpResponse::NotFound)),
+        )
+        .await;
+
+        let res = actix_test::call_service(&app, actix_test::TestRequest::get().to_request()).await;
+        assert_eq!(res.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_guard_matches_required_directives() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(csp_middleware(pinned_nonce_policy()))
+                .route(
+                    "/scripts",
+                    web::get()
+                        .guard(CspGuard::new().with_directive("script-src"))
+                        .to(HttpResponse::Ok),
+                )
+                .route(
+                    "/trusted-types",
+                    web::get()
+                        .guard(CspGuard::new().with_directive("require-trusted-types-for"))
+                        .to(HttpResponse::Ok),
+                )
+                .default_service(web::to(HttpResponse::NotFound)),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::get().uri("/scripts").to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), 200);
+
+        let req = actix_test::TestRequest::get()
+            .uri("/trusted-types")
+            .to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), 404);
+    }
+
+    #[cfg(feature = "session")]
+    #[actix_web::test]
+    async fn test_guard_accepts_only_the_session_nonce() {
+        use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+        use actix_web::cookie::{Cookie, Key};
+        use actix_web::dev::ServiceResponse;
+        use actix_web_csp::{CspConfigBuilder, CspMiddleware, NonceScope};
+
+        let config = CspConfigBuilder::new()
+            .policy(pinned_nonce_policy())
+            .with_nonce_generator(16)
+            .with_nonce_scope(NonceScope::Session)
+            .build();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config))
+                .wrap(SessionMiddleware::new(
+                    CookieSessionStore::default(),
+                    Key::generate(),
+                ))
+                .route("/form", web::get().to(HttpResponse::Ok))
+                .route(
+                    "/transfer",
+                    web::post()
+                        .guard(CspGuard::new().with_nonce_header("x-csp-nonce"))
+                        .to(HttpResponse::Ok),
+                )
+                .default_service(web::to(HttpResponse::Forbidden)),
+        )
+        .await;
+
+        fn issued<B>(res: &ServiceResponse<B>) -> (String, Cookie<'static>) {
+            let header = res
+                .headers()
+                .get("content-security-policy")
+                .unwrap()
+                .to_str()
+                .unwrap();
+            let nonce = header
+                .split("'nonce-")
+                .skip(1)
+                .filter_map(|rest| rest.split('\'').next())
+                .find(|nonce| *nonce != "deploy-7f3a")
+                .unwrap()
+                .to_string();
+            let cookie = res.response().cookies().next().unwrap().into_owned();
+            (nonce, cookie)
+        }
+
+        let form = actix_test::TestRequest::get().uri("/form").to_request();
+        let (nonce, cookie) = issued(&actix_test::call_service(&app, form).await);
+        let form = actix_test::TestRequest::get().uri("/form").to_request();
+        let (other_nonce, _) = issued(&actix_test::call_service(&app, form).await);
+        assert_ne!(nonce, other_nonce);
+
+        let req = actix_test::TestRequest::post()
+            .uri("/transfer")
+            .cookie(cookie.clone())
+            .insert_header(("x-csp-nonce", nonce.as_str()))
+            .to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), 200);
+
+        // the nonce baked into the policy is public, and another session's
+        // nonce was issued to someone else
+        for forged in ["deploy-7f3a", other_nonce.as_str(), ""] {
+            let req = actix_test::TestRequest::post()
+                .uri("/transfer")
+                .cookie(cookie.clone())
+                .insert_header(("x-csp-nonce", forged))
+                .to_request();
+            let res = actix_test::call_service(&app, req).await;
+            assert_eq!(res.status(), 403, "nonce {forged:?}");
+        }
+
+        // without the session the nonce was issued in, it is stale
+        let req = actix_test::TestRequest::post()
+            .uri("/transfer")
+            .insert_header(("x-csp-nonce", nonce.as_str()))
+            .to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), 403);
+    }
+
+    #[cfg(feature = "session")]
+    #[actix_web::test]
+    async fn test_guard_rejects_nonce_pinned_in_policy() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(csp_middleware(pinned_nonce_policy()))
+                .route(
+                    "/transfer",
+                    web::post()
+                        .guard(CspGuard::new().with_nonce_header("x-csp-nonce"))
+                        .to(HttpResponse::Ok),
+                )
+                .default_service(web::to(HttpResponse::Forbidden)),
+        )
+        .await;
+
+        for nonce in [Some("deploy-7f3a"), Some(""), None] {
+            let mut req = actix_test::TestRequest::post().uri("/transfer");
+            if let Some(nonce) = nonce {
+                req = req.insert_header(("x-csp-nonce", nonce));
+            }
+            let res = actix_test::call_service(&app, req.to_request()).await;
+            assert_eq!(res.status(), 403, "nonce {nonce:?}");
+        }
+    }
+}
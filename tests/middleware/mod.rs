@@ -1,2 +1,14 @@
+#[cfg(feature = "reporting")]
+pub mod blocklist;
 pub mod csp;
 pub mod extensions;
+pub mod guard;
+pub mod proxy;
+#[cfg(feature = "reporting")]
+pub mod reporting;
+pub mod response;
+pub mod rewrite;
+pub mod routes;
+#[cfg(feature = "page-audit")]
+pub mod shadow;
+pub mod vhost;
@@ -0,0 +1,261 @@
+use actix_web::{test as actix_test, web, App, HttpMessage, HttpRequest, HttpResponse};
+use actix_web_csp::{
+    core::{CspConfigBuilder, CspPolicyBuilder, Source},
+    middleware::{CspMiddleware, ForwardedInfo, TrustedProxies},
+};
+use std::net::{IpAddr, SocketAddr};
+
+fn proxied_middleware(proxies: TrustedProxies) -> CspMiddleware {
+    CspMiddleware::new(
+        CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .default_src([Source::Self_])
+                    .build_unchecked(),
+            )
+            .with_upgrade_insecure_requests_on_tls(true)
+            .with_trusted_proxies(proxies)
+            .build(),
+    )
+}
+
+async fn forwarded_origin(req: HttpRequest) -> HttpResponse {
+    let origin = req
+        .extensions()
+        .get::<ForwardedInfo>()
+        .map(|info| format!("{} {:?}", info.origin(), info.client_ip))
+        .unwrap_or_default();
+    HttpResponse::Ok().body(origin)
+}
+
+#[cfg(feature = "verify")]
+async fn self_allows(req: HttpRequest) -> HttpResponse {
+    use actix_web_csp::CspExtensions;
+
+    let mut verifier = req.policy_verifier().unwrap();
+    let allowed = ["https://app.example.com/app.js", "http://10.0.0.2/app.js"]
+        .map(|uri| verifier.verify_uri(uri, "script-src").unwrap().to_string());
+    HttpResponse::Ok().body(allowed.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trusted_proxies_match_cidr_blocks() {
+        let proxies = TrustedProxies::new(["10.0.0.0/8", "192.168.1.1", "fd00::/8"]).unwrap();
+
+        assert!(proxies.contains("10.200.0.1".parse().unwrap()));
+        assert!(proxies.contains("192.168.1.1".parse().unwrap()));
+        assert!(!proxies.contains("192.168.1.2".parse().unwrap()));
+        assert!(proxies.contains("fd12::1".parse().unwrap()));
+        assert!(proxies.contains("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(!proxies.contains("2001:db8::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_trusted_proxies_reject_invalid_networks() {
+        assert!(TrustedProxies::new(["10.0.0.0/33"]).is_err());
+        assert!(TrustedProxies::new(["proxy.internal"]).is_err());
+        assert!(TrustedProxies::new(["10.0.0.0/x"]).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_forwarded_headers_from_trusted_proxy_are_resolved() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(proxied_middleware(
+                    TrustedProxies::new(["10.0.0.0/8"]).unwrap(),
+                ))
+                .route("/", web::get().to(forwarded_origin)),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::get()
+            .uri("/")
+            .peer_addr(SocketAddr::from(([10, 0, 0, 2], 4000)))
+            .insert_header(("x-forwarded-proto", "https"))
+            .insert_header(("x-forwarded-host", "app.example.com"))
+            .insert_header(("x-forwarded-for", "203.0.113.9, 10.0.0.7"))
+            .to_request();
+        let res = actix_test::call_service(&app, req).await;
+
+        assert_eq!(
+            res.headers().get("content-security-policy").unwrap(),
+            "default-src 'self'; upgrade-insecure-requests"
+        );
+        let expected = format!(
+            "https://app.example.com {:?}",
+            Some("203.0.113.9".parse::<IpAddr>().unwrap())
+        );
+        assert_eq!(actix_test::read_body(res).await, expected);
+    }
+
+    #[actix_web::test]
+    async fn test_forwarded_headers_from_untrusted_peer_are_ignored() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(proxied_middleware(
+                    TrustedProxies::new(["10.0.0.0/8"]).unwrap(),
+                ))
+                .route("/", web::get().to(forwarded_origin)),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::get()
+            .uri("/")
+            .peer_addr(SocketAddr::from(([198, 51, 100, 4], 4000)))
+            .insert_header(("host", "internal:8080"))
+            .insert_header(("forwarded", "for=203.0.113.9;proto=https;host=evil.example"))
+            .to_request();
+        let res = actix_test::call_service(&app, req).await;
+
+        assert_eq!(
+            res.headers().get("content-security-policy").unwrap(),
+            "default-src 'self'"
+        );
+        let expected = format!(
+            "http://internal:8080 {:?}",
+            Some("198.51.100.4".parse::<IpAddr>().unwrap())
+        );
+        assert_eq!(actix_test::read_body(res).await, expected);
+    }
+
+    #[actix_web::test]
+    async fn test_client_supplied_forwarded_hops_are_skipped() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(proxied_middleware(
+                    TrustedProxies::new(["10.0.0.0/8"]).unwrap(),
+                ))
+                .route("/", web::get().to(forwarded_origin)),
+        )
+        .await;
+
+        // The client forged the first hop; the trusted proxy appended the second
+        let req = actix_test::TestRequest::get()
+            .uri("/")
+            .peer_addr(SocketAddr::from(([10, 0, 0, 2], 4000)))
+            .insert_header((
+                "forwarded",
+                "for=198.51.100.1;proto=https;host=evil.example, \
+                 for=203.0.113.9;proto=http;host=app.example.com",
+            ))
+            .to_request();
+        let res = actix_test::call_service(&app, req).await;
+
+        assert_eq!(
+            res.headers().get("content-security-policy").unwrap(),
+            "default-src 'self'"
+        );
+        let expected = format!(
+            "http://app.example.com {:?}",
+            Some("203.0.113.9".parse::<IpAddr>().unwrap())
+        );
+        assert_eq!(actix_test::read_body(res).await, expected);
+    }
+
+    #[actix_web::test]
+    async fn test_client_supplied_x_forwarded_values_are_skipped() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(proxied_middleware(
+                    TrustedProxies::new(["10.0.0.0/8"]).unwrap(),
+                ))
+                .route("/", web::get().to(forwarded_origin)),
+        )
+        .await;
+
+        // Two trusted proxies appended to what the client sent
+        let req = actix_test::TestRequest::get()
+            .uri("/")
+            .peer_addr(SocketAddr::from(([10, 0, 0, 2], 4000)))
+            .insert_header(("x-forwarded-proto", "https, http, http"))
+            .insert_header((
+                "x-forwarded-host",
+                "evil.example, app.example.com, app.example.com",
+            ))
+            .insert_header(("x-forwarded-for", "198.51.100.1, 203.0.113.9, 10.0.0.7"))
+            .to_request();
+        let res = actix_test::call_service(&app, req).await;
+
+        assert_eq!(
+            res.headers().get("content-security-policy").unwrap(),
+            "default-src 'self'"
+        );
+        let expected = format!(
+            "http://app.example.com {:?}",
+            Some("203.0.113.9".parse::<IpAddr>().unwrap())
+        );
+        assert_eq!(actix_test::read_body(res).await, expected);
+    }
+
+    #[cfg(feature = "reporting")]
+    #[actix_web::test]
+    async fn test_reporting_middleware_resolves_client_ip() {
+        use actix_web_csp::middleware::CspReportingMiddleware;
+        use actix_web_csp::test::ViolationReportBuilder;
+        use std::sync::{Arc, Mutex};
+
+        let received = Arc::new(Mutex::new(None));
+        let sink = received.clone();
+        let app = actix_test::init_service(
+            App::new().wrap(
+                CspReportingMiddleware::new(move |report| {
+                    *sink.lock().unwrap() = report.client_ip;
+                })
+                .with_trusted_proxies(TrustedProxies::new(["127.0.0.1"]).unwrap()),
+            ),
+        )
+        .await;
+
+        let req = ViolationReportBuilder::new("https://evil.example/x.js", "script-src")
+            .to_request("/csp-report")
+            .peer_addr(SocketAddr::from(([127, 0, 0, 1], 9000)))
+            .insert_header(("x-forwarded-for", "203.0.113.9"))
+            .to_request();
+        let res = actix_test::call_service(&app, req).await;
+
+        assert!(res.status().is_success());
+        assert_eq!(
+            *received.lock().unwrap(),
+            Some("203.0.113.9".parse().unwrap())
+        );
+    }
+
+    #[cfg(feature = "verify")]
+    #[actix_web::test]
+    async fn test_policy_verifier_matches_self_against_forwarded_origin() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(proxied_middleware(
+                    TrustedProxies::new(["10.0.0.0/8"]).unwrap(),
+                ))
+                .route("/", web::get().to(self_allows)),
+        )
+        .await;
+
+        let proxied = || {
+            actix_test::TestRequest::get()
+                .uri("/")
+                .insert_header(("host", "10.0.0.2"))
+                .insert_header(("x-forwarded-proto", "https"))
+                .insert_header(("x-forwarded-host", "app.example.com"))
+                .insert_header(("x-forwarded-for", "203.0.113.9"))
+        };
+
+        let req = proxied()
+            .peer_addr(SocketAddr::from(([10, 0, 0, 7], 4000)))
+            .to_request();
+        let body = actix_test::call_and_read_body(&app, req).await;
+        assert_eq!(body, "true false");
+
+        // an untrusted peer cannot move 'self' to another origin
+        let req = proxied()
+            .peer_addr(SocketAddr::from(([203, 0, 113, 9], 4000)))
+            .to_request();
+        let body = actix_test::call_and_read_body(&app, req).await;
+        assert_eq!(body, "false true");
+    }
+}
@@ -0,0 +1,770 @@
+use actix_web::{guard, http::StatusCode, test as actix_test, web, App, HttpResponse};
+use actix_web_csp::middleware::{CspReportingMiddleware, ReportPath};
+use actix_web_csp::monitoring::{MalformedReport, OverflowStrategy, ReportRouter};
+use actix_web_csp::test::ViolationReportBuilder;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+fn counting_middleware() -> (CspReportingMiddleware, Arc<Mutex<usize>>) {
+    let received = Arc::new(Mutex::new(0));
+    let sink = received.clone();
+    let middleware = CspReportingMiddleware::new(move |_| *sink.lock().unwrap() += 1);
+    (middleware, received)
+}
+
+fn report_to(path: &str) -> actix_test::TestRequest {
+    ViolationReportBuilder::new("https://evil.example/x.js", "script-src").to_request(path)
+}
+
+async fn fallback() -> HttpResponse {
+    HttpResponse::NotFound().finish()
+}
+
+/// A queued middleware whose handler reports each report it starts on and then
+/// waits for a release before recording it.
+struct HeldQueue {
+    middleware: CspReportingMiddleware,
+    started: Receiver<String>,
+    release: SyncSender<()>,
+    delivered: Arc<Mutex<Vec<String>>>,
+}
+
+fn held_queue(capacity: usize, overflow: OverflowStrategy) -> HeldQueue {
+    let (started_tx, started) = mpsc::sync_channel(16);
+    let (release, release_rx) = mpsc::sync_channel::<()>(16);
+    let release_rx = Mutex::new(release_rx);
+    let delivered: Arc<Mutex<Vec<String>>> = Arc::default();
+    let sink = delivered.clone();
+    let middleware = CspReportingMiddleware::new(move |report| {
+        let _ = started_tx.send(report.blocked_uri.clone());
+        let _ = release_rx.lock().unwrap().recv();
+        sink.lock().unwrap().push(report.blocked_uri);
+    })
+    .with_report_queue(capacity, overflow);
+    HeldQueue {
+        middleware,
+        started,
+        release,
+        delivered,
+    }
+}
+
+fn report_for(index: usize) -> actix_http::Request {
+    ViolationReportBuilder::new(format!("https://cdn{index}.example/x.js"), "script-src")
+        .to_request("/csp-report")
+        .to_request()
+}
+
+/// A report request whose body never arrives.
+fn stalled_report() -> actix_http::Request {
+    let mut req = actix_test::TestRequest::post()
+        .uri("/csp-report")
+        .insert_header(("content-type", "application/csp-report"))
+        .to_request();
+    *req.payload() = actix_http::Payload::Stream {
+        payload: Box::pin(futures::stream::pending()),
+    };
+    req
+}
+
+fn wait_for_deliveries(delivered: &Mutex<Vec<String>>, count: usize) -> Vec<String> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while delivered.lock().unwrap().len() < count && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    delivered.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_path_matching() {
+        let exact = ReportPath::from("/csp-report/");
+        assert!(exact.matches("/csp-report"));
+        assert!(exact.matches("/csp-report/"));
+        assert!(!exact.matches("/csp-report/extra"));
+        assert!(!exact.matches("/csp-reports"));
+
+        let prefix = ReportPath::prefix("/reports");
+        assert!(prefix.matches("/reports"));
+        assert!(prefix.matches("/reports/csp"));
+        assert!(!prefix.matches("/reportsx"));
+
+        assert!(ReportPath::prefix("/").matches("/anything"));
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_accepts_multiple_paths_and_trailing_slash() {
+        let (middleware, received) = counting_middleware();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware.with_report_paths([
+                    ReportPath::exact("/csp-report"),
+                    ReportPath::prefix("/reports"),
+                ]))
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        for path in ["/csp-report/", "/reports/csp", "/other"] {
+            let res = actix_test::call_service(&app, report_to(path).to_request()).await;
+            assert_eq!(res.status().is_success(), path != "/other", "{path}");
+        }
+        assert_eq!(*received.lock().unwrap(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_matches_scope_relative_path() {
+        let (middleware, received) = counting_middleware();
+        let app = actix_test::init_service(
+            App::new().service(
+                web::scope("/api")
+                    .wrap(middleware)
+                    .default_service(web::to(fallback)),
+            ),
+        )
+        .await;
+
+        let res = actix_test::call_service(&app, report_to("/api/csp-report").to_request()).await;
+        assert!(res.status().is_success());
+        assert_eq!(*received.lock().unwrap(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_applies_guards() {
+        let (middleware, received) = counting_middleware();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware.with_report_guard(guard::Host("reports.example.com")))
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        let res = actix_test::call_service(&app, report_to("/csp-report").to_request()).await;
+        assert!(res.status().is_client_error());
+
+        let req = report_to("/csp-report")
+            .insert_header(("host", "reports.example.com"))
+            .to_request();
+        let res = actix_test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+        assert_eq!(*received.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_reporting_middleware_with_guards_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let (middleware, _) = counting_middleware();
+        assert_send_sync(&middleware.with_report_guard(guard::Host("reports.example.com")));
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_rejects_oversized_reports_early() {
+        let (middleware, received) = counting_middleware();
+        let middleware = middleware.with_max_report_size(64);
+        let stats = middleware.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        let declared = actix_test::TestRequest::post()
+            .uri("/csp-report")
+            .set_payload(vec![b'x'; 4096])
+            .to_request();
+        let error = actix_test::try_call_service(&app, declared)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.as_response_error().status_code(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        let understated = actix_test::TestRequest::post()
+            .uri("/csp-report")
+            .set_payload(vec![b'x'; 4096])
+            .insert_header(("content-length", "32"))
+            .to_request();
+        let error = actix_test::try_call_service(&app, understated)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.as_response_error().status_code(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        assert_eq!(*received.lock().unwrap(), 0);
+        let expected = if cfg!(feature = "stats") { 2 } else { 0 };
+        assert_eq!(stats.oversized_report_count(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_honours_app_payload_limit() {
+        let (middleware, received) = counting_middleware();
+        let stats = middleware.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::PayloadConfig::new(32))
+                .wrap(middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        let error = actix_test::try_call_service(&app, report_to("/csp-report").to_request())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.as_response_error().status_code(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(*received.lock().unwrap(), 0);
+        let expected = if cfg!(feature = "stats") { 1 } else { 0 };
+        assert_eq!(stats.oversized_report_count(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_rejects_compressed_reports() {
+        let (middleware, received) = counting_middleware();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        let req = report_to("/csp-report")
+            .insert_header(("content-encoding", "gzip"))
+            .to_request();
+        let error = actix_test::try_call_service(&app, req).await.unwrap_err();
+        assert_eq!(
+            error.as_response_error().status_code(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        let req = report_to("/csp-report")
+            .insert_header(("content-encoding", "identity"))
+            .to_request();
+        let res = actix_test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+        assert_eq!(*received.lock().unwrap(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_hands_malformed_reports_to_handler() {
+        let captured: Arc<Mutex<Vec<MalformedReport>>> = Arc::default();
+        let sink = captured.clone();
+        let (middleware, received) = counting_middleware();
+        let middleware = middleware
+            .with_malformed_report_handler(move |report| sink.lock().unwrap().push(report));
+        let stats = middleware.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        for body in [&b"not json"[..], br#"{"other": {}}"#] {
+            let req = actix_test::TestRequest::post()
+                .uri("/csp-report")
+                .set_payload(body)
+                .to_request();
+            let res = actix_test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        }
+
+        let captured = captured.lock().unwrap();
+        assert_eq!(captured.len(), 2);
+        assert_eq!(captured[0].body, &b"not json"[..]);
+        assert!(captured[1].reason.contains("csp-report"));
+        assert_eq!(*received.lock().unwrap(), 0);
+        let expected = if cfg!(feature = "stats") { 2 } else { 0 };
+        assert_eq!(stats.malformed_report_count(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_can_reject_malformed_reports() {
+        let (middleware, _) = counting_middleware();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware.with_reject_malformed_reports(true))
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::post()
+            .uri("/csp-report")
+            .set_payload("{")
+            .to_request();
+        let res = actix_test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let res = actix_test::call_service(&app, report_to("/csp-report").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_fingerprints_clients() {
+        use actix_web::http::header::USER_AGENT;
+
+        let fingerprints: Arc<Mutex<Vec<Option<String>>>> = Arc::default();
+        let sink = fingerprints.clone();
+        let middleware = CspReportingMiddleware::new(move |report| {
+            sink.lock().unwrap().push(report.client_fingerprint)
+        })
+        .with_client_fingerprinting(true);
+        let stats = middleware.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        for (peer, user_agent) in [
+            ("198.51.100.7:4000", "Extension/1.0"),
+            ("198.51.100.7:4001", "Extension/1.0"),
+            ("198.51.100.7:4002", "Extension/1.0"),
+            ("203.0.113.9:4000", "Browser/2.0"),
+        ] {
+            let req = report_to("/csp-report")
+                .peer_addr(peer.parse().unwrap())
+                .insert_header((USER_AGENT, user_agent))
+                .to_request();
+            actix_test::call_service(&app, req).await;
+        }
+
+        let fingerprints = fingerprints.lock().unwrap();
+        assert!(fingerprints.iter().all(Option::is_some));
+        assert_eq!(fingerprints[0], fingerprints[2]);
+        assert_ne!(fingerprints[0], fingerprints[3]);
+
+        let top = stats.top_client_fingerprints(1);
+        if cfg!(feature = "stats") {
+            assert_eq!(top, [(fingerprints[0].clone().unwrap(), 3)]);
+        } else {
+            assert!(top.is_empty());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_does_not_fingerprint_by_default() {
+        let fingerprints: Arc<Mutex<Vec<Option<String>>>> = Arc::default();
+        let sink = fingerprints.clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspReportingMiddleware::new(move |report| {
+                    sink.lock().unwrap().push(report.client_fingerprint)
+                }))
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        actix_test::call_service(&app, report_to("/csp-report").to_request()).await;
+
+        assert_eq!(*fingerprints.lock().unwrap(), [None]);
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_survives_panicking_handler() {
+        let fallback_seen: Arc<Mutex<Vec<String>>> = Arc::default();
+        let sink = fallback_seen.clone();
+        let middleware = CspReportingMiddleware::new(|report| {
+            if report.blocked_uri.contains("boom") {
+                panic!("handler failed");
+            }
+        })
+        .with_handler_panic_fallback(move |report| {
+            sink.lock().unwrap().push(report.blocked_uri.clone())
+        });
+        let stats = middleware.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        for blocked_uri in ["https://boom.example/x.js", "https://fine.example/x.js"] {
+            let req = ViolationReportBuilder::new(blocked_uri, "script-src")
+                .to_request("/csp-report")
+                .to_request();
+            let res = actix_test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+
+        assert_eq!(
+            *fallback_seen.lock().unwrap(),
+            ["https://boom.example/x.js"]
+        );
+        let expected = if cfg!(feature = "stats") { 1 } else { 0 };
+        assert_eq!(stats.handler_panic_count(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_survives_panicking_enricher() {
+        let (middleware, received) = counting_middleware();
+        let middleware =
+            middleware.with_enricher(|_: &mut actix_web_csp::monitoring::CspViolationReport| {
+                panic!("lookup failed")
+            });
+        let stats = middleware.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        let res = actix_test::call_service(&app, report_to("/csp-report").to_request()).await;
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(*received.lock().unwrap(), 0);
+        let expected = if cfg!(feature = "stats") { 1 } else { 0 };
+        assert_eq!(stats.handler_panic_count(), expected);
+    }
+
+    #[test]
+    fn test_report_router_matches_directive_families() {
+        let router = ReportRouter::new()
+            .with_route("script-src", |_| {})
+            .with_route("frame-ancestors", |_| {});
+        let report = |directive: &str| ViolationReportBuilder::new("inline", directive);
+
+        assert!(router.has_route(report("script-src").report()));
+        assert!(router.has_route(report("Script-Src-Elem").report()));
+        assert!(router.has_route(report("frame-ancestors").report()));
+        assert!(!router.has_route(report("script-src-foo").report()));
+        assert!(!router.has_route(report("img-src").report()));
+
+        let mut legacy = report("").report().clone();
+        legacy.violated_directive = "script-src-attr 'self'".to_string();
+        assert!(router.has_route(&legacy));
+    }
+
+    #[actix_web::test]
+    async fn test_clickjacking_alert_receives_framing_reports() {
+        let alerts: Arc<Mutex<Vec<String>>> = Arc::default();
+        let sink = alerts.clone();
+        let (middleware, received) = counting_middleware();
+        let middleware =
+            middleware.with_router(ReportRouter::new().with_clickjacking_alert(move |report| {
+                sink.lock().unwrap().push(report.document_uri)
+            }));
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        for directive in ["frame-ancestors", "script-src"] {
+            let req = ViolationReportBuilder::new("https://evil.example/", directive)
+                .to_request("/csp-report")
+                .to_request();
+            let res = actix_test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+
+        assert_eq!(alerts.lock().unwrap().len(), 1);
+        assert_eq!(*received.lock().unwrap(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_routes_reports_by_directive() {
+        let seen: Arc<Mutex<Vec<(&str, String)>>> = Arc::default();
+        let (scripts, framing, other) = (seen.clone(), seen.clone(), seen.clone());
+        let router = ReportRouter::new()
+            .with_route("script-src", move |report| {
+                scripts
+                    .lock()
+                    .unwrap()
+                    .push(("scripts", report.blocked_uri))
+            })
+            .with_route("frame-ancestors", move |report| {
+                framing
+                    .lock()
+                    .unwrap()
+                    .push(("framing", report.blocked_uri))
+            });
+        let middleware = CspReportingMiddleware::new(move |report| {
+            other.lock().unwrap().push(("other", report.blocked_uri))
+        })
+        .with_router(router);
+        let stats = middleware.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        for (blocked_uri, directive) in [
+            ("https://a.example/x.js", "script-src-elem"),
+            ("https://evil.example/", "frame-ancestors"),
+            ("https://b.example/x.png", "img-src"),
+        ] {
+            let req = ViolationReportBuilder::new(blocked_uri, directive)
+                .to_request("/csp-report")
+                .to_request();
+            let res = actix_test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                ("scripts", "https://a.example/x.js".to_string()),
+                ("framing", "https://evil.example/".to_string()),
+                ("other", "https://b.example/x.png".to_string()),
+            ]
+        );
+        let expected = if cfg!(feature = "stats") { 3 } else { 0 };
+        assert_eq!(stats.violation_count(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_queues_reports_for_handler_thread() {
+        let threads: Arc<Mutex<Vec<Option<String>>>> = Arc::default();
+        let sink = threads.clone();
+        let middleware = CspReportingMiddleware::new(move |_| {
+            let name = std::thread::current().name().map(str::to_string);
+            sink.lock().unwrap().push(name);
+        })
+        .with_report_queue(8, OverflowStrategy::DropOldest);
+        let stats = middleware.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        let res = actix_test::call_service(&app, report_to("/csp-report").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while threads.lock().unwrap().is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(
+            *threads.lock().unwrap(),
+            [Some("csp-report-queue".to_string())]
+        );
+        assert_eq!(stats.report_queue_depth(), 0);
+        assert_eq!(stats.dropped_report_count(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_report_queue_drop_newest_keeps_queued_reports() {
+        let held = held_queue(1, OverflowStrategy::DropNewest);
+        let stats = held.middleware.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(held.middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        actix_test::call_service(&app, report_for(0)).await;
+        held.started.recv_timeout(Duration::from_secs(5)).unwrap();
+        for index in 1..4 {
+            let res = actix_test::call_service(&app, report_for(index)).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+
+        let (dropped, depth) = if cfg!(feature = "stats") {
+            (2, 1)
+        } else {
+            (0, 0)
+        };
+        assert_eq!(stats.dropped_report_count(), dropped);
+        assert_eq!(stats.report_queue_depth(), depth);
+
+        for _ in 0..2 {
+            held.release.send(()).unwrap();
+        }
+        assert_eq!(
+            wait_for_deliveries(&held.delivered, 2),
+            ["https://cdn0.example/x.js", "https://cdn1.example/x.js"]
+        );
+        assert_eq!(stats.report_queue_depth(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_report_queue_drop_oldest_keeps_latest_reports() {
+        let held = held_queue(1, OverflowStrategy::DropOldest);
+        let stats = held.middleware.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(held.middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        actix_test::call_service(&app, report_for(0)).await;
+        held.started.recv_timeout(Duration::from_secs(5)).unwrap();
+        for index in 1..4 {
+            actix_test::call_service(&app, report_for(index)).await;
+        }
+
+        let expected = if cfg!(feature = "stats") { 2 } else { 0 };
+        assert_eq!(stats.dropped_report_count(), expected);
+
+        for _ in 0..2 {
+            held.release.send(()).unwrap();
+        }
+        assert_eq!(
+            wait_for_deliveries(&held.delivered, 2),
+            ["https://cdn0.example/x.js", "https://cdn3.example/x.js"]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_report_queue_block_waits_for_room() {
+        let held = held_queue(1, OverflowStrategy::Block);
+        let stats = held.middleware.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(held.middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        actix_test::call_service(&app, report_for(0)).await;
+        held.started.recv_timeout(Duration::from_secs(5)).unwrap();
+        actix_test::call_service(&app, report_for(1)).await;
+
+        let release = held.release.clone();
+        let releaser = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            for _ in 0..3 {
+                release.send(()).unwrap();
+            }
+        });
+        let res = actix_test::call_service(&app, report_for(2)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        releaser.join().unwrap();
+
+        assert_eq!(
+            wait_for_deliveries(&held.delivered, 3),
+            [
+                "https://cdn0.example/x.js",
+                "https://cdn1.example/x.js",
+                "https://cdn2.example/x.js"
+            ]
+        );
+        assert_eq!(stats.dropped_report_count(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_times_out_slow_report_bodies() {
+        let (middleware, received) = counting_middleware();
+        let middleware = middleware.with_report_read_timeout(Duration::from_millis(50));
+        let stats = middleware.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        let error = actix_test::try_call_service(&app, stalled_report())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.as_response_error().status_code(),
+            StatusCode::REQUEST_TIMEOUT
+        );
+        assert_eq!(*received.lock().unwrap(), 0);
+        let expected = if cfg!(feature = "stats") { 1 } else { 0 };
+        assert_eq!(stats.timed_out_report_count(), expected);
+
+        let res = actix_test::call_service(&app, report_for(0)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_sheds_reports_over_concurrency_limit() {
+        use actix_web::dev::Service;
+
+        let (middleware, received) = counting_middleware();
+        let middleware = middleware.with_max_concurrent_reports(1);
+        assert_eq!(middleware.max_concurrent_reports(), Some(1));
+        let stats = middleware.stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        let mut stalled = Box::pin(app.call(stalled_report()));
+        assert!(futures::poll!(&mut stalled).is_pending());
+
+        let res = actix_test::call_service(&app, report_for(0)).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(*received.lock().unwrap(), 0);
+        let expected = if cfg!(feature = "stats") { 1 } else { 0 };
+        assert_eq!(stats.shed_report_count(), expected);
+
+        drop(stalled);
+        let res = actix_test::call_service(&app, report_for(1)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(*received.lock().unwrap(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_configured_reporting_route_applies_the_middleware_limits() {
+        use actix_web::dev::Service;
+
+        let (middleware, received) = counting_middleware();
+        let (csp, configure_reporting) = actix_web_csp::middleware::csp_with_configured_reporting(
+            actix_web_csp::CspPolicyBuilder::new()
+                .default_src([actix_web_csp::Source::Self_])
+                .report_uri("/csp-report")
+                .build_unchecked(),
+            middleware
+                .with_report_read_timeout(Duration::from_millis(50))
+                .with_max_concurrent_reports(1),
+        );
+        let stats = csp.config().stats().clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(csp)
+                .configure(configure_reporting)
+                .default_service(web::to(fallback)),
+        )
+        .await;
+
+        let mut stalled = Box::pin(app.call(stalled_report()));
+        assert!(futures::poll!(&mut stalled).is_pending());
+        let res = actix_test::call_service(&app, report_for(0)).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let error = stalled.await.unwrap_err();
+        assert_eq!(
+            error.as_response_error().status_code(),
+            StatusCode::REQUEST_TIMEOUT
+        );
+        let expected = if cfg!(feature = "stats") { 1 } else { 0 };
+        assert_eq!(stats.shed_report_count(), expected);
+        assert_eq!(stats.timed_out_report_count(), expected);
+
+        let res = actix_test::call_service(&app, report_for(1)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(*received.lock().unwrap(), 1);
+
+        let req = actix_test::TestRequest::get()
+            .uri("/csp-report")
+            .to_request();
+        let res = actix_test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
@@ -0,0 +1,149 @@
+use actix_web::{test, web, App, HttpMessage, HttpRequest, HttpResponse};
+use actix_web_csp::{
+    csp_middleware, csp_middleware_with_nonce, CspDisabled, CspPolicy, CspPolicyBuilder,
+    CspResponseExt, Source,
+};
+
+fn base_policy() -> CspPolicy {
+    CspPolicyBuilder::new()
+        .default_src([Source::Self_])
+        .script_src([Source::Self_])
+        .build_unchecked()
+}
+
+async fn nonce_page(req: HttpRequest) -> HttpResponse {
+    HttpResponse::Ok().csp_nonce_body(
+        &req,
+        r#"<script nonce="{{csp-nonce}}">init()</script><style nonce="{{csp-nonce}}"></style>"#,
+    )
+}
+
+async fn widened_page() -> HttpResponse {
+    HttpResponse::Ok()
+        .append_csp_source("script-src", Source::Host("widgets.example.com".into()))
+        .append_csp_source("img-src", Source::Scheme("data".into()))
+        .finish()
+}
+
+async fn oauth_callback() -> HttpResponse {
+    HttpResponse::Ok().disable_csp().finish()
+}
+
+async fn sdk_rendered_page(req: HttpRequest) -> HttpResponse {
+    req.extensions_mut().insert(CspDisabled);
+    HttpResponse::Ok()
+        .content_type("text/html")
+        .body("<script>sdk()</script>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_csp_nonce_body_substitutes_request_nonce() {
+        let app = test::init_service(
+            App::new()
+                .wrap(csp_middleware_with_nonce(base_policy(), 16))
+                .route("/", web::get().to(nonce_page)),
+        )
+        .await;
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        let header = resp
+            .headers()
+            .get("content-security-policy")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "text/html; charset=utf-8"
+        );
+
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(!body.contains("{{csp-nonce}}"));
+
+        let nonce = body
+            .split("nonce=\"")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap();
+        assert!(!nonce.is_empty());
+        assert!(header.contains(&format!("'nonce-{nonce}'")));
+    }
+
+    #[actix_web::test]
+    async fn test_csp_nonce_body_without_nonce_clears_placeholder() {
+        let app = test::init_service(
+            App::new()
+                .wrap(csp_middleware(base_policy()))
+                .route("/", web::get().to(nonce_page)),
+        )
+        .await;
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains(r#"<script nonce="">"#));
+    }
+
+    #[actix_web::test]
+    async fn test_append_csp_source_extends_only_that_response() {
+        let app = test::init_service(
+            App::new()
+                .wrap(csp_middleware(base_policy()))
+                .route("/widened", web::get().to(widened_page))
+                .route("/plain", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/widened").to_request()).await;
+        let header = resp.headers().get("content-security-policy").unwrap();
+        assert_eq!(
+            header,
+            "default-src 'self'; script-src 'self' widgets.example.com; img-src 'self' data:"
+        );
+
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/plain").to_request()).await;
+        let header = resp.headers().get("content-security-policy").unwrap();
+        assert_eq!(header, "default-src 'self'; script-src 'self'");
+    }
+
+    #[actix_web::test]
+    async fn test_disable_csp_suppresses_header() {
+        let app = test::init_service(
+            App::new()
+                .wrap(csp_middleware(base_policy()))
+                .route("/callback", web::get().to(oauth_callback)),
+        )
+        .await;
+
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/callback").to_request()).await;
+        assert!(resp.headers().get("content-security-policy").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_csp_disabled_request_extension_suppresses_headers() {
+        let app = test::init_service(
+            App::new()
+                .wrap(csp_middleware_with_nonce(base_policy(), 16))
+                .route("/sdk", web::get().to(sdk_rendered_page))
+                .route("/plain", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/sdk").to_request()).await;
+        assert!(resp.headers().get("content-security-policy").is_none());
+        let body = test::read_body(resp).await;
+        assert_eq!(body, "<script>sdk()</script>");
+
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/plain").to_request()).await;
+        assert!(resp.headers().get("content-security-policy").is_some());
+    }
+}
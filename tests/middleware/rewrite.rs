@@ -0,0 +1,323 @@
+use actix_web::{http::StatusCode, test, web, App, HttpRequest, HttpResponse};
+use actix_web_csp::{
+    CspConfigBuilder, CspMiddleware, CspPolicy, CspPolicyBuilder, CspResponseExt, Source,
+};
+use bytes::Bytes;
+use futures::stream;
+
+fn rewriting_middleware(enabled: bool) -> CspMiddleware {
+    let policy: CspPolicy = CspPolicyBuilder::new()
+        .default_src([Source::Self_])
+        .script_src([Source::Self_])
+        .style_src([Source::Self_])
+        .build_unchecked();
+
+    CspMiddleware::new(
+        CspConfigBuilder::new()
+            .policy(policy)
+            .with_nonce_generator(16)
+            .with_html_nonce_rewriting(enabled)
+            .build(),
+    )
+}
+
+async fn streamed_page() -> HttpResponse {
+    let chunks = [
+        "<html><head><sty",
+        "le>body{}</style><scr",
+        "ipt src=\"/app.js\"",
+        "></script><SCRIPT nonce=\"keep\">x()</SCRIPT>",
+        "<scripts></scripts><p>done</p></html>",
+    ];
+
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .streaming(stream::iter(chunks.into_iter().map(|chunk| {
+            Ok::<_, actix_web::Error>(Bytes::from_static(chunk.as_bytes()))
+        })))
+}
+
+async fn json_payload() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(r#"{"html":"<script>"}"#)
+}
+
+async fn gzip_page() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html")
+        .insert_header(("content-encoding", "gzip"))
+        .body("<script></script>")
+}
+
+async fn event_stream() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .streaming(stream::iter(["data: <script>\n\n", "data: done\n\n"].map(
+            |chunk| Ok::<_, actix_web::Error>(Bytes::from_static(chunk.as_bytes())),
+        )))
+}
+
+const STATIC_INDEX: &str = "<html><script src=\"/app.js\"></script></html>";
+
+/// Answers the way `actix-files` serves an `index.html` from disk.
+async fn static_index() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .insert_header(("etag", "\"a1b2-1700000000\""))
+        .insert_header(("last-modified", "Tue, 14 Nov 2023 22:13:20 GMT"))
+        .insert_header(("accept-ranges", "bytes"))
+        .body(STATIC_INDEX)
+}
+
+/// Honours `If-None-Match` and `Range` like `actix-files` does.
+async fn conditional_index(req: HttpRequest) -> HttpResponse {
+    let etag = "\"a1b2-1700000000\"";
+    if req
+        .headers()
+        .get("if-none-match")
+        .is_some_and(|value| value == etag)
+    {
+        return HttpResponse::NotModified()
+            .insert_header(("etag", etag))
+            .finish();
+    }
+    if req.headers().contains_key("range") {
+        return static_index_range().await;
+    }
+    static_index().await
+}
+
+async fn static_index_range() -> HttpResponse {
+    HttpResponse::PartialContent()
+        .content_type("text/html; charset=utf-8")
+        .insert_header(("content-range", "bytes 6-13/46"))
+        .body(&STATIC_INDEX[6..14])
+}
+
+/// A trusted template that opts into rewriting.
+async fn trusted_page() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html")
+        .rewrite_html_nonces()
+        .body("<script src=\"/app.js\"></script><style>p{}</style>")
+}
+
+/// Echoes a user comment that smuggles in a script, and so does not opt in.
+async fn comment_page() -> HttpResponse {
+    let comment = "<script>steal(document.cookie)</script>";
+    HttpResponse::Ok()
+        .content_type("text/html")
+        .body(format!("<p>{comment}</p>"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn fetch(enabled: bool, path: &str) -> (Option<String>, String) {
+        let app = test::init_service(
+            App::new()
+                .wrap(rewriting_middleware(enabled))
+                .route("/page", web::get().to(streamed_page))
+                .route("/json", web::get().to(json_payload))
+                .route("/gzip", web::get().to(gzip_page))
+                .route("/events", web::get().to(event_stream))
+                .route("/index.html", web::get().to(static_index))
+                .route("/range", web::get().to(static_index_range)),
+        )
+        .await;
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri(path).to_request()).await;
+        let header = resp
+            .headers()
+            .get("content-security-policy")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let nonce = header
+            .split("'nonce-")
+            .nth(1)
+            .and_then(|rest| rest.split('\'').next())
+            .map(str::to_owned);
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+
+        (nonce, body)
+    }
+
+    #[actix_web::test]
+    async fn test_streamed_html_gets_nonce_across_chunk_boundaries() {
+        let (nonce, body) = fetch(true, "/page").await;
+        let nonce = nonce.unwrap();
+
+        assert_eq!(
+            body,
+            format!(
+                "<html><head><style nonce=\"{nonce}\">body{{}}</style>\
+                 <script nonce=\"{nonce}\" src=\"/app.js\"></script>\
+                 <SCRIPT nonce=\"keep\">x()</SCRIPT><scripts></scripts><p>done</p></html>"
+            )
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_rewriting_is_opt_in() {
+        let (_, body) = fetch(false, "/page").await;
+        assert_eq!(body.matches("nonce=").count(), 1);
+        assert!(body.contains("<script src=\"/app.js\">"));
+    }
+
+    #[actix_web::test]
+    async fn test_non_html_responses_are_not_rewritten() {
+        let (_, body) = fetch(true, "/json").await;
+        assert_eq!(body, r#"{"html":"<script>"}"#);
+    }
+
+    #[actix_web::test]
+    async fn test_encoded_html_is_not_rewritten() {
+        let (_, body) = fetch(true, "/gzip").await;
+        assert_eq!(body, "<script></script>");
+    }
+
+    #[actix_web::test]
+    async fn test_event_streams_are_not_rewritten() {
+        let (_, body) = fetch(true, "/events").await;
+        assert_eq!(body, "data: <script>\n\ndata: done\n\n");
+    }
+
+    #[actix_web::test]
+    async fn test_static_html_drops_validators_of_the_original_file() {
+        let app = test::init_service(
+            App::new()
+                .wrap(rewriting_middleware(true))
+                .route("/index.html", web::get().to(static_index)),
+        )
+        .await;
+
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get().uri("/index.html").to_request(),
+        )
+        .await;
+        for name in ["etag", "last-modified", "accept-ranges", "content-length"] {
+            assert!(!resp.headers().contains_key(name), "{name}");
+        }
+
+        let (nonce, body) = fetch(true, "/index.html").await;
+        assert_eq!(
+            body,
+            format!(
+                "<html><script nonce=\"{}\" src=\"/app.js\"></script></html>",
+                nonce.unwrap()
+            )
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_partial_content_is_not_rewritten() {
+        let (_, body) = fetch(true, "/range").await;
+        assert_eq!(body, "<script ");
+    }
+
+    #[actix_web::test]
+    async fn test_conditional_html_requests_get_a_fresh_rewritten_page() {
+        let app = test::init_service(
+            App::new()
+                .wrap(rewriting_middleware(true))
+                .route("/", web::get().to(conditional_index)),
+        )
+        .await;
+
+        for (name, value) in [
+            ("if-none-match", "\"a1b2-1700000000\""),
+            ("range", "bytes=6-13"),
+        ] {
+            let req = test::TestRequest::get()
+                .uri("/")
+                .insert_header(("accept", "text/html,application/xhtml+xml;q=0.9,*/*;q=0.8"))
+                .insert_header((name, value))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+
+            assert_eq!(resp.status(), StatusCode::OK, "{name}");
+            assert!(!resp.headers().contains_key("etag"));
+            let body = test::read_body(resp).await;
+            assert!(body.starts_with(b"<html><script nonce=\""), "{name}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_conditional_asset_requests_still_revalidate() {
+        let app = test::init_service(
+            App::new()
+                .wrap(rewriting_middleware(true))
+                .route("/", web::get().to(conditional_index)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header(("accept", "*/*"))
+            .insert_header(("if-none-match", "\"a1b2-1700000000\""))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get("etag").unwrap(), "\"a1b2-1700000000\"");
+    }
+
+    #[actix_web::test]
+    async fn test_conditional_requests_are_untouched_without_rewriting() {
+        let app = test::init_service(
+            App::new()
+                .wrap(rewriting_middleware(false))
+                .route("/", web::get().to(conditional_index)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header(("accept", "text/html"))
+            .insert_header(("if-none-match", "\"a1b2-1700000000\""))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[actix_web::test]
+    async fn test_rewriting_can_be_opted_into_per_response() {
+        let app = test::init_service(
+            App::new()
+                .wrap(rewriting_middleware(false))
+                .route("/trusted", web::get().to(trusted_page))
+                .route("/comments", web::get().to(comment_page)),
+        )
+        .await;
+
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/trusted").to_request()).await;
+        let header = resp.headers().get("content-security-policy").unwrap();
+        let header = header.to_str().unwrap().to_owned();
+        let nonce = header
+            .split("'nonce-")
+            .nth(1)
+            .and_then(|rest| rest.split('\'').next())
+            .unwrap()
+            .to_owned();
+        let body = test::read_body(resp).await;
+        assert_eq!(
+            body,
+            format!(
+                "<script nonce=\"{nonce}\" src=\"/app.js\"></script>\
+                 <style nonce=\"{nonce}\">p{{}}</style>"
+            )
+        );
+
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/comments").to_request()).await;
+        let body = test::read_body(resp).await;
+        assert_eq!(body, "<p><script>steal(document.cookie)</script></p>");
+    }
+}
@@ -0,0 +1,95 @@
+use actix_web::{test, web, App, HttpResponse};
+use actix_web_csp::{csp_middleware, CspPolicy, CspPolicyBuilder, PolicyRoutes, Source};
+use std::collections::HashMap;
+
+fn base_policy() -> CspPolicy {
+    CspPolicyBuilder::new()
+        .default_src([Source::None])
+        .build_unchecked()
+}
+
+fn checkout_policy() -> CspPolicy {
+    CspPolicyBuilder::new()
+        .default_src([Source::Self_])
+        .script_src([Source::Self_, Source::Host("js.stripe.com".into())])
+        .build_unchecked()
+}
+
+fn docs_policy() -> CspPolicy {
+    CspPolicyBuilder::new()
+        .default_src([Source::Self_])
+        .style_src([Source::Self_, Source::UnsafeInline])
+        .build_unchecked()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn csp_for(routes: PolicyRoutes, host: &str, path: &str) -> String {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    csp_middleware(base_policy())
+                        .with_vhost_policies(HashMap::from([("tenant.example.com", docs_policy())]))
+                        .with_policy_routes(routes),
+                )
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri(path)
+            .insert_header(("host", host))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        resp.headers()
+            .get("content-security-policy")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned()
+    }
+
+    fn routes() -> PolicyRoutes {
+        PolicyRoutes::new()
+            .prefix("/checkout", checkout_policy())
+            .prefix("/docs", docs_policy())
+    }
+
+    #[actix_web::test]
+    async fn test_policy_selected_by_path_prefix() {
+        let header = csp_for(routes(), "shop.example.com", "/checkout/pay?step=2").await;
+        assert!(header.contains("js.stripe.com"));
+
+        let header = csp_for(routes(), "shop.example.com", "/docs/intro").await;
+        assert!(header.contains("'unsafe-inline'"));
+    }
+
+    #[actix_web::test]
+    async fn test_percent_encoded_path_selects_route_policy() {
+        let header = csp_for(routes(), "shop.example.com", "/%63heckout/pay").await;
+        assert!(header.contains("js.stripe.com"));
+
+        let header = csp_for(routes(), "shop.example.com", "/d%6Fcs/intro").await;
+        assert!(header.contains("'unsafe-inline'"));
+    }
+
+    #[actix_web::test]
+    async fn test_unmatched_path_uses_route_default_or_config_policy() {
+        let header = csp_for(routes(), "shop.example.com", "/about").await;
+        assert_eq!(header, "default-src 'none'");
+
+        let with_default = routes().default(checkout_policy());
+        let header = csp_for(with_default, "shop.example.com", "/about").await;
+        assert!(header.contains("js.stripe.com"));
+    }
+
+    #[actix_web::test]
+    async fn test_vhost_policy_takes_precedence_over_routes() {
+        let header = csp_for(routes(), "tenant.example.com", "/checkout").await;
+        assert!(!header.contains("js.stripe.com"));
+        assert!(header.contains("'unsafe-inline'"));
+    }
+}
@@ -0,0 +1,104 @@
+use actix_web::{
+    body::{BodySize, MessageBody},
+    http::header,
+    test as actix_test, web, App, HttpResponse,
+};
+use actix_web_csp::{
+    core::{CspConfig, CspConfigBuilder, CspPolicyBuilder, Source},
+    middleware::CspMiddleware,
+};
+use std::time::{Duration, Instant};
+
+const PAGE: &str = r#"<html><head>
+    <script src="/app.js"></script>
+    <script>inline()</script>
+    </head><body><img src="https://tracker.example.net/p.gif"></body></html>"#;
+
+fn shadow_config() -> CspConfig {
+    CspConfigBuilder::new()
+        .policy(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .build_unchecked(),
+        )
+        .with_shadow_audit(true)
+        .build()
+}
+
+async fn html_page() -> HttpResponse {
+    HttpResponse::Ok().content_type("text/html").body(PAGE)
+}
+
+async fn json_page() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(r#"{"html":"<script>x()</script>"}"#)
+}
+
+/// The audit runs on a background thread; wait for it to catch up.
+async fn wait_for_shadow_violations(config: &CspConfig, expected: usize) -> usize {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while config.stats().shadow_violation_count() < expected && Instant::now() < deadline {
+        actix_web::rt::time::sleep(Duration::from_millis(10)).await;
+    }
+    config.stats().shadow_violation_count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shadow_audit_is_opt_in() {
+        assert!(!CspConfigBuilder::new().build().shadow_audit());
+        assert!(shadow_config().shadow_audit());
+    }
+
+    #[actix_web::test]
+    async fn test_shadow_audit_records_predicted_violations() {
+        let config = shadow_config();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config.clone()))
+                .route("/", web::get().to(html_page)),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::get()
+            .uri("/")
+            .insert_header((header::HOST, "app.example.com"))
+            .to_request();
+        let res = actix_test::call_service(&app, req).await;
+        // Audited bodies pass through unchanged and keep their length
+        assert_eq!(
+            res.response().body().size(),
+            BodySize::Sized(PAGE.len() as u64)
+        );
+        let body = actix_test::read_body(res).await;
+        assert_eq!(body, PAGE.as_bytes());
+
+        assert_eq!(wait_for_shadow_violations(&config, 2).await, 2);
+    }
+
+    #[actix_web::test]
+    async fn test_shadow_audit_skips_non_html_responses() {
+        let config = shadow_config();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(CspMiddleware::new(config.clone()))
+                .route("/api", web::get().to(json_page))
+                .route("/", web::get().to(html_page)),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::get().uri("/api").to_request();
+        actix_test::read_body(actix_test::call_service(&app, req).await).await;
+        let req = actix_test::TestRequest::get().uri("/").to_request();
+        actix_test::read_body(actix_test::call_service(&app, req).await).await;
+
+        // Only the HTML page's two violations are counted
+        assert_eq!(wait_for_shadow_violations(&config, 2).await, 2);
+        actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(config.stats().shadow_violation_count(), 2);
+    }
+}
@@ -0,0 +1,110 @@
+use actix_web::{test, web, App, HttpResponse};
+use actix_web_csp::{
+    csp_middleware, csp_middleware_with_nonce, CspPolicy, CspPolicyBuilder, Source,
+};
+use std::collections::HashMap;
+
+fn tenant_policy(host: &'static str) -> CspPolicy {
+    CspPolicyBuilder::new()
+        .default_src([Source::Self_])
+        .script_src([Source::Self_, Source::Host(host.into())])
+        .build_unchecked()
+}
+
+fn fallback_policy() -> CspPolicy {
+    CspPolicyBuilder::new()
+        .default_src([Source::None])
+        .build_unchecked()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn csp_for_host(host: &str) -> String {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    csp_middleware(fallback_policy()).with_vhost_policies(HashMap::from([
+                        ("tenant-a.example.com", tenant_policy("cdn.tenant-a.com")),
+                        (
+                            "Tenant-B.example.com:8443",
+                            tenant_policy("cdn.tenant-b.com"),
+                        ),
+                    ])),
+                )
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header(("host", host))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        resp.headers()
+            .get("content-security-policy")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned()
+    }
+
+    #[actix_web::test]
+    async fn test_vhost_policy_selected_by_host_header() {
+        let header = csp_for_host("tenant-a.example.com").await;
+        assert!(header.contains("cdn.tenant-a.com"));
+    }
+
+    #[actix_web::test]
+    async fn test_vhost_matching_ignores_case_and_port_for_bare_keys() {
+        let header = csp_for_host("TENANT-A.example.com:8080").await;
+        assert!(header.contains("cdn.tenant-a.com"));
+    }
+
+    #[actix_web::test]
+    async fn test_vhost_key_with_port_only_matches_that_port() {
+        let header = csp_for_host("tenant-b.example.com:8443").await;
+        assert!(header.contains("cdn.tenant-b.com"));
+
+        let header = csp_for_host("tenant-b.example.com").await;
+        assert_eq!(header, "default-src 'none'");
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_host_falls_back_to_config_policy() {
+        let header = csp_for_host("unknown.example.com").await;
+        assert_eq!(header, "default-src 'none'");
+    }
+
+    #[actix_web::test]
+    async fn test_vhost_policy_receives_runtime_nonce() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    csp_middleware_with_nonce(fallback_policy(), 16).with_vhost_policies([(
+                        "tenant-a.example.com",
+                        tenant_policy("cdn.tenant-a.com"),
+                    )]),
+                )
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header(("host", "tenant-a.example.com"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let header = resp
+            .headers()
+            .get("content-security-policy")
+            .unwrap()
+            .to_str()
+            .unwrap();
+
+        assert!(header.contains("cdn.tenant-a.com"));
+        assert!(header.contains("'nonce-"));
+    }
+}
@@ -1,8 +1,13 @@
+#[cfg(feature = "bench-support")]
+pub mod bench_support;
 pub mod core;
 pub mod helpers;
+pub mod integrations;
+pub mod logging;
 pub mod middleware;
 pub mod monitoring;
 pub mod presets;
 pub mod property_roundtrip;
 pub mod security;
+pub mod testing;
 pub mod utils;
@@ -0,0 +1,188 @@
+use actix_web_csp::monitoring::{dev::suggest_fix, CspViolationReport, DevReporter};
+
+fn violation(
+    document_uri: &str,
+    blocked_uri: &str,
+    effective_directive: &str,
+) -> CspViolationReport {
+    CspViolationReport {
+        document_uri: document_uri.to_string(),
+        blocked_uri: blocked_uri.to_string(),
+        violated_directive: effective_directive.to_string(),
+        effective_directive: effective_directive.to_string(),
+        disposition: "enforce".to_string(),
+        ..CspViolationReport::default()
+    }
+}
+
+fn reporter() -> DevReporter {
+    DevReporter::new().with_enabled(true).with_colors(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest_fix_for_external_hosts() {
+        let report = violation(
+            "https://app.example.com/",
+            "https://cdn.x.com/lib.js",
+            "script-src-elem",
+        );
+        assert_eq!(
+            suggest_fix(&report).unwrap(),
+            "add `.script_src([... Source::Host(\"cdn.x.com\".into())])`"
+        );
+
+        let report = violation(
+            "https://app.example.com/",
+            "https://api.example.com:8443/v1",
+            "connect-src",
+        );
+        assert_eq!(
+            suggest_fix(&report).unwrap(),
+            "add `.connect_src([... Source::Host(\"api.example.com:8443\".into())])`"
+        );
+    }
+
+    #[test]
+    fn test_suggest_fix_for_keywords_and_schemes() {
+        let same_origin = violation(
+            "https://app.example.com/page",
+            "https://app.example.com/logo.png",
+            "img-src",
+        );
+        assert_eq!(
+            suggest_fix(&same_origin).unwrap(),
+            "add `.img_src([... Source::Self_])`"
+        );
+
+        let data = violation("https://app.example.com/", "data", "font-src");
+        assert_eq!(
+            suggest_fix(&data).unwrap(),
+            "add `.font_src([... Source::Scheme(\"data\".into())])`"
+        );
+
+        let inline = violation("https://app.example.com/", "inline", "script-src-elem");
+        assert!(suggest_fix(&inline)
+            .unwrap()
+            .contains("with_nonce_generator"));
+
+        let unknown = violation("https://app.example.com/", "inline", "trusted-types");
+        assert!(suggest_fix(&unknown).is_none());
+    }
+
+    #[test]
+    fn test_format_violation_includes_location_and_fix() {
+        let report = violation(
+            "https://app.example.com/",
+            "https://cdn.x.com/lib.js",
+            "script-src",
+        )
+        .with_source_file("https://app.example.com/app.js".to_string())
+        .with_line_number(12)
+        .with_column_number(4);
+
+        let output = reporter().format_violation(&report);
+
+        assert_eq!(
+            output,
+            "  script-src blocked https://cdn.x.com/lib.js\n    \
+             at https://app.example.com/app.js:12:4\n    \
+             fix: add `.script_src([... Source::Host(\"cdn.x.com\".into())])`\n"
+        );
+        assert!(!output.contains('\x1b'));
+        assert!(reporter()
+            .with_colors(true)
+            .format_violation(&report)
+            .contains('\x1b'));
+    }
+
+    #[test]
+    fn test_summary_groups_by_page_and_counts_repeats() {
+        let reporter = reporter();
+        let handler = reporter.handler();
+
+        handler(violation(
+            "https://a.example/",
+            "https://x.example/a.js",
+            "script-src",
+        ));
+        handler(violation(
+            "https://b.example/",
+            "https://y.example/b.png",
+            "img-src",
+        ));
+        handler(violation(
+            "https://a.example/",
+            "https://x.example/a.js",
+            "script-src",
+        ));
+
+        let summary = reporter.summary();
+        let page_a = summary.find("[csp] https://a.example/").unwrap();
+        let page_b = summary.find("[csp] https://b.example/").unwrap();
+        assert!(page_a < page_b);
+        assert_eq!(summary.matches("[csp]").count(), 2);
+        assert!(summary[page_a..page_b].contains("seen 2 times"));
+
+        reporter.clear();
+        assert!(reporter.summary().is_empty());
+    }
+
+    #[test]
+    fn test_reporter_keeps_a_bounded_number_of_pages() {
+        let reporter = reporter();
+
+        for page in 0..150 {
+            reporter.report(&violation(
+                &format!("https://app.example/{page}"),
+                "https://x.example/a.js",
+                "script-src",
+            ));
+        }
+        for script in 0..150 {
+            reporter.report(&violation(
+                "https://app.example/busy",
+                &format!("https://x.example/{script}.js"),
+                "script-src",
+            ));
+        }
+
+        let summary = reporter.summary();
+        assert_eq!(summary.matches("[csp]").count(), 100);
+        assert!(!summary.contains("[csp] https://app.example/0\n"));
+        assert!(summary.contains("[csp] https://app.example/149\n"));
+        assert_eq!(summary.matches("https://x.example/").count(), 99 + 100);
+        assert!(!summary.contains("https://x.example/49.js"));
+        assert!(summary.contains("https://x.example/50.js"));
+    }
+
+    #[test]
+    fn test_control_characters_are_escaped() {
+        let mut report = violation(
+            "https://app.example/\x1b[2J",
+            "https://x.example/a.js\x1b]0;owned\x07",
+            "script-src",
+        );
+        report.source_file = Some("https://app.example/app.js\r".to_string());
+        report.script_sample = Some("alert(1)\x1b[0m".to_string());
+        let reporter = reporter();
+        reporter.report(&report);
+
+        let output = reporter.format_violation(&report) + &reporter.summary();
+        assert!(!output.chars().any(|c| c.is_control() && c != '\n'));
+        assert!(output.contains("a.js\\u{1b}]0;owned\\u{7}"));
+        assert!(output.contains("[csp] https://app.example/\\u{1b}[2J"));
+    }
+
+    #[test]
+    fn test_disabled_reporter_records_nothing() {
+        let reporter = reporter().with_enabled(false);
+        reporter.report(&violation("https://a.example/", "eval", "script-src"));
+
+        assert!(!reporter.is_enabled());
+        assert!(reporter.summary().is_empty());
+    }
+}
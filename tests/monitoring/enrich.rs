@@ -0,0 +1,138 @@
+use actix_web::{test as actix_test, App};
+use actix_web_csp::middleware::CspReportingMiddleware;
+use actix_web_csp::monitoring::{CspViolationReport, GeoInfo, GeoIpEnricher};
+use actix_web_csp::test::ViolationReportBuilder;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+fn lookup(ip: IpAddr) -> Option<GeoInfo> {
+    match ip.to_string().as_str() {
+        "198.51.100.7" => Some(GeoInfo {
+            country: Some("DE".to_string()),
+            asn: Some(64500),
+            as_organization: Some("Example Hosting".to_string()),
+        }),
+        _ => None,
+    }
+}
+
+fn capturing_middleware() -> (CspReportingMiddleware, Arc<Mutex<Vec<CspViolationReport>>>) {
+    let received: Arc<Mutex<Vec<CspViolationReport>>> = Arc::default();
+    let sink = received.clone();
+    let middleware = CspReportingMiddleware::new(move |report| sink.lock().unwrap().push(report));
+    (middleware, received)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_geoip_enricher_annotates_reports() {
+        let (middleware, received) = capturing_middleware();
+        let app = actix_test::init_service(
+            App::new().wrap(middleware.with_enricher(GeoIpEnricher::new(lookup))),
+        )
+        .await;
+
+        for peer in ["198.51.100.7:4000", "203.0.113.9:4000"] {
+            let req = ViolationReportBuilder::new("https://evil.example/x.js", "script-src")
+                .to_request("/csp-report")
+                .peer_addr(peer.parse().unwrap())
+                .to_request();
+            actix_test::call_service(&app, req).await;
+        }
+
+        let received = received.lock().unwrap();
+        let geo = received[0].geo.as_ref().unwrap();
+        assert_eq!(geo.country.as_deref(), Some("DE"));
+        assert_eq!(geo.asn, Some(64500));
+        assert!(received[1].geo.is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_enrichers_run_in_order_before_handler() {
+        let (middleware, received) = capturing_middleware();
+        let middleware = middleware
+            .with_enricher(|report: &mut CspViolationReport| {
+                report.script_sample = Some("first".to_string());
+            })
+            .with_enricher(|report: &mut CspViolationReport| {
+                if let Some(sample) = &mut report.script_sample {
+                    sample.push_str(", second");
+                }
+            });
+        let app = actix_test::init_service(App::new().wrap(middleware)).await;
+
+        let req = ViolationReportBuilder::new("inline", "script-src")
+            .to_request("/csp-report")
+            .to_request();
+        actix_test::call_service(&app, req).await;
+
+        assert_eq!(
+            received.lock().unwrap()[0].script_sample.as_deref(),
+            Some("first, second")
+        );
+    }
+
+    #[cfg(feature = "geoip")]
+    #[actix_web::test]
+    async fn test_maxmind_enricher_reads_country_and_asn() {
+        use actix_web_csp::monitoring::MaxMindEnricher;
+
+        // built by tests/fixtures/write_geoip_fixture.py
+        let fixture = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/geoip-test.mmdb"
+        );
+        let enricher = MaxMindEnricher::open(fixture).unwrap();
+
+        assert_eq!(
+            enricher.lookup("198.51.100.7".parse().unwrap()),
+            lookup("198.51.100.7".parse().unwrap())
+        );
+        assert_eq!(
+            enricher.lookup("203.0.113.9".parse().unwrap()),
+            Some(GeoInfo {
+                country: Some("FR".to_string()),
+                ..GeoInfo::default()
+            })
+        );
+        assert_eq!(
+            enricher.lookup("::ffff:198.51.100.7".parse().unwrap()),
+            lookup("198.51.100.7".parse().unwrap())
+        );
+        assert_eq!(enricher.lookup("192.0.2.1".parse().unwrap()), None);
+        assert_eq!(enricher.lookup("2001:db8::1".parse().unwrap()), None);
+
+        let (middleware, received) = capturing_middleware();
+        let app =
+            actix_test::init_service(App::new().wrap(middleware.with_enricher(enricher))).await;
+        let req = ViolationReportBuilder::new("https://evil.example/x.js", "script-src")
+            .to_request("/csp-report")
+            .peer_addr("198.51.100.7:4000".parse().unwrap())
+            .to_request();
+        actix_test::call_service(&app, req).await;
+
+        let received = received.lock().unwrap();
+        let geo = received[0].geo.as_ref().unwrap();
+        assert_eq!(geo.asn, Some(64500));
+        assert_eq!(geo.as_organization.as_deref(), Some("Example Hosting"));
+    }
+
+    #[cfg(feature = "geoip")]
+    #[test]
+    fn test_maxmind_enricher_rejects_invalid_database() {
+        use actix_web_csp::monitoring::MaxMindEnricher;
+        use actix_web_csp::CspError;
+
+        assert!(matches!(
+            MaxMindEnricher::from_bytes(b"not a database".to_vec()),
+            Err(CspError::ConfigError(_))
+        ));
+        assert!(matches!(
+            MaxMindEnricher::open("/nonexistent/GeoLite2-Country.mmdb"),
+            Err(CspError::IoError(_))
+        ));
+    }
+}
@@ -1,2 +1,8 @@
+pub mod dev;
+#[cfg(feature = "reporting")]
+pub mod enrich;
 pub mod perf;
+pub mod scrub;
 pub mod stats;
+#[cfg(feature = "user-agent")]
+pub mod user_agent;
@@ -1,4 +1,5 @@
 use actix_web_csp::monitoring::{AdaptiveCache, PerformanceMetrics, PerformanceTimer};
+use actix_web_csp::{CspConfigBuilder, CspPolicyBuilder, Source};
 use std::num::NonZeroUsize;
 use std::time::Duration;
 
@@ -18,6 +19,10 @@ mod tests {
     }
 
     #[test]
+    #[cfg_attr(
+        not(feature = "stats"),
+        allow(clippy::default_constructed_unit_structs)
+    )]
     fn test_performance_metrics_default() {
         let metrics = PerformanceMetrics::default();
 
@@ -26,6 +31,7 @@ mod tests {
         assert_eq!(metrics.cache_hit_rate(), 0.0);
     }
 
+    #[cfg(feature = "stats")]
     #[test]
     fn test_performance_metrics_record_header_generation() {
         let metrics = PerformanceMetrics::new();
@@ -41,6 +47,7 @@ mod tests {
         assert_eq!(metrics.max_header_generation_ns(), 2000);
     }
 
+    #[cfg(feature = "stats")]
     #[test]
     fn test_performance_metrics_record_policy_hash() {
         let metrics = PerformanceMetrics::new();
@@ -54,6 +61,7 @@ mod tests {
         assert_eq!(metrics.avg_policy_hash_ns(), 1000.0);
     }
 
+    #[cfg(feature = "stats")]
     #[test]
     fn test_performance_metrics_cache_hit_rate() {
         let metrics = PerformanceMetrics::new();
@@ -68,6 +76,7 @@ mod tests {
         assert_eq!(metrics.cache_hit_rate(), 0.75);
     }
 
+    #[cfg(feature = "stats")]
     #[test]
     fn test_performance_metrics_reset() {
         let metrics = PerformanceMetrics::new();
@@ -138,6 +147,41 @@ mod tests {
         assert_eq!(missing, None);
     }
 
+    #[test]
+    fn test_adaptive_cache_shrinks_when_hot_and_underused() {
+        let mut cache = AdaptiveCache::new(NonZeroUsize::new(256).unwrap());
+        cache.put(1, "one");
+        for _ in 0..20 {
+            cache.get(&1);
+        }
+
+        assert!(cache.shrink_if_underused());
+        assert_eq!(cache.cap().get(), 128);
+        assert_eq!(cache.get(&1), Some(&"one"));
+    }
+
+    #[test]
+    fn test_adaptive_cache_keeps_capacity_when_cold() {
+        let mut cache: AdaptiveCache<i32, i32> =
+            AdaptiveCache::new(NonZeroUsize::new(256).unwrap());
+        cache.get(&1);
+
+        assert!(!cache.shrink_if_underused());
+        assert_eq!(cache.cap().get(), 256);
+    }
+
+    #[test]
+    fn test_adaptive_cache_retain() {
+        let mut cache = AdaptiveCache::new(NonZeroUsize::new(8).unwrap());
+        for key in 0..6 {
+            cache.put(key, key * 10);
+        }
+
+        assert_eq!(cache.retain(|key, _| key % 2 == 0), 3);
+        assert_eq!(cache.len(), 3);
+        assert!(cache.get(&1).is_none());
+    }
+
     #[test]
     fn test_adaptive_cache_hit_rate() {
         let capacity = NonZeroUsize::new(5).unwrap();
@@ -191,6 +235,7 @@ mod tests {
         assert_eq!(cache.get(&"key3".to_string()), Some(&300));
     }
 
+    #[cfg(feature = "stats")]
     #[test]
     fn test_performance_metrics_concurrent_access() {
         use std::sync::Arc;
@@ -225,4 +270,83 @@ mod tests {
         assert!(metrics.avg_policy_hash_ns() > 0.0);
         assert_eq!(metrics.cache_hit_rate(), 0.5);
     }
+
+    #[test]
+    fn test_buffer_pool_hit_rate_reported_through_config() {
+        actix_web_csp::utils::configure_buffer_pool(8, 512);
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .default_src([Source::Self_])
+                    .script_src([Source::Self_, Source::Host("cdn.example.com".into())])
+                    .build_unchecked(),
+            )
+            .build();
+
+        for _ in 0..4 {
+            config.policy().compile().unwrap();
+        }
+
+        let hit_rate = config.perf_metrics().buffer_pool_hit_rate();
+        if cfg!(feature = "stats") {
+            assert!(hit_rate > 0.0, "{hit_rate}");
+        } else {
+            assert_eq!(hit_rate, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_perf_metrics_report_nonce_pool_stats() {
+        let config = CspConfigBuilder::new()
+            .policy(
+                CspPolicyBuilder::new()
+                    .script_src([Source::Self_])
+                    .build_unchecked(),
+            )
+            .with_nonce_generator(16)
+            .build();
+        for _ in 0..3 {
+            config.generate_nonce();
+        }
+
+        let stats = config.perf_metrics().nonce_pool_stats();
+        if cfg!(feature = "stats") {
+            let stats = stats.unwrap();
+            assert_eq!(stats.hits, 3);
+            assert_eq!(stats.misses, 0);
+            assert_eq!(config.perf_metrics().nonce_pool_hit_rate(), 1.0);
+        } else {
+            assert!(stats.is_none());
+        }
+
+        let without = CspConfigBuilder::new().build();
+        assert!(without.perf_metrics().nonce_pool_stats().is_none());
+    }
+
+    #[test]
+    fn test_adaptive_cache_stats_track_evictions_and_occupancy() {
+        let mut cache = AdaptiveCache::new(NonZeroUsize::new(2).unwrap());
+
+        cache.put(1, "one");
+        cache.put(2, "two");
+        cache.put(2, "two again");
+        assert_eq!(cache.stats().evictions, 0);
+        cache.put(3, "three");
+
+        cache.get(&3);
+        cache.get(&1);
+        assert_eq!(cache.retain(|key, _| *key != 3), 1);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!(stats.evictions, 2);
+        assert_eq!((stats.entries, stats.capacity), (1, 2));
+        assert_eq!(stats.hit_rate(), 0.5);
+
+        cache.clear();
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 0);
+        assert_eq!(stats.hits, 1, "lifetime counters survive clear");
+        assert_eq!(cache.hit_rate(), 0.0);
+    }
 }
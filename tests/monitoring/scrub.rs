@@ -0,0 +1,156 @@
+use actix_web_csp::monitoring::{CspViolationReport, ReportScrubber};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrubber_strips_query_strings() {
+        let mut report = CspViolationReport {
+            document_uri: "https://app.example.com/reset?token=s3cr3t#step-2".to_string(),
+            referrer: "https://mail.example/?user=alice@example.com".to_string(),
+            blocked_uri: "https://evil.example/x.js?session=abc".to_string(),
+            source_file: Some("https://app.example.com/app.js?v=3".to_string()),
+            ..CspViolationReport::default()
+        };
+        ReportScrubber::new()
+            .with_strip_query_strings(true)
+            .scrub(&mut report);
+
+        assert_eq!(report.document_uri, "https://app.example.com/reset");
+        assert_eq!(report.referrer, "https://mail.example/");
+        assert_eq!(report.blocked_uri, "https://evil.example/x.js");
+        assert_eq!(
+            report.source_file.as_deref(),
+            Some("https://app.example.com/app.js")
+        );
+    }
+
+    #[test]
+    fn test_scrubber_truncates_script_samples_on_char_boundaries() {
+        let mut report = CspViolationReport {
+            script_sample: Some("sendToken('s3cr3t'); 🙂 trailing".to_string()),
+            ..CspViolationReport::default()
+        };
+        ReportScrubber::new()
+            .with_max_script_sample(22)
+            .scrub(&mut report);
+        assert_eq!(
+            report.script_sample.as_deref(),
+            Some("sendToken('s3cr3t'); 🙂")
+        );
+
+        ReportScrubber::new()
+            .with_max_script_sample(0)
+            .scrub(&mut report);
+        assert!(report.script_sample.is_none());
+    }
+
+    #[test]
+    fn test_default_scrubber_leaves_report_unchanged() {
+        let mut report = CspViolationReport {
+            document_uri: "https://app.example.com/reset?token=s3cr3t#step-2".to_string(),
+            script_sample: Some("sendToken('s3cr3t'); 🙂 trailing".to_string()),
+            ..CspViolationReport::default()
+        };
+        ReportScrubber::new().scrub(&mut report);
+
+        assert_eq!(
+            report.document_uri,
+            "https://app.example.com/reset?token=s3cr3t#step-2"
+        );
+        assert_eq!(
+            report.script_sample.as_deref(),
+            Some("sendToken('s3cr3t'); 🙂 trailing")
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_scrubber_redacts_patterns() {
+        let mut report = CspViolationReport {
+            document_uri: "https://app.example.com/reset?token=s3cr3t#step-2".to_string(),
+            referrer: "https://mail.example/?user=alice@example.com".to_string(),
+            script_sample: Some("sendToken('s3cr3t');".to_string()),
+            ..CspViolationReport::default()
+        };
+        ReportScrubber::new()
+            .with_redaction(r"s3cr3t")
+            .unwrap()
+            .with_redaction(r"[\w.+-]+@[\w-]+\.[\w.]+")
+            .unwrap()
+            .scrub(&mut report);
+
+        assert_eq!(
+            report.document_uri,
+            "https://app.example.com/reset?token=[REDACTED]#step-2"
+        );
+        assert_eq!(report.referrer, "https://mail.example/?user=[REDACTED]");
+        assert!(report.script_sample.unwrap().contains("[REDACTED]"));
+        assert!(ReportScrubber::new().with_redaction("(").is_err());
+    }
+
+    #[cfg(feature = "reporting")]
+    #[actix_web::test]
+    async fn test_reporting_middleware_scrubs_before_handler() {
+        use actix_web::{test as actix_test, App};
+        use actix_web_csp::middleware::CspReportingMiddleware;
+        use actix_web_csp::test::ViolationReportBuilder;
+        use std::sync::{Arc, Mutex};
+
+        let received: Arc<Mutex<Vec<String>>> = Arc::default();
+        let sink = received.clone();
+        let middleware = CspReportingMiddleware::new(move |report| {
+            sink.lock().unwrap().push(report.document_uri)
+        })
+        .with_scrubber(ReportScrubber::new().with_strip_query_strings(true));
+        let app = actix_test::init_service(App::new().wrap(middleware)).await;
+
+        let req = ViolationReportBuilder::new("inline", "script-src")
+            .document_uri("https://app.example.com/account?token=s3cr3t")
+            .to_request("/csp-report")
+            .to_request();
+        actix_test::call_service(&app, req).await;
+
+        assert_eq!(
+            *received.lock().unwrap(),
+            ["https://app.example.com/account"]
+        );
+    }
+
+    #[cfg(feature = "reporting")]
+    #[actix_web::test]
+    async fn test_csp_with_configured_reporting_scrubs_before_handler() {
+        use actix_web::{test as actix_test, App};
+        use actix_web_csp::middleware::{csp_with_configured_reporting, CspReportingMiddleware};
+        use actix_web_csp::test::ViolationReportBuilder;
+        use actix_web_csp::{CspPolicyBuilder, Source};
+        use std::sync::{Arc, Mutex};
+
+        let received: Arc<Mutex<Vec<String>>> = Arc::default();
+        let sink = received.clone();
+        let (csp, configure_reporting) = csp_with_configured_reporting(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .report_uri("/csp-report")
+                .build_unchecked(),
+            CspReportingMiddleware::new(move |report| {
+                sink.lock().unwrap().push(report.document_uri)
+            })
+            .with_scrubber(ReportScrubber::new().with_strip_query_strings(true)),
+        );
+        let app =
+            actix_test::init_service(App::new().wrap(csp).configure(configure_reporting)).await;
+
+        let req = ViolationReportBuilder::new("inline", "script-src")
+            .document_uri("https://app.example.com/account?token=s3cr3t")
+            .to_request("/csp-report")
+            .to_request();
+        actix_test::call_service(&app, req).await;
+
+        assert_eq!(
+            *received.lock().unwrap(),
+            ["https://app.example.com/account"]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use actix_web_csp::monitoring::CspStats;
+use actix_web_csp::monitoring::{CspStats, HistogramSnapshot, StatsSnapshot, WindowedRates};
 use std::thread;
 use std::time::Duration;
 
@@ -19,6 +19,10 @@ mod tests {
     }
 
     #[test]
+    #[cfg_attr(
+        not(feature = "stats"),
+        allow(clippy::default_constructed_unit_structs)
+    )]
     fn test_csp_stats_default() {
         let stats = CspStats::default();
 
@@ -77,6 +81,7 @@ mod tests {
         assert_eq!(stats.policy_validations(), 0);
     }
 
+    #[cfg(feature = "stats")]
     #[test]
     fn test_csp_stats_display() {
         let stats = CspStats::new();
@@ -137,4 +142,487 @@ mod tests {
         let _uptime1 = stats1.uptime_secs();
         let _uptime2 = stats2.uptime_secs();
     }
+
+    #[test]
+    fn test_stats_snapshot_to_prometheus() {
+        let snapshot = StatsSnapshot {
+            request_count: 7,
+            violation_count: 3,
+            oversized_report_count: 1,
+            malformed_report_count: 4,
+            blocked_report_count: 5,
+            dropped_report_count: 6,
+            report_queue_depth: 2,
+            policy_validations: 2,
+            violations_by_directive: [("script-src-elem".to_string(), 3)].into(),
+            violations_by_disposition: [("enforce".to_string(), 2), ("report".to_string(), 1)]
+                .into(),
+            violations_by_disposition_and_directive: [(
+                "report".to_string(),
+                [("img-src".to_string(), 1)].into(),
+            )]
+            .into(),
+            report_only_violation_rates: WindowedRates {
+                one_minute: 2.0,
+                five_minutes: 0.5,
+                fifteen_minutes: 0.25,
+            },
+            top_blocked_origins: vec![("https://evil.example".to_string(), 2)],
+            request_rates: WindowedRates {
+                one_minute: 7.0,
+                five_minutes: 1.5,
+                fifteen_minutes: 0.5,
+            },
+            ..StatsSnapshot::default()
+        };
+
+        let metrics = snapshot.to_prometheus();
+
+        assert!(metrics.contains("# TYPE csp_requests_total counter\ncsp_requests_total 7\n"));
+        assert!(metrics.contains("csp_violations_total 3\n"));
+        assert!(metrics.contains("csp_reports_oversized_total 1\n"));
+        assert!(metrics.contains("csp_reports_malformed_total 4\n"));
+        assert!(metrics.contains("csp_reports_blocked_total 5\n"));
+        assert!(metrics.contains("csp_reports_dropped_total 6\n"));
+        assert!(metrics.contains("# TYPE csp_report_queue_depth gauge\ncsp_report_queue_depth 2\n"));
+        assert!(metrics.contains("csp_policy_validations_total 2\n"));
+        assert!(metrics
+            .contains("csp_violations_by_directive_total{directive=\"script-src-elem\"} 3\n"));
+        assert!(metrics.contains("csp_violations_by_disposition_total{disposition=\"report\"} 1\n"));
+        assert!(
+            metrics.contains("csp_blocked_origin_violations{origin=\"https://evil.example\"} 2\n")
+        );
+        assert!(metrics.contains("# TYPE csp_requests_per_minute gauge\n"));
+        assert!(metrics.contains("csp_requests_per_minute{window=\"5m\"} 1.5\n"));
+        assert!(metrics.contains("csp_violations_per_minute{window=\"15m\"} 0\n"));
+        assert!(metrics.contains(
+            "csp_violations_per_minute_by_disposition{disposition=\"report\",window=\"1m\"} 2\n"
+        ));
+        assert!(metrics.contains(
+            "csp_violations_per_minute_by_disposition{disposition=\"enforce\",window=\"1m\"} 0\n"
+        ));
+        assert!(metrics.contains(
+            "csp_violations_by_directive_and_disposition_total{disposition=\"report\",directive=\"img-src\"} 1\n"
+        ));
+    }
+
+    #[test]
+    fn test_windowed_rates_start_at_zero() {
+        let stats = CspStats::new();
+
+        assert_eq!(stats.request_rates(), WindowedRates::default());
+        assert_eq!(stats.violation_rates(), WindowedRates::default());
+    }
+
+    #[cfg(feature = "reporting")]
+    #[actix_web::test]
+    async fn test_violation_rates_follow_recent_reports() {
+        use actix_web::{test as actix_test, App};
+        use actix_web_csp::middleware::CspReportingMiddleware;
+        use actix_web_csp::test::ViolationReportBuilder;
+        use std::sync::Arc;
+
+        let stats = Arc::new(CspStats::new());
+        let app = actix_test::init_service(
+            App::new().wrap(CspReportingMiddleware::new(|_| {}).with_stats(stats.clone())),
+        )
+        .await;
+
+        for _ in 0..3 {
+            let req = ViolationReportBuilder::new("https://evil.example/a.js", "script-src")
+                .to_request("/csp-report")
+                .to_request();
+            actix_test::call_service(&app, req).await;
+        }
+
+        // Within the first second the rate is taken over one second of uptime
+        let rates = stats.violation_rates();
+        assert!(rates.one_minute >= 3.0);
+        assert!(rates.five_minutes >= 3.0);
+        assert!(rates.fifteen_minutes >= 3.0);
+        assert_eq!(stats.snapshot().violation_rates, rates);
+
+        stats.reset();
+        assert_eq!(stats.violation_rates(), WindowedRates::default());
+    }
+
+    #[cfg(feature = "reporting")]
+    #[actix_web::test]
+    async fn test_stats_break_down_reported_violations() {
+        use actix_web::{test as actix_test, App};
+        use actix_web_csp::middleware::CspReportingMiddleware;
+        use actix_web_csp::test::ViolationReportBuilder;
+        use std::sync::Arc;
+
+        let stats = Arc::new(CspStats::new());
+        let app = actix_test::init_service(
+            App::new().wrap(CspReportingMiddleware::new(|_| {}).with_stats(stats.clone())),
+        )
+        .await;
+
+        for report in [
+            ViolationReportBuilder::new("https://evil.example/a.js", "script-src-elem"),
+            ViolationReportBuilder::new("https://evil.example/b.js", "script-src-elem"),
+            ViolationReportBuilder::new("inline", "style-src-attr").report_only(),
+            ViolationReportBuilder::new("https://cdn.example/x.png", "made-up-directive"),
+        ] {
+            let req = report.to_request("/csp-report").to_request();
+            actix_test::call_service(&app, req).await;
+        }
+
+        assert_eq!(stats.violation_count(), 4);
+        assert_eq!(stats.violations_by_directive()["script-src-elem"], 2);
+        assert_eq!(stats.violations_by_directive()["other"], 1);
+        assert_eq!(stats.violations_by_disposition()["enforce"], 3);
+        assert_eq!(stats.violations_by_disposition()["report"], 1);
+        assert_eq!(
+            stats.top_blocked_origins(2),
+            [
+                ("https://evil.example".to_string(), 2),
+                ("https://cdn.example".to_string(), 1)
+            ]
+        );
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.violation_count, 4);
+        assert_eq!(snapshot.top_blocked_origins.len(), 3);
+
+        stats.reset();
+        assert!(stats.violations_by_directive().is_empty());
+        assert!(stats.top_blocked_origins(10).is_empty());
+    }
+
+    #[cfg(feature = "reporting")]
+    #[actix_web::test]
+    async fn test_stats_separate_enforced_and_report_only_violations() {
+        use actix_web::{test as actix_test, App};
+        use actix_web_csp::middleware::CspReportingMiddleware;
+        use actix_web_csp::test::ViolationReportBuilder;
+        use std::sync::Arc;
+
+        let stats = Arc::new(CspStats::new());
+        let app = actix_test::init_service(
+            App::new().wrap(CspReportingMiddleware::new(|_| {}).with_stats(stats.clone())),
+        )
+        .await;
+
+        for report in [
+            ViolationReportBuilder::new("https://evil.example/a.js", "script-src"),
+            ViolationReportBuilder::new("https://cdn.example/a.js", "script-src").report_only(),
+            ViolationReportBuilder::new("https://cdn.example/b.js", "script-src").report_only(),
+            ViolationReportBuilder::new("https://cdn.example/x.png", "img-src").report_only(),
+        ] {
+            let req = report.to_request("/csp-report").to_request();
+            actix_test::call_service(&app, req).await;
+        }
+
+        assert_eq!(stats.violation_count(), 4);
+        assert_eq!(stats.enforced_violation_count(), 1);
+        assert_eq!(stats.report_only_violation_count(), 3);
+
+        let breakdown = stats.violations_by_disposition_and_directive();
+        assert_eq!(breakdown["enforce"]["script-src"], 1);
+        assert_eq!(breakdown["report"]["script-src"], 2);
+        assert_eq!(breakdown["report"]["img-src"], 1);
+        assert!(!breakdown["enforce"].contains_key("img-src"));
+
+        // Within the first second the rate is taken over one second of uptime
+        assert!(stats.enforced_violation_rates().one_minute >= 1.0);
+        assert!(
+            stats.report_only_violation_rates().one_minute
+                > stats.enforced_violation_rates().one_minute
+        );
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.violations_by_disposition_and_directive, breakdown);
+        assert_eq!(
+            snapshot.report_only_violation_rates,
+            stats.report_only_violation_rates()
+        );
+        assert!(stats
+            .to_string()
+            .contains("Violations reported: 4 (1 enforced, 3 report-only)"));
+
+        stats.reset();
+        assert_eq!(stats.report_only_violation_count(), 0);
+        assert!(stats.violations_by_disposition_and_directive().is_empty());
+        assert_eq!(stats.enforced_violation_rates(), WindowedRates::default());
+    }
+
+    #[cfg(feature = "reporting")]
+    #[actix_web::test]
+    async fn test_blocked_origin_tracking_is_bounded() {
+        use actix_web::{test as actix_test, App};
+        use actix_web_csp::middleware::CspReportingMiddleware;
+        use actix_web_csp::test::ViolationReportBuilder;
+        use std::sync::Arc;
+
+        let stats = Arc::new(CspStats::new());
+        let app = actix_test::init_service(
+            App::new().wrap(CspReportingMiddleware::new(|_| {}).with_stats(stats.clone())),
+        )
+        .await;
+
+        for _ in 0..5 {
+            let req = ViolationReportBuilder::new("https://frequent.example/x.js", "script-src")
+                .to_request("/csp-report")
+                .to_request();
+            actix_test::call_service(&app, req).await;
+        }
+        for index in 0..200 {
+            let req = ViolationReportBuilder::new(
+                format!("https://host-{index}.example/x.js"),
+                "script-src",
+            )
+            .to_request("/csp-report")
+            .to_request();
+            actix_test::call_service(&app, req).await;
+        }
+
+        assert_eq!(stats.top_blocked_origins(usize::MAX).len(), 64);
+        assert_eq!(
+            stats.top_blocked_origins(1)[0].0,
+            "https://frequent.example"
+        );
+    }
+
+    #[cfg(feature = "reporting")]
+    #[actix_web::test]
+    async fn test_policy_trends_flag_violation_spike_after_update() {
+        use actix_web::{test as actix_test, App};
+        use actix_web_csp::core::{CspConfig, CspPolicyBuilder, Source};
+        use actix_web_csp::middleware::CspReportingMiddleware;
+        use actix_web_csp::test::ViolationReportBuilder;
+
+        let config = CspConfig::new(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .build_unchecked(),
+        );
+        let app = actix_test::init_service(
+            App::new().wrap(CspReportingMiddleware::new(|_| {}).with_stats(config.stats().clone())),
+        )
+        .await;
+
+        let report = || {
+            ViolationReportBuilder::new("inline", "script-src")
+                .to_request("/csp-report")
+                .to_request()
+        };
+
+        // The first version draws enough reports to serve as the baseline
+        for _ in 0..10 {
+            actix_test::call_service(&app, report()).await;
+        }
+        config.update_policy(|policy| {
+            policy.append_source("script-src", Source::None);
+        });
+        for _ in 0..30 {
+            actix_test::call_service(&app, report()).await;
+        }
+
+        let trends = config.stats().policy_trends();
+        let snapshot = config.stats().snapshot();
+        if cfg!(feature = "stats") {
+            assert_eq!(trends.len(), 2);
+            assert_eq!(trends[0].violations, 10);
+            assert!(!trends[0].active);
+            assert_eq!(trends[1].policy_hash, config.policy().structural_hash());
+            assert_eq!(trends[1].violations, 30);
+            assert!(trends[1].regression);
+            assert!(snapshot
+                .to_prometheus()
+                .contains("csp_policy_regressions 1\n"));
+        } else {
+            assert!(trends.is_empty());
+        }
+        assert_eq!(snapshot.policy_trends, trends);
+    }
+
+    #[cfg(feature = "reporting")]
+    #[actix_web::test]
+    async fn test_policy_trends_need_a_baseline_before_flagging() {
+        use actix_web::{test as actix_test, App};
+        use actix_web_csp::core::{CspConfig, CspPolicyBuilder, Source};
+        use actix_web_csp::middleware::CspReportingMiddleware;
+        use actix_web_csp::test::ViolationReportBuilder;
+
+        let config = CspConfig::new(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .build_unchecked(),
+        );
+        let app = actix_test::init_service(
+            App::new().wrap(CspReportingMiddleware::new(|_| {}).with_stats(config.stats().clone())),
+        )
+        .await;
+
+        // Replaced at once and without a single report, the first version says
+        // nothing about the usual violation rate
+        config.update_policy(|policy| {
+            policy.append_source("script-src", Source::None);
+        });
+        for _ in 0..12 {
+            let req = ViolationReportBuilder::new("inline", "script-src")
+                .to_request("/csp-report")
+                .to_request();
+            actix_test::call_service(&app, req).await;
+        }
+
+        let trends = config.stats().policy_trends();
+        if cfg!(feature = "stats") {
+            assert_eq!(trends.len(), 2);
+            assert_eq!(trends[1].violations, 12);
+            assert_eq!(trends[1].previous_violations_per_minute, Some(0.0));
+            assert!(!trends[1].regression);
+        }
+        assert!(!config
+            .stats()
+            .snapshot()
+            .to_prometheus()
+            .contains("csp_policy_regressions 1\n"));
+    }
+
+    #[test]
+    fn test_policy_trends_ignore_unchanged_updates() {
+        use actix_web_csp::core::{CspConfig, CspPolicy};
+
+        let config = CspConfig::new(CspPolicy::default());
+        config.update_policy(|_| {});
+
+        let expected = if cfg!(feature = "stats") { 1 } else { 0 };
+        assert_eq!(config.stats().policy_trends().len(), expected);
+        assert!(config
+            .stats()
+            .policy_trends()
+            .iter()
+            .all(|trend| trend.active && !trend.regression));
+    }
+
+    #[test]
+    fn test_stats_snapshot_renders_histograms() {
+        let snapshot = StatsSnapshot {
+            report_size_bytes: HistogramSnapshot {
+                buckets: vec![(256.0, 1), (512.0, 3)],
+                sum: 1200.0,
+                count: 4,
+            },
+            ..StatsSnapshot::default()
+        };
+
+        let metrics = snapshot.to_prometheus();
+
+        assert!(metrics.contains("# TYPE csp_report_size_bytes histogram\n"));
+        assert!(metrics.contains("csp_report_size_bytes_bucket{le=\"512\"} 3\n"));
+        assert!(metrics.contains("csp_report_size_bytes_bucket{le=\"+Inf\"} 4\n"));
+        assert!(metrics.contains("csp_report_size_bytes_sum 1200\n"));
+        assert!(metrics.contains("csp_violation_handler_duration_seconds_count 0\n"));
+    }
+
+    #[cfg(feature = "reporting")]
+    #[actix_web::test]
+    async fn test_stats_record_report_sizes_and_handler_latency() {
+        use actix_web::{test as actix_test, App};
+        use actix_web_csp::middleware::CspReportingMiddleware;
+        use actix_web_csp::test::ViolationReportBuilder;
+        use std::sync::Arc;
+
+        let stats = Arc::new(CspStats::new());
+        let app = actix_test::init_service(
+            App::new().wrap(
+                CspReportingMiddleware::new(|_| thread::sleep(Duration::from_millis(2)))
+                    .with_stats(stats.clone()),
+            ),
+        )
+        .await;
+
+        for _ in 0..2 {
+            let req = ViolationReportBuilder::new("https://evil.example/a.js", "script-src")
+                .to_request("/csp-report")
+                .to_request();
+            actix_test::call_service(&app, req).await;
+        }
+
+        let sizes = stats.report_size_histogram();
+        let durations = stats.handler_duration_histogram();
+        if cfg!(feature = "stats") {
+            assert_eq!(sizes.count, 2);
+            assert!(sizes.sum > 0.0);
+            assert_eq!(sizes.buckets.last().unwrap().1, 2);
+            assert_eq!(durations.count, 2);
+            assert!(durations.sum >= 0.004);
+            // Both observations are slower than the 1 ms bucket
+            assert_eq!(durations.buckets[2], (0.001, 0));
+        } else {
+            assert_eq!(sizes, HistogramSnapshot::default());
+        }
+
+        stats.reset();
+        assert_eq!(stats.handler_duration_histogram().count, 0);
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn test_stats_snapshot_reports_per_cache_metrics() {
+        use actix_web_csp::{CspConfigBuilder, CspPolicyBuilder, Source};
+        use std::num::NonZeroU64;
+
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .build_unchecked();
+        let config = CspConfigBuilder::new()
+            .policy(policy.clone())
+            .with_cache_size(8)
+            .build();
+        let key = NonZeroU64::new(7).unwrap();
+
+        assert!(config.get_cached_policy(key).is_none());
+        config.cache_policy(key, policy);
+        assert!(config.get_cached_policy(key).is_some());
+
+        let snapshot = config.stats().snapshot();
+        let policy_cache = snapshot.caches["policy"];
+        assert_eq!((policy_cache.hits, policy_cache.misses), (1, 1));
+        assert_eq!((policy_cache.entries, policy_cache.capacity), (1, 8));
+        assert!(snapshot.caches.contains_key("buffer_pool"));
+        assert_eq!(
+            snapshot.caches.contains_key("verification"),
+            cfg!(feature = "verify")
+        );
+
+        let metrics = snapshot.to_prometheus();
+        assert!(metrics.contains("# TYPE csp_cache_hits_total counter"));
+        assert!(metrics.contains("csp_cache_hits_total{cache=\"policy\"} 1"));
+        assert!(metrics.contains("csp_cache_misses_total{cache=\"policy\"} 1"));
+        assert!(metrics.contains("csp_cache_evictions_total{cache=\"policy\"} 0"));
+        assert!(metrics.contains("# TYPE csp_cache_entries gauge"));
+        assert!(metrics.contains("csp_cache_capacity{cache=\"policy\"} 8"));
+        assert!(metrics.contains("csp_cache_entries{cache=\"buffer_pool\"}"));
+
+        config.stats().reset();
+        let policy_cache = config.stats().cache_stats()["policy"];
+        assert_eq!((policy_cache.hits, policy_cache.misses), (0, 0));
+        assert_eq!(policy_cache.entries, 1);
+    }
+
+    #[cfg(all(feature = "stats", feature = "verify"))]
+    #[test]
+    fn test_stats_snapshot_counts_invalid_verifier_uris() {
+        use actix_web_csp::{CspPolicyBuilder, PolicyVerifier, Source};
+
+        let stats = CspStats::new();
+        let before = stats.snapshot().invalid_uri_count;
+        let mut verifier = PolicyVerifier::new(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .build_unchecked(),
+        );
+        assert!(!verifier.verify_uri("::garbage::", "img-src").unwrap());
+
+        let snapshot = stats.snapshot();
+        assert!(snapshot.invalid_uri_count > before);
+        assert!(snapshot
+            .to_prometheus()
+            .contains("# TYPE csp_verifier_invalid_uris_total counter"));
+    }
 }
@@ -0,0 +1,74 @@
+use actix_web::http::header::USER_AGENT;
+use actix_web::{test as actix_test, App};
+use actix_web_csp::middleware::CspReportingMiddleware;
+use actix_web_csp::monitoring::user_agent::parse_user_agent;
+use actix_web_csp::test::ViolationReportBuilder;
+
+const SAFARI_16: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 13_4) AppleWebKit/605.1.15 \
+    (KHTML, like Gecko) Version/16.4 Safari/605.1.15";
+const CHROME_120: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
+    (KHTML, like Gecko) Chrome/120.0.6099.109 Safari/537.36";
+const EDGE_120: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
+    (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.2210.91";
+const FIREFOX_121: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0";
+const IE_11: &str = "Mozilla/5.0 (Windows NT 10.0; WOW64; Trident/7.0; rv:11.0) like Gecko";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_user_agent_recognises_major_browsers() {
+        for (user_agent, family, major) in [
+            (SAFARI_16, "Safari", 16),
+            (CHROME_120, "Chrome", 120),
+            (EDGE_120, "Edge", 120),
+            (FIREFOX_121, "Firefox", 121),
+            (IE_11, "Internet Explorer", 11),
+        ] {
+            let browser = parse_user_agent(user_agent).unwrap();
+            assert_eq!(browser.family, family, "{user_agent}");
+            assert_eq!(browser.major_version(), Some(major), "{user_agent}");
+        }
+    }
+
+    #[test]
+    fn test_parse_user_agent_keeps_full_version() {
+        let browser = parse_user_agent(CHROME_120).unwrap();
+
+        assert_eq!(browser.version.as_deref(), Some("120.0.6099.109"));
+        assert_eq!(browser.to_string(), "Chrome 120.0.6099.109");
+    }
+
+    #[test]
+    fn test_parse_user_agent_rejects_unknown_clients() {
+        assert!(parse_user_agent("curl/8.4.0").is_none());
+        assert!(parse_user_agent("").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_reporting_middleware_attaches_browser() {
+        let middleware = CspReportingMiddleware::new(|report| {
+            assert_eq!(report.browser.unwrap().family, "Safari");
+        });
+        let stats = middleware.stats().clone();
+        let app = actix_test::init_service(App::new().wrap(middleware)).await;
+
+        for _ in 0..2 {
+            let req = ViolationReportBuilder::new("inline", "script-src")
+                .to_request("/csp-report")
+                .insert_header((USER_AGENT, SAFARI_16))
+                .to_request();
+            actix_test::call_service(&app, req).await;
+        }
+
+        if cfg!(feature = "stats") {
+            assert_eq!(stats.violations_by_browser()["Safari 16"], 2);
+            let metrics = stats.snapshot().to_prometheus();
+            assert!(metrics.contains("# TYPE csp_browser_violations gauge\n"));
+            assert!(metrics.contains("csp_browser_violations{browser=\"Safari 16\"} 2\n"));
+        } else {
+            assert!(stats.violations_by_browser().is_empty());
+        }
+    }
+}
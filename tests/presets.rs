@@ -1,4 +1,9 @@
-use actix_web_csp::{preset_policy, CspPreset};
+use actix_web::{test as actix_test, web, App, HttpResponse};
+use actix_web_csp::core::Sandbox;
+use actix_web_csp::presets::FrameAncestor;
+use actix_web_csp::{
+    preset_policy, presets, CspConfigBuilder, CspMiddleware, CspPolicyBuilder, CspPreset, Source,
+};
 
 #[cfg(test)]
 mod tests {
@@ -45,4 +50,167 @@ mod tests {
         );
         assert_eq!("api-only".parse::<CspPreset>().unwrap(), CspPreset::Api);
     }
+
+    #[test]
+    fn test_realtime_preset_mirrors_websocket_hosts() {
+        let policy = presets::realtime(["events.example.com", "http://localhost:8080"]);
+        let connect_src = policy.get_directive("connect-src").unwrap().to_string();
+
+        assert_eq!(
+            connect_src,
+            "connect-src 'self' https://events.example.com http://localhost:8080 \
+             wss://events.example.com ws://localhost:8080"
+        );
+        policy.validate().unwrap();
+    }
+
+    #[test]
+    fn test_websocket_mirroring_follows_policy_updates() {
+        let config = CspConfigBuilder::new()
+            .policy(preset_policy(CspPreset::Strict))
+            .with_websocket_mirroring(true)
+            .build();
+
+        config.update_policy(|policy| {
+            policy.append_source(
+                "connect-src",
+                Source::Host("https://api.example.com".into()),
+            );
+        });
+
+        let rendered = config.policy().to_string();
+        assert!(
+            rendered.contains("connect-src 'self' https://api.example.com wss://api.example.com")
+        );
+    }
+
+    #[test]
+    fn test_embeddable_preset_uses_typed_ancestors() {
+        let policy = presets::embeddable([
+            FrameAncestor::same_origin(),
+            FrameAncestor::origin("https://partner.example.com:8443").unwrap(),
+        ]);
+
+        assert_eq!(
+            policy.get_directive("frame-ancestors").unwrap().to_string(),
+            "frame-ancestors 'self' https://partner.example.com:8443"
+        );
+        assert!(presets::embeddable([])
+            .to_string()
+            .contains("frame-ancestors 'none'"));
+        assert!(FrameAncestor::origin("https://partner.example.com/widget").is_err());
+        assert!(FrameAncestor::origin("partner.example.com").is_err());
+    }
+
+    #[test]
+    fn test_clickjacking_protection_only_restricts_framing() {
+        let policy = presets::clickjacking_protection([
+            FrameAncestor::same_origin(),
+            FrameAncestor::origin("https://partner.example.com").unwrap(),
+        ]);
+
+        assert_eq!(
+            policy.to_string(),
+            "frame-ancestors 'self' https://partner.example.com"
+        );
+        policy.validate().unwrap();
+        assert_eq!(
+            presets::clickjacking_protection([]).to_string(),
+            "frame-ancestors 'none'"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_clickjacking_protection_as_additional_policy() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(
+                    actix_web_csp::csp_middleware(
+                        CspPolicyBuilder::new()
+                            .default_src([Source::Self_])
+                            .build_unchecked(),
+                    )
+                    .with_additional_policy(presets::clickjacking_protection([])),
+                )
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        let values: Vec<_> = res
+            .headers()
+            .get_all("content-security-policy")
+            .map(|value| value.to_str().unwrap())
+            .collect();
+        assert_eq!(values, ["default-src 'self'", "frame-ancestors 'none'"]);
+    }
+
+    #[test]
+    fn test_frame_ancestors_none_rejects_embed_sandbox_tokens() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .frame_ancestors([Source::None])
+            .sandbox(Sandbox::new().allow_scripts().allow_top_navigation())
+            .build_unchecked();
+
+        assert!(policy.validate().is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_frame_options_follow_frame_ancestors() {
+        async fn fetch(policy: actix_web_csp::CspPolicy) -> Option<String> {
+            let config = CspConfigBuilder::new()
+                .policy(policy)
+                .with_frame_options_sync(true)
+                .build();
+            let app = actix_test::init_service(App::new().wrap(CspMiddleware::new(config)).route(
+                "/",
+                web::get().to(|| async {
+                    HttpResponse::Ok()
+                        .insert_header(("x-frame-options", "DENY"))
+                        .finish()
+                }),
+            ))
+            .await;
+            let res = actix_test::call_service(
+                &app,
+                actix_test::TestRequest::get().uri("/").to_request(),
+            )
+            .await;
+            res.headers()
+                .get("x-frame-options")
+                .map(|value| value.to_str().unwrap().to_owned())
+        }
+
+        assert_eq!(
+            fetch(presets::embeddable([FrameAncestor::same_origin()])).await,
+            Some("SAMEORIGIN".to_string())
+        );
+        assert_eq!(
+            fetch(presets::embeddable([FrameAncestor::origin(
+                "https://partner.example.com"
+            )
+            .unwrap()]))
+            .await,
+            None
+        );
+    }
+
+    #[test]
+    fn test_wasm_app_preset_allows_wasm_and_blob_workers() {
+        let policy = presets::wasm_app();
+        policy.validate().unwrap();
+
+        assert_eq!(
+            policy.get_directive("script-src").unwrap().to_string(),
+            "script-src 'self' 'wasm-unsafe-eval' blob:"
+        );
+        assert_eq!(
+            policy.get_directive("worker-src").unwrap().to_string(),
+            "worker-src 'self' blob:"
+        );
+        assert!(!policy.to_string().contains("'unsafe-eval'"));
+    }
 }
@@ -61,4 +61,20 @@ proptest! {
 
         prop_assert_eq!(restored.to_string(), policy.to_string());
     }
+
+    #[test]
+    fn compiled_header_parses_back(
+        default_src in prop::collection::vec(arb_source(), 1..4),
+        script_src in prop::collection::vec(arb_source(), 0..4),
+    ) {
+        let policy = CspPolicyBuilder::new()
+            .default_src(default_src)
+            .script_src(script_src)
+            .report_uri("/csp-report")
+            .build_unchecked();
+
+        if policy.validate().is_ok() && policy.compile().is_ok() {
+            prop_assert!(policy.roundtrip_check().is_ok());
+        }
+    }
 }
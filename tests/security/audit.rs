@@ -0,0 +1,397 @@
+use actix_web_csp::{
+    core::{CspPolicy, CspPolicyBuilder, Directive, Source},
+    integrations::Service,
+    presets,
+    security::{Browser, ConflictKind, PaymentRule, PolicyAuditor},
+};
+
+fn checkout_policy() -> CspPolicy {
+    CspPolicyBuilder::new()
+        .default_src([Source::Self_])
+        .script_src([Source::Self_])
+        .apply_service(Service::Stripe)
+        .report_uri("/csp-report")
+        .build_unchecked()
+}
+
+fn with_directive(
+    mut policy: CspPolicy,
+    name: &'static str,
+    sources: impl IntoIterator<Item = Source>,
+) -> CspPolicy {
+    let mut directive = Directive::new(name);
+    for source in sources {
+        directive.add_source(source);
+    }
+    policy.add_directive(directive);
+    policy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_browser_compat_flags_unsupported_directives_and_keywords() {
+        let mut policy = CspPolicyBuilder::new()
+            .script_src([Source::Self_, Source::WasmUnsafeEval])
+            .build_unchecked();
+        policy.add_directive(Directive::new("require-trusted-types-for"));
+
+        let notes = PolicyAuditor::new()
+            .with_target(Browser::Chrome, 100)
+            .with_target(Browser::Firefox, 115)
+            .without_browser(Browser::Edge)
+            .without_browser(Browser::Safari)
+            .browser_compat(&policy);
+
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].target.browser, Browser::Firefox);
+        assert_eq!(notes[0].feature, "require-trusted-types-for");
+        assert_eq!(notes[0].supported_since, None);
+    }
+
+    #[test]
+    fn test_browser_compat_reports_minimum_versions() {
+        let policy = CspPolicyBuilder::new()
+            .script_src([Source::Self_, Source::StrictDynamic])
+            .build_unchecked();
+
+        let notes = PolicyAuditor::new()
+            .without_browser(Browser::Edge)
+            .with_target(Browser::Safari, 15)
+            .browser_compat(&policy);
+
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].supported_since, Some(16));
+        assert_eq!(
+            notes[0].to_string(),
+            "'strict-dynamic' in script-src is ignored by Safari 15 (supported since 16)"
+        );
+    }
+
+    #[test]
+    fn test_browser_compat_is_empty_for_baseline_policy() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .img_src([Source::Self_, Source::Scheme("data".into())])
+            .build_unchecked();
+
+        assert!(PolicyAuditor::new().browser_compat(&policy).is_empty());
+    }
+
+    #[test]
+    fn test_relaxations_explain_wasm_preset_trade_offs() {
+        let relaxations = PolicyAuditor::new().relaxations(&presets::wasm_app());
+        let found: Vec<_> = relaxations
+            .iter()
+            .map(|relaxation| (relaxation.directive.as_str(), relaxation.source))
+            .collect();
+
+        assert_eq!(
+            found,
+            [
+                ("script-src", "'wasm-unsafe-eval'"),
+                ("script-src", "blob:"),
+                ("worker-src", "blob:"),
+            ]
+        );
+        assert!(relaxations[0]
+            .to_string()
+            .starts_with("'wasm-unsafe-eval' in script-src: "));
+    }
+
+    #[test]
+    fn test_relaxations_ignore_sources_outside_their_directives() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .img_src([Source::Self_, Source::Scheme("blob".into())])
+            .script_src([Source::Self_, Source::UnsafeEval])
+            .build_unchecked();
+
+        let relaxations = PolicyAuditor::new().relaxations(&policy);
+
+        assert_eq!(relaxations.len(), 1);
+        assert_eq!(relaxations[0].source, "'unsafe-eval'");
+        assert!(PolicyAuditor::new()
+            .relaxations(
+                &CspPolicyBuilder::new()
+                    .default_src([Source::Self_])
+                    .build_unchecked()
+            )
+            .is_empty());
+    }
+
+    #[test]
+    fn test_relaxations_explain_unsafe_hashes() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .event_handler_hashes(["doSomething()"])
+            .build_unchecked();
+
+        let relaxations = PolicyAuditor::new().relaxations(&policy);
+
+        assert_eq!(relaxations.len(), 1);
+        assert_eq!(relaxations[0].directive, "script-src");
+        assert_eq!(relaxations[0].source, "'unsafe-hashes'");
+        assert!(relaxations[0].trade_off.contains("addEventListener"));
+    }
+
+    #[test]
+    fn test_audit_reports_unsafe_keywords_reporting_and_size() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_, Source::UnsafeInline, Source::UnsafeEval])
+            .style_src([Source::Self_, Source::UnsafeInline])
+            .build_unchecked();
+
+        let audit = PolicyAuditor::new().audit(&policy);
+
+        assert_eq!(audit.policy.directive_count, 3);
+        assert_eq!(
+            audit.policy.unsafe_keywords,
+            ["'unsafe-inline'", "'unsafe-eval'"]
+        );
+        assert!(!audit.policy.reporting);
+        assert_eq!(
+            audit.policy.header_bytes,
+            Some(policy.compile().unwrap().header_value().len())
+        );
+        assert_eq!(audit.relaxations.len(), 1);
+        assert!(audit.has_warnings());
+        let rendered = audit.to_string();
+        assert!(rendered.contains("3 directives"));
+        assert!(rendered.contains("no reporting configured"));
+        assert!(rendered.contains("relaxation: 'unsafe-eval' in script-src"));
+
+        let clean = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .report_uri("/csp-report")
+            .build_unchecked();
+        let audit = PolicyAuditor::new().audit(&clean);
+        assert!(audit.policy.reporting);
+        assert!(!audit.has_warnings());
+    }
+
+    #[test]
+    fn test_payment_page_accepts_restricted_reported_policy() {
+        let audit = PolicyAuditor::new().audit_payment_page("/checkout", &checkout_policy());
+
+        assert!(audit.is_compliant(), "{audit}");
+        assert!(audit.to_string().contains("all checks passed"));
+    }
+
+    #[test]
+    fn test_payment_page_requires_script_restrictions_and_reporting() {
+        let policy = CspPolicyBuilder::new()
+            .img_src([Source::Self_])
+            .build_unchecked();
+
+        let audit = PolicyAuditor::new().audit_payment_page("/checkout", &policy);
+
+        assert!(audit.has(PaymentRule::ScriptsUnrestricted));
+        assert!(audit.has(PaymentRule::ReportingMissing));
+        assert_eq!(PaymentRule::ScriptsUnrestricted.requirement(), "6.4.3");
+        assert_eq!(PaymentRule::ReportingMissing.requirement(), "11.6.1");
+        assert!(audit.to_string().contains("[PCI DSS 11.6.1]"));
+    }
+
+    #[test]
+    fn test_payment_page_flags_unsafe_keywords_and_wildcards() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([
+                Source::Self_,
+                Source::UnsafeInline,
+                Source::Host("*.com".into()),
+                Source::Host("https://*.CO.UK".into()),
+                Source::Host("*.js.stripe.com".into()),
+                Source::Scheme("data".into()),
+            ])
+            .connect_src([Source::Scheme("https".into())])
+            .img_src([Source::Host("*".into())])
+            .report_to("csp-endpoint")
+            .build_unchecked();
+
+        let audit = PolicyAuditor::new().audit_payment_page("/pay", &policy);
+        let wildcards: Vec<_> = audit
+            .findings
+            .iter()
+            .filter(|finding| finding.rule == PaymentRule::WildcardSource)
+            .map(|finding| finding.source.as_deref().unwrap())
+            .collect();
+
+        assert!(audit.has(PaymentRule::UnsafeScriptKeyword));
+        assert_eq!(wildcards, ["*.com", "https://*.CO.UK", "data:", "https:"]);
+        assert!(!audit.has(PaymentRule::ReportingMissing));
+    }
+
+    #[test]
+    fn test_payment_page_allows_inline_guarded_by_nonce() {
+        let policy = CspPolicyBuilder::new()
+            .script_src([
+                Source::Self_,
+                Source::UnsafeInline,
+                Source::Nonce("abc123".into()),
+            ])
+            .report_uri("/csp-report")
+            .build_unchecked();
+
+        let audit = PolicyAuditor::new().audit_payment_page("/checkout", &policy);
+
+        assert!(audit.is_compliant(), "{audit}");
+    }
+
+    #[test]
+    fn test_payment_page_flags_report_only_policy() {
+        let mut policy = checkout_policy();
+        policy.set_report_only(true);
+
+        let audit = PolicyAuditor::new().audit_payment_page("/checkout", &policy);
+
+        assert_eq!(audit.findings.len(), 1);
+        assert!(audit.has(PaymentRule::ReportOnly));
+    }
+
+    #[test]
+    fn test_audit_route_adds_payment_section_for_payment_paths() {
+        let auditor = PolicyAuditor::new().with_payment_path("/checkout/");
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .build_unchecked();
+
+        assert!(auditor.is_payment_path("/checkout"));
+        assert!(auditor.is_payment_path("/checkout/confirm"));
+        assert!(!auditor.is_payment_path("/checkouts"));
+        assert!(auditor.audit_route("/about", &policy).payment.is_none());
+
+        let audit = auditor.audit_route("/checkout/confirm", &policy);
+        let payment = audit.payment.as_ref().unwrap();
+        assert!(payment.has(PaymentRule::ReportingMissing));
+        assert!(audit.has_warnings());
+        assert!(audit
+            .to_string()
+            .contains("payment page /checkout/confirm: 1 finding\n"));
+    }
+
+    #[test]
+    fn test_directive_conflicts_flag_directives_inheriting_none() {
+        let policy = with_directive(
+            CspPolicyBuilder::new()
+                .default_src([Source::None])
+                .img_src([Source::Self_])
+                .build_unchecked(),
+            "script-src-elem",
+            [Source::Self_],
+        );
+
+        let conflicts = PolicyAuditor::new().directive_conflicts(&policy);
+        let inherited = conflicts
+            .iter()
+            .filter(|conflict| conflict.kind == ConflictKind::InheritsNone)
+            .map(|conflict| conflict.directive.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(inherited, ["style-src", "connect-src", "font-src"]);
+
+        let connect = conflicts
+            .iter()
+            .find(|conflict| conflict.directive == "connect-src")
+            .unwrap();
+        assert_eq!(connect.related, "default-src");
+        assert!(connect.to_string().starts_with(
+            "connect-src: not set, so it falls back to default-src 'none' and fetch()"
+        ));
+    }
+
+    #[test]
+    fn test_directive_conflicts_flag_refinements_without_base_directive() {
+        let policy = with_directive(
+            CspPolicyBuilder::new()
+                .default_src([Source::Self_])
+                .build_unchecked(),
+            "script-src-elem",
+            [Source::Self_, Source::Host("cdn.example.com".into())],
+        );
+
+        let conflicts = PolicyAuditor::new().directive_conflicts(&policy);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].kind, ConflictKind::MissingBaseDirective);
+        assert_eq!(conflicts[0].directive, "script-src");
+        assert_eq!(conflicts[0].related, "script-src-elem");
+        assert!(conflicts[0].advice.contains(
+            "script-src-attr and browsers without script-src-elem support fall back to default-src"
+        ));
+
+        let both = with_directive(
+            with_directive(
+                CspPolicyBuilder::new().build_unchecked(),
+                "style-src-elem",
+                [Source::Self_],
+            ),
+            "style-src-attr",
+            [Source::UnsafeInline],
+        );
+        let conflicts = PolicyAuditor::new().directive_conflicts(&both);
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].advice.contains(
+            "style-src-elem and style-src-attr are, so browsers without support for them are unrestricted"
+        ));
+    }
+
+    #[test]
+    fn test_directive_conflicts_flag_directives_dropping_self() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .img_src([Source::Scheme("data".into())])
+            .script_src([Source::Nonce("abc123".into()), Source::StrictDynamic])
+            .style_src([Source::Self_, Source::UnsafeInline])
+            .object_src([Source::None])
+            .build_unchecked();
+
+        let audit = PolicyAuditor::new().audit(&policy);
+        assert_eq!(audit.conflicts.len(), 1);
+        assert_eq!(audit.conflicts[0].kind, ConflictKind::DropsSelf);
+        assert_eq!(audit.conflicts[0].directive, "img-src");
+        assert!(audit.has_warnings());
+        assert!(audit
+            .to_string()
+            .contains("conflict: img-src: replaces default-src rather than extending it"));
+
+        let consistent = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .img_src([Source::Self_, Source::Scheme("data".into())])
+            .report_uri("/csp-report")
+            .build_unchecked();
+        let audit = PolicyAuditor::new().audit(&consistent);
+        assert!(audit.conflicts.is_empty());
+        assert!(!audit.has_warnings());
+    }
+
+    #[test]
+    fn test_static_nonces_flag_literal_nonce_sources() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_, Source::Nonce("r4nd0m".into())])
+            .style_src([Source::Self_, Source::NoncePlaceholder])
+            .report_uri("/csp-report")
+            .build_unchecked();
+
+        let audit = PolicyAuditor::new().audit(&policy);
+        assert_eq!(audit.static_nonces.len(), 1);
+        assert_eq!(audit.static_nonces[0].directive, "script-src");
+        assert_eq!(audit.static_nonces[0].nonce, "r4nd0m");
+        assert!(audit.has_warnings());
+        assert!(audit
+            .to_string()
+            .contains("static nonce: 'nonce-r4nd0m' in script-src is sent with every response"));
+
+        let per_request = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_, Source::NoncePlaceholder])
+            .report_uri("/csp-report")
+            .build_unchecked();
+        let audit = PolicyAuditor::new().audit(&per_request);
+        assert!(audit.static_nonces.is_empty());
+        assert!(!audit.has_warnings());
+    }
+}
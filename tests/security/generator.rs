@@ -0,0 +1,104 @@
+use actix_web_csp::{
+    core::Source,
+    security::{HashAlgorithm, HashGenerator, PolicyGenerator},
+};
+
+fn header(generator: &PolicyGenerator) -> String {
+    let mut generated = generator.generate();
+    generated
+        .policy
+        .header_value()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generator_starts_from_none() {
+        let generator = PolicyGenerator::new();
+
+        assert_eq!(
+            header(&generator),
+            "default-src 'none'; base-uri 'none'; form-action 'none'"
+        );
+    }
+
+    #[test]
+    fn test_generator_collects_origins_across_documents() {
+        let mut generator = PolicyGenerator::new();
+        generator
+            .add_document(
+                "https://shop.example.com/",
+                r#"<link rel="stylesheet" href="/site.css">
+                   <script src="https://cdn.example.net/app.js"></script>
+                   <img src="data:image/png;base64,AAAA">"#,
+            )
+            .unwrap();
+        generator
+            .add_document(
+                "https://shop.example.com/checkout",
+                r#"<script src="https://cdn.example.net/pay.js"></script>
+                   <iframe src="http://legacy.example.org:8080/widget"></iframe>
+                   <form action="/pay"></form>"#,
+            )
+            .unwrap();
+
+        let header = header(&generator);
+
+        assert_eq!(generator.document_count(), 2);
+        assert!(header.contains("script-src cdn.example.net"));
+        assert!(header.contains("style-src 'self'"));
+        assert!(header.contains("img-src data:"));
+        assert!(header.contains("frame-src http://legacy.example.org:8080"));
+        assert!(header.contains("form-action 'self'"));
+    }
+
+    #[test]
+    fn test_generator_hashes_inline_content() {
+        let mut generator = PolicyGenerator::new().with_hash_algorithm(HashAlgorithm::Sha384);
+        let html = r#"<script>init()</script>
+            <style>body { margin: 0 }</style>
+            <button onclick="buy()">Buy</button>
+            <script>init()</script>"#;
+        generator
+            .add_document("https://shop.example.com/", html)
+            .unwrap();
+
+        let generated = generator.generate();
+        let script_src = generated.policy.get_directive("script-src").unwrap();
+
+        assert_eq!(generated.hashes.len(), 3);
+        assert_eq!(generated.hashes[0].excerpt, "init()");
+        assert!(script_src.sources().contains(&Source::UnsafeHashes));
+        assert!(script_src
+            .sources()
+            .contains(&HashGenerator::generate_source(
+                HashAlgorithm::Sha384,
+                b"buy()"
+            )));
+        assert_eq!(
+            generated
+                .policy
+                .get_directive("style-src")
+                .unwrap()
+                .sources(),
+            [HashGenerator::generate_source(
+                HashAlgorithm::Sha384,
+                b"body { margin: 0 }"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_generator_rejects_invalid_page_url() {
+        let mut generator = PolicyGenerator::new();
+
+        assert!(generator.add_document("not a url", "<p></p>").is_err());
+        assert_eq!(generator.document_count(), 0);
+    }
+}
@@ -68,6 +68,7 @@ mod tests {
         assert_ne!(sha256_1, sha384);
     }
 
+    #[cfg(feature = "ring")]
     #[test]
     fn test_hash_algorithm_digest_algorithm() {
         let sha256 = HashAlgorithm::Sha256;
@@ -118,4 +119,67 @@ mod tests {
         let hash = HashGenerator::generate(HashAlgorithm::Sha256, &large_content);
         assert!(!hash.is_empty());
     }
+
+    #[test]
+    fn test_event_handler_source_hashes_attribute_value() {
+        let source = HashGenerator::event_handler_source(HashAlgorithm::Sha256, "doSomething()");
+
+        assert_eq!(
+            source,
+            HashGenerator::generate_source(HashAlgorithm::Sha256, b"doSomething()")
+        );
+        assert_ne!(
+            source,
+            HashGenerator::event_handler_source(HashAlgorithm::Sha256, " doSomething()")
+        );
+    }
+
+    #[test]
+    fn test_hash_generator_matches_known_digests() {
+        let large = vec![b'a'; 100_000];
+        let cases: [(HashAlgorithm, &[u8], &str); 6] = [
+            (
+                HashAlgorithm::Sha256,
+                b"",
+                "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=",
+            ),
+            (
+                HashAlgorithm::Sha256,
+                b"alert('Hello, world.');",
+                "qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng=",
+            ),
+            (
+                HashAlgorithm::Sha384,
+                b"alert('Hello, world.');",
+                "H8BRh8j48O9oYatfu5AZzq6A9RINhZO5H16dQZngK7T62em8MUt1FLm52t+eX6xO",
+            ),
+            (
+                HashAlgorithm::Sha512,
+                b"alert('Hello, world.');",
+                "Q2bFTOhEALkN8hOms2FKTDLy7eugP2zFZ1T8LCvX42Fp3WoNr3bjZSAHeOsHrbV1Fu9/A0EzCinRE7Af1ofPrw==",
+            ),
+            (
+                HashAlgorithm::Sha256,
+                &large,
+                "bRzyLXzAmwhd/CXuGh864CZYBMYHvCB0rSU7zIL9ge4=",
+            ),
+            (
+                HashAlgorithm::Sha512,
+                &large,
+                "x2ZP8Yu++lho0eujhjcIetcOY4ABP1L0zj3UqYvr1JNTzkC+Hf3a0iWbrNx70Q6Y1EpqaNXRCRvNVbR++1zVDg==",
+            ),
+        ];
+
+        for (algorithm, data, expected) in cases {
+            assert_eq!(HashGenerator::generate(algorithm, data), expected);
+        }
+        assert_eq!(
+            HashGenerator::batch_verify(&[(
+                HashAlgorithm::Sha384,
+                b"alert('Hello, world.');",
+                "H8BRh8j48O9oYatfu5AZzq6A9RINhZO5H16dQZngK7T62em8MUt1FLm52t+eX6xO",
+            )]),
+            [true]
+        );
+    }
 }
@@ -1,3 +1,10 @@
+pub mod audit;
+#[cfg(feature = "page-audit")]
+pub mod generator;
 pub mod hash;
 pub mod nonce;
+#[cfg(feature = "page-audit")]
+pub mod page_audit;
+#[cfg(feature = "verify")]
+pub mod remote;
 pub mod verify;
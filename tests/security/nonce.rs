@@ -1,4 +1,18 @@
 use actix_web_csp::security::{NonceGenerator, RequestNonce};
+use std::time::{Duration, Instant};
+
+/// Waits for the refill task to queue `count` nonces, giving up after a few
+/// seconds so a slow machine does not fail the test.
+async fn wait_for_queued(generator: &NonceGenerator, count: usize) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while generator.queued() != count {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        actix_web::rt::time::sleep(Duration::from_millis(1)).await;
+    }
+    true
+}
 
 #[cfg(test)]
 mod tests {
@@ -95,18 +109,145 @@ mod tests {
         assert_eq!(generator.length(), 32);
     }
 
+    #[test]
+    fn test_nonce_generator_pool_stats_count_reuse() {
+        let generator = NonceGenerator::new(16);
+
+        generator.generate();
+        generator.generate();
+        let stats = generator.pool_stats();
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.capacity, 32);
+        assert_eq!(stats.hit_rate(), 0.5);
+
+        generator.clone().generate();
+        assert_eq!(generator.pool_stats().hits, 2);
+        assert_eq!(generator.generated_count(), 3);
+    }
+
+    #[test]
+    fn test_nonce_generator_prewarm_and_pool_size() {
+        let generator = NonceGenerator::new(16).with_pool_size(4);
+        assert_eq!(generator.pool_size(), 4);
+
+        assert_eq!(generator.prewarm(10), 4);
+        assert_eq!(generator.prewarm(1), 0);
+        generator.generate();
+        let stats = generator.pool_stats();
+        assert_eq!(stats.misses, 0);
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.entries, 4);
+
+        generator.set_pool_size(1);
+        let stats = generator.pool_stats();
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.capacity, 1);
+        assert_eq!(stats.evictions, 3);
+
+        let unpooled = NonceGenerator::new(16).with_pool_size(0);
+        unpooled.generate();
+        unpooled.generate();
+        assert_eq!(unpooled.pool_stats().misses, 2);
+        assert_eq!(unpooled.pool_stats().entries, 0);
+
+        assert_eq!(NonceGenerator::with_capacity(8, 16).pool_stats().entries, 8);
+    }
+
+    #[test]
+    fn test_nonce_generator_generate_batch_queues_nonces() {
+        let generator = NonceGenerator::new(16).with_batch_capacity(8);
+        assert_eq!(generator.queued(), 0);
+
+        assert_eq!(generator.generate_batch(5), 5);
+        assert_eq!(generator.generate_batch(5), 3);
+        assert_eq!(generator.queued(), 8);
+
+        let nonces = (0..8).map(|_| generator.generate()).collect::<Vec<_>>();
+        assert_eq!(generator.queued(), 0);
+        assert_eq!(
+            generator.pool_stats().hits + generator.pool_stats().misses,
+            0
+        );
+        assert!(nonces.iter().all(|nonce| nonce.len() == 22));
+        for (i, nonce) in nonces.iter().enumerate() {
+            assert!(!nonces[i + 1..].contains(nonce));
+        }
+
+        generator.generate();
+        assert_eq!(generator.pool_stats().misses, 1);
+        assert_eq!(generator.generated_count(), 9);
+    }
+
+    #[test]
+    fn test_nonce_generator_set_length_drops_queued_nonces() {
+        let generator = NonceGenerator::new(16);
+        generator.generate_batch(4);
+        assert_eq!(generator.clone().queued(), 4);
+
+        generator.set_length(32);
+        assert_eq!(generator.queued(), 0);
+        assert_eq!(generator.generate().len(), 43);
+    }
+
+    #[test]
+    fn test_nonce_generator_set_length_during_batches_keeps_one_length() {
+        let generator = NonceGenerator::new(16).with_batch_capacity(64);
+        let batches = std::thread::spawn({
+            let generator = generator.clone();
+            move || {
+                for _ in 0..2_000 {
+                    generator.generate_batch(8);
+                    while generator.queued() > 32 {
+                        generator.generate();
+                    }
+                }
+            }
+        });
+
+        generator.set_length(32);
+        batches.join().unwrap();
+        while generator.queued() > 0 {
+            assert_eq!(generator.generate().len(), 43);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_nonce_generator_refill_task_tops_up_queue() {
+        let generator = NonceGenerator::new(16).with_batch_capacity(16);
+        let refill = generator.start_refill(4, Duration::from_millis(5));
+
+        assert!(wait_for_queued(&generator, 16).await);
+
+        for _ in 0..14 {
+            generator.generate();
+        }
+        assert!(wait_for_queued(&generator, 16).await);
+        refill.abort();
+    }
+
+    #[actix_web::test]
+    async fn test_nonce_generator_refill_task_clamps_zero_interval() {
+        let generator = NonceGenerator::new(16).with_batch_capacity(16);
+        let refill = generator.start_refill(4, Duration::ZERO);
+
+        assert!(wait_for_queued(&generator, 16).await);
+        refill.abort();
+    }
+
     #[test]
     fn test_request_nonce_creation() {
         let nonce_value = "test-nonce-123";
-        let request_nonce = RequestNonce(nonce_value.to_string());
+        let request_nonce = RequestNonce::from(nonce_value);
 
-        assert_eq!(*request_nonce, nonce_value);
+        assert_eq!(&*request_nonce, nonce_value);
     }
 
     #[test]
     fn test_request_nonce_clone() {
         let nonce_value = "test-nonce-456";
-        let request_nonce1 = RequestNonce(nonce_value.to_string());
+        let request_nonce1 = RequestNonce::from(nonce_value);
         let request_nonce2 = request_nonce1.clone();
 
         assert_eq!(*request_nonce1, *request_nonce2);
@@ -115,9 +256,18 @@ mod tests {
     #[test]
     fn test_request_nonce_deref() {
         let nonce_value = "test-nonce-789";
-        let request_nonce = RequestNonce(nonce_value.to_string());
+        let request_nonce = RequestNonce::from(nonce_value);
 
         assert_eq!(request_nonce.len(), nonce_value.len());
         assert!(request_nonce.contains("nonce"));
     }
+
+    #[test]
+    fn test_request_nonce_converts_into_string() {
+        let request_nonce = RequestNonce::from(String::from("test-nonce-abc"));
+        let shared = request_nonce.shared();
+
+        assert_eq!(String::from(request_nonce), "test-nonce-abc");
+        assert_eq!(&*shared, "test-nonce-abc");
+    }
 }
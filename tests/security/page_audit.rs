@@ -0,0 +1,149 @@
+use actix_web_csp::{
+    core::{CspPolicyBuilder, RequireSriFor, Source},
+    security::{
+        page_audit::{audit_html, extract_resources, PageResource},
+        PolicyVerifier, ResourceRef,
+    },
+};
+use std::borrow::Cow;
+
+const PAGE_URL: &str = "https://app.example.com/dashboard";
+
+fn verifier() -> PolicyVerifier {
+    let policy = CspPolicyBuilder::new()
+        .default_src([Source::Self_])
+        .script_src([
+            Source::Self_,
+            Source::Nonce(Cow::Borrowed("n0nce")),
+            Source::Host(Cow::Borrowed("cdn.example.com")),
+        ])
+        .style_src([Source::Self_])
+        .img_src([Source::Self_, Source::Scheme(Cow::Borrowed("data"))])
+        .build_unchecked();
+
+    PolicyVerifier::with_origin(policy, "https://app.example.com").unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_resources_resolves_urls_and_skips_comments() {
+        let html = r#"<!doctype html>
+            <!-- <script src="https://commented.example.com/x.js"></script> -->
+            <link rel="stylesheet" href="/styles/app.css">
+            <SCRIPT src="https://cdn.example.com/lib.js"></SCRIPT>
+            <img src='logo.png' alt="logo">"#;
+
+        let resources = extract_resources(html, PAGE_URL).unwrap();
+        let urls: Vec<_> = resources
+            .iter()
+            .filter_map(|resource| match resource {
+                PageResource::External(reference) => {
+                    Some((reference.directive.as_ref(), reference.url.as_str()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            urls,
+            [
+                ("style-src", "https://app.example.com/styles/app.css"),
+                ("script-src", "https://cdn.example.com/lib.js"),
+                ("img-src", "https://app.example.com/logo.png"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_resources_follows_html_tokenization() {
+        let html = r#"<textarea><img src="https://textarea.example.com/x.png"></textarea>
+            <title><script>alert(1)</script></title>
+            <script>if (a < b) { document.write("<img src='x.png'>"); }</script>
+            <img src="/a.png?x=1&amp;y=2" alt="a > b">
+            <button onclick="go()">go</button>"#;
+
+        let resources = extract_resources(html, PAGE_URL).unwrap();
+
+        assert_eq!(
+            resources,
+            [
+                PageResource::InlineScript {
+                    content: r#"if (a < b) { document.write("<img src='x.png'>"); }"#.to_string(),
+                    nonce: None,
+                },
+                PageResource::External(ResourceRef::new(
+                    "img-src",
+                    "https://app.example.com/a.png?x=1&y=2".to_string(),
+                )),
+                PageResource::EventHandler {
+                    attribute: "onclick".to_string(),
+                    content: "go()".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_audit_html_clean_template() {
+        let html = r#"<html><head>
+            <script nonce="n0nce">window.app = {};</script>
+            <script src="https://cdn.example.com/lib.js"></script>
+            </head><body><img src="data:image/png;base64,AAAA"></body></html>"#;
+
+        let report = audit_html(&verifier(), html, PAGE_URL).unwrap();
+
+        assert!(report.is_clean(), "{:?}", report.violations);
+        assert_eq!(report.resources.len(), 3);
+    }
+
+    #[test]
+    fn test_audit_html_reports_violations() {
+        let html = r#"<script>alert(1)</script>
+            <button onclick="go()">Go</button>
+            <style>body { color: red }</style>
+            <iframe src="https://ads.example.net/frame"></iframe>"#;
+
+        let report = audit_html(&verifier(), html, PAGE_URL).unwrap();
+        let directives: Vec<_> = report
+            .violations
+            .iter()
+            .map(|violation| violation.directive.as_str())
+            .collect();
+
+        assert_eq!(
+            directives,
+            ["default-src", "script-src", "script-src", "style-src"]
+        );
+    }
+
+    #[test]
+    fn test_audit_html_flags_missing_integrity_when_sri_required() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .require_sri_for(RequireSriFor::new().script())
+            .build_unchecked();
+        let sri_verifier = PolicyVerifier::with_origin(policy, "https://app.example.com").unwrap();
+        let html = r#"<script src="/app.js" integrity="sha384-abc"></script>
+            <script src="/legacy.js"></script>
+            <link rel="stylesheet" href="/app.css">"#;
+
+        let report = audit_html(&sri_verifier, html, PAGE_URL).unwrap();
+
+        assert_eq!(report.violations.len(), 1);
+        let violation = &report.violations[0];
+        assert_eq!(violation.directive, "require-sri-for");
+        assert_eq!(
+            violation.resource,
+            PageResource::External(ResourceRef::new(
+                "script-src",
+                "https://app.example.com/legacy.js"
+            ))
+        );
+
+        let report = audit_html(&verifier(), html, PAGE_URL).unwrap();
+        assert!(report.is_clean(), "{:?}", report.violations);
+    }
+}
@@ -0,0 +1,173 @@
+use actix_web_csp::{
+    core::{CspPolicy, CspPolicyBuilder, Source},
+    security::{AsyncPolicyVerifier, PolicyVerifier},
+    CspError,
+};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::time::Duration;
+use url::Url;
+
+fn policy() -> CspPolicy {
+    CspPolicyBuilder::new()
+        .default_src([Source::Self_])
+        .script_src([Source::Host("cdn.example.com".into())])
+        .build_unchecked()
+}
+
+/// A verifier whose remote allows `tenant.example.com` and counts its lookups.
+fn counting_verifier(delay: Duration) -> (AsyncPolicyVerifier, Arc<AtomicUsize>) {
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let counter = lookups.clone();
+    let verifier = AsyncPolicyVerifier::new(
+        PolicyVerifier::new(policy()),
+        move |url: Url, _directive: String| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                actix_web::rt::time::sleep(delay).await;
+                Ok::<_, CspError>(url.host_str() == Some("tenant.example.com"))
+            }
+        },
+    );
+    (verifier, lookups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_policy_allowed_uri_skips_remote() {
+        let (mut verifier, lookups) = counting_verifier(Duration::ZERO);
+
+        assert!(verifier
+            .verify_uri("https://cdn.example.com/app.js", "script-src")
+            .await
+            .unwrap());
+        assert_eq!(lookups.load(Ordering::SeqCst), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_remote_answers_are_cached() {
+        let (mut verifier, lookups) = counting_verifier(Duration::ZERO);
+
+        for _ in 0..3 {
+            assert!(verifier
+                .verify_uri("https://tenant.example.com/app.js", "script-src")
+                .await
+                .unwrap());
+            assert!(!verifier
+                .verify_uri("https://evil.example.com/app.js", "script-src")
+                .await
+                .unwrap());
+        }
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+
+        verifier.clear_cache();
+        assert!(verifier
+            .verify_uri("https://tenant.example.com/app.js", "script-src")
+            .await
+            .unwrap());
+        assert_eq!(lookups.load(Ordering::SeqCst), 3);
+    }
+
+    #[actix_web::test]
+    async fn test_cache_keeps_uri_and_directive_apart() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let counter = lookups.clone();
+        let mut verifier = AsyncPolicyVerifier::new(
+            PolicyVerifier::new(policy()),
+            move |_url: Url, directive: String| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async move { Ok::<_, CspError>(directive == "img-src") }
+            },
+        );
+
+        for _ in 0..2 {
+            assert!(verifier
+                .verify_uri("https://tenant.example.com/a", "img-src")
+                .await
+                .unwrap());
+            assert!(!verifier
+                .verify_uri("https://tenant.example.com/a", "script-src")
+                .await
+                .unwrap());
+            assert!(!verifier
+                .verify_uri("https://tenant.example.com/aimg-src", "")
+                .await
+                .unwrap());
+        }
+        assert_eq!(lookups.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_verifier_futures_are_send() {
+        fn assert_send<T: Send>(_: T) {}
+
+        let (mut verifier, _) = counting_verifier(Duration::ZERO);
+        assert_send(verifier.verify_uri("https://tenant.example.com/app.js", "script-src"));
+    }
+
+    #[actix_web::test]
+    async fn test_zero_ttl_disables_cache() {
+        let (verifier, lookups) = counting_verifier(Duration::ZERO);
+        let mut verifier = verifier.with_cache_ttl(Duration::ZERO);
+
+        for _ in 0..2 {
+            verifier
+                .verify_uri("https://tenant.example.com/app.js", "script-src")
+                .await
+                .unwrap();
+        }
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_slow_remote_times_out_without_caching() {
+        let (verifier, lookups) = counting_verifier(Duration::from_millis(200));
+        let mut verifier = verifier.with_timeout(Duration::from_millis(10));
+
+        for expected_lookups in 1..=2 {
+            let error = verifier
+                .verify_uri("https://tenant.example.com/app.js", "script-src")
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(error, CspError::VerificationError(ref message) if message.contains("timed out"))
+            );
+            assert_eq!(lookups.load(Ordering::SeqCst), expected_lookups);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_remote_errors_are_returned() {
+        let mut verifier = AsyncPolicyVerifier::new(
+            PolicyVerifier::new(policy()),
+            |_url: Url, _directive: String| async {
+                Err::<bool, _>(CspError::VerificationError("service unavailable".into()))
+            },
+        );
+
+        let error = verifier
+            .verify_uri("https://tenant.example.com/app.js", "img-src")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            CspError::VerificationError("service unavailable".into()).to_string()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_uris_are_blocked_without_a_lookup() {
+        let (mut verifier, lookups) = counting_verifier(Duration::ZERO);
+
+        for _ in 0..2 {
+            assert!(!verifier.verify_uri("not a uri", "img-src").await.unwrap());
+        }
+        assert_eq!(lookups.load(Ordering::SeqCst), 0);
+        assert_eq!(verifier.verifier().invalid_uri_count(), 2);
+    }
+}
@@ -1,8 +1,23 @@
+#![cfg_attr(not(feature = "verify"), allow(unused_imports, dead_code))]
+
 use actix_web_csp::{
-    core::{CspPolicyBuilder, Source},
-    security::{HashAlgorithm, HashGenerator, PolicyVerifier},
+    core::{CspPolicyBuilder, Directive, Source},
+    security::{
+        CheckOutcome, HashAlgorithm, HashGenerator, MatchSpecificity, PolicyVerifier, ResourceRef,
+        Verdict,
+    },
 };
 use std::borrow::Cow;
+use url::Url;
+
+/// Resolves scheme-relative URIs against `https:`, as a browser on an HTTPS
+/// page would.
+fn browser_like(uri: &str) -> Option<Url> {
+    match uri.strip_prefix("//") {
+        Some(rest) => Url::parse(&format!("https://{rest}")).ok(),
+        None => Url::parse(uri).ok(),
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -18,6 +33,7 @@ mod tests {
         assert!(verifier.policy().get_directive("default-src").is_some());
     }
 
+    #[cfg(feature = "verify")]
     #[test]
     fn test_policy_verifier_with_origin_supports_self() {
         let policy = CspPolicyBuilder::new()
@@ -34,6 +50,7 @@ mod tests {
             .unwrap());
     }
 
+    #[cfg(feature = "verify")]
     #[test]
     fn test_verify_uri_allowed() {
         let policy = CspPolicyBuilder::new()
@@ -58,6 +75,7 @@ mod tests {
             .unwrap());
     }
 
+    #[cfg(feature = "verify")]
     #[test]
     fn test_verify_uri_fallback_to_default_src() {
         let policy = CspPolicyBuilder::new()
@@ -74,6 +92,7 @@ mod tests {
             .unwrap());
     }
 
+    #[cfg(feature = "verify")]
     #[test]
     fn test_verify_uri_none_source() {
         let policy = CspPolicyBuilder::new()
@@ -91,6 +110,7 @@ mod tests {
             .unwrap());
     }
 
+    #[cfg(feature = "verify")]
     #[test]
     fn test_verify_hash() {
         let script = b"console.log('test');";
@@ -111,6 +131,7 @@ mod tests {
             .unwrap());
     }
 
+    #[cfg(feature = "verify")]
     #[test]
     fn test_verify_nonce() {
         let nonce = "random123";
@@ -125,6 +146,7 @@ mod tests {
         assert!(!verifier.verify_nonce("different456", "script-src").unwrap());
     }
 
+    #[cfg(feature = "verify")]
     #[test]
     fn test_verify_inline_script() {
         let script = b"console.log('test');";
@@ -165,6 +187,7 @@ mod tests {
         assert!(!verifier_nonce.verify_inline_script(script, None).unwrap());
     }
 
+    #[cfg(feature = "verify")]
     #[test]
     fn test_verify_inline_style() {
         let style = b"body { color: red; }";
@@ -269,6 +292,7 @@ mod tests {
         assert!(!verifier.has_directive("style-src"));
     }
 
+    #[cfg(feature = "verify")]
     #[test]
     fn test_clear_caches() {
         let policy = CspPolicyBuilder::new()
@@ -286,6 +310,104 @@ mod tests {
             .unwrap());
     }
 
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_policy_mut_invalidates_cached_results() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Host(Cow::Borrowed("cdn.example.com"))])
+            .build_unchecked();
+        let mut verifier = PolicyVerifier::new(policy);
+        let uri = "https://cdn.example.com/app.js";
+
+        assert!(verifier.verify_uri(uri, "script-src").unwrap());
+
+        verifier.policy_mut().remove_directive("script-src");
+        assert!(!verifier.verify_uri(uri, "script-src").unwrap());
+
+        let mut directive = Directive::new("script-src");
+        directive.add_source(Source::Host(Cow::Borrowed("cdn.example.com")));
+        verifier.policy_mut().add_directive(directive);
+        assert!(verifier.verify_uri(uri, "script-src").unwrap());
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_verify_uri_blocks_and_caches_invalid_uris() {
+        let policy = CspPolicyBuilder::new()
+            .img_src([Source::Scheme(Cow::Borrowed("https"))])
+            .build_unchecked();
+        let mut verifier = PolicyVerifier::new(policy);
+
+        assert!(!verifier.verify_uri("not a uri", "img-src").unwrap());
+        assert!(!verifier.verify_uri("not a uri", "img-src").unwrap());
+        assert_eq!(verifier.invalid_uri_count(), 2);
+
+        // Blocked even where no directive restricts the load.
+        assert!(verifier
+            .verify_uri("https://cdn.example.com/app.js", "script-src")
+            .unwrap());
+        assert!(!verifier.verify_uri("http://[::1", "script-src").unwrap());
+        assert_eq!(verifier.invalid_uri_count(), 3);
+
+        verifier.clear_caches();
+        assert!(!verifier.verify_uri("not a uri", "img-src").unwrap());
+        assert_eq!(verifier.invalid_uri_count(), 4);
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_custom_url_parser_is_used_for_every_check() {
+        let policy = CspPolicyBuilder::new()
+            .img_src([Source::Host(Cow::Borrowed("cdn.example.com"))])
+            .build_unchecked();
+        let mut verifier = PolicyVerifier::new(policy.clone());
+        assert!(!verifier
+            .verify_uri("//cdn.example.com/logo.png", "img-src")
+            .unwrap());
+
+        let mut verifier = PolicyVerifier::new(policy).with_url_parser(browser_like);
+        assert!(verifier
+            .verify_uri("//cdn.example.com/logo.png", "img-src")
+            .unwrap());
+        assert_eq!(verifier.invalid_uri_count(), 0);
+        assert!(
+            verifier
+                .verify_uri_detailed("//cdn.example.com/logo.png", "img-src")
+                .unwrap()
+                .allowed
+        );
+        assert!(verifier
+            .explain("//cdn.example.com/logo.png", "img-src")
+            .unwrap()
+            .is_allowed());
+        let report =
+            verifier.coverage_report(&[ResourceRef::new("img-src", "//cdn.example.com/a.png")]);
+        assert!(report.is_fully_allowed());
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_url_parser_rejection_blocks_the_uri() {
+        let policy = CspPolicyBuilder::new()
+            .img_src([Source::Scheme(Cow::Borrowed("https"))])
+            .build_unchecked();
+        let mut verifier = PolicyVerifier::new(policy).with_url_parser(|uri: &str| {
+            Url::parse(uri)
+                .ok()
+                .filter(|url| url.host_str() != Some("tracker.example"))
+        });
+
+        assert!(verifier
+            .verify_uri("https://cdn.example.com/a.png", "img-src")
+            .unwrap());
+        assert!(!verifier
+            .verify_uri("https://tracker.example/pixel.gif", "img-src")
+            .unwrap());
+        assert_eq!(verifier.invalid_uri_count(), 1);
+    }
+
+    #[cfg(feature = "verify")]
     #[test]
     fn test_verify_uri_does_not_panic_when_url_cache_is_full() {
         let policy = CspPolicyBuilder::new()
@@ -300,6 +422,7 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "verify")]
     #[test]
     fn test_verify_uri_matches_host_source_with_port_and_path_prefix() {
         let policy = CspPolicyBuilder::new()
@@ -319,6 +442,7 @@ mod tests {
             .unwrap());
     }
 
+    #[cfg(feature = "verify")]
     #[test]
     fn test_verify_uri_blocks_host_allowlists_when_strict_dynamic_is_present() {
         let policy = CspPolicyBuilder::new()
@@ -336,6 +460,7 @@ mod tests {
             .unwrap());
     }
 
+    #[cfg(feature = "verify")]
     #[test]
     fn test_verify_inline_script_ignores_unsafe_inline_when_strict_dynamic_uses_nonce() {
         let policy = CspPolicyBuilder::new()
@@ -355,4 +480,391 @@ mod tests {
             .verify_inline_script(b"console.log('with nonce');", Some("nonce123"))
             .unwrap());
     }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_unfilled_nonce_placeholder_does_not_disable_unsafe_inline() {
+        let policy = CspPolicyBuilder::new()
+            .script_src([Source::UnsafeInline, Source::NoncePlaceholder])
+            .build_unchecked();
+
+        let unfilled = PolicyVerifier::new(policy.clone());
+        assert!(unfilled
+            .verify_inline_script(b"console.log('inline');", None)
+            .unwrap());
+
+        let filled = PolicyVerifier::new(policy.clone_with_runtime_nonce("nonce123"));
+        assert!(!filled
+            .verify_inline_script(b"console.log('inline');", None)
+            .unwrap());
+        assert!(filled
+            .verify_inline_script(b"console.log('inline');", Some("nonce123"))
+            .unwrap());
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_coverage_report_tracks_grants_and_unused_sources() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([
+                Source::Self_,
+                Source::Host(Cow::Borrowed("cdn.example.com")),
+                Source::Host(Cow::Borrowed("legacy.example.com")),
+            ])
+            .img_src([Source::Self_, Source::Scheme(Cow::Borrowed("data"))])
+            .build_unchecked();
+        let verifier = PolicyVerifier::with_origin(policy, "https://app.example.com").unwrap();
+
+        let report = verifier.coverage_report(&[
+            ResourceRef::new("script-src", "https://cdn.example.com/app.js"),
+            ResourceRef::new("script-src", "https://evil.example.net/x.js"),
+            ResourceRef::new("img-src", "data:image/png;base64,AAAA"),
+            ResourceRef::new("font-src", "https://app.example.com/font.woff2"),
+        ]);
+
+        assert!(!report.is_fully_allowed());
+        let blocked: Vec<_> = report.blocked().map(|c| c.resource.url.as_str()).collect();
+        assert_eq!(blocked, ["https://evil.example.net/x.js"]);
+
+        assert_eq!(
+            report.resources[0].granted_by,
+            Some(Source::Host(Cow::Borrowed("cdn.example.com")))
+        );
+        assert_eq!(
+            report.resources[3].effective_directive.as_deref(),
+            Some("default-src")
+        );
+        assert_eq!(report.resources[3].granted_by, Some(Source::Self_));
+
+        assert_eq!(
+            report.unused_sources,
+            vec![
+                ("script-src".to_string(), Source::Self_),
+                (
+                    "script-src".to_string(),
+                    Source::Host(Cow::Borrowed("legacy.example.com"))
+                ),
+                ("img-src".to_string(), Source::Self_),
+            ]
+        );
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_verify_uri_matches_scheme_qualified_wildcards() {
+        let policy = CspPolicyBuilder::new()
+            .script_src([
+                Source::Host(Cow::Borrowed("https://*.example.com:*")),
+                Source::Host(Cow::Borrowed("*://cdn.example.net")),
+            ])
+            .build_unchecked();
+
+        let mut verifier = PolicyVerifier::with_origin(policy, "https://app.example.com").unwrap();
+
+        assert!(verifier
+            .verify_uri("https://static.example.com:8443/app.js", "script-src")
+            .unwrap());
+        assert!(!verifier
+            .verify_uri("http://static.example.com/app.js", "script-src")
+            .unwrap());
+        assert!(verifier
+            .verify_uri("http://cdn.example.net/lib.js", "script-src")
+            .unwrap());
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn test_verification_cache_reports_to_stats() {
+        use actix_web_csp::CspStats;
+
+        let verification = || CspStats::new().cache_stats()["verification"];
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .img_src([Source::Host("cache-stats.example".into())])
+            .build_unchecked();
+        let mut verifier = PolicyVerifier::new(policy);
+        let before = verification();
+
+        assert!(verifier
+            .verify_uri("https://cache-stats.example/a.png", "img-src")
+            .unwrap());
+        assert!(verifier
+            .verify_uri("https://cache-stats.example/a.png", "img-src")
+            .unwrap());
+
+        let after = verification();
+        assert!(after.hits > before.hits);
+        assert!(after.misses > before.misses);
+        assert!(after.capacity >= 512);
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_verify_uri_detailed_reports_matched_source_and_specificity() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([
+                Source::Self_,
+                Source::Host(Cow::Borrowed("cdn.example.com/js/")),
+                Source::Host(Cow::Borrowed("api.example.com")),
+                Source::Host(Cow::Borrowed("*.trusted.com")),
+                Source::Scheme(Cow::Borrowed("https")),
+            ])
+            .img_src([Source::Host(Cow::Borrowed("*"))])
+            .media_src([Source::Host(Cow::Borrowed("http://legacy.example.com"))])
+            .build_unchecked();
+        let verifier = PolicyVerifier::with_origin(policy, "https://app.example.com").unwrap();
+
+        for (uri, directive, source, specificity) in [
+            (
+                "https://app.example.com/main.js",
+                "script-src",
+                Source::Self_,
+                MatchSpecificity::SelfOrigin,
+            ),
+            (
+                "https://cdn.example.com/js/lib.js",
+                "script-src",
+                Source::Host(Cow::Borrowed("cdn.example.com/js/")),
+                MatchSpecificity::Path,
+            ),
+            (
+                "https://api.example.com/widget.js",
+                "script-src",
+                Source::Host(Cow::Borrowed("api.example.com")),
+                MatchSpecificity::Host,
+            ),
+            (
+                "https://a.trusted.com/x.js",
+                "script-src",
+                Source::Host(Cow::Borrowed("*.trusted.com")),
+                MatchSpecificity::WildcardSubdomain,
+            ),
+            (
+                "https://anything.example.net/x.js",
+                "script-src",
+                Source::Scheme(Cow::Borrowed("https")),
+                MatchSpecificity::Scheme,
+            ),
+            (
+                "https://images.example.org/a.png",
+                "img-src",
+                Source::Host(Cow::Borrowed("*")),
+                MatchSpecificity::AnyHost,
+            ),
+        ] {
+            let detail = verifier.verify_uri_detailed(uri, directive).unwrap();
+            assert!(detail.allowed, "{uri}");
+            assert_eq!(detail.matched_source, Some(source), "{uri}");
+            assert_eq!(detail.specificity, Some(specificity), "{uri}");
+            assert_eq!(detail.is_broad(), specificity.is_broad(), "{uri}");
+        }
+
+        let detail = verifier
+            .verify_uri_detailed("http://legacy.example.com/a.mp4", "media-src")
+            .unwrap();
+        assert!(detail.allowed);
+        assert!(!detail.is_broad());
+        assert!(detail.insecure_scheme);
+        assert_eq!(detail.effective_directive.as_deref(), Some("media-src"));
+        assert!(
+            !verifier
+                .verify_uri_detailed("http://images.example.org/a.png", "img-src")
+                .unwrap()
+                .allowed
+        );
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_verify_uri_detailed_blocked_and_unrestricted() {
+        let verifier = PolicyVerifier::new(
+            CspPolicyBuilder::new()
+                .script_src([Source::Host(Cow::Borrowed("cdn.example.com"))])
+                .build_unchecked(),
+        );
+
+        let blocked = verifier
+            .verify_uri_detailed("https://evil.example.com/x.js", "script-src")
+            .unwrap();
+        assert!(!blocked.allowed);
+        assert_eq!(blocked.matched_source, None);
+        assert_eq!(blocked.specificity, None);
+        assert!(!blocked.is_broad());
+
+        let unrestricted = verifier
+            .verify_uri_detailed("http://evil.example.com/a.png", "img-src")
+            .unwrap();
+        assert!(unrestricted.allowed);
+        assert_eq!(unrestricted.effective_directive, None);
+        assert_eq!(
+            unrestricted.specificity,
+            Some(MatchSpecificity::Unrestricted)
+        );
+        assert!(unrestricted.is_broad());
+
+        let invalid = verifier
+            .verify_uri_detailed("not a uri", "img-src")
+            .unwrap();
+        assert!(!invalid.allowed);
+        assert_eq!(invalid.matched_source, None);
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_explain_traces_each_source_after_fallback() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([
+                Source::Self_,
+                Source::Nonce(Cow::Borrowed("abc123")),
+                Source::Host(Cow::Borrowed("ftp://static.example.com")),
+                Source::Host(Cow::Borrowed("api.example.com:8443")),
+                Source::Host(Cow::Borrowed("static.example.com/js/")),
+                Source::Host(Cow::Borrowed("*.trusted.com")),
+                Source::Scheme(Cow::Borrowed("data")),
+            ])
+            .build_unchecked();
+        let mut verifier = PolicyVerifier::with_origin(policy, "https://app.example.com").unwrap();
+
+        let explanation = verifier
+            .explain("https://static.example.com/css/site.css", "style-src")
+            .unwrap();
+        assert!(!explanation.is_allowed());
+        assert_eq!(explanation.verdict, Verdict::NoMatch);
+        assert!(explanation.fell_back());
+        assert_eq!(
+            explanation.effective_directive.as_deref(),
+            Some("default-src")
+        );
+        assert_eq!(
+            explanation
+                .checks
+                .iter()
+                .map(|check| check.outcome)
+                .collect::<Vec<_>>(),
+            [
+                CheckOutcome::NotSameOrigin,
+                CheckOutcome::NotApplicable,
+                CheckOutcome::SchemeMismatch,
+                CheckOutcome::HostMismatch,
+                CheckOutcome::PathMismatch,
+                CheckOutcome::HostMismatch,
+                CheckOutcome::SchemeMismatch,
+            ]
+        );
+        assert_eq!(explanation.matched_source(), None);
+
+        let rendered = explanation.to_string();
+        assert!(rendered.starts_with(
+            "style-src would block https://static.example.com/css/site.css (checked default-src)"
+        ));
+        assert!(rendered.contains("static.example.com/js/: path does not match"));
+
+        let port = verifier
+            .explain("https://api.example.com/v1", "connect-src")
+            .unwrap();
+        assert_eq!(
+            port.checks.iter().map(|check| check.outcome).nth(3),
+            Some(CheckOutcome::PortMismatch)
+        );
+
+        let allowed = verifier
+            .explain("https://a.trusted.com/x.js", "script-src")
+            .unwrap();
+        assert!(allowed.is_allowed());
+        assert_eq!(allowed.verdict, Verdict::Matched);
+        assert_eq!(allowed.checks.len(), 6);
+        assert_eq!(
+            allowed.matched_source(),
+            Some(&Source::Host(Cow::Borrowed("*.trusted.com")))
+        );
+        assert!(verifier
+            .verify_uri("https://a.trusted.com/x.js", "script-src")
+            .unwrap());
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_explain_policy_level_verdicts() {
+        let policy = CspPolicyBuilder::new()
+            .script_src([
+                Source::StrictDynamic,
+                Source::Nonce(Cow::Borrowed("abc123")),
+                Source::Host(Cow::Borrowed("cdn.example.com")),
+            ])
+            .object_src([Source::None])
+            .img_src([Source::Self_])
+            .build_unchecked();
+        let verifier = PolicyVerifier::new(policy);
+
+        let strict = verifier
+            .explain("https://cdn.example.com/app.js", "script-src")
+            .unwrap();
+        assert_eq!(strict.verdict, Verdict::StrictDynamic);
+        assert!(strict.checks.is_empty());
+        assert!(strict.to_string().contains("'strict-dynamic'"));
+
+        let none = verifier
+            .explain("https://example.com/a.swf", "object-src")
+            .unwrap();
+        assert_eq!(none.verdict, Verdict::NoneKeyword);
+        assert!(!none.fell_back());
+
+        let no_origin = verifier
+            .explain("https://example.com/a.png", "img-src")
+            .unwrap();
+        assert_eq!(no_origin.checks[0].outcome, CheckOutcome::NoOrigin);
+
+        let unrestricted = verifier
+            .explain("https://example.com/font.woff2", "font-src")
+            .unwrap();
+        assert_eq!(unrestricted.verdict, Verdict::Unrestricted);
+        assert!(unrestricted.is_allowed());
+        assert_eq!(unrestricted.effective_directive, None);
+        assert!(unrestricted.to_string().ends_with("(no directive applies)"));
+
+        let invalid = verifier.explain("not a uri", "font-src").unwrap();
+        assert_eq!(invalid.verdict, Verdict::InvalidUri);
+        assert!(!invalid.is_allowed());
+        assert!(invalid.to_string().contains("not a valid URL"));
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_invalid_uris_are_blocked_and_counted_by_every_check() {
+        let policy = CspPolicyBuilder::new()
+            .img_src([Source::Scheme(Cow::Borrowed("https"))])
+            .build_unchecked();
+        let mut verifier = PolicyVerifier::new(policy);
+
+        // script-src has no directive and no default-src to fall back on, so
+        // only the unparseable URI blocks the load
+        for directive in ["img-src", "script-src"] {
+            assert!(!verifier.verify_uri("not a uri", directive).unwrap());
+
+            let detail = verifier
+                .verify_uri_detailed("not a uri", directive)
+                .unwrap();
+            assert!(!detail.allowed);
+            assert_eq!(detail.specificity, None);
+            assert!(!detail.is_broad());
+
+            let explanation = verifier.explain("not a uri", directive).unwrap();
+            assert_eq!(explanation.verdict, Verdict::InvalidUri);
+            assert!(explanation.checks.is_empty());
+
+            let report = verifier.coverage_report(&[ResourceRef::new(directive, "not a uri")]);
+            assert!(!report.resources[0].allowed);
+        }
+        assert_eq!(
+            verifier
+                .explain("not a uri", "img-src")
+                .unwrap()
+                .effective_directive
+                .as_deref(),
+            Some("img-src")
+        );
+        assert_eq!(verifier.invalid_uri_count(), 9);
+    }
 }
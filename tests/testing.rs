@@ -0,0 +1,168 @@
+#![cfg_attr(not(feature = "reporting"), allow(unused_imports))]
+
+use actix_web::{test as actix_test, web, App, HttpRequest, HttpResponse};
+use actix_web_csp::{
+    assert_csp_contains, csp_middleware_with_nonce, csp_with_reporting,
+    test::{
+        extract_header_nonce, extract_nonce, policy_contains, PolicyPropagationCheck, TestPolicy,
+        ViolationReportBuilder,
+    },
+    CspConfig, CspMiddleware, CspPolicy, CspPolicyBuilder, CspResponseExt, CspViolationReport,
+    Source,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+async fn nonce_page(req: HttpRequest) -> HttpResponse {
+    HttpResponse::Ok().csp_nonce_body(&req, r#"<script nonce="{{csp-nonce}}">1</script>"#)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_contains_is_order_insensitive() {
+        let header = "default-src 'self'; script-src 'self' cdn.example.com 'unsafe-inline'";
+
+        assert!(policy_contains(header, "script-src cdn.example.com 'self'"));
+        assert!(policy_contains(header, "SCRIPT-SRC"));
+        assert!(!policy_contains(header, "script-src evil.example.com"));
+        assert!(!policy_contains(header, "img-src 'self'"));
+    }
+
+    #[test]
+    fn test_test_policy_is_permissive() {
+        let policy: CspPolicy = TestPolicy::default().into();
+        let directive = policy.get_directive("default-src").unwrap();
+
+        assert!(directive.sources().contains(&Source::UnsafeInline));
+        assert!(directive.sources().contains(&Source::UnsafeEval));
+    }
+
+    #[actix_web::test]
+    async fn test_nonce_helpers_agree_with_header() {
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_])
+            .build_unchecked();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(csp_middleware_with_nonce(policy, 16))
+                .route("/", web::get().to(nonce_page)),
+        )
+        .await;
+
+        let res =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri("/").to_request())
+                .await;
+        assert_csp_contains!(res, "script-src 'self'");
+        let header_nonce = extract_header_nonce(res.headers()).unwrap();
+        let body = actix_test::read_body(res).await;
+
+        assert_eq!(extract_nonce(&body), Some(header_nonce));
+    }
+
+    #[cfg(feature = "reporting")]
+    #[actix_web::test]
+    async fn test_violation_report_builder_posts_to_reporting_endpoint() {
+        let reports = Arc::new(Mutex::new(Vec::<CspViolationReport>::new()));
+        let sink = reports.clone();
+        let (csp, reporting) = csp_with_reporting(TestPolicy::default().into(), move |report| {
+            sink.lock().unwrap().push(report);
+        });
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(csp)
+                .configure(reporting)
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = ViolationReportBuilder::new("https://evil.example.com/x.js", "script-src")
+            .original_policy("script-src 'self'")
+            .to_request("/csp-report")
+            .to_request();
+        let res = actix_test::call_service(&app, req).await;
+
+        assert!(res.status().is_success());
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].blocked_uri, "https://evil.example.com/x.js");
+    }
+
+    #[test]
+    fn test_policy_update_reaches_every_worker() {
+        let config = CspConfig::new(
+            CspPolicyBuilder::new()
+                .script_src([Source::Self_])
+                .build_unchecked(),
+        );
+
+        let factory_config = config.clone();
+        let report =
+            PolicyPropagationCheck::new(move || CspMiddleware::new(factory_config.clone()))
+                .with_workers(3)
+                .assert_propagates(
+                    || {
+                        config.update_policy(|policy| {
+                            policy.append_source(
+                                "script-src",
+                                Source::Host("cdn.example.com".into()),
+                            );
+                        });
+                    },
+                    "script-src 'self' cdn.example.com",
+                );
+
+        assert_eq!(report.workers, 3);
+        assert!(report.probes >= 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "did not send `script-src 'self' cdn.example.com`")]
+    fn test_per_worker_configs_fail_the_propagation_check() {
+        let policy = CspPolicyBuilder::new()
+            .script_src([Source::Self_])
+            .build_unchecked();
+        let shared = CspConfig::new(policy.clone());
+
+        // Each worker builds a config of its own, so updating `shared` changes nothing
+        PolicyPropagationCheck::new(move || CspMiddleware::new(CspConfig::new(policy.clone())))
+            .with_workers(2)
+            .with_deadline(Duration::from_millis(300))
+            .assert_propagates(
+                || {
+                    shared.update_policy(|policy| {
+                        policy.append_source("script-src", Source::Host("cdn.example.com".into()));
+                    });
+                },
+                "script-src 'self' cdn.example.com",
+            );
+    }
+
+    #[test]
+    fn test_propagation_check_stops_the_server_when_update_panics() {
+        let config = CspConfig::new(
+            CspPolicyBuilder::new()
+                .script_src([Source::Self_])
+                .build_unchecked(),
+        );
+        let factory_config = Arc::new(config.clone());
+        let tracked = factory_config.clone();
+
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            PolicyPropagationCheck::new(move || CspMiddleware::new((*factory_config).clone()))
+                .with_workers(2)
+                .assert_propagates(|| panic!("update failed"), "script-src 'self'")
+        }));
+
+        assert!(outcome.is_err());
+        // Worker threads drop their app factories shortly after the server stops.
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while Arc::strong_count(&tracked) > 1 && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(Arc::strong_count(&tracked), 1);
+    }
+}
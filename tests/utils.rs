@@ -1,4 +1,8 @@
-use actix_web_csp::utils::intern_string;
+use actix_web_csp::core::{CspPolicyBuilder, Source};
+use actix_web_csp::utils::{
+    buffer_pool_stats, configure_buffer_pool, intern_stats, intern_string,
+    register_interned_strings,
+};
 use std::time::Duration;
 
 #[derive(Debug, Clone)]
@@ -234,4 +238,54 @@ mod tests {
         assert!(intern_string("default").is_none());
         assert!(intern_string("src").is_none());
     }
+
+    #[test]
+    fn test_register_interned_strings_extends_table() {
+        assert!(intern_string("cdn.intern-test.example").is_none());
+
+        let added = register_interned_strings([
+            "cdn.intern-test.example",
+            "cdn.intern-test.example",
+            "'self'",
+        ]);
+
+        assert_eq!(added, 1);
+        assert_eq!(
+            intern_string("cdn.intern-test.example"),
+            Some("cdn.intern-test.example")
+        );
+        assert_eq!(register_interned_strings(["cdn.intern-test.example"]), 0);
+    }
+
+    #[test]
+    fn test_intern_stats_count_hits_and_misses() {
+        let before = intern_stats();
+
+        intern_string("'self'");
+        intern_string("stats.intern-test.example");
+
+        let after = intern_stats();
+        assert!(after.hits > before.hits);
+        assert!(after.misses > before.misses);
+        assert!(after.entries >= 32);
+        assert!(after.hit_rate() > 0.0 && after.hit_rate() < 1.0);
+    }
+
+    #[test]
+    fn test_buffer_pool_reuses_serialization_buffers() {
+        configure_buffer_pool(8, 512);
+        let policy = CspPolicyBuilder::new()
+            .default_src([Source::Self_])
+            .script_src([Source::Self_, Source::Host("cdn.example.com".into())])
+            .build_unchecked();
+
+        let before = buffer_pool_stats();
+        for _ in 0..4 {
+            policy.compile().unwrap();
+        }
+        let after = buffer_pool_stats();
+
+        assert_eq!(after.capacity, 8);
+        assert!(after.hits > before.hits, "{after:?}");
+    }
 }